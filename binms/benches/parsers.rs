@@ -0,0 +1,150 @@
+//! Benchmarks of the parsers for the larger structures in executables and their debug information.
+//!
+//! The fixtures are synthesized to resemble the large cases encountered in practice: the resources
+//! of a library localized into many languages and the global types and symbols of a program with
+//! debug information.
+
+
+use std::hint::black_box;
+use std::io::Cursor;
+
+use binms::code_view::{dword_xor_rotate_hash, GlobalSymbolsSubsection, GlobalTypesSubsection};
+use binms::pe::{Executable, KnownDataDirectoryEntry, OptionalHeader, ResourceDirectoryTable};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use testgen::memory::report_peak_rss_growth;
+use testgen::pe;
+
+
+const LANGUAGE_IDS: [u32; 8] = [0x0405, 0x0407, 0x0409, 0x040C, 0x0410, 0x0411, 0x0415, 0x0C0A];
+
+
+/// A library with string tables, dialogs and version information in each of [`LANGUAGE_IDS`].
+fn localized_library() -> Vec<u8> {
+    let mut library = pe::PortableExecutable::new("LOCALIZED.DLL");
+    for (type_id, resource_count, size) in [(6, 128, 512), (5, 32, 1024), (16, 1, 900)] {
+        for name_id in 1..=resource_count {
+            for language_id in LANGUAGE_IDS {
+                let data = (0..size)
+                    .map(|i| (i as u32 ^ name_id ^ language_id) as u8)
+                    .collect();
+                library.resources.push(pe::Resource { type_id, name_id, language_id, data });
+            }
+        }
+    }
+    library.to_bytes()
+}
+
+fn bench_pe_resources(c: &mut Criterion) {
+    let library = localized_library();
+    let executable = Executable::read(&mut Cursor::new(&library)).unwrap();
+    let Some(OptionalHeader::Coff(coff)) = executable.optional_header.as_ref() else { panic!("no COFF optional header") };
+    let resource_entry = coff.optional_windows_header.as_ref().unwrap()
+        .known_data_directory_entry(KnownDataDirectoryEntry::ResourceTable).unwrap();
+    let read = || ResourceDirectoryTable::read_root_from_pe(&mut Cursor::new(&library), &resource_entry, &executable.section_table).unwrap();
+
+    report_peak_rss_growth("pe_resources", read);
+    let mut group = c.benchmark_group("pe_resources");
+    group.throughput(Throughput::Bytes(resource_entry.size.into()));
+    group.bench_function("read_root_from_pe", |b| b.iter(|| black_box(read())));
+    group.finish();
+}
+
+
+/// A global types subsection containing `count` triples of an argument list, a procedure and a
+/// field list of an enumeration.
+fn global_types_subsection(count: u32) -> Vec<u8> {
+    let mut records: Vec<Vec<u8>> = Vec::new();
+    for i in 0..count {
+        let argument_list_index = 0x1000 + 3*i;
+        // LF_ARGLIST (int)
+        records.push(b"\x06\x00\x01\x02\x01\x00\x74\x00".to_vec());
+        // LF_PROCEDURE returning void with the argument list above, C calling convention
+        let mut procedure = b"\x0A\x00\x08\x00\x03\x00\x00\x00\x01\x00".to_vec();
+        procedure.extend_from_slice(&u16::try_from(argument_list_index).unwrap().to_le_bytes());
+        records.push(procedure);
+        // LF_FIELDLIST with LF_ENUMERATE AB = 1, padded
+        records.push(b"\x0E\x00\x04\x02\x03\x04\x03\x00\x01\x00\x02AB\xF3\xF2\xF1".to_vec());
+    }
+
+    let mut data = Vec::new();
+    data.extend_from_slice(&0u32.to_le_bytes()); // flags
+    data.extend_from_slice(&u32::try_from(records.len()).unwrap().to_le_bytes());
+    let mut offset = 0u32;
+    for record in &records {
+        data.extend_from_slice(&offset.to_le_bytes());
+        offset += u32::try_from(record.len()).unwrap();
+    }
+    for record in &records {
+        data.extend_from_slice(record);
+    }
+    data
+}
+
+/// A hash table with a single bucket containing the given pairs of symbol offset and value.
+fn single_bucket_hash_table(entries: &[(u32, u32)]) -> Vec<u8> {
+    let mut table = Vec::new();
+    table.extend_from_slice(&1u16.to_le_bytes()); // bucket count
+    table.extend_from_slice(&0u16.to_le_bytes()); // padding
+    table.extend_from_slice(&0u32.to_le_bytes()); // bucket offset
+    table.extend_from_slice(&u32::try_from(entries.len()).unwrap().to_le_bytes());
+    for (symbol_offset, value) in entries {
+        table.extend_from_slice(&symbol_offset.to_le_bytes());
+        table.extend_from_slice(&value.to_le_bytes());
+    }
+    table
+}
+
+/// A global symbols subsection containing `count` public symbols along with their hash tables.
+fn global_symbols_subsection(count: u32) -> Vec<u8> {
+    let mut symbols = Vec::new();
+    let mut name_hashes = Vec::new();
+    let mut addresses = Vec::new();
+    for i in 0..count {
+        let symbol_offset = u32::try_from(symbols.len()).unwrap();
+        let name = format!("_Function{}@8", i);
+        let address = 0x10 * i;
+        let length = u16::try_from(2 + 4 + 2 + 2 + 1 + name.len()).unwrap();
+        symbols.extend_from_slice(&length.to_le_bytes());
+        symbols.extend_from_slice(&0x0203u16.to_le_bytes()); // S_PUB32
+        symbols.extend_from_slice(&address.to_le_bytes());
+        symbols.extend_from_slice(&1u16.to_le_bytes()); // segment
+        symbols.extend_from_slice(&0u16.to_le_bytes()); // type
+        symbols.push(u8::try_from(name.len()).unwrap());
+        symbols.extend_from_slice(name.as_bytes());
+        name_hashes.push((symbol_offset, dword_xor_rotate_hash(name.as_bytes())));
+        addresses.push((symbol_offset, address));
+    }
+    let symbol_hash_table = single_bucket_hash_table(&name_hashes);
+    let address_hash_table = single_bucket_hash_table(&addresses);
+
+    let mut data = Vec::new();
+    data.extend_from_slice(&0x000Au16.to_le_bytes());
+    data.extend_from_slice(&0x000Cu16.to_le_bytes());
+    data.extend_from_slice(&u32::try_from(symbols.len()).unwrap().to_le_bytes());
+    data.extend_from_slice(&u32::try_from(symbol_hash_table.len()).unwrap().to_le_bytes());
+    data.extend_from_slice(&u32::try_from(address_hash_table.len()).unwrap().to_le_bytes());
+    data.extend_from_slice(&symbols);
+    data.extend_from_slice(&symbol_hash_table);
+    data.extend_from_slice(&address_hash_table);
+    data
+}
+
+fn bench_code_view(c: &mut Criterion) {
+    let types = global_types_subsection(5000);
+    let symbols = global_symbols_subsection(20_000);
+    let read_types = || GlobalTypesSubsection::read(&mut Cursor::new(&types)).unwrap();
+    let read_symbols = || GlobalSymbolsSubsection::read(&mut Cursor::new(&symbols)).unwrap();
+
+    report_peak_rss_growth("code_view/global_types", read_types);
+    report_peak_rss_growth("code_view/global_symbols", read_symbols);
+    let mut group = c.benchmark_group("code_view");
+    group.throughput(Throughput::Bytes(types.len() as u64));
+    group.bench_function("global_types", |b| b.iter(|| black_box(read_types())));
+    group.throughput(Throughput::Bytes(symbols.len() as u64));
+    group.bench_function("global_symbols", |b| b.iter(|| black_box(read_symbols())));
+    group.finish();
+}
+
+
+criterion_group!(benches, bench_pe_resources, bench_code_view);
+criterion_main!(benches);
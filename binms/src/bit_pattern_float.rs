@@ -1,4 +1,8 @@
 //! Floating-point values that are ordered, compared and hashed by their bit patterns.
+//!
+//! When serialized, finite values are output as numbers. Infinities and NaNs cannot be represented
+//! as numbers in formats such as JSON, so they are output as strings containing their bit pattern
+//! in hexadecimal (e.g. `"0x7FC00000"`) to keep the payload of NaNs intact.
 
 
 use core::cmp::Ordering;
@@ -9,9 +13,8 @@ use serde::{Deserialize, Serialize};
 
 
 macro_rules! impl_bpf {
-    ($name:ident, $fty:ty, $complex_name:ident) => {
+    ($name:ident, $fty:ty, $bits_ty:ty, $complex_name:ident) => {
         #[derive(Clone, Copy, Debug, Default)]
-        #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
         pub struct $name($fty);
         impl From<$fty> for $name {
             fn from(value: $fty) -> Self {
@@ -44,6 +47,40 @@ macro_rules! impl_bpf {
                 self.0.to_bits().hash(hasher)
             }
         }
+        #[cfg(feature = "serde")]
+        impl Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                if self.0.is_finite() {
+                    self.0.serialize(serializer)
+                } else {
+                    let width = 2 * core::mem::size_of::<$bits_ty>();
+                    format!("0x{:01$X}", self.0.to_bits(), width).serialize(serializer)
+                }
+            }
+        }
+        #[cfg(feature = "serde")]
+        impl<'d> Deserialize<'d> for $name {
+            fn deserialize<D: serde::Deserializer<'d>>(deserializer: D) -> Result<Self, D::Error> {
+                use serde::de::Error as _;
+
+                #[derive(Deserialize)]
+                #[serde(untagged)]
+                enum NumberOrBits {
+                    Number($fty),
+                    Bits(String),
+                }
+
+                match NumberOrBits::deserialize(deserializer)? {
+                    NumberOrBits::Number(value) => Ok(Self(value)),
+                    NumberOrBits::Bits(bits_string) => {
+                        let bits = bits_string.strip_prefix("0x")
+                            .and_then(|hex| <$bits_ty>::from_str_radix(hex, 16).ok())
+                            .ok_or_else(|| D::Error::custom("expected a number or a hexadecimal bit pattern"))?;
+                        Ok(Self(<$fty>::from_bits(bits)))
+                    },
+                }
+            }
+        }
 
         #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
         #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
@@ -54,5 +91,36 @@ macro_rules! impl_bpf {
     };
 }
 
-impl_bpf!(BitPatternF32, f32, ComplexBitPatternF32);
-impl_bpf!(BitPatternF64, f64, ComplexBitPatternF64);
+impl_bpf!(BitPatternF32, f32, u32, ComplexBitPatternF32);
+impl_bpf!(BitPatternF64, f64, u64, ComplexBitPatternF64);
+
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::{BitPatternF32, BitPatternF64};
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_f64_json_round_trip(bits in any::<u64>()) {
+            let value = BitPatternF64::from(f64::from_bits(bits));
+            let json = serde_json::to_value(value).unwrap();
+            let read_back: BitPatternF64 = serde_json::from_value(json).unwrap();
+            prop_assert_eq!(read_back, value);
+        }
+
+        #[test]
+        fn test_f32_json_round_trip(bits in any::<u32>()) {
+            let value = BitPatternF32::from(f32::from_bits(bits));
+            let json = serde_json::to_string(&value).unwrap();
+            let read_back: BitPatternF32 = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(read_back, value);
+        }
+    }
+
+    #[test]
+    fn test_nan_json() {
+        let value = BitPatternF32::from(f32::from_bits(0x7FC0_0001));
+        assert_eq!(serde_json::to_string(&value).unwrap(), "\"0x7FC00001\"");
+    }
+}
@@ -3,11 +3,13 @@
 //! Windows 3.0 encodes its icon resources as bitmaps based on the BITMAPINFOHEADER structure,
 //! which is implemented in this file. This is sometimes known as the Windows v3 bitmap format.
 //!
+//! The older OS/2 v1/Windows v2 format (`BITMAPCOREHEADER`), whose palette entries are three bytes
+//! long instead of four, is also supported, as is the OS/2 v2 format (`BITMAPCOREHEADER2`) in its
+//! full 64-byte and truncated 16-byte forms. All of them are read into a [`BitmapInfoHeader`].
+//!
 //! Support for the following formats is currently not implemented:
 //! * BMP files (`BITMAPFILEHEADER`)
 //! * Windows 1.0 icons/cursors (see [`ico1`])
-//! * OS/2 v1/Windows v2 bitmaps (`BITMAPCOREHEADER`)
-//! * OS/2 v2 bitmaps (`BITMAPCOREHEADER2`)
 //! * Windows v4 bitmaps (`BITMAPV4HEADER`)
 //! * Windows v5 bitmaps (`BITMAPV5HEADER`)
 //! * other uncommon Windows bitmaps (`BITMAPV2HEADER`, `BITMAPV3HEADER`)
@@ -24,6 +26,19 @@ use std::fmt;
 use from_to_repr::from_to_other;
 
 
+/// The size of `BITMAPCOREHEADER` (OS/2 v1, Windows v2).
+pub const CORE_HEADER_SIZE: u32 = 12;
+
+/// The size of `BITMAPINFOHEADER` (Windows v3).
+pub const INFO_HEADER_SIZE: u32 = 40;
+
+/// The size of a `BITMAPCOREHEADER2` (OS/2 v2) truncated after the bit count.
+pub const OS2_SHORT_HEADER_SIZE: u32 = 16;
+
+/// The size of a full `BITMAPCOREHEADER2` (OS/2 v2).
+pub const OS2_HEADER_SIZE: u32 = 64;
+
+
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum ReadStage {
     HeaderLength,
@@ -118,9 +133,16 @@ impl Palette {
 }
 
 
+/// The header of a bitmap.
+///
+/// Headers in the older formats are converted into this structure, with the fields they lack set
+/// to zero; `header_size` retains the size of the original header.
+///
+/// Note that OS/2 v2 assigns different meanings to some compression values, e.g. 3 is Huffman 1D
+/// instead of [`Compression::BitFields`]. Only uncompressed bitmaps are supported, though.
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct BitmapInfoHeader {
-    pub header_size: u32, // always 40
+    pub header_size: u32, // one of the *_HEADER_SIZE constants
     pub width: i32,
     pub height: i32,
     pub planes: u16, // always 1 (packed pixels)
@@ -142,25 +164,47 @@ impl BitmapInfoHeader {
         let header_size = u32::from_le_bytes(rest[0..4].try_into().unwrap());
         let rest = &rest[4..];
 
-        if header_size != 40 {
-            return Err(Error::WrongHeaderSize { expected: 40, obtained: header_size });
+        if ![CORE_HEADER_SIZE, INFO_HEADER_SIZE, OS2_SHORT_HEADER_SIZE, OS2_HEADER_SIZE].contains(&header_size) {
+            return Err(Error::WrongHeaderSize { expected: INFO_HEADER_SIZE, obtained: header_size });
         }
 
-        if rest.len() < 36 {
+        let header_rest_size = usize::try_from(header_size - 4).unwrap();
+        if rest.len() < header_rest_size {
             return Err(Error::TooShort { stage: ReadStage::Header });
         }
+        let (header_bytes, rest) = rest.split_at(header_rest_size);
+
+        let (width, height, planes, bit_count) = if header_size == CORE_HEADER_SIZE {
+            // unsigned 16-bit dimensions
+            (
+                i32::from(u16::from_le_bytes(header_bytes[0..2].try_into().unwrap())),
+                i32::from(u16::from_le_bytes(header_bytes[2..4].try_into().unwrap())),
+                u16::from_le_bytes(header_bytes[4..6].try_into().unwrap()),
+                u16::from_le_bytes(header_bytes[6..8].try_into().unwrap()),
+            )
+        } else {
+            (
+                i32::from_le_bytes(header_bytes[0..4].try_into().unwrap()),
+                i32::from_le_bytes(header_bytes[4..8].try_into().unwrap()),
+                u16::from_le_bytes(header_bytes[8..10].try_into().unwrap()),
+                u16::from_le_bytes(header_bytes[10..12].try_into().unwrap()),
+            )
+        };
 
-        let width = i32::from_le_bytes(rest[0..4].try_into().unwrap());
-        let height = i32::from_le_bytes(rest[4..8].try_into().unwrap());
-        let planes = u16::from_le_bytes(rest[8..10].try_into().unwrap());
-        let bit_count = u16::from_le_bytes(rest[10..12].try_into().unwrap());
-        let compression_u32 = u32::from_le_bytes(rest[12..16].try_into().unwrap());
-        let size_image = u32::from_le_bytes(rest[16..20].try_into().unwrap());
-        let x_pixels_per_meter = i32::from_le_bytes(rest[20..24].try_into().unwrap());
-        let y_pixels_per_meter = i32::from_le_bytes(rest[24..28].try_into().unwrap());
-        let colors_used = u32::from_le_bytes(rest[28..32].try_into().unwrap());
-        let important_colors = u32::from_le_bytes(rest[32..36].try_into().unwrap());
-        let rest = &rest[36..];
+        // the remaining fields are shared by BITMAPINFOHEADER and the full BITMAPCOREHEADER2;
+        // the latter appends some more fields which we skip
+        let (compression_u32, size_image, x_pixels_per_meter, y_pixels_per_meter, colors_used, important_colors) = if header_bytes.len() >= 36 {
+            (
+                u32::from_le_bytes(header_bytes[12..16].try_into().unwrap()),
+                u32::from_le_bytes(header_bytes[16..20].try_into().unwrap()),
+                i32::from_le_bytes(header_bytes[20..24].try_into().unwrap()),
+                i32::from_le_bytes(header_bytes[24..28].try_into().unwrap()),
+                u32::from_le_bytes(header_bytes[28..32].try_into().unwrap()),
+                u32::from_le_bytes(header_bytes[32..36].try_into().unwrap()),
+            )
+        } else {
+            (0, 0, 0, 0, 0, 0)
+        };
 
         if planes != 1 {
             return Err(Error::WrongPlaneCount { expected: 1, obtained: planes });
@@ -188,6 +232,18 @@ impl BitmapInfoHeader {
         };
         Ok((rest, header))
     }
+
+    /// The size of an entry in the palette following the header, in bytes.
+    ///
+    /// `BITMAPCOREHEADER` bitmaps store each color as a BGR triple (`RGBTRIPLE`), all other formats
+    /// pad it to a doubleword (`RGBQUAD`).
+    pub fn palette_entry_size(&self) -> usize {
+        if self.header_size == CORE_HEADER_SIZE {
+            3
+        } else {
+            4
+        }
+    }
 }
 
 
@@ -341,7 +397,7 @@ impl Bitmap {
             },
             Palette::Advisory { color_count } => {
                 // just gloss over it
-                let palette_byte_count = usize::try_from(color_count).unwrap() * 4;
+                let palette_byte_count = usize::try_from(color_count).unwrap() * header.palette_entry_size();
                 if rest.len() < palette_byte_count {
                     return Err(Error::TooShort { stage: ReadStage::Palette });
                 }
@@ -350,18 +406,18 @@ impl Bitmap {
             Palette::Indexed { color_count } => {
                 // assemble the palette in PNG order
                 let color_count_usize = usize::try_from(color_count).unwrap();
-                let palette_byte_count = color_count_usize * 4;
+                let entry_size = header.palette_entry_size();
+                let palette_byte_count = color_count_usize * entry_size;
                 if rest.len() < palette_byte_count {
                     return Err(Error::TooShort { stage: ReadStage::Palette });
                 }
 
                 let mut palette = Vec::with_capacity(color_count_usize * 3);
-                for color_bytes in rest[..palette_byte_count].chunks(4) {
-                    // color is in 0RGB order
-                    let color = u32::from_le_bytes(color_bytes.try_into().unwrap());
-                    palette.push(u8::try_from((color >> 16) & 0xFF).unwrap());
-                    palette.push(u8::try_from((color >>  8) & 0xFF).unwrap());
-                    palette.push(u8::try_from((color >>  0) & 0xFF).unwrap());
+                for color_bytes in rest[..palette_byte_count].chunks(entry_size) {
+                    // color is stored as B, G, R (and possibly a padding byte)
+                    palette.push(color_bytes[2]);
+                    palette.push(color_bytes[1]);
+                    palette.push(color_bytes[0]);
                 }
 
                 (&rest[palette_byte_count..], Some(palette))
@@ -412,7 +468,7 @@ impl Bitmap {
 
             (height_usize, data_byte_count, Some(alpha_byte_count))
         } else {
-            let height_usize = raw_height_usize / 2;
+            let height_usize = raw_height_usize;
             let data_byte_count = stride_bytes * height_usize;
 
             // enough bytes?
@@ -667,3 +723,49 @@ fn scale_u5_to_u8(u5_value: u8) -> u8 {
     // 0b1_1111 * 0b1111_1111 == 0b0001_1110_1110_0001, which fits into u16
     u8::try_from((u16::from(u5_value) * 0b1111_1111) / 0b1_1111).unwrap()
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::{Bitmap, CORE_HEADER_SIZE, OS2_SHORT_HEADER_SIZE};
+
+    #[test]
+    fn test_core_header() {
+        // 2x2, 1bpp, bottom-up
+        let mut bytes = Vec::new();
+        bytes.extend(CORE_HEADER_SIZE.to_le_bytes());
+        bytes.extend(2u16.to_le_bytes()); // width
+        bytes.extend(2u16.to_le_bytes()); // height
+        bytes.extend(1u16.to_le_bytes()); // planes
+        bytes.extend(1u16.to_le_bytes()); // bit count
+        bytes.extend([0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00]); // palette: red, blue
+        bytes.extend([0b0100_0000, 0x00, 0x00, 0x00]); // bottom row
+        bytes.extend([0b1000_0000, 0x00, 0x00, 0x00]); // top row
+        bytes.push(0xAA);
+
+        let (rest, bitmap) = Bitmap::take_from_bytes(&bytes, false).unwrap();
+        assert_eq!(rest, &[0xAA]);
+        assert_eq!(bitmap.actual_width(), 2);
+        assert_eq!(bitmap.actual_height(), 2);
+        assert_eq!(bitmap.palette.as_deref(), Some(&[0xFF, 0x00, 0x00, 0x00, 0x00, 0xFF][..]));
+        assert_eq!(
+            bitmap.to_rgba8(),
+            [
+                0x00, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0xFF,
+                0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF, 0xFF,
+            ],
+        );
+
+        // the same image with a truncated OS/2 v2 header, which has quad palette entries
+        let mut os2_bytes = Vec::new();
+        os2_bytes.extend(OS2_SHORT_HEADER_SIZE.to_le_bytes());
+        os2_bytes.extend(2i32.to_le_bytes());
+        os2_bytes.extend(2i32.to_le_bytes());
+        os2_bytes.extend(1u16.to_le_bytes());
+        os2_bytes.extend(1u16.to_le_bytes());
+        os2_bytes.extend([0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0x00, 0x00]);
+        os2_bytes.extend(&bytes[18..]);
+        let (_rest, os2_bitmap) = Bitmap::take_from_bytes(&os2_bytes, false).unwrap();
+        assert_eq!(os2_bitmap.to_rgba8(), bitmap.to_rgba8());
+    }
+}
@@ -587,6 +587,155 @@ impl Font {
     }
 }
 
+/// The font directory resource (RT_FONTDIR) of a font file, which summarizes each of its fonts.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct FontDirectory {
+    // count: u16,
+    pub entries: Vec<FontDirectoryEntry>, // [FontDirectoryEntry; count]
+}
+impl FontDirectory {
+    pub fn take_from_bytes(bytes: &[u8]) -> Result<(&[u8], Self), Error> {
+        if bytes.len() < 2 {
+            return Err(Error::TooShort);
+        }
+        let count = u16::from_le_bytes(bytes[0..2].try_into().unwrap());
+        let mut rest = &bytes[2..];
+        let mut entries = Vec::with_capacity(count.into());
+        for _ in 0..count {
+            let (new_rest, entry) = FontDirectoryEntry::take_from_bytes(rest)?;
+            entries.push(entry);
+            rest = new_rest;
+        }
+        Ok((rest, Self { entries }))
+    }
+}
+
+/// An entry in the font directory, consisting of the ordinal of the font resource and a copy of
+/// the beginning of its header.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct FontDirectoryEntry {
+    pub ordinal: u16,
+    pub version: u16,
+    pub size: u32,
+    pub copyright: DisplayBytesVec, // [u8; 60]
+    pub font_type: TypeFlags, // u16
+    pub point_size: u16,
+    pub vertical_dpi: u16,
+    pub horizontal_dpi: u16,
+    pub ascent: u16,
+    pub internal_leading: u16,
+    pub external_leading: u16,
+    pub italic: Italic, // u8
+    pub underline: Underline, // u8
+    pub strike_out: StrikeOut, // u8
+    pub weight: u16,
+    pub char_set: u8,
+    pub pixel_width: u16,
+    pub pixel_height: u16,
+    pub pitch_and_family: PitchAndFamily, // u8
+    pub average_width: u16,
+    pub max_width: u16,
+    pub first_char: u8,
+    pub last_char: u8,
+    pub default_char: u8,
+    pub break_char_relative: u8, // relative to first_char
+    pub bytes_per_row: u16,
+    pub device_name_offset: u32,
+    pub name_offset: u32,
+    pub reserved: u32,
+
+    pub device_name: String, // NUL-terminated
+    pub name: String, // NUL-terminated
+}
+impl FontDirectoryEntry {
+    pub fn take_from_bytes(bytes: &[u8]) -> Result<(&[u8], Self), Error> {
+        // ordinal and the first 113 bytes of the font header
+        if bytes.len() < 115 {
+            return Err(Error::TooShort);
+        }
+
+        let ordinal = u16::from_le_bytes(bytes[0..2].try_into().unwrap());
+        let header = &bytes[2..];
+        let version = u16::from_le_bytes(header[0..2].try_into().unwrap());
+        let size = u32::from_le_bytes(header[2..6].try_into().unwrap());
+        let copyright = DisplayBytesVec::from(header[6..66].to_vec());
+        let font_type = TypeFlags::from_bits_retain(u16::from_le_bytes(header[66..68].try_into().unwrap()));
+        let point_size = u16::from_le_bytes(header[68..70].try_into().unwrap());
+        let vertical_dpi = u16::from_le_bytes(header[70..72].try_into().unwrap());
+        let horizontal_dpi = u16::from_le_bytes(header[72..74].try_into().unwrap());
+        let ascent = u16::from_le_bytes(header[74..76].try_into().unwrap());
+        let internal_leading = u16::from_le_bytes(header[76..78].try_into().unwrap());
+        let external_leading = u16::from_le_bytes(header[78..80].try_into().unwrap());
+        let italic = Italic::from_bits_retain(header[80]);
+        let underline = Underline::from_bits_retain(header[81]);
+        let strike_out = StrikeOut::from_bits_retain(header[82]);
+        let weight = u16::from_le_bytes(header[83..85].try_into().unwrap());
+        let char_set = header[85];
+        let pixel_width = u16::from_le_bytes(header[86..88].try_into().unwrap());
+        let pixel_height = u16::from_le_bytes(header[88..90].try_into().unwrap());
+        let pitch_and_family = PitchAndFamily::from(header[90]);
+        let average_width = u16::from_le_bytes(header[91..93].try_into().unwrap());
+        let max_width = u16::from_le_bytes(header[93..95].try_into().unwrap());
+        let first_char = header[95];
+        let last_char = header[96];
+        let default_char = header[97];
+        let break_char_relative = header[98];
+        let bytes_per_row = u16::from_le_bytes(header[99..101].try_into().unwrap());
+        let device_name_offset = u32::from_le_bytes(header[101..105].try_into().unwrap());
+        let name_offset = u32::from_le_bytes(header[105..109].try_into().unwrap());
+        let reserved = u32::from_le_bytes(header[109..113].try_into().unwrap());
+
+        // the device name and the face name follow, each NUL-terminated
+        let mut rest = &header[113..];
+        let mut strings = [String::new(), String::new()];
+        for (string, string_type) in strings.iter_mut().zip([StringType::DeviceName, StringType::Name]) {
+            let nul_pos = rest.iter()
+                .position(|b| *b == 0x00)
+                .ok_or(Error::TooShort)?;
+            *string = collect_nul_terminated_ascii_string(rest)
+                .ok_or(Error::InvalidUtf8String { string_type })?;
+            rest = &rest[nul_pos+1..];
+        }
+        let [device_name, name] = strings;
+
+        Ok((rest, Self {
+            ordinal,
+            version,
+            size,
+            copyright,
+            font_type,
+            point_size,
+            vertical_dpi,
+            horizontal_dpi,
+            ascent,
+            internal_leading,
+            external_leading,
+            italic,
+            underline,
+            strike_out,
+            weight,
+            char_set,
+            pixel_width,
+            pixel_height,
+            pitch_and_family,
+            average_width,
+            max_width,
+            first_char,
+            last_char,
+            default_char,
+            break_char_relative,
+            bytes_per_row,
+            device_name_offset,
+            name_offset,
+            reserved,
+            device_name,
+            name,
+        }))
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub enum VersionSpecific {
@@ -826,3 +975,44 @@ impl<I: Iterator<Item = bool>> BitIterExt<I> for I {
         BytePacker::new(self)
     }
 }
+
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::{Family, FontDirectory};
+    use crate::part_int::U4;
+
+    #[test]
+    fn test_font_directory() {
+        let mut bytes = vec![0x01, 0x00, 0x03, 0x00];
+        let mut header = vec![0u8; 113];
+        header[0..2].copy_from_slice(&0x0200u16.to_le_bytes());
+        header[68..70].copy_from_slice(&10u16.to_le_bytes()); // point size
+        header[83..85].copy_from_slice(&400u16.to_le_bytes()); // weight
+        header[88..90].copy_from_slice(&13u16.to_le_bytes()); // pixel height
+        bytes.extend_from_slice(&header);
+        bytes.extend_from_slice(b"\0MS Sans Serif\0");
+
+        let (rest, directory) = FontDirectory::take_from_bytes(&bytes).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(directory.entries.len(), 1);
+        let entry = &directory.entries[0];
+        assert_eq!(entry.ordinal, 3);
+        assert_eq!(entry.point_size, 10);
+        assert_eq!(entry.weight, 400);
+        assert_eq!(entry.pixel_height, 13);
+        assert_eq!(entry.device_name, "");
+        assert_eq!(entry.name, "MS Sans Serif");
+    }
+
+    #[test]
+    fn test_family_json_round_trip() {
+        for value in [1, 5, 9] {
+            let family = Family::from(U4::from_base_type(value).unwrap());
+            let json = serde_json::to_value(family).unwrap();
+            let read_back: Family = serde_json::from_value(json).unwrap();
+            assert_eq!(read_back, family);
+        }
+        assert_eq!(serde_json::to_string(&Family::from(U4::from_base_type(2).unwrap())).unwrap(), "\"Swiss\"");
+    }
+}
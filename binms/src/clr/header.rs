@@ -8,6 +8,15 @@ bitflags! {
     #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
     pub struct ClrFlags : u32 {
         const CLR_ONLY = 0b0000_0001;
+        const REQUIRES_32_BIT = 0b0000_0010;
+
+        /// Set in native images generated by NGEN.
+        const IL_LIBRARY = 0b0000_0100;
+
+        const STRONG_NAME_SIGNED = 0b0000_1000;
+        const NATIVE_ENTRY_POINT = 0b0001_0000;
+        const TRACK_DEBUG_DATA = 0x0001_0000;
+        const PREFERS_32_BIT = 0x0002_0000;
     }
 }
 
@@ -54,32 +63,32 @@ impl ClrHeader {
         let rest = &rest[20..];
 
         let (rest, resources_range) = if rest.len() >= 8 {
-            AddressAndLength32::take_from_bytes(&rest[0..8])?
+            AddressAndLength32::take_from_bytes(rest)?
         } else {
             (rest, AddressAndLength32::default())
         };
         let (rest, strong_name_signature_range) = if rest.len() >= 8 {
-            AddressAndLength32::take_from_bytes(&rest[0..8])?
+            AddressAndLength32::take_from_bytes(rest)?
         } else {
             (rest, AddressAndLength32::default())
         };
         let (rest, code_manager_table_range) = if rest.len() >= 8 {
-            AddressAndLength32::take_from_bytes(&rest[0..8])?
+            AddressAndLength32::take_from_bytes(rest)?
         } else {
             (rest, AddressAndLength32::default())
         };
         let (rest, v_table_fixups_range) = if rest.len() >= 8 {
-            AddressAndLength32::take_from_bytes(&rest[0..8])?
+            AddressAndLength32::take_from_bytes(rest)?
         } else {
             (rest, AddressAndLength32::default())
         };
         let (rest, export_address_table_jumps_range) = if rest.len() >= 8 {
-            AddressAndLength32::take_from_bytes(&rest[0..8])?
+            AddressAndLength32::take_from_bytes(rest)?
         } else {
             (rest, AddressAndLength32::default())
         };
         let (rest, managed_native_header_range) = if rest.len() >= 8 {
-            AddressAndLength32::take_from_bytes(&rest[0..8])?
+            AddressAndLength32::take_from_bytes(rest)?
         } else {
             (rest, AddressAndLength32::default())
         };
@@ -99,4 +108,25 @@ impl ClrHeader {
         };
         Ok((rest, header))
     }
+
+    /// Determines the state of the strong-name signature, given the bytes referenced by
+    /// `strong_name_signature_range` (if any).
+    pub fn strong_name_status(&self, signature: Option<&[u8]>) -> StrongNameStatus {
+        match signature {
+            None | Some([]) => StrongNameStatus::Unsigned,
+            Some(sig) if !self.flags.contains(ClrFlags::STRONG_NAME_SIGNED) || sig.iter().all(|b| *b == 0x00) => {
+                // space has been reserved for the signature but it has not been filled in yet
+                StrongNameStatus::DelaySigned
+            },
+            Some(_) => StrongNameStatus::Signed,
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum StrongNameStatus {
+    Unsigned,
+    DelaySigned,
+    Signed,
 }
@@ -0,0 +1,871 @@
+//! The CLR metadata (ECMA-335 partition II, section 24) of a managed assembly.
+//!
+//! The [CLR header](crate::clr::header::ClrHeader) points (via `metadata_range`) at the metadata
+//! root, which is followed by a set of streams. The streams relevant to us are:
+//!
+//! * `#~` (or `#-` if unoptimized): the tables, each a sequence of fixed-length rows
+//! * `#Strings`: a heap of NUL-terminated UTF-8 strings
+//! * `#Blob`: a heap of length-prefixed byte strings
+//! * `#GUID`: an array of GUIDs
+//!
+//! The size of each column depends on the sizes of the heaps and tables it refers to, so the row
+//! layout of every table has to be known to find any table within the stream.
+
+
+use std::fmt;
+
+use bitflags::bitflags;
+use from_to_repr::from_to_other;
+use sha1::{Digest, Sha1};
+use tracing::debug;
+
+use crate::clr::{Error, Guid};
+use crate::int_from_byte_slice::IntFromByteSlice;
+
+
+pub const METADATA_SIGNATURE: u32 = 0x424A_5342; // b"BSJB"
+
+
+#[derive(Clone, Copy, Debug)]
+#[from_to_other(base_type = u8, derive_compare = "as_int")]
+pub enum Table {
+    Module = 0x00,
+    TypeRef = 0x01,
+    TypeDef = 0x02,
+    FieldPtr = 0x03,
+    Field = 0x04,
+    MethodPtr = 0x05,
+    MethodDef = 0x06,
+    ParamPtr = 0x07,
+    Param = 0x08,
+    InterfaceImpl = 0x09,
+    MemberRef = 0x0A,
+    Constant = 0x0B,
+    CustomAttribute = 0x0C,
+    FieldMarshal = 0x0D,
+    DeclSecurity = 0x0E,
+    ClassLayout = 0x0F,
+    FieldLayout = 0x10,
+    StandAloneSig = 0x11,
+    EventMap = 0x12,
+    EventPtr = 0x13,
+    Event = 0x14,
+    PropertyMap = 0x15,
+    PropertyPtr = 0x16,
+    Property = 0x17,
+    MethodSemantics = 0x18,
+    MethodImpl = 0x19,
+    ModuleRef = 0x1A,
+    TypeSpec = 0x1B,
+    ImplMap = 0x1C,
+    FieldRva = 0x1D,
+    EncLog = 0x1E,
+    EncMap = 0x1F,
+    Assembly = 0x20,
+    AssemblyProcessor = 0x21,
+    AssemblyOs = 0x22,
+    AssemblyRef = 0x23,
+    AssemblyRefProcessor = 0x24,
+    AssemblyRefOs = 0x25,
+    File = 0x26,
+    ExportedType = 0x27,
+    ManifestResource = 0x28,
+    NestedClass = 0x29,
+    GenericParam = 0x2A,
+    MethodSpec = 0x2B,
+    GenericParamConstraint = 0x2C,
+    Other(u8),
+}
+
+
+/// A coded index refers to a row in one of multiple tables; the table is encoded in the lowest
+/// bits of the value.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum CodedIndexKind {
+    TypeDefOrRef,
+    HasConstant,
+    HasCustomAttribute,
+    HasFieldMarshal,
+    HasDeclSecurity,
+    MemberRefParent,
+    HasSemantics,
+    MethodDefOrRef,
+    MemberForwarded,
+    Implementation,
+    CustomAttributeType,
+    ResolutionScope,
+    TypeOrMethodDef,
+}
+impl CodedIndexKind {
+    /// The tables that can be referenced, in the order of their tag values. `None` marks tag values
+    /// that are not in use.
+    pub fn tables(&self) -> &'static [Option<Table>] {
+        use Table as T;
+        match self {
+            Self::TypeDefOrRef => &[Some(T::TypeDef), Some(T::TypeRef), Some(T::TypeSpec)],
+            Self::HasConstant => &[Some(T::Field), Some(T::Param), Some(T::Property)],
+            Self::HasCustomAttribute => &[
+                Some(T::MethodDef), Some(T::Field), Some(T::TypeRef), Some(T::TypeDef), Some(T::Param),
+                Some(T::InterfaceImpl), Some(T::MemberRef), Some(T::Module), Some(T::DeclSecurity),
+                Some(T::Property), Some(T::Event), Some(T::StandAloneSig), Some(T::ModuleRef),
+                Some(T::TypeSpec), Some(T::Assembly), Some(T::AssemblyRef), Some(T::File),
+                Some(T::ExportedType), Some(T::ManifestResource), Some(T::GenericParam),
+                Some(T::GenericParamConstraint), Some(T::MethodSpec),
+            ],
+            Self::HasFieldMarshal => &[Some(T::Field), Some(T::Param)],
+            Self::HasDeclSecurity => &[Some(T::TypeDef), Some(T::MethodDef), Some(T::Assembly)],
+            Self::MemberRefParent => &[Some(T::TypeDef), Some(T::TypeRef), Some(T::ModuleRef), Some(T::MethodDef), Some(T::TypeSpec)],
+            Self::HasSemantics => &[Some(T::Event), Some(T::Property)],
+            Self::MethodDefOrRef => &[Some(T::MethodDef), Some(T::MemberRef)],
+            Self::MemberForwarded => &[Some(T::Field), Some(T::MethodDef)],
+            Self::Implementation => &[Some(T::File), Some(T::AssemblyRef), Some(T::ExportedType)],
+            Self::CustomAttributeType => &[None, None, Some(T::MethodDef), Some(T::MemberRef), None],
+            Self::ResolutionScope => &[Some(T::Module), Some(T::ModuleRef), Some(T::AssemblyRef), Some(T::TypeRef)],
+            Self::TypeOrMethodDef => &[Some(T::TypeDef), Some(T::MethodDef)],
+        }
+    }
+
+    /// The number of low bits used to encode the table.
+    pub fn tag_bits(&self) -> u32 {
+        let table_count = u32::try_from(self.tables().len()).unwrap();
+        u32::BITS - (table_count - 1).leading_zeros()
+    }
+}
+
+
+/// A reference to a row in a table. Row numbers start at 1; row 0 is a null reference.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct TableRow {
+    pub table: Table,
+    pub row: u32,
+}
+
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum Column {
+    U16,
+    U32,
+    StringIndex,
+    GuidIndex,
+    BlobIndex,
+    TableIndex(Table),
+    CodedIndex(CodedIndexKind),
+}
+
+fn table_columns(table: Table) -> &'static [Column] {
+    use CodedIndexKind as K;
+    use Column as C;
+    use Table as T;
+    match table {
+        T::Module => &[C::U16, C::StringIndex, C::GuidIndex, C::GuidIndex, C::GuidIndex],
+        T::TypeRef => &[C::CodedIndex(K::ResolutionScope), C::StringIndex, C::StringIndex],
+        T::TypeDef => &[C::U32, C::StringIndex, C::StringIndex, C::CodedIndex(K::TypeDefOrRef), C::TableIndex(T::Field), C::TableIndex(T::MethodDef)],
+        T::FieldPtr => &[C::TableIndex(T::Field)],
+        T::Field => &[C::U16, C::StringIndex, C::BlobIndex],
+        T::MethodPtr => &[C::TableIndex(T::MethodDef)],
+        T::MethodDef => &[C::U32, C::U16, C::U16, C::StringIndex, C::BlobIndex, C::TableIndex(T::Param)],
+        T::ParamPtr => &[C::TableIndex(T::Param)],
+        T::Param => &[C::U16, C::U16, C::StringIndex],
+        T::InterfaceImpl => &[C::TableIndex(T::TypeDef), C::CodedIndex(K::TypeDefOrRef)],
+        T::MemberRef => &[C::CodedIndex(K::MemberRefParent), C::StringIndex, C::BlobIndex],
+        T::Constant => &[C::U16, C::CodedIndex(K::HasConstant), C::BlobIndex],
+        T::CustomAttribute => &[C::CodedIndex(K::HasCustomAttribute), C::CodedIndex(K::CustomAttributeType), C::BlobIndex],
+        T::FieldMarshal => &[C::CodedIndex(K::HasFieldMarshal), C::BlobIndex],
+        T::DeclSecurity => &[C::U16, C::CodedIndex(K::HasDeclSecurity), C::BlobIndex],
+        T::ClassLayout => &[C::U16, C::U32, C::TableIndex(T::TypeDef)],
+        T::FieldLayout => &[C::U32, C::TableIndex(T::Field)],
+        T::StandAloneSig => &[C::BlobIndex],
+        T::EventMap => &[C::TableIndex(T::TypeDef), C::TableIndex(T::Event)],
+        T::EventPtr => &[C::TableIndex(T::Event)],
+        T::Event => &[C::U16, C::StringIndex, C::CodedIndex(K::TypeDefOrRef)],
+        T::PropertyMap => &[C::TableIndex(T::TypeDef), C::TableIndex(T::Property)],
+        T::PropertyPtr => &[C::TableIndex(T::Property)],
+        T::Property => &[C::U16, C::StringIndex, C::BlobIndex],
+        T::MethodSemantics => &[C::U16, C::TableIndex(T::MethodDef), C::CodedIndex(K::HasSemantics)],
+        T::MethodImpl => &[C::TableIndex(T::TypeDef), C::CodedIndex(K::MethodDefOrRef), C::CodedIndex(K::MethodDefOrRef)],
+        T::ModuleRef => &[C::StringIndex],
+        T::TypeSpec => &[C::BlobIndex],
+        T::ImplMap => &[C::U16, C::CodedIndex(K::MemberForwarded), C::StringIndex, C::TableIndex(T::ModuleRef)],
+        T::FieldRva => &[C::U32, C::TableIndex(T::Field)],
+        T::EncLog => &[C::U32, C::U32],
+        T::EncMap => &[C::U32],
+        T::Assembly => &[C::U32, C::U16, C::U16, C::U16, C::U16, C::U32, C::BlobIndex, C::StringIndex, C::StringIndex],
+        T::AssemblyProcessor => &[C::U32],
+        T::AssemblyOs => &[C::U32, C::U32, C::U32],
+        T::AssemblyRef => &[C::U16, C::U16, C::U16, C::U16, C::U32, C::BlobIndex, C::StringIndex, C::StringIndex, C::BlobIndex],
+        T::AssemblyRefProcessor => &[C::U32, C::TableIndex(T::AssemblyRef)],
+        T::AssemblyRefOs => &[C::U32, C::U32, C::U32, C::TableIndex(T::AssemblyRef)],
+        T::File => &[C::U32, C::StringIndex, C::BlobIndex],
+        T::ExportedType => &[C::U32, C::U32, C::StringIndex, C::StringIndex, C::CodedIndex(K::Implementation)],
+        T::ManifestResource => &[C::U32, C::U32, C::StringIndex, C::CodedIndex(K::Implementation)],
+        T::NestedClass => &[C::TableIndex(T::TypeDef), C::TableIndex(T::TypeDef)],
+        T::GenericParam => &[C::U16, C::U16, C::CodedIndex(K::TypeOrMethodDef), C::StringIndex],
+        T::MethodSpec => &[C::CodedIndex(K::MethodDefOrRef), C::BlobIndex],
+        T::GenericParamConstraint => &[C::TableIndex(T::GenericParam), C::CodedIndex(K::TypeDefOrRef)],
+        T::Other(_) => &[],
+    }
+}
+
+
+bitflags! {
+    #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    pub struct HeapSizes : u8 {
+        const WIDE_STRING_INDEXES = 0x01;
+        const WIDE_GUID_INDEXES = 0x02;
+        const WIDE_BLOB_INDEXES = 0x04;
+        const PADDING_BIT = 0x08;
+        const DELTA_ONLY = 0x20;
+        const EXTRA_DATA = 0x40;
+        const HAS_DELETE = 0x80;
+    }
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct StreamHeader {
+    pub offset: u32,
+    pub size: u32,
+    pub name: String, // NUL-terminated, padded to 4 bytes
+}
+
+
+/// The metadata of a managed assembly, consisting of the metadata root and the contents of the
+/// streams relevant to reading the tables.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ClrMetadata {
+    // signature: u32, // METADATA_SIGNATURE
+    pub major_version: u16,
+    pub minor_version: u16,
+    // reserved: u32,
+    // version_length: u32,
+    pub version: String, // [u8; version_length], NUL-padded
+    pub flags: u16,
+    // stream_count: u16,
+    pub streams: Vec<StreamHeader>,
+
+    pub heap_sizes: HeapSizes,
+    pub valid_tables: u64,
+    pub sorted_tables: u64,
+    pub row_counts: [u32; 64],
+
+    tables_data: Vec<u8>, // the tables following the row counts in the #~ stream
+    strings_heap: Vec<u8>,
+    blob_heap: Vec<u8>,
+    guid_heap: Vec<u8>,
+}
+impl ClrMetadata {
+    pub fn take_from_bytes(bytes: &[u8]) -> Result<(&[u8], Self), Error> {
+        if bytes.len() < 16 {
+            return Err(Error::TooShort);
+        }
+        let signature = u32::from_le_byte_slice(&bytes[0..4]);
+        if signature != METADATA_SIGNATURE {
+            return Err(Error::WrongMagic { expected: METADATA_SIGNATURE, obtained: signature });
+        }
+        let major_version = u16::from_le_byte_slice(&bytes[4..6]);
+        let minor_version = u16::from_le_byte_slice(&bytes[6..8]);
+        let version_length = usize::try_from(u32::from_le_byte_slice(&bytes[12..16])).unwrap();
+        let rest = &bytes[16..];
+        if rest.len() < version_length + 4 {
+            return Err(Error::TooShort);
+        }
+        let version_bytes = &rest[..version_length];
+        let version_end = version_bytes.iter().position(|b| *b == 0x00).unwrap_or(version_length);
+        let version = String::from_utf8(version_bytes[..version_end].to_vec())
+            .map_err(|_| Error::InvalidText)?;
+        let rest = &rest[version_length..];
+
+        let flags = u16::from_le_byte_slice(&rest[0..2]);
+        let stream_count = u16::from_le_byte_slice(&rest[2..4]);
+        let mut rest = &rest[4..];
+
+        let mut streams = Vec::with_capacity(stream_count.into());
+        for _ in 0..stream_count {
+            if rest.len() < 8 {
+                return Err(Error::TooShort);
+            }
+            let offset = u32::from_le_byte_slice(&rest[0..4]);
+            let size = u32::from_le_byte_slice(&rest[4..8]);
+            rest = &rest[8..];
+
+            let Some(name_length) = rest.iter().position(|b| *b == 0x00) else {
+                return Err(Error::TooShort);
+            };
+            let name = String::from_utf8(rest[..name_length].to_vec())
+                .map_err(|_| Error::InvalidText)?;
+            let padded_length = (name_length + 1).next_multiple_of(4);
+            if rest.len() < padded_length {
+                return Err(Error::TooShort);
+            }
+            rest = &rest[padded_length..];
+
+            streams.push(StreamHeader {
+                offset,
+                size,
+                name,
+            });
+        }
+
+        let stream_data = |name: &str| -> Result<Vec<u8>, Error> {
+            let Some(stream) = streams.iter().find(|s| s.name == name) else {
+                return Ok(Vec::new());
+            };
+            let start = usize::try_from(stream.offset).unwrap();
+            let end = start + usize::try_from(stream.size).unwrap();
+            if end > bytes.len() {
+                debug!("metadata stream {:?} ends at {:#X}, beyond the end of the metadata ({:#X})", name, end, bytes.len());
+                return Err(Error::TooShort);
+            }
+            Ok(bytes[start..end].to_vec())
+        };
+        let strings_heap = stream_data("#Strings")?;
+        let blob_heap = stream_data("#Blob")?;
+        let guid_heap = stream_data("#GUID")?;
+        let mut tables_stream = stream_data("#~")?;
+        if tables_stream.is_empty() {
+            tables_stream = stream_data("#-")?;
+        }
+
+        // tables stream header
+        if tables_stream.len() < 24 {
+            debug!("tables stream is too short ({} bytes)", tables_stream.len());
+            return Err(Error::TooShort);
+        }
+        let heap_sizes = HeapSizes::from_bits_retain(tables_stream[6]);
+        let valid_tables = u64::from_le_byte_slice(&tables_stream[8..16]);
+        let sorted_tables = u64::from_le_byte_slice(&tables_stream[16..24]);
+        let mut tables_offset = 24;
+        let mut row_counts = [0u32; 64];
+        for (i, row_count) in row_counts.iter_mut().enumerate() {
+            if valid_tables & (1 << i) == 0 {
+                continue;
+            }
+            if tables_stream.len() < tables_offset + 4 {
+                return Err(Error::TooShort);
+            }
+            *row_count = u32::from_le_byte_slice(&tables_stream[tables_offset..tables_offset+4]);
+            tables_offset += 4;
+        }
+        if heap_sizes.contains(HeapSizes::EXTRA_DATA) {
+            tables_offset += 4;
+        }
+        if tables_stream.len() < tables_offset {
+            return Err(Error::TooShort);
+        }
+        let tables_data = tables_stream[tables_offset..].to_vec();
+
+        let metadata = Self {
+            major_version,
+            minor_version,
+            version,
+            flags,
+            streams,
+            heap_sizes,
+            valid_tables,
+            sorted_tables,
+            row_counts,
+            tables_data,
+            strings_heap,
+            blob_heap,
+            guid_heap,
+        };
+        Ok((&[], metadata))
+    }
+
+    pub fn row_count(&self, table: Table) -> u32 {
+        self.row_counts[usize::from(table.to_base_type()) % 64]
+    }
+
+    fn column_size(&self, column: Column) -> usize {
+        match column {
+            Column::U16 => 2,
+            Column::U32 => 4,
+            Column::StringIndex => if self.heap_sizes.contains(HeapSizes::WIDE_STRING_INDEXES) { 4 } else { 2 },
+            Column::GuidIndex => if self.heap_sizes.contains(HeapSizes::WIDE_GUID_INDEXES) { 4 } else { 2 },
+            Column::BlobIndex => if self.heap_sizes.contains(HeapSizes::WIDE_BLOB_INDEXES) { 4 } else { 2 },
+            Column::TableIndex(table) => if self.row_count(table) < 0x1_0000 { 2 } else { 4 },
+            Column::CodedIndex(kind) => {
+                let max_rows = kind.tables().iter()
+                    .flatten()
+                    .map(|t| self.row_count(*t))
+                    .max()
+                    .unwrap_or(0);
+                if max_rows < (1 << (16 - kind.tag_bits())) { 2 } else { 4 }
+            },
+        }
+    }
+
+    fn row_size(&self, table: Table) -> usize {
+        table_columns(table).iter()
+            .map(|c| self.column_size(*c))
+            .sum()
+    }
+
+    /// Returns the raw values of the columns of each row of the given table.
+    pub fn table_rows(&self, table: Table) -> Result<Vec<Vec<u32>>, Error> {
+        let table_index = table.to_base_type();
+        if table_index >= 64 || self.valid_tables & (1 << table_index) == 0 {
+            return Ok(Vec::new());
+        }
+
+        // skip the preceding tables
+        let mut offset = 0usize;
+        for preceding_index in 0..table_index {
+            let preceding_table = Table::from_base_type(preceding_index);
+            let row_count = usize::try_from(self.row_count(preceding_table)).unwrap();
+            if row_count > 0 && matches!(preceding_table, Table::Other(_)) {
+                debug!("cannot skip unknown metadata table {:#04X}", preceding_index);
+                return Err(Error::InvalidTable { obtained: preceding_index });
+            }
+            offset += row_count * self.row_size(preceding_table);
+        }
+
+        let columns = table_columns(table);
+        let row_count = usize::try_from(self.row_count(table)).unwrap();
+        let row_size = self.row_size(table);
+        if self.tables_data.len() < offset + row_count * row_size {
+            debug!("metadata table {:?} extends beyond the end of the tables stream", table);
+            return Err(Error::TooShort);
+        }
+
+        let mut rows = Vec::with_capacity(row_count);
+        for _ in 0..row_count {
+            let mut values = Vec::with_capacity(columns.len());
+            for column in columns {
+                let size = self.column_size(*column);
+                let value = if size == 2 {
+                    u16::from_le_byte_slice(&self.tables_data[offset..offset+2]).into()
+                } else {
+                    u32::from_le_byte_slice(&self.tables_data[offset..offset+4])
+                };
+                values.push(value);
+                offset += size;
+            }
+            rows.push(values);
+        }
+        Ok(rows)
+    }
+
+    /// Obtains the string at the given index of the `#Strings` heap.
+    pub fn string(&self, index: u32) -> Result<String, Error> {
+        let start = usize::try_from(index).unwrap();
+        if start >= self.strings_heap.len() {
+            if start == 0 {
+                // empty heap; index 0 is always the empty string
+                return Ok(String::new());
+            }
+            return Err(Error::HeapIndex { obtained: index });
+        }
+        let string_bytes = &self.strings_heap[start..];
+        let length = string_bytes.iter()
+            .position(|b| *b == 0x00)
+            .unwrap_or(string_bytes.len());
+        String::from_utf8(string_bytes[..length].to_vec())
+            .map_err(|_| Error::InvalidText)
+    }
+
+    /// Obtains the blob at the given index of the `#Blob` heap.
+    pub fn blob(&self, index: u32) -> Result<&[u8], Error> {
+        let start = usize::try_from(index).unwrap();
+        if start >= self.blob_heap.len() {
+            if start == 0 {
+                return Ok(&[]);
+            }
+            return Err(Error::HeapIndex { obtained: index });
+        }
+        let (rest, length) = take_compressed_unsigned(&self.blob_heap[start..])?;
+        let length = usize::try_from(length).unwrap();
+        if rest.len() < length {
+            return Err(Error::TooShort);
+        }
+        Ok(&rest[..length])
+    }
+
+    /// Obtains the GUID at the given (1-based) index of the `#GUID` heap.
+    pub fn guid(&self, index: u32) -> Result<Option<Guid>, Error> {
+        if index == 0 {
+            return Ok(None);
+        }
+        let start = usize::try_from(index - 1).unwrap() * 16;
+        if start + 16 > self.guid_heap.len() {
+            return Err(Error::HeapIndex { obtained: index });
+        }
+        Ok(Some(Guid::from_le_bytes(self.guid_heap[start..start+16].try_into().unwrap())))
+    }
+
+    /// Decodes a coded index into the referenced table and row.
+    pub fn decode_coded_index(&self, kind: CodedIndexKind, value: u32) -> Result<Option<TableRow>, Error> {
+        let tag_bits = kind.tag_bits();
+        let tag = usize::try_from(value & ((1 << tag_bits) - 1)).unwrap();
+        let row = value >> tag_bits;
+        let Some(Some(table)) = kind.tables().get(tag) else {
+            return Err(Error::InvalidTable { obtained: tag.try_into().unwrap() });
+        };
+        if row == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(TableRow { table: *table, row }))
+        }
+    }
+
+    /// Collects the rows of the `ManifestResource` table.
+    pub fn manifest_resources(&self) -> Result<Vec<ManifestResource>, Error> {
+        let mut resources = Vec::new();
+        for row in self.table_rows(Table::ManifestResource)? {
+            resources.push(ManifestResource {
+                offset: row[0],
+                flags: ManifestResourceFlags::from_bits_retain(row[1]),
+                name: self.string(row[2])?,
+                implementation: self.decode_coded_index(CodedIndexKind::Implementation, row[3])?,
+            });
+        }
+        Ok(resources)
+    }
+
+    /// Collects the rows of the `TypeDef` table.
+    pub fn type_defs(&self) -> Result<Vec<TypeDef>, Error> {
+        let mut type_defs = Vec::new();
+        for row in self.table_rows(Table::TypeDef)? {
+            type_defs.push(TypeDef {
+                flags: TypeAttributes::from_bits_retain(row[0]),
+                name: self.string(row[1])?,
+                namespace: self.string(row[2])?,
+                extends: self.decode_coded_index(CodedIndexKind::TypeDefOrRef, row[3])?,
+                field_list: row[4],
+                method_list: row[5],
+            });
+        }
+        Ok(type_defs)
+    }
+
+    /// Collects the rows of the `TypeRef` table.
+    pub fn type_refs(&self) -> Result<Vec<TypeRef>, Error> {
+        let mut type_refs = Vec::new();
+        for row in self.table_rows(Table::TypeRef)? {
+            type_refs.push(TypeRef {
+                resolution_scope: self.decode_coded_index(CodedIndexKind::ResolutionScope, row[0])?,
+                name: self.string(row[1])?,
+                namespace: self.string(row[2])?,
+            });
+        }
+        Ok(type_refs)
+    }
+
+    /// Collects the rows of the `MethodDef` table.
+    pub fn method_defs(&self) -> Result<Vec<MethodDef>, Error> {
+        let mut method_defs = Vec::new();
+        for row in self.table_rows(Table::MethodDef)? {
+            method_defs.push(MethodDef {
+                rva: row[0],
+                impl_flags: row[1].try_into().unwrap(),
+                flags: row[2].try_into().unwrap(),
+                name: self.string(row[3])?,
+                signature: self.blob(row[4])?.to_vec(),
+                param_list: row[5],
+            });
+        }
+        Ok(method_defs)
+    }
+
+    /// Collects the rows of the `MemberRef` table.
+    pub fn member_refs(&self) -> Result<Vec<MemberRef>, Error> {
+        let mut member_refs = Vec::new();
+        for row in self.table_rows(Table::MemberRef)? {
+            member_refs.push(MemberRef {
+                class: self.decode_coded_index(CodedIndexKind::MemberRefParent, row[0])?,
+                name: self.string(row[1])?,
+                signature: self.blob(row[2])?.to_vec(),
+            });
+        }
+        Ok(member_refs)
+    }
+
+    /// Collects the rows of the `CustomAttribute` table.
+    pub fn custom_attributes(&self) -> Result<Vec<CustomAttribute>, Error> {
+        let mut custom_attributes = Vec::new();
+        for row in self.table_rows(Table::CustomAttribute)? {
+            custom_attributes.push(CustomAttribute {
+                parent: self.decode_coded_index(CodedIndexKind::HasCustomAttribute, row[0])?,
+                constructor: self.decode_coded_index(CodedIndexKind::CustomAttributeType, row[1])?,
+                value: self.blob(row[2])?.to_vec(),
+            });
+        }
+        Ok(custom_attributes)
+    }
+
+    /// Obtains the row of the `Assembly` table, which only exists in the main module of an
+    /// assembly.
+    pub fn assembly(&self) -> Result<Option<Assembly>, Error> {
+        let rows = self.table_rows(Table::Assembly)?;
+        let Some(row) = rows.first() else {
+            return Ok(None)
+        };
+        Ok(Some(Assembly {
+            hash_algorithm: row[0],
+            major_version: row[1].try_into().unwrap(),
+            minor_version: row[2].try_into().unwrap(),
+            build_number: row[3].try_into().unwrap(),
+            revision_number: row[4].try_into().unwrap(),
+            flags: AssemblyFlags::from_bits_retain(row[5]),
+            public_key: self.blob(row[6])?.to_vec(),
+            name: self.string(row[7])?,
+            culture: self.string(row[8])?,
+        }))
+    }
+}
+
+
+bitflags! {
+    #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    pub struct ManifestResourceFlags : u32 {
+        const PUBLIC = 0x0001;
+        const PRIVATE = 0x0002;
+    }
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ManifestResource {
+    /// The offset of the resource's data within the CLR header's `resources_range`, if it is
+    /// embedded in this file.
+    pub offset: u32,
+
+    pub flags: ManifestResourceFlags,
+    pub name: String,
+
+    /// The file or assembly containing the resource; `None` if it is embedded in this file.
+    pub implementation: Option<TableRow>,
+}
+
+
+bitflags! {
+    #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    pub struct TypeAttributes : u32 {
+        // visibility (mask 0x07)
+        const PUBLIC = 0x0000_0001;
+        const NESTED_PUBLIC = 0x0000_0002;
+
+        const INTERFACE = 0x0000_0020;
+        const ABSTRACT = 0x0000_0080;
+        const SEALED = 0x0000_0100;
+        const SPECIAL_NAME = 0x0000_0400;
+        const IMPORT = 0x0000_1000;
+        const SERIALIZABLE = 0x0000_2000;
+        const WINDOWS_RUNTIME = 0x0000_4000;
+        const BEFORE_FIELD_INIT = 0x0010_0000;
+    }
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct TypeDef {
+    pub flags: TypeAttributes,
+    pub name: String,
+    pub namespace: String,
+
+    /// The base type; a `TypeDef`, `TypeRef` or `TypeSpec` row.
+    pub extends: Option<TableRow>,
+
+    /// The first row of the `Field` table belonging to this type.
+    pub field_list: u32,
+
+    /// The first row of the `MethodDef` table belonging to this type; the methods extend until the
+    /// `method_list` of the following type.
+    pub method_list: u32,
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct TypeRef {
+    pub resolution_scope: Option<TableRow>,
+    pub name: String,
+    pub namespace: String,
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct MethodDef {
+    pub rva: u32,
+    pub impl_flags: u16,
+    pub flags: u16,
+    pub name: String,
+    pub signature: Vec<u8>,
+    pub param_list: u32,
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct MemberRef {
+    /// The type containing the member; a `TypeDef`, `TypeRef`, `ModuleRef`, `MethodDef` or
+    /// `TypeSpec` row.
+    pub class: Option<TableRow>,
+
+    pub name: String,
+    pub signature: Vec<u8>,
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CustomAttribute {
+    /// The entity to which the attribute is attached.
+    pub parent: Option<TableRow>,
+
+    /// The constructor of the attribute; a `MethodDef` or `MemberRef` row.
+    pub constructor: Option<TableRow>,
+
+    /// The arguments passed to the constructor, starting with the prolog 0x0001.
+    pub value: Vec<u8>,
+}
+
+
+bitflags! {
+    #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    pub struct AssemblyFlags : u32 {
+        const PUBLIC_KEY = 0x0001;
+        const RETARGETABLE = 0x0100;
+        const WINDOWS_RUNTIME = 0x0200;
+        const DISABLE_JIT_COMPILE_OPTIMIZER = 0x4000;
+        const ENABLE_JIT_COMPILE_TRACKING = 0x8000;
+    }
+}
+
+
+/// The identity of an assembly.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Assembly {
+    pub hash_algorithm: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub build_number: u16,
+    pub revision_number: u16,
+    pub flags: AssemblyFlags,
+    pub public_key: Vec<u8>,
+    pub name: String,
+    pub culture: String,
+}
+impl Assembly {
+    /// The public key token, i.e. the last 8 bytes of the SHA-1 hash of the public key in reverse
+    /// order. `None` if the assembly does not have a strong name.
+    pub fn public_key_token(&self) -> Option<[u8; 8]> {
+        if self.public_key.is_empty() {
+            return None;
+        }
+        let hash = Sha1::digest(&self.public_key);
+        let mut token: [u8; 8] = hash[hash.len()-8..].try_into().unwrap();
+        token.reverse();
+        Some(token)
+    }
+
+    /// The display name of the assembly as used by the runtime, e.g.
+    /// `System, Version=4.0.0.0, Culture=neutral, PublicKeyToken=b77a5c561934e089`.
+    pub fn display_name(&self) -> String {
+        self.to_string()
+    }
+}
+impl fmt::Display for Assembly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "{}, Version={}.{}.{}.{}, Culture={}, PublicKeyToken=",
+            self.name, self.major_version, self.minor_version, self.build_number, self.revision_number,
+            if self.culture.is_empty() { "neutral" } else { self.culture.as_str() },
+        )?;
+        match self.public_key_token() {
+            Some(token) => {
+                for b in token {
+                    write!(f, "{:02x}", b)?;
+                }
+                Ok(())
+            },
+            None => write!(f, "null"),
+        }
+    }
+}
+
+
+/// Reads an unsigned integer in the compressed format used by blob lengths and signatures.
+pub fn take_compressed_unsigned(bytes: &[u8]) -> Result<(&[u8], u32), Error> {
+    if bytes.is_empty() {
+        return Err(Error::TooShort);
+    }
+    if bytes[0] & 0b1000_0000 == 0 {
+        Ok((&bytes[1..], bytes[0].into()))
+    } else if bytes[0] & 0b1100_0000 == 0b1000_0000 {
+        if bytes.len() < 2 {
+            return Err(Error::TooShort);
+        }
+        let value = (u32::from(bytes[0] & 0b0011_1111) << 8) | u32::from(bytes[1]);
+        Ok((&bytes[2..], value))
+    } else if bytes[0] & 0b1110_0000 == 0b1100_0000 {
+        if bytes.len() < 4 {
+            return Err(Error::TooShort);
+        }
+        let value = (u32::from(bytes[0] & 0b0001_1111) << 24)
+            | (u32::from(bytes[1]) << 16)
+            | (u32::from(bytes[2]) << 8)
+            | u32::from(bytes[3]);
+        Ok((&bytes[4..], value))
+    } else {
+        Err(Error::VariableLengthIntegerLength { max_size: 4 })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::clr::Error;
+    use super::{take_compressed_unsigned, Assembly, AssemblyFlags};
+
+    #[test]
+    fn test_take_compressed_unsigned() {
+        // the examples from ECMA-335 II.23.2
+        let cases: [(&[u8], u32); 8] = [
+            (&[0x03], 0x03),
+            (&[0x7F], 0x7F),
+            (&[0x80, 0x80], 0x80),
+            (&[0xAE, 0x57], 0x2E57),
+            (&[0xBF, 0xFF], 0x3FFF),
+            (&[0xC0, 0x00, 0x40, 0x00], 0x4000),
+            (&[0xDF, 0xFF, 0xFF, 0xFF], 0x1FFF_FFFF),
+            (&[0x00], 0x00),
+        ];
+        for (bytes, value) in cases {
+            assert_eq!(take_compressed_unsigned(bytes), Ok((&[][..], value)), "{:02X?}", bytes);
+        }
+
+        assert_eq!(take_compressed_unsigned(&[0x81, 0x02, 0x03]), Ok((&[0x03][..], 0x0102)));
+        assert_eq!(take_compressed_unsigned(&[]), Err(Error::TooShort));
+        assert_eq!(take_compressed_unsigned(&[0x80]), Err(Error::TooShort));
+        assert_eq!(take_compressed_unsigned(&[0xC0, 0x00, 0x40]), Err(Error::TooShort));
+        assert_eq!(
+            take_compressed_unsigned(&[0xE0, 0x00, 0x00, 0x00, 0x00]),
+            Err(Error::VariableLengthIntegerLength { max_size: 4 }),
+        );
+    }
+
+    #[test]
+    fn test_public_key_token() {
+        // the ECMA standard public key, whose token belongs to mscorlib, System etc.
+        let mut assembly = Assembly {
+            hash_algorithm: 0x8004,
+            major_version: 4,
+            minor_version: 0,
+            build_number: 0,
+            revision_number: 0,
+            flags: AssemblyFlags::PUBLIC_KEY,
+            public_key: vec![0, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0],
+            name: "System".to_owned(),
+            culture: String::new(),
+        };
+        assert_eq!(assembly.public_key_token(), Some([0xb7, 0x7a, 0x5c, 0x56, 0x19, 0x34, 0xe0, 0x89]));
+        assert_eq!(
+            assembly.display_name(),
+            "System, Version=4.0.0.0, Culture=neutral, PublicKeyToken=b77a5c561934e089",
+        );
+
+        assembly.flags = AssemblyFlags::empty();
+        assembly.public_key.clear();
+        assembly.culture = "de-AT".to_owned();
+        assert_eq!(assembly.public_key_token(), None);
+        assert_eq!(assembly.display_name(), "System, Version=4.0.0.0, Culture=de-AT, PublicKeyToken=null");
+    }
+}
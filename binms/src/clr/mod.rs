@@ -1,5 +1,8 @@
 pub mod header;
+pub mod metadata;
+pub mod ready_to_run;
 pub mod resources;
+pub mod winmd;
 
 
 use std::fmt;
@@ -14,6 +17,8 @@ pub enum Error {
     WrongMagic { expected: u32, obtained: u32 },
     InvalidText,
     InvalidTypeIndex { obtained: u64 },
+    HeapIndex { obtained: u32 },
+    InvalidTable { obtained: u8 },
 }
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -32,6 +37,10 @@ impl fmt::Display for Error {
                 => write!(f, "invalid encoding of a textual string"),
             Self::InvalidTypeIndex { obtained }
                 => write!(f, "invalid resource type index {}", obtained),
+            Self::HeapIndex { obtained }
+                => write!(f, "metadata heap index {} out of range", obtained),
+            Self::InvalidTable { obtained }
+                => write!(f, "invalid metadata table {:#04X}", obtained),
         }
     }
 }
@@ -60,3 +69,35 @@ impl AddressAndLength32 {
         ))
     }
 }
+
+
+/// A globally unique identifier in the Microsoft layout.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Guid {
+    pub data1: u32,
+    pub data2: u16,
+    pub data3: u16,
+    pub data4: [u8; 8],
+}
+impl Guid {
+    pub fn from_le_bytes(bytes: [u8; 16]) -> Self {
+        Self {
+            data1: u32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+            data2: u16::from_le_bytes(bytes[4..6].try_into().unwrap()),
+            data3: u16::from_le_bytes(bytes[6..8].try_into().unwrap()),
+            data4: bytes[8..16].try_into().unwrap(),
+        }
+    }
+}
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-",
+            self.data1, self.data2, self.data3, self.data4[0], self.data4[1],
+        )?;
+        for b in &self.data4[2..] {
+            write!(f, "{:02X}", b)?;
+        }
+        write!(f, "}}")
+    }
+}
@@ -0,0 +1,172 @@
+//! Headers of precompiled (ReadyToRun and NGEN) .NET images.
+//!
+//! Both kinds of image contain native code generated from the IL code of the assembly. Their
+//! export tables are generated by the compiler as well and do not constitute a stable API.
+//!
+//! The [CLR header](crate::clr::header::ClrHeader) of a ReadyToRun image points (via
+//! `managed_native_header_range`) at the following structure:
+//!
+//! ```plain
+//! struct ReadyToRunHeader {
+//!     pub signature: u32, // b"RTR\0"
+//!     pub major_version: u16,
+//!     pub minor_version: u16,
+//!     pub flags: u32,
+//!     pub section_count: u32,
+//!     pub sections: [ReadyToRunSection; section_count],
+//! }
+//! ```
+//!
+//! In NGEN images, the same field points at an undocumented `CORCOMPILE_HEADER` structure instead;
+//! such images are recognized by the `IL_LIBRARY` flag in the CLR header.
+
+
+use bitflags::bitflags;
+use from_to_repr::from_to_other;
+use tracing::debug;
+
+use crate::clr::{AddressAndLength32, Error};
+use crate::clr::header::{ClrFlags, ClrHeader};
+use crate::int_from_byte_slice::IntFromByteSlice;
+
+
+pub const READY_TO_RUN_SIGNATURE: u32 = 0x0052_5452; // b"RTR\0"
+
+
+/// The kind of precompiled native code contained in a .NET image.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum NativeImageKind {
+    /// The image only contains IL code, which is compiled at runtime.
+    None,
+
+    /// The image was precompiled by crossgen into the version-resilient ReadyToRun format.
+    ReadyToRun,
+
+    /// The image was precompiled by NGEN and is only valid for a specific runtime installation.
+    Ngen,
+}
+impl NativeImageKind {
+    /// Identifies the kind of native image, given the CLR header and the bytes referenced by its
+    /// `managed_native_header_range` (if any).
+    pub fn detect(header: &ClrHeader, managed_native_header: Option<&[u8]>) -> Self {
+        let has_rtr_signature = managed_native_header
+            .is_some_and(|mnh| mnh.len() >= 4 && u32::from_le_byte_slice(&mnh[0..4]) == READY_TO_RUN_SIGNATURE);
+        if has_rtr_signature {
+            return Self::ReadyToRun;
+        }
+        if header.flags.contains(ClrFlags::IL_LIBRARY) {
+            return Self::Ngen;
+        }
+        Self::None
+    }
+
+    pub fn is_precompiled(&self) -> bool {
+        !matches!(self, Self::None)
+    }
+}
+
+
+bitflags! {
+    #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    pub struct ReadyToRunFlags : u32 {
+        const PLATFORM_NEUTRAL_SOURCE = 0x0000_0001;
+        const SKIP_TYPE_VALIDATION = 0x0000_0002;
+        const PARTIAL = 0x0000_0004;
+        const NON_SHARED_PINVOKE_STUBS = 0x0000_0008;
+        const EMBEDDED_MSIL = 0x0000_0010;
+        const COMPONENT = 0x0000_0020;
+        const MULTI_MODULE_VERSION_BUBBLE = 0x0000_0040;
+        const UNRELATED_R2R_CODE = 0x0000_0080;
+    }
+}
+
+
+#[derive(Clone, Copy, Debug)]
+#[from_to_other(base_type = u32, derive_compare = "as_int")]
+pub enum ReadyToRunSectionType {
+    CompilerIdentifier = 100,
+    ImportSections = 101,
+    RuntimeFunctions = 102,
+    MethodDefEntryPoints = 103,
+    ExceptionInfo = 104,
+    DebugInfo = 105,
+    DelayLoadMethodCallThunks = 106,
+    AvailableTypes = 108,
+    InstanceMethodEntryPoints = 109,
+    InliningInfo = 110,
+    ProfileDataInfo = 111,
+    ManifestMetadata = 112,
+    AttributePresence = 113,
+    InliningInfo2 = 114,
+    ComponentAssemblies = 115,
+    OwnerCompositeExecutable = 116,
+    PgoInstrumentationData = 117,
+    ManifestAssemblyMvids = 118,
+    CrossModuleInlineInfo = 119,
+    HotColdMap = 120,
+    MethodIsGenericMap = 121,
+    EnclosingTypeMap = 122,
+    TypeGenericInfoMap = 123,
+    Other(u32),
+}
+
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ReadyToRunSection {
+    pub section_type: ReadyToRunSectionType, // u32
+    pub range: AddressAndLength32, // 64
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ReadyToRunHeader {
+    // signature: u32, // READY_TO_RUN_SIGNATURE
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub flags: ReadyToRunFlags,
+    // section_count: u32,
+    pub sections: Vec<ReadyToRunSection>,
+}
+impl ReadyToRunHeader {
+    pub fn take_from_bytes(bytes: &[u8]) -> Result<(&[u8], Self), Error> {
+        let rest = bytes;
+        if rest.len() < 16 {
+            return Err(Error::TooShort);
+        }
+
+        let signature = u32::from_le_byte_slice(&rest[0..4]);
+        if signature != READY_TO_RUN_SIGNATURE {
+            return Err(Error::WrongMagic { expected: READY_TO_RUN_SIGNATURE, obtained: signature });
+        }
+        let major_version = u16::from_le_byte_slice(&rest[4..6]);
+        let minor_version = u16::from_le_byte_slice(&rest[6..8]);
+        let flags = ReadyToRunFlags::from_bits_retain(u32::from_le_byte_slice(&rest[8..12]));
+        let section_count = u32::from_le_byte_slice(&rest[12..16]);
+        let mut rest = &rest[16..];
+
+        let section_count_usize = usize::try_from(section_count).unwrap();
+        if rest.len() / 12 < section_count_usize {
+            debug!("ReadyToRun header claims {} sections but only {} bytes remain", section_count, rest.len());
+            return Err(Error::TooShort);
+        }
+        let mut sections = Vec::with_capacity(section_count_usize);
+        for _ in 0..section_count {
+            let section_type = ReadyToRunSectionType::from_base_type(u32::from_le_byte_slice(&rest[0..4]));
+            let (new_rest, range) = AddressAndLength32::take_from_bytes(&rest[4..12])?;
+            debug_assert!(new_rest.is_empty());
+            rest = &rest[12..];
+            sections.push(ReadyToRunSection {
+                section_type,
+                range,
+            });
+        }
+
+        let header = Self {
+            major_version,
+            minor_version,
+            flags,
+            sections,
+        };
+        Ok((rest, header))
+    }
+}
@@ -236,6 +236,138 @@ pub struct ClrResource {
     pub resource_type: ResourceType,
     pub data: DisplayBytesVec,
 }
+impl ClrResource {
+    /// Decodes the value of the resource.
+    ///
+    /// `custom_resource_types` is the list of custom types of the containing
+    /// [`ClrResourceContainer`], which is used to name the type of values that have been serialized
+    /// using a custom serializer.
+    pub fn decode_value(&self, custom_resource_types: &[String]) -> Result<ClrResourceValue, Error> {
+        let data: &[u8] = self.data.as_ref();
+        fn fixed<const N: usize>(data: &[u8]) -> Result<[u8; N], Error> {
+            data.get(0..N)
+                .map(|d| d.try_into().unwrap())
+                .ok_or(Error::TooShort)
+        }
+        fn length_prefixed(data: &[u8]) -> Result<Vec<u8>, Error> {
+            let length = usize::try_from(u32::from_le_bytes(fixed(data)?)).unwrap();
+            data.get(4..4+length)
+                .map(|d| d.to_vec())
+                .ok_or(Error::TooShort)
+        }
+
+        let value = match self.resource_type {
+            ResourceType::Null => ClrResourceValue::Null,
+            ResourceType::String => {
+                let (_, string_bytes) = take_length_prefixed_bytes(data)?;
+                let string = String::from_utf8(string_bytes.to_vec())
+                    .map_err(|_| Error::InvalidText)?;
+                ClrResourceValue::String(string)
+            },
+            ResourceType::Boolean => ClrResourceValue::Boolean(fixed::<1>(data)?[0] != 0),
+            ResourceType::Char => {
+                let unit = u16::from_le_bytes(fixed(data)?);
+                ClrResourceValue::Char(char::from_u32(unit.into()).unwrap_or(char::REPLACEMENT_CHARACTER))
+            },
+            ResourceType::Byte => ClrResourceValue::Byte(fixed::<1>(data)?[0]),
+            ResourceType::SignedByte => ClrResourceValue::SignedByte(i8::from_le_bytes(fixed(data)?)),
+            ResourceType::Int16 => ClrResourceValue::Int16(i16::from_le_bytes(fixed(data)?)),
+            ResourceType::UInt16 => ClrResourceValue::UInt16(u16::from_le_bytes(fixed(data)?)),
+            ResourceType::Int32 => ClrResourceValue::Int32(i32::from_le_bytes(fixed(data)?)),
+            ResourceType::UInt32 => ClrResourceValue::UInt32(u32::from_le_bytes(fixed(data)?)),
+            ResourceType::Int64 => ClrResourceValue::Int64(i64::from_le_bytes(fixed(data)?)),
+            ResourceType::UInt64 => ClrResourceValue::UInt64(u64::from_le_bytes(fixed(data)?)),
+            ResourceType::Single => ClrResourceValue::Single(f32::from_le_bytes(fixed(data)?).to_string()),
+            ResourceType::Double => ClrResourceValue::Double(f64::from_le_bytes(fixed(data)?).to_string()),
+            ResourceType::Decimal => ClrResourceValue::Decimal(decimal_to_string(fixed(data)?)),
+            ResourceType::DateTime => ClrResourceValue::DateTime(i64::from_le_bytes(fixed(data)?)),
+            ResourceType::TimeSpan => ClrResourceValue::TimeSpan(i64::from_le_bytes(fixed(data)?)),
+            ResourceType::ByteArray => ClrResourceValue::ByteArray(length_prefixed(data)?.into()),
+            ResourceType::Stream => ClrResourceValue::Stream(length_prefixed(data)?.into()),
+            ResourceType::Custom(index) => {
+                let type_name = usize::try_from(index).ok()
+                    .and_then(|i| custom_resource_types.get(i))
+                    .ok_or(Error::InvalidTypeIndex { obtained: index + 64 })?
+                    .clone();
+                ClrResourceValue::Custom {
+                    type_name,
+                    data: self.data.clone(),
+                }
+            },
+        };
+        Ok(value)
+    }
+}
+
+
+/// The decoded value of a [`ClrResource`].
+///
+/// Floating-point values are stored in their textual representation.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum ClrResourceValue {
+    Null,
+    String(String),
+    Boolean(bool),
+    Char(char),
+    Byte(u8),
+    SignedByte(i8),
+    Int16(i16),
+    UInt16(u16),
+    Int32(i32),
+    UInt32(u32),
+    Int64(i64),
+    UInt64(u64),
+    Single(String),
+    Double(String),
+    Decimal(String),
+
+    /// The result of `DateTime.ToBinary`: the number of 100ns ticks since 0001-01-01 in the lower
+    /// 62 bits and the kind (unspecified, UTC, local) in the upper 2 bits.
+    DateTime(i64),
+
+    /// The number of 100ns ticks.
+    TimeSpan(i64),
+
+    ByteArray(DisplayBytesVec),
+    Stream(DisplayBytesVec),
+
+    /// A value serialized by a custom serializer (generally `BinaryFormatter`).
+    Custom { type_name: String, data: DisplayBytesVec },
+}
+impl ClrResourceValue {
+    /// Returns the embedded file data if this value is a byte array or a stream.
+    pub fn as_file_data(&self) -> Option<&[u8]> {
+        match self {
+            Self::ByteArray(data) | Self::Stream(data) => Some(data.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+
+/// Formats the 128-bit representation of a `System.Decimal` value.
+fn decimal_to_string(bytes: [u8; 16]) -> String {
+    // lo: u32, mid: u32, hi: u32, flags: u32
+    let lo = u32::from_le_byte_slice(&bytes[0..4]);
+    let mid = u32::from_le_byte_slice(&bytes[4..8]);
+    let hi = u32::from_le_byte_slice(&bytes[8..12]);
+    let flags = u32::from_le_byte_slice(&bytes[12..16]);
+    let scale = usize::try_from((flags >> 16) & 0xFF).unwrap();
+    let negative = flags & 0x8000_0000 != 0;
+
+    let magnitude = (u128::from(hi) << 64) | (u128::from(mid) << 32) | u128::from(lo);
+    let mut digits = magnitude.to_string();
+    if scale > 0 {
+        if digits.len() <= scale {
+            digits = format!("{}{}", "0".repeat(scale + 1 - digits.len()), digits);
+        }
+        digits.insert(digits.len() - scale, '.');
+    }
+    if negative {
+        digits.insert(0, '-');
+    }
+    digits
+}
 
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -409,6 +541,16 @@ fn take_length_prefixed_bytes(slice: &[u8]) -> Result<(&[u8], &[u8]), Error> {
 }
 
 
+/// Obtains the data of the wrapped resource at the given offset, as referenced by a
+/// [manifest resource](crate::clr::metadata::ManifestResource).
+pub fn wrapped_resource_at(slice: &[u8], offset: u32) -> Option<&[u8]> {
+    let offset = usize::try_from(offset).unwrap();
+    let length_bytes = slice.get(offset..offset.checked_add(4)?)?;
+    let length = usize::try_from(u32::from_le_bytes(length_bytes.try_into().unwrap())).unwrap();
+    slice.get(offset+4..(offset+4).checked_add(length)?)
+}
+
+
 pub fn collect_wrapped_resource_containers(slice: &[u8]) -> Vec<Vec<u8>> {
     let mut rest = slice;
     let mut containers = Vec::new();
@@ -429,3 +571,36 @@ pub fn collect_wrapped_resource_containers(slice: &[u8]) -> Vec<Vec<u8>> {
     }
     containers
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::decimal_to_string;
+
+    fn decimal(lo: u32, mid: u32, hi: u32, flags: u32) -> [u8; 16] {
+        let mut bytes = [0u8; 16];
+        bytes[0..4].copy_from_slice(&lo.to_le_bytes());
+        bytes[4..8].copy_from_slice(&mid.to_le_bytes());
+        bytes[8..12].copy_from_slice(&hi.to_le_bytes());
+        bytes[12..16].copy_from_slice(&flags.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_decimal_to_string() {
+        assert_eq!(decimal_to_string(decimal(0, 0, 0, 0)), "0");
+        assert_eq!(decimal_to_string(decimal(123, 0, 0, 0)), "123");
+        assert_eq!(decimal_to_string(decimal(15, 0, 0, 0x0001_0000)), "1.5");
+        assert_eq!(decimal_to_string(decimal(5, 0, 0, 0x8003_0000)), "-0.005");
+        assert_eq!(decimal_to_string(decimal(100, 0, 0, 0x0002_0000)), "1.00");
+        assert_eq!(decimal_to_string(decimal(0, 1, 0, 0)), "4294967296");
+        assert_eq!(
+            decimal_to_string(decimal(u32::MAX, u32::MAX, u32::MAX, 0x8000_0000)),
+            "-79228162514264337593543950335",
+        );
+        assert_eq!(
+            decimal_to_string(decimal(u32::MAX, u32::MAX, u32::MAX, 0x001C_0000)),
+            "7.9228162514264337593543950335",
+        );
+    }
+}
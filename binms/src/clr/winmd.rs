@@ -0,0 +1,258 @@
+//! Windows Runtime metadata (`.winmd`) files.
+//!
+//! These use the same format as the metadata of a managed assembly but describe the API surface
+//! of the Windows Runtime. The types are defined in the `TypeDef` table; the interface IDs are
+//! stored as arguments to `Windows.Foundation.Metadata.GuidAttribute` custom attributes.
+
+
+use std::collections::BTreeMap;
+
+use crate::clr::{Error, Guid};
+use crate::clr::metadata::{ClrMetadata, Table, TableRow, TypeAttributes};
+
+
+/// The prefix of the metadata version string of Windows Runtime metadata files.
+pub const WINDOWS_RUNTIME_VERSION_PREFIX: &str = "WindowsRuntime";
+
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum TypeKind {
+    Interface,
+    RuntimeClass,
+    Struct,
+    Enum,
+    Delegate,
+    Attribute,
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct WinmdType {
+    pub namespace: String,
+    pub name: String,
+    pub kind: TypeKind,
+    pub flags: TypeAttributes,
+
+    /// The interface ID; generally only available for interfaces and delegates.
+    pub guid: Option<Guid>,
+
+    /// The names of the methods in the order of their definition.
+    pub methods: Vec<String>,
+}
+impl WinmdType {
+    pub fn full_name(&self) -> String {
+        if self.namespace.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}.{}", self.namespace, self.name)
+        }
+    }
+}
+
+
+/// Returns whether the metadata is Windows Runtime metadata.
+pub fn is_winmd(metadata: &ClrMetadata) -> bool {
+    metadata.version.starts_with(WINDOWS_RUNTIME_VERSION_PREFIX)
+}
+
+
+/// Collects the types defined in the metadata along with their methods.
+pub fn collect_types(metadata: &ClrMetadata) -> Result<Vec<WinmdType>, Error> {
+    let type_defs = metadata.type_defs()?;
+    let type_refs = metadata.type_refs()?;
+    let method_defs = metadata.method_defs()?;
+    let member_refs = metadata.member_refs()?;
+    let custom_attributes = metadata.custom_attributes()?;
+
+    let type_name = |row: TableRow| -> Option<(&str, &str)> {
+        let index = usize::try_from(row.row).unwrap().checked_sub(1)?;
+        match row.table {
+            Table::TypeDef => type_defs.get(index).map(|td| (td.namespace.as_str(), td.name.as_str())),
+            Table::TypeRef => type_refs.get(index).map(|tr| (tr.namespace.as_str(), tr.name.as_str())),
+            _ => None,
+        }
+    };
+
+    // find the GUIDs
+    let mut type_def_row_to_guid = BTreeMap::new();
+    for attribute in &custom_attributes {
+        let Some(TableRow { table: Table::TypeDef, row: parent_row }) = attribute.parent else { continue };
+        let attribute_type = match attribute.constructor {
+            Some(TableRow { table: Table::MemberRef, row }) => usize::try_from(row).unwrap()
+                .checked_sub(1)
+                .and_then(|i| member_refs.get(i))
+                .and_then(|mr| mr.class)
+                .and_then(type_name),
+            _ => None,
+        };
+        if attribute_type.map(|(_ns, name)| name) != Some("GuidAttribute") {
+            continue;
+        }
+
+        // prolog (0x0001), u32, u16, u16, [u8; 8]
+        let value = &attribute.value;
+        if value.len() < 18 || value[0..2] != [0x01, 0x00] {
+            continue;
+        }
+        let guid = Guid::from_le_bytes(value[2..18].try_into().unwrap());
+        type_def_row_to_guid.insert(parent_row, guid);
+    }
+
+    let mut types = Vec::with_capacity(type_defs.len());
+    for (i, type_def) in type_defs.iter().enumerate() {
+        if type_def.name == "<Module>" {
+            continue;
+        }
+
+        let kind = if type_def.flags.contains(TypeAttributes::INTERFACE) {
+            TypeKind::Interface
+        } else {
+            match type_def.extends.and_then(type_name) {
+                Some(("System", "Enum")) => TypeKind::Enum,
+                Some(("System", "ValueType")) => TypeKind::Struct,
+                Some(("System", "MulticastDelegate")) => TypeKind::Delegate,
+                Some(("System", "Attribute")) => TypeKind::Attribute,
+                _ => TypeKind::RuntimeClass,
+            }
+        };
+
+        // the methods extend until the first method of the next type
+        let method_start = usize::try_from(type_def.method_list).unwrap().max(1);
+        let method_end = type_defs.get(i + 1)
+            .map(|next| usize::try_from(next.method_list).unwrap())
+            .unwrap_or(method_defs.len() + 1)
+            .min(method_defs.len() + 1);
+        let methods = if method_start < method_end {
+            method_defs[method_start-1..method_end-1].iter()
+                .map(|md| md.name.clone())
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let row = u32::try_from(i + 1).unwrap();
+        types.push(WinmdType {
+            namespace: type_def.namespace.clone(),
+            name: type_def.name.clone(),
+            kind,
+            flags: type_def.flags,
+            guid: type_def_row_to_guid.get(&row).copied(),
+            methods,
+        });
+    }
+    Ok(types)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use testgen::clr::{Cell, Metadata};
+
+    use crate::clr::Guid;
+    use crate::clr::metadata::{ClrMetadata, TypeAttributes};
+    use super::{collect_types, is_winmd, TypeKind};
+
+
+    const TYPE_REF: u8 = 0x01;
+    const TYPE_DEF: u8 = 0x02;
+    const METHOD_DEF: u8 = 0x06;
+    const MEMBER_REF: u8 = 0x0A;
+    const CUSTOM_ATTRIBUTE: u8 = 0x0C;
+
+    /// The GUID of `Windows.Foundation.IUriRuntimeClass` in its in-memory layout.
+    const IURI_GUID: [u8; 16] = [
+        0x57, 0x5E, 0x36, 0x9E, 0xB2, 0x48, 0x60, 0x41, 0x95, 0x6F, 0xC7, 0x38, 0x51, 0x20, 0xBB, 0xFC,
+    ];
+
+    fn synthesize() -> Vec<u8> {
+        let mut metadata = Metadata::new("WindowsRuntime 1.4");
+
+        let type_ref = |metadata: &mut Metadata, namespace: &str, name: &str| {
+            let row = [Cell::U16(0), Cell::U16(metadata.string(name)), Cell::U16(metadata.string(namespace))];
+            metadata.push_row(TYPE_REF, &row)
+        };
+        let object = type_ref(&mut metadata, "System", "Object");
+        let guid_attribute = type_ref(&mut metadata, "Windows.Foundation.Metadata", "GuidAttribute");
+        let enumeration = type_ref(&mut metadata, "System", "Enum");
+        let delegate = type_ref(&mut metadata, "System", "MulticastDelegate");
+
+        // TypeDefOrRef: two tag bits, TypeRef is 1
+        let extends = |row: u16| (row << 2) | 1;
+        let type_def = |metadata: &mut Metadata, flags: TypeAttributes, namespace: &str, name: &str, extends: u16, method_list: u16| {
+            let row = [
+                Cell::U32(flags.bits()), Cell::U16(metadata.string(name)), Cell::U16(metadata.string(namespace)),
+                Cell::U16(extends), Cell::U16(1), Cell::U16(method_list),
+            ];
+            metadata.push_row(TYPE_DEF, &row)
+        };
+        let winrt = TypeAttributes::PUBLIC | TypeAttributes::WINDOWS_RUNTIME;
+        type_def(&mut metadata, TypeAttributes::empty(), "", "<Module>", 0, 1);
+        let interface = type_def(&mut metadata, winrt | TypeAttributes::INTERFACE | TypeAttributes::ABSTRACT, "Windows.Foundation", "IUriRuntimeClass", 0, 1);
+        type_def(&mut metadata, winrt | TypeAttributes::SEALED, "Windows.Foundation", "Uri", extends(object), 3);
+        type_def(&mut metadata, winrt | TypeAttributes::SEALED, "Windows.Foundation", "AsyncStatus", extends(enumeration), 4);
+        type_def(&mut metadata, winrt | TypeAttributes::SEALED, "Windows.Foundation", "AsyncActionCompletedHandler", extends(delegate), 4);
+
+        let signature = metadata.blob(&[0x20, 0x00, 0x01]);
+        for name in ["get_Host", "get_Port", "CreateUri", "Invoke"] {
+            let row = [
+                Cell::U32(0), Cell::U16(0), Cell::U16(0x0086), Cell::U16(metadata.string(name)),
+                Cell::U16(signature), Cell::U16(1),
+            ];
+            metadata.push_row(METHOD_DEF, &row);
+        }
+
+        // MemberRefParent: three tag bits, TypeRef is 1
+        let constructor_row = [Cell::U16((guid_attribute << 3) | 1), Cell::U16(metadata.string(".ctor")), Cell::U16(signature)];
+        let constructor = metadata.push_row(MEMBER_REF, &constructor_row);
+
+        // HasCustomAttribute: five tag bits, TypeDef is 3; CustomAttributeType: three tag bits,
+        // MemberRef is 3
+        let mut value = vec![0x01, 0x00];
+        value.extend_from_slice(&IURI_GUID);
+        value.extend_from_slice(&[0x00, 0x00]);
+        let attribute_row = [Cell::U16((interface << 5) | 3), Cell::U16((constructor << 3) | 3), Cell::U16(metadata.blob(&value))];
+        metadata.push_row(CUSTOM_ATTRIBUTE, &attribute_row);
+
+        metadata.to_bytes()
+    }
+
+    #[test]
+    fn test_collect_types() {
+        let bytes = synthesize();
+        let (_rest, metadata) = ClrMetadata::take_from_bytes(&bytes).unwrap();
+        assert!(is_winmd(&metadata));
+
+        let types = collect_types(&metadata).unwrap();
+        let summary: Vec<(String, TypeKind, Vec<&str>)> = types.iter()
+            .map(|t| (t.full_name(), t.kind, t.methods.iter().map(|m| m.as_str()).collect()))
+            .collect();
+        assert_eq!(summary, vec![
+            ("Windows.Foundation.IUriRuntimeClass".to_owned(), TypeKind::Interface, vec!["get_Host", "get_Port"]),
+            ("Windows.Foundation.Uri".to_owned(), TypeKind::RuntimeClass, vec!["CreateUri"]),
+            ("Windows.Foundation.AsyncStatus".to_owned(), TypeKind::Enum, vec![]),
+            ("Windows.Foundation.AsyncActionCompletedHandler".to_owned(), TypeKind::Delegate, vec!["Invoke"]),
+        ]);
+
+        assert_eq!(types[0].guid, Some(Guid::from_le_bytes(IURI_GUID)));
+        assert_eq!(types[0].guid.unwrap().to_string(), "{9E365E57-48B2-4160-956F-C7385120BBFC}");
+        assert!(types[0].flags.contains(TypeAttributes::WINDOWS_RUNTIME));
+        assert_eq!(types[1].guid, None);
+    }
+
+    #[test]
+    fn test_truncated_tables() {
+        let bytes = synthesize();
+        let (_rest, metadata) = ClrMetadata::take_from_bytes(&bytes).unwrap();
+        assert!(metadata.type_defs().is_ok());
+
+        // cut the tables stream short by claiming more methods than there are; the row counts
+        // follow the 24-byte header of the tables stream in the order TypeRef, TypeDef, MethodDef
+        let tables_offset = usize::try_from(metadata.streams.iter().find(|s| s.name == "#~").unwrap().offset).unwrap();
+        let method_count_offset = tables_offset + 24 + 2*4;
+        let mut metadata_bytes = bytes.clone();
+        assert_eq!(metadata_bytes[method_count_offset..method_count_offset+4], 4u32.to_le_bytes());
+        metadata_bytes[method_count_offset..method_count_offset+4].copy_from_slice(&0x1000u32.to_le_bytes());
+        let (_rest, metadata) = ClrMetadata::take_from_bytes(&metadata_bytes).unwrap();
+        assert!(collect_types(&metadata).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 //! Numeric and type leaves in the CodeView debugging format.
 
 
-use std::io::{self, Read, Seek, SeekFrom};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 
 use bitflags::bitflags;
 use display_bytes::DisplayBytesVec;
@@ -15,7 +15,7 @@ use crate::bit_pattern_float::{
 };
 use crate::code_view::SymbolEntry;
 use crate::int_from_byte_slice::IntFromByteSlice;
-use crate::read_pascal_byte_string;
+use crate::{read_pascal_byte_string, write_pascal_byte_string};
 
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -151,6 +151,53 @@ impl NumericLeaf {
             },
         }
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        let (index, value_bytes): (u16, Vec<u8>) = match self {
+            Self::Immediate(value) => {
+                if *value >= 0x8000 {
+                    error!("immediate numeric leaf value {:#06X} collides with the numeric leaf types", value);
+                    return Err(io::ErrorKind::InvalidData.into());
+                }
+                (*value, Vec::new())
+            },
+            Self::SignedChar(value) => (0x8000, value.to_le_bytes().to_vec()),
+            Self::SignedShort(value) => (0x8001, value.to_le_bytes().to_vec()),
+            Self::UnsignedShort(value) => (0x8002, value.to_le_bytes().to_vec()),
+            Self::SignedLong(value) => (0x8003, value.to_le_bytes().to_vec()),
+            Self::UnsignedLong(value) => (0x8004, value.to_le_bytes().to_vec()),
+            Self::Float32(value) => (0x8005, f32::from(*value).to_le_bytes().to_vec()),
+            Self::Float64(value) => (0x8006, f64::from(*value).to_le_bytes().to_vec()),
+            Self::Float80(value) => (0x8007, value.to_vec()),
+            Self::Float128(value) => (0x8008, value.to_vec()),
+            Self::SignedQuadWord(value) => (0x8009, value.to_le_bytes().to_vec()),
+            Self::UnsignedQuadWord(value) => (0x800A, value.to_le_bytes().to_vec()),
+            Self::Float48(value) => (0x800B, value.to_vec()),
+            Self::Complex32(value) => {
+                let mut bytes = f32::from(value.real).to_le_bytes().to_vec();
+                bytes.extend_from_slice(&f32::from(value.imag).to_le_bytes());
+                (0x800C, bytes)
+            },
+            Self::Complex64(value) => {
+                let mut bytes = f64::from(value.real).to_le_bytes().to_vec();
+                bytes.extend_from_slice(&f64::from(value.imag).to_le_bytes());
+                (0x800D, bytes)
+            },
+            Self::Complex80(value) => (0x800E, [value.real, value.imag].concat()),
+            Self::Complex128(value) => (0x800F, [value.real, value.imag].concat()),
+            Self::String(value) => {
+                let string_bytes: &[u8] = value.as_ref();
+                let length = u16::try_from(string_bytes.len())
+                    .inspect_err(|_| error!("numeric leaf string is {} bytes long, more than 65535", string_bytes.len()))
+                    .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+                let mut bytes = length.to_le_bytes().to_vec();
+                bytes.extend_from_slice(string_bytes);
+                (0x8010, bytes)
+            },
+        };
+        writer.write_all(&index.to_le_bytes())?;
+        writer.write_all(&value_bytes)
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -260,13 +307,12 @@ pub enum TypeLeaf {
     Procedure(ProcedureTypeLeaf),
     MemberFunction(MemberFunctionTypeLeaf),
     VirtualFunctionTableShape(VirtualFunctionTableShapeTypeLeaf),
-    /*
     Cobol0(Cobol0TypeLeaf),
     Cobol1(Cobol1TypeLeaf),
     BasicArray(BasicArrayTypeLeaf),
     Label(LabelTypeLeaf),
-    Null(NullTypeLeaf),
-    NotTranslated(NotTranslatedTypeLeaf),
+    Null,
+    NotTranslated,
     MultiDimensionalArray(MultiDimensionalArrayTypeLeaf),
     VirtualFunctionTablePath(VirtualFunctionTablePathTypeLeaf),
     PrecompiledTypeReference(PrecompiledTypeReferenceTypeLeaf),
@@ -274,46 +320,33 @@ pub enum TypeLeaf {
     OemGenericType(OemGenericTypeTypeLeaf),
 
     Skip(SkipTypeLeaf),
-    */
     ArgumentList(ArgumentListTypeLeaf),
-    /*
     DefaultArgument(DefaultArgumentTypeLeaf),
     List(ListTypeLeaf),
-    */
     FieldList(FieldListTypeLeaf),
     DerivedClasses(DerivedClassesTypeLeaf),
     BitFields(BitFieldsTypeLeaf),
     MethodList(MethodListTypeLeaf),
-    /*
-    DimensionedArrayDefaultLowerConstantUpper(DimensionedArrayDefaultLowerConstantUpperTypeLeaf),
-    DimensionedArrayConstantLowerConstantUpper(DimensionedArrayConstantLowerConstantUpperTypeLeaf),
-    DimensionedArrayDefaultLowerVariableUpper(DimensionedArrayDefaultLowerVariableUpperTypeLeaf),
-    DimensionedArrayVariableLowerVariableUpper(DimensionedArrayVariableLowerVariableUpperTypeLeaf),
+    DimensionedArrayDefaultLowerConstantUpper(DimensionedArrayConstantBoundsTypeLeaf),
+    DimensionedArrayConstantLowerConstantUpper(DimensionedArrayConstantBoundsTypeLeaf),
+    DimensionedArrayDefaultLowerVariableUpper(DimensionedArrayVariableBoundsTypeLeaf),
+    DimensionedArrayVariableLowerVariableUpper(DimensionedArrayVariableBoundsTypeLeaf),
     ReferencedSymbol(ReferencedSymbolTypeLeaf),
-    */
 
     RealBaseClass(RealBaseClassTypeLeaf),
-    /*
-    DirectVirtualBaseClass(DirectVirtualBaseClassTypeLeaf),
-    IndirectVirtualBaseClass(IndirectVirtualBaseClassTypeLeaf),
-    */
+    DirectVirtualBaseClass(VirtualBaseClassTypeLeaf),
+    IndirectVirtualBaseClass(VirtualBaseClassTypeLeaf),
     EnumerationNameAndValue(EnumerationNameAndValueTypeLeaf),
-    /*
     FriendFunction(FriendFunctionTypeLeaf),
     IndexToAnotherTypeRecord(IndexToAnotherTypeRecordTypeLeaf),
-    */
     DataMember(DataMemberTypeLeaf),
     StaticDataMember(StaticDataMemberTypeLeaf),
     Method(MethodTypeLeaf),
     NestedTypeDefinition(NestedTypeDefinitionTypeLeaf),
     VirtualFunctionTablePointer(VirtualFunctionTablePointerTypeLeaf),
-    /*
     FriendClass(FriendClassTypeLeaf),
-    */
     OneMethod(OneMethodTypeLeaf),
-    /*
     VirtualFunctionOffset(VirtualFunctionOffsetTypeLeaf),
-    */
 
     Other { index: u16, data: DisplayBytesVec },
 }
@@ -368,10 +401,60 @@ impl TypeLeaf {
                 let content = VirtualFunctionTableShapeTypeLeaf::read(reader)?;
                 Ok(Self::VirtualFunctionTableShape(content))
             },
+            TypeLeafIndex::Cobol0 => {
+                let content = Cobol0TypeLeaf::read(reader)?;
+                Ok(Self::Cobol0(content))
+            },
+            TypeLeafIndex::Cobol1 => {
+                let content = Cobol1TypeLeaf::read(reader)?;
+                Ok(Self::Cobol1(content))
+            },
+            TypeLeafIndex::BasicArray => {
+                let content = BasicArrayTypeLeaf::read(reader)?;
+                Ok(Self::BasicArray(content))
+            },
+            TypeLeafIndex::Label => {
+                let content = LabelTypeLeaf::read(reader)?;
+                Ok(Self::Label(content))
+            },
+            TypeLeafIndex::Null => Ok(Self::Null),
+            TypeLeafIndex::NotTranslated => Ok(Self::NotTranslated),
+            TypeLeafIndex::MultiDimensionalArray => {
+                let content = MultiDimensionalArrayTypeLeaf::read(reader)?;
+                Ok(Self::MultiDimensionalArray(content))
+            },
+            TypeLeafIndex::VirtualFunctionTablePath => {
+                let content = VirtualFunctionTablePathTypeLeaf::read(reader)?;
+                Ok(Self::VirtualFunctionTablePath(content))
+            },
+            TypeLeafIndex::PrecompiledTypeReference => {
+                let content = PrecompiledTypeReferenceTypeLeaf::read(reader)?;
+                Ok(Self::PrecompiledTypeReference(content))
+            },
+            TypeLeafIndex::PrecompiledTypesEnd => {
+                let content = PrecompiledTypesEndTypeLeaf::read(reader)?;
+                Ok(Self::PrecompiledTypesEnd(content))
+            },
+            TypeLeafIndex::OemGenericType => {
+                let content = OemGenericTypeTypeLeaf::read(reader)?;
+                Ok(Self::OemGenericType(content))
+            },
+            TypeLeafIndex::Skip => {
+                let content = SkipTypeLeaf::read(reader)?;
+                Ok(Self::Skip(content))
+            },
             TypeLeafIndex::ArgumentList => {
                 let content = ArgumentListTypeLeaf::read(reader)?;
                 Ok(Self::ArgumentList(content))
             },
+            TypeLeafIndex::DefaultArgument => {
+                let content = DefaultArgumentTypeLeaf::read(reader)?;
+                Ok(Self::DefaultArgument(content))
+            },
+            TypeLeafIndex::List => {
+                let content = ListTypeLeaf::read(reader)?;
+                Ok(Self::List(content))
+            },
             TypeLeafIndex::FieldList => {
                 let content = FieldListTypeLeaf::read(reader)?;
                 Ok(Self::FieldList(content))
@@ -388,14 +471,50 @@ impl TypeLeaf {
                 let content = MethodListTypeLeaf::read(reader)?;
                 Ok(Self::MethodList(content))
             },
+            TypeLeafIndex::DimensionedArrayDefaultLowerConstantUpper => {
+                let content = DimensionedArrayConstantBoundsTypeLeaf::read(reader, false)?;
+                Ok(Self::DimensionedArrayDefaultLowerConstantUpper(content))
+            },
+            TypeLeafIndex::DimensionedArrayConstantLowerConstantUpper => {
+                let content = DimensionedArrayConstantBoundsTypeLeaf::read(reader, true)?;
+                Ok(Self::DimensionedArrayConstantLowerConstantUpper(content))
+            },
+            TypeLeafIndex::DimensionedArrayDefaultLowerVariableUpper => {
+                let content = DimensionedArrayVariableBoundsTypeLeaf::read(reader, false)?;
+                Ok(Self::DimensionedArrayDefaultLowerVariableUpper(content))
+            },
+            TypeLeafIndex::DimensionedArrayVariableLowerVariableUpper => {
+                let content = DimensionedArrayVariableBoundsTypeLeaf::read(reader, true)?;
+                Ok(Self::DimensionedArrayVariableLowerVariableUpper(content))
+            },
+            TypeLeafIndex::ReferencedSymbol => {
+                let content = ReferencedSymbolTypeLeaf::read(reader)?;
+                Ok(Self::ReferencedSymbol(content))
+            },
             TypeLeafIndex::RealBaseClass => {
                 let content = RealBaseClassTypeLeaf::read(reader)?;
                 Ok(Self::RealBaseClass(content))
             },
+            TypeLeafIndex::DirectVirtualBaseClass => {
+                let content = VirtualBaseClassTypeLeaf::read(reader)?;
+                Ok(Self::DirectVirtualBaseClass(content))
+            },
+            TypeLeafIndex::IndirectVirtualBaseClass => {
+                let content = VirtualBaseClassTypeLeaf::read(reader)?;
+                Ok(Self::IndirectVirtualBaseClass(content))
+            },
             TypeLeafIndex::EnumerationNameAndValue => {
                 let content = EnumerationNameAndValueTypeLeaf::read(reader)?;
                 Ok(Self::EnumerationNameAndValue(content))
             },
+            TypeLeafIndex::FriendFunction => {
+                let content = FriendFunctionTypeLeaf::read(reader)?;
+                Ok(Self::FriendFunction(content))
+            },
+            TypeLeafIndex::IndexToAnotherTypeRecord => {
+                let content = IndexToAnotherTypeRecordTypeLeaf::read(reader)?;
+                Ok(Self::IndexToAnotherTypeRecord(content))
+            },
             TypeLeafIndex::DataMember => {
                 let content = DataMemberTypeLeaf::read(reader)?;
                 Ok(Self::DataMember(content))
@@ -416,10 +535,18 @@ impl TypeLeaf {
                 let content = VirtualFunctionTablePointerTypeLeaf::read(reader)?;
                 Ok(Self::VirtualFunctionTablePointer(content))
             },
+            TypeLeafIndex::FriendClass => {
+                let content = FriendClassTypeLeaf::read(reader)?;
+                Ok(Self::FriendClass(content))
+            },
             TypeLeafIndex::OneMethod => {
                 let content = OneMethodTypeLeaf::read(reader)?;
                 Ok(Self::OneMethod(content))
             },
+            TypeLeafIndex::VirtualFunctionOffset => {
+                let content = VirtualFunctionOffsetTypeLeaf::read(reader)?;
+                Ok(Self::VirtualFunctionOffset(content))
+            },
             other => {
                 let other_u16 = other.to_base_type();
                 let mut data_vec = Vec::new();
@@ -429,6 +556,117 @@ impl TypeLeaf {
             },
         }
     }
+
+    pub fn index(&self) -> TypeLeafIndex {
+        match self {
+            Self::Modifier(_) => TypeLeafIndex::Modifier,
+            Self::Pointer(_) => TypeLeafIndex::Pointer,
+            Self::Array(_) => TypeLeafIndex::Array,
+            Self::Class(_) => TypeLeafIndex::Class,
+            Self::Structure(_) => TypeLeafIndex::Structure,
+            Self::Union(_) => TypeLeafIndex::Union,
+            Self::Enum(_) => TypeLeafIndex::Enum,
+            Self::Procedure(_) => TypeLeafIndex::Procedure,
+            Self::MemberFunction(_) => TypeLeafIndex::MemberFunction,
+            Self::VirtualFunctionTableShape(_) => TypeLeafIndex::VirtualFunctionTableShape,
+            Self::Cobol0(_) => TypeLeafIndex::Cobol0,
+            Self::Cobol1(_) => TypeLeafIndex::Cobol1,
+            Self::BasicArray(_) => TypeLeafIndex::BasicArray,
+            Self::Label(_) => TypeLeafIndex::Label,
+            Self::Null => TypeLeafIndex::Null,
+            Self::NotTranslated => TypeLeafIndex::NotTranslated,
+            Self::MultiDimensionalArray(_) => TypeLeafIndex::MultiDimensionalArray,
+            Self::VirtualFunctionTablePath(_) => TypeLeafIndex::VirtualFunctionTablePath,
+            Self::PrecompiledTypeReference(_) => TypeLeafIndex::PrecompiledTypeReference,
+            Self::PrecompiledTypesEnd(_) => TypeLeafIndex::PrecompiledTypesEnd,
+            Self::OemGenericType(_) => TypeLeafIndex::OemGenericType,
+            Self::Skip(_) => TypeLeafIndex::Skip,
+            Self::ArgumentList(_) => TypeLeafIndex::ArgumentList,
+            Self::DefaultArgument(_) => TypeLeafIndex::DefaultArgument,
+            Self::List(_) => TypeLeafIndex::List,
+            Self::FieldList(_) => TypeLeafIndex::FieldList,
+            Self::DerivedClasses(_) => TypeLeafIndex::DerivedClasses,
+            Self::BitFields(_) => TypeLeafIndex::BitFields,
+            Self::MethodList(_) => TypeLeafIndex::MethodList,
+            Self::DimensionedArrayDefaultLowerConstantUpper(_) => TypeLeafIndex::DimensionedArrayDefaultLowerConstantUpper,
+            Self::DimensionedArrayConstantLowerConstantUpper(_) => TypeLeafIndex::DimensionedArrayConstantLowerConstantUpper,
+            Self::DimensionedArrayDefaultLowerVariableUpper(_) => TypeLeafIndex::DimensionedArrayDefaultLowerVariableUpper,
+            Self::DimensionedArrayVariableLowerVariableUpper(_) => TypeLeafIndex::DimensionedArrayVariableLowerVariableUpper,
+            Self::ReferencedSymbol(_) => TypeLeafIndex::ReferencedSymbol,
+            Self::RealBaseClass(_) => TypeLeafIndex::RealBaseClass,
+            Self::DirectVirtualBaseClass(_) => TypeLeafIndex::DirectVirtualBaseClass,
+            Self::IndirectVirtualBaseClass(_) => TypeLeafIndex::IndirectVirtualBaseClass,
+            Self::EnumerationNameAndValue(_) => TypeLeafIndex::EnumerationNameAndValue,
+            Self::FriendFunction(_) => TypeLeafIndex::FriendFunction,
+            Self::IndexToAnotherTypeRecord(_) => TypeLeafIndex::IndexToAnotherTypeRecord,
+            Self::DataMember(_) => TypeLeafIndex::DataMember,
+            Self::StaticDataMember(_) => TypeLeafIndex::StaticDataMember,
+            Self::Method(_) => TypeLeafIndex::Method,
+            Self::NestedTypeDefinition(_) => TypeLeafIndex::NestedTypeDefinition,
+            Self::VirtualFunctionTablePointer(_) => TypeLeafIndex::VirtualFunctionTablePointer,
+            Self::FriendClass(_) => TypeLeafIndex::FriendClass,
+            Self::OneMethod(_) => TypeLeafIndex::OneMethod,
+            Self::VirtualFunctionOffset(_) => TypeLeafIndex::VirtualFunctionOffset,
+            Self::Other { index, .. } => TypeLeafIndex::from_base_type(*index),
+        }
+    }
+
+    /// Writes the leaf, starting with its index.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.index().to_base_type().to_le_bytes())?;
+
+        match self {
+            Self::Modifier(content) => content.write(writer),
+            Self::Pointer(content) => content.write(writer),
+            Self::Array(content) => content.write(writer),
+            Self::Class(content) => content.write(writer),
+            Self::Structure(content) => content.write(writer),
+            Self::Union(content) => content.write(writer),
+            Self::Enum(content) => content.write(writer),
+            Self::Procedure(content) => content.write(writer),
+            Self::MemberFunction(content) => content.write(writer),
+            Self::VirtualFunctionTableShape(content) => content.write(writer),
+            Self::Cobol0(content) => content.write(writer),
+            Self::Cobol1(content) => content.write(writer),
+            Self::BasicArray(content) => content.write(writer),
+            Self::Label(content) => content.write(writer),
+            Self::Null => Ok(()),
+            Self::NotTranslated => Ok(()),
+            Self::MultiDimensionalArray(content) => content.write(writer),
+            Self::VirtualFunctionTablePath(content) => content.write(writer),
+            Self::PrecompiledTypeReference(content) => content.write(writer),
+            Self::PrecompiledTypesEnd(content) => content.write(writer),
+            Self::OemGenericType(content) => content.write(writer),
+            Self::Skip(content) => content.write(writer),
+            Self::ArgumentList(content) => content.write(writer),
+            Self::DefaultArgument(content) => content.write(writer),
+            Self::List(content) => content.write(writer),
+            Self::FieldList(content) => content.write(writer),
+            Self::DerivedClasses(content) => content.write(writer),
+            Self::BitFields(content) => content.write(writer),
+            Self::MethodList(content) => content.write(writer),
+            Self::DimensionedArrayDefaultLowerConstantUpper(content) => content.write(writer),
+            Self::DimensionedArrayConstantLowerConstantUpper(content) => content.write(writer),
+            Self::DimensionedArrayDefaultLowerVariableUpper(content) => content.write(writer),
+            Self::DimensionedArrayVariableLowerVariableUpper(content) => content.write(writer),
+            Self::ReferencedSymbol(content) => content.write(writer),
+            Self::RealBaseClass(content) => content.write(writer),
+            Self::DirectVirtualBaseClass(content) => content.write(writer),
+            Self::IndirectVirtualBaseClass(content) => content.write(writer),
+            Self::EnumerationNameAndValue(content) => content.write(writer),
+            Self::FriendFunction(content) => content.write(writer),
+            Self::IndexToAnotherTypeRecord(content) => content.write(writer),
+            Self::DataMember(content) => content.write(writer),
+            Self::StaticDataMember(content) => content.write(writer),
+            Self::Method(content) => content.write(writer),
+            Self::NestedTypeDefinition(content) => content.write(writer),
+            Self::VirtualFunctionTablePointer(content) => content.write(writer),
+            Self::FriendClass(content) => content.write(writer),
+            Self::OneMethod(content) => content.write(writer),
+            Self::VirtualFunctionOffset(content) => content.write(writer),
+            Self::Other { data, .. } => writer.write_all(data.as_ref()),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -464,6 +702,15 @@ impl MemberAttributes {
             reserved,
         }
     }
+
+    pub fn to_u16(&self) -> u16 {
+        u16::from(self.access.into_repr())
+            | (u16::from(self.method_property.into_repr()) << 2)
+            | (if self.pseudo { 1 << 5 } else { 0 })
+            | (if self.no_inherit { 1 << 6 } else { 0 })
+            | (if self.no_construct { 1 << 7 } else { 0 })
+            | (u16::from(self.reserved) << 8)
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, FromToRepr, Hash, Ord, PartialEq, PartialOrd)]
@@ -521,7 +768,7 @@ impl ModifierTypeLeaf {
         reader.read_exact(&mut header_buf)?;
 
         let attributes_u16 = u16::from_le_byte_slice(&header_buf[0..2]);
-        let base_type_index = u16::from_le_byte_slice(&header_buf[0..2]);
+        let base_type_index = u16::from_le_byte_slice(&header_buf[2..4]);
 
         let attributes = ModifierTypeAttributes::from_bits_retain(attributes_u16);
 
@@ -530,6 +777,11 @@ impl ModifierTypeLeaf {
             base_type_index,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.attributes.bits().to_le_bytes())?;
+        writer.write_all(&self.base_type_index.to_le_bytes())
+    }
 }
 
 bitflags! {
@@ -608,6 +860,25 @@ impl PointerTypeLeaf {
             variant,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.attribute.to_u16().to_le_bytes())?;
+        writer.write_all(&self.pointee_type_index.to_le_bytes())?;
+
+        match &self.variant {
+            PointerTypeVariant::Empty => Ok(()),
+            PointerTypeVariant::DataMember { class_type_index, format } => {
+                writer.write_all(&class_type_index.to_le_bytes())?;
+                writer.write_all(&format.to_base_type().to_le_bytes())
+            },
+            PointerTypeVariant::BaseSegment(segment) => writer.write_all(&segment.to_le_bytes()),
+            PointerTypeVariant::BaseSymbol(symbol) => symbol.write(writer),
+            PointerTypeVariant::BaseType { base_type_index, base_type_name } => {
+                writer.write_all(&base_type_index.to_le_bytes())?;
+                write_pascal_byte_string(writer, base_type_name.as_ref())
+            },
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -646,6 +917,16 @@ impl PointerTypeAttributes {
             unused,
         }
     }
+
+    pub fn to_u16(&self) -> u16 {
+        u16::from(self.kind.to_base_type() & 0b11111)
+            | (u16::from(self.mode.to_base_type() & 0b111) << 5)
+            | (if self.is_flat_32 { 1 << 8 } else { 0 })
+            | (if self.is_volatile { 1 << 9 } else { 0 })
+            | (if self.is_const { 1 << 10 } else { 0 })
+            | (if self.is_unaligned { 1 << 11 } else { 0 })
+            | (u16::from(self.unused & 0b1111) << 12)
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -742,6 +1023,13 @@ impl ArrayTypeLeaf {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.element_type_index.to_le_bytes())?;
+        writer.write_all(&self.index_type_index.to_le_bytes())?;
+        self.length.write(writer)?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -783,6 +1071,16 @@ impl StructureTypeLeaf {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.element_count.to_le_bytes())?;
+        writer.write_all(&self.field_list_type_index.to_le_bytes())?;
+        writer.write_all(&self.properties.bits().to_le_bytes())?;
+        writer.write_all(&self.derivation_list_type_index.to_le_bytes())?;
+        writer.write_all(&self.virtual_function_table_shape_descriptor_type_index.to_le_bytes())?;
+        self.length.write(writer)?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 bitflags! {
@@ -834,6 +1132,14 @@ impl UnionTypeLeaf {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.field_count.to_le_bytes())?;
+        writer.write_all(&self.field_list_type_index.to_le_bytes())?;
+        writer.write_all(&self.properties.bits().to_le_bytes())?;
+        self.length.write(writer)?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -869,6 +1175,14 @@ impl EnumTypeLeaf {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.option_count.to_le_bytes())?;
+        writer.write_all(&self.underlying_type_index.to_le_bytes())?;
+        writer.write_all(&self.field_list_type_index.to_le_bytes())?;
+        writer.write_all(&self.member_attributes.to_u16().to_le_bytes())?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -901,6 +1215,13 @@ impl ProcedureTypeLeaf {
             argument_list_type_index,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.return_value_type_index.to_le_bytes())?;
+        writer.write_all(&[self.calling_convention.to_base_type(), self.reserved])?;
+        writer.write_all(&self.parameter_count.to_le_bytes())?;
+        writer.write_all(&self.argument_list_type_index.to_le_bytes())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -942,6 +1263,16 @@ impl MemberFunctionTypeLeaf {
             this_adjuster,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.return_value_type_index.to_le_bytes())?;
+        writer.write_all(&self.class_type_index.to_le_bytes())?;
+        writer.write_all(&self.this_arg_type_index.to_le_bytes())?;
+        writer.write_all(&[self.calling_convention.to_base_type(), self.reserved])?;
+        writer.write_all(&self.parameter_count.to_le_bytes())?;
+        writer.write_all(&self.argument_list_type_index.to_le_bytes())?;
+        writer.write_all(&self.this_adjuster.to_le_bytes())
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -1001,6 +1332,21 @@ impl VirtualFunctionTableShapeTypeLeaf {
             descriptors,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.descriptor_count.to_le_bytes())?;
+
+        // two descriptors per byte, first one in the upper nibble
+        let descriptor_bytes: Vec<u8> = self.descriptors
+            .chunks(2)
+            .map(|pair| {
+                let first = pair[0].to_base_type() & 0b1111;
+                let second = pair.get(1).map(|d| d.to_base_type() & 0b1111).unwrap_or(0);
+                (first << 4) | second
+            })
+            .collect();
+        writer.write_all(&descriptor_bytes)
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -1017,6 +1363,302 @@ pub enum VirtualFunctionTableShapeDescriptor {
     Other(u8),
 }
 
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Cobol0TypeLeaf {
+    pub parent_type_index: u16,
+    pub data: DisplayBytesVec, // remainder of leaf
+}
+impl Cobol0TypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut header_buf = [0u8; 2];
+        reader.read_exact(&mut header_buf)?;
+        let parent_type_index = u16::from_le_bytes(header_buf);
+
+        let mut data_vec = Vec::new();
+        reader.read_to_end(&mut data_vec)?;
+        let data = DisplayBytesVec::from(data_vec);
+
+        Ok(Self {
+            parent_type_index,
+            data,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.parent_type_index.to_le_bytes())?;
+        writer.write_all(self.data.as_ref())
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Cobol1TypeLeaf {
+    pub data: DisplayBytesVec, // remainder of leaf
+}
+impl Cobol1TypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut data_vec = Vec::new();
+        reader.read_to_end(&mut data_vec)?;
+        let data = DisplayBytesVec::from(data_vec);
+
+        Ok(Self {
+            data,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(self.data.as_ref())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct BasicArrayTypeLeaf {
+    pub element_type_index: u16,
+}
+impl BasicArrayTypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut buf = [0u8; 2];
+        reader.read_exact(&mut buf)?;
+        let element_type_index = u16::from_le_bytes(buf);
+
+        Ok(Self {
+            element_type_index,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.element_type_index.to_le_bytes())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct LabelTypeLeaf {
+    pub addressing_mode: LabelAddressingMode, // u16
+}
+impl LabelTypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut buf = [0u8; 2];
+        reader.read_exact(&mut buf)?;
+        let addressing_mode_u16 = u16::from_le_bytes(buf);
+
+        let addressing_mode = LabelAddressingMode::from_base_type(addressing_mode_u16);
+
+        Ok(Self {
+            addressing_mode,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.addressing_mode.to_base_type().to_le_bytes())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[from_to_other(base_type = u16, derive_compare = "as_int")]
+pub enum LabelAddressingMode {
+    Near = 0,
+    Far = 4,
+    Other(u16),
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct MultiDimensionalArrayTypeLeaf {
+    pub element_type_index: u16,
+    pub dimension_info_type_index: u16,
+    pub name: DisplayBytesVec, // PascalString
+}
+impl MultiDimensionalArrayTypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut header_buf = [0u8; 4];
+        reader.read_exact(&mut header_buf)?;
+        let element_type_index = u16::from_le_byte_slice(&header_buf[0..2]);
+        let dimension_info_type_index = u16::from_le_byte_slice(&header_buf[2..4]);
+
+        let name_vec = read_pascal_byte_string(reader)?;
+
+        let name = DisplayBytesVec::from(name_vec);
+
+        Ok(Self {
+            element_type_index,
+            dimension_info_type_index,
+            name,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.element_type_index.to_le_bytes())?;
+        writer.write_all(&self.dimension_info_type_index.to_le_bytes())?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct VirtualFunctionTablePathTypeLeaf {
+    pub base_class_count: u16,
+    pub base_class_type_indexes: Vec<u16>, // [u16; base_class_count]
+}
+impl VirtualFunctionTablePathTypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut count_buf = [0u8; 2];
+        reader.read_exact(&mut count_buf)?;
+        let base_class_count = u16::from_le_bytes(count_buf);
+
+        let base_class_type_indexes = read_type_indexes(reader, usize::from(base_class_count))?;
+
+        Ok(Self {
+            base_class_count,
+            base_class_type_indexes,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.base_class_count.to_le_bytes())?;
+        write_type_indexes(writer, &self.base_class_type_indexes)
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct PrecompiledTypeReferenceTypeLeaf {
+    pub start_type_index: u16,
+    pub type_count: u16,
+    pub signature: u32,
+    pub module_name: DisplayBytesVec, // PascalString
+}
+impl PrecompiledTypeReferenceTypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut header_buf = [0u8; 8];
+        reader.read_exact(&mut header_buf)?;
+        let start_type_index = u16::from_le_byte_slice(&header_buf[0..2]);
+        let type_count = u16::from_le_byte_slice(&header_buf[2..4]);
+        let signature = u32::from_le_byte_slice(&header_buf[4..8]);
+
+        let module_name_vec = read_pascal_byte_string(reader)?;
+
+        let module_name = DisplayBytesVec::from(module_name_vec);
+
+        Ok(Self {
+            start_type_index,
+            type_count,
+            signature,
+            module_name,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.start_type_index.to_le_bytes())?;
+        writer.write_all(&self.type_count.to_le_bytes())?;
+        writer.write_all(&self.signature.to_le_bytes())?;
+        write_pascal_byte_string(writer, self.module_name.as_ref())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct PrecompiledTypesEndTypeLeaf {
+    pub signature: u32,
+}
+impl PrecompiledTypesEndTypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut buf = [0u8; 4];
+        reader.read_exact(&mut buf)?;
+        let signature = u32::from_le_bytes(buf);
+
+        Ok(Self {
+            signature,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.signature.to_le_bytes())
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct OemGenericTypeTypeLeaf {
+    pub oem_id: u16,
+    pub oem_type_id: u16,
+    pub type_index_count: u16,
+    pub type_indexes: Vec<u16>, // [u16; type_index_count]
+    pub data: DisplayBytesVec, // remainder of leaf
+}
+impl OemGenericTypeTypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut header_buf = [0u8; 6];
+        reader.read_exact(&mut header_buf)?;
+        let oem_id = u16::from_le_byte_slice(&header_buf[0..2]);
+        let oem_type_id = u16::from_le_byte_slice(&header_buf[2..4]);
+        let type_index_count = u16::from_le_byte_slice(&header_buf[4..6]);
+
+        let type_indexes = read_type_indexes(reader, usize::from(type_index_count))?;
+
+        let mut data_vec = Vec::new();
+        reader.read_to_end(&mut data_vec)?;
+        let data = DisplayBytesVec::from(data_vec);
+
+        Ok(Self {
+            oem_id,
+            oem_type_id,
+            type_index_count,
+            type_indexes,
+            data,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.oem_id.to_le_bytes())?;
+        writer.write_all(&self.oem_type_id.to_le_bytes())?;
+        writer.write_all(&self.type_index_count.to_le_bytes())?;
+        write_type_indexes(writer, &self.type_indexes)?;
+        writer.write_all(self.data.as_ref())
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct SkipTypeLeaf {
+    pub next_type_index: u16,
+    pub padding: DisplayBytesVec, // remainder of leaf
+}
+impl SkipTypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut buf = [0u8; 2];
+        reader.read_exact(&mut buf)?;
+        let next_type_index = u16::from_le_bytes(buf);
+
+        let mut padding_vec = Vec::new();
+        reader.read_to_end(&mut padding_vec)?;
+        let padding = DisplayBytesVec::from(padding_vec);
+
+        Ok(Self {
+            next_type_index,
+            padding,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.next_type_index.to_le_bytes())?;
+        writer.write_all(self.padding.as_ref())
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct ArgumentListTypeLeaf {
@@ -1034,14 +1676,70 @@ impl ArgumentListTypeLeaf {
         reader.read_exact(&mut argument_type_indexes_buf)?;
         let argument_type_indexes: Vec<u16> = argument_type_indexes_buf
             .chunks(2)
-            .map(|chunk| u16::from_le_byte_slice(chunk))
-            .collect();
+            .map(|chunk| u16::try_from_le_byte_slice(chunk, 0))
+            .collect::<Result<_, _>>()?;
 
         Ok(Self {
             argument_count,
             argument_type_indexes,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.argument_count.to_le_bytes())?;
+        write_type_indexes(writer, &self.argument_type_indexes)
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct DefaultArgumentTypeLeaf {
+    pub type_index: u16,
+    pub expression: DisplayBytesVec, // PascalString
+}
+impl DefaultArgumentTypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut buf = [0u8; 2];
+        reader.read_exact(&mut buf)?;
+        let type_index = u16::from_le_bytes(buf);
+
+        let expression_vec = read_pascal_byte_string(reader)?;
+
+        let expression = DisplayBytesVec::from(expression_vec);
+
+        Ok(Self {
+            type_index,
+            expression,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.type_index.to_le_bytes())?;
+        write_pascal_byte_string(writer, self.expression.as_ref())
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct ListTypeLeaf {
+    pub data: DisplayBytesVec, // remainder of leaf; format is defined by the producer
+}
+impl ListTypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut data_vec = Vec::new();
+        reader.read_to_end(&mut data_vec)?;
+        let data = DisplayBytesVec::from(data_vec);
+
+        Ok(Self {
+            data,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(self.data.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1102,6 +1800,24 @@ impl FieldListTypeLeaf {
             fields,
         })
     }
+
+    /// Writes the fields, padding each of them to a multiple of 4 bytes.
+    ///
+    /// The padding assumes that the field list leaf itself starts at a multiple of 4 bytes, as it
+    /// does in a type record.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        let mut buf = Vec::new();
+        for field in &self.fields {
+            field.write(&mut buf)?;
+
+            // the leaf index and the length of the type record have already been written
+            let padding_bytes = (4 - (buf.len() + 4) % 4) % 4;
+            for i in (1..=padding_bytes).rev() {
+                buf.push(0xF0 | u8::try_from(i).unwrap());
+            }
+        }
+        writer.write_all(&buf)
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1122,14 +1838,19 @@ impl DerivedClassesTypeLeaf {
         reader.read_exact(&mut derived_class_buf)?;
         let derived_class_type_record_indices: Vec<u16> = derived_class_buf
             .chunks(2)
-            .map(|chunk| u16::from_le_byte_slice(chunk))
-            .collect();
+            .map(|chunk| u16::try_from_le_byte_slice(chunk, 0))
+            .collect::<Result<_, _>>()?;
 
         Ok(Self {
             derived_class_count,
             derived_class_type_record_indices,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.derived_class_count.to_le_bytes())?;
+        write_type_indexes(writer, &self.derived_class_type_record_indices)
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1154,6 +1875,11 @@ impl BitFieldsTypeLeaf {
             type_record_index,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&[self.bit_count, self.position])?;
+        writer.write_all(&self.type_record_index.to_le_bytes())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1192,6 +1918,13 @@ impl MethodListTypeLeaf {
             methods,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        for method in &self.methods {
+            method.write(writer)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1225,6 +1958,213 @@ impl MethodListEntry {
             virtual_function_table_offset,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.member_attributes.to_u16().to_le_bytes())?;
+        writer.write_all(&self.type_record_index.to_le_bytes())?;
+        if let Some(vfto) = self.virtual_function_table_offset {
+            writer.write_all(&vfto.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// The constant bound of a dimension of an array.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum ArrayBound {
+    Signed(i64),
+    Unsigned(u64),
+}
+
+/// Returns the size in bytes and the signedness of values of the given type if it is a primitive
+/// integer type.
+///
+/// Primitive type indexes consist of the size (bits 0-2), the kind (bits 4-7) and the pointer mode
+/// (bits 8-10, zero for values).
+fn primitive_integer_layout(type_index: u16) -> Option<(usize, bool)> {
+    if type_index >= 0x1000 || type_index & 0x0700 != 0 {
+        return None;
+    }
+    let size = type_index & 0b111;
+    match (type_index >> 4) & 0xF {
+        // signed and unsigned integral values: 1, 2, 4 or 8 bytes
+        0x1 if size <= 3 => Some((1 << size, true)),
+        0x2 if size <= 3 => Some((1 << size, false)),
+        // really int: char, wchar, int2, uint2, int4, uint4, int8, uint8
+        0x7 => Some(match size {
+            0 => (1, true),
+            1 => (2, false),
+            2 => (2, true),
+            3 => (2, false),
+            4 => (4, true),
+            5 => (4, false),
+            6 => (8, true),
+            _ => (8, false),
+        }),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct DimensionedArrayConstantBoundsTypeLeaf {
+    pub rank: u16,
+    pub index_type_index: u16,
+    pub lower_bounds: Option<Vec<ArrayBound>>, // [index type; rank] if the lower bounds are given
+    pub upper_bounds: Vec<ArrayBound>, // [index type; rank]
+}
+impl DimensionedArrayConstantBoundsTypeLeaf {
+    /// Reads the leaf. If `with_lower_bounds` is set, each dimension has a lower and an upper bound;
+    /// otherwise, the lower bounds are implied by the language.
+    ///
+    /// Each bound is stored as a value of the index type, which must be a primitive integer type.
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R, with_lower_bounds: bool) -> Result<Self, io::Error> {
+        let mut header_buf = [0u8; 4];
+        reader.read_exact(&mut header_buf)?;
+        let rank = u16::from_le_byte_slice(&header_buf[0..2]);
+        let index_type_index = u16::from_le_byte_slice(&header_buf[2..4]);
+
+        let Some((bound_size, signed)) = primitive_integer_layout(index_type_index) else {
+            error!("array index type {:#06X} is not a primitive integer type", index_type_index);
+            return Err(io::ErrorKind::InvalidData.into());
+        };
+        let mut read_bound = || -> Result<ArrayBound, io::Error> {
+            let mut bound_buf = [0u8; 8];
+            reader.read_exact(&mut bound_buf[..bound_size])?;
+            if signed {
+                // sign-extend by shifting the value to the top and back
+                let shift = 64 - 8*bound_size;
+                Ok(ArrayBound::Signed((i64::from_le_bytes(bound_buf) << shift) >> shift))
+            } else {
+                Ok(ArrayBound::Unsigned(u64::from_le_bytes(bound_buf)))
+            }
+        };
+
+        let rank_usize = usize::from(rank);
+        let mut lower_bounds = if with_lower_bounds { Some(Vec::with_capacity(rank_usize)) } else { None };
+        let mut upper_bounds = Vec::with_capacity(rank_usize);
+        for _ in 0..rank_usize {
+            if let Some(lbs) = lower_bounds.as_mut() {
+                lbs.push(read_bound()?);
+            }
+            upper_bounds.push(read_bound()?);
+        }
+
+        Ok(Self {
+            rank,
+            index_type_index,
+            lower_bounds,
+            upper_bounds,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        let Some((bound_size, signed)) = primitive_integer_layout(self.index_type_index) else {
+            error!("array index type {:#06X} is not a primitive integer type", self.index_type_index);
+            return Err(io::ErrorKind::InvalidInput.into());
+        };
+        let write_bound = |writer: &mut W, bound: &ArrayBound| -> Result<(), io::Error> {
+            let bits = 8*bound_size;
+            let fits = match (*bound, signed) {
+                (ArrayBound::Signed(value), true) => bits == 64 || (value >> (bits - 1) == 0 || value >> (bits - 1) == -1),
+                (ArrayBound::Unsigned(value), false) => bits == 64 || value >> bits == 0,
+                _ => false,
+            };
+            if !fits {
+                error!("array bound {:?} does not fit index type {:#06X}", bound, self.index_type_index);
+                return Err(io::ErrorKind::InvalidInput.into());
+            }
+            let bytes = match *bound {
+                ArrayBound::Signed(value) => value.to_le_bytes(),
+                ArrayBound::Unsigned(value) => value.to_le_bytes(),
+            };
+            writer.write_all(&bytes[..bound_size])
+        };
+
+        writer.write_all(&self.rank.to_le_bytes())?;
+        writer.write_all(&self.index_type_index.to_le_bytes())?;
+        for (i, upper_bound) in self.upper_bounds.iter().enumerate() {
+            if let Some(lower_bound) = self.lower_bounds.as_ref().and_then(|lbs| lbs.get(i)) {
+                write_bound(writer, lower_bound)?;
+            }
+            write_bound(writer, upper_bound)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct DimensionedArrayVariableBoundsTypeLeaf {
+    pub rank: u16,
+    pub index_type_index: u16,
+    pub lower_bound_type_indexes: Option<Vec<u16>>, // [u16; rank] if the lower bounds are given
+    pub upper_bound_type_indexes: Vec<u16>, // [u16; rank]
+}
+impl DimensionedArrayVariableBoundsTypeLeaf {
+    /// Reads the leaf. If `with_lower_bounds` is set, each dimension has a lower and an upper bound;
+    /// otherwise, the lower bounds are implied by the language.
+    ///
+    /// The bounds are type indexes of [`ReferencedSymbolTypeLeaf`]s describing the variables that
+    /// contain the bounds.
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R, with_lower_bounds: bool) -> Result<Self, io::Error> {
+        let mut header_buf = [0u8; 4];
+        reader.read_exact(&mut header_buf)?;
+        let rank = u16::from_le_byte_slice(&header_buf[0..2]);
+        let index_type_index = u16::from_le_byte_slice(&header_buf[2..4]);
+
+        let rank_usize = usize::from(rank);
+        let (lower_bound_type_indexes, upper_bound_type_indexes) = if with_lower_bounds {
+            let bounds = read_type_indexes(reader, 2*rank_usize)?;
+            let lower = bounds.iter().step_by(2).copied().collect();
+            let upper = bounds.iter().skip(1).step_by(2).copied().collect();
+            (Some(lower), upper)
+        } else {
+            (None, read_type_indexes(reader, rank_usize)?)
+        };
+
+        Ok(Self {
+            rank,
+            index_type_index,
+            lower_bound_type_indexes,
+            upper_bound_type_indexes,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.rank.to_le_bytes())?;
+        writer.write_all(&self.index_type_index.to_le_bytes())?;
+        for (i, upper_bound) in self.upper_bound_type_indexes.iter().enumerate() {
+            if let Some(lower_bound) = self.lower_bound_type_indexes.as_ref().and_then(|lbs| lbs.get(i)) {
+                writer.write_all(&lower_bound.to_le_bytes())?;
+            }
+            writer.write_all(&upper_bound.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct ReferencedSymbolTypeLeaf {
+    pub symbol: SymbolEntry,
+}
+impl ReferencedSymbolTypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let symbol = SymbolEntry::read(reader)?;
+
+        Ok(Self {
+            symbol,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        self.symbol.write(writer)
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1254,6 +2194,53 @@ impl RealBaseClassTypeLeaf {
             offset,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.type_record_index.to_le_bytes())?;
+        writer.write_all(&self.member_attributes.to_u16().to_le_bytes())?;
+        self.offset.write(writer)
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct VirtualBaseClassTypeLeaf {
+    pub type_record_index: u16,
+    pub virtual_base_pointer_type_index: u16,
+    pub member_attributes: MemberAttributes, // u16
+    pub virtual_base_pointer_offset: NumericLeaf,
+    pub virtual_base_table_offset: NumericLeaf,
+}
+impl VirtualBaseClassTypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut header_buf = [0u8; 6];
+        reader.read_exact(&mut header_buf)?;
+        let type_record_index = u16::from_le_byte_slice(&header_buf[0..2]);
+        let virtual_base_pointer_type_index = u16::from_le_byte_slice(&header_buf[2..4]);
+        let member_attributes_u16 = u16::from_le_byte_slice(&header_buf[4..6]);
+
+        let member_attributes = MemberAttributes::from_u16(member_attributes_u16);
+
+        let virtual_base_pointer_offset = NumericLeaf::read(reader)?;
+        let virtual_base_table_offset = NumericLeaf::read(reader)?;
+
+        Ok(Self {
+            type_record_index,
+            virtual_base_pointer_type_index,
+            member_attributes,
+            virtual_base_pointer_offset,
+            virtual_base_table_offset,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.type_record_index.to_le_bytes())?;
+        writer.write_all(&self.virtual_base_pointer_type_index.to_le_bytes())?;
+        writer.write_all(&self.member_attributes.to_u16().to_le_bytes())?;
+        self.virtual_base_pointer_offset.write(writer)?;
+        self.virtual_base_table_offset.write(writer)
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1283,6 +2270,63 @@ impl EnumerationNameAndValueTypeLeaf {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.member_attributes.to_u16().to_le_bytes())?;
+        self.value.write(writer)?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct FriendFunctionTypeLeaf {
+    pub type_record_index: u16,
+    pub name: DisplayBytesVec, // PascalString
+}
+impl FriendFunctionTypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut buf = [0u8; 2];
+        reader.read_exact(&mut buf)?;
+        let type_record_index = u16::from_le_bytes(buf);
+
+        let name_vec = read_pascal_byte_string(reader)?;
+
+        let name = DisplayBytesVec::from(name_vec);
+
+        Ok(Self {
+            type_record_index,
+            name,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.type_record_index.to_le_bytes())?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct IndexToAnotherTypeRecordTypeLeaf {
+    pub type_record_index: u16,
+}
+impl IndexToAnotherTypeRecordTypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut buf = [0u8; 2];
+        reader.read_exact(&mut buf)?;
+        let type_record_index = u16::from_le_bytes(buf);
+
+        Ok(Self {
+            type_record_index,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.type_record_index.to_le_bytes())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1317,6 +2361,13 @@ impl DataMemberTypeLeaf {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.type_record_index.to_le_bytes())?;
+        writer.write_all(&self.member_attributes.to_u16().to_le_bytes())?;
+        self.offset.write(writer)?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1347,6 +2398,12 @@ impl StaticDataMemberTypeLeaf {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.type_record_index.to_le_bytes())?;
+        writer.write_all(&self.member_attributes.to_u16().to_le_bytes())?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1374,6 +2431,12 @@ impl MethodTypeLeaf {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.overload_count.to_le_bytes())?;
+        writer.write_all(&self.method_list_type_index.to_le_bytes())?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1398,6 +2461,11 @@ impl NestedTypeDefinitionTypeLeaf {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.nested_type_record_index.to_le_bytes())?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1416,6 +2484,32 @@ impl VirtualFunctionTablePointerTypeLeaf {
             pointer_type_record_index,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.pointer_type_record_index.to_le_bytes())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct FriendClassTypeLeaf {
+    pub type_record_index: u16,
+}
+impl FriendClassTypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut buf = [0u8; 2];
+        reader.read_exact(&mut buf)?;
+        let type_record_index = u16::from_le_bytes(buf);
+
+        Ok(Self {
+            type_record_index,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.type_record_index.to_le_bytes())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1459,4 +2553,129 @@ impl OneMethodTypeLeaf {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.member_attributes.to_u16().to_le_bytes())?;
+        writer.write_all(&self.type_record_index.to_le_bytes())?;
+        if let Some(vfto) = self.virtual_function_table_offset {
+            writer.write_all(&vfto.to_le_bytes())?;
+        }
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct VirtualFunctionOffsetTypeLeaf {
+    pub type_record_index: u16,
+    pub offset: u32,
+}
+impl VirtualFunctionOffsetTypeLeaf {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut buf = [0u8; 6];
+        reader.read_exact(&mut buf)?;
+        let type_record_index = u16::from_le_byte_slice(&buf[0..2]);
+        let offset = u32::from_le_byte_slice(&buf[2..6]);
+
+        Ok(Self {
+            type_record_index,
+            offset,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.type_record_index.to_le_bytes())?;
+        writer.write_all(&self.offset.to_le_bytes())
+    }
+}
+
+
+/// Reads a sequence of `count` type indexes.
+fn read_type_indexes<R: Read>(reader: &mut R, count: usize) -> Result<Vec<u16>, io::Error> {
+    let mut buf = vec![0u8; 2*count];
+    reader.read_exact(&mut buf)?;
+    buf
+        .chunks(2)
+        .map(|chunk| u16::try_from_le_byte_slice(chunk, 0))
+        .collect()
+}
+
+/// Writes a sequence of type indexes.
+fn write_type_indexes<W: Write>(writer: &mut W, type_indexes: &[u16]) -> Result<(), io::Error> {
+    for type_index in type_indexes {
+        writer.write_all(&type_index.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use super::{primitive_integer_layout, ArrayBound, NumericLeaf, TypeLeaf};
+
+    #[test]
+    fn test_primitive_integer_layout() {
+        assert_eq!(primitive_integer_layout(0x0010), Some((1, true))); // T_CHAR
+        assert_eq!(primitive_integer_layout(0x0021), Some((2, false))); // T_USHORT
+        assert_eq!(primitive_integer_layout(0x0012), Some((4, true))); // T_LONG
+        assert_eq!(primitive_integer_layout(0x0023), Some((8, false))); // T_UQUAD
+        assert_eq!(primitive_integer_layout(0x0070), Some((1, true))); // T_RCHAR
+        assert_eq!(primitive_integer_layout(0x0071), Some((2, false))); // T_WCHAR
+        assert_eq!(primitive_integer_layout(0x0072), Some((2, true))); // T_INT2
+        assert_eq!(primitive_integer_layout(0x0075), Some((4, false))); // T_UINT4
+        assert_eq!(primitive_integer_layout(0x0076), Some((8, true))); // T_INT8
+        assert_eq!(primitive_integer_layout(0x0077), Some((8, false))); // T_UINT8
+        assert_eq!(primitive_integer_layout(0x0040), None); // T_REAL32
+        assert_eq!(primitive_integer_layout(0x0474), None); // T_32PINT4
+        assert_eq!(primitive_integer_layout(0x1000), None);
+    }
+
+    #[test]
+    fn test_dimensioned_array_leaves() {
+        // LF_DIMCONLU: rank 2, index type T_SHORT, bounds [-1..=10, 0..=0x7FFF]
+        let data = b"\x09\x02\x02\x00\x11\x00\xFF\xFF\x0A\x00\x00\x00\xFF\x7F";
+        let leaf = TypeLeaf::read(&mut Cursor::new(data)).unwrap();
+        let TypeLeaf::DimensionedArrayConstantLowerConstantUpper(dims) = &leaf else { panic!("wrong leaf: {:?}", leaf) };
+        assert_eq!(dims.rank, 2);
+        assert_eq!(dims.index_type_index, 0x0011);
+        assert_eq!(dims.lower_bounds, Some(vec![ArrayBound::Signed(-1), ArrayBound::Signed(0)]));
+        assert_eq!(dims.upper_bounds, vec![ArrayBound::Signed(10), ArrayBound::Signed(0x7FFF)]);
+        let mut written = Vec::new();
+        leaf.write(&mut written).unwrap();
+        assert_eq!(written, data);
+
+        // LF_DIMCONU: rank 3, index type T_UCHAR, upper bounds only
+        let data = b"\x08\x02\x03\x00\x20\x00\x02\x80\xFF";
+        let leaf = TypeLeaf::read(&mut Cursor::new(data)).unwrap();
+        let TypeLeaf::DimensionedArrayDefaultLowerConstantUpper(dims) = &leaf else { panic!("wrong leaf: {:?}", leaf) };
+        assert_eq!(dims.lower_bounds, None);
+        assert_eq!(dims.upper_bounds, vec![ArrayBound::Unsigned(2), ArrayBound::Unsigned(0x80), ArrayBound::Unsigned(0xFF)]);
+        let mut written = Vec::new();
+        leaf.write(&mut written).unwrap();
+        assert_eq!(written, data);
+
+        // the bounds cannot be sized by a non-integer index type
+        assert!(TypeLeaf::read(&mut Cursor::new(b"\x08\x02\x01\x00\x40\x00\x00\x00\x80\x3F")).is_err());
+
+        // LF_DIMVARLU: rank 1, index type T_SHORT, bounds from symbols at 0x1000 and 0x1001
+        let data = b"\x0B\x02\x01\x00\x11\x00\x00\x10\x01\x10";
+        let leaf = TypeLeaf::read(&mut Cursor::new(data)).unwrap();
+        let TypeLeaf::DimensionedArrayVariableLowerVariableUpper(dims) = leaf else { panic!("wrong leaf: {:?}", leaf) };
+        assert_eq!(dims.lower_bound_type_indexes, Some(vec![0x1000]));
+        assert_eq!(dims.upper_bound_type_indexes, vec![0x1001]);
+    }
+
+    #[test]
+    fn test_virtual_base_class_leaf() {
+        // LF_VBCLASS: base 0x1002, vbptr type 0x1003, public, vbptr offset 4, vbtable offset 1
+        let data = b"\x01\x04\x02\x10\x03\x10\x03\x00\x04\x00\x01\x00";
+        let leaf = TypeLeaf::read(&mut Cursor::new(data)).unwrap();
+        let TypeLeaf::DirectVirtualBaseClass(base) = leaf else { panic!("wrong leaf: {:?}", leaf) };
+        assert_eq!(base.type_record_index, 0x1002);
+        assert_eq!(base.virtual_base_pointer_type_index, 0x1003);
+        assert_eq!(base.virtual_base_pointer_offset, NumericLeaf::Immediate(4));
+        assert_eq!(base.virtual_base_table_offset, NumericLeaf::Immediate(1));
+    }
 }
@@ -8,7 +8,8 @@ pub mod leaves;
 pub mod symbol_entries;
 
 
-use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
 
 use bitflags::bitflags;
 use display_bytes::{DisplayBytesSlice, DisplayBytesVec};
@@ -17,10 +18,12 @@ use from_to_repr::from_to_other;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, instrument};
 
-use crate::read_pascal_byte_string;
+use crate::{read_pascal_byte_string, write_pascal_byte_string};
 use crate::code_view::leaves::TypeLeaf;
 use crate::code_view::symbol_entries::SymbolEntry;
 use crate::int_from_byte_slice::IntFromByteSlice;
+use crate::ne::SegmentTableEntry;
+use crate::pe::SectionTable;
 
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -108,8 +111,11 @@ impl DebugInfo {
                     let content = SegmentMapSubsection::read(&mut data_reader)?;
                     SubsectionData::SegmentMap(content)
                 },
+                SubsectionType::SegmentName => {
+                    let content = SegmentNameSubsection::read(&mut data_reader)?;
+                    SubsectionData::SegmentName(content)
+                },
                 /*
-                SubsectionType::SegmentName => todo!(),
                 SubsectionType::PreCompile => todo!(),
                 */
                 SubsectionType::FileIndex => {
@@ -138,6 +144,83 @@ impl DebugInfo {
             subsection_directory_entries,
         })
     }
+
+    /// Writes the debug information.
+    ///
+    /// Each subsection is written at the offset stored in its metadata and padded with zero bytes
+    /// to the stored size; the subsection directory is written at the stored directory offset.
+    pub fn write<W: Write + Seek>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.signature)?;
+        writer.write_all(&self.directory_offset.to_le_bytes())?;
+
+        for entry in &self.subsection_directory_entries {
+            let mut data = Vec::new();
+            entry.data.write(&mut data)?;
+
+            let size_bytes: usize = entry.metadata.size_bytes.try_into().unwrap();
+            if data.len() > size_bytes {
+                error!("{:?} subsection is {} bytes long but only {} bytes are allocated to it", entry.metadata.subsection_type, data.len(), size_bytes);
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+            data.resize(size_bytes, 0x00);
+
+            writer.seek(SeekFrom::Start(entry.metadata.offset.into()))?;
+            writer.write_all(&data)?;
+        }
+
+        writer.seek(SeekFrom::Start(self.directory_offset.into()))?;
+        self.subsection_directory_header.write(writer)?;
+        for entry in &self.subsection_directory_entries {
+            entry.metadata.write(writer)?;
+        }
+        Ok(())
+    }
+
+    /// The segment map subsection, if any.
+    pub fn segment_map(&self) -> Option<&SegmentMapSubsection> {
+        self.subsection_directory_entries.iter()
+            .find_map(|entry| match &entry.data {
+                SubsectionData::SegmentMap(content) => Some(content),
+                _ => None,
+            })
+    }
+
+    /// The segment name subsection, if any.
+    pub fn segment_names(&self) -> Option<&SegmentNameSubsection> {
+        self.subsection_directory_entries.iter()
+            .find_map(|entry| match &entry.data {
+                SubsectionData::SegmentName(content) => Some(content),
+                _ => None,
+            })
+    }
+
+    /// Iterates over the symbols of all the symbol subsections: those of the modules, the global
+    /// symbols, the global public symbols and the static symbols.
+    pub fn symbols(&self) -> impl Iterator<Item = &SymbolEntry> {
+        self.subsection_directory_entries.iter()
+            .flat_map(|entry| match &entry.data {
+                SubsectionData::Symbols(content) => content.symbols.as_slice(),
+                SubsectionData::GlobalSymbols(content)
+                    |SubsectionData::GlobalPublicSymbols(content)
+                    |SubsectionData::StaticSymbols(content)
+                    => content.symbols.as_slice(),
+                _ => &[],
+            })
+    }
+
+    /// The layout of the logical segments in the given PE image.
+    ///
+    /// See [`SegmentLayout::for_pe`].
+    pub fn segment_layout_for_pe(&self, section_table: &SectionTable) -> SegmentLayout {
+        SegmentLayout::for_pe(self.segment_map(), self.segment_names(), section_table)
+    }
+
+    /// The layout of the logical segments in the given NE image.
+    ///
+    /// See [`SegmentLayout::for_ne`].
+    pub fn segment_layout_for_ne(&self, segment_table: &[SegmentTableEntry]) -> SegmentLayout {
+        SegmentLayout::for_ne(self.segment_map(), self.segment_names(), segment_table)
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -179,6 +262,14 @@ impl SubsectionDirectoryHeader {
             flags,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.header_length.to_le_bytes())?;
+        writer.write_all(&self.entry_length.to_le_bytes())?;
+        writer.write_all(&self.entry_count.to_le_bytes())?;
+        writer.write_all(&self.next_directory_offset.to_le_bytes())?;
+        writer.write_all(&self.flags.to_le_bytes())
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -208,6 +299,13 @@ impl SubsectionDirectoryEntryMetadata {
             size_bytes,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.subsection_type.to_base_type().to_le_bytes())?;
+        writer.write_all(&self.module_index.to_le_bytes())?;
+        writer.write_all(&self.offset.to_le_bytes())?;
+        writer.write_all(&self.size_bytes.to_le_bytes())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -262,14 +360,32 @@ pub enum SubsectionData {
     MakePCode(MakePCodeSubsection),
     */
     SegmentMap(SegmentMapSubsection),
-    /*
     SegmentName(SegmentNameSubsection),
+    /*
     PreCompile(PreCompileSubsection),
     */
     FileIndex(FileIndexSubsection),
     StaticSymbols(GlobalSymbolsSubsection),
     Other(DisplayBytesVec),
 }
+impl SubsectionData {
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        match self {
+            Self::Module(content) => content.write(writer),
+            Self::Symbols(content) => content.write(writer),
+            Self::SourceLineModule(content) => content.write(writer),
+            Self::Libraries(content) => content.write(writer),
+            Self::GlobalSymbols(content) => content.write(writer),
+            Self::GlobalPublicSymbols(content) => content.write(writer),
+            Self::GlobalTypes(content) => content.write(writer),
+            Self::SegmentMap(content) => content.write(writer),
+            Self::SegmentName(content) => content.write(writer),
+            Self::FileIndex(content) => content.write(writer),
+            Self::StaticSymbols(content) => content.write(writer),
+            Self::Other(data) => writer.write_all(data.as_ref()),
+        }
+    }
+}
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
@@ -309,6 +425,17 @@ impl ModuleSubsection {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.overlay_number.to_le_bytes())?;
+        writer.write_all(&self.library_index.to_le_bytes())?;
+        writer.write_all(&self.code_segment_count.to_le_bytes())?;
+        writer.write_all(&self.debugging_style.to_le_bytes())?;
+        for seg in &self.segment_info {
+            seg.write(writer)?;
+        }
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -336,6 +463,13 @@ impl ModuleSegmentInfo {
             code_size,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.segment.to_le_bytes())?;
+        writer.write_all(&self.padding.to_le_bytes())?;
+        writer.write_all(&self.code_offset.to_le_bytes())?;
+        writer.write_all(&self.code_size.to_le_bytes())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -353,6 +487,10 @@ impl TypesSubsection {
             data: DisplayBytesVec::from(data),
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(self.data.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -416,6 +554,14 @@ impl SymbolsSubsection {
             symbols,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.signature.to_le_bytes())?;
+        for symbol in &self.symbols {
+            symbol.write(writer)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -445,25 +591,25 @@ impl SourceLineModuleSubsection {
         reader.read_exact(&mut source_file_offsets_buf)?;
         let source_file_offsets: Vec<u32> = source_file_offsets_buf
             .chunks(4)
-            .map(|chunk| u32::from_le_byte_slice(chunk))
-            .collect();
+            .map(|chunk| u32::try_from_le_byte_slice(chunk, 0))
+            .collect::<Result<_, _>>()?;
 
         let mut segment_starts_ends_buf = vec![0u8; 8*segment_count_usize];
         reader.read_exact(&mut segment_starts_ends_buf)?;
         let segment_starts_ends: Vec<(u32, u32)> = segment_starts_ends_buf
             .chunks(8)
-            .map(|chunk| (
-                u32::from_le_byte_slice(&chunk[0..4]),
-                u32::from_le_byte_slice(&chunk[4..8]),
-            ))
-            .collect();
+            .map(|chunk| Ok((
+                u32::try_from_le_byte_slice(chunk, 0)?,
+                u32::try_from_le_byte_slice(chunk, 4)?,
+            )))
+            .collect::<Result<_, io::Error>>()?;
 
         let mut segment_indices_buf = vec![0u8; 2*segment_count_usize];
         reader.read_exact(&mut segment_indices_buf)?;
         let segment_indices: Vec<u16> = segment_indices_buf
             .chunks(2)
-            .map(|chunk| u16::from_le_byte_slice(chunk))
-            .collect();
+            .map(|chunk| u16::try_from_le_byte_slice(chunk, 0))
+            .collect::<Result<_, _>>()?;
 
         let padding = if segment_count % 2 == 0 {
             None
@@ -489,6 +635,28 @@ impl SourceLineModuleSubsection {
             source_files,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.source_file_count.to_le_bytes())?;
+        writer.write_all(&self.segment_count.to_le_bytes())?;
+        for offset in &self.source_file_offsets {
+            writer.write_all(&offset.to_le_bytes())?;
+        }
+        for (start, end) in &self.segment_starts_ends {
+            writer.write_all(&start.to_le_bytes())?;
+            writer.write_all(&end.to_le_bytes())?;
+        }
+        for index in &self.segment_indices {
+            writer.write_all(&index.to_le_bytes())?;
+        }
+        if let Some(padding) = self.padding {
+            writer.write_all(&padding.to_le_bytes())?;
+        }
+        for source_file in &self.source_files {
+            source_file.write(writer)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -517,18 +685,18 @@ impl SourceLineFile {
         reader.read_exact(&mut source_line_offsets_buf)?;
         let source_line_offsets: Vec<u32> = source_line_offsets_buf
             .chunks(4)
-            .map(|chunk| u32::from_le_byte_slice(chunk))
-            .collect();
+            .map(|chunk| u32::try_from_le_byte_slice(chunk, 0))
+            .collect::<Result<_, _>>()?;
 
         let mut segment_starts_ends_buf = vec![0u8; 8*segment_count_usize];
         reader.read_exact(&mut segment_starts_ends_buf)?;
         let segment_starts_ends: Vec<(u32, u32)> = segment_starts_ends_buf
             .chunks(8)
-            .map(|chunk| (
-                u32::from_le_byte_slice(&chunk[0..4]),
-                u32::from_le_byte_slice(&chunk[4..8]),
-            ))
-            .collect();
+            .map(|chunk| Ok((
+                u32::try_from_le_byte_slice(chunk, 0)?,
+                u32::try_from_le_byte_slice(chunk, 4)?,
+            )))
+            .collect::<Result<_, io::Error>>()?;
 
         let name_vec = read_pascal_byte_string(reader)?;
         let name = DisplayBytesVec::from(name_vec);
@@ -570,6 +738,26 @@ impl SourceLineFile {
             segments,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.segment_count.to_le_bytes())?;
+        writer.write_all(&self.padding.to_le_bytes())?;
+        for offset in &self.source_line_offsets {
+            writer.write_all(&offset.to_le_bytes())?;
+        }
+        for (start, end) in &self.segment_starts_ends {
+            writer.write_all(&start.to_le_bytes())?;
+            writer.write_all(&end.to_le_bytes())?;
+        }
+        write_pascal_byte_string(writer, self.name.as_ref())?;
+        for padding_byte in self.padding2.iter().flatten() {
+            writer.write_all(&[*padding_byte])?;
+        }
+        for segment in &self.segments {
+            segment.write(writer)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -595,15 +783,15 @@ impl SourceLineSegment {
         reader.read_exact(&mut line_offsets_buf)?;
         let line_offsets: Vec<u32> = line_offsets_buf
             .chunks(4)
-            .map(|chunk| u32::from_le_byte_slice(chunk))
-            .collect();
+            .map(|chunk| u32::try_from_le_byte_slice(chunk, 0))
+            .collect::<Result<_, _>>()?;
 
         let mut line_numbers_buf = vec![0u8; 2*line_pair_count_usize];
         reader.read_exact(&mut line_numbers_buf)?;
         let line_numbers: Vec<u16> = line_numbers_buf
             .chunks(2)
-            .map(|chunk| u16::from_le_byte_slice(chunk))
-            .collect();
+            .map(|chunk| u16::try_from_le_byte_slice(chunk, 0))
+            .collect::<Result<_, _>>()?;
 
         let padding = if line_pair_count % 2 == 0 {
             None
@@ -621,6 +809,21 @@ impl SourceLineSegment {
             padding,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.segment_index.to_le_bytes())?;
+        writer.write_all(&self.line_pair_count.to_le_bytes())?;
+        for offset in &self.line_offsets {
+            writer.write_all(&offset.to_le_bytes())?;
+        }
+        for number in &self.line_numbers {
+            writer.write_all(&number.to_le_bytes())?;
+        }
+        if let Some(padding) = self.padding {
+            writer.write_all(&padding.to_le_bytes())?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -657,6 +860,13 @@ impl LibrariesSubsection {
             libraries,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        for library in &self.libraries {
+            write_pascal_byte_string(writer, library.as_ref())?;
+        }
+        Ok(())
+    }
 }
 
 
@@ -669,8 +879,8 @@ pub struct GlobalSymbolsSubsection {
     pub symbol_hash_table_length: u32,
     pub address_hash_table_length: u32,
     pub symbols: Vec<SymbolEntry>, // until symbols_length bytes have been read
-    pub symbol_hash_table: DisplayBytesVec, // [u8; symbol_hash_table_length]
-    pub address_hash_table: DisplayBytesVec, // [u8; address_hash_table_length]
+    pub symbol_hash_table: SymbolHashTable, // [u8; symbol_hash_table_length]
+    pub address_hash_table: AddressHashTable, // [u8; address_hash_table_length]
 }
 impl GlobalSymbolsSubsection {
     #[instrument(skip_all)]
@@ -691,8 +901,8 @@ impl GlobalSymbolsSubsection {
         let mut address_hash_table_vec = vec![0u8; address_hash_table_length.try_into().unwrap()];
         reader.read_exact(&mut address_hash_table_vec)?;
 
-        let symbol_hash_table = DisplayBytesVec::from(symbol_hash_table_vec);
-        let address_hash_table = DisplayBytesVec::from(address_hash_table_vec);
+        let symbol_hash_table = SymbolHashTable::read(symbol_hash_function_index, symbol_hash_table_vec)?;
+        let address_hash_table = AddressHashTable::read(address_hash_function_index, address_hash_table_vec)?;
 
         let mut symbol_reader = Cursor::new(&symbol_bytes);
 
@@ -746,6 +956,342 @@ impl GlobalSymbolsSubsection {
             address_hash_table,
         })
     }
+
+    /// Returns the symbols along with their offsets from the beginning of the symbol data, which is
+    /// how the hash tables refer to them.
+    pub fn symbols_with_offsets(&self) -> impl Iterator<Item = (u32, &SymbolEntry)> {
+        self.symbols
+            .iter()
+            .scan(0u32, |offset, symbol| {
+                let symbol_offset = *offset;
+                *offset += u32::from(symbol.length) + 2;
+                Some((symbol_offset, symbol))
+            })
+    }
+
+    /// Returns the symbol at the given offset from the beginning of the symbol data.
+    pub fn symbol_at_offset(&self, offset: u32) -> Option<&SymbolEntry> {
+        self.symbols_with_offsets()
+            .take_while(|(symbol_offset, _)| *symbol_offset <= offset)
+            .find(|(symbol_offset, _)| *symbol_offset == offset)
+            .map(|(_, symbol)| symbol)
+    }
+
+    /// Finds the symbols with the given name.
+    ///
+    /// The symbol hash table is used if its format is known; otherwise, all symbols are searched.
+    pub fn find_symbols_by_name(&self, name: &[u8]) -> Vec<&SymbolEntry> {
+        let has_name = |symbol: &SymbolEntry| symbol.data.name()
+            .is_some_and(|n| AsRef::<[u8]>::as_ref(n) == name);
+
+        match &self.symbol_hash_table {
+            SymbolHashTable::DwordXorRotate(buckets) => {
+                let hash = dword_xor_rotate_hash(name);
+                let Some(bucket) = buckets.bucket_for_hash(hash) else {
+                    return Vec::new();
+                };
+                bucket.iter()
+                    .filter(|entry| entry.value == hash)
+                    .filter_map(|entry| self.symbol_at_offset(entry.symbol_offset))
+                    .filter(|symbol| has_name(symbol))
+                    .collect()
+            },
+            SymbolHashTable::Other(_) => {
+                self.symbols.iter()
+                    .filter(|symbol| has_name(symbol))
+                    .collect()
+            },
+        }
+    }
+
+    /// Checks whether the hash tables in known formats are consistent with the symbols.
+    ///
+    /// Returns a description of each discrepancy.
+    pub fn validate_hash_tables(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let offset_to_symbol: BTreeMap<u32, &SymbolEntry> = self.symbols_with_offsets().collect();
+
+        if let SymbolHashTable::DwordXorRotate(table) = &self.symbol_hash_table {
+            let mut hashed_offsets = BTreeSet::new();
+            for (bucket_index, bucket) in table.buckets.iter().enumerate() {
+                for entry in bucket {
+                    hashed_offsets.insert(entry.symbol_offset);
+                    let Some(symbol) = offset_to_symbol.get(&entry.symbol_offset) else {
+                        problems.push(format!("symbol hash table refers to offset {:#X}, where no symbol starts", entry.symbol_offset));
+                        continue;
+                    };
+                    if table.bucket_index_for_hash(entry.value) != Some(bucket_index) {
+                        problems.push(format!("hash {:#010X} of symbol at offset {:#X} is in bucket {} instead of {:?}", entry.value, entry.symbol_offset, bucket_index, table.bucket_index_for_hash(entry.value)));
+                    }
+                    // references (e.g. S_PROCREF) only carry a checksum of the name
+                    if let Some(name) = symbol.data.name() {
+                        let hash = dword_xor_rotate_hash(name.as_ref());
+                        if hash != entry.value {
+                            problems.push(format!("symbol hash table stores hash {:#010X} for {} at offset {:#X}, expected {:#010X}", entry.value, name, entry.symbol_offset, hash));
+                        }
+                    }
+                }
+            }
+            for offset in offset_to_symbol.keys() {
+                if !hashed_offsets.contains(offset) {
+                    problems.push(format!("symbol at offset {:#X} is missing from the symbol hash table", offset));
+                }
+            }
+        }
+
+        if let AddressHashTable::SortedOffsets(table) = &self.address_hash_table {
+            for (segment_index, segment) in table.buckets.iter().enumerate() {
+                for entry in segment {
+                    if !offset_to_symbol.contains_key(&entry.symbol_offset) {
+                        problems.push(format!("address hash table refers to offset {:#X}, where no symbol starts", entry.symbol_offset));
+                    }
+                }
+                if segment.windows(2).any(|pair| pair[0].value > pair[1].value) {
+                    problems.push(format!("address hash table entries of segment {} are not sorted by address", segment_index + 1));
+                }
+            }
+        }
+
+        problems
+    }
+
+    /// Writes the subsection.
+    ///
+    /// The lengths stored in the header must match the lengths of the symbols and hash tables as
+    /// they are written.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        let mut symbol_bytes = Vec::new();
+        for symbol in &self.symbols {
+            symbol.write(&mut symbol_bytes)?;
+        }
+        let mut symbol_hash_table_bytes = Vec::new();
+        self.symbol_hash_table.write(&mut symbol_hash_table_bytes)?;
+        let mut address_hash_table_bytes = Vec::new();
+        self.address_hash_table.write(&mut address_hash_table_bytes)?;
+
+        let parts = [
+            ("symbols", self.symbols_length, &symbol_bytes),
+            ("symbol hash table", self.symbol_hash_table_length, &symbol_hash_table_bytes),
+            ("address hash table", self.address_hash_table_length, &address_hash_table_bytes),
+        ];
+        for (name, stored_length, bytes) in &parts {
+            if usize::try_from(*stored_length).unwrap() != bytes.len() {
+                error!("{} take up {} bytes but the stored length is {}", name, bytes.len(), stored_length);
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+        }
+
+        writer.write_all(&self.symbol_hash_function_index.to_le_bytes())?;
+        writer.write_all(&self.address_hash_function_index.to_le_bytes())?;
+        writer.write_all(&self.symbols_length.to_le_bytes())?;
+        writer.write_all(&self.symbol_hash_table_length.to_le_bytes())?;
+        writer.write_all(&self.address_hash_table_length.to_le_bytes())?;
+        writer.write_all(&symbol_bytes)?;
+        writer.write_all(&symbol_hash_table_bytes)?;
+        writer.write_all(&address_hash_table_bytes)
+    }
+}
+
+/// The table used to look up symbols by name.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum SymbolHashTable {
+    /// Hash function index 0x000A: symbols are distributed into buckets by their
+    /// [`dword_xor_rotate_hash`]; the value of each entry is the hash.
+    DwordXorRotate(BucketedHashTable),
+
+    /// No hash table or one in an unsupported format.
+    Other(DisplayBytesVec),
+}
+impl SymbolHashTable {
+    pub fn read(hash_function_index: u16, data: Vec<u8>) -> Result<Self, io::Error> {
+        match hash_function_index {
+            0x000A => {
+                let table = BucketedHashTable::read(&data)?;
+                Ok(Self::DwordXorRotate(table))
+            },
+            _ => Ok(Self::Other(DisplayBytesVec::from(data))),
+        }
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        match self {
+            Self::DwordXorRotate(table) => table.write(writer),
+            Self::Other(data) => writer.write_all(data.as_ref()),
+        }
+    }
+}
+
+/// The table used to look up symbols by address.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum AddressHashTable {
+    /// Hash function index 0x000C: there is one bucket per segment (starting at segment 1) and the
+    /// entries of each bucket are sorted by the offset of the symbol within the segment, which is
+    /// the value of each entry.
+    SortedOffsets(BucketedHashTable),
+
+    /// No hash table or one in an unsupported format.
+    Other(DisplayBytesVec),
+}
+impl AddressHashTable {
+    pub fn read(hash_function_index: u16, data: Vec<u8>) -> Result<Self, io::Error> {
+        match hash_function_index {
+            0x000C => {
+                let table = BucketedHashTable::read(&data)?;
+                Ok(Self::SortedOffsets(table))
+            },
+            _ => Ok(Self::Other(DisplayBytesVec::from(data))),
+        }
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        match self {
+            Self::SortedOffsets(table) => table.write(writer),
+            Self::Other(data) => writer.write_all(data.as_ref()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct BucketedHashTable {
+    pub bucket_count: u16,
+    pub padding: u16,
+    pub bucket_offsets: Vec<u32>, // [u32; bucket_count], from the start of the entries
+    pub bucket_entry_counts: Vec<u32>, // [u32; bucket_count]
+    pub buckets: Vec<Vec<HashTableEntry>>, // [[HashTableEntry; bucket_entry_counts[i]]; bucket_count]
+}
+impl BucketedHashTable {
+    #[instrument(skip_all)]
+    pub fn read(data: &[u8]) -> Result<Self, io::Error> {
+        let bucket_count = u16::try_from_le_byte_slice(data, 0)
+            .inspect_err(|_| error!("hash table is {} bytes long, too short for its header", data.len()))?;
+        let padding = u16::try_from_le_byte_slice(data, 2)
+            .inspect_err(|_| error!("hash table is {} bytes long, too short for its header", data.len()))?;
+
+        let bucket_count_usize = usize::from(bucket_count);
+        let entries_start = 4 + 2*4*bucket_count_usize;
+        if data.len() < entries_start {
+            error!("hash table with {} buckets is {} bytes long, too short for its bucket tables", bucket_count, data.len());
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let read_u32s = |start: usize| -> Result<Vec<u32>, io::Error> {
+            (0..bucket_count_usize)
+                .map(|i| u32::try_from_le_byte_slice(data, start + 4*i))
+                .collect()
+        };
+        let bucket_offsets = read_u32s(4)?;
+        let bucket_entry_counts = read_u32s(4 + 4*bucket_count_usize)?;
+
+        let entries = &data[entries_start..];
+        let mut buckets = Vec::with_capacity(bucket_count_usize);
+        for (offset, count) in bucket_offsets.iter().zip(bucket_entry_counts.iter()) {
+            let start: usize = (*offset).try_into().unwrap();
+            let length = usize::try_from(*count).unwrap() * 8;
+            let Some(bucket_bytes) = start.checked_add(length).and_then(|end| entries.get(start..end)) else {
+                error!("hash table bucket with {} entries at offset {:#X} exceeds the table", count, offset);
+                return Err(io::ErrorKind::InvalidData.into());
+            };
+            let bucket = bucket_bytes
+                .chunks(8)
+                .map(|chunk| Ok(HashTableEntry {
+                    symbol_offset: u32::try_from_le_byte_slice(chunk, 0)?,
+                    value: u32::try_from_le_byte_slice(chunk, 4)?,
+                }))
+                .collect::<Result<_, io::Error>>()?;
+            buckets.push(bucket);
+        }
+
+        Ok(Self {
+            bucket_count,
+            padding,
+            bucket_offsets,
+            bucket_entry_counts,
+            buckets,
+        })
+    }
+
+    /// Returns the index of the bucket in which a name hash is stored.
+    pub fn bucket_index_for_hash(&self, hash: u32) -> Option<usize> {
+        if self.bucket_count == 0 {
+            None
+        } else {
+            Some(usize::try_from(hash % u32::from(self.bucket_count)).unwrap())
+        }
+    }
+
+    /// Returns the bucket in which a name hash is stored.
+    pub fn bucket_for_hash(&self, hash: u32) -> Option<&[HashTableEntry]> {
+        self.bucket_index_for_hash(hash)
+            .and_then(|i| self.buckets.get(i))
+            .map(|b| b.as_slice())
+    }
+
+    /// Writes the hash table, placing the entries of each bucket at the stored offset.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.bucket_count.to_le_bytes())?;
+        writer.write_all(&self.padding.to_le_bytes())?;
+        for offset in &self.bucket_offsets {
+            writer.write_all(&offset.to_le_bytes())?;
+        }
+        for count in &self.bucket_entry_counts {
+            writer.write_all(&count.to_le_bytes())?;
+        }
+
+        let mut entries = Vec::new();
+        for (offset, bucket) in self.bucket_offsets.iter().zip(self.buckets.iter()) {
+            let mut bucket_bytes = Vec::with_capacity(8*bucket.len());
+            for entry in bucket {
+                bucket_bytes.extend_from_slice(&entry.symbol_offset.to_le_bytes());
+                bucket_bytes.extend_from_slice(&entry.value.to_le_bytes());
+            }
+
+            let start: usize = (*offset).try_into().unwrap();
+            let end = start + bucket_bytes.len();
+            if entries.len() < end {
+                entries.resize(end, 0x00);
+            }
+            entries[start..end].copy_from_slice(&bucket_bytes);
+        }
+        writer.write_all(&entries)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct HashTableEntry {
+    /// The offset of the symbol from the beginning of the symbol data.
+    pub symbol_offset: u32,
+
+    /// The hash of the symbol's name or the offset of the symbol within its segment, depending on
+    /// the table.
+    pub value: u32,
+}
+
+/// Hashes a symbol name using the function with index 0x000A.
+///
+/// The name is processed in little-endian 32-bit chunks with ASCII letters upper-cased; each chunk
+/// is XORed into the hash, which is then rotated left by 4 bits. The bytes of the final incomplete
+/// chunk, if any, are XORed in without rotation; like in the reference implementation, which
+/// collects them from the last one backwards and shifts after each, they end up one byte higher
+/// than in a little-endian chunk.
+pub fn dword_xor_rotate_hash(name: &[u8]) -> u32 {
+    const UPPER_CASE_MASK: u32 = 0xDFDF_DFDF;
+
+    let mut hash = 0u32;
+    let mut chunks = name.chunks_exact(4);
+    for chunk in &mut chunks {
+        hash ^= u32::from_le_byte_slice(chunk) & UPPER_CASE_MASK;
+        hash = hash.rotate_left(4);
+    }
+
+    let mut rest_buf = [0u8; 4];
+    let rest = chunks.remainder();
+    rest_buf[1..1+rest.len()].copy_from_slice(rest);
+    hash ^= u32::from_le_bytes(rest_buf) & UPPER_CASE_MASK;
+
+    hash
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -770,8 +1316,8 @@ impl GlobalTypesSubsection {
         reader.read_exact(&mut type_offsets_buf)?;
         let type_offsets: Vec<u32> = type_offsets_buf
             .chunks(4)
-            .map(|chunk| u32::from_le_byte_slice(chunk))
-            .collect();
+            .map(|chunk| u32::try_from_le_byte_slice(chunk, 0))
+            .collect::<Result<_, _>>()?;
 
         // TODO: NB07+NB08: offsets are from beginning of subsection table
         // NB09: offsets are from first type
@@ -801,6 +1347,45 @@ impl GlobalTypesSubsection {
             type_leaves,
         })
     }
+
+    /// Writes the subsection, placing each type record at the stored offset.
+    ///
+    /// Each type record is padded to a multiple of 4 bytes.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        if self.type_offsets.len() != self.type_leaves.len() {
+            error!("{} type offsets are stored for {} type leaves", self.type_offsets.len(), self.type_leaves.len());
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+
+        writer.write_all(&self.flags.to_le_bytes())?;
+        writer.write_all(&self.type_count.to_le_bytes())?;
+        for offset in &self.type_offsets {
+            writer.write_all(&offset.to_le_bytes())?;
+        }
+
+        let mut types = Vec::new();
+        for (offset, type_leaf) in self.type_offsets.iter().zip(self.type_leaves.iter()) {
+            let mut record = vec![0u8; 2];
+            type_leaf.write(&mut record)?;
+            let padding_bytes = (4 - record.len() % 4) % 4;
+            for i in (1..=padding_bytes).rev() {
+                record.push(0xF0 | u8::try_from(i).unwrap());
+            }
+
+            let length = u16::try_from(record.len() - 2)
+                .inspect_err(|_| error!("type record at offset {:#X} is {} bytes long, too long for its length field", offset, record.len() - 2))
+                .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+            record[0..2].copy_from_slice(&length.to_le_bytes());
+
+            let start: usize = (*offset).try_into().unwrap();
+            let end = start + record.len();
+            if types.len() < end {
+                types.resize(end, 0x00);
+            }
+            types[start..end].copy_from_slice(&record);
+        }
+        writer.write_all(&types)
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -828,13 +1413,13 @@ impl SegmentMapSubsection {
         let logical_segment_descriptors: Vec<SegmentDescriptor> = descriptor_buf
             .chunks(20)
             .take(logical_descriptor_count_usize)
-            .map(|chunk| SegmentDescriptor::from_byte_slice(chunk))
-            .collect();
+            .map(SegmentDescriptor::try_from_byte_slice)
+            .collect::<Result<_, _>>()?;
         let group_descriptors: Vec<SegmentDescriptor> = descriptor_buf
             .chunks(20)
             .skip(logical_descriptor_count_usize)
-            .map(|chunk| SegmentDescriptor::from_byte_slice(chunk))
-            .collect();
+            .map(SegmentDescriptor::try_from_byte_slice)
+            .collect::<Result<_, _>>()?;
 
         Ok(Self {
             descriptor_count,
@@ -843,6 +1428,15 @@ impl SegmentMapSubsection {
             group_descriptors,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.descriptor_count.to_le_bytes())?;
+        writer.write_all(&self.logical_descriptor_count.to_le_bytes())?;
+        for descriptor in self.logical_segment_descriptors.iter().chain(self.group_descriptors.iter()) {
+            writer.write_all(&descriptor.to_bytes())?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -858,23 +1452,20 @@ pub struct SegmentDescriptor {
     pub segment_count_bytes: u32,
 }
 impl SegmentDescriptor {
-    pub fn from_byte_slice(bytes: &[u8]) -> Self {
-        if bytes.len() != 20 {
-            panic!("byte slice has {} items, expected 20", bytes.len());
-        }
-
-        let flags_u16 = u16::from_le_byte_slice(&bytes[0..2]);
-        let logical_overlay_number = u16::from_le_byte_slice(&bytes[2..4]);
-        let descriptor_group_index = u16::from_le_byte_slice(&bytes[4..6]);
-        let frame = u16::from_le_byte_slice(&bytes[6..8]);
-        let segment_name_offset = u16::from_le_byte_slice(&bytes[8..10]);
-        let class_name_offset = u16::from_le_byte_slice(&bytes[10..12]);
-        let logical_segment_offset = u32::from_le_byte_slice(&bytes[12..16]);
-        let segment_count_bytes = u32::from_le_byte_slice(&bytes[16..20]);
+    /// Reads a segment descriptor from the first 20 bytes of the slice.
+    pub fn try_from_byte_slice(bytes: &[u8]) -> Result<Self, io::Error> {
+        let flags_u16 = u16::try_from_le_byte_slice(bytes, 0)?;
+        let logical_overlay_number = u16::try_from_le_byte_slice(bytes, 2)?;
+        let descriptor_group_index = u16::try_from_le_byte_slice(bytes, 4)?;
+        let frame = u16::try_from_le_byte_slice(bytes, 6)?;
+        let segment_name_offset = u16::try_from_le_byte_slice(bytes, 8)?;
+        let class_name_offset = u16::try_from_le_byte_slice(bytes, 10)?;
+        let logical_segment_offset = u32::try_from_le_byte_slice(bytes, 12)?;
+        let segment_count_bytes = u32::try_from_le_byte_slice(bytes, 16)?;
 
         let flags = SegmentDescriptorFlags::from_bits_retain(flags_u16);
 
-        Self {
+        Ok(Self {
             flags,
             logical_overlay_number,
             descriptor_group_index,
@@ -883,7 +1474,20 @@ impl SegmentDescriptor {
             class_name_offset,
             logical_segment_offset,
             segment_count_bytes,
-        }
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 20] {
+        let mut bytes = [0u8; 20];
+        bytes[0..2].copy_from_slice(&self.flags.bits().to_le_bytes());
+        bytes[2..4].copy_from_slice(&self.logical_overlay_number.to_le_bytes());
+        bytes[4..6].copy_from_slice(&self.descriptor_group_index.to_le_bytes());
+        bytes[6..8].copy_from_slice(&self.frame.to_le_bytes());
+        bytes[8..10].copy_from_slice(&self.segment_name_offset.to_le_bytes());
+        bytes[10..12].copy_from_slice(&self.class_name_offset.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.logical_segment_offset.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.segment_count_bytes.to_le_bytes());
+        bytes
     }
 }
 
@@ -904,6 +1508,225 @@ bitflags! {
     }
 }
 
+/// The names of the segments and their classes, referenced by byte offset from the segment
+/// descriptors in the [`SegmentMapSubsection`].
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct SegmentNameSubsection {
+    pub names: Vec<DisplayBytesVec>, // NUL-terminated, one after the other
+}
+impl SegmentNameSubsection {
+    #[instrument(skip_all)]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        let Some(last_terminator) = data.iter().rposition(|b| *b == 0x00) else {
+            if data.is_empty() {
+                return Ok(Self { names: Vec::new() });
+            }
+            error!("segment name subsection does not contain a single NUL-terminated name");
+            return Err(io::ErrorKind::InvalidData.into());
+        };
+        if last_terminator != data.len() - 1 {
+            error!("last segment name at offset {} is not NUL-terminated", last_terminator + 1);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+
+        let names = data[..last_terminator]
+            .split(|b| *b == 0x00)
+            .map(|name| DisplayBytesVec::from(name.to_vec()))
+            .collect();
+        Ok(Self {
+            names,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        for name in &self.names {
+            writer.write_all(name.as_ref())?;
+            writer.write_all(&[0x00])?;
+        }
+        Ok(())
+    }
+
+    /// The name starting at the given byte offset, as referenced by
+    /// [`SegmentDescriptor::segment_name_offset`] and [`SegmentDescriptor::class_name_offset`].
+    pub fn name_at(&self, offset: u16) -> Option<&[u8]> {
+        let mut name_offset = 0;
+        for name in &self.names {
+            if name_offset == usize::from(offset) {
+                return Some(name.as_ref());
+            }
+            let name_bytes: &[u8] = name.as_ref();
+            name_offset += name_bytes.len() + 1;
+        }
+        None
+    }
+}
+
+/// Where a logical segment, as referenced by the segment numbers in CodeView symbols, ends up in
+/// the executable.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct SegmentPlacement {
+    /// The 1-based number of the logical segment.
+    pub logical_segment: u16,
+
+    pub flags: SegmentDescriptorFlags,
+
+    /// The 1-based number of the PE section or NE segment containing the logical segment.
+    pub frame: u16,
+
+    /// The offset of the logical segment within its PE section or NE segment.
+    pub offset: u32,
+
+    pub length: u32,
+
+    pub name: Option<DisplayBytesVec>,
+    pub class_name: Option<DisplayBytesVec>,
+
+    /// The relative virtual address of the start of the logical segment; only known in PE images.
+    pub rva: Option<u32>,
+}
+
+/// The correlation of the logical segments of CodeView debug information with the sections of a PE
+/// image or the segments of an NE image.
+///
+/// CodeView symbols specify their addresses as logical segment and offset. If the debug information
+/// contains a [`SegmentMapSubsection`], it defines which section (or segment) and offset each
+/// logical segment corresponds to; otherwise, each logical segment is the section of the same
+/// number.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct SegmentLayout {
+    pub placements: Vec<SegmentPlacement>, // ordered by logical segment
+}
+impl SegmentLayout {
+    /// Derives the layout from the segment map, without correlating it with an executable.
+    pub fn from_segment_map(segment_map: &SegmentMapSubsection, segment_names: Option<&SegmentNameSubsection>) -> Self {
+        let name_at = |offset: u16| segment_names
+            .and_then(|names| names.name_at(offset))
+            .map(|name| DisplayBytesVec::from(name.to_vec()));
+        let placements = segment_map.logical_segment_descriptors.iter()
+            .zip(1..=u16::MAX)
+            .map(|(descriptor, logical_segment)| SegmentPlacement {
+                logical_segment,
+                flags: descriptor.flags,
+                frame: descriptor.frame,
+                offset: descriptor.logical_segment_offset,
+                length: descriptor.segment_count_bytes,
+                name: name_at(descriptor.segment_name_offset),
+                class_name: name_at(descriptor.class_name_offset),
+                rva: None,
+            })
+            .collect();
+        Self {
+            placements,
+        }
+    }
+
+    /// The layout assumed if there is no segment map: each logical segment is the frame of the
+    /// same number, with the given lengths.
+    fn identity<I: IntoIterator<Item = u32>>(frame_lengths: I) -> Self {
+        let placements = frame_lengths.into_iter()
+            .zip(1..=u16::MAX)
+            .map(|(length, number)| SegmentPlacement {
+                logical_segment: number,
+                flags: SegmentDescriptorFlags::empty(),
+                frame: number,
+                offset: 0,
+                length,
+                name: None,
+                class_name: None,
+                rva: None,
+            })
+            .collect();
+        Self {
+            placements,
+        }
+    }
+
+    /// Correlates the segment map (if any) with the section table of a PE image, calculating the
+    /// RVA of each logical segment.
+    ///
+    /// Logical segments with absolute addresses and those referencing a nonexistent section are
+    /// kept, but without an RVA.
+    pub fn for_pe(segment_map: Option<&SegmentMapSubsection>, segment_names: Option<&SegmentNameSubsection>, section_table: &SectionTable) -> Self {
+        let sections = section_table.as_entries();
+        let mut layout = match segment_map {
+            Some(map) => Self::from_segment_map(map, segment_names),
+            None => Self::identity(sections.iter().map(|s| s.virtual_size)),
+        };
+        for placement in &mut layout.placements {
+            if placement.flags.contains(SegmentDescriptorFlags::ABSOLUTE_ADDRESS) {
+                continue;
+            }
+            let Some(section) = usize::from(placement.frame).checked_sub(1).and_then(|i| sections.get(i)) else {
+                debug!("logical segment {} is in section {} of {}", placement.logical_segment, placement.frame, sections.len());
+                continue;
+            };
+            if u64::from(placement.offset) + u64::from(placement.length) > u64::from(section.virtual_size) {
+                debug!(
+                    "logical segment {} (offset {:#X}, length {:#X}) extends beyond the end of section {} ({:#X} bytes)",
+                    placement.logical_segment, placement.offset, placement.length, placement.frame, section.virtual_size,
+                );
+            }
+            placement.rva = section.virtual_address.checked_add(placement.offset);
+        }
+        layout
+    }
+
+    /// Correlates the segment map (if any) with the segment table of an NE image.
+    ///
+    /// NE images have no flat address space, so no RVAs are calculated; logical segments
+    /// referencing a nonexistent segment are kept.
+    pub fn for_ne(segment_map: Option<&SegmentMapSubsection>, segment_names: Option<&SegmentNameSubsection>, segment_table: &[SegmentTableEntry]) -> Self {
+        // a length of 0 means 64 KiB
+        let segment_length = |segment: &SegmentTableEntry| match segment.segment_length {
+            0 => 0x1_0000,
+            length => u32::from(length),
+        };
+        let layout = match segment_map {
+            Some(map) => Self::from_segment_map(map, segment_names),
+            None => Self::identity(segment_table.iter().map(segment_length)),
+        };
+        for placement in &layout.placements {
+            if placement.flags.contains(SegmentDescriptorFlags::ABSOLUTE_ADDRESS) {
+                continue;
+            }
+            match usize::from(placement.frame).checked_sub(1).and_then(|i| segment_table.get(i)) {
+                None => debug!("logical segment {} is in segment {} of {}", placement.logical_segment, placement.frame, segment_table.len()),
+                Some(segment) => if u64::from(placement.offset) + u64::from(placement.length) > u64::from(segment_length(segment)) {
+                    debug!("logical segment {} extends beyond the end of segment {}", placement.logical_segment, placement.frame);
+                },
+            }
+        }
+        layout
+    }
+
+    /// The placement of the logical segment with the given 1-based number.
+    pub fn placement(&self, logical_segment: u16) -> Option<&SegmentPlacement> {
+        let index = usize::from(logical_segment).checked_sub(1)?;
+        self.placements.get(index)
+    }
+
+    /// Translates a logical segment and offset into the 1-based number of the PE section or NE
+    /// segment and the offset within it.
+    pub fn resolve(&self, logical_segment: u16, offset: u32) -> Option<(u16, u32)> {
+        let placement = self.placement(logical_segment)?;
+        Some((placement.frame, placement.offset.checked_add(offset)?))
+    }
+
+    /// Translates a logical segment and offset into an RVA, if the layout has been correlated with
+    /// a PE image.
+    pub fn rva(&self, logical_segment: u16, offset: u32) -> Option<u32> {
+        self.placement(logical_segment)?
+            .rva?
+            .checked_add(offset)
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct FileIndexSubsection {
@@ -930,22 +1753,22 @@ impl FileIndexSubsection {
         reader.read_exact(&mut module_start_indexes_buf)?;
         let module_start_indexes: Vec<u16> = module_start_indexes_buf
             .chunks(2)
-            .map(|chunk| u16::from_le_byte_slice(chunk))
-            .collect();
+            .map(|chunk| u16::try_from_le_byte_slice(chunk, 0))
+            .collect::<Result<_, _>>()?;
 
         let mut file_name_reference_count_per_module_buf = vec![0u8; 2*module_count_usize];
         reader.read_exact(&mut file_name_reference_count_per_module_buf)?;
         let file_name_reference_count_per_module: Vec<u16> = file_name_reference_count_per_module_buf
             .chunks(2)
-            .map(|chunk| u16::from_le_byte_slice(chunk))
-            .collect();
+            .map(|chunk| u16::try_from_le_byte_slice(chunk, 0))
+            .collect::<Result<_, _>>()?;
 
         let mut name_reference_offsets_buf = vec![0u8; 4*name_reference_count_usize];
         reader.read_exact(&mut name_reference_offsets_buf)?;
         let name_reference_offsets: Vec<u32> = name_reference_offsets_buf
             .chunks(4)
-            .map(|chunk| u32::from_le_byte_slice(chunk))
-            .collect();
+            .map(|chunk| u32::try_from_le_byte_slice(chunk, 0))
+            .collect::<Result<_, _>>()?;
 
         let name_base = reader.seek(SeekFrom::Current(0))?;
 
@@ -965,4 +1788,253 @@ impl FileIndexSubsection {
             names,
         })
     }
+
+    /// Writes the subsection, placing each name at the stored offset.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        if self.name_reference_offsets.len() != self.names.len() {
+            error!("{} name offsets are stored for {} names", self.name_reference_offsets.len(), self.names.len());
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+
+        writer.write_all(&self.module_count.to_le_bytes())?;
+        writer.write_all(&self.name_reference_count.to_le_bytes())?;
+        for index in &self.module_start_indexes {
+            writer.write_all(&index.to_le_bytes())?;
+        }
+        for count in &self.file_name_reference_count_per_module {
+            writer.write_all(&count.to_le_bytes())?;
+        }
+        for offset in &self.name_reference_offsets {
+            writer.write_all(&offset.to_le_bytes())?;
+        }
+
+        let mut names = Vec::new();
+        for (offset, name) in self.name_reference_offsets.iter().zip(self.names.iter()) {
+            let mut name_bytes = Vec::new();
+            write_pascal_byte_string(&mut name_bytes, name.as_ref())?;
+
+            let start: usize = (*offset).try_into().unwrap();
+            let end = start + name_bytes.len();
+            if names.len() < end {
+                names.resize(end, 0x00);
+            }
+            names[start..end].copy_from_slice(&name_bytes);
+        }
+        writer.write_all(&names)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use crate::pe::{SectionTable, SectionTableEntry};
+    use super::{
+        dword_xor_rotate_hash, AddressHashTable, DebugInfo, GlobalSymbolsSubsection, SegmentLayout,
+        SegmentMapSubsection, SegmentNameSubsection, SubsectionData, SymbolHashTable,
+    };
+
+    fn public_symbol(offset: u32, name: &[u8]) -> Vec<u8> {
+        let length = u16::try_from(2 + 4 + 2 + 2 + 1 + name.len()).unwrap();
+        let mut ret = Vec::new();
+        ret.extend_from_slice(&length.to_le_bytes());
+        ret.extend_from_slice(&0x0203u16.to_le_bytes()); // S_PUB32
+        ret.extend_from_slice(&offset.to_le_bytes());
+        ret.extend_from_slice(&1u16.to_le_bytes()); // segment
+        ret.extend_from_slice(&0u16.to_le_bytes()); // type
+        ret.push(u8::try_from(name.len()).unwrap());
+        ret.extend_from_slice(name);
+        ret
+    }
+
+    fn hash_table(buckets: &[&[(u32, u32)]]) -> Vec<u8> {
+        let mut ret = Vec::new();
+        ret.extend_from_slice(&u16::try_from(buckets.len()).unwrap().to_le_bytes());
+        ret.extend_from_slice(&0u16.to_le_bytes());
+        let mut offset = 0u32;
+        for bucket in buckets {
+            ret.extend_from_slice(&offset.to_le_bytes());
+            offset += u32::try_from(bucket.len() * 8).unwrap();
+        }
+        for bucket in buckets {
+            ret.extend_from_slice(&u32::try_from(bucket.len()).unwrap().to_le_bytes());
+        }
+        for (symbol_offset, value) in buckets.iter().flat_map(|b| b.iter()) {
+            ret.extend_from_slice(&symbol_offset.to_le_bytes());
+            ret.extend_from_slice(&value.to_le_bytes());
+        }
+        ret
+    }
+
+    fn global_symbols_subsection() -> Vec<u8> {
+        let mut symbols = public_symbol(0x10, b"main");
+        let sqrt_offset = u32::try_from(symbols.len()).unwrap();
+        symbols.extend(public_symbol(0x20, b"sqrt"));
+
+        let main_hash = dword_xor_rotate_hash(b"main");
+        let sqrt_hash = dword_xor_rotate_hash(b"sqrt");
+        assert_eq!(main_hash % 2, 0);
+        assert_eq!(sqrt_hash % 2, 1);
+        let symbol_hash_table = hash_table(&[&[(0, main_hash)], &[(sqrt_offset, sqrt_hash)]]);
+        let address_hash_table = hash_table(&[&[(0, 0x10), (sqrt_offset, 0x20)]]);
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&0x000Au16.to_le_bytes());
+        data.extend_from_slice(&0x000Cu16.to_le_bytes());
+        data.extend_from_slice(&u32::try_from(symbols.len()).unwrap().to_le_bytes());
+        data.extend_from_slice(&u32::try_from(symbol_hash_table.len()).unwrap().to_le_bytes());
+        data.extend_from_slice(&u32::try_from(address_hash_table.len()).unwrap().to_le_bytes());
+        data.extend_from_slice(&symbols);
+        data.extend_from_slice(&symbol_hash_table);
+        data.extend_from_slice(&address_hash_table);
+        data
+    }
+
+    fn global_types_subsection() -> Vec<u8> {
+        let records: [&[u8]; 3] = [
+            // LF_ARGLIST (int)
+            b"\x06\x00\x01\x02\x01\x00\x74\x00",
+            // LF_PROCEDURE returning void with the argument list above, C calling convention
+            b"\x0A\x00\x08\x00\x03\x00\x00\x00\x01\x00\x00\x10",
+            // LF_FIELDLIST with LF_ENUMERATE AB = 1, padded
+            b"\x0E\x00\x04\x02\x03\x04\x03\x00\x01\x00\x02AB\xF3\xF2\xF1",
+        ];
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&0u32.to_le_bytes()); // flags
+        data.extend_from_slice(&u32::try_from(records.len()).unwrap().to_le_bytes());
+        let mut offset = 0u32;
+        for record in &records {
+            data.extend_from_slice(&offset.to_le_bytes());
+            offset += u32::try_from(record.len()).unwrap();
+        }
+        for record in &records {
+            data.extend_from_slice(record);
+        }
+        data
+    }
+
+    #[test]
+    fn test_dword_xor_rotate_hash() {
+        assert_eq!(dword_xor_rotate_hash(b""), 0);
+        assert_eq!(dword_xor_rotate_hash(b"main"), 0xE494_14D4);
+        assert_eq!(dword_xor_rotate_hash(b"main"), dword_xor_rotate_hash(b"MAIN"));
+
+        // the bytes after the last full chunk are shifted up by a byte, with the first of them in
+        // bits 8 to 15
+        assert_eq!(dword_xor_rotate_hash(b"x"), 0x0000_5800);
+        assert_eq!(dword_xor_rotate_hash(b"abc"), 0x4342_4100);
+        assert_eq!(dword_xor_rotate_hash(b"printf"), 0xE4D3_7104);
+        assert_eq!(dword_xor_rotate_hash(b"_exit"), 0x9584_01F4);
+    }
+
+    #[test]
+    fn test_global_symbol_hash_tables() {
+        let data = global_symbols_subsection();
+
+        let subsection = GlobalSymbolsSubsection::read(&mut Cursor::new(&data)).unwrap();
+        assert!(matches!(subsection.symbol_hash_table, SymbolHashTable::DwordXorRotate(_)));
+        assert!(matches!(subsection.address_hash_table, AddressHashTable::SortedOffsets(_)));
+        assert_eq!(subsection.validate_hash_tables(), Vec::<String>::new());
+
+        let found = subsection.find_symbols_by_name(b"sqrt");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0], &subsection.symbols[1]);
+        assert!(subsection.find_symbols_by_name(b"SQRT").is_empty());
+        assert!(subsection.find_symbols_by_name(b"nope").is_empty());
+    }
+    #[test]
+    fn test_segment_layout() {
+        // flags, overlay, group, frame, name offset, class name offset, offset, length
+        let descriptors: [(u16, u16, u32, u32); 3] = [
+            (1, 0, 0x0000, 0x1800), // _TEXT in .text
+            (1, 11, 0x1800, 0x0200), // _TEXT$mn in .text
+            (2, 20, 0x0010, 0x0100), // _DATA in .data
+        ];
+        let mut data = Vec::new();
+        data.extend_from_slice(&3u16.to_le_bytes());
+        data.extend_from_slice(&3u16.to_le_bytes());
+        for (frame, name_offset, offset, length) in descriptors {
+            data.extend_from_slice(&0x0109u16.to_le_bytes()); // READ | LINEAR_ADDRESS_32 | SELECTOR
+            data.extend_from_slice(&0u16.to_le_bytes());
+            data.extend_from_slice(&0u16.to_le_bytes());
+            data.extend_from_slice(&frame.to_le_bytes());
+            data.extend_from_slice(&name_offset.to_le_bytes());
+            data.extend_from_slice(&6u16.to_le_bytes());
+            data.extend_from_slice(&offset.to_le_bytes());
+            data.extend_from_slice(&length.to_le_bytes());
+        }
+        let segment_map = SegmentMapSubsection::read(&mut Cursor::new(&data)).unwrap();
+        let segment_names = SegmentNameSubsection::read(&mut Cursor::new(b"_TEXT\0CODE\0_TEXT$mn\0_DATA\0")).unwrap();
+
+        let section_table = SectionTable::from(vec![
+            SectionTableEntry { virtual_size: 0x1A00, virtual_address: 0x1000, ..Default::default() },
+            SectionTableEntry { virtual_size: 0x0200, virtual_address: 0x3000, ..Default::default() },
+        ]);
+        let layout = SegmentLayout::for_pe(Some(&segment_map), Some(&segment_names), &section_table);
+        assert_eq!(layout.placements.len(), 3);
+        assert_eq!(layout.placements[1].name.as_ref().map(|n| n.as_ref()), Some(&b"_TEXT$mn"[..]));
+        assert_eq!(layout.placements[1].class_name.as_ref().map(|n| n.as_ref()), Some(&b"CODE"[..]));
+        assert_eq!(layout.rva(1, 0x20), Some(0x1020));
+        assert_eq!(layout.rva(2, 0x20), Some(0x2820));
+        assert_eq!(layout.resolve(2, 0x20), Some((1, 0x1820)));
+        assert_eq!(layout.rva(3, 0x04), Some(0x3014));
+        assert_eq!(layout.rva(4, 0x04), None);
+
+        // without a segment map, logical segments are sections
+        let layout = SegmentLayout::for_pe(None, None, &section_table);
+        assert_eq!(layout.rva(2, 0x20), Some(0x3020));
+    }
+
+    /// Debug info with global symbols, global types, libraries and segment names.
+    fn debug_info() -> Vec<u8> {
+        let subsections = [
+            (0x0129u16, global_symbols_subsection()),
+            (0x012B, global_types_subsection()),
+            (0x0128, b"\x08LIBC.LIB\x0cKERNEL32.LIB".to_vec()),
+            (0x012E, b"_TEXT\0CODE\0".to_vec()),
+        ];
+
+        let mut data = b"NB09\0\0\0\0".to_vec();
+        let mut directory = Vec::new();
+        for (subsection_type, subsection) in &subsections {
+            directory.extend_from_slice(&subsection_type.to_le_bytes());
+            directory.extend_from_slice(&0xFFFFu16.to_le_bytes()); // module index
+            directory.extend_from_slice(&u32::try_from(data.len()).unwrap().to_le_bytes());
+            directory.extend_from_slice(&u32::try_from(subsection.len()).unwrap().to_le_bytes());
+            data.extend_from_slice(subsection);
+        }
+        let directory_offset = u32::try_from(data.len()).unwrap();
+        data[4..8].copy_from_slice(&directory_offset.to_le_bytes());
+        data.extend_from_slice(&16u16.to_le_bytes()); // header length
+        data.extend_from_slice(&12u16.to_le_bytes()); // entry length
+        data.extend_from_slice(&u32::try_from(subsections.len()).unwrap().to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes()); // next directory offset
+        data.extend_from_slice(&0u32.to_le_bytes()); // flags
+        data.extend_from_slice(&directory);
+        data
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let data = debug_info();
+        let debug_info = DebugInfo::read(&mut Cursor::new(&data)).unwrap();
+        assert!(matches!(debug_info.subsection_directory_entries[0].data, SubsectionData::GlobalSymbols(_)));
+        assert!(matches!(debug_info.subsection_directory_entries[1].data, SubsectionData::GlobalTypes(_)));
+        assert!(matches!(debug_info.subsection_directory_entries[2].data, SubsectionData::Libraries(_)));
+        assert_eq!(debug_info.segment_names().unwrap().name_at(6), Some(&b"CODE"[..]));
+
+        let mut written = Cursor::new(Vec::new());
+        debug_info.write(&mut written).unwrap();
+        assert_eq!(written.into_inner(), data);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let debug_info = DebugInfo::read(&mut Cursor::new(debug_info())).unwrap();
+        let json = serde_json::to_value(&debug_info).unwrap();
+        let read_back: DebugInfo = serde_json::from_value(json).unwrap();
+        assert_eq!(read_back, debug_info);
+    }
 }
@@ -1,7 +1,7 @@
 //! Entries in symbol tables in the CodeView debugging format.
 
 
-use std::io::{self, Cursor, Read, Seek};
+use std::io::{self, Cursor, Read, Seek, Write};
 
 use bitflags::bitflags;
 use display_bytes::DisplayBytesVec;
@@ -10,7 +10,7 @@ use from_to_repr::from_to_other;
 use serde::{Deserialize, Serialize};
 use tracing::error;
 
-use crate::read_pascal_byte_string;
+use crate::{read_pascal_byte_string, write_pascal_byte_string};
 use crate::code_view::leaves::NumericLeaf;
 use crate::int_from_byte_slice::IntFromByteSlice;
 
@@ -48,6 +48,23 @@ impl SymbolEntry {
             data,
         })
     }
+
+    /// Writes the symbol entry, padding the data with zero bytes up to the stored length.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        let mut data_buf = Vec::new();
+        self.data.write(&mut data_buf)?;
+
+        let data_length = usize::from(self.length.saturating_sub(2));
+        if self.length < 2 || data_buf.len() > data_length {
+            error!("symbol entry data is {} bytes long but the entry length is {}", data_buf.len(), self.length);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        data_buf.resize(data_length, 0x00);
+
+        writer.write_all(&self.length.to_le_bytes())?;
+        writer.write_all(&self.kind.to_base_type().to_le_bytes())?;
+        writer.write_all(&data_buf)
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -361,6 +378,98 @@ impl SymbolEntryData {
             },
         }
     }
+
+    /// Returns the name of the symbol, if this kind of symbol has one.
+    pub fn name(&self) -> Option<&DisplayBytesVec> {
+        match self {
+            Self::RegisterVariable(d) => Some(&d.name),
+            Self::Constant(d) => Some(&d.name),
+            Self::UserDefinedType(d) => Some(&d.name),
+            Self::ObjectName(d) => Some(&d.name),
+            Self::MicrofocusCobolUserDefinedType(d) => Some(&d.name),
+            Self::ManyRegisters(d) => Some(&d.name),
+            Self::BpRelative16_16(d) => Some(&d.name),
+            Self::LocalData16_16(d) => Some(&d.name),
+            Self::GlobalData16_16(d) => Some(&d.name),
+            Self::PublicSymbol16_16(d) => Some(&d.name),
+            Self::LocalProcedure16_16(d) => Some(&d.name),
+            Self::GlobalProcedure16_16(d) => Some(&d.name),
+            Self::Thunk16_16(d) => Some(&d.name),
+            Self::Block16_16(d) => Some(&d.name),
+            Self::With16_16(d) => Some(&d.name),
+            Self::Label16_16(d) => Some(&d.name),
+            Self::RegisterRelativeOffset16_16(d) => Some(&d.name),
+            Self::BpRelative16_32(d) => Some(&d.name),
+            Self::LocalData16_32(d) => Some(&d.name),
+            Self::GlobalData16_32(d) => Some(&d.name),
+            Self::PublicSymbol16_32(d) => Some(&d.name),
+            Self::LocalProcedure16_32(d) => Some(&d.name),
+            Self::GlobalProcedure16_32(d) => Some(&d.name),
+            Self::Thunk16_32(d) => Some(&d.name),
+            Self::Block16_32(d) => Some(&d.name),
+            Self::With16_32(d) => Some(&d.name),
+            Self::Label16_32(d) => Some(&d.name),
+            Self::RegisterRelativeOffset16_32(d) => Some(&d.name),
+            Self::LocalThreadData16_32(d) => Some(&d.name),
+            Self::GlobalThreadData16_32(d) => Some(&d.name),
+            Self::LocalProcedureMips(d) => Some(&d.name),
+            Self::GlobalProcedureMips(d) => Some(&d.name),
+            _ => None,
+        }
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        match self {
+            Self::CompileFlags(data) => data.write(writer),
+            Self::RegisterVariable(data) => data.write(writer),
+            Self::Constant(data) => data.write(writer),
+            Self::UserDefinedType(data) => data.write(writer),
+            Self::StartSearch(data) => data.write(writer),
+            Self::End => Ok(()),
+            Self::Skip(data) => data.write(writer),
+            Self::CodeViewReserved(data) => writer.write_all(data.as_ref()),
+            Self::ObjectName(data) => data.write(writer),
+            Self::EndArguments => Ok(()),
+            Self::MicrofocusCobolUserDefinedType(data) => data.write(writer),
+            Self::ManyRegisters(data) => data.write(writer),
+            Self::ReturnDescription(data) => data.write(writer),
+            Self::EntryThisPointer(data) => data.write(writer),
+            Self::BpRelative16_16(data) => data.write(writer),
+            Self::LocalData16_16(data) => data.write(writer),
+            Self::GlobalData16_16(data) => data.write(writer),
+            Self::PublicSymbol16_16(data) => data.write(writer),
+            Self::LocalProcedure16_16(data) => data.write(writer),
+            Self::GlobalProcedure16_16(data) => data.write(writer),
+            Self::Thunk16_16(data) => data.write(writer),
+            Self::Block16_16(data) => data.write(writer),
+            Self::With16_16(data) => data.write(writer),
+            Self::Label16_16(data) => data.write(writer),
+            Self::ChangeExecutionModel16_16(data) => data.write(writer),
+            Self::VirtualFunctionTablePath16_16(data) => data.write(writer),
+            Self::RegisterRelativeOffset16_16(data) => data.write(writer),
+            Self::BpRelative16_32(data) => data.write(writer),
+            Self::LocalData16_32(data) => data.write(writer),
+            Self::GlobalData16_32(data) => data.write(writer),
+            Self::PublicSymbol16_32(data) => data.write(writer),
+            Self::LocalProcedure16_32(data) => data.write(writer),
+            Self::GlobalProcedure16_32(data) => data.write(writer),
+            Self::Thunk16_32(data) => data.write(writer),
+            Self::Block16_32(data) => data.write(writer),
+            Self::With16_32(data) => data.write(writer),
+            Self::Label16_32(data) => data.write(writer),
+            Self::ChangeExecutionModel16_32(data) => data.write(writer),
+            Self::VirtualFunctionTablePath16_32(data) => data.write(writer),
+            Self::RegisterRelativeOffset16_32(data) => data.write(writer),
+            Self::LocalThreadData16_32(data) => data.write(writer),
+            Self::GlobalThreadData16_32(data) => data.write(writer),
+            Self::LocalProcedureMips(data) => data.write(writer),
+            Self::GlobalProcedureMips(data) => data.write(writer),
+            Self::ProcedureReference(data) => data.write(writer),
+            Self::DataReference(data) => data.write(writer),
+            Self::PageAlignment(data) => data.write(writer),
+            Self::Other(data) => writer.write_all(data.as_ref()),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -427,6 +536,22 @@ impl CompileFlags {
             version,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        let flags_u24: u32 =
+            u32::from(self.language.to_base_type())
+            | (if self.p_code_present { 1 << 8 } else { 0 })
+            | (u32::from(self.float_precision.to_base_type() & 0b11) << 9)
+            | (u32::from(self.float_package.to_base_type() & 0b11) << 11)
+            | (u32::from(self.ambient_data.to_base_type() & 0b111) << 13)
+            | (u32::from(self.ambient_code.to_base_type() & 0b111) << 16)
+            | (if self.mode_32 { 1 << 19 } else { 0 })
+            | (u32::from(self.reserved & 0b1111) << 20)
+        ;
+        let flags_bytes = flags_u24.to_le_bytes();
+        writer.write_all(&[self.machine.to_base_type(), flags_bytes[0], flags_bytes[1], flags_bytes[2]])?;
+        write_pascal_byte_string(writer, self.version.as_ref())
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -537,6 +662,13 @@ impl RegisterVariable {
             tracking,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.value_type.to_le_bytes())?;
+        writer.write_all(&self.register.to_le_bytes())?;
+        write_pascal_byte_string(writer, self.name.as_ref())?;
+        writer.write_all(self.tracking.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -563,6 +695,12 @@ impl Constant {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.value_type.to_le_bytes())?;
+        self.value.write(writer)?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -586,6 +724,11 @@ impl UserDefinedType {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.value_type.to_le_bytes())?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -607,6 +750,11 @@ impl StartSearch {
             segment,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.symbol_offset.to_le_bytes())?;
+        writer.write_all(&self.segment.to_le_bytes())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -624,6 +772,10 @@ impl Skip {
             skip_data,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(self.skip_data.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -647,6 +799,11 @@ impl ObjectName {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.signature.to_le_bytes())?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -677,6 +834,16 @@ impl ManyRegisters {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        let count = u8::try_from(self.registers.len())
+            .inspect_err(|_| error!("{} registers are more than fit into a ManyRegisters entry", self.registers.len()))
+            .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+        writer.write_all(&self.value_type.to_le_bytes())?;
+        writer.write_all(&[count])?;
+        writer.write_all(&self.registers)?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -707,6 +874,12 @@ impl ReturnDescription {
             data,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.function_flags.bits().to_le_bytes())?;
+        writer.write_all(&[self.return_style.to_base_type()])?;
+        writer.write_all(self.data.as_ref())
+    }
 }
 
 bitflags! {
@@ -745,6 +918,10 @@ impl EntryThisPointer {
             symbol,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        write_pascal_byte_string(writer, self.symbol.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -773,6 +950,12 @@ impl<T: IntFromByteSlice> BpRelative16<T> {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.offset.to_le_byte_vec())?;
+        writer.write_all(&self.value_type.to_le_bytes())?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -804,6 +987,13 @@ impl<T: IntFromByteSlice> Data16<T> {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.offset.to_le_byte_vec())?;
+        writer.write_all(&self.segment.to_le_bytes())?;
+        writer.write_all(&self.value_type.to_le_bytes())?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -861,6 +1051,20 @@ impl<T: IntFromByteSlice> ProcedureStart16<T> {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.parent_scope.to_le_bytes())?;
+        writer.write_all(&self.scope_end.to_le_bytes())?;
+        writer.write_all(&self.next_scope.to_le_bytes())?;
+        writer.write_all(&self.proc_length.to_le_byte_vec())?;
+        writer.write_all(&self.debug_start.to_le_byte_vec())?;
+        writer.write_all(&self.debug_end.to_le_byte_vec())?;
+        writer.write_all(&self.offset.to_le_byte_vec())?;
+        writer.write_all(&self.segment.to_le_bytes())?;
+        writer.write_all(&self.procedure_type.to_le_bytes())?;
+        writer.write_all(&[self.flags.bits()])?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 bitflags! {
@@ -926,6 +1130,18 @@ impl<T: IntFromByteSlice> Thunk16<T> {
             variant,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.parent_scope.to_le_bytes())?;
+        writer.write_all(&self.scope_end.to_le_bytes())?;
+        writer.write_all(&self.next_scope.to_le_bytes())?;
+        writer.write_all(&self.offset.to_le_byte_vec())?;
+        writer.write_all(&self.segment.to_le_bytes())?;
+        writer.write_all(&self.length.to_le_bytes())?;
+        writer.write_all(&[self.thunk_type.to_base_type()])?;
+        write_pascal_byte_string(writer, self.name.as_ref())?;
+        writer.write_all(self.variant.as_ref())
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -978,6 +1194,15 @@ impl<T: IntFromByteSlice> BlockStart16<T> {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.parent_scope.to_le_bytes())?;
+        writer.write_all(&self.scope_end.to_le_bytes())?;
+        writer.write_all(&self.length.to_le_byte_vec())?;
+        writer.write_all(&self.offset.to_le_byte_vec())?;
+        writer.write_all(&self.segment.to_le_bytes())?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1011,6 +1236,13 @@ impl<T: IntFromByteSlice> Label16<T> {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.offset.to_le_byte_vec())?;
+        writer.write_all(&self.segment.to_le_bytes())?;
+        writer.write_all(&[self.flags.bits()])?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1045,6 +1277,13 @@ impl<T: IntFromByteSlice> ChangeExecutionModel16<T> {
             variant,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.offset.to_le_byte_vec())?;
+        writer.write_all(&self.segment.to_le_bytes())?;
+        writer.write_all(&self.new_execution_model.to_base_type().to_le_bytes())?;
+        writer.write_all(self.variant.as_ref())
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -1090,6 +1329,13 @@ impl<T: IntFromByteSlice> VirtualFunctionTablePath16<T> {
             path,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.offset.to_le_byte_vec())?;
+        writer.write_all(&self.segment.to_le_bytes())?;
+        writer.write_all(&self.root.to_le_bytes())?;
+        writer.write_all(&self.path.to_le_bytes())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1122,6 +1368,13 @@ impl<T: IntFromByteSlice> RegisterRelativeOffset16<T> {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.offset.to_le_byte_vec())?;
+        writer.write_all(&self.register.to_le_bytes())?;
+        writer.write_all(&self.value_type.to_le_bytes())?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1151,6 +1404,13 @@ impl ThreadData16_32 {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.offset.to_le_bytes())?;
+        writer.write_all(&self.segment.to_le_bytes())?;
+        writer.write_all(&self.value_type.to_le_bytes())?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1216,6 +1476,24 @@ impl ProcedureMips {
             name,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.parent_scope.to_le_bytes())?;
+        writer.write_all(&self.scope_end.to_le_bytes())?;
+        writer.write_all(&self.next_scope.to_le_bytes())?;
+        writer.write_all(&self.length.to_le_bytes())?;
+        writer.write_all(&self.debug_start.to_le_bytes())?;
+        writer.write_all(&self.debug_end.to_le_bytes())?;
+        writer.write_all(&self.int_save_mask.to_le_bytes())?;
+        writer.write_all(&self.float_save_mask.to_le_bytes())?;
+        writer.write_all(&self.int_save_offset.to_le_bytes())?;
+        writer.write_all(&self.float_save_offset.to_le_bytes())?;
+        writer.write_all(&self.offset.to_le_bytes())?;
+        writer.write_all(&self.segment.to_le_bytes())?;
+        writer.write_all(&self.procedure_type.to_le_bytes())?;
+        writer.write_all(&[self.return_register, self.frame_pointer_register])?;
+        write_pascal_byte_string(writer, self.name.as_ref())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1240,6 +1518,12 @@ impl CodeViewPackReference {
             module,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.checksum.to_le_bytes())?;
+        writer.write_all(&self.offset.to_le_bytes())?;
+        writer.write_all(&self.module.to_le_bytes())
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1257,4 +1541,8 @@ impl PageAlignment {
             padding,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(self.padding.as_ref())
+    }
 }
@@ -0,0 +1,421 @@
+//! COFF symbol tables and line numbers.
+//!
+//! Before CodeView took over, Microsoft linkers emitted debug information in the COFF format. It
+//! is still found in the debug directory of NT 3.x and NT 4 era executables and their separate
+//! `.dbg` files, where it is prefixed by the following header:
+//!
+//! ```plain
+//! struct CoffSymbolsHeader {
+//!     pub symbol_count: u32,
+//!     pub first_symbol_offset: u32, // relative to the start of this header
+//!     pub line_number_count: u32,
+//!     pub first_line_number_offset: u32, // relative to the start of this header
+//!     pub first_code_rva: u32,
+//!     pub last_code_rva: u32,
+//!     pub first_data_rva: u32,
+//!     pub last_data_rva: u32,
+//! }
+//! ```
+//!
+//! The string table, which stores symbol names longer than eight bytes, immediately follows the
+//! symbol table.
+
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use display_bytes::DisplayBytesVec;
+use from_to_repr::from_to_other;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::int_from_byte_slice::IntFromByteSlice;
+
+
+const SYMBOL_SIZE: usize = 18;
+const LINE_NUMBER_SIZE: usize = 6;
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct CoffDebugInfo {
+    pub header: CoffSymbolsHeader,
+    pub symbols: Vec<CoffSymbol>, // [CoffSymbol; header.symbol_count - total auxiliary record count]
+    pub line_numbers: Vec<CoffLineNumber>, // [CoffLineNumber; header.line_number_count]
+}
+impl CoffDebugInfo {
+    /// Reads COFF debug information, starting with the header.
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let start = reader.stream_position()?;
+        let header = CoffSymbolsHeader::read(reader)?;
+
+        reader.seek(SeekFrom::Start(start + u64::from(header.first_symbol_offset)))?;
+        let symbols = read_symbol_table(reader, header.symbol_count)?;
+
+        // the count is not to be trusted; let the reads run into the end of the data instead
+        let mut line_numbers = Vec::new();
+        if header.line_number_count > 0 {
+            reader.seek(SeekFrom::Start(start + u64::from(header.first_line_number_offset)))?;
+            for _ in 0..header.line_number_count {
+                line_numbers.push(CoffLineNumber::read(reader)?);
+            }
+        }
+
+        Ok(Self {
+            header,
+            symbols,
+            line_numbers,
+        })
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct CoffSymbolsHeader {
+    pub symbol_count: u32,
+    pub first_symbol_offset: u32,
+    pub line_number_count: u32,
+    pub first_line_number_offset: u32,
+    pub first_code_rva: u32,
+    pub last_code_rva: u32,
+    pub first_data_rva: u32,
+    pub last_data_rva: u32,
+}
+impl CoffSymbolsHeader {
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut buf = [0u8; 32];
+        reader.read_exact(&mut buf)?;
+
+        Ok(Self {
+            symbol_count: u32::from_le_byte_slice(&buf[0..4]),
+            first_symbol_offset: u32::from_le_byte_slice(&buf[4..8]),
+            line_number_count: u32::from_le_byte_slice(&buf[8..12]),
+            first_line_number_offset: u32::from_le_byte_slice(&buf[12..16]),
+            first_code_rva: u32::from_le_byte_slice(&buf[16..20]),
+            last_code_rva: u32::from_le_byte_slice(&buf[20..24]),
+            first_data_rva: u32::from_le_byte_slice(&buf[24..28]),
+            last_data_rva: u32::from_le_byte_slice(&buf[28..32]),
+        })
+    }
+}
+
+
+/// Reads a COFF symbol table, including the string table that follows it.
+///
+/// `record_count` is the number of 18-byte records, including auxiliary records.
+pub fn read_symbol_table<R: Read>(reader: &mut R, record_count: u32) -> Result<Vec<CoffSymbol>, io::Error> {
+    let record_count_usize = usize::try_from(record_count).unwrap();
+    let records_length = record_count_usize.checked_mul(SYMBOL_SIZE)
+        .ok_or(io::ErrorKind::InvalidData)
+        .inspect_err(|_| error!("COFF symbol table with {} records is too large", record_count))?;
+
+    // only allocate as much as is actually there
+    let mut records = Vec::new();
+    reader.by_ref()
+        .take(u64::try_from(records_length).unwrap())
+        .read_to_end(&mut records)?;
+    if records.len() != records_length {
+        error!("COFF symbol table has {} records but only {} bytes", record_count, records.len());
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    // the string table follows; its length includes the length field itself
+    let mut string_table_length_buf = [0u8; 4];
+    let string_table = match reader.read_exact(&mut string_table_length_buf) {
+        Ok(()) => {
+            let string_table_length = u32::from_le_bytes(string_table_length_buf).saturating_sub(4);
+            let mut string_table = Vec::new();
+            reader.by_ref()
+                .take(u64::from(string_table_length))
+                .read_to_end(&mut string_table)?;
+            if string_table.len() != usize::try_from(string_table_length).unwrap() {
+                error!("COFF string table has {} bytes but only {} bytes remain", string_table_length, string_table.len());
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            string_table
+        },
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Vec::new(),
+        Err(e) => return Err(e),
+    };
+
+    let mut symbols = Vec::new();
+    let mut index = 0;
+    while index < record_count_usize {
+        let record = &records[index*SYMBOL_SIZE..(index+1)*SYMBOL_SIZE];
+        let name_bytes = if record[0..4] == [0, 0, 0, 0] {
+            // offset into the string table (which counts the length field)
+            let offset = usize::try_from(u32::from_le_byte_slice(&record[4..8])).unwrap();
+            let Some(string_start) = offset.checked_sub(4).and_then(|o| string_table.get(o..)) else {
+                error!("COFF symbol {} name offset {} is outside the string table", index, offset);
+                return Err(io::ErrorKind::InvalidData.into());
+            };
+            let nul_pos = string_start.iter()
+                .position(|b| *b == 0x00)
+                .unwrap_or(string_start.len());
+            string_start[..nul_pos].to_vec()
+        } else {
+            let nul_pos = record[0..8].iter()
+                .position(|b| *b == 0x00)
+                .unwrap_or(8);
+            record[0..nul_pos].to_vec()
+        };
+        let value = u32::from_le_byte_slice(&record[8..12]);
+        let section_number = i16::from_le_byte_slice(&record[12..14]);
+        let symbol_type = u16::from_le_byte_slice(&record[14..16]);
+        let storage_class = StorageClass::from_base_type(record[16]);
+        let aux_count = usize::from(record[17]);
+
+        let aux_start = index + 1;
+        let aux_end = aux_start + aux_count;
+        if aux_end > record_count_usize {
+            error!("COFF symbol {} has {} auxiliary records, exceeding the symbol table", index, aux_count);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let auxiliary_records = (aux_start..aux_end)
+            .map(|i| DisplayBytesVec::from(records[i*SYMBOL_SIZE..(i+1)*SYMBOL_SIZE].to_vec()))
+            .collect();
+
+        symbols.push(CoffSymbol {
+            index: u32::try_from(index).unwrap(),
+            name: DisplayBytesVec::from(name_bytes),
+            value,
+            section_number,
+            symbol_type,
+            storage_class,
+            auxiliary_records,
+        });
+        index = aux_end;
+    }
+    Ok(symbols)
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct CoffSymbol {
+    /// The index of the symbol's record in the symbol table, counting auxiliary records.
+    ///
+    /// Line number records refer to functions using this index.
+    pub index: u32,
+
+    pub name: DisplayBytesVec, // [u8; 8] or u32 zero + u32 string table offset
+
+    /// The value of the symbol; for symbols defined in a section, the offset within it.
+    pub value: u32,
+
+    /// The 1-based index of the section; 0 for undefined, -1 for absolute and -2 for debugging
+    /// symbols.
+    pub section_number: i16,
+
+    pub symbol_type: u16,
+    pub storage_class: StorageClass, // u8
+    // auxiliary_record_count: u8,
+    pub auxiliary_records: Vec<DisplayBytesVec>, // [[u8; 18]; auxiliary_record_count]
+}
+impl CoffSymbol {
+    /// Whether the symbol's type is marked as a function.
+    pub fn is_function(&self) -> bool {
+        (self.symbol_type >> 4) & 0x3 == 0x2
+    }
+}
+
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[from_to_other(base_type = u8, derive_compare = "as_int")]
+pub enum StorageClass {
+    Null = 0,
+    Automatic = 1,
+    External = 2,
+    Static = 3,
+    Register = 4,
+    ExternalDefinition = 5,
+    Label = 6,
+    UndefinedLabel = 7,
+    MemberOfStructure = 8,
+    Argument = 9,
+    StructureTag = 10,
+    MemberOfUnion = 11,
+    UnionTag = 12,
+    TypeDefinition = 13,
+    UndefinedStatic = 14,
+    EnumerationTag = 15,
+    MemberOfEnumeration = 16,
+    RegisterParameter = 17,
+    BitField = 18,
+    Block = 100,
+    Function = 101,
+    EndOfStructure = 102,
+    File = 103,
+    Section = 104,
+    WeakExternal = 105,
+    ClrToken = 107,
+    EndOfFunction = 0xFF,
+    Other(u8),
+}
+
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum CoffLineNumber {
+    /// Marks the start of the line numbers of a function; refers to its symbol by index.
+    FunctionStart { symbol_index: u32 },
+
+    /// A line of source code; the line number is relative to the start of the function.
+    Line { virtual_address: u32, line_number: u16 },
+}
+impl CoffLineNumber {
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut buf = [0u8; LINE_NUMBER_SIZE];
+        reader.read_exact(&mut buf)?;
+
+        let address_or_index = u32::from_le_byte_slice(&buf[0..4]);
+        let line_number = u16::from_le_byte_slice(&buf[4..6]);
+        if line_number == 0 {
+            Ok(Self::FunctionStart { symbol_index: address_or_index })
+        } else {
+            Ok(Self::Line { virtual_address: address_or_index, line_number })
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Cursor};
+
+    use super::{read_symbol_table, CoffDebugInfo, CoffLineNumber, StorageClass, SYMBOL_SIZE};
+
+
+    fn record(name: &[u8; 8], value: u32, section_number: i16, symbol_type: u16, storage_class: u8, aux_count: u8) -> Vec<u8> {
+        let mut record = Vec::with_capacity(SYMBOL_SIZE);
+        record.extend_from_slice(name);
+        record.extend_from_slice(&value.to_le_bytes());
+        record.extend_from_slice(&section_number.to_le_bytes());
+        record.extend_from_slice(&symbol_type.to_le_bytes());
+        record.push(storage_class);
+        record.push(aux_count);
+        record
+    }
+
+    fn long_name(offset: u32) -> [u8; 8] {
+        let mut name = [0u8; 8];
+        name[4..8].copy_from_slice(&offset.to_le_bytes());
+        name
+    }
+
+    /// A file symbol with one auxiliary record, a function with a long name and a short-named
+    /// static, followed by the string table.
+    fn symbol_table() -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(record(b".file\0\0\0", 0, -2, 0, 103, 1));
+        let mut aux = b"hello.c".to_vec();
+        aux.resize(SYMBOL_SIZE, 0);
+        data.extend(aux);
+        data.extend(record(&long_name(4), 0x10, 1, 0x20, 2, 0));
+        data.extend(record(b"counter\0", 0x200, 2, 0, 3, 0));
+
+        let strings = b"a_rather_long_function_name\0";
+        data.extend((u32::try_from(strings.len()).unwrap() + 4).to_le_bytes());
+        data.extend(strings);
+        data
+    }
+
+    #[test]
+    fn test_read_symbol_table() {
+        let symbols = read_symbol_table(&mut Cursor::new(symbol_table()), 4).unwrap();
+        assert_eq!(symbols.len(), 3);
+
+        assert_eq!(symbols[0].index, 0);
+        assert_eq!(symbols[0].name.as_ref() as &[u8], b".file");
+        assert_eq!(symbols[0].storage_class, StorageClass::File);
+        assert_eq!(symbols[0].auxiliary_records.len(), 1);
+        let aux: &[u8] = symbols[0].auxiliary_records[0].as_ref();
+        assert_eq!(&aux[0..8], b"hello.c\0");
+
+        assert_eq!(symbols[1].index, 2);
+        assert_eq!(symbols[1].name.as_ref() as &[u8], b"a_rather_long_function_name");
+        assert_eq!(symbols[1].value, 0x10);
+        assert_eq!(symbols[1].section_number, 1);
+        assert!(symbols[1].is_function());
+        assert_eq!(symbols[1].storage_class, StorageClass::External);
+
+        assert_eq!(symbols[2].index, 3);
+        assert_eq!(symbols[2].name.as_ref() as &[u8], b"counter");
+        assert!(!symbols[2].is_function());
+        assert_eq!(symbols[2].storage_class, StorageClass::Static);
+    }
+
+    #[test]
+    fn test_reject_broken_symbol_tables() {
+        // more records than there is data
+        let err = read_symbol_table(&mut Cursor::new(symbol_table()), u32::MAX).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // auxiliary records beyond the end of the table
+        let mut data = symbol_table();
+        data[3 * SYMBOL_SIZE + 17] = 1;
+        let err = read_symbol_table(&mut Cursor::new(data), 4).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // string table longer than the data
+        let mut data = symbol_table();
+        let length_offset = 4 * SYMBOL_SIZE;
+        data[length_offset..length_offset+4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = read_symbol_table(&mut Cursor::new(data), 4).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // name offset outside the string table
+        let mut data = symbol_table();
+        let name_offset = 2 * SYMBOL_SIZE + 4;
+        data[name_offset..name_offset+4].copy_from_slice(&0x1000u32.to_le_bytes());
+        let err = read_symbol_table(&mut Cursor::new(data), 4).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    /// The symbol table with a function start and two line numbers after it.
+    fn debug_info() -> Vec<u8> {
+        let symbols = symbol_table();
+        let line_numbers_offset = 32 + symbols.len();
+
+        let mut data = Vec::new();
+        for value in [4, 32, 3, u32::try_from(line_numbers_offset).unwrap(), 0x1000, 0x2000, 0x3000, 0x4000] {
+            data.extend(u32::to_le_bytes(value));
+        }
+        data.extend(symbols);
+        data.extend(2u32.to_le_bytes());
+        data.extend(0u16.to_le_bytes());
+        data.extend(0x1010u32.to_le_bytes());
+        data.extend(1u16.to_le_bytes());
+        data.extend(0x1018u32.to_le_bytes());
+        data.extend(3u16.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_read_debug_info() {
+        let mut data = debug_info();
+        let info = CoffDebugInfo::read(&mut Cursor::new(&data)).unwrap();
+        assert_eq!(info.symbols.len(), 3);
+        assert_eq!(info.line_numbers, [
+            CoffLineNumber::FunctionStart { symbol_index: 2 },
+            CoffLineNumber::Line { virtual_address: 0x1010, line_number: 1 },
+            CoffLineNumber::Line { virtual_address: 0x1018, line_number: 3 },
+        ]);
+
+        // a huge line number count runs into the end of the data
+        data[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = CoffDebugInfo::read(&mut Cursor::new(&data)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let info = CoffDebugInfo::read(&mut Cursor::new(debug_info())).unwrap();
+        let json = serde_json::to_value(&info).unwrap();
+        let read_back: CoffDebugInfo = serde_json::from_value(json).unwrap();
+        assert_eq!(read_back, info);
+    }
+}
@@ -0,0 +1,345 @@
+//! Mapping of addresses to the functions containing them.
+//!
+//! Information about function boundaries is scattered across multiple places in an executable and
+//! its debug information, each with its own gaps: exports have names but no extent, the exception
+//! directory has extents but no names (and does not cover leaf functions), and CodeView or COFF
+//! symbols are only available if the debug information has been kept. A [`FunctionMap`] combines
+//! all of them.
+
+
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::code_view::SegmentLayout;
+use crate::code_view::symbol_entries::{SymbolEntry, SymbolEntryData};
+use crate::coff::CoffSymbol;
+use crate::pe::{ExceptionData, ExportAddressTableEntry, ExportData, SectionTable};
+
+
+/// Where information about a function was obtained from.
+///
+/// The order of the variants is the order of preference when choosing a function's name.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum FunctionSource {
+    /// A procedure or public symbol in CodeView debug information.
+    CodeView,
+
+    /// A function symbol in a COFF symbol table.
+    Coff,
+
+    /// An entry in the export table.
+    Export,
+
+    /// A runtime function entry in the exception directory (`.pdata`).
+    ExceptionDirectory,
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct MappedFunction {
+    pub begin_address: u32,
+
+    /// The address of the first byte after the function, if any source knows it.
+    pub end_address: Option<u32>,
+
+    /// The name of the function, taken from the most preferred source that provides one.
+    pub name: Option<String>,
+
+    /// The sources which mention a function at this address.
+    pub sources: Vec<FunctionSource>,
+}
+
+
+/// The result of looking up an address in a [`FunctionMap`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct FunctionLookup<'m> {
+    pub function: &'m MappedFunction,
+
+    /// The end of the function; if it is not known, the start of the following function is
+    /// assumed. `None` if neither is known.
+    pub end_address: Option<u32>,
+
+    /// The offset of the looked-up address from the start of the function.
+    pub offset: u32,
+}
+
+
+/// A collection of known functions, keyed by their start address (RVA).
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct FunctionMap {
+    begin_to_function: BTreeMap<u32, MappedFunction>,
+}
+impl FunctionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.begin_to_function.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.begin_to_function.is_empty()
+    }
+
+    /// Iterates over the known functions, ordered by start address.
+    pub fn functions(&self) -> impl Iterator<Item = &MappedFunction> {
+        self.begin_to_function.values()
+    }
+
+    /// Registers a function, merging the information with that of an existing function starting at
+    /// the same address.
+    pub fn add(&mut self, begin_address: u32, end_address: Option<u32>, name: Option<String>, source: FunctionSource) {
+        let end_address = end_address.filter(|end| *end > begin_address);
+        match self.begin_to_function.entry(begin_address) {
+            Entry::Vacant(ve) => {
+                ve.insert(MappedFunction {
+                    begin_address,
+                    end_address,
+                    name,
+                    sources: vec![source],
+                });
+            },
+            Entry::Occupied(mut oe) => {
+                let function = oe.get_mut();
+                let preferred = function.sources.iter().all(|s| source < *s);
+                if name.is_some() && (function.name.is_none() || preferred) {
+                    function.name = name;
+                }
+                if end_address.is_some() && (function.end_address.is_none() || preferred) {
+                    function.end_address = end_address;
+                }
+                if !function.sources.contains(&source) {
+                    function.sources.push(source);
+                    function.sources.sort_unstable();
+                }
+            },
+        }
+    }
+
+    /// Registers the exported functions. Forwarders are skipped.
+    pub fn add_exports(&mut self, export_data: &ExportData) {
+        for (ordinal, entry) in &export_data.ordinal_to_address {
+            let ExportAddressTableEntry::Code { code_rva } = entry else { continue };
+
+            // prefer the first name in the table if multiple names refer to the same ordinal
+            let name = export_data.name_table.iter()
+                .find(|(_name, name_ordinal)| name_ordinal == ordinal)
+                .map(|(name, _ordinal)| name.clone());
+            self.add(*code_rva, None, name, FunctionSource::Export);
+        }
+    }
+
+    /// Registers the functions listed in the exception directory.
+    pub fn add_exception_data(&mut self, exception_data: &ExceptionData) {
+        for runtime_function in &exception_data.runtime_functions {
+            self.add(
+                runtime_function.begin_address,
+                Some(runtime_function.end_address),
+                None,
+                FunctionSource::ExceptionDirectory,
+            );
+        }
+    }
+
+    /// Registers the procedures and public symbols among the given CodeView symbols.
+    ///
+    /// The symbols' logical segments are translated into RVAs using the given layout, usually
+    /// obtained from [`DebugInfo::segment_layout_for_pe`].
+    ///
+    /// [`DebugInfo::segment_layout_for_pe`]: crate::code_view::DebugInfo::segment_layout_for_pe
+    pub fn add_code_view_symbols<'s, I: IntoIterator<Item = &'s SymbolEntry>>(&mut self, symbols: I, segment_layout: &SegmentLayout) {
+        for symbol in symbols {
+            let (segment, offset, length, name) = match &symbol.data {
+                SymbolEntryData::LocalProcedure16_32(procedure)|SymbolEntryData::GlobalProcedure16_32(procedure)
+                    => (procedure.segment, procedure.offset, Some(procedure.proc_length), &procedure.name),
+                SymbolEntryData::PublicSymbol16_32(public)
+                    => (public.segment, public.offset, None, &public.name),
+                _ => continue,
+            };
+            let Some(begin_address) = segment_layout.rva(segment, offset) else { continue };
+            let end_address = length.and_then(|l| begin_address.checked_add(l));
+            let name_bytes: &[u8] = name.as_ref();
+            let name = String::from_utf8_lossy(name_bytes).into_owned();
+            self.add(begin_address, end_address, Some(name), FunctionSource::CodeView);
+        }
+    }
+
+    /// Registers the functions among the given COFF symbols.
+    ///
+    /// The symbols' section numbers are interpreted as 1-based indexes into the section table.
+    pub fn add_coff_symbols<'s, I: IntoIterator<Item = &'s CoffSymbol>>(&mut self, symbols: I, section_table: &SectionTable) {
+        let sections = section_table.as_entries();
+        for symbol in symbols {
+            if !symbol.is_function() {
+                continue;
+            }
+            let Some(index) = usize::try_from(symbol.section_number).ok().and_then(|sn| sn.checked_sub(1)) else { continue };
+            let Some(section) = sections.get(index) else { continue };
+            let Some(begin_address) = section.virtual_address.checked_add(symbol.value) else { continue };
+            let name_bytes: &[u8] = symbol.name.as_ref();
+            let name = String::from_utf8_lossy(name_bytes).into_owned();
+            self.add(begin_address, None, Some(name), FunctionSource::Coff);
+        }
+    }
+
+    /// Finds the function containing the given address.
+    ///
+    /// If the end of the closest preceding function is unknown, it is assumed to extend up to the
+    /// start of the next function.
+    pub fn lookup(&self, address: u32) -> Option<FunctionLookup<'_>> {
+        let (begin_address, function) = self.begin_to_function.range(..=address).next_back()?;
+        let end_address = match function.end_address {
+            Some(end) => Some(end),
+            None => address.checked_add(1)
+                .and_then(|next| self.begin_to_function.range(next..).next())
+                .map(|(next_begin, _)| *next_begin),
+        };
+        if end_address.is_some_and(|end| address >= end) {
+            return None;
+        }
+        Some(FunctionLookup {
+            function,
+            end_address,
+            offset: address - begin_address,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use display_bytes::DisplayBytesVec;
+    use crate::code_view::SegmentLayout;
+    use crate::code_view::symbol_entries::{
+        Data16, ProcedureFlags, ProcedureStart16, SymbolEntry, SymbolEntryData, SymbolEntryType,
+    };
+    use crate::pe::{SectionTable, SectionTableEntry};
+    use super::{FunctionMap, FunctionSource};
+
+    #[test]
+    fn test_merge_prefers_code_view_names() {
+        let mut map = FunctionMap::new();
+        map.add(0x1000, Some(0x1080), None, FunctionSource::ExceptionDirectory);
+        map.add(0x1000, None, Some("ExportedName".to_owned()), FunctionSource::Export);
+        map.add(0x1000, None, Some("?InternalName@@YAXXZ".to_owned()), FunctionSource::CodeView);
+        map.add(0x1000, None, Some("OtherExport".to_owned()), FunctionSource::Export);
+
+        assert_eq!(map.len(), 1);
+        let function = map.functions().next().unwrap();
+        assert_eq!(function.name.as_deref(), Some("?InternalName@@YAXXZ"));
+        assert_eq!(function.end_address, Some(0x1080));
+        assert_eq!(function.sources, [FunctionSource::CodeView, FunctionSource::Export, FunctionSource::ExceptionDirectory]);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let mut map = FunctionMap::new();
+        map.add(0x1000, Some(0x1040), None, FunctionSource::ExceptionDirectory);
+        map.add(0x1100, None, Some("Exported".to_owned()), FunctionSource::Export);
+        map.add(0x1200, None, Some("Last".to_owned()), FunctionSource::Export);
+
+        assert!(map.lookup(0x0FFF).is_none());
+
+        let lookup = map.lookup(0x1000).unwrap();
+        assert_eq!(lookup.function.begin_address, 0x1000);
+        assert_eq!(lookup.end_address, Some(0x1040));
+        assert_eq!(lookup.offset, 0);
+
+        assert_eq!(map.lookup(0x103F).unwrap().offset, 0x3F);
+
+        // in the gap between two functions with known extent
+        assert!(map.lookup(0x1040).is_none());
+
+        // extent inferred from the next function
+        let lookup = map.lookup(0x1150).unwrap();
+        assert_eq!(lookup.function.name.as_deref(), Some("Exported"));
+        assert_eq!(lookup.end_address, Some(0x1200));
+        assert_eq!(lookup.offset, 0x50);
+
+        // extent unknown
+        let lookup = map.lookup(0x9999).unwrap();
+        assert_eq!(lookup.function.name.as_deref(), Some("Last"));
+        assert_eq!(lookup.end_address, None);
+    }
+
+    #[test]
+    fn test_empty_extents_are_ignored() {
+        let mut map = FunctionMap::new();
+        map.add(0x2000, Some(0x2000), None, FunctionSource::ExceptionDirectory);
+        assert_eq!(map.functions().next().unwrap().end_address, None);
+    }
+
+    #[test]
+    fn test_add_code_view_symbols() {
+        fn procedure(segment: u16, offset: u32, proc_length: u32, name: &str) -> SymbolEntry {
+            SymbolEntry {
+                length: 0,
+                kind: SymbolEntryType::GlobalProcedure16_32,
+                data: SymbolEntryData::GlobalProcedure16_32(ProcedureStart16 {
+                    parent_scope: 0,
+                    scope_end: 0,
+                    next_scope: 0,
+                    proc_length,
+                    debug_start: 0,
+                    debug_end: 0,
+                    offset,
+                    segment,
+                    procedure_type: 0,
+                    flags: ProcedureFlags::empty(),
+                    name: DisplayBytesVec::from(name.as_bytes().to_vec()),
+                }),
+            }
+        }
+        fn data(kind: SymbolEntryType, segment: u16, offset: u32, name: &str) -> SymbolEntry {
+            let data = Data16 {
+                offset,
+                segment,
+                value_type: 0,
+                name: DisplayBytesVec::from(name.as_bytes().to_vec()),
+            };
+            SymbolEntry {
+                length: 0,
+                kind,
+                data: match kind {
+                    SymbolEntryType::PublicSymbol16_32 => SymbolEntryData::PublicSymbol16_32(data),
+                    _ => SymbolEntryData::GlobalData16_32(data),
+                },
+            }
+        }
+
+        let section_table = SectionTable::from(vec![
+            SectionTableEntry { virtual_size: 0x2000, virtual_address: 0x1000, ..Default::default() },
+            SectionTableEntry { virtual_size: 0x1000, virtual_address: 0x3000, ..Default::default() },
+        ]);
+        let layout = SegmentLayout::for_pe(None, None, &section_table);
+        let symbols = [
+            procedure(1, 0x0100, 0x40, "_main"),
+            data(SymbolEntryType::PublicSymbol16_32, 1, 0x0100, "_main"),
+            data(SymbolEntryType::PublicSymbol16_32, 1, 0x0200, "_helper"),
+            // not functions, or in no known segment
+            data(SymbolEntryType::GlobalData16_32, 2, 0x0010, "_counter"),
+            procedure(7, 0x0000, 0x10, "_nowhere"),
+        ];
+
+        let mut map = FunctionMap::new();
+        map.add(0x1100, None, Some("main".to_owned()), FunctionSource::Export);
+        map.add_code_view_symbols(&symbols, &layout);
+
+        let functions: Vec<_> = map.functions().collect();
+        assert_eq!(functions.len(), 2);
+        assert_eq!(functions[0].begin_address, 0x1100);
+        assert_eq!(functions[0].end_address, Some(0x1140));
+        assert_eq!(functions[0].name.as_deref(), Some("_main"));
+        assert_eq!(functions[0].sources, [FunctionSource::CodeView, FunctionSource::Export]);
+        assert_eq!(functions[1].begin_address, 0x1200);
+        assert_eq!(functions[1].end_address, None);
+        assert_eq!(functions[1].name.as_deref(), Some("_helper"));
+    }
+}
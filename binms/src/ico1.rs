@@ -71,6 +71,41 @@ impl Icon1 {
             other => Err(Error::UnknownIndicator(other)),
         }
     }
+
+    /// Returns the data of the given variant, if the icon contains it.
+    pub fn variant(&self, variant: Icon1Variant) -> Option<&IconData> {
+        match variant {
+            Icon1Variant::DeviceIndependent => self.device_independent.as_ref(),
+            Icon1Variant::DeviceDependent => self.device_dependent.as_ref(),
+        }
+    }
+
+    /// Returns the variants contained in the icon, in the order in which they are stored.
+    pub fn variants(&self) -> Vec<(Icon1Variant, &IconData)> {
+        [Icon1Variant::DeviceIndependent, Icon1Variant::DeviceDependent]
+            .into_iter()
+            .filter_map(|v| self.variant(v).map(|data| (v, data)))
+            .collect()
+    }
+}
+
+/// The variants in which a Windows 1.0 icon may be stored.
+///
+/// The device-independent variant is meant to be scaled to the display; the device-dependent
+/// variant has been drawn for a specific display.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Icon1Variant {
+    DeviceIndependent,
+    DeviceDependent,
+}
+impl Icon1Variant {
+    /// The name of the variant, e.g. `device-independent`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::DeviceIndependent => "device-independent",
+            Self::DeviceDependent => "device-dependent",
+        }
+    }
 }
 
 /// The icon data.
@@ -173,4 +208,31 @@ impl IconData {
     pub fn xor_bytes_as_sixels(&self) -> String {
         self.bytes_as_sixels(&self.xor_bytes)
     }
+
+    /// Converts the icon into rows of RGBA pixels with 8 bits per channel, top row first.
+    ///
+    /// Pixels whose XOR bit is set are white, pixels whose AND bit is set (and XOR bit is not) are
+    /// transparent and the others are black.
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        let width_pixels = usize::from(self.width_pixels);
+        let width_bytes = usize::from(self.width_bytes);
+        let mut rgba = Vec::with_capacity(4 * width_pixels * usize::from(self.height_pixels));
+        let row_iterator = self.and_bytes
+            .chunks(width_bytes)
+            .zip(self.xor_bytes.chunks(width_bytes));
+        for (and_row, xor_row) in row_iterator {
+            for x in 0..width_pixels {
+                let mask = 0x80 >> (x % 8);
+                let pixel = if xor_row[x / 8] & mask != 0 {
+                    [0xFF, 0xFF, 0xFF, 0xFF]
+                } else if and_row[x / 8] & mask != 0 {
+                    [0x00, 0x00, 0x00, 0x00]
+                } else {
+                    [0x00, 0x00, 0x00, 0xFF]
+                };
+                rgba.extend_from_slice(&pixel);
+            }
+        }
+        rgba
+    }
 }
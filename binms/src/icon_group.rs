@@ -1,15 +1,62 @@
+//! Icon and cursor groups (`RT_GROUP_ICON`, `RT_GROUP_CURSOR`) and standalone `.ico`/`.cur` files.
+//!
+//! A group resource lists the images of an icon or cursor, each of which is stored in a separate
+//! `RT_ICON` or `RT_CURSOR` resource referenced by its ID. A standalone file has practically the
+//! same directory structure, except that the images follow the directory and are referenced by
+//! their offset within the file.
+//!
+//! Cursor groups use a different entry layout (16-bit width and doubled 16-bit height instead of
+//! the 8-bit width, height, color count and reserved fields) and their images are prefixed with the
+//! hotspot coordinates, which a standalone file stores in the directory instead. [`GroupIcon`]
+//! always uses the icon layout; the conversion functions take care of the differences.
+
+
+use std::collections::BTreeSet;
 use std::fmt;
 
 
+/// The group type of icon groups.
+pub const GROUP_TYPE_ICON: u16 = 1;
+
+/// The group type of cursor groups.
+pub const GROUP_TYPE_CURSOR: u16 = 2;
+
+const DIRECTORY_HEADER_SIZE: usize = 6;
+const ENTRY_SIZE: usize = 14;
+const FILE_ENTRY_SIZE: usize = 16;
+const HOTSPOT_SIZE: usize = 4;
+
+
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum Error {
     TooShort,
+    NonZeroReserved { reserved: u16 },
+    UnknownGroupType { group_type: u16 },
+    TooManyImages { count: usize },
+    DuplicateId { id: u16 },
+    MissingImage { id: u16 },
+    ByteCountMismatch { id: u16, expected: u32, obtained: usize },
+    ImageOutOfBounds { index: usize },
 }
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         match self {
             Self::TooShort
                 => write!(f, "icon list data too short"),
+            Self::NonZeroReserved { reserved }
+                => write!(f, "reserved field is {:#06X} instead of 0", reserved),
+            Self::UnknownGroupType { group_type }
+                => write!(f, "unknown group type {}", group_type),
+            Self::TooManyImages { count }
+                => write!(f, "too many images ({})", count),
+            Self::DuplicateId { id }
+                => write!(f, "image ID {} is referenced multiple times", id),
+            Self::MissingImage { id }
+                => write!(f, "image with ID {} not found", id),
+            Self::ByteCountMismatch { id, expected, obtained }
+                => write!(f, "image with ID {} should have {} bytes but has {}", id, expected, obtained),
+            Self::ImageOutOfBounds { index }
+                => write!(f, "image at index {} extends beyond the end of the file", index),
         }
     }
 }
@@ -57,6 +104,172 @@ impl IconGroup {
         };
         Ok((rest, icon_group))
     }
+
+    /// Serializes this group in the format of a group resource.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let count = u16::try_from(self.icons.len())
+            .expect("too many icons");
+        let mut bytes = Vec::with_capacity(DIRECTORY_HEADER_SIZE + self.icons.len() * ENTRY_SIZE);
+        bytes.extend(self.reserved.to_le_bytes());
+        bytes.extend(self.group_type.to_le_bytes());
+        bytes.extend(count.to_le_bytes());
+        for icon in &self.icons {
+            icon.write_fields(&mut bytes);
+            bytes.extend(icon.id.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Checks whether this group is well-formed: whether the reserved field is zero, the group
+    /// type is known and no image is referenced twice.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.reserved != 0 {
+            return Err(Error::NonZeroReserved { reserved: self.reserved });
+        }
+        if self.group_type != GROUP_TYPE_ICON && self.group_type != GROUP_TYPE_CURSOR {
+            return Err(Error::UnknownGroupType { group_type: self.group_type });
+        }
+        if u16::try_from(self.icons.len()).is_err() {
+            return Err(Error::TooManyImages { count: self.icons.len() });
+        }
+        let mut seen_ids = BTreeSet::new();
+        for icon in &self.icons {
+            if !seen_ids.insert(icon.id) {
+                return Err(Error::DuplicateId { id: icon.id });
+            }
+        }
+        Ok(())
+    }
+
+    /// Assembles a standalone `.ico` or `.cur` file from this group and the images it references.
+    ///
+    /// `get_image` is called with the ID of each image and returns the data of the corresponding
+    /// `RT_ICON` or `RT_CURSOR` resource.
+    pub fn to_icon_file<'a, F: FnMut(u16) -> Option<&'a [u8]>>(&self, mut get_image: F) -> Result<Vec<u8>, Error> {
+        self.validate()?;
+        let is_cursor = self.group_type == GROUP_TYPE_CURSOR;
+
+        let mut images = Vec::with_capacity(self.icons.len());
+        for icon in &self.icons {
+            let image = get_image(icon.id)
+                .ok_or(Error::MissingImage { id: icon.id })?;
+            if usize::try_from(icon.byte_count).unwrap() != image.len() {
+                return Err(Error::ByteCountMismatch { id: icon.id, expected: icon.byte_count, obtained: image.len() });
+            }
+            images.push(image);
+        }
+
+        let mut bytes = Vec::new();
+        bytes.extend(self.reserved.to_le_bytes());
+        bytes.extend(self.group_type.to_le_bytes());
+        bytes.extend(u16::try_from(self.icons.len()).unwrap().to_le_bytes());
+
+        let mut offset = DIRECTORY_HEADER_SIZE + self.icons.len() * FILE_ENTRY_SIZE;
+        let mut image_data = Vec::with_capacity(images.len());
+        for (icon, image) in self.icons.iter().zip(images) {
+            let (entry, data) = if is_cursor {
+                if image.len() < HOTSPOT_SIZE {
+                    return Err(Error::TooShort);
+                }
+                let (hotspot, data) = image.split_at(HOTSPOT_SIZE);
+                let height = u16::from_le_bytes([icon.color_count, icon.reserved]) / 2;
+                let entry = GroupIcon {
+                    width: icon.width,
+                    height: height.try_into().unwrap_or(0),
+                    color_count: 0,
+                    reserved: 0,
+                    planes: u16::from_le_bytes(hotspot[0..2].try_into().unwrap()),
+                    bit_count: u16::from_le_bytes(hotspot[2..4].try_into().unwrap()),
+                    byte_count: u32::try_from(data.len()).unwrap(),
+                    id: icon.id,
+                };
+                (entry, data)
+            } else {
+                (*icon, image)
+            };
+
+            entry.write_fields(&mut bytes);
+            bytes.extend(u32::try_from(offset).unwrap().to_le_bytes());
+            offset += data.len();
+            image_data.push(data);
+        }
+        for data in image_data {
+            bytes.extend(data);
+        }
+        Ok(bytes)
+    }
+
+    /// Splits a standalone `.ico` or `.cur` file into a group and the data of its images, in the
+    /// format of `RT_ICON` or `RT_CURSOR` resources.
+    ///
+    /// The images are assigned the IDs `first_id`, `first_id + 1` and so on.
+    pub fn split_icon_file(bytes: &[u8], first_id: u16) -> Result<(Self, Vec<Vec<u8>>), Error> {
+        if bytes.len() < DIRECTORY_HEADER_SIZE {
+            return Err(Error::TooShort);
+        }
+        let reserved = u16::from_le_bytes(bytes[0..2].try_into().unwrap());
+        let group_type = u16::from_le_bytes(bytes[2..4].try_into().unwrap());
+        let count = usize::from(u16::from_le_bytes(bytes[4..6].try_into().unwrap()));
+        if bytes.len() < DIRECTORY_HEADER_SIZE + count * FILE_ENTRY_SIZE {
+            return Err(Error::TooShort);
+        }
+        let is_cursor = group_type == GROUP_TYPE_CURSOR;
+
+        let mut icons = Vec::with_capacity(count);
+        let mut images = Vec::with_capacity(count);
+        for index in 0..count {
+            let entry_bytes = &bytes[DIRECTORY_HEADER_SIZE + index * FILE_ENTRY_SIZE..DIRECTORY_HEADER_SIZE + (index + 1) * FILE_ENTRY_SIZE];
+            // the image offset takes the place of the ID and then some
+            let (_rest, entry) = GroupIcon::take_from_bytes(entry_bytes)?;
+            let offset = usize::try_from(u32::from_le_bytes(entry_bytes[12..16].try_into().unwrap())).unwrap();
+            let byte_count = usize::try_from(entry.byte_count).unwrap();
+            let data = offset.checked_add(byte_count)
+                .and_then(|end| bytes.get(offset..end))
+                .ok_or(Error::ImageOutOfBounds { index })?;
+            let id = u16::try_from(index).ok()
+                .and_then(|i| first_id.checked_add(i))
+                .ok_or(Error::TooManyImages { count })?;
+
+            if is_cursor {
+                // move the hotspot from the directory entry into the image
+                let mut image = Vec::with_capacity(HOTSPOT_SIZE + data.len());
+                image.extend(entry.planes.to_le_bytes());
+                image.extend(entry.bit_count.to_le_bytes());
+                image.extend(data);
+
+                let [height_lo, height_hi] = (u16::from(entry.height) * 2).to_le_bytes();
+                let bit_count = bit_count_of_image(data).unwrap_or(0);
+                icons.push(GroupIcon {
+                    width: entry.width,
+                    height: 0,
+                    color_count: height_lo,
+                    reserved: height_hi,
+                    planes: 1,
+                    bit_count,
+                    byte_count: u32::try_from(image.len()).unwrap(),
+                    id,
+                });
+                images.push(image);
+            } else {
+                icons.push(GroupIcon { id, ..entry });
+                images.push(data.to_vec());
+            }
+        }
+
+        let group = Self {
+            reserved,
+            group_type,
+            icons,
+        };
+        group.validate()?;
+        Ok((group, images))
+    }
+}
+
+/// Returns the bit depth stored in the bitmap header at the beginning of an image, if any.
+fn bit_count_of_image(data: &[u8]) -> Option<u16> {
+    let (_rest, header) = crate::bitmap::BitmapInfoHeader::take_from_bytes(data).ok()?;
+    Some(header.bit_count)
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -98,4 +311,86 @@ impl GroupIcon {
         };
         Ok((&bytes[14..], icon))
     }
+
+    /// Writes the fields shared by group entries and standalone file entries, i.e. all but the
+    /// image ID or offset.
+    fn write_fields(&self, bytes: &mut Vec<u8>) {
+        bytes.push(self.width);
+        bytes.push(self.height);
+        bytes.push(self.color_count);
+        bytes.push(self.reserved);
+        bytes.extend(self.planes.to_le_bytes());
+        bytes.extend(self.bit_count.to_le_bytes());
+        bytes.extend(self.byte_count.to_le_bytes());
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, GroupIcon, IconGroup, GROUP_TYPE_CURSOR, GROUP_TYPE_ICON};
+
+    fn image(width: u8, height: u8, bit_count: u16) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(40u32.to_le_bytes());
+        bytes.extend(i32::from(width).to_le_bytes());
+        bytes.extend((2 * i32::from(height)).to_le_bytes());
+        bytes.extend(1u16.to_le_bytes());
+        bytes.extend(bit_count.to_le_bytes());
+        bytes.resize(40, 0x00);
+        bytes.extend([0x12, 0x34, 0x56, 0x78]);
+        bytes
+    }
+
+    #[test]
+    fn test_icon_file_round_trip() {
+        let images = [image(16, 16, 4), image(32, 32, 8)];
+        let group = IconGroup {
+            reserved: 0,
+            group_type: GROUP_TYPE_ICON,
+            icons: vec![
+                GroupIcon { width: 16, height: 16, color_count: 16, reserved: 0, planes: 1, bit_count: 4, byte_count: 44, id: 7 },
+                GroupIcon { width: 32, height: 32, color_count: 0, reserved: 0, planes: 1, bit_count: 8, byte_count: 44, id: 3 },
+            ],
+        };
+        let (_rest, reread) = IconGroup::take_from_bytes(&group.to_bytes()).unwrap();
+        assert_eq!(reread, group);
+
+        let file = group.to_icon_file(|id| match id {
+            7 => Some(&images[0]),
+            3 => Some(&images[1]),
+            _ => None,
+        }).unwrap();
+        assert_eq!(file.len(), 6 + 2*16 + 2*44);
+        assert_eq!(&file[18..22], &38u32.to_le_bytes());
+        assert_eq!(&file[38..], [images[0].as_slice(), images[1].as_slice()].concat());
+
+        let (split_group, split_images) = IconGroup::split_icon_file(&file, 1).unwrap();
+        assert_eq!(split_images, images);
+        assert_eq!(split_group.icons[1], GroupIcon { id: 2, ..group.icons[1] });
+
+        assert_eq!(group.to_icon_file(|_| None), Err(Error::MissingImage { id: 7 }));
+    }
+
+    #[test]
+    fn test_cursor_file_round_trip() {
+        let mut cursor_image = vec![0x05, 0x00, 0x0A, 0x00]; // hotspot (5, 10)
+        cursor_image.extend(image(32, 32, 1));
+        let group = IconGroup {
+            reserved: 0,
+            group_type: GROUP_TYPE_CURSOR,
+            icons: vec![
+                // width 32, height 64 (both halves), in the cursor layout
+                GroupIcon { width: 32, height: 0, color_count: 64, reserved: 0, planes: 1, bit_count: 1, byte_count: 48, id: 1 },
+            ],
+        };
+
+        let file = group.to_icon_file(|_| Some(&cursor_image)).unwrap();
+        assert_eq!(&file[6..14], &[32, 32, 0, 0, 5, 0, 10, 0]);
+        assert_eq!(&file[22..], &cursor_image[4..]);
+
+        let (split_group, split_images) = IconGroup::split_icon_file(&file, 1).unwrap();
+        assert_eq!(split_group, group);
+        assert_eq!(split_images, [cursor_image]);
+    }
 }
@@ -1,8 +1,35 @@
-pub trait IntFromByteSlice {
+use std::io;
+
+
+pub trait IntFromByteSlice : Sized {
     fn size() -> usize;
     fn from_be_byte_slice(bytes: &[u8]) -> Self;
     fn from_le_byte_slice(bytes: &[u8]) -> Self;
     fn from_ne_byte_slice(bytes: &[u8]) -> Self;
+
+    /// Reads a big-endian value at `offset` within `bytes`.
+    ///
+    /// Unlike [`IntFromByteSlice::from_be_byte_slice`], the slice may be longer than the value, and
+    /// a slice that is too short results in an [`io::ErrorKind::UnexpectedEof`] error instead of a
+    /// panic.
+    fn try_from_be_byte_slice(bytes: &[u8], offset: usize) -> Result<Self, io::Error>;
+
+    /// Reads a little-endian value at `offset` within `bytes`.
+    ///
+    /// Unlike [`IntFromByteSlice::from_le_byte_slice`], the slice may be longer than the value, and
+    /// a slice that is too short results in an [`io::ErrorKind::UnexpectedEof`] error instead of a
+    /// panic.
+    fn try_from_le_byte_slice(bytes: &[u8], offset: usize) -> Result<Self, io::Error>;
+
+    fn to_be_byte_vec(&self) -> Vec<u8>;
+    fn to_le_byte_vec(&self) -> Vec<u8>;
+}
+
+/// Returns the `size` bytes at `offset` within `bytes`, or an error if the slice is too short.
+fn value_bytes(bytes: &[u8], offset: usize, size: usize) -> Result<&[u8], io::Error> {
+    offset.checked_add(size)
+        .and_then(|end| bytes.get(offset..end))
+        .ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
 }
 
 macro_rules! impl_ifbs {
@@ -23,6 +50,24 @@ macro_rules! impl_ifbs {
             fn from_ne_byte_slice(bytes: &[u8]) -> Self {
                 Self::from_ne_bytes(bytes.try_into().unwrap())
             }
+
+            fn try_from_be_byte_slice(bytes: &[u8], offset: usize) -> Result<Self, io::Error> {
+                let value_bytes = value_bytes(bytes, offset, Self::size())?;
+                Ok(Self::from_be_bytes(value_bytes.try_into().unwrap()))
+            }
+
+            fn try_from_le_byte_slice(bytes: &[u8], offset: usize) -> Result<Self, io::Error> {
+                let value_bytes = value_bytes(bytes, offset, Self::size())?;
+                Ok(Self::from_le_bytes(value_bytes.try_into().unwrap()))
+            }
+
+            fn to_be_byte_vec(&self) -> Vec<u8> {
+                self.to_be_bytes().to_vec()
+            }
+
+            fn to_le_byte_vec(&self) -> Vec<u8> {
+                self.to_le_bytes().to_vec()
+            }
         }
     };
 }
@@ -38,3 +83,22 @@ impl_ifbs!(i32);
 impl_ifbs!(i64);
 impl_ifbs!(i128);
 impl_ifbs!(isize);
+
+
+#[cfg(test)]
+mod tests {
+    use super::IntFromByteSlice;
+    use std::io;
+
+    #[test]
+    fn test_try_from_byte_slice() {
+        let bytes = [0x12, 0x34, 0x56, 0x78, 0x9A];
+        assert_eq!(u16::try_from_le_byte_slice(&bytes, 1).unwrap(), 0x5634);
+        assert_eq!(u16::try_from_be_byte_slice(&bytes, 1).unwrap(), 0x3456);
+        assert_eq!(i32::try_from_le_byte_slice(&bytes, 1).unwrap(), -0x6587_A9CC);
+        assert_eq!(u8::try_from_le_byte_slice(&bytes, 4).unwrap(), 0x9A);
+        assert_eq!(u32::try_from_le_byte_slice(&bytes, 2).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(u8::try_from_le_byte_slice(&bytes, usize::MAX).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(0x1234u16.to_be_byte_vec(), vec![0x12, 0x34]);
+    }
+}
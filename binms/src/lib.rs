@@ -3,23 +3,33 @@ pub mod bitmap;
 pub mod bitmap_font;
 pub mod clr;
 pub mod code_view;
+pub mod coff;
+pub mod function_map;
 pub mod ico1;
 pub mod icon_group;
 mod int_from_byte_slice;
 #[macro_use] pub(crate) mod macros;
+pub mod minidump;
 pub mod mz;
 pub mod ne;
 pub mod nt4dbg;
 pub mod part_int;
 pub mod pe;
+pub mod regf;
+pub mod resource_map;
+pub mod string_table;
+pub mod strings;
+pub mod toolchain;
+pub mod wince_rom;
 
 
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 
 use tracing::{debug, instrument};
 
 
-pub(crate) fn read_nul_terminated_ascii_string<R: Read>(reader: &mut R) -> Result<String, io::Error> {
+/// Reads a byte string that is terminated by a NUL byte, which is consumed but not returned.
+pub(crate) fn read_nul_terminated_byte_string<R: Read>(reader: &mut R) -> Result<Vec<u8>, io::Error> {
     let mut buf = [0u8];
     let mut ret = Vec::new();
     loop {
@@ -29,9 +39,41 @@ pub(crate) fn read_nul_terminated_ascii_string<R: Read>(reader: &mut R) -> Resul
         }
         ret.push(buf[0]);
     }
-    String::from_utf8(ret)
-        .inspect_err(|_| debug!("NUL-terminated string is invalid UTF-8"))
-        .map_err(|_| io::ErrorKind::InvalidData.into())
+    Ok(ret)
+}
+
+/// Decodes the name of a symbol or module stored as a byte string.
+///
+/// Such names are generally ASCII, but the toolchains simply copied the bytes of the source code,
+/// so names in older binaries are occasionally encoded in the ANSI or OEM codepage of the
+/// developer's system. Names that are valid UTF-8 are returned as such; all others are decoded as
+/// Windows-1252, which maps every byte to a distinct character. Callers that need the original
+/// bytes must keep them around themselves.
+pub fn decode_byte_name(bytes: &[u8]) -> String {
+    if let Ok(s) = std::str::from_utf8(bytes) {
+        return s.to_owned();
+    }
+    debug!("name {:?} is invalid UTF-8; decoding it as Windows-1252", bytes);
+    bytes.iter()
+        .map(|&b| windows_1252_char(b))
+        .collect()
+}
+
+/// Maps a byte to its character in Windows-1252.
+///
+/// The five bytes that are not assigned in Windows-1252 are mapped to the C1 control character of
+/// the same value, as done by `MultiByteToWideChar`.
+fn windows_1252_char(b: u8) -> char {
+    const HIGH_CONTROL_RANGE: [char; 32] = [
+        '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+        '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+        '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+        '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+    ];
+    match b {
+        0x80..=0x9F => HIGH_CONTROL_RANGE[usize::from(b - 0x80)],
+        _ => char::from(b),
+    }
 }
 
 pub(crate) fn collect_nul_terminated_ascii_string(bytes: &[u8]) -> Option<String> {
@@ -46,6 +88,8 @@ pub(crate) fn collect_nul_terminated_ascii_string(bytes: &[u8]) -> Option<String
 }
 
 /// Reads a UTF-16LE string that is prefixed by a u16le length.
+///
+/// Unpaired surrogates are replaced by U+FFFD.
 pub(crate) fn read_pascal_utf16le_string<R: Read>(reader: &mut R) -> Result<String, io::Error> {
     let mut length_buf = [0u8; 2];
     reader.read_exact(&mut length_buf)?;
@@ -59,9 +103,14 @@ pub(crate) fn read_pascal_utf16le_string<R: Read>(reader: &mut R) -> Result<Stri
         let word = u16::from_le_bytes(word_bytes.try_into().unwrap());
         words.push(word);
     }
-    String::from_utf16(&words)
-        .inspect_err(|_| debug!("Pascal little-endian wide string is invalid UTF-16"))
-        .map_err(|_| io::ErrorKind::InvalidData.into())
+    // unpaired surrogates are replaced instead of rejecting the whole file
+    match String::from_utf16(&words) {
+        Ok(s) => Ok(s),
+        Err(_) => {
+            debug!("Pascal little-endian wide string {:?} is invalid UTF-16; decoding it lossily", words);
+            Ok(String::from_utf16_lossy(&words))
+        },
+    }
 }
 
 /// Reads a byte string that is prefixed by a u8 length.
@@ -75,3 +124,12 @@ pub(crate) fn read_pascal_byte_string<R: Read>(reader: &mut R) -> Result<Vec<u8>
     reader.read_exact(&mut string_bytes)?;
     Ok(string_bytes)
 }
+
+/// Writes a byte string that is prefixed by a u8 length.
+pub(crate) fn write_pascal_byte_string<W: Write>(writer: &mut W, string_bytes: &[u8]) -> Result<(), io::Error> {
+    let length_u8 = u8::try_from(string_bytes.len())
+        .inspect_err(|_| debug!("Pascal string is {} bytes long, more than 255", string_bytes.len()))
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    writer.write_all(&[length_u8])?;
+    writer.write_all(string_bytes)
+}
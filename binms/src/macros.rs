@@ -49,8 +49,10 @@ macro_rules! define_part_int_enum {
                 use std::str::FromStr;
                 use serde::de::Error as _;
 
-                let stringy: &str = serde::Deserialize::deserialize(deserializer)?;
-                let value = match stringy {
+                // not borrowed, as strings containing escapes and those read from a reader or a
+                // value tree cannot be borrowed
+                let stringy: String = serde::Deserialize::deserialize(deserializer)?;
+                let value = match stringy.as_str() {
                     $(
                         $option_name => Self(<$part_int>::from_base_type($option_value).unwrap()),
                     )*
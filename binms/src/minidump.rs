@@ -0,0 +1,334 @@
+//! Crash dump format written by `MiniDumpWriteDump`.
+
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use from_to_repr::from_to_other;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Minidump {
+    pub header: Header,
+    pub streams: Vec<DirectoryEntry>, // [DirectoryEntry; header.stream_count] at header.stream_directory_rva
+    pub modules: Vec<Module>, // from the ModuleList stream
+    pub memory_ranges: Vec<MemoryRange>, // from the MemoryList and Memory64List streams
+}
+impl Minidump {
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let header = Header::read(reader)?;
+
+        // the stream directory must fit into the file, which bounds the stream count
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        let max_stream_count = file_len.saturating_sub(header.stream_directory_rva.into()) / DirectoryEntry::SIZE;
+        if u64::from(header.stream_count) > max_stream_count {
+            debug!("minidump claims {} streams but only {} fit into the file", header.stream_count, max_stream_count);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+
+        reader.seek(SeekFrom::Start(header.stream_directory_rva.into()))?;
+        let mut streams = Vec::with_capacity(header.stream_count.try_into().unwrap());
+        for _ in 0..header.stream_count {
+            let stream = DirectoryEntry::read(reader)?;
+            streams.push(stream);
+        }
+
+        let mut modules = Vec::new();
+        let mut memory_ranges = Vec::new();
+        for stream in &streams {
+            match stream.stream_type {
+                StreamType::ModuleList => {
+                    reader.seek(SeekFrom::Start(stream.rva.into()))?;
+                    let mut count_buf = [0u8; 4];
+                    reader.read_exact(&mut count_buf)?;
+                    let count = u32::from_le_bytes(count_buf);
+                    for _ in 0..count {
+                        let module = Module::read(reader)?;
+                        modules.push(module);
+                    }
+                },
+                StreamType::MemoryList => {
+                    reader.seek(SeekFrom::Start(stream.rva.into()))?;
+                    let mut count_buf = [0u8; 4];
+                    reader.read_exact(&mut count_buf)?;
+                    let count = u32::from_le_bytes(count_buf);
+                    for _ in 0..count {
+                        let mut buf = [0u8; 16];
+                        reader.read_exact(&mut buf)?;
+                        let start_address = u64::from_le_bytes(buf[0..8].try_into().unwrap());
+                        let size = u32::from_le_bytes(buf[8..12].try_into().unwrap());
+                        let rva = u32::from_le_bytes(buf[12..16].try_into().unwrap());
+                        memory_ranges.push(MemoryRange {
+                            start_address,
+                            size: size.into(),
+                            file_offset: rva.into(),
+                        });
+                    }
+                },
+                StreamType::Memory64List => {
+                    reader.seek(SeekFrom::Start(stream.rva.into()))?;
+                    let mut buf = [0u8; 16];
+                    reader.read_exact(&mut buf)?;
+                    let count = u64::from_le_bytes(buf[0..8].try_into().unwrap());
+                    let mut file_offset = u64::from_le_bytes(buf[8..16].try_into().unwrap());
+                    for _ in 0..count {
+                        reader.read_exact(&mut buf)?;
+                        let start_address = u64::from_le_bytes(buf[0..8].try_into().unwrap());
+                        let size = u64::from_le_bytes(buf[8..16].try_into().unwrap());
+                        memory_ranges.push(MemoryRange {
+                            start_address,
+                            size,
+                            file_offset,
+                        });
+
+                        // the memory of a Memory64List is stored contiguously
+                        file_offset = file_offset.checked_add(size)
+                            .ok_or(io::ErrorKind::InvalidData)
+                            .inspect_err(|_| debug!("Memory64List data offset overflows"))?;
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        // read module names
+        for module in &mut modules {
+            reader.seek(SeekFrom::Start(module.name_rva.into()))?;
+            module.name = read_minidump_string(reader)?;
+        }
+
+        memory_ranges.sort_unstable();
+
+        Ok(Self {
+            header,
+            streams,
+            modules,
+            memory_ranges,
+        })
+    }
+
+    /// Returns the memory range containing the given address, if the dump contains one.
+    pub fn memory_range_for(&self, address: u64) -> Option<&MemoryRange> {
+        self.memory_ranges.iter()
+            .find(|mr| mr.contains(address))
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Header {
+    pub signature: u32, // b"MDMP"
+    pub version: u32, // low word 0xA793, high word implementation-specific
+    pub stream_count: u32,
+    pub stream_directory_rva: u32,
+    pub checksum: u32,
+    pub time_date_stamp: u32,
+    pub flags: u64,
+}
+impl Header {
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut buf = [0u8; 32];
+        reader.read_exact(&mut buf)?;
+
+        let signature = u32::from_le_bytes(buf[0..4].try_into().unwrap());
+        if &buf[0..4] != b"MDMP" {
+            debug!("minidump signature is {:#010X}, expected b\"MDMP\"", signature);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let version = u32::from_le_bytes(buf[4..8].try_into().unwrap());
+        if version & 0xFFFF != 0xA793 {
+            debug!("minidump version is {:#010X}, expected low word {:#06X}", version, 0xA793);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let stream_count = u32::from_le_bytes(buf[8..12].try_into().unwrap());
+        let stream_directory_rva = u32::from_le_bytes(buf[12..16].try_into().unwrap());
+        let checksum = u32::from_le_bytes(buf[16..20].try_into().unwrap());
+        let time_date_stamp = u32::from_le_bytes(buf[20..24].try_into().unwrap());
+        let flags = u64::from_le_bytes(buf[24..32].try_into().unwrap());
+
+        Ok(Self {
+            signature,
+            version,
+            stream_count,
+            stream_directory_rva,
+            checksum,
+            time_date_stamp,
+            flags,
+        })
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct DirectoryEntry {
+    pub stream_type: StreamType, // u32
+    pub data_size: u32,
+    pub rva: u32,
+}
+impl DirectoryEntry {
+    pub const SIZE: u64 = 12;
+
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut buf = [0u8; 12];
+        reader.read_exact(&mut buf)?;
+
+        let stream_type = StreamType::from_base_type(u32::from_le_bytes(buf[0..4].try_into().unwrap()));
+        let data_size = u32::from_le_bytes(buf[4..8].try_into().unwrap());
+        let rva = u32::from_le_bytes(buf[8..12].try_into().unwrap());
+
+        Ok(Self {
+            stream_type,
+            data_size,
+            rva,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[from_to_other(base_type = u32, derive_compare = "as_int")]
+pub enum StreamType {
+    Unused = 0,
+    ThreadList = 3,
+    ModuleList = 4,
+    MemoryList = 5,
+    Exception = 6,
+    SystemInfo = 7,
+    ThreadExList = 8,
+    Memory64List = 9,
+    CommentA = 10,
+    CommentW = 11,
+    HandleData = 12,
+    FunctionTable = 13,
+    UnloadedModuleList = 14,
+    MiscInfo = 15,
+    MemoryInfoList = 16,
+    ThreadInfoList = 17,
+    HandleOperationList = 18,
+    Token = 19,
+    JavaScriptData = 20,
+    SystemMemoryInfo = 21,
+    ProcessVmCounters = 22,
+    IptTrace = 23,
+    ThreadNames = 24,
+    Other(u32),
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Module {
+    pub image_base: u64,
+    pub image_size: u32,
+    pub checksum: u32,
+    pub time_date_stamp: u32,
+    pub name_rva: u32,
+    pub version_info: [u32; 13], // VS_FIXEDFILEINFO
+    pub codeview_record: LocationDescriptor,
+    pub misc_record: LocationDescriptor,
+    pub reserved0: u64,
+    pub reserved1: u64,
+
+    pub name: String, // MINIDUMP_STRING at name_rva
+}
+impl Module {
+    /// Reads the module entry. The name is not read; it has to be obtained separately via
+    /// `name_rva`.
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut buf = [0u8; 108];
+        reader.read_exact(&mut buf)?;
+
+        let image_base = u64::from_le_bytes(buf[0..8].try_into().unwrap());
+        let image_size = u32::from_le_bytes(buf[8..12].try_into().unwrap());
+        let checksum = u32::from_le_bytes(buf[12..16].try_into().unwrap());
+        let time_date_stamp = u32::from_le_bytes(buf[16..20].try_into().unwrap());
+        let name_rva = u32::from_le_bytes(buf[20..24].try_into().unwrap());
+        let mut version_info = [0u32; 13];
+        for (i, vi) in version_info.iter_mut().enumerate() {
+            *vi = u32::from_le_bytes(buf[24+4*i..24+4*i+4].try_into().unwrap());
+        }
+        let codeview_record = LocationDescriptor::from_bytes(buf[76..84].try_into().unwrap());
+        let misc_record = LocationDescriptor::from_bytes(buf[84..92].try_into().unwrap());
+        let reserved0 = u64::from_le_bytes(buf[92..100].try_into().unwrap());
+        let reserved1 = u64::from_le_bytes(buf[100..108].try_into().unwrap());
+
+        Ok(Self {
+            image_base,
+            image_size,
+            checksum,
+            time_date_stamp,
+            name_rva,
+            version_info,
+            codeview_record,
+            misc_record,
+            reserved0,
+            reserved1,
+            name: String::new(),
+        })
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct LocationDescriptor {
+    pub data_size: u32,
+    pub rva: u32,
+}
+impl LocationDescriptor {
+    pub fn from_bytes(bytes: [u8; 8]) -> Self {
+        let data_size = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+        let rva = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        Self {
+            data_size,
+            rva,
+        }
+    }
+}
+
+
+/// A range of the dumped process's memory that is contained in the dump.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct MemoryRange {
+    pub start_address: u64,
+    pub size: u64,
+    pub file_offset: u64,
+}
+impl MemoryRange {
+    pub fn contains(&self, address: u64) -> bool {
+        address >= self.start_address && address - self.start_address < self.size
+    }
+}
+
+
+/// Reads a string that is prefixed by its u32le length in bytes and encoded in UTF-16LE.
+fn read_minidump_string<R: Read>(reader: &mut R) -> Result<String, io::Error> {
+    let mut length_buf = [0u8; 4];
+    reader.read_exact(&mut length_buf)?;
+    let length_bytes = u32::from_le_bytes(length_buf);
+    if length_bytes % 2 != 0 {
+        debug!("minidump string has odd byte length {}", length_bytes);
+        return Err(io::ErrorKind::InvalidData.into());
+    }
+
+    let mut string_bytes = Vec::new();
+    reader.by_ref().take(length_bytes.into()).read_to_end(&mut string_bytes)?;
+    if string_bytes.len() != usize::try_from(length_bytes).unwrap() {
+        debug!("minidump string is truncated");
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    let words: Vec<u16> = string_bytes
+        .chunks(2)
+        .map(|ch| u16::from_le_bytes(ch.try_into().unwrap()))
+        .collect();
+    String::from_utf16(&words)
+        .inspect_err(|_| debug!("minidump string is invalid UTF-16"))
+        .map_err(|_| io::ErrorKind::InvalidData.into())
+}
@@ -14,6 +14,27 @@ use tracing::debug;
 pub const BYTES_PER_PARAGRAPH: usize = 16;
 pub const BYTES_PER_PAGE: usize = 512;
 
+/// The code of the stub that Microsoft linkers place into NE and PE executables.
+///
+/// Outputs the message at offset 0x0E using `int 21h`/`ah=09h` and exits with code 1.
+pub const CANONICAL_STUB_CODE: [u8; 14] = [
+    0x0E,             // push cs
+    0x1F,             // pop ds
+    0xBA, 0x0E, 0x00, // mov dx, 0x000E
+    0xB4, 0x09,       // mov ah, 0x09
+    0xCD, 0x21,       // int 0x21
+    0xB8, 0x01, 0x4C, // mov ax, 0x4C01
+    0xCD, 0x21,       // int 0x21
+];
+
+/// The messages output by stubs with the canonical code.
+pub const CANONICAL_STUB_MESSAGES: [&[u8]; 2] = [
+    b"This program cannot be run in DOS mode.\r\r\n$",
+    b"This program must be run under Microsoft Windows.\r\n$",
+];
+
+const NEW_HEADER_OFFSET_OFFSET: u64 = 0x3C;
+
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
@@ -92,6 +113,156 @@ impl Executable {
             relocation_entries,
         })
     }
+
+    /// The offset at which the MZ header ends and the executable image (the stub) starts.
+    pub fn image_offset(&self) -> u64 {
+        u64::from(self.header_size_paragraphs) * u64::try_from(BYTES_PER_PARAGRAPH).unwrap()
+    }
+
+    /// The offset at which the executable image ends, according to the page counts.
+    pub fn image_end_offset(&self) -> u64 {
+        let full_bytes = u64::from(self.pages) * u64::try_from(BYTES_PER_PAGE).unwrap();
+        if self.last_page_bytes == 0 {
+            full_bytes
+        } else {
+            full_bytes.saturating_sub(u64::try_from(BYTES_PER_PAGE).unwrap()) + u64::from(self.last_page_bytes)
+        }
+    }
+
+    /// Reads the DOS stub, i.e. the bytes between the end of the MZ header and the start of the
+    /// NE/PE header (or the end of the MZ image if there is no such header).
+    ///
+    /// Microsoft linkers also place the "Rich" header into this area; see [`split_rich_header`].
+    pub fn read_stub<R: Read + Seek>(&self, reader: &mut R) -> Result<Vec<u8>, io::Error> {
+        let start = self.image_offset();
+        let end = if self.relocation_table_offset >= 0x0040 {
+            // new-style executable; stub ends where the new header starts
+            reader.seek(SeekFrom::Start(NEW_HEADER_OFFSET_OFFSET))?;
+            let mut offset_buf = [0u8; 4];
+            reader.read_exact(&mut offset_buf)?;
+            u32::from_le_bytes(offset_buf).into()
+        } else {
+            self.image_end_offset()
+        };
+        if end < start {
+            debug!("stub ends at {:#X} before it starts at {:#X}", end, start);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+
+        reader.seek(SeekFrom::Start(start))?;
+        let mut stub = Vec::new();
+        reader.by_ref().take(end - start).read_to_end(&mut stub)?;
+        if stub.len() != usize::try_from(end - start).unwrap() {
+            debug!("stub ending at {:#X} extends beyond the end of the file", end);
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(stub)
+    }
+}
+
+
+/// The result of comparing a DOS stub to the canonical stub placed into executables by Microsoft
+/// linkers.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum StubComparison {
+    /// The stub is the canonical stub with the message at the given index of
+    /// [`CANONICAL_STUB_MESSAGES`].
+    Canonical { message_index: usize },
+
+    /// The stub differs from every canonical stub.
+    Custom,
+}
+
+/// Compares a DOS stub to the canonical stubs.
+///
+/// A Rich header as well as zero padding at the end of the stub are ignored.
+pub fn compare_stub(stub: &[u8]) -> StubComparison {
+    let (code, _rich) = split_rich_header(stub);
+
+    let mut trimmed = code;
+    while let Some((0x00, rest)) = trimmed.split_last() {
+        trimmed = rest;
+    }
+
+    if let Some(message) = trimmed.strip_prefix(&CANONICAL_STUB_CODE[..]) {
+        for (message_index, canonical_message) in CANONICAL_STUB_MESSAGES.iter().enumerate() {
+            if message == *canonical_message {
+                return StubComparison::Canonical { message_index };
+            }
+        }
+    }
+    StubComparison::Custom
+}
+
+/// Splits a DOS stub into the actual stub and the Rich header (which is placed after the stub by
+/// Microsoft linkers and documents the tools used to create the object files).
+///
+/// The Rich header is returned in its encrypted form, starting with the encrypted `DanS` marker and
+/// ending with the key following the `Rich` marker.
+pub fn split_rich_header(stub: &[u8]) -> (&[u8], Option<&[u8]>) {
+    // find the "Rich" marker, which is aligned to 4 bytes and followed by the key
+    let rich_pos = (0..stub.len().saturating_sub(7))
+        .step_by(4)
+        .rev()
+        .find(|pos| &stub[*pos..*pos+4] == b"Rich");
+    let Some(rich_pos) = rich_pos else {
+        return (stub, None);
+    };
+    let key = u32::from_le_bytes(stub[rich_pos+4..rich_pos+8].try_into().unwrap());
+
+    // go back to the "DanS" marker
+    let dans = u32::from_le_bytes(*b"DanS");
+    let dans_pos = (0..rich_pos)
+        .step_by(4)
+        .rev()
+        .find(|pos| u32::from_le_bytes(stub[*pos..*pos+4].try_into().unwrap()) ^ key == dans);
+    match dans_pos {
+        Some(dans_pos) => (&stub[..dans_pos], Some(&stub[dans_pos..rich_pos+8])),
+        None => (stub, None),
+    }
+}
+
+/// An entry of a Rich header: the number of object files produced by one build of one tool.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct RichEntry {
+    /// The tool, e.g. the C compiler or the assembler of a specific Visual Studio release.
+    pub product_id: u16,
+
+    /// The build number of the tool.
+    pub build: u16,
+
+    pub count: u32,
+}
+
+/// Decrypts a Rich header as returned by [`split_rich_header`] and returns its entries.
+///
+/// Returns `None` if the header is malformed, i.e. its length is not a multiple of 8 or the padding
+/// after the `DanS` marker is not zero.
+pub fn decode_rich_header(rich: &[u8]) -> Option<Vec<RichEntry>> {
+    if rich.len() < 24 || !rich.len().is_multiple_of(8) {
+        return None;
+    }
+    let key = u32::from_le_bytes(rich[rich.len()-4..].try_into().unwrap());
+    let words: Vec<u32> = rich[..rich.len()-8]
+        .chunks_exact(4)
+        .map(|w| u32::from_le_bytes(w.try_into().unwrap()) ^ key)
+        .collect();
+    if words[0] != u32::from_le_bytes(*b"DanS") || words[1..4].iter().any(|w| *w != 0) {
+        debug!("Rich header does not start with DanS and padding: {:08X?}", &words[..4]);
+        return None;
+    }
+
+    let entries = words[4..]
+        .chunks_exact(2)
+        .map(|pair| RichEntry {
+            product_id: (pair[0] >> 16) as u16,
+            build: (pair[0] & 0xFFFF) as u16,
+            count: pair[1],
+        })
+        .collect();
+    Some(entries)
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -100,3 +271,23 @@ pub struct RelocationEntry {
     pub offset: u16,
     pub segment: u16,
 }
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Cursor};
+    use testgen::pe;
+    use super::Executable;
+
+    #[test]
+    fn test_read_stub() {
+        let mut bytes = pe::PortableExecutable::new("SYNTH.DLL").to_bytes();
+        let mz = Executable::read(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(mz.read_stub(&mut Cursor::new(&bytes)).unwrap(), b"");
+
+        // a new header far beyond the end of the file is not allocated up front
+        bytes[0x3C..0x40].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+        let error = mz.read_stub(&mut Cursor::new(&bytes)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -3,7 +3,6 @@
 //! The NE format was introduced with Windows 1.0 and supplanted by PE in Windows NT 3.1 and Windows
 //! 95.
 
-use std::collections::BTreeMap;
 use std::io::{self, Read, Seek, SeekFrom};
 
 use bitflags::bitflags;
@@ -11,6 +10,8 @@ use display_bytes::DisplayBytesVec;
 use from_to_repr::{FromToRepr, from_to_other};
 use tracing::debug;
 
+use crate::resource_map::ResourceMap;
+
 
 const SEGMENTED_HEADER_OFFSET_OFFSET: u64 = 0x3C;
 
@@ -225,6 +226,46 @@ impl Executable {
             non_resident_name_table,
         })
     }
+
+    /// Returns the entry points in the entry table along with their ordinals.
+    ///
+    /// Unused entries are skipped but still count towards the ordinals, which start at 1.
+    pub fn entries(&self) -> Vec<Entry> {
+        let mut ret = Vec::new();
+        let mut ordinal: u16 = 1;
+        for bundle in &self.entry_table {
+            match bundle {
+                EntryBundle::Unused { entry_count } => {
+                    ordinal = ordinal.wrapping_add((*entry_count).into());
+                },
+                EntryBundle::Fixed { segment_number, entries } => {
+                    for entry in entries {
+                        ret.push(Entry {
+                            ordinal,
+                            moveable: false,
+                            flags: entry.flags,
+                            segment_number: *segment_number,
+                            entry_point_offset: entry.entry_point_offset,
+                        });
+                        ordinal = ordinal.wrapping_add(1);
+                    }
+                },
+                EntryBundle::Moveable { entries } => {
+                    for entry in entries {
+                        ret.push(Entry {
+                            ordinal,
+                            moveable: true,
+                            flags: entry.flags,
+                            segment_number: entry.segment_number,
+                            entry_point_offset: entry.entry_point_offset,
+                        });
+                        ordinal = ordinal.wrapping_add(1);
+                    }
+                },
+            }
+        }
+        ret
+    }
 }
 
 
@@ -417,6 +458,24 @@ impl SegmentTableEntry {
             relocation_entries,
         })
     }
+
+    /// Returns the offset of this segment's data from the beginning of the file, in bytes.
+    ///
+    /// An offset of 0 means that the segment has no data in the file.
+    pub fn file_offset_bytes(&self, logical_sector_alignment_shift_count: u16) -> u64 {
+        u64::from(self.logical_sector_offset) * (1 << logical_sector_alignment_shift_count)
+    }
+
+    /// Returns the length of this segment's data in the file, in bytes.
+    ///
+    /// A stored length of 0 is interpreted as 64 KiB.
+    pub fn length_bytes(&self) -> u32 {
+        if self.segment_length == 0 {
+            0x1_0000
+        } else {
+            self.segment_length.into()
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -507,13 +566,18 @@ impl ResourceId {
     }
 }
 
+/// The largest supported resource alignment shift count.
+///
+/// Resource offsets and lengths are 16-bit values in units of `1 << alignment_shift_count` bytes;
+/// larger shift counts would describe resources beyond the 4 GiB that an NE file can address.
+pub const MAX_RESOURCE_ALIGNMENT_SHIFT_COUNT: u16 = 16;
+
 #[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct ResourceTable {
     pub alignment_shift_count: u16,
 
-    #[cfg_attr(feature = "serde", serde(with = "serde_resource_id_value"))]
-    pub id_to_type: BTreeMap<ResourceId, ResourceType>,
+    pub id_to_type: ResourceMap<ResourceId, ResourceType>,
 }
 impl ResourceTable {
     pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
@@ -522,8 +586,17 @@ impl ResourceTable {
         let mut buf = [0u8; 2];
         reader.read_exact(&mut buf)?;
         let alignment_shift_count = u16::from_le_bytes(buf);
+        if alignment_shift_count > MAX_RESOURCE_ALIGNMENT_SHIFT_COUNT {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "resource alignment shift count {} exceeds maximum of {}",
+                    alignment_shift_count, MAX_RESOURCE_ALIGNMENT_SHIFT_COUNT,
+                ),
+            ));
+        }
 
-        let mut id_to_type = BTreeMap::new();
+        let mut id_to_type = ResourceMap::new();
         loop {
             reader.read_exact(&mut buf)?;
             let type_id_value = u16::from_le_bytes(buf);
@@ -538,7 +611,7 @@ impl ResourceTable {
             let count = u16::from_le_bytes(buf2[0..2].try_into().unwrap());
             let reserved = u32::from_le_bytes(buf2[2..6].try_into().unwrap());
 
-            let mut resources = BTreeMap::new();
+            let mut resources = ResourceMap::new();
             for _ in 0..count {
                 let mut resource_buf = [0u8; 12];
                 reader.read_exact(&mut resource_buf)?;
@@ -549,27 +622,34 @@ impl ResourceTable {
                 let resource_id_value = u16::from_le_bytes(resource_buf[6..8].try_into().unwrap());
                 let reserved = u32::from_le_bytes(resource_buf[8..12].try_into().unwrap());
 
-                let file_offset_bytes = u64::from(resource_offset_units) * (1 << alignment_shift_count);
-                let resource_length_bytes = usize::from(resource_length_units) * (1 << alignment_shift_count);
                 let resource_id = ResourceId::from_reader_and_value(reader, resource_id_value, resource_table_pos)?;
-
-                let location = reader.seek(SeekFrom::Current(0))?;
-                reader.seek(SeekFrom::Start(file_offset_bytes))?;
-                let mut data = vec![0u8; resource_length_bytes.into()];
-                reader.read_exact(&mut data)?;
-                reader.seek(SeekFrom::Start(location))?;
-
-                resources.insert(resource_id.clone(), Resource {
+                let mut resource = Resource {
                     resource_offset_units,
                     resource_length_units,
+                    alignment_shift_count,
                     flags,
                     resource_id,
                     reserved,
-                    data: data.into(),
-                });
+                    data: DisplayBytesVec::default(),
+                };
+
+                let location = reader.seek(SeekFrom::Current(0))?;
+                reader.seek(SeekFrom::Start(resource.file_offset_bytes()))?;
+                let mut data = vec![0u8; resource.length_bytes()];
+                reader.read_exact(&mut data)?;
+                reader.seek(SeekFrom::Start(location))?;
+                resource.data = data.into();
+
+                if resources.contains_key(&resource.resource_id) {
+                    debug!("duplicate resource ID {:?} in type {:?}", resource.resource_id, type_id);
+                }
+                resources.push(resource.resource_id.clone(), resource);
             }
 
-            id_to_type.insert(
+            if id_to_type.contains_key(&type_id) {
+                debug!("duplicate resource type ID {:?}", type_id);
+            }
+            id_to_type.push(
                 type_id.clone(),
                 ResourceType {
                     type_id,
@@ -586,33 +666,6 @@ impl ResourceTable {
     }
 }
 
-/// Serializes the map of resource identifiers to some serializable value as pairs of resource
-/// identifiers and values.
-///
-/// This is done to work around limitations in some serialization formats (e.g. JSON) which only
-/// accept string keys.
-#[cfg(feature = "serde")]
-mod serde_resource_id_value {
-    use std::collections::BTreeMap;
-    use serde::{Deserialize, Deserializer, Serialize, Serializer};
-    use super::ResourceId;
-
-    pub(crate) fn serialize<S: Serializer, V: Serialize>(value: &BTreeMap<ResourceId, V>, serializer: S) -> Result<S::Ok, S::Error> {
-        let vector: Vec<(&ResourceId, &V)> = value
-            .iter()
-            .collect();
-        vector.serialize(serializer)
-    }
-
-    pub(crate) fn deserialize<'d, D: Deserializer<'d>, V: Deserialize<'d>>(deserializer: D) -> Result<BTreeMap<ResourceId, V>, D::Error> {
-        let vector: Vec<(ResourceId, V)> = Vec::deserialize(deserializer)?;
-        let map: BTreeMap<ResourceId, V> = vector
-            .into_iter()
-            .collect();
-        Ok(map)
-    }
-}
-
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct ResourceType {
@@ -620,8 +673,7 @@ pub struct ResourceType {
     // count: u16,
     pub reserved: u32,
 
-    #[cfg_attr(feature = "serde", serde(with = "serde_resource_id_value"))]
-    pub resources: BTreeMap<ResourceId, Resource>, // [Resource; count],
+    pub resources: ResourceMap<ResourceId, Resource>, // [Resource; count],
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -629,11 +681,26 @@ pub struct ResourceType {
 pub struct Resource {
     pub resource_offset_units: u16, // (relative to beginning of file, units of (1 << alignment_shift_count))
     pub resource_length_units: u16, // (units of (1 << alignment_shift_count))
+    pub alignment_shift_count: u16, // copied from the containing ResourceTable
     pub flags: ResourceFlags,
     pub resource_id: ResourceId,
     pub reserved: u32,
     pub data: DisplayBytesVec, // [u8; resource_length],
 }
+impl Resource {
+    /// Returns the offset of this resource's data from the beginning of the file, in bytes.
+    pub fn file_offset_bytes(&self) -> u64 {
+        u64::from(self.resource_offset_units) << self.alignment_shift_count
+    }
+
+    /// Returns the length of this resource's data, in bytes.
+    ///
+    /// Since the length is stored in alignment units, this may include some padding after the
+    /// actual data.
+    pub fn length_bytes(&self) -> usize {
+        usize::from(self.resource_length_units) << self.alignment_shift_count
+    }
+}
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
@@ -713,11 +780,23 @@ pub struct MoveableSegmentEntry {
     pub entry_point_offset: u16,
 }
 
+/// An entry point from the entry table, detached from its bundle.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct Entry {
+    pub ordinal: u16,
+    pub moveable: bool,
+    pub flags: SegmentEntryFlags,
+    pub segment_number: u8,
+    pub entry_point_offset: u16,
+}
+
 
 bitflags! {
     #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
     #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
     pub struct ExeFlags : u16 {
+        // program flags (low byte)
         const SINGLE_DATA = 0x0001;
         const MULTIPLE_DATA = 0x0002;
         const GLOBAL_INIT = 0x0004;
@@ -726,7 +805,19 @@ bitflags! {
         const INSTRUCTIONS_286 = 0x0020;
         const INSTRUCTIONS_386 = 0x0040;
         const INSTRUCTIONS_FPU = 0x0080;
+
+        // application flags (high byte); 0x0700 is the application type, see ExeFlags::application_type
+
+        /// On Windows, the application loads its own segments; the header tables (including the
+        /// entry table) need not describe the code that is actually run. On OS/2, the application
+        /// is a family application that has been bound to also run under DOS.
+        const SELF_LOADING = 0x0800;
+
         const LINK_ERRORS = 0x2000;
+
+        /// The library's WEP (Windows Exit Procedure) is to be called on unload.
+        const CALL_WEP = 0x4000;
+
         const LIBRARY_MODULE = 0x8000;
     }
 
@@ -734,9 +825,19 @@ bitflags! {
     #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
     pub struct SegmentFlags : u16 {
         const DATA = 0x0001;
+        const ITERATED = 0x0008;
         const MOVEABLE = 0x0010;
+        const SHAREABLE = 0x0020;
         const PRELOAD = 0x0040;
+
+        /// Execute-only for code segments, read-only for data segments.
+        const RESTRICTED_ACCESS = 0x0080;
+
         const HAS_RELOCATION_INFO = 0x0100;
+        const CONFORMING = 0x0200;
+        const DISCARDABLE = 0x1000;
+
+        /// The discard priority; the lowest bit is also `DISCARDABLE`.
         const DISCARD = 0xF000;
     }
 
@@ -766,6 +867,28 @@ impl SegmentFlags {
     pub fn type_only(self) -> Self {
         Self::from_bits_retain(self.bits() & 0x0007)
     }
+
+    /// The privilege level (ring) at which the segment runs (OS/2 only).
+    pub fn privilege_level(self) -> u8 {
+        ((self.bits() >> 10) & 0b11).try_into().unwrap()
+    }
+}
+
+impl ExeFlags {
+    pub fn application_type(self) -> ApplicationType {
+        ApplicationType::from_base_type(((self.bits() >> 8) & 0b111).try_into().unwrap())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+#[from_to_other(base_type = u8, derive_compare = "as_int")]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum ApplicationType {
+    Unspecified = 0,
+    FullScreen = 1,
+    WindowCompatible = 2,
+    WindowApi = 3,
+    Other(u8),
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -787,3 +910,59 @@ pub enum RelocationEntryTargetType {
     ImportName = 0x02,
     OperatingSystemFixup = 0x03,
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::{Executable, ResourceId};
+    use std::io::Cursor;
+    use testgen::ne;
+
+    #[test]
+    fn test_read_synthesized_resources() {
+        let mut library = ne::NewExecutable::new("SYNTH");
+        library.resources = vec![
+            ne::Resource { type_id: 2, name_id: 1, data: b"bitmap".to_vec() },
+            ne::Resource { type_id: 6, name_id: 1, data: b"string table".to_vec() },
+            ne::Resource { type_id: 6, name_id: 2, data: b"another string table".to_vec() },
+        ];
+        let mut bytes = library.to_bytes();
+        let executable = Executable::read(&mut Cursor::new(&bytes)).unwrap();
+
+        let resource_table = &executable.resource_table;
+        assert_eq!(resource_table.id_to_type.len(), 2);
+        let string_tables = &resource_table.id_to_type[&ResourceId::Numbered(0x8006)].resources;
+        assert_eq!(string_tables.len(), 2);
+        let resource = &string_tables[&ResourceId::Numbered(0x8002)];
+        let data: &[u8] = resource.data.as_ref();
+        assert!(data.starts_with(b"another string table"));
+
+        // the byte offset and length locate the data in the raw file
+        let offset = usize::try_from(resource.file_offset_bytes()).unwrap();
+        assert_eq!(&bytes[offset..offset+resource.length_bytes()], data);
+
+        // overly large alignment shift counts are rejected
+        let ne_offset = usize::try_from(u32::from_le_bytes(bytes[0x3C..0x40].try_into().unwrap())).unwrap();
+        let resource_table_offset = ne_offset + usize::from(u16::from_le_bytes(bytes[ne_offset+0x24..ne_offset+0x26].try_into().unwrap()));
+        bytes[resource_table_offset..resource_table_offset+2].copy_from_slice(&17u16.to_le_bytes());
+        assert!(Executable::read(&mut Cursor::new(&bytes)).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let mut library = ne::NewExecutable::new("SYNTH");
+        library.exports = vec![
+            ne::Export { ordinal: 1, name: Some("WEP".to_owned()), resident: true },
+            ne::Export { ordinal: 2, name: Some("SYNTHPROC".to_owned()), resident: false },
+        ];
+        library.resources = vec![
+            ne::Resource { type_id: 6, name_id: 1, data: b"\x05Hello".to_vec() },
+        ];
+        let executable = Executable::read(&mut Cursor::new(library.to_bytes())).unwrap();
+
+        let json = serde_json::to_value(&executable).unwrap();
+        let read_back: Executable = serde_json::from_value(json).unwrap();
+        assert_eq!(read_back, executable);
+    }
+}
@@ -180,3 +180,70 @@ pub enum DebugType {
     ExtendedDllCharacteristics = 20,
     Other(u32),
 }
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use super::{DbgFile, DebugType, DEBUG_DIRECTORY_SIZE};
+
+    /// A `.dbg` file with one section, two exported names and a COFF and an unknown debug
+    /// directory.
+    fn dbg_file() -> Vec<u8> {
+        let exported_names = b"SynthProc\0Gr\xFC\xDFe\0\0\0";
+        let debug_directories = [(1u32, 0x100u32), (0x1234, 0x20)];
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&0x4944u16.to_le_bytes()); // signature
+        data.extend_from_slice(&0u16.to_le_bytes()); // flags
+        data.extend_from_slice(&0x014Cu16.to_le_bytes()); // machine
+        data.extend_from_slice(&0x2102u16.to_le_bytes()); // characteristics
+        data.extend_from_slice(&0x3200_0000u32.to_le_bytes()); // time date stamp
+        data.extend_from_slice(&0u32.to_le_bytes()); // image checksum
+        data.extend_from_slice(&0x1000_0000u32.to_le_bytes()); // image base
+        data.extend_from_slice(&0x3000u32.to_le_bytes()); // image size
+        data.extend_from_slice(&1u32.to_le_bytes()); // section count
+        data.extend_from_slice(&u32::try_from(exported_names.len()).unwrap().to_le_bytes());
+        data.extend_from_slice(&u32::try_from(debug_directories.len() * DEBUG_DIRECTORY_SIZE).unwrap().to_le_bytes());
+        data.extend_from_slice(&[0u8; 12]);
+
+        let mut section = [0u8; 40];
+        section[0..5].copy_from_slice(b".text");
+        section[8..12].copy_from_slice(&0x1800u32.to_le_bytes());
+        section[12..16].copy_from_slice(&0x1000u32.to_le_bytes());
+        data.extend_from_slice(&section);
+
+        data.extend_from_slice(exported_names);
+
+        for (kind, size) in debug_directories {
+            data.extend_from_slice(&0u32.to_le_bytes()); // characteristics
+            data.extend_from_slice(&0x3200_0000u32.to_le_bytes()); // time date stamp
+            data.extend_from_slice(&0u16.to_le_bytes()); // major version
+            data.extend_from_slice(&0u16.to_le_bytes()); // minor version
+            data.extend_from_slice(&kind.to_le_bytes());
+            data.extend_from_slice(&size.to_le_bytes());
+            data.extend_from_slice(&0u32.to_le_bytes()); // virtual address
+            data.extend_from_slice(&0x200u32.to_le_bytes()); // raw data pointer
+        }
+        data
+    }
+
+    #[test]
+    fn test_read() {
+        let dbg = DbgFile::read(&mut Cursor::new(dbg_file())).unwrap();
+        assert_eq!(dbg.section_table.as_entries().len(), 1);
+        assert_eq!(dbg.exported_names.len(), 2);
+        assert_eq!(dbg.exported_names[1].as_ref() as &[u8], b"Gr\xFC\xDFe");
+        assert_eq!(dbg.debug_directories[0].kind, DebugType::Coff);
+        assert_eq!(dbg.debug_directories[1].kind, DebugType::Other(0x1234));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let dbg = DbgFile::read(&mut Cursor::new(dbg_file())).unwrap();
+        let json = serde_json::to_value(&dbg).unwrap();
+        let read_back: DbgFile = serde_json::from_value(json).unwrap();
+        assert_eq!(read_back, dbg);
+    }
+}
@@ -18,6 +18,12 @@ macro_rules! define_part_int {
         #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
         pub struct $name($base_type);
         impl $name {
+            /// The number of bits that values of this type may occupy.
+            pub const BIT_COUNT: u32 = $bit_count;
+
+            /// The largest value of this type.
+            pub const MAX: Self = Self((1 << $bit_count) - 1);
+
             pub const fn from_base_type(value: $base_type) -> Option<Self> {
                 if value < (1 << $bit_count) {
                     Some(Self(value))
@@ -34,6 +40,11 @@ macro_rules! define_part_int {
                     .ok_or(PartIntError { value })
             }
         }
+        impl From<$name> for $base_type {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
         impl fmt::Display for $name {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
                 write!(f, "{}", self.0)
@@ -62,3 +73,40 @@ macro_rules! define_part_int {
 
 define_part_int!(U3, u8, 3);
 define_part_int!(U4, u8, 4);
+
+
+#[cfg(test)]
+mod tests {
+    use super::{U3, U4};
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_u3_conversion(value in any::<u8>()) {
+            let converted = U3::from_base_type(value);
+            prop_assert_eq!(converted.is_some(), value <= U3::MAX.as_base_type());
+            prop_assert_eq!(converted, U3::try_from(value).ok());
+            if let Some(part) = converted {
+                prop_assert_eq!(u8::from(part), value);
+                prop_assert_eq!(part.to_string(), value.to_string());
+            }
+        }
+
+        #[test]
+        fn test_u4_conversion(value in any::<u8>()) {
+            let converted = U4::from_base_type(value);
+            prop_assert_eq!(converted.is_some(), value <= U4::MAX.as_base_type());
+            prop_assert_eq!(converted, U4::try_from(value).ok());
+            if let Some(part) = converted {
+                prop_assert_eq!(u8::from(part), value);
+                prop_assert_eq!(part.to_string(), value.to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn test_max() {
+        assert_eq!(U3::MAX.as_base_type(), (1 << U3::BIT_COUNT) - 1);
+        assert_eq!(U4::MAX.as_base_type(), (1 << U4::BIT_COUNT) - 1);
+    }
+}
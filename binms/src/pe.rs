@@ -4,8 +4,11 @@
 //! Windows to this day.
 
 use std::collections::BTreeMap;
+use std::fmt;
 use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
 
+use binstruct::{binread_struct, impl_bitflags_field};
 use bitflags::bitflags;
 use display_bytes::{DisplayBytes, DisplayBytesVec};
 use from_to_repr::from_to_other;
@@ -13,11 +16,19 @@ use from_to_repr::from_to_other;
 use serde::{Deserialize, Serialize};
 use tracing::debug;
 
-use crate::{read_nul_terminated_ascii_string, read_pascal_utf16le_string};
+use crate::{decode_byte_name, read_nul_terminated_byte_string, read_pascal_utf16le_string};
+use crate::int_from_byte_slice::IntFromByteSlice;
+use crate::resource_map::ResourceMap;
 
 
 const SEGMENTED_HEADER_OFFSET_OFFSET: u64 = 0x3C;
 
+/// The earliest `TimeDateStamp` considered a plausible build time (1990-01-01T00:00:00Z).
+pub const PLAUSIBLE_TIME_DATE_STAMP_MIN: u32 = 631_152_000;
+
+/// The latest `TimeDateStamp` considered a plausible build time (2030-12-31T23:59:59Z).
+pub const PLAUSIBLE_TIME_DATE_STAMP_MAX: u32 = 1_924_991_999;
+
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Executable {
@@ -100,9 +111,86 @@ impl Executable {
             section_table,
         })
     }
+
+    /// Interprets the `TimeDateStamp` field of the COFF header.
+    pub fn decoded_time_date_stamp(&self) -> TimeDateStamp {
+        TimeDateStamp::decode(self.time_date_stamp)
+    }
+}
+
+
+/// The interpretation of a `TimeDateStamp` field.
+///
+/// Originally, the field contains the build time as seconds since the Unix epoch. Linkers that
+/// produce reproducible builds (e.g. `link.exe /Brepro`) store a hash of the file's contents
+/// instead, which usually corresponds to an implausible time.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum TimeDateStamp {
+    /// The field is zero.
+    Unset,
+
+    /// The field contains a plausible build time, in seconds since 1970-01-01T00:00:00Z.
+    BuildTime(u32),
+
+    /// The field contains a value outside of the plausible range, most likely a hash.
+    ReproducibleBuildHash(u32),
+}
+impl TimeDateStamp {
+    /// Interprets a `TimeDateStamp` value, considering times between
+    /// [`PLAUSIBLE_TIME_DATE_STAMP_MIN`] and [`PLAUSIBLE_TIME_DATE_STAMP_MAX`] plausible.
+    pub fn decode(value: u32) -> Self {
+        if value == 0 {
+            Self::Unset
+        } else if (PLAUSIBLE_TIME_DATE_STAMP_MIN..=PLAUSIBLE_TIME_DATE_STAMP_MAX).contains(&value) {
+            Self::BuildTime(value)
+        } else {
+            Self::ReproducibleBuildHash(value)
+        }
+    }
+
+    /// Formats the build time according to RFC 3339, if the field contains one.
+    pub fn to_rfc3339(&self) -> Option<String> {
+        let Self::BuildTime(seconds) = self else { return None };
+        let (days, seconds_of_day) = (seconds / 86_400, seconds % 86_400);
+        let (year, month, day) = civil_from_days(days);
+        Some(format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year, month, day,
+            seconds_of_day / 3600, (seconds_of_day / 60) % 60, seconds_of_day % 60,
+        ))
+    }
+}
+impl fmt::Display for TimeDateStamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unset => write!(f, "unset"),
+            Self::BuildTime(_) => write!(f, "{}", self.to_rfc3339().unwrap()),
+            Self::ReproducibleBuildHash(hash) => write!(f, "reproducible-build hash {:#010X}", hash),
+        }
+    }
+}
+
+/// Converts a number of days since 1970-01-01 into a year, month and day of the proleptic
+/// Gregorian calendar.
+///
+/// Follows Howard Hinnant's `civil_from_days` algorithm, restricted to dates after the epoch.
+fn civil_from_days(days_since_epoch: u32) -> (u32, u32, u32) {
+    // shift the epoch to 0000-03-01, so that leap days end up at the end of the year
+    let days = days_since_epoch + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era/1460 + day_of_era/36_524 - day_of_era/146_096) / 365;
+    let day_of_year = day_of_era - (365*year_of_era + year_of_era/4 - year_of_era/100);
+    let shifted_month = (5*day_of_year + 2) / 153;
+    let day = day_of_year - (153*shifted_month + 2)/5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era*400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
 }
 
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 #[from_to_other(base_type = u16, derive_compare = "as_int")]
 pub enum MachineType {
     Unknown = 0x0000,
@@ -133,6 +221,7 @@ pub enum MachineType {
     RiscV128 = 0x5128,
     HitachiSh3 = 0x01A2,
     HitachiSh3Dsp = 0x01A3,
+    HitachiSh3E = 0x01A4,
     HitachiSh4 = 0x01A6,
     HitachiSh5 = 0x01A8,
     ArmThumb = 0x01C2,
@@ -386,14 +475,8 @@ impl OptionalHeader {
                     .reserve(data_directory_entry_count.try_into().unwrap());
 
                 for _ in 0..data_directory_entry_count {
-                    let mut entry_buf = [0u8; 8];
-                    reader.read_exact(&mut entry_buf)?;
-                    let address = u32::from_le_bytes(entry_buf[0..4].try_into().unwrap());
-                    let size = u32::from_le_bytes(entry_buf[4..8].try_into().unwrap());
-                    windows_header.data_directory_entries.push(DataDirectoryEntry {
-                        address,
-                        size,
-                    });
+                    let entry = DataDirectoryEntry::read(reader)?;
+                    windows_header.data_directory_entries.push(entry);
                 }
             }
 
@@ -467,6 +550,27 @@ impl OptionalWindowsHeader {
             None
         }
     }
+
+    /// Returns the indexes of the data directory entries whose data contains the given relative
+    /// virtual address.
+    ///
+    /// The certificate table is skipped, since its address is a file offset.
+    pub fn data_directory_entries_for_virtual(&self, virtual_addr: u32) -> Vec<usize> {
+        self.data_directory_entries
+            .iter()
+            .enumerate()
+            .filter(|(index, _entry)| {
+                KnownDataDirectoryEntry::try_from(*index)
+                    .map(|known| !known.address_is_file_offset())
+                    .unwrap_or(true)
+            })
+            .filter(|(_index, entry)| {
+                virtual_addr >= entry.address
+                    && u64::from(virtual_addr) < u64::from(entry.address) + u64::from(entry.size)
+            })
+            .map(|(index, _entry)| index)
+            .collect()
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -562,13 +666,17 @@ bitflags! {
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub struct DataDirectoryEntry {
-    pub address: u32,
-    pub size: u32,
+binread_struct! {
+    #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+    pub struct DataDirectoryEntry {
+        0x00 => pub address: u32,
+        0x04 => pub size: u32,
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum KnownDataDirectoryEntry {
     ExportTable,
     ImportTable,
@@ -609,6 +717,40 @@ impl From<KnownDataDirectoryEntry> for usize {
         }
     }
 }
+impl TryFrom<usize> for KnownDataDirectoryEntry {
+    type Error = usize;
+
+    fn try_from(value: usize) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(KnownDataDirectoryEntry::ExportTable),
+            1 => Ok(KnownDataDirectoryEntry::ImportTable),
+            2 => Ok(KnownDataDirectoryEntry::ResourceTable),
+            3 => Ok(KnownDataDirectoryEntry::ExceptionTable),
+            4 => Ok(KnownDataDirectoryEntry::CertificateTable),
+            5 => Ok(KnownDataDirectoryEntry::BaseRelocationTable),
+            6 => Ok(KnownDataDirectoryEntry::Debug),
+            7 => Ok(KnownDataDirectoryEntry::Architecture),
+            8 => Ok(KnownDataDirectoryEntry::GlobalPtr),
+            9 => Ok(KnownDataDirectoryEntry::TlsTable),
+            10 => Ok(KnownDataDirectoryEntry::LoadConfigTable),
+            11 => Ok(KnownDataDirectoryEntry::BoundImport),
+            12 => Ok(KnownDataDirectoryEntry::ImportAddressTable),
+            13 => Ok(KnownDataDirectoryEntry::DelayImportDescriptor),
+            14 => Ok(KnownDataDirectoryEntry::ClrRuntimeHeader),
+            15 => Ok(KnownDataDirectoryEntry::Reserved15),
+            other => Err(other),
+        }
+    }
+}
+impl KnownDataDirectoryEntry {
+    /// Whether the address in this data directory entry is a file offset instead of a relative
+    /// virtual address.
+    ///
+    /// This is only the case for the certificate table, which is not mapped into memory.
+    pub fn address_is_file_offset(&self) -> bool {
+        *self == KnownDataDirectoryEntry::CertificateTable
+    }
+}
 
 #[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
@@ -711,15 +853,28 @@ impl SectionTable {
         false
     }
 
+    /// Returns the section that contains the given relative virtual address.
+    pub fn section_for_virtual(&self, virtual_addr: u32) -> Option<&SectionTableEntry> {
+        self.entries
+            .iter()
+            .find(|entry| virtual_addr >= entry.virtual_address && virtual_addr - entry.virtual_address < entry.virtual_size)
+    }
+
+    /// Returns the class of the section that contains the given relative virtual address.
+    pub fn class_for_virtual(&self, virtual_addr: u32) -> Option<SectionClass> {
+        self.section_for_virtual(virtual_addr)
+            .map(|entry| entry.class())
+    }
+
     pub fn virtual_to_raw(&self, virtual_addr: u32) -> Option<u32> {
         for entry in &self.entries {
-            if virtual_addr >= entry.virtual_address && virtual_addr < entry.virtual_address + entry.virtual_size {
+            if virtual_addr >= entry.virtual_address && virtual_addr - entry.virtual_address < entry.virtual_size {
                 let offset = virtual_addr - entry.virtual_address;
                 if offset >= entry.raw_data_size {
                     // that won't fit
                     return None;
                 }
-                return Some(entry.raw_data_pointer + offset);
+                return entry.raw_data_pointer.checked_add(offset);
             }
         }
         None
@@ -727,18 +882,116 @@ impl SectionTable {
 
     pub fn raw_to_virtual(&self, raw_addr: u32) -> Option<u32> {
         for entry in &self.entries {
-            if raw_addr >= entry.raw_data_pointer && raw_addr < entry.raw_data_pointer + entry.raw_data_size {
+            if raw_addr >= entry.raw_data_pointer && raw_addr - entry.raw_data_pointer < entry.raw_data_size {
                 let offset = raw_addr - entry.raw_data_pointer;
                 if offset >= entry.virtual_size {
                     // that won't fit
                     return None;
                 }
-                return Some(entry.virtual_address + offset);
+                return entry.virtual_address.checked_add(offset);
             }
         }
         None
     }
 }
+
+/// A reader over the image of a PE file as it is mapped into memory, addressed by relative virtual
+/// address.
+///
+/// Where the virtual size of a section exceeds the size of its raw data, the loader fills the rest
+/// of the section with zeroes; so does this reader. Such reads are recorded, since some packers
+/// rely on them while most well-formed files do not. Addresses outside of all sections are treated
+/// as the end of the file.
+#[derive(Debug)]
+pub struct VirtualReader<'s, R> {
+    reader: R,
+    section_table: &'s SectionTable,
+    position: u32,
+    zero_filled: Vec<Range<u32>>,
+}
+impl<'s, R: Read + Seek> VirtualReader<'s, R> {
+    pub fn new(reader: R, section_table: &'s SectionTable) -> Self {
+        Self {
+            reader,
+            section_table,
+            position: 0,
+            zero_filled: Vec::new(),
+        }
+    }
+
+    /// The ranges of relative virtual addresses read so far that are not backed by raw data and
+    /// have therefore been filled with zeroes.
+    pub fn zero_filled(&self) -> &[Range<u32>] {
+        &self.zero_filled
+    }
+
+    /// The number of bytes from the current position to the end of the section containing it, or
+    /// 0 if the position is outside of all sections.
+    pub fn section_rest(&self) -> u32 {
+        self.section_table.section_for_virtual(self.position)
+            .map(|section| section.virtual_size - (self.position - section.virtual_address))
+            .unwrap_or(0)
+    }
+
+    fn record_zero_fill(&mut self, range: Range<u32>) {
+        if let Some(last) = self.zero_filled.last_mut() && last.end == range.start {
+            last.end = range.end;
+            return;
+        }
+        self.zero_filled.push(range);
+    }
+}
+impl<'s, R: Read + Seek> Read for VirtualReader<'s, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let Some(section) = self.section_table.section_for_virtual(self.position) else {
+            return Ok(0);
+        };
+        let offset = self.position - section.virtual_address;
+        let section_rest = usize::try_from(section.virtual_size - offset).unwrap();
+        let max_count = buf.len().min(section_rest);
+
+        let overflow = || io::Error::new(io::ErrorKind::InvalidData, "section extends beyond the address space");
+        let count = if offset < section.raw_data_size {
+            let raw_rest = usize::try_from(section.raw_data_size - offset).unwrap();
+            let raw_count = max_count.min(raw_rest);
+            let raw_position = section.raw_data_pointer.checked_add(offset)
+                .ok_or_else(overflow)?;
+            self.reader.seek(SeekFrom::Start(raw_position.into()))?;
+            self.reader.read(&mut buf[..raw_count])?
+        } else {
+            buf[..max_count].fill(0);
+            let end = u32::try_from(max_count).ok()
+                .and_then(|c| self.position.checked_add(c))
+                .ok_or_else(overflow)?;
+            self.record_zero_fill(self.position..end);
+            max_count
+        };
+        self.position = u32::try_from(count).ok()
+            .and_then(|c| self.position.checked_add(c))
+            .ok_or_else(overflow)?;
+        Ok(count)
+    }
+}
+impl<'s, R: Read + Seek> Seek for VirtualReader<'s, R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_position = match pos {
+            SeekFrom::Start(p) => u32::try_from(p).ok(),
+            SeekFrom::Current(delta) => i64::from(self.position)
+                .checked_add(delta)
+                .and_then(|p| u32::try_from(p).ok()),
+            SeekFrom::End(_) => return Err(io::ErrorKind::Unsupported.into()),
+        };
+        let Some(new_position) = new_position else {
+            return Err(io::ErrorKind::InvalidInput.into());
+        };
+        self.position = new_position;
+        Ok(new_position.into())
+    }
+}
+
 impl From<Vec<SectionTableEntry>> for SectionTable {
     fn from(value: Vec<SectionTableEntry>) -> Self {
         Self {
@@ -750,48 +1003,20 @@ impl From<SectionTable> for Vec<SectionTableEntry> {
     fn from(value: SectionTable) -> Self { value.entries }
 }
 
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
-#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
-pub struct SectionTableEntry {
-    pub name: DisplayBytes<8>,
-    pub virtual_size: u32,
-    pub virtual_address: u32,
-    pub raw_data_size: u32,
-    pub raw_data_pointer: u32,
-    pub relocations_pointer: u32,
-    pub line_numbers_pointer: u32,
-    pub relocations_count: u16,
-    pub line_numbers_count: u16,
-    pub characteristics: SectionCharacteristics, // u32
-}
-impl SectionTableEntry {
-    pub fn read<R: Read>(reader: &mut R) -> Result<Self, io::Error> {
-        let mut entry_buf = [0u8; 40];
-        reader.read_exact(&mut entry_buf)?;
-
-        let name = entry_buf[0..8].try_into().unwrap();
-        let virtual_size = u32::from_le_bytes(entry_buf[8..12].try_into().unwrap());
-        let virtual_address = u32::from_le_bytes(entry_buf[12..16].try_into().unwrap());
-        let raw_data_size = u32::from_le_bytes(entry_buf[16..20].try_into().unwrap());
-        let raw_data_pointer = u32::from_le_bytes(entry_buf[20..24].try_into().unwrap());
-        let relocations_pointer = u32::from_le_bytes(entry_buf[24..28].try_into().unwrap());
-        let line_numbers_pointer = u32::from_le_bytes(entry_buf[28..32].try_into().unwrap());
-        let relocations_count = u16::from_le_bytes(entry_buf[32..34].try_into().unwrap());
-        let line_numbers_count = u16::from_le_bytes(entry_buf[34..36].try_into().unwrap());
-        let characteristics = SectionCharacteristics::from_bits_retain(u32::from_le_bytes(entry_buf[36..40].try_into().unwrap()));
-
-        Ok(Self {
-            name,
-            virtual_size,
-            virtual_address,
-            raw_data_size,
-            raw_data_pointer,
-            relocations_pointer,
-            line_numbers_pointer,
-            relocations_count,
-            line_numbers_count,
-            characteristics,
-        })
+binread_struct! {
+    #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+    pub struct SectionTableEntry {
+        0x00 => pub name: DisplayBytes<8>,
+        0x08 => pub virtual_size: u32,
+        0x0C => pub virtual_address: u32,
+        0x10 => pub raw_data_size: u32,
+        0x14 => pub raw_data_pointer: u32,
+        0x18 => pub relocations_pointer: u32,
+        0x1C => pub line_numbers_pointer: u32,
+        0x20 => pub relocations_count: u16,
+        0x22 => pub line_numbers_count: u16,
+        0x24 => pub characteristics: SectionCharacteristics,
     }
 }
 
@@ -825,6 +1050,78 @@ bitflags! {
         const MEM_WRITE = 0x8000_0000;
     }
 }
+impl_bitflags_field!(SectionCharacteristics, u32);
+
+impl SectionTableEntry {
+    /// The name of the section without the NUL bytes padding it.
+    pub fn trimmed_name(&self) -> &[u8] {
+        let name: &[u8] = self.name.as_ref();
+        let length = name.iter().rposition(|b| *b != 0x00).map_or(0, |i| i + 1);
+        &name[..length]
+    }
+
+    /// Classifies the section according to its name and characteristics.
+    ///
+    /// Resource, relocation and debug sections are recognized by their conventional names, since
+    /// their characteristics are those of any other read-only data. Other sections are classified
+    /// by their content flags; sections that lack them are classified by the execute flag or,
+    /// failing that, by the names used by Microsoft and Borland linkers.
+    pub fn class(&self) -> SectionClass {
+        let name = self.trimmed_name();
+        if name == b".rsrc" {
+            return SectionClass::Resource;
+        }
+        if name == b".reloc" {
+            return SectionClass::Relocation;
+        }
+        if name.starts_with(b".debug") {
+            return SectionClass::Debug;
+        }
+
+        let characteristics = self.characteristics;
+        if characteristics.intersects(SectionCharacteristics::CONTAINS_CODE | SectionCharacteristics::MEM_EXECUTE) {
+            SectionClass::Code
+        } else if characteristics.contains(SectionCharacteristics::CONTAINS_INITIALIZED_DATA) {
+            SectionClass::InitializedData
+        } else if characteristics.contains(SectionCharacteristics::CONTAINS_UNINITIALIZED_DATA) {
+            SectionClass::UninitializedData
+        } else {
+            match name {
+                b".text" | b"CODE" => SectionClass::Code,
+                b".data" | b".rdata" | b"DATA" => SectionClass::InitializedData,
+                b".bss" | b"BSS" => SectionClass::UninitializedData,
+                _ => SectionClass::Other,
+            }
+        }
+    }
+}
+
+/// What a section of a PE file contains, as determined by [`SectionTableEntry::class`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum SectionClass {
+    Code,
+    InitializedData,
+    UninitializedData,
+    Resource,
+    Relocation,
+    Debug,
+    Other,
+}
+impl fmt::Display for SectionClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Code => "code",
+            Self::InitializedData => "initialized-data",
+            Self::UninitializedData => "uninitialized-data",
+            Self::Resource => "resource",
+            Self::Relocation => "relocation",
+            Self::Debug => "debug",
+            Self::Other => "other",
+        };
+        f.write_str(name)
+    }
+}
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct ExportData {
@@ -842,6 +1139,19 @@ pub struct ExportData {
     // pub ordinal_table_rva: u32,
     pub ordinal_to_address: BTreeMap<u32, ExportAddressTableEntry>,
     pub name_to_ordinal: BTreeMap<String, u32>,
+
+    /// The names and their ordinals in the order in which they are stored in the name pointer
+    /// table.
+    ///
+    /// Should be sorted by name, since the loader performs a binary search; may contain
+    /// duplicates, unlike `name_to_ordinal`.
+    pub name_table: Vec<(String, u32)>,
+
+    /// The names in `name_table` as they are stored in the file.
+    ///
+    /// These only differ from the decoded names if a name is not valid UTF-8; see
+    /// [`decode_byte_name`].
+    pub raw_name_table: Vec<Vec<u8>>,
 }
 impl ExportData {
     pub fn read<R: Read + Seek>(reader: &mut R, export_directory_entry: &DataDirectoryEntry, section_table: &SectionTable) -> Result<Self, io::Error> {
@@ -908,7 +1218,7 @@ impl ExportData {
 
         // read name
         reader.seek(SeekFrom::Start(name_offset.into()))?;
-        let name = read_nul_terminated_ascii_string(reader)?;
+        let name = decode_byte_name(&read_nul_terminated_byte_string(reader)?);
 
         // read address table
         let ordinal_to_address = if address_table_entry_count > 0 {
@@ -931,7 +1241,7 @@ impl ExportData {
 
                     let addr_table_pos = reader.seek(SeekFrom::Current(0))?;
                     reader.seek(SeekFrom::Start(addr_pos.into()))?;
-                    let target = read_nul_terminated_ascii_string(reader)?;
+                    let target = decode_byte_name(&read_nul_terminated_byte_string(reader)?);
                     reader.seek(SeekFrom::Start(addr_table_pos))?;
                     ordinal_to_address.insert(ordinal, ExportAddressTableEntry::Forwarder { target });
                 } else {
@@ -945,8 +1255,8 @@ impl ExportData {
         };
 
         // read names
-        let name_table = if has_names {
-            let mut name_table = Vec::with_capacity(name_pointer_and_ordinal_table_entry_count.try_into().unwrap());
+        let raw_name_table = if has_names {
+            let mut raw_name_table = Vec::with_capacity(name_pointer_and_ordinal_table_entry_count.try_into().unwrap());
             reader.seek(SeekFrom::Start(name_pointer_offset.into()))?;
             for i in 0..name_pointer_and_ordinal_table_entry_count {
                 let mut address_buf = [0u8; 4];
@@ -957,11 +1267,11 @@ impl ExportData {
                     .inspect_err(|_| debug!("failed to convert name {} offset virtual to raw", i))?;
                 let name_pointer_pos = reader.seek(SeekFrom::Current(0))?;
                 reader.seek(SeekFrom::Start(offset.into()))?;
-                let name = read_nul_terminated_ascii_string(reader)?;
+                let name = read_nul_terminated_byte_string(reader)?;
                 reader.seek(SeekFrom::Start(name_pointer_pos))?;
-                name_table.push(name);
+                raw_name_table.push(name);
             }
-            name_table
+            raw_name_table
         } else {
             Vec::with_capacity(0)
         };
@@ -985,9 +1295,13 @@ impl ExportData {
         };
 
         // join the preceding two tables
-        let name_to_ordinal: BTreeMap<String, u32> = name_table.into_iter()
+        let name_table: Vec<(String, u32)> = raw_name_table.iter()
+            .map(|raw_name| decode_byte_name(raw_name))
             .zip(name_ordinal_table.into_iter())
             .collect();
+        let name_to_ordinal: BTreeMap<String, u32> = name_table.iter()
+            .cloned()
+            .collect();
 
         reader.seek(SeekFrom::Start(position))?;
         Ok(Self {
@@ -999,8 +1313,29 @@ impl ExportData {
             ordinal_base,
             ordinal_to_address,
             name_to_ordinal,
+            name_table,
+            raw_name_table,
         })
     }
+
+    /// Whether the name pointer table is sorted by the byte values of the names, as required by
+    /// the loader's binary search.
+    pub fn is_name_table_sorted(&self) -> bool {
+        self.raw_name_table
+            .windows(2)
+            .all(|pair| pair[0] <= pair[1])
+    }
+
+    /// Returns the hint of the given name, i.e. its index in the name pointer table.
+    ///
+    /// Importing files store the hint next to the imported name; the loader tries the name at that
+    /// index first and only falls back to a binary search if it does not match.
+    pub fn hint_for_name(&self, name: &str) -> Option<u16> {
+        let index = self.name_table
+            .iter()
+            .position(|(n, _ordinal)| n == name)?;
+        index.try_into().ok()
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1013,17 +1348,32 @@ pub enum ExportAddressTableEntry {
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct ResourceDirectoryTable {
+    /// Resource flags; reserved and generally 0.
     pub characteristics: u32,
+
+    /// The time at which the resource compiler created this table, in seconds since the Unix epoch.
+    ///
+    /// Many resource compilers leave this at 0.
     pub timestamp: u32,
+
+    /// Major version number, as set by the resource compiler.
     pub major_version: u16,
+
+    /// Minor version number, as set by the resource compiler.
     pub minor_version: u16,
+
     // name_entry_count: u16,
     // id_entry_count: u16,
-    #[cfg_attr(feature = "serde", serde(with = "serde_resource_id_child"))]
-    pub id_to_entry: BTreeMap<ResourceIdentifier, ResourceChild>, // [(ResourceIdentifier, ResourceChild); name_entry_count + id_entry_count]
+    pub id_to_entry: ResourceMap<ResourceIdentifier, ResourceChild>, // [(ResourceIdentifier, ResourceChild); name_entry_count + id_entry_count]
 }
 impl ResourceDirectoryTable {
-    pub fn read_from_pe<R: Read + Seek>(reader: &mut R, resources_start_virtual: u32, section_table: &SectionTable) -> Result<Self, io::Error> {
+    /// Reads a resource directory table from the current position of the reader.
+    ///
+    /// Fails once the [time limit](binstruct::deadline) has been exceeded, as malformed trees can
+    /// make the walk take practically forever.
+    pub fn read_from_pe<R: Read + Seek>(reader: &mut VirtualReader<'_, R>, resources_start_virtual: u32) -> Result<Self, io::Error> {
+        binstruct::deadline::check()?;
+
         let mut header_buf = [0u8; 16];
         reader.read_exact(&mut header_buf)?;
 
@@ -1041,12 +1391,12 @@ impl ResourceDirectoryTable {
         reader.read_exact(&mut entry_bytes)?;
 
         // collect the entries
-        let mut id_to_entry = BTreeMap::new();
+        let mut id_to_entry = ResourceMap::new();
         let mut rest = entry_bytes.as_slice();
 
         for _ in 0..name_entry_count {
-            let name_offset = u32::from_le_bytes(rest[0..4].try_into().unwrap());
-            let value_offset = u32::from_le_bytes(rest[4..8].try_into().unwrap());
+            let name_offset = u32::try_from_le_byte_slice(rest, 0)?;
+            let value_offset = u32::try_from_le_byte_slice(rest, 4)?;
 
             // the name offset should have the top bit set
             if name_offset & 0x8000_0000 == 0 {
@@ -1054,11 +1404,7 @@ impl ResourceDirectoryTable {
                 return Err(io::ErrorKind::InvalidData.into());
             }
             let name_position_virtual = resources_start_virtual + (name_offset & 0x7FFF_FFFF);
-            let Some(name_position_raw) = section_table.virtual_to_raw(name_position_virtual) else {
-                debug!("failed to find entry name raw position for virtual position {:#010X}", name_position_virtual);
-                return Err(io::ErrorKind::InvalidData.into());
-            };
-            reader.seek(SeekFrom::Start(name_position_raw.into()))?;
+            reader.seek(SeekFrom::Start(name_position_virtual.into()))?;
             let name = read_pascal_utf16le_string(reader)?;
 
             // decode the data
@@ -1066,41 +1412,33 @@ impl ResourceDirectoryTable {
                 reader,
                 resources_start_virtual,
                 value_offset,
-                section_table,
             )?;
 
-            let old_entry_opt = id_to_entry.insert(
-                ResourceIdentifier::Name(name.clone()),
-                data,
-            );
-            if old_entry_opt.is_some() {
-                debug!("duplicate resource key {:?}", ResourceIdentifier::Name(name));
-                return Err(io::ErrorKind::InvalidData.into());
+            let id = ResourceIdentifier::Name(name);
+            if id_to_entry.contains_key(&id) {
+                debug!("duplicate resource key {:?}", id);
             }
+            id_to_entry.push(id, data);
 
             rest = &rest[8..];
         }
 
         for _ in 0..id_entry_count {
-            let id = u32::from_le_bytes(rest[0..4].try_into().unwrap());
-            let value_offset = u32::from_le_bytes(rest[4..8].try_into().unwrap());
+            let id = u32::try_from_le_byte_slice(rest, 0)?;
+            let value_offset = u32::try_from_le_byte_slice(rest, 4)?;
 
             // decode the data
             let data = ResourceChild::read_from_pe(
                 reader,
                 resources_start_virtual,
                 value_offset,
-                section_table,
             )?;
 
-            let old_entry_opt = id_to_entry.insert(
-                ResourceIdentifier::Integer(id),
-                data,
-            );
-            if old_entry_opt.is_some() {
-                debug!("duplicate resource key {:?}", ResourceIdentifier::Integer(id));
-                return Err(io::ErrorKind::InvalidData.into());
+            let id = ResourceIdentifier::Integer(id);
+            if id_to_entry.contains_key(&id) {
+                debug!("duplicate resource key {:?}", id);
             }
+            id_to_entry.push(id, data);
 
             rest = &rest[8..];
         }
@@ -1114,56 +1452,84 @@ impl ResourceDirectoryTable {
         })
     }
 
+    /// Reads the resource tree of a PE file.
+    ///
+    /// Parts of the tree beyond the raw data of their section are read as zeroes, as the loader
+    /// does; use [`read_root_from_pe_with_zero_fill`](Self::read_root_from_pe_with_zero_fill) to
+    /// find out whether this was necessary.
     pub fn read_root_from_pe<R: Read + Seek>(reader: &mut R, resource_table_directory_entry: &DataDirectoryEntry, section_table: &SectionTable) -> Result<Self, io::Error> {
+        Self::read_root_from_pe_with_zero_fill(reader, resource_table_directory_entry, section_table)
+            .map(|(root, _zero_filled)| root)
+    }
+
+    /// Reads the resource tree of a PE file, additionally returning the ranges of relative virtual
+    /// addresses that had to be filled with zeroes because they are not backed by raw data.
+    pub fn read_root_from_pe_with_zero_fill<R: Read + Seek>(reader: &mut R, resource_table_directory_entry: &DataDirectoryEntry, section_table: &SectionTable) -> Result<(Self, Vec<Range<u32>>), io::Error> {
         // ensure the sections don't overlap
         if section_table.has_overlap() {
             debug!("sections overlap while trying to read resource root");
             return Err(io::ErrorKind::InvalidData.into());
         }
+        if section_table.section_for_virtual(resource_table_directory_entry.address).is_none() {
+            debug!("resource table address {:#010X} is not within any section", resource_table_directory_entry.address);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
 
         let position = reader.seek(SeekFrom::Current(0))?;
 
-        // go to offset of resource table directory
-        let resource_table_directory_offset = section_table.virtual_to_raw(resource_table_directory_entry.address)
-            .ok_or_else(|| io::ErrorKind::InvalidData)
-            .inspect_err(|_| debug!("failed to convert resource table address from virtual to raw"))?;
-        reader.seek(SeekFrom::Start(resource_table_directory_offset.into()))?;
-
         // recursively read the topmost table
-        let ret = Self::read_from_pe(reader, resource_table_directory_entry.address, section_table)?;
+        let mut virtual_reader = VirtualReader::new(&mut *reader, section_table);
+        virtual_reader.seek(SeekFrom::Start(resource_table_directory_entry.address.into()))?;
+        let ret = Self::read_from_pe(&mut virtual_reader, resource_table_directory_entry.address)?;
+        let zero_filled = virtual_reader.zero_filled().to_vec();
 
         // return to original position
         reader.seek(SeekFrom::Start(position))?;
 
         // done
-        Ok(ret)
+        Ok((ret, zero_filled))
     }
-}
 
-/// Serializes the map of resource identifiers to resource children as pairs of resource identifiers
-/// and children.
-///
-/// This is done to work around limitations in some serialization formats (e.g. JSON) which only
-/// accept string keys.
-#[cfg(feature = "serde")]
-mod serde_resource_id_child {
-    use std::collections::BTreeMap;
-    use serde::{Deserialize, Deserializer, Serialize, Serializer};
-    use super::{ResourceChild, ResourceIdentifier};
+    /// Adds the entries of another resource tree that are missing from this one.
+    ///
+    /// Subdirectories present in both trees are merged recursively; where both trees contain data
+    /// (or one contains data and the other a subdirectory) under the same identifier, this tree's
+    /// entry is kept. Duplicate identifiers are resolved as in [`ResourceMap::get`]. The relative
+    /// virtual addresses of the added data refer to the file that `other` was read from.
+    pub fn merge(&mut self, other: ResourceDirectoryTable) {
+        for (id, other_child) in other.id_to_entry {
+            match (self.id_to_entry.get_mut(&id), other_child) {
+                (None, other_child) => {
+                    self.id_to_entry.push(id, other_child);
+                },
+                (Some(ResourceChild::Subdirectory(subdir)), ResourceChild::Subdirectory(other_subdir)) => {
+                    subdir.merge(other_subdir);
+                },
+                (Some(_), _) => {},
+            }
+        }
+    }
 
-    pub(crate) fn serialize<S: Serializer>(value: &BTreeMap<ResourceIdentifier, ResourceChild>, serializer: S) -> Result<S::Ok, S::Error> {
-        let vector: Vec<(&ResourceIdentifier, &ResourceChild)> = value
-            .iter()
-            .collect();
-        vector.serialize(serializer)
+    /// Returns all the data entries in the tree below this table, in tree order, each with the
+    /// identifiers of the entries leading to it.
+    ///
+    /// In a well-formed resource tree, the identifiers are those of the type, the resource and the
+    /// language.
+    pub fn data_entries(&self) -> Vec<(Vec<&ResourceIdentifier>, &ResourceData)> {
+        let mut entries = Vec::new();
+        self.collect_data_entries(&mut Vec::new(), &mut entries);
+        entries
     }
 
-    pub(crate) fn deserialize<'d, D: Deserializer<'d>>(deserializer: D) -> Result<BTreeMap<ResourceIdentifier, ResourceChild>, D::Error> {
-        let vector: Vec<(ResourceIdentifier, ResourceChild)> = Vec::deserialize(deserializer)?;
-        let map: BTreeMap<ResourceIdentifier, ResourceChild> = vector
-            .into_iter()
-            .collect();
-        Ok(map)
+    fn collect_data_entries<'a>(&'a self, path: &mut Vec<&'a ResourceIdentifier>, entries: &mut Vec<(Vec<&'a ResourceIdentifier>, &'a ResourceData)>) {
+        for (id, child) in &self.id_to_entry {
+            path.push(id);
+            match child {
+                ResourceChild::Data(data) => entries.push((path.clone(), data)),
+                ResourceChild::Subdirectory(subdir) => subdir.collect_data_entries(path, entries),
+            }
+            path.pop();
+        }
     }
 }
 
@@ -1183,27 +1549,19 @@ pub enum ResourceChild {
     Subdirectory(ResourceDirectoryTable),
 }
 impl ResourceChild {
-    pub fn read_from_pe<R: Read + Seek>(reader: &mut R, resources_start_virtual: u32, value_offset_virtual: u32, section_table: &SectionTable) -> Result<Self, io::Error> {
+    pub fn read_from_pe<R: Read + Seek>(reader: &mut VirtualReader<'_, R>, resources_start_virtual: u32, value_offset_virtual: u32) -> Result<Self, io::Error> {
         // check the top bit of the value offset to see if this is a data or subdirectory node
         if value_offset_virtual & 0x8000_0000 == 0 {
             // data
             let data_loc_virtual = resources_start_virtual + value_offset_virtual;
-            let Some(data_loc_raw) = section_table.virtual_to_raw(data_loc_virtual) else {
-                debug!("failed to find entry value raw position for virtual position {:#010X}", data_loc_virtual);
-                return Err(io::ErrorKind::InvalidData.into());
-            };
-            reader.seek(SeekFrom::Start(data_loc_raw.into()))?;
-            let data = ResourceData::read_from_pe(reader, section_table)?;
+            reader.seek(SeekFrom::Start(data_loc_virtual.into()))?;
+            let data = ResourceData::read_from_pe(reader)?;
             Ok(Self::Data(data))
         } else {
             // subdirectory
             let subdir_loc_virtual = resources_start_virtual + (value_offset_virtual & 0x7FFF_FFFF);
-            let Some(subdir_loc_raw) = section_table.virtual_to_raw(subdir_loc_virtual) else {
-                debug!("failed to find entry value raw position for virtual position {:#010X}", subdir_loc_virtual);
-                return Err(io::ErrorKind::InvalidData.into());
-            };
-            reader.seek(SeekFrom::Start(subdir_loc_raw.into()))?;
-            let subdir = ResourceDirectoryTable::read_from_pe(reader, resources_start_virtual, section_table)?;
+            reader.seek(SeekFrom::Start(subdir_loc_virtual.into()))?;
+            let subdir = ResourceDirectoryTable::read_from_pe(reader, resources_start_virtual)?;
             Ok(Self::Subdirectory(subdir))
         }
     }
@@ -1219,7 +1577,7 @@ pub struct ResourceData {
     pub data: Option<DisplayBytesVec>, // size bytes at data_rva; None if loading fails
 }
 impl ResourceData {
-    pub fn read_from_pe<R: Read + Seek>(reader: &mut R, section_table: &SectionTable) -> Result<Self, io::Error> {
+    pub fn read_from_pe<R: Read + Seek>(reader: &mut VirtualReader<'_, R>) -> Result<Self, io::Error> {
         let mut header_buf = [0u8; 16];
         reader.read_exact(&mut header_buf)?;
 
@@ -1230,13 +1588,15 @@ impl ResourceData {
 
         // try our luck
         let mut data = None;
-        if let Some(data_raw) = section_table.virtual_to_raw(data_rva) {
-            if let Ok(_) = reader.seek(SeekFrom::Start(data_raw.into())) {
-                if let Ok(size_usize) = usize::try_from(size) {
-                    let mut buf = vec![0u8; size_usize];
-                    if let Ok(_) = reader.read_exact(&mut buf) {
-                        data = Some(DisplayBytesVec::from(buf));
-                    }
+        if let Ok(_) = reader.seek(SeekFrom::Start(data_rva.into())) {
+            // zero-filling makes reads up to the end of the section succeed; don't allocate more
+            // than that on the word of a single entry
+            if size > reader.section_rest() {
+                debug!("resource data at {:#010X} ({} bytes) extends beyond its section", data_rva, size);
+            } else if let Ok(size_usize) = usize::try_from(size) {
+                let mut buf = vec![0u8; size_usize];
+                if let Ok(_) = reader.read_exact(&mut buf) {
+                    data = Some(DisplayBytesVec::from(buf));
                 }
             }
         }
@@ -1250,3 +1610,1214 @@ impl ResourceData {
         })
     }
 }
+
+/// The contents of the exception directory (`.pdata`) of an x64 or ARM64 image.
+///
+/// Each entry describes the extent of a non-leaf function along with information on how to unwind
+/// its stack frame; this also covers functions that are not exported.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct ExceptionData {
+    pub runtime_functions: Vec<RuntimeFunction>,
+}
+impl ExceptionData {
+    pub fn read<R: Read + Seek>(reader: &mut R, machine_type: MachineType, exception_directory_entry: &DataDirectoryEntry, section_table: &SectionTable) -> Result<Self, io::Error> {
+        let entry_size: u32 = match machine_type {
+            MachineType::Amd64 => 12,
+            MachineType::Arm64 => 8,
+            other => {
+                debug!("exception directory parsing not supported for machine type {:?}", other);
+                return Err(io::ErrorKind::Unsupported.into());
+            },
+        };
+
+        let position = reader.stream_position()?;
+
+        let exception_directory_offset = section_table.virtual_to_raw(exception_directory_entry.address)
+            .ok_or(io::ErrorKind::InvalidData)
+            .inspect_err(|_| debug!("failed to convert exception directory entry address from virtual to raw"))?;
+        reader.seek(SeekFrom::Start(exception_directory_offset.into()))?;
+
+        // the size is not to be trusted; the reads fail at the end of the file anyway
+        let entry_count = exception_directory_entry.size / entry_size;
+        let mut entries = Vec::new();
+        for _ in 0..entry_count {
+            let mut buf = [0u8; 12];
+            reader.read_exact(&mut buf[..usize::try_from(entry_size).unwrap()])?;
+            let begin_address = u32::from_le_bytes(buf[0..4].try_into().unwrap());
+            if begin_address == 0 {
+                // padding at the end of the table
+                continue;
+            }
+            entries.push((
+                begin_address,
+                u32::from_le_bytes(buf[4..8].try_into().unwrap()),
+                u32::from_le_bytes(buf[8..12].try_into().unwrap()),
+            ));
+        }
+
+        let mut runtime_functions = Vec::with_capacity(entries.len());
+        for (begin_address, second, third) in entries {
+            let runtime_function = if machine_type == MachineType::Amd64 {
+                // second is the end address, third is the unwind info address
+                let unwind_info = X64UnwindInfo::read_from_pe(reader, third, section_table)
+                    .inspect_err(|e| debug!("failed to read unwind info at {:#010X}: {}", third, e))
+                    .ok();
+                RuntimeFunction {
+                    begin_address,
+                    end_address: second,
+                    unwind: UnwindData::X64 {
+                        unwind_info_address: third,
+                        unwind_info,
+                    },
+                }
+            } else {
+                // second contains either packed unwind data or the unwind info address
+                let flag = second & 0b11;
+                if flag == 0 {
+                    let unwind_info = Arm64UnwindInfo::read_from_pe(reader, second, section_table)
+                        .inspect_err(|e| debug!("failed to read unwind info at {:#010X}: {}", second, e))
+                        .ok();
+                    let end_address = unwind_info.as_ref()
+                        .map(|ui| begin_address.wrapping_add(ui.function_length))
+                        .unwrap_or(begin_address);
+                    RuntimeFunction {
+                        begin_address,
+                        end_address,
+                        unwind: UnwindData::Arm64 {
+                            unwind_info_address: second,
+                            unwind_info,
+                        },
+                    }
+                } else {
+                    let packed = Arm64PackedUnwindData::from_u32(second);
+                    RuntimeFunction {
+                        begin_address,
+                        end_address: begin_address.wrapping_add(packed.function_length),
+                        unwind: UnwindData::Arm64Packed(packed),
+                    }
+                }
+            };
+            runtime_functions.push(runtime_function);
+        }
+
+        reader.seek(SeekFrom::Start(position))?;
+        Ok(Self {
+            runtime_functions,
+        })
+    }
+}
+
+/// The extent of a function along with the information how to unwind it.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct RuntimeFunction {
+    pub begin_address: u32,
+
+    /// The address of the first byte after the function.
+    ///
+    /// Stored directly on x64; calculated from the function length on ARM64. If the function
+    /// length cannot be obtained, this is equal to `begin_address`.
+    pub end_address: u32,
+
+    pub unwind: UnwindData,
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum UnwindData {
+    X64 { unwind_info_address: u32, unwind_info: Option<X64UnwindInfo> }, // None if loading fails
+    Arm64 { unwind_info_address: u32, unwind_info: Option<Arm64UnwindInfo> }, // None if loading fails
+    Arm64Packed(Arm64PackedUnwindData),
+}
+
+bitflags! {
+    #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+    pub struct X64UnwindFlags : u8 {
+        /// The function has an exception handler.
+        const EHANDLER = 0x01;
+
+        /// The function has a termination handler.
+        const UHANDLER = 0x02;
+
+        /// The unwind info is followed by the runtime function entry of the primary function.
+        const CHAININFO = 0x04;
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct X64UnwindInfo {
+    pub version: u8, // u3
+    pub flags: X64UnwindFlags, // u5
+    pub prolog_size: u8,
+    // pub unwind_code_count: u8,
+    pub frame_register: u8, // u4
+    pub frame_offset: u8, // u4; scaled by 16
+    pub unwind_codes: Vec<X64UnwindCode>,
+
+    /// The address of the exception or termination handler; only if `EHANDLER` or `UHANDLER` is
+    /// set.
+    pub handler_address: Option<u32>,
+
+    /// The begin, end and unwind info addresses of the primary function; only if `CHAININFO` is
+    /// set.
+    pub chained_function: Option<(u32, u32, u32)>,
+}
+impl X64UnwindInfo {
+    pub fn read_from_pe<R: Read + Seek>(reader: &mut R, unwind_info_address: u32, section_table: &SectionTable) -> Result<Self, io::Error> {
+        let offset = section_table.virtual_to_raw(unwind_info_address)
+            .ok_or(io::ErrorKind::InvalidData)?;
+        reader.seek(SeekFrom::Start(offset.into()))?;
+
+        let mut header_buf = [0u8; 4];
+        reader.read_exact(&mut header_buf)?;
+        let version = header_buf[0] & 0b111;
+        let flags = X64UnwindFlags::from_bits_retain(header_buf[0] >> 3);
+        let prolog_size = header_buf[1];
+        let unwind_code_count = header_buf[2];
+        let frame_register = header_buf[3] & 0xF;
+        let frame_offset = header_buf[3] >> 4;
+
+        // the slot array is padded to an even count
+        let slot_count = (usize::from(unwind_code_count) + 1) & !1;
+        let mut slot_bytes = vec![0u8; slot_count * 2];
+        reader.read_exact(&mut slot_bytes)?;
+        let slots: Vec<u16> = slot_bytes
+            .chunks(2)
+            .map(|ch| u16::try_from_le_byte_slice(ch, 0))
+            .collect::<Result<_, _>>()?;
+        let unwind_codes = X64UnwindCode::decode_all(&slots[..usize::from(unwind_code_count)])?;
+
+        let mut handler_address = None;
+        let mut chained_function = None;
+        if flags.contains(X64UnwindFlags::CHAININFO) {
+            let mut chain_buf = [0u8; 12];
+            reader.read_exact(&mut chain_buf)?;
+            chained_function = Some((
+                u32::from_le_bytes(chain_buf[0..4].try_into().unwrap()),
+                u32::from_le_bytes(chain_buf[4..8].try_into().unwrap()),
+                u32::from_le_bytes(chain_buf[8..12].try_into().unwrap()),
+            ));
+        } else if flags.intersects(X64UnwindFlags::EHANDLER | X64UnwindFlags::UHANDLER) {
+            let mut handler_buf = [0u8; 4];
+            reader.read_exact(&mut handler_buf)?;
+            handler_address = Some(u32::from_le_bytes(handler_buf));
+        }
+
+        Ok(Self {
+            version,
+            flags,
+            prolog_size,
+            frame_register,
+            frame_offset,
+            unwind_codes,
+            handler_address,
+            chained_function,
+        })
+    }
+}
+
+/// A single operation performed by the prolog of an x64 function.
+///
+/// Registers are given as their numbers in the order RAX, RCX, RDX, RBX, RSP, RBP, RSI, RDI, R8
+/// through R15; see [`x64_register_name`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct X64UnwindCode {
+    /// Offset of the end of the instruction within the prolog.
+    pub prolog_offset: u8,
+    pub operation: X64UnwindOperation,
+}
+impl X64UnwindCode {
+    fn decode_all(slots: &[u16]) -> Result<Vec<Self>, io::Error> {
+        let mut codes = Vec::new();
+        let mut i = 0;
+        while i < slots.len() {
+            let prolog_offset = (slots[i] & 0xFF) as u8;
+            let op = ((slots[i] >> 8) & 0xF) as u8;
+            let info = (slots[i] >> 12) as u8;
+
+            let slot = |index: usize| slots.get(i + index)
+                .copied()
+                .ok_or(io::ErrorKind::InvalidData);
+            let slot_u32 = |index: usize| -> Result<u32, io::Error> {
+                Ok(u32::from(slot(index)?) | (u32::from(slot(index + 1)?) << 16))
+            };
+
+            let (operation, slot_count) = match op {
+                0 => (X64UnwindOperation::PushNonVolatile { register: info }, 1),
+                1 if info == 0 => (X64UnwindOperation::Allocate { size: u32::from(slot(1)?) * 8 }, 2),
+                1 => (X64UnwindOperation::Allocate { size: slot_u32(1)? }, 3),
+                2 => (X64UnwindOperation::Allocate { size: u32::from(info) * 8 + 8 }, 1),
+                3 => (X64UnwindOperation::SetFramePointer, 1),
+                4 => (X64UnwindOperation::SaveNonVolatile { register: info, offset: u32::from(slot(1)?) * 8 }, 2),
+                5 => (X64UnwindOperation::SaveNonVolatile { register: info, offset: slot_u32(1)? }, 3),
+                6 => (X64UnwindOperation::Epilog { info, data: slot(1)? }, 2),
+                7 => (X64UnwindOperation::Other { op, info }, 3),
+                8 => (X64UnwindOperation::SaveXmm128 { register: info, offset: u32::from(slot(1)?) * 16 }, 2),
+                9 => (X64UnwindOperation::SaveXmm128 { register: info, offset: slot_u32(1)? }, 3),
+                10 => (X64UnwindOperation::PushMachineFrame { with_error_code: info == 1 }, 1),
+                _ => (X64UnwindOperation::Other { op, info }, 1),
+            };
+            codes.push(Self {
+                prolog_offset,
+                operation,
+            });
+            i += slot_count;
+        }
+        Ok(codes)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum X64UnwindOperation {
+    /// `UWOP_PUSH_NONVOL`
+    PushNonVolatile { register: u8 },
+
+    /// `UWOP_ALLOC_SMALL` or `UWOP_ALLOC_LARGE`
+    Allocate { size: u32 },
+
+    /// `UWOP_SET_FPREG`; the register and offset are stored in the unwind info.
+    SetFramePointer,
+
+    /// `UWOP_SAVE_NONVOL` or `UWOP_SAVE_NONVOL_FAR`
+    SaveNonVolatile { register: u8, offset: u32 },
+
+    /// `UWOP_EPILOG`, found in version 2 unwind info.
+    Epilog { info: u8, data: u16 },
+
+    /// `UWOP_SAVE_XMM128` or `UWOP_SAVE_XMM128_FAR`
+    SaveXmm128 { register: u8, offset: u32 },
+
+    /// `UWOP_PUSH_MACHFRAME`
+    PushMachineFrame { with_error_code: bool },
+
+    Other { op: u8, info: u8 },
+}
+
+/// Returns the name of the x64 general-purpose register with the given number, as used in unwind
+/// codes.
+pub fn x64_register_name(register: u8) -> &'static str {
+    const NAMES: [&str; 16] = [
+        "rax", "rcx", "rdx", "rbx", "rsp", "rbp", "rsi", "rdi",
+        "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15",
+    ];
+    NAMES.get(usize::from(register)).copied().unwrap_or("?")
+}
+
+/// Unwind data of an ARM64 function, packed into the runtime function entry.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Arm64PackedUnwindData {
+    /// 1 for a complete function, 2 for a fragment without a prolog.
+    pub flag: u8, // u2
+
+    /// The length of the function in bytes (stored as an 11-bit count of instructions).
+    pub function_length: u32,
+
+    /// The number of non-volatile floating-point registers (d8 onward) saved.
+    pub saved_fp_registers: u8, // u3
+
+    /// The number of non-volatile integer registers (x19 onward) saved.
+    pub saved_int_registers: u8, // u4
+
+    /// Whether the parameter registers x0 through x7 are homed.
+    pub homes_parameters: bool,
+
+    /// Whether and how the frame chain and link register are saved.
+    pub cr: u8, // u2
+
+    /// The size of the stack frame in bytes (stored as a 9-bit count of 16-byte units).
+    pub frame_size: u32,
+}
+impl Arm64PackedUnwindData {
+    pub fn from_u32(value: u32) -> Self {
+        Self {
+            flag: (value & 0b11) as u8,
+            function_length: ((value >> 2) & 0x7FF) * 4,
+            saved_fp_registers: ((value >> 13) & 0b111) as u8,
+            saved_int_registers: ((value >> 16) & 0xF) as u8,
+            homes_parameters: (value >> 20) & 0b1 != 0,
+            cr: ((value >> 21) & 0b11) as u8,
+            frame_size: (value >> 23) * 16,
+        }
+    }
+}
+
+/// Unwind information of an ARM64 function, stored separately in the `.xdata` section.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Arm64UnwindInfo {
+    /// The length of the function in bytes (stored as an 18-bit count of instructions).
+    pub function_length: u32,
+
+    pub version: u8, // u2
+
+    /// If set, `epilog_scopes` is empty and `packed_epilog_index` is the index of the first unwind
+    /// code of the only epilog.
+    pub single_packed_epilog: bool,
+
+    pub packed_epilog_index: Option<u16>,
+
+    pub epilog_scopes: Vec<Arm64EpilogScope>,
+
+    /// The unwind codes in their byte-oriented encoding, including the trailing padding.
+    pub unwind_codes: DisplayBytesVec,
+
+    /// The address of the exception handler, if any.
+    pub handler_address: Option<u32>,
+}
+impl Arm64UnwindInfo {
+    pub fn read_from_pe<R: Read + Seek>(reader: &mut R, unwind_info_address: u32, section_table: &SectionTable) -> Result<Self, io::Error> {
+        let offset = section_table.virtual_to_raw(unwind_info_address)
+            .ok_or(io::ErrorKind::InvalidData)?;
+        reader.seek(SeekFrom::Start(offset.into()))?;
+
+        let mut header_buf = [0u8; 4];
+        reader.read_exact(&mut header_buf)?;
+        let header = u32::from_le_bytes(header_buf);
+        let function_length = (header & 0x3_FFFF) * 4;
+        let version = ((header >> 18) & 0b11) as u8;
+        let has_exception_data = (header >> 20) & 0b1 != 0;
+        let single_packed_epilog = (header >> 21) & 0b1 != 0;
+        let mut epilog_count = (header >> 22) & 0x1F;
+        let mut code_words = header >> 27;
+
+        if epilog_count == 0 && code_words == 0 {
+            // extended header
+            reader.read_exact(&mut header_buf)?;
+            let extended_header = u32::from_le_bytes(header_buf);
+            epilog_count = extended_header & 0xFFFF;
+            code_words = (extended_header >> 16) & 0xFF;
+        }
+
+        let mut packed_epilog_index = None;
+        let mut epilog_scopes = Vec::new();
+        if single_packed_epilog {
+            packed_epilog_index = Some(epilog_count as u16);
+        } else {
+            for _ in 0..epilog_count {
+                let mut scope_buf = [0u8; 4];
+                reader.read_exact(&mut scope_buf)?;
+                epilog_scopes.push(Arm64EpilogScope::from_u32(u32::from_le_bytes(scope_buf)));
+            }
+        }
+
+        let mut unwind_codes = vec![0u8; usize::try_from(code_words * 4).unwrap()];
+        reader.read_exact(&mut unwind_codes)?;
+
+        let handler_address = if has_exception_data {
+            let mut handler_buf = [0u8; 4];
+            reader.read_exact(&mut handler_buf)?;
+            Some(u32::from_le_bytes(handler_buf))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            function_length,
+            version,
+            single_packed_epilog,
+            packed_epilog_index,
+            epilog_scopes,
+            unwind_codes: DisplayBytesVec::from(unwind_codes),
+            handler_address,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Arm64EpilogScope {
+    /// The offset of the epilog relative to the start of the function, in bytes.
+    pub start_offset: u32,
+
+    /// The index of the first unwind code byte describing this epilog.
+    pub start_index: u16,
+}
+impl Arm64EpilogScope {
+    pub fn from_u32(value: u32) -> Self {
+        Self {
+            start_offset: (value & 0x3_FFFF) * 4,
+            start_index: (value >> 22) as u16,
+        }
+    }
+}
+
+
+/// The timestamp value of an import descriptor whose imports have been bound using the bound
+/// import directory.
+pub const NEW_STYLE_BINDING_TIMESTAMP: u32 = 0xFFFF_FFFF;
+
+/// The value of the optional header magic field in PE32+ (64-bit) files.
+pub const PE32_PLUS_MAGIC: u16 = 0x020B;
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct ImportDescriptor {
+    pub import_lookup_table_rva: u32,
+
+    /// 0 if the imports are not bound, [`NEW_STYLE_BINDING_TIMESTAMP`] if they are bound according
+    /// to the bound import directory, otherwise the timestamp of the DLL to which the imports have
+    /// been bound (old-style binding).
+    pub time_date_stamp: u32,
+
+    pub forwarder_chain: u32,
+    pub name_rva: u32,
+    pub import_address_table_rva: u32,
+    pub name: String, // at name_rva
+}
+impl ImportDescriptor {
+    /// Reads the import descriptors from the import directory, up to the terminating all-zero
+    /// descriptor.
+    pub fn read_all<R: Read + Seek>(reader: &mut R, import_directory_entry: &DataDirectoryEntry, section_table: &SectionTable) -> Result<Vec<Self>, io::Error> {
+        let import_directory_offset = section_table.virtual_to_raw(import_directory_entry.address)
+            .ok_or(io::ErrorKind::InvalidData)
+            .inspect_err(|_| debug!("failed to convert import directory entry address from virtual to raw"))?;
+
+        let mut descriptors = Vec::new();
+        let mut descriptor_offset = u64::from(import_directory_offset);
+        loop {
+            reader.seek(SeekFrom::Start(descriptor_offset))?;
+            let mut buf = [0u8; 20];
+            reader.read_exact(&mut buf)?;
+            if buf.iter().all(|b| *b == 0x00) {
+                break;
+            }
+            descriptor_offset += 20;
+
+            let import_lookup_table_rva = u32::from_le_bytes(buf[0..4].try_into().unwrap());
+            let time_date_stamp = u32::from_le_bytes(buf[4..8].try_into().unwrap());
+            let forwarder_chain = u32::from_le_bytes(buf[8..12].try_into().unwrap());
+            let name_rva = u32::from_le_bytes(buf[12..16].try_into().unwrap());
+            let import_address_table_rva = u32::from_le_bytes(buf[16..20].try_into().unwrap());
+
+            let name_offset = section_table.virtual_to_raw(name_rva)
+                .ok_or(io::ErrorKind::InvalidData)
+                .inspect_err(|_| debug!("failed to convert import name address {:#010X} from virtual to raw", name_rva))?;
+            reader.seek(SeekFrom::Start(name_offset.into()))?;
+            let name = decode_byte_name(&read_nul_terminated_byte_string(reader)?);
+
+            descriptors.push(Self {
+                import_lookup_table_rva,
+                time_date_stamp,
+                forwarder_chain,
+                name_rva,
+                import_address_table_rva,
+                name,
+            });
+        }
+        Ok(descriptors)
+    }
+
+    /// Reads the entries of the import lookup table, up to the terminating zero entry.
+    ///
+    /// The entries are 64 bits wide in PE32+ files and 32 bits wide otherwise. If the descriptor
+    /// has no import lookup table (as produced by some older linkers), the import address table is
+    /// read instead, which only works if the imports have not been bound.
+    pub fn read_lookup_entries<R: Read + Seek>(&self, reader: &mut R, section_table: &SectionTable, is_pe32_plus: bool) -> Result<Vec<ImportLookupEntry>, io::Error> {
+        let table_rva = if self.import_lookup_table_rva != 0 {
+            self.import_lookup_table_rva
+        } else {
+            self.import_address_table_rva
+        };
+        let table_offset = section_table.virtual_to_raw(table_rva)
+            .ok_or(io::ErrorKind::InvalidData)
+            .inspect_err(|_| debug!("failed to convert import lookup table address {:#010X} from virtual to raw", table_rva))?;
+
+        let entry_size: u64 = if is_pe32_plus { 8 } else { 4 };
+        let mut entries = Vec::new();
+        let mut entry_offset = u64::from(table_offset);
+        loop {
+            reader.seek(SeekFrom::Start(entry_offset))?;
+            entry_offset += entry_size;
+            let (is_ordinal, value) = if is_pe32_plus {
+                let mut buf = [0u8; 8];
+                reader.read_exact(&mut buf)?;
+                let entry = u64::from_le_bytes(buf);
+                (entry & (1 << 63) != 0, entry & 0x7FFF_FFFF)
+            } else {
+                let mut buf = [0u8; 4];
+                reader.read_exact(&mut buf)?;
+                let entry = u32::from_le_bytes(buf);
+                (entry & (1 << 31) != 0, u64::from(entry & 0x7FFF_FFFF))
+            };
+            if !is_ordinal && value == 0 {
+                break;
+            }
+
+            if is_ordinal {
+                entries.push(ImportLookupEntry::ByOrdinal { ordinal: (value & 0xFFFF) as u16 });
+                continue;
+            }
+
+            // hint/name table entry
+            let hint_name_rva: u32 = value.try_into().unwrap();
+            let hint_name_offset = section_table.virtual_to_raw(hint_name_rva)
+                .ok_or(io::ErrorKind::InvalidData)
+                .inspect_err(|_| debug!("failed to convert hint/name address {:#010X} from virtual to raw", hint_name_rva))?;
+            reader.seek(SeekFrom::Start(hint_name_offset.into()))?;
+            let mut hint_buf = [0u8; 2];
+            reader.read_exact(&mut hint_buf)?;
+            let hint = u16::from_le_bytes(hint_buf);
+            let name = decode_byte_name(&read_nul_terminated_byte_string(reader)?);
+            entries.push(ImportLookupEntry::ByName { hint, name });
+        }
+        Ok(entries)
+    }
+}
+
+/// A function imported from a DLL, as described by an entry in the import lookup table.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum ImportLookupEntry {
+    ByOrdinal { ordinal: u16 },
+
+    /// Imported by name; `hint` is the index in the exporting DLL's name pointer table at which
+    /// the name is expected (see [`ExportData::hint_for_name`]).
+    ByName { hint: u16, name: String },
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct BoundImportDescriptor {
+    /// The timestamp of the DLL to which the imports have been bound.
+    pub time_date_stamp: u32,
+
+    pub module_name: String, // u16 offset relative to the start of the bound import directory
+    // forwarder_ref_count: u16,
+
+    /// The DLLs to which the bound DLL forwards some of the imported functions.
+    pub forwarder_refs: Vec<BoundForwarderRef>, // [BoundForwarderRef; forwarder_ref_count]
+}
+impl BoundImportDescriptor {
+    /// Reads the descriptors from the bound import directory, up to the terminating all-zero
+    /// descriptor.
+    pub fn read_all<R: Read + Seek>(reader: &mut R, bound_import_directory_entry: &DataDirectoryEntry, section_table: &SectionTable) -> Result<Vec<Self>, io::Error> {
+        // the bound import directory is generally stored after the section table, outside of any
+        // section; the headers are mapped at the same offset as in the file
+        let address = bound_import_directory_entry.address;
+        let directory_offset = section_table.virtual_to_raw(address)
+            .unwrap_or(address);
+
+        // don't allocate more than the rest of the section (or of the headers) on the word of the
+        // data directory
+        let directory_rest = match section_table.section_for_virtual(address) {
+            Some(section) => section.raw_data_size.saturating_sub(address - section.virtual_address),
+            None => section_table.as_entries().iter()
+                .map(|section| section.raw_data_pointer)
+                .filter(|pointer| *pointer > 0)
+                .min()
+                .map(|headers_end| headers_end.saturating_sub(directory_offset))
+                .unwrap_or(0),
+        };
+        if bound_import_directory_entry.size > directory_rest {
+            debug!(
+                "bound import directory at {:#010X} ({} bytes) extends beyond its section; only reading {} bytes",
+                address, bound_import_directory_entry.size, directory_rest,
+            );
+        }
+        let directory_size = usize::try_from(bound_import_directory_entry.size.min(directory_rest)).unwrap();
+
+        reader.seek(SeekFrom::Start(directory_offset.into()))?;
+        let mut directory = vec![0u8; directory_size];
+        reader.read_exact(&mut directory)?;
+
+        let module_name = |offset: u16| -> Result<String, io::Error> {
+            let name_bytes = directory.get(usize::from(offset)..)
+                .ok_or(io::ErrorKind::InvalidData)
+                .inspect_err(|_| debug!("bound import module name offset {} is outside the directory", offset))?;
+            crate::collect_nul_terminated_ascii_string(name_bytes)
+                .ok_or(io::ErrorKind::InvalidData)
+                .inspect_err(|_| debug!("bound import module name at offset {} is invalid", offset))
+                .map_err(io::Error::from)
+        };
+
+        let mut descriptors = Vec::new();
+        let mut records = directory.chunks_exact(8);
+        while let Some(record) = records.next() {
+            if record.iter().all(|b| *b == 0x00) {
+                break;
+            }
+            let time_date_stamp = u32::try_from_le_byte_slice(record, 0)?;
+            let module_name_offset = u16::try_from_le_byte_slice(record, 4)?;
+            let forwarder_ref_count = u16::try_from_le_byte_slice(record, 6)?;
+
+            let mut forwarder_refs = Vec::with_capacity(forwarder_ref_count.into());
+            for _ in 0..forwarder_ref_count {
+                let forwarder_record = records.next()
+                    .ok_or(io::ErrorKind::UnexpectedEof)
+                    .inspect_err(|_| debug!("bound import forwarder references exceed the directory"))?;
+                let forwarder_time_date_stamp = u32::try_from_le_byte_slice(forwarder_record, 0)?;
+                let forwarder_name_offset = u16::try_from_le_byte_slice(forwarder_record, 4)?;
+                forwarder_refs.push(BoundForwarderRef {
+                    time_date_stamp: forwarder_time_date_stamp,
+                    module_name: module_name(forwarder_name_offset)?,
+                });
+            }
+
+            descriptors.push(Self {
+                time_date_stamp,
+                module_name: module_name(module_name_offset)?,
+                forwarder_refs,
+            });
+        }
+        Ok(descriptors)
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct BoundForwarderRef {
+    pub time_date_stamp: u32,
+    pub module_name: String, // u16 offset relative to the start of the bound import directory
+    // reserved: u16,
+}
+
+
+/// The parts of the load configuration directory that describe the security features the image
+/// was built with.
+///
+/// The directory has grown with almost every Windows release; its `size` field states how much of
+/// it the linker knew about. Fields beyond that size are `None`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct LoadConfigDirectory {
+    pub size: u32,
+
+    /// The virtual address (not RVA) of the cookie used by the stack buffer overrun checks of
+    /// Visual C++ (`/GS`); 0 if the image does not use them.
+    pub security_cookie: Option<u64>,
+
+    /// The virtual address of the table of safe exception handlers (`/SAFESEH`); 32-bit x86 only.
+    pub se_handler_table: Option<u64>,
+
+    /// The number of entries in the table of safe exception handlers; 32-bit x86 only.
+    pub se_handler_count: Option<u64>,
+}
+impl LoadConfigDirectory {
+    /// Reads the load configuration directory. `is_pe32_plus` selects the 64-bit layout.
+    pub fn read<R: Read + Seek>(reader: &mut R, load_config_directory_entry: &DataDirectoryEntry, section_table: &SectionTable, is_pe32_plus: bool) -> Result<Self, io::Error> {
+        let directory_offset = section_table.virtual_to_raw(load_config_directory_entry.address)
+            .ok_or(io::ErrorKind::InvalidData)
+            .inspect_err(|_| debug!("load config address {:#010X} is not within any section", load_config_directory_entry.address))?;
+        reader.seek(SeekFrom::Start(directory_offset.into()))?;
+        let mut size_buf = [0u8; 4];
+        reader.read_exact(&mut size_buf)?;
+        let size = u32::from_le_bytes(size_buf);
+
+        // the size in the data directory entry is unreliable (Windows XP required it to be 64 on
+        // x86 regardless of the actual structure); trust the structure's own size field
+        let mut directory = vec![0u8; usize::try_from(size.clamp(4, 0x1000)).unwrap()];
+        directory[0..4].copy_from_slice(&size_buf);
+        reader.read_exact(&mut directory[4..])?;
+
+        let field = |offset: usize, is_u64: bool| -> Option<u64> {
+            if is_u64 {
+                u64::try_from_le_byte_slice(&directory, offset).ok()
+            } else {
+                u32::try_from_le_byte_slice(&directory, offset).ok().map(u64::from)
+            }
+        };
+        let (security_cookie, se_handler_table, se_handler_count) = if is_pe32_plus {
+            (field(0x58, true), field(0x60, true), field(0x68, true))
+        } else {
+            (field(0x3C, false), field(0x40, false), field(0x44, false))
+        };
+        Ok(Self {
+            size,
+            security_cookie,
+            se_handler_table,
+            se_handler_count,
+        })
+    }
+
+    /// Whether the image uses the stack buffer overrun checks of Visual C++ (`/GS`).
+    pub fn has_security_cookie(&self) -> bool {
+        self.security_cookie.is_some_and(|c| c != 0)
+    }
+
+    /// Whether the image lists its safe exception handlers (`/SAFESEH`).
+    pub fn has_safe_seh(&self) -> bool {
+        self.se_handler_table.is_some_and(|t| t != 0)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Arm64PackedUnwindData, BoundImportDescriptor, Executable, ExportData, ImportDescriptor,
+        ImportLookupEntry, NEW_STYLE_BINDING_TIMESTAMP, X64UnwindCode, X64UnwindOperation, KnownDataDirectoryEntry, OptionalHeader, PE32_PLUS_MAGIC, ResourceChild,
+        ResourceData, ResourceDirectoryTable, ResourceIdentifier, SectionCharacteristics, SectionClass,
+        SectionTable, SectionTableEntry, TimeDateStamp, VirtualReader,
+    };
+    use std::io::{self, Cursor, Read, Seek, SeekFrom};
+    use std::time::Duration;
+    use testgen::pe;
+
+    #[test]
+    fn test_time_date_stamp() {
+        assert_eq!(TimeDateStamp::decode(0), TimeDateStamp::Unset);
+        assert_eq!(TimeDateStamp::decode(0x3B8530F6).to_string(), "2001-08-23T16:36:06Z");
+        assert_eq!(TimeDateStamp::decode(631_152_000).to_rfc3339().unwrap(), "1990-01-01T00:00:00Z");
+        assert_eq!(TimeDateStamp::decode(1_924_991_999).to_rfc3339().unwrap(), "2030-12-31T23:59:59Z");
+        assert_eq!(TimeDateStamp::decode(951_782_400).to_rfc3339().unwrap(), "2000-02-29T00:00:00Z");
+        assert_eq!(TimeDateStamp::decode(0xE1F5C8A3), TimeDateStamp::ReproducibleBuildHash(0xE1F5C8A3));
+        assert_eq!(TimeDateStamp::decode(0x12345678).to_rfc3339(), None);
+    }
+
+    #[test]
+    fn test_read_synthesized_resources() {
+        let mut library = pe::PortableExecutable::new("SYNTH.DLL");
+        library.resources = vec![
+            pe::Resource { type_id: 6, name_id: 1, language_id: 0x0409, data: b"strings".to_vec() },
+            pe::Resource { type_id: 16, name_id: 1, language_id: 0x0407, data: b"version (de)".to_vec() },
+            pe::Resource { type_id: 16, name_id: 1, language_id: 0x0409, data: b"version (en)".to_vec() },
+        ];
+        let bytes = library.to_bytes();
+
+        let mut reader = Cursor::new(bytes.as_slice());
+        let executable = Executable::read(&mut reader).unwrap();
+        let Some(OptionalHeader::Coff(coff)) = &executable.optional_header else { panic!("no COFF optional header") };
+        let windows = coff.optional_windows_header.as_ref().unwrap();
+        let resource_entry = windows.known_data_directory_entry(KnownDataDirectoryEntry::ResourceTable).unwrap();
+        assert_eq!(
+            windows.data_directory_entries_for_virtual(resource_entry.address + 1),
+            [usize::from(KnownDataDirectoryEntry::ResourceTable)],
+        );
+        let root = ResourceDirectoryTable::read_root_from_pe(&mut reader, &resource_entry, &executable.section_table)
+            .unwrap();
+
+        // the walk gives up once the time limit has been exceeded
+        let timed_out = {
+            let _deadline = binstruct::deadline::set_time_limit(Some(Duration::ZERO));
+            ResourceDirectoryTable::read_root_from_pe(&mut reader, &resource_entry, &executable.section_table)
+        };
+        assert_eq!(timed_out.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        let lookup = |table: &ResourceDirectoryTable, id: u32| match table.id_to_entry.get(&ResourceIdentifier::Integer(id)) {
+            Some(ResourceChild::Subdirectory(subdirectory)) => subdirectory.clone(),
+            other => panic!("expected subdirectory for {}, got {:?}", id, other),
+        };
+        let version_names = lookup(&root, 16);
+        let version_languages = lookup(&version_names, 1);
+        assert_eq!(version_languages.id_to_entry.len(), 2);
+        let Some(ResourceChild::Data(german)) = version_languages.id_to_entry.get(&ResourceIdentifier::Integer(0x0407)) else {
+            panic!("no German version resource");
+        };
+        let german_data: &[u8] = german.data.as_ref().unwrap().as_ref();
+        assert_eq!(german_data, b"version (de)");
+    }
+
+    #[test]
+    fn test_read_resources_beyond_raw_data() {
+        let mut library = pe::PortableExecutable::new("SYNTH.DLL");
+        library.resources = vec![
+            pe::Resource { type_id: 16, name_id: 1, language_id: 0x0407, data: b"version (de)".to_vec() },
+            pe::Resource { type_id: 16, name_id: 1, language_id: 0x0409, data: b"version (en)".to_vec() },
+        ];
+        let mut bytes = library.to_bytes();
+
+        // cut off the raw data of the resource section right before the English resource
+        let header_offset = bytes.windows(8).position(|w| w == b".rsrc\0\0\0").unwrap();
+        let raw_pointer = u32::from_le_bytes(bytes[header_offset+20..header_offset+24].try_into().unwrap());
+        let data_offset = bytes.windows(12).position(|w| w == b"version (en)").unwrap();
+        let raw_size = u32::try_from(data_offset).unwrap() - raw_pointer;
+        bytes[header_offset+16..header_offset+20].copy_from_slice(&raw_size.to_le_bytes());
+
+        let mut reader = Cursor::new(bytes.as_slice());
+        let executable = Executable::read(&mut reader).unwrap();
+        let Some(OptionalHeader::Coff(coff)) = &executable.optional_header else { panic!("no COFF optional header") };
+        let windows = coff.optional_windows_header.as_ref().unwrap();
+        let resource_entry = windows.known_data_directory_entry(KnownDataDirectoryEntry::ResourceTable).unwrap();
+        let (root, zero_filled) = ResourceDirectoryTable::read_root_from_pe_with_zero_fill(&mut reader, &resource_entry, &executable.section_table)
+            .unwrap();
+
+        let Some(ResourceChild::Subdirectory(names)) = root.id_to_entry.get(&ResourceIdentifier::Integer(16)) else { panic!("no version resources") };
+        let Some(ResourceChild::Subdirectory(languages)) = names.id_to_entry.get(&ResourceIdentifier::Integer(1)) else { panic!("no version resource 1") };
+        let data = |language: u32| match languages.id_to_entry.get(&ResourceIdentifier::Integer(language)) {
+            Some(ResourceChild::Data(d)) => d.clone(),
+            other => panic!("expected data for language {:#06X}, got {:?}", language, other),
+        };
+        let german = data(0x0407);
+        let german_data: &[u8] = german.data.as_ref().unwrap().as_ref();
+        assert_eq!(german_data, b"version (de)");
+        let english = data(0x0409);
+        let english_data: &[u8] = english.data.as_ref().unwrap().as_ref();
+        assert_eq!(english_data, [0u8; 12]);
+        assert_eq!(zero_filled, [english.data_rva..english.data_rva+12]);
+    }
+
+    #[test]
+    fn test_virtual_reader_rejects_overflowing_sections() {
+        let data = [0x55u8; 64];
+
+        // raw data pointer plus offset overflows
+        let section_table = SectionTable::from(vec![SectionTableEntry {
+            virtual_address: 0x1000,
+            virtual_size: 0x100,
+            raw_data_size: 0x100,
+            raw_data_pointer: 0xFFFF_FFF0,
+            ..SectionTableEntry::default()
+        }]);
+        let mut reader = VirtualReader::new(Cursor::new(&data[..]), &section_table);
+        reader.seek(SeekFrom::Start(0x1020)).unwrap();
+        let mut buf = [0u8; 4];
+        assert_eq!(reader.read(&mut buf).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+
+        // section extends to the end of the address space
+        let section_table = SectionTable::from(vec![SectionTableEntry {
+            virtual_address: 0xFFFF_FF00,
+            virtual_size: 0x100,
+            ..SectionTableEntry::default()
+        }]);
+        assert!(section_table.section_for_virtual(0xFFFF_FFFF).is_some());
+        assert!(section_table.virtual_to_raw(0xFFFF_FFFF).is_none());
+        let mut reader = VirtualReader::new(Cursor::new(&data[..]), &section_table);
+        reader.seek(SeekFrom::Start(0xFFFF_FFF0)).unwrap();
+        let mut buf = [0xAAu8; 8];
+        assert_eq!(reader.read(&mut buf).unwrap(), 8);
+        assert_eq!(buf, [0u8; 8]);
+        let mut buf = [0xAAu8; 32];
+        assert_eq!(reader.read(&mut buf).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_resource_data_larger_than_section() {
+        let section_table = SectionTable::from(vec![SectionTableEntry {
+            virtual_address: 0x1000,
+            virtual_size: 0x100,
+            raw_data_size: 0x20,
+            raw_data_pointer: 0,
+            ..SectionTableEntry::default()
+        }]);
+        let mut data = vec![0u8; 0x20];
+        data[0..4].copy_from_slice(&0x1010u32.to_le_bytes());
+
+        // fits into the virtual section; the rest is zero-filled
+        data[4..8].copy_from_slice(&0x80u32.to_le_bytes());
+        let mut reader = VirtualReader::new(Cursor::new(&data[..]), &section_table);
+        reader.seek(SeekFrom::Start(0x1000)).unwrap();
+        let resource = ResourceData::read_from_pe(&mut reader).unwrap();
+        let resource_data: &[u8] = resource.data.as_ref().unwrap().as_ref();
+        assert_eq!(resource_data.len(), 0x80);
+
+        // a huge size is not allocated
+        data[4..8].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+        let mut reader = VirtualReader::new(Cursor::new(&data[..]), &section_table);
+        reader.seek(SeekFrom::Start(0x1000)).unwrap();
+        let resource = ResourceData::read_from_pe(&mut reader).unwrap();
+        assert!(resource.data.is_none());
+    }
+
+    #[test]
+    fn test_decode_x64_unwind_codes() {
+        use X64UnwindOperation::*;
+        let cases: &[(&[u16], &[(u8, X64UnwindOperation)])] = &[
+            // push rbp; sub rsp, 0x20
+            (&[0x3205, 0x5001], &[(0x05, Allocate { size: 0x20 }), (0x01, PushNonVolatile { register: 5 })]),
+            // UWOP_ALLOC_LARGE with a scaled 16-bit and an unscaled 32-bit size
+            (&[0x010C, 0x0200], &[(0x0C, Allocate { size: 0x1000 })]),
+            (&[0x110C, 0x2345, 0x0001], &[(0x0C, Allocate { size: 0x0001_2345 })]),
+            // lea rbp, [rsp+...]
+            (&[0x0308], &[(0x08, SetFramePointer)]),
+            // mov [rsp+0x30], rbx; mov [rsp+0x12345678], rsi
+            (&[0x3410, 0x0006], &[(0x10, SaveNonVolatile { register: 3, offset: 0x30 })]),
+            (&[0x6512, 0x5678, 0x1234], &[(0x12, SaveNonVolatile { register: 6, offset: 0x1234_5678 })]),
+            // movaps [rsp+0x20], xmm6; movaps [rsp+0x10000], xmm7
+            (&[0x6818, 0x0002], &[(0x18, SaveXmm128 { register: 6, offset: 0x20 })]),
+            (&[0x791C, 0x0000, 0x0001], &[(0x1C, SaveXmm128 { register: 7, offset: 0x0001_0000 })]),
+            // interrupt frame with error code
+            (&[0x1A00], &[(0x00, PushMachineFrame { with_error_code: true })]),
+            // version 2 epilog code
+            (&[0x1601, 0x0002], &[(0x01, Epilog { info: 1, data: 0x0002 })]),
+        ];
+        for (slots, expected) in cases {
+            let codes = X64UnwindCode::decode_all(slots).unwrap();
+            let actual: Vec<(u8, X64UnwindOperation)> = codes.iter()
+                .map(|c| (c.prolog_offset, c.operation))
+                .collect();
+            assert_eq!(actual.as_slice(), *expected, "slots {:04X?}", slots);
+        }
+
+        // operations that are missing their additional slots
+        for slots in [&[0x010Cu16][..], &[0x110C, 0x2345], &[0x3410], &[0x791C, 0x0000]] {
+            assert!(X64UnwindCode::decode_all(slots).is_err(), "slots {:04X?}", slots);
+        }
+    }
+
+    #[test]
+    fn test_decode_arm64_packed_unwind_data() {
+        let cases = [
+            (0x0162_0041, Arm64PackedUnwindData {
+                flag: 1,
+                function_length: 0x40,
+                saved_fp_registers: 0,
+                saved_int_registers: 2,
+                homes_parameters: false,
+                cr: 3,
+                frame_size: 0x20,
+            }),
+            (0xFF9F_FFFE, Arm64PackedUnwindData {
+                flag: 2,
+                function_length: 0x1FFC,
+                saved_fp_registers: 7,
+                saved_int_registers: 15,
+                homes_parameters: true,
+                cr: 0,
+                frame_size: 0x1FF0,
+            }),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(Arm64PackedUnwindData::from_u32(value), expected, "value {:#010X}", value);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_exception_data_json_round_trip() {
+        use super::{DataDirectoryEntry, ExceptionData, MachineType, UnwindData};
+
+        fn put(data: &mut [u8], rva: u32, values: &[u32]) {
+            let offset = usize::try_from(rva - 0x1000).unwrap();
+            for (i, value) in values.iter().enumerate() {
+                data[offset+4*i..offset+4*i+4].copy_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        let section_table = SectionTable::from(vec![
+            SectionTableEntry { virtual_size: 0x400, virtual_address: 0x1000, raw_data_size: 0x400, ..Default::default() },
+        ]);
+        let directory_entry = DataDirectoryEntry { address: 0x1000, size: 4 * 12 };
+
+        // x64: a prolog with two codes, a function with an exception handler, unreadable unwind
+        // info and padding
+        let mut data = vec![0u8; 0x400];
+        put(&mut data, 0x1000, &[
+            0x1100, 0x1140, 0x1200,
+            0x1180, 0x11A0, 0x1220,
+            0x11A0, 0x11B0, 0x9000,
+            0, 0, 0,
+        ]);
+        put(&mut data, 0x1200, &[0x0002_0601, 0x5001_3205]);
+        put(&mut data, 0x1220, &[0x0000_0009, 0x0000_1300]);
+        let x64 = ExceptionData::read(&mut Cursor::new(&data), MachineType::Amd64, &directory_entry, &section_table).unwrap();
+        assert_eq!(x64.runtime_functions.len(), 3);
+        assert!(matches!(x64.runtime_functions[2].unwind, UnwindData::X64 { unwind_info: None, .. }));
+
+        // ARM64: packed unwind data, unwind info with a single packed epilog and an exception
+        // handler, and unwind info with an epilog scope
+        let mut data = vec![0u8; 0x400];
+        put(&mut data, 0x1000, &[
+            0x1100, 0x0162_0041,
+            0x1200, 0x1300,
+            0x1280, 0x1320,
+        ]);
+        put(&mut data, 0x1300, &[0x0870_0010, 0xE4E3_E1C8, 0x0000_1400]);
+        put(&mut data, 0x1320, &[0x0840_0008, 0x0100_0004, 0xE4E3_C8E1]);
+        let directory_entry = DataDirectoryEntry { address: 0x1000, size: 3 * 8 };
+        let arm64 = ExceptionData::read(&mut Cursor::new(&data), MachineType::Arm64, &directory_entry, &section_table).unwrap();
+        assert!(matches!(arm64.runtime_functions[0].unwind, UnwindData::Arm64Packed(_)));
+        assert!(matches!(&arm64.runtime_functions[1].unwind, UnwindData::Arm64 { unwind_info: Some(ui), .. } if ui.handler_address == Some(0x1400)));
+        assert!(matches!(&arm64.runtime_functions[2].unwind, UnwindData::Arm64 { unwind_info: Some(ui), .. } if ui.epilog_scopes.len() == 1));
+
+        for exception_data in [x64, arm64] {
+            let json = serde_json::to_value(&exception_data).unwrap();
+            let read_back: ExceptionData = serde_json::from_value(json).unwrap();
+            assert_eq!(read_back, exception_data);
+        }
+    }
+
+    #[test]
+    fn test_read_non_utf8_export_names() {
+        let mut library = pe::PortableExecutable::new("SYNTH.DLL");
+        for (ordinal, name) in [(1, "Gr_sse"), (2, "Hallo")] {
+            library.exports.push(pe::Export {
+                ordinal,
+                name: Some(name.to_owned()),
+                target: pe::ExportTarget::Code { rva: pe::TEXT_RVA },
+            });
+        }
+        let mut bytes = library.to_bytes();
+
+        // replace the underscore with a lowercase u with diaeresis in Windows-1252
+        let name_pos = bytes.windows(7)
+            .position(|w| w == b"Gr_sse\0")
+            .unwrap();
+        bytes[name_pos + 2] = 0xFC;
+
+        let mut reader = Cursor::new(bytes.as_slice());
+        let executable = Executable::read(&mut reader).unwrap();
+        let Some(OptionalHeader::Coff(coff)) = &executable.optional_header else { panic!("no COFF optional header") };
+        let windows = coff.optional_windows_header.as_ref().unwrap();
+        let export_entry = windows.known_data_directory_entry(KnownDataDirectoryEntry::ExportTable).unwrap();
+        let export_data = ExportData::read(&mut reader, &export_entry, &executable.section_table).unwrap();
+
+        assert_eq!(export_data.name_table, [("Grüsse".to_owned(), 1), ("Hallo".to_owned(), 2)]);
+        assert_eq!(export_data.raw_name_table, [b"Gr\xFCsse".to_vec(), b"Hallo".to_vec()]);
+        assert_eq!(export_data.name_to_ordinal.get("Grüsse"), Some(&1));
+        assert!(export_data.is_name_table_sorted());
+    }
+
+    #[test]
+    fn test_section_class() {
+        let mut library = pe::PortableExecutable::new("SYNTH.DLL");
+        library.exports.push(pe::Export {
+            ordinal: 1,
+            name: Some("Function".to_owned()),
+            target: pe::ExportTarget::Code { rva: pe::TEXT_RVA },
+        });
+        library.resources.push(pe::Resource { type_id: 16, name_id: 1, language_id: 0x0409, data: b"version".to_vec() });
+        let bytes = library.to_bytes();
+
+        let executable = Executable::read(&mut Cursor::new(bytes.as_slice())).unwrap();
+        let classes: Vec<(&[u8], SectionClass)> = executable.section_table.as_entries()
+            .iter()
+            .map(|entry| (entry.trimmed_name(), entry.class()))
+            .collect();
+        assert_eq!(
+            classes,
+            [
+                (&b".text"[..], SectionClass::Code),
+                (&b".edata"[..], SectionClass::InitializedData),
+                (&b".rsrc"[..], SectionClass::Resource),
+            ],
+        );
+        assert_eq!(executable.section_table.class_for_virtual(pe::TEXT_RVA), Some(SectionClass::Code));
+        assert_eq!(executable.section_table.class_for_virtual(0), None);
+
+        // Borland's linkers leave out the content flags of the uninitialized data section
+        let bss = SectionTableEntry {
+            name: (*b"BSS\0\0\0\0\0").into(),
+            characteristics: SectionCharacteristics::MEM_READ | SectionCharacteristics::MEM_WRITE,
+            ..Default::default()
+        };
+        assert_eq!(bss.class(), SectionClass::UninitializedData);
+        let debug = SectionTableEntry {
+            name: (*b".debug$S").into(),
+            characteristics: SectionCharacteristics::CONTAINS_INITIALIZED_DATA | SectionCharacteristics::MEM_DISCARDABLE,
+            ..Default::default()
+        };
+        assert_eq!(debug.class(), SectionClass::Debug);
+        assert_eq!(SectionClass::UninitializedData.to_string(), "uninitialized-data");
+    }
+
+    fn importing_library() -> pe::PortableExecutable {
+        let mut library = pe::PortableExecutable::new("SYNTH.DLL");
+        library.imports = vec![
+            pe::Import {
+                module_name: "KERNEL32.dll".to_owned(),
+                functions: vec![
+                    pe::ImportedFunction::ByName { hint: 0x0123, name: "GetVersion".to_owned() },
+                    pe::ImportedFunction::ByOrdinal(17),
+                ],
+            },
+            pe::Import {
+                module_name: "USER32.dll".to_owned(),
+                functions: vec![pe::ImportedFunction::ByName { hint: 0, name: "MessageBoxA".to_owned() }],
+            },
+        ];
+        library.bound_imports = vec![pe::BoundImport {
+            module_name: "KERNEL32.dll".to_owned(),
+            time_date_stamp: 0x3B7DFE0E,
+            forwarder_refs: vec![("NTDLL.DLL".to_owned(), 0x3B7DFE0F)],
+        }];
+        library
+    }
+
+    #[test]
+    fn test_read_imports() {
+        let mut bytes = importing_library().to_bytes();
+
+        let mut reader = Cursor::new(bytes.as_slice());
+        let executable = Executable::read(&mut reader).unwrap();
+        let Some(OptionalHeader::Coff(coff)) = &executable.optional_header else { panic!("no COFF optional header") };
+        let windows = coff.optional_windows_header.as_ref().unwrap();
+        let import_entry = windows.known_data_directory_entry(KnownDataDirectoryEntry::ImportTable).unwrap();
+        let descriptors = ImportDescriptor::read_all(&mut reader, &import_entry, &executable.section_table).unwrap();
+        let names: Vec<&str> = descriptors.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["KERNEL32.dll", "USER32.dll"]);
+        assert_eq!(descriptors[0].time_date_stamp, NEW_STYLE_BINDING_TIMESTAMP);
+        assert_eq!(descriptors[1].time_date_stamp, 0);
+        assert_eq!(
+            descriptors[0].read_lookup_entries(&mut reader, &executable.section_table, false).unwrap(),
+            [
+                ImportLookupEntry::ByName { hint: 0x0123, name: "GetVersion".to_owned() },
+                ImportLookupEntry::ByOrdinal { ordinal: 17 },
+            ],
+        );
+
+        let bound_entry = windows.known_data_directory_entry(KnownDataDirectoryEntry::BoundImport).unwrap();
+        let bound = BoundImportDescriptor::read_all(&mut reader, &bound_entry, &executable.section_table).unwrap();
+        assert_eq!(bound.len(), 1);
+        assert_eq!(bound[0].module_name, "KERNEL32.dll");
+        assert_eq!(bound[0].time_date_stamp, 0x3B7DFE0E);
+        assert_eq!(bound[0].forwarder_refs.len(), 1);
+        assert_eq!(bound[0].forwarder_refs[0].module_name, "NTDLL.DLL");
+        assert_eq!(bound[0].forwarder_refs[0].time_date_stamp, 0x3B7DFE0F);
+
+        // a huge bound import directory size is clamped to the end of the headers
+        let size_offset = 0x40 + 4 + 20 + 96 + 8*usize::from(KnownDataDirectoryEntry::BoundImport) + 4;
+        bytes[size_offset..size_offset+4].copy_from_slice(&0x7FFF_FFF0u32.to_le_bytes());
+        let mut reader = Cursor::new(bytes.as_slice());
+        let executable = Executable::read(&mut reader).unwrap();
+        let Some(OptionalHeader::Coff(coff)) = &executable.optional_header else { panic!("no COFF optional header") };
+        let windows = coff.optional_windows_header.as_ref().unwrap();
+        let bound_entry = windows.known_data_directory_entry(KnownDataDirectoryEntry::BoundImport).unwrap();
+        assert_eq!(bound_entry.size, 0x7FFF_FFF0);
+        let clamped = BoundImportDescriptor::read_all(&mut reader, &bound_entry, &executable.section_table).unwrap();
+        assert_eq!(clamped, bound);
+    }
+    #[test]
+    fn test_read_lookup_entries() {
+        for pe32_plus in [false, true] {
+            let mut library = importing_library();
+            library.pe32_plus = pe32_plus;
+            library.imports[1].functions.push(pe::ImportedFunction::ByOrdinal(0xFFFF));
+            let bytes = library.to_bytes();
+
+            let mut reader = Cursor::new(bytes.as_slice());
+            let executable = Executable::read(&mut reader).unwrap();
+            let Some(OptionalHeader::Coff(coff)) = &executable.optional_header else { panic!("no COFF optional header") };
+            assert_eq!(coff.magic == PE32_PLUS_MAGIC, pe32_plus);
+            let windows = coff.optional_windows_header.as_ref().unwrap();
+            let import_entry = windows.known_data_directory_entry(KnownDataDirectoryEntry::ImportTable).unwrap();
+            let descriptors = ImportDescriptor::read_all(&mut reader, &import_entry, &executable.section_table).unwrap();
+            assert_eq!(
+                descriptors[0].read_lookup_entries(&mut reader, &executable.section_table, pe32_plus).unwrap(),
+                [
+                    ImportLookupEntry::ByName { hint: 0x0123, name: "GetVersion".to_owned() },
+                    ImportLookupEntry::ByOrdinal { ordinal: 17 },
+                ],
+            );
+            assert_eq!(
+                descriptors[1].read_lookup_entries(&mut reader, &executable.section_table, pe32_plus).unwrap(),
+                [
+                    ImportLookupEntry::ByName { hint: 0, name: "MessageBoxA".to_owned() },
+                    ImportLookupEntry::ByOrdinal { ordinal: 0xFFFF },
+                ],
+            );
+        }
+
+        // reading a PE32+ table with 32-bit entries takes the upper half of the first entry for the
+        // end of the table
+        let mut library = importing_library();
+        library.pe32_plus = true;
+        let bytes = library.to_bytes();
+        let mut reader = Cursor::new(bytes.as_slice());
+        let executable = Executable::read(&mut reader).unwrap();
+        let Some(OptionalHeader::Coff(coff)) = &executable.optional_header else { panic!("no COFF optional header") };
+        let windows = coff.optional_windows_header.as_ref().unwrap();
+        let import_entry = windows.known_data_directory_entry(KnownDataDirectoryEntry::ImportTable).unwrap();
+        let descriptors = ImportDescriptor::read_all(&mut reader, &import_entry, &executable.section_table).unwrap();
+        assert_eq!(
+            descriptors[0].read_lookup_entries(&mut reader, &executable.section_table, false).unwrap(),
+            [ImportLookupEntry::ByName { hint: 0x0123, name: "GetVersion".to_owned() }],
+        );
+    }
+}
@@ -0,0 +1,699 @@
+//! Windows NT registry hive format.
+//!
+//! A hive consists of a 4096-byte base block followed by hive bins (`hbin`). Each hive bin is
+//! subdivided into cells; cells are referenced by their offset relative to the start of the first
+//! hive bin. Keys (`nk`), values (`vk`), subkey lists (`lf`, `lh`, `li`, `ri`) and big data
+//! (`db`) are stored in cells.
+
+
+use std::collections::BTreeSet;
+use std::io::{self, Read};
+
+use bitflags::bitflags;
+use display_bytes::DisplayBytesVec;
+use from_to_repr::from_to_other;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+
+const BASE_BLOCK_SIZE: usize = 4096;
+const NO_CELL: u32 = 0xFFFF_FFFF;
+const MAX_SUBKEY_LIST_DEPTH: usize = 8;
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Hive {
+    pub base_block: BaseBlock,
+    bins: Vec<u8>, // [u8; base_block.hive_bins_data_size]
+}
+impl Hive {
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, io::Error> {
+        let base_block = BaseBlock::read(reader)?;
+
+        let mut bins = Vec::new();
+        reader.take(base_block.hive_bins_data_size.into()).read_to_end(&mut bins)?;
+        if bins.len() != usize::try_from(base_block.hive_bins_data_size).unwrap() {
+            debug!("hive bins are shorter than the {} bytes announced in the base block", base_block.hive_bins_data_size);
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        if !bins.starts_with(b"hbin") {
+            debug!("first hive bin does not start with b\"hbin\"");
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+
+        Ok(Self {
+            base_block,
+            bins,
+        })
+    }
+
+    /// Returns the data of the cell at the given offset.
+    fn cell(&self, offset: u32) -> Result<&[u8], io::Error> {
+        let offset_usize = usize::try_from(offset).unwrap();
+        if offset_usize + 4 > self.bins.len() {
+            debug!("cell offset {:#X} out of range", offset);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let size = i32::from_le_bytes(self.bins[offset_usize..offset_usize+4].try_into().unwrap());
+
+        // negative size means allocated
+        let size_usize = usize::try_from(size.unsigned_abs()).unwrap();
+        if size_usize < 4 || offset_usize + size_usize > self.bins.len() {
+            debug!("cell at offset {:#X} has invalid size {}", offset, size);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        Ok(&self.bins[offset_usize+4..offset_usize+size_usize])
+    }
+
+    /// Returns the root key of the hive.
+    pub fn root_key(&self) -> Result<Key, io::Error> {
+        self.key_at(self.base_block.root_cell_offset)
+    }
+
+    /// Returns the key at the given cell offset.
+    pub fn key_at(&self, offset: u32) -> Result<Key, io::Error> {
+        Key::from_cell(offset, self.cell(offset)?)
+    }
+
+    /// Returns the subkeys of the given key.
+    pub fn subkeys(&self, key: &Key) -> Result<Vec<Key>, io::Error> {
+        if key.subkey_count == 0 || key.subkey_list_offset == NO_CELL {
+            return Ok(Vec::new());
+        }
+
+        let mut offsets = Vec::new();
+        let mut visited_lists = BTreeSet::new();
+        self.collect_subkey_offsets(key.subkey_list_offset, 0, &mut visited_lists, &mut offsets)?;
+
+        let mut subkeys = Vec::with_capacity(offsets.len());
+        for offset in offsets {
+            subkeys.push(self.key_at(offset)?);
+        }
+        Ok(subkeys)
+    }
+
+    fn collect_subkey_offsets(
+        &self,
+        list_offset: u32,
+        depth: usize,
+        visited_lists: &mut BTreeSet<u32>,
+        offsets: &mut Vec<u32>,
+    ) -> Result<(), io::Error> {
+        if depth > MAX_SUBKEY_LIST_DEPTH {
+            debug!("subkey lists nested too deeply");
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        // index roots referencing the same list more than once would otherwise fan out exponentially
+        if !visited_lists.insert(list_offset) {
+            debug!("subkey list at {:#X} is referenced more than once", list_offset);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+
+        let cell = self.cell(list_offset)?;
+        if cell.len() < 4 {
+            debug!("subkey list at {:#X} too short", list_offset);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let count = usize::from(u16::from_le_bytes(cell[2..4].try_into().unwrap()));
+        let entry_size = match &cell[0..2] {
+            b"lf"|b"lh" => 8, // offset, hint or hash
+            b"li"|b"ri" => 4, // offset
+            other => {
+                debug!("unknown subkey list signature {:?} at {:#X}", other, list_offset);
+                return Err(io::ErrorKind::InvalidData.into());
+            },
+        };
+        if 4 + count * entry_size > cell.len() {
+            debug!("subkey list at {:#X} with {} entries does not fit into its cell", list_offset, count);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+
+        for i in 0..count {
+            let entry_start = 4 + i * entry_size;
+            let offset = u32::from_le_bytes(cell[entry_start..entry_start+4].try_into().unwrap());
+            if &cell[0..2] == b"ri" {
+                // index root: points to further lists
+                self.collect_subkey_offsets(offset, depth + 1, visited_lists, offsets)?;
+            } else {
+                offsets.push(offset);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the subkey of the given key with the given name (compared case-insensitively).
+    pub fn subkey(&self, key: &Key, name: &str) -> Result<Option<Key>, io::Error> {
+        let subkeys = self.subkeys(key)?;
+        Ok(subkeys.into_iter().find(|k| k.name.eq_ignore_ascii_case(name)))
+    }
+
+    /// Returns the key at the given backslash-separated path, relative to the root key.
+    pub fn key_by_path(&self, path: &str) -> Result<Option<Key>, io::Error> {
+        let mut key = self.root_key()?;
+        for piece in path.split('\\').filter(|p| !p.is_empty()) {
+            match self.subkey(&key, piece)? {
+                Some(k) => key = k,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(key))
+    }
+
+    /// Returns the values of the given key.
+    pub fn values(&self, key: &Key) -> Result<Vec<Value>, io::Error> {
+        if key.value_count == 0 || key.value_list_offset == NO_CELL {
+            return Ok(Vec::new());
+        }
+
+        let list = self.cell(key.value_list_offset)?;
+        let value_count = usize::try_from(key.value_count).unwrap();
+        if value_count * 4 > list.len() {
+            debug!("value list at {:#X} with {} entries does not fit into its cell", key.value_list_offset, value_count);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+
+        let mut values = Vec::with_capacity(value_count);
+        for i in 0..value_count {
+            let offset = u32::from_le_bytes(list[4*i..4*i+4].try_into().unwrap());
+            values.push(Value::from_cell(offset, self.cell(offset)?)?);
+        }
+        Ok(values)
+    }
+
+    /// Returns the value of the given key with the given name (compared case-insensitively).
+    ///
+    /// The default value of a key has an empty name.
+    pub fn value(&self, key: &Key, name: &str) -> Result<Option<Value>, io::Error> {
+        let values = self.values(key)?;
+        Ok(values.into_iter().find(|v| v.name.eq_ignore_ascii_case(name)))
+    }
+
+    /// Returns the raw data of the given value.
+    pub fn value_data(&self, value: &Value) -> Result<Vec<u8>, io::Error> {
+        let size = usize::try_from(value.data_size & 0x7FFF_FFFF).unwrap();
+        if value.data_size & 0x8000_0000 != 0 {
+            // stored directly in the offset field
+            if size > 4 {
+                debug!("inline value data has size {}", size);
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+            return Ok(value.data_offset.to_le_bytes()[..size].to_vec());
+        }
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+
+        let cell = self.cell(value.data_offset)?;
+        if cell.starts_with(b"db") && size > cell.len() {
+            // big data: list of segments
+            if cell.len() < 8 {
+                debug!("big data cell at {:#X} too short", value.data_offset);
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+            let segment_count = usize::from(u16::from_le_bytes(cell[2..4].try_into().unwrap()));
+            let segment_list_offset = u32::from_le_bytes(cell[4..8].try_into().unwrap());
+            if size > self.bins.len() {
+                // each segment is a separate cell, so the data cannot be larger than the hive
+                debug!("big data at {:#X} is larger than the hive", value.data_offset);
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+            let segment_list = self.cell(segment_list_offset)?;
+            if segment_count * 4 > segment_list.len() {
+                debug!("big data segment list at {:#X} too short", segment_list_offset);
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+
+            let mut data = Vec::new();
+            for i in 0..segment_count {
+                let segment_offset = u32::from_le_bytes(segment_list[4*i..4*i+4].try_into().unwrap());
+                let segment = self.cell(segment_offset)?;
+                let take = segment.len().min(size - data.len());
+                data.extend_from_slice(&segment[..take]);
+            }
+            if data.len() < size {
+                debug!("big data at {:#X} is shorter than expected", value.data_offset);
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+            return Ok(data);
+        }
+
+        if size > cell.len() {
+            debug!("value data at {:#X} is shorter than expected", value.data_offset);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        Ok(cell[..size].to_vec())
+    }
+
+    /// Returns the decoded data of the given value.
+    pub fn decoded_value_data(&self, value: &Value) -> Result<ValueData, io::Error> {
+        let data = self.value_data(value)?;
+        Ok(ValueData::decode(value.data_type, data))
+    }
+
+    /// Returns the default value of the given key if it is a string.
+    pub fn default_string(&self, key: &Key) -> Option<String> {
+        let value = self.value(key, "").ok()??;
+        match self.decoded_value_data(&value).ok()? {
+            ValueData::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the COM classes registered in the hive along with their servers.
+    ///
+    /// Both `SOFTWARE` hives, which store the classes below `Classes`, and classes hives, which
+    /// store them at the root, are supported. Classes whose subkeys cannot be read are skipped.
+    pub fn com_servers(&self) -> Result<Vec<ComServer>, io::Error> {
+        let mut servers = Vec::new();
+        for clsid_path in ["Classes\\CLSID", "Classes\\Wow6432Node\\CLSID", "CLSID", "Wow6432Node\\CLSID"] {
+            let Some(clsid_key) = self.key_by_path(clsid_path)? else { continue };
+            for class_key in self.subkeys(&clsid_key)? {
+                let class_name = self.default_string(&class_key);
+                let server_keys = match self.subkeys(&class_key) {
+                    Ok(sk) => sk,
+                    Err(e) => {
+                        debug!("failed to read subkeys of CLSID {}: {}", class_key.name, e);
+                        continue;
+                    },
+                };
+                for server_key in server_keys {
+                    let is_server = COM_SERVER_KEYS
+                        .iter()
+                        .any(|kind| server_key.name.eq_ignore_ascii_case(kind));
+                    if !is_server {
+                        continue;
+                    }
+                    let Some(server_path) = self.default_string(&server_key) else { continue };
+                    servers.push(ComServer {
+                        clsid: class_key.name.clone(),
+                        class_name: class_name.clone(),
+                        server_kind: server_key.name,
+                        server_path,
+                    });
+                }
+            }
+        }
+        Ok(servers)
+    }
+}
+
+
+/// The names of the subkeys of a COM class that register its servers.
+const COM_SERVER_KEYS: [&str; 6] = [
+    "InprocServer32", "InprocServer", "InprocHandler32", "InprocHandler", "LocalServer32", "LocalServer",
+];
+
+/// A server of a COM class registered in a hive.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct ComServer {
+    pub clsid: String,
+    pub class_name: Option<String>, // default value of the CLSID key
+    pub server_kind: String, // name of the subkey, e.g. InprocServer32
+    pub server_path: String, // default value of the server key
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct BaseBlock {
+    pub signature: u32, // b"regf"
+    pub primary_sequence_number: u32,
+    pub secondary_sequence_number: u32,
+    pub last_written: u64, // FILETIME
+    pub major_version: u32,
+    pub minor_version: u32,
+    pub file_type: u32,
+    pub file_format: u32,
+    pub root_cell_offset: u32,
+    pub hive_bins_data_size: u32,
+    pub clustering_factor: u32,
+    pub file_name: String, // [u16; 32], NUL-padded
+    pub checksum: u32, // at offset 508
+}
+impl BaseBlock {
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut buf = vec![0u8; BASE_BLOCK_SIZE];
+        reader.read_exact(&mut buf)?;
+
+        let signature = u32::from_le_bytes(buf[0..4].try_into().unwrap());
+        if &buf[0..4] != b"regf" {
+            debug!("registry hive signature is {:#010X}, expected b\"regf\"", signature);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let primary_sequence_number = u32::from_le_bytes(buf[4..8].try_into().unwrap());
+        let secondary_sequence_number = u32::from_le_bytes(buf[8..12].try_into().unwrap());
+        let last_written = u64::from_le_bytes(buf[12..20].try_into().unwrap());
+        let major_version = u32::from_le_bytes(buf[20..24].try_into().unwrap());
+        let minor_version = u32::from_le_bytes(buf[24..28].try_into().unwrap());
+        let file_type = u32::from_le_bytes(buf[28..32].try_into().unwrap());
+        let file_format = u32::from_le_bytes(buf[32..36].try_into().unwrap());
+        let root_cell_offset = u32::from_le_bytes(buf[36..40].try_into().unwrap());
+        let hive_bins_data_size = u32::from_le_bytes(buf[40..44].try_into().unwrap());
+        let clustering_factor = u32::from_le_bytes(buf[44..48].try_into().unwrap());
+        let file_name = decode_utf16le_nul_padded(&buf[48..112]);
+        let checksum = u32::from_le_bytes(buf[508..512].try_into().unwrap());
+
+        Ok(Self {
+            signature,
+            primary_sequence_number,
+            secondary_sequence_number,
+            last_written,
+            major_version,
+            minor_version,
+            file_type,
+            file_format,
+            root_cell_offset,
+            hive_bins_data_size,
+            clustering_factor,
+            file_name,
+            checksum,
+        })
+    }
+
+    /// Calculates the checksum of a base block, which is the XOR of its first 127 u32le values.
+    pub fn calculate_checksum(base_block_bytes: &[u8]) -> u32 {
+        base_block_bytes[..508]
+            .chunks(4)
+            .map(|ch| u32::from_le_bytes(ch.try_into().unwrap()))
+            .fold(0, |a, b| a ^ b)
+    }
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Key {
+    pub offset: u32, // offset of this key's cell
+    // signature: b"nk",
+    pub flags: KeyFlags, // u16
+    pub last_written: u64, // FILETIME
+    pub access_bits: u32,
+    pub parent_offset: u32,
+    pub subkey_count: u32,
+    pub volatile_subkey_count: u32,
+    pub subkey_list_offset: u32,
+    pub volatile_subkey_list_offset: u32,
+    pub value_count: u32,
+    pub value_list_offset: u32,
+    pub security_offset: u32,
+    pub class_name_offset: u32,
+    pub largest_subkey_name_length: u32,
+    pub largest_subkey_class_name_length: u32,
+    pub largest_value_name_length: u32,
+    pub largest_value_data_size: u32,
+    pub work_var: u32,
+    // name_length: u16,
+    pub class_name_length: u16,
+    pub name: String, // [u8; name_length] if flags contains COMPRESSED_NAME, [u16; name_length/2] otherwise
+}
+impl Key {
+    pub fn from_cell(offset: u32, cell: &[u8]) -> Result<Self, io::Error> {
+        if cell.len() < 76 || &cell[0..2] != b"nk" {
+            debug!("cell at {:#X} is not a key", offset);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+
+        let flags = KeyFlags::from_bits_retain(u16::from_le_bytes(cell[2..4].try_into().unwrap()));
+        let last_written = u64::from_le_bytes(cell[4..12].try_into().unwrap());
+        let access_bits = u32::from_le_bytes(cell[12..16].try_into().unwrap());
+        let parent_offset = u32::from_le_bytes(cell[16..20].try_into().unwrap());
+        let subkey_count = u32::from_le_bytes(cell[20..24].try_into().unwrap());
+        let volatile_subkey_count = u32::from_le_bytes(cell[24..28].try_into().unwrap());
+        let subkey_list_offset = u32::from_le_bytes(cell[28..32].try_into().unwrap());
+        let volatile_subkey_list_offset = u32::from_le_bytes(cell[32..36].try_into().unwrap());
+        let value_count = u32::from_le_bytes(cell[36..40].try_into().unwrap());
+        let value_list_offset = u32::from_le_bytes(cell[40..44].try_into().unwrap());
+        let security_offset = u32::from_le_bytes(cell[44..48].try_into().unwrap());
+        let class_name_offset = u32::from_le_bytes(cell[48..52].try_into().unwrap());
+        let largest_subkey_name_length = u32::from_le_bytes(cell[52..56].try_into().unwrap());
+        let largest_subkey_class_name_length = u32::from_le_bytes(cell[56..60].try_into().unwrap());
+        let largest_value_name_length = u32::from_le_bytes(cell[60..64].try_into().unwrap());
+        let largest_value_data_size = u32::from_le_bytes(cell[64..68].try_into().unwrap());
+        let work_var = u32::from_le_bytes(cell[68..72].try_into().unwrap());
+        let name_length = usize::from(u16::from_le_bytes(cell[72..74].try_into().unwrap()));
+        let class_name_length = u16::from_le_bytes(cell[74..76].try_into().unwrap());
+
+        if 76 + name_length > cell.len() {
+            debug!("name of key at {:#X} does not fit into its cell", offset);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let name = decode_name(&cell[76..76+name_length], flags.contains(KeyFlags::COMPRESSED_NAME));
+
+        Ok(Self {
+            offset,
+            flags,
+            last_written,
+            access_bits,
+            parent_offset,
+            subkey_count,
+            volatile_subkey_count,
+            subkey_list_offset,
+            volatile_subkey_list_offset,
+            value_count,
+            value_list_offset,
+            security_offset,
+            class_name_offset,
+            largest_subkey_name_length,
+            largest_subkey_class_name_length,
+            largest_value_name_length,
+            largest_value_data_size,
+            work_var,
+            class_name_length,
+            name,
+        })
+    }
+}
+
+bitflags! {
+    #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+    pub struct KeyFlags : u16 {
+        const VOLATILE = 0x0001;
+        const HIVE_EXIT = 0x0002;
+        const HIVE_ENTRY = 0x0004;
+        const NO_DELETE = 0x0008;
+        const SYMLINK = 0x0010;
+        const COMPRESSED_NAME = 0x0020;
+        const PREDEFINED_HANDLE = 0x0040;
+        const VIRTUAL_SOURCE = 0x0080;
+        const VIRTUAL_TARGET = 0x0100;
+        const VIRTUAL_STORE = 0x0200;
+    }
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Value {
+    pub offset: u32, // offset of this value's cell
+    // signature: b"vk",
+    // name_length: u16,
+    pub data_size: u32, // top bit set if data is stored in data_offset
+    pub data_offset: u32,
+    pub data_type: ValueType, // u32
+    pub flags: u16, // 0x0001 = compressed name
+    pub spare: u16,
+    pub name: String, // [u8; name_length] if flags & 0x0001, [u16; name_length/2] otherwise
+}
+impl Value {
+    pub fn from_cell(offset: u32, cell: &[u8]) -> Result<Self, io::Error> {
+        if cell.len() < 20 || &cell[0..2] != b"vk" {
+            debug!("cell at {:#X} is not a value", offset);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+
+        let name_length = usize::from(u16::from_le_bytes(cell[2..4].try_into().unwrap()));
+        let data_size = u32::from_le_bytes(cell[4..8].try_into().unwrap());
+        let data_offset = u32::from_le_bytes(cell[8..12].try_into().unwrap());
+        let data_type = ValueType::from_base_type(u32::from_le_bytes(cell[12..16].try_into().unwrap()));
+        let flags = u16::from_le_bytes(cell[16..18].try_into().unwrap());
+        let spare = u16::from_le_bytes(cell[18..20].try_into().unwrap());
+
+        if 20 + name_length > cell.len() {
+            debug!("name of value at {:#X} does not fit into its cell", offset);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let name = decode_name(&cell[20..20+name_length], flags & 0x0001 != 0);
+
+        Ok(Self {
+            offset,
+            data_size,
+            data_offset,
+            data_type,
+            flags,
+            spare,
+            name,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[from_to_other(base_type = u32, derive_compare = "as_int")]
+pub enum ValueType {
+    None = 0,
+    String = 1,
+    ExpandString = 2,
+    Binary = 3,
+    Dword = 4,
+    DwordBigEndian = 5,
+    Link = 6,
+    MultiString = 7,
+    ResourceList = 8,
+    FullResourceDescriptor = 9,
+    ResourceRequirementsList = 10,
+    Qword = 11,
+    Other(u32),
+}
+
+
+/// The data of a registry value, decoded according to its type.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum ValueData {
+    String(String),
+    MultiString(Vec<String>),
+    Dword(u32),
+    Qword(u64),
+    Binary(DisplayBytesVec),
+}
+impl ValueData {
+    /// Decodes the data according to the value type, falling back to binary data if the data does
+    /// not match the type.
+    pub fn decode(data_type: ValueType, data: Vec<u8>) -> Self {
+        match data_type {
+            ValueType::String|ValueType::ExpandString|ValueType::Link if data.len().is_multiple_of(2)
+                => Self::String(decode_utf16le_nul_padded(&data)),
+            ValueType::MultiString if data.len().is_multiple_of(2) => {
+                let words: Vec<u16> = data
+                    .chunks(2)
+                    .map(|ch| u16::from_le_bytes(ch.try_into().unwrap()))
+                    .collect();
+                let strings: Vec<String> = words
+                    .split(|w| *w == 0)
+                    .filter(|s| !s.is_empty())
+                    .map(String::from_utf16_lossy)
+                    .collect();
+                Self::MultiString(strings)
+            },
+            ValueType::Dword if data.len() == 4
+                => Self::Dword(u32::from_le_bytes(data.try_into().unwrap())),
+            ValueType::DwordBigEndian if data.len() == 4
+                => Self::Dword(u32::from_be_bytes(data.try_into().unwrap())),
+            ValueType::Qword if data.len() == 8
+                => Self::Qword(u64::from_le_bytes(data.try_into().unwrap())),
+            _ => Self::Binary(DisplayBytesVec::from(data)),
+        }
+    }
+}
+
+
+/// Decodes a key or value name, which is either Latin-1 ("compressed") or UTF-16LE.
+fn decode_name(bytes: &[u8], compressed: bool) -> String {
+    if compressed {
+        bytes.iter().map(|b| char::from(*b)).collect()
+    } else {
+        let words: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|ch| u16::from_le_bytes(ch.try_into().unwrap()))
+            .collect();
+        String::from_utf16_lossy(&words)
+    }
+}
+
+/// Decodes a UTF-16LE string, stopping at the first NUL character.
+fn decode_utf16le_nul_padded(bytes: &[u8]) -> String {
+    let words: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|ch| u16::from_le_bytes(ch.try_into().unwrap()))
+        .take_while(|w| *w != 0)
+        .collect();
+    String::from_utf16_lossy(&words)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{Hive, ValueData};
+    use std::io::Cursor;
+    use testgen::regf;
+
+    const CLSID: &str = "{00021401-0000-0000-C000-000000000046}";
+
+    fn synthesize_classes_hive() -> Vec<u8> {
+        let mut server = regf::Key::new("InprocServer32");
+        server.values = vec![
+            (String::new(), "%SystemRoot%\\system32\\shell32.dll".to_owned()),
+            ("ThreadingModel".to_owned(), "Apartment".to_owned()),
+        ];
+        let mut class = regf::Key::new(CLSID);
+        class.values = vec![(String::new(), "Shortcut".to_owned())];
+        class.subkeys = vec![server, regf::Key::new("ProgID")];
+        let mut clsid = regf::Key::new("CLSID");
+        clsid.subkeys = vec![class];
+        let mut root = regf::Key::new("ROOT");
+        root.subkeys = vec![clsid];
+        regf::hive(&root)
+    }
+
+    #[test]
+    fn test_read_synthesized_hive() {
+        let bytes = synthesize_classes_hive();
+        let hive = Hive::read(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(hive.root_key().unwrap().name, "ROOT");
+
+        let server = hive.key_by_path(&format!("clsid\\{}\\InprocServer32", CLSID)).unwrap().unwrap();
+        let values = hive.values(&server).unwrap();
+        assert_eq!(values.len(), 2);
+        let threading = hive.value(&server, "threadingmodel").unwrap().unwrap();
+        assert_eq!(hive.decoded_value_data(&threading).unwrap(), ValueData::String("Apartment".to_owned()));
+        assert!(hive.key_by_path("CLSID\\{nonexistent}").unwrap().is_none());
+
+        let servers = hive.com_servers().unwrap();
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].clsid, CLSID);
+        assert_eq!(servers[0].class_name.as_deref(), Some("Shortcut"));
+        assert_eq!(servers[0].server_kind, "InprocServer32");
+        assert_eq!(servers[0].server_path, "%SystemRoot%\\system32\\shell32.dll");
+    }
+
+    #[test]
+    fn test_reject_truncated_hive() {
+        let mut bytes = synthesize_classes_hive();
+        bytes.truncate(4096 + 0x100);
+        assert!(Hive::read(&mut Cursor::new(&bytes)).is_err());
+
+        // a huge announced size is not allocated up front
+        let mut bytes = synthesize_classes_hive();
+        bytes[40..44].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+        assert!(Hive::read(&mut Cursor::new(&bytes)).is_err());
+    }
+
+    #[test]
+    fn test_reject_broken_lists() {
+        // turn the subkey list of the root key into an index root that references itself
+        let mut bytes = synthesize_classes_hive();
+        let hive = Hive::read(&mut Cursor::new(&bytes)).unwrap();
+        let list_offset = hive.root_key().unwrap().subkey_list_offset;
+        let list_start = 4096 + usize::try_from(list_offset).unwrap() + 4;
+        bytes[list_start..list_start+4].copy_from_slice(b"ri\x02\x00");
+        bytes[list_start+4..list_start+8].copy_from_slice(&list_offset.to_le_bytes());
+        bytes[list_start+8..list_start+12].copy_from_slice(&list_offset.to_le_bytes());
+        let hive = Hive::read(&mut Cursor::new(&bytes)).unwrap();
+        assert!(hive.subkeys(&hive.root_key().unwrap()).is_err());
+
+        // turn the data of a value into big data larger than the whole hive
+        let mut bytes = synthesize_classes_hive();
+        let hive = Hive::read(&mut Cursor::new(&bytes)).unwrap();
+        let server = hive.key_by_path(&format!("CLSID\\{}\\InprocServer32", CLSID)).unwrap().unwrap();
+        let mut threading = hive.value(&server, "ThreadingModel").unwrap().unwrap();
+        let data_start = 4096 + usize::try_from(threading.data_offset).unwrap() + 4;
+        bytes[data_start..data_start+4].copy_from_slice(b"db\x01\x00");
+        bytes[data_start+4..data_start+8].copy_from_slice(&threading.data_offset.to_le_bytes());
+        threading.data_size = 0x7FFF_0000;
+        let hive = Hive::read(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(hive.value_data(&threading).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+}
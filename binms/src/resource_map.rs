@@ -0,0 +1,268 @@
+//! Maps of resource identifiers to resources that keep duplicate identifiers.
+//!
+//! Neither NE nor PE files prevent a resource directory from listing the same identifier more than
+//! once. Damaged or deliberately malformed files do so, and a map that only keeps one entry per
+//! identifier would hide the other entries from anyone inspecting the file.
+
+
+use std::fmt;
+use std::ops::Index;
+use std::slice;
+use std::vec;
+
+
+/// An entry of a [`ResourceMap`].
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ResourceEntry<K, V> {
+    /// The position of the entry within its directory in the file.
+    pub index: usize,
+
+    pub id: K,
+    pub value: V,
+}
+
+
+/// A map of resource identifiers to values that keeps entries with duplicate identifiers.
+///
+/// The entries are ordered by their identifier; entries with the same identifier are ordered by
+/// their position in the file. Looking up an identifier that occurs more than once returns the
+/// entry that comes first in the file, which is the entry that the 16-bit Windows loader finds by
+/// searching the directory from the start. (The 32-bit loader performs a binary search, so which
+/// of the duplicates it finds depends on the number of entries.)
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ResourceMap<K, V> {
+    entries: Vec<ResourceEntry<K, V>>,
+}
+impl<K, V> ResourceMap<K, V> {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entries, ordered by identifier and then by position in the file.
+    pub fn entries(&self) -> &[ResourceEntry<K, V>] {
+        &self.entries
+    }
+
+    /// Iterates over the identifiers and values, ordered by identifier and then by position in the
+    /// file.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter(self.entries.iter())
+    }
+
+    /// Iterates mutably over the identifiers and values in the same order as [`iter`](Self::iter).
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut(self.entries.iter_mut())
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> {
+        self.entries.iter().map(|entry| &entry.id)
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> {
+        self.entries.iter().map(|entry| &entry.value)
+    }
+}
+impl<K: Ord, V> ResourceMap<K, V> {
+    /// Adds an entry as if it came after all the entries added so far in the file.
+    pub fn push(&mut self, id: K, value: V) {
+        let index = self.entries.len();
+        let position = self.entries.partition_point(|entry| entry.id <= id);
+        self.entries.insert(position, ResourceEntry { index, id, value });
+    }
+
+    /// The entries with the given identifier, ordered by position in the file.
+    pub fn get_all(&self, id: &K) -> &[ResourceEntry<K, V>] {
+        let start = self.entries.partition_point(|entry| entry.id < *id);
+        let end = self.entries.partition_point(|entry| entry.id <= *id);
+        &self.entries[start..end]
+    }
+
+    /// The value of the entry with the given identifier that comes first in the file.
+    pub fn get(&self, id: &K) -> Option<&V> {
+        self.get_all(id).first()
+            .map(|entry| &entry.value)
+    }
+
+    /// The value of the entry with the given identifier that comes first in the file, mutably.
+    pub fn get_mut(&mut self, id: &K) -> Option<&mut V> {
+        let position = self.entries.partition_point(|entry| entry.id < *id);
+        self.entries.get_mut(position)
+            .filter(|entry| entry.id == *id)
+            .map(|entry| &mut entry.value)
+    }
+
+    pub fn contains_key(&self, id: &K) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// The identifiers that occur more than once, each returned once.
+    pub fn duplicate_ids(&self) -> Vec<&K> {
+        let mut duplicates: Vec<&K> = Vec::new();
+        for pair in self.entries.windows(2) {
+            if pair[0].id == pair[1].id && duplicates.last() != Some(&&pair[0].id) {
+                duplicates.push(&pair[0].id);
+            }
+        }
+        duplicates
+    }
+}
+impl<K, V> Default for ResourceMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<K: Ord, V> FromIterator<(K, V)> for ResourceMap<K, V> {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+impl<K: Ord, V> Extend<(K, V)> for ResourceMap<K, V> {
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (id, value) in iter {
+            self.push(id, value);
+        }
+    }
+}
+impl<K: Ord + fmt::Debug, V> Index<&K> for ResourceMap<K, V> {
+    type Output = V;
+
+    fn index(&self, id: &K) -> &Self::Output {
+        self.get(id)
+            .unwrap_or_else(|| panic!("resource {:?} not found", id))
+    }
+}
+impl<K, V> IntoIterator for ResourceMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.entries.into_iter())
+    }
+}
+impl<'a, K, V> IntoIterator for &'a ResourceMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+impl<'a, K, V> IntoIterator for &'a mut ResourceMap<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Serializes the map as a sequence of pairs of identifiers and values in file order.
+///
+/// Pairs are used instead of a map to work around limitations in some serialization formats (e.g.
+/// JSON) which only accept string keys, and to keep duplicate identifiers.
+#[cfg(feature = "serde")]
+impl<K: serde::Serialize, V: serde::Serialize> serde::Serialize for ResourceMap<K, V> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut file_order: Vec<&ResourceEntry<K, V>> = self.entries.iter().collect();
+        file_order.sort_unstable_by_key(|entry| entry.index);
+        serializer.collect_seq(file_order.into_iter().map(|entry| (&entry.id, &entry.value)))
+    }
+}
+#[cfg(feature = "serde")]
+impl<'d, K: serde::Deserialize<'d> + Ord, V: serde::Deserialize<'d>> serde::Deserialize<'d> for ResourceMap<K, V> {
+    fn deserialize<D: serde::Deserializer<'d>>(deserializer: D) -> Result<Self, D::Error> {
+        let pairs: Vec<(K, V)> = Vec::deserialize(deserializer)?;
+        Ok(pairs.into_iter().collect())
+    }
+}
+
+
+/// An iterator over the identifiers and values of a [`ResourceMap`].
+pub struct Iter<'a, K, V>(slice::Iter<'a, ResourceEntry<K, V>>);
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|entry| (&entry.id, &entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|entry| (&entry.id, &entry.value))
+    }
+}
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {}
+
+/// A mutable iterator over the identifiers and values of a [`ResourceMap`].
+pub struct IterMut<'a, K, V>(slice::IterMut<'a, ResourceEntry<K, V>>);
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|entry| (&entry.id, &mut entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+impl<'a, K, V> ExactSizeIterator for IterMut<'a, K, V> {}
+
+/// An owning iterator over the identifiers and values of a [`ResourceMap`].
+pub struct IntoIter<K, V>(vec::IntoIter<ResourceEntry<K, V>>);
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|entry| (entry.id, entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+
+#[cfg(test)]
+mod tests {
+    use super::ResourceMap;
+
+    #[test]
+    fn test_duplicates() {
+        let map: ResourceMap<u32, &str> = [(5, "five"), (2, "two"), (5, "FIVE"), (3, "three"), (5, "Five")]
+            .into_iter()
+            .collect();
+        assert_eq!(map.len(), 5);
+        assert_eq!(
+            map.iter().map(|(id, value)| (*id, *value)).collect::<Vec<_>>(),
+            vec![(2, "two"), (3, "three"), (5, "five"), (5, "FIVE"), (5, "Five")],
+        );
+        assert_eq!(map.get(&5), Some(&"five"));
+        assert_eq!(map.get(&4), None);
+        assert_eq!(map.get_all(&5).iter().map(|entry| entry.index).collect::<Vec<_>>(), vec![0, 2, 4]);
+        assert_eq!(map.duplicate_ids(), vec![&5]);
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(&map).unwrap();
+            assert_eq!(json, r#"[[5,"five"],[2,"two"],[5,"FIVE"],[3,"three"],[5,"Five"]]"#);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! String tables (`RT_STRING`) of 16-bit Windows executables.
+//!
+//! The strings of a module are stored in blocks of 16; the block with resource ID `n` contains the
+//! strings with IDs `(n - 1) * 16` to `(n - 1) * 16 + 15`. Each string is stored as a length byte
+//! followed by that many bytes in the ANSI codepage of the system the module was localized for;
+//! there is no terminator. Missing strings have a length of zero.
+
+
+use std::fmt;
+
+use crate::windows_1252_char;
+
+
+/// The number of strings in each block.
+pub const STRINGS_PER_BLOCK: u16 = 16;
+
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Error {
+    InvalidBlockId,
+    StringTooLong { index: u16, length: u8, available: usize },
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Self::InvalidBlockId
+                => write!(f, "string table blocks are numbered from 1"),
+            Self::StringTooLong { index, length, available }
+                => write!(f, "string at index {} has {} bytes but only {} remain", index, length, available),
+        }
+    }
+}
+impl std::error::Error for Error {
+}
+
+
+/// A block of up to 16 strings in a 16-bit string table.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct StringBlock {
+    /// The resource ID of the block, without the high bit that marks it as numeric.
+    pub block_id: u16,
+
+    /// The raw bytes of the strings, in order; shorter than 16 entries if the data ends early.
+    pub strings: Vec<Vec<u8>>,
+}
+impl StringBlock {
+    /// Reads a block from the data of an NE `RT_STRING` resource.
+    ///
+    /// The data is padded to the alignment of the resource table, so anything after the 16th
+    /// string is ignored. If the data ends between two strings, the remaining strings are
+    /// considered missing.
+    pub fn from_ne_bytes(block_id: u16, bytes: &[u8]) -> Result<Self, Error> {
+        if block_id == 0 {
+            return Err(Error::InvalidBlockId);
+        }
+
+        let mut strings = Vec::with_capacity(STRINGS_PER_BLOCK.into());
+        let mut rest = bytes;
+        for index in 0..STRINGS_PER_BLOCK {
+            let Some((&length, after_length)) = rest.split_first() else {
+                break;
+            };
+            let Some((string, after_string)) = after_length.split_at_checked(length.into()) else {
+                return Err(Error::StringTooLong { index, length, available: after_length.len() });
+            };
+            strings.push(string.to_vec());
+            rest = after_string;
+        }
+        Ok(Self {
+            block_id,
+            strings,
+        })
+    }
+
+    /// The ID of the first string in this block.
+    pub fn first_string_id(&self) -> u16 {
+        (self.block_id - 1).wrapping_mul(STRINGS_PER_BLOCK)
+    }
+
+    /// Returns the ID and raw bytes of each string that is not empty.
+    pub fn entries(&self) -> impl Iterator<Item = (u16, &[u8])> {
+        let first_string_id = self.first_string_id();
+        self.strings.iter()
+            .zip(0..)
+            .filter(|(string, _)| !string.is_empty())
+            .map(move |(string, index)| (first_string_id.wrapping_add(index), string.as_slice()))
+    }
+}
+
+
+/// Decodes a string stored in Windows-1252, the ANSI codepage of Western European systems.
+///
+/// Modules localized for other regions use other codepages; their strings have to be decoded from
+/// the raw bytes by the caller.
+pub fn decode_windows_1252(bytes: &[u8]) -> String {
+    bytes.iter()
+        .map(|&b| windows_1252_char(b))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_windows_1252, Error, StringBlock};
+
+    #[test]
+    fn test_read_ne_string_block() {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"\x05Datei\x00\x0AS\xFC\xDFigkeit\x80");
+        data.extend_from_slice(&[0x00; 12]);
+        data.extend_from_slice(b"\x03end");
+        data.extend_from_slice(b"\x00\x00padding");
+
+        let block = StringBlock::from_ne_bytes(3, &data).unwrap();
+        assert_eq!(block.strings.len(), 16);
+        let entries: Vec<(u16, String)> = block.entries()
+            .map(|(id, bytes)| (id, decode_windows_1252(bytes)))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (32, "Datei".to_owned()),
+                (34, "S\u{FC}\u{DF}igkeit\u{20AC}".to_owned()),
+                (47, "end".to_owned()),
+            ],
+        );
+
+        // data ending between strings is fine, data ending within a string is not
+        assert_eq!(StringBlock::from_ne_bytes(1, b"\x02ab").unwrap().strings.len(), 1);
+        assert_eq!(
+            StringBlock::from_ne_bytes(1, b"\x02ab\x05abc"),
+            Err(Error::StringTooLong { index: 1, length: 5, available: 3 }),
+        );
+    }
+}
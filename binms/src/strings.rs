@@ -0,0 +1,121 @@
+//! Extraction of printable strings from binary data.
+
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+
+/// The encoding of a string found in binary data.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum StringEncoding {
+    /// One byte per character, each of them printable ASCII or a tab.
+    Ascii,
+
+    /// Two bytes per character in little-endian order, each of them printable ASCII, printable
+    /// Latin-1 or a tab.
+    ///
+    /// Other characters are not considered since almost any pair of bytes encodes a valid
+    /// character beyond Latin-1, which would turn most binary data into strings.
+    Utf16Le,
+}
+
+
+/// A string found in binary data.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct FoundString {
+    /// The offset of the first byte of the string within the data.
+    pub offset: usize,
+
+    pub encoding: StringEncoding,
+    pub value: String,
+}
+
+
+fn is_printable(c: u16) -> bool {
+    c == 0x09 || (0x20..=0x7E).contains(&c) || (0xA0..=0xFF).contains(&c)
+}
+
+
+/// Finds the runs of at least `min_length` printable characters in the given encodings.
+///
+/// UTF-16 strings are searched for at even as well as odd offsets. The strings are returned sorted
+/// by offset.
+pub fn find_strings(data: &[u8], min_length: usize, encodings: &[StringEncoding]) -> Vec<FoundString> {
+    let min_length = min_length.max(1);
+    let mut strings = Vec::new();
+
+    if encodings.contains(&StringEncoding::Ascii) {
+        // bytes beyond ASCII would be decoded as Latin-1, which is far too lenient for single bytes
+        let units = data.iter()
+            .enumerate()
+            .map(|(offset, b)| (offset, if b.is_ascii() { u16::from(*b) } else { 0x00 }));
+        collect_runs(units, min_length, StringEncoding::Ascii, &mut strings);
+    }
+    if encodings.contains(&StringEncoding::Utf16Le) {
+        for start in 0..2 {
+            let units = data.get(start..).unwrap_or_default()
+                .chunks_exact(2)
+                .enumerate()
+                .map(|(i, pair)| (start + 2*i, u16::from_le_bytes([pair[0], pair[1]])));
+            collect_runs(units, min_length, StringEncoding::Utf16Le, &mut strings);
+        }
+    }
+
+    strings.sort_unstable();
+    strings
+}
+
+/// Collects the runs of at least `min_length` printable code units into `strings`.
+fn collect_runs<I: Iterator<Item = (usize, u16)>>(units: I, min_length: usize, encoding: StringEncoding, strings: &mut Vec<FoundString>) {
+    let mut push_run = |run: Option<(usize, String)>| {
+        if let Some((offset, value)) = run && value.chars().count() >= min_length {
+            strings.push(FoundString { offset, encoding, value });
+        }
+    };
+
+    let mut current_run: Option<(usize, String)> = None;
+    for (offset, unit) in units {
+        if is_printable(unit) {
+            let (_, value) = current_run.get_or_insert_with(|| (offset, String::new()));
+            value.push(char::from_u32(unit.into()).unwrap());
+        } else {
+            push_run(current_run.take());
+        }
+    }
+    push_run(current_run.take());
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{find_strings, FoundString, StringEncoding};
+
+    #[test]
+    fn test_find_strings() {
+        let mut data = b"\x00\x01GetProcAddress\x00\x00\x01".to_vec();
+        for c in "Gr\u{FC}\u{DF}e".encode_utf16() {
+            data.extend_from_slice(&c.to_le_bytes());
+        }
+        data.extend_from_slice(b"\x00\x00ab\x00");
+
+        let found = |encodings: &[StringEncoding]| -> Vec<(usize, StringEncoding, String)> {
+            find_strings(&data, 4, encodings).into_iter()
+                .map(|FoundString { offset, encoding, value }| (offset, encoding, value))
+                .collect()
+        };
+        assert_eq!(
+            found(&[StringEncoding::Ascii]),
+            [(2, StringEncoding::Ascii, "GetProcAddress".to_owned())],
+        );
+        assert_eq!(
+            found(&[StringEncoding::Ascii, StringEncoding::Utf16Le]),
+            [
+                (2, StringEncoding::Ascii, "GetProcAddress".to_owned()),
+                (19, StringEncoding::Utf16Le, "Gr\u{FC}\u{DF}e".to_owned()),
+            ],
+        );
+    }
+}
@@ -0,0 +1,339 @@
+//! Guessing the toolchain that produced a PE executable.
+//!
+//! No field of a PE file names the compiler and linker that built it, but each toolchain leaves
+//! traces. Microsoft linkers since Visual C++ 6.0 record the tools that produced the object files
+//! in the Rich header, and the linker version in the optional header follows the release of
+//! Visual C++. The GNU and Borland linkers name their sections in their own ways, and the security
+//! features recorded in the load configuration directory (stack cookies and safe exception handler
+//! tables) were introduced by specific Visual C++ releases. Packers and other post-processing tools
+//! destroy most of these traces, so the result is only a guess.
+
+
+use std::fmt;
+use std::io::{self, Read, Seek};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::decode_byte_name;
+use crate::mz::{decode_rich_header, split_rich_header, RichEntry};
+use crate::pe::{self, KnownDataDirectoryEntry, LoadConfigDirectory, OptionalHeader};
+
+
+/// The Rich header product IDs of the Microsoft Macro Assembler (6.13 to 14.x).
+const MASM_PRODUCT_IDS: [u16; 9] = [0x000E, 0x000F, 0x0012, 0x002A, 0x0040, 0x007D, 0x0095, 0x009E, 0x0103];
+
+/// The Rich header product IDs of tools that do not translate source code: the entry counting the
+/// imported functions, linkers, object file converters, resource converters and the generators of
+/// export files and import libraries.
+const NON_COMPILER_PRODUCT_IDS: [u16; 17] = [
+    0x0001, 0x0002, 0x0003, 0x0004, 0x0005, 0x0006, 0x000C, 0x0010, 0x0011, 0x0013, 0x0014,
+    0x00FD, 0x00FE, 0x00FF, 0x0100, 0x0101, 0x0102,
+];
+
+/// The message output by the DOS stub that Borland linkers place into executables.
+const BORLAND_STUB_MESSAGE: &[u8] = b"This program must be run under Win32";
+
+/// The names of the sections created by Borland's Delphi compiler and linker.
+const BORLAND_SECTION_NAMES: [&str; 3] = ["CODE", "DATA", "BSS"];
+
+/// The names of sections only created by the GNU linker. Sections with long names (referenced as
+/// `/4` etc.) are also a sign of the GNU linker.
+const GNU_SECTION_NAMES: [&str; 4] = [".eh_fram", ".eh_frame", ".xdata", ".bss"];
+
+
+/// The traces of the toolchain found in a PE executable.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct BuildTraits {
+    pub major_linker_version: u8,
+    pub minor_linker_version: u8,
+
+    /// The entries of the Rich header; `None` if the executable has none.
+    pub rich_entries: Option<Vec<RichEntry>>,
+
+    pub section_names: Vec<String>,
+
+    /// Whether the DOS stub outputs the message of the stub placed by Borland linkers.
+    pub borland_stub: bool,
+
+    /// The load configuration directory; `None` if the executable has none or it cannot be read.
+    pub load_config: Option<LoadConfigDirectory>,
+
+    /// Whether the executable has an exception directory (`.pdata`).
+    pub has_exception_directory: bool,
+}
+impl BuildTraits {
+    /// Collects the traces of the toolchain from a PE executable.
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, io::Error> {
+        let executable = pe::Executable::read(reader)?;
+        let Some(OptionalHeader::Coff(coff)) = executable.optional_header.as_ref() else {
+            debug!("PE file has no COFF optional header");
+            return Err(io::ErrorKind::InvalidData.into());
+        };
+
+        let stub = executable.mz.read_stub(reader)?;
+        let (_code, rich) = split_rich_header(&stub);
+        let rich_entries = rich.and_then(decode_rich_header);
+        let borland_stub = stub.windows(BORLAND_STUB_MESSAGE.len())
+            .any(|w| w == BORLAND_STUB_MESSAGE);
+
+        let section_names = executable.section_table.as_entries()
+            .iter()
+            .map(|section| {
+                let name_bytes: &[u8] = section.name.as_ref();
+                let name_end = name_bytes.iter().position(|b| *b == 0x00).unwrap_or(name_bytes.len());
+                decode_byte_name(&name_bytes[..name_end])
+            })
+            .collect();
+
+        let windows = coff.optional_windows_header.as_ref();
+        let present_entry = |known_entry| windows
+            .and_then(|w| w.known_data_directory_entry(known_entry))
+            .filter(|e| e.address != 0 && e.size != 0);
+        let load_config = present_entry(KnownDataDirectoryEntry::LoadConfigTable)
+            .and_then(|entry| {
+                let is_pe32_plus = coff.magic == pe::PE32_PLUS_MAGIC;
+                LoadConfigDirectory::read(reader, &entry, &executable.section_table, is_pe32_plus)
+                    .inspect_err(|e| debug!("failed to read load config directory: {}", e))
+                    .ok()
+            });
+        let has_exception_directory = present_entry(KnownDataDirectoryEntry::ExceptionTable).is_some();
+
+        Ok(Self {
+            major_linker_version: coff.major_linker_version,
+            minor_linker_version: coff.minor_linker_version,
+            rich_entries,
+            section_names,
+            borland_stub,
+            load_config,
+            has_exception_directory,
+        })
+    }
+}
+
+
+/// A toolchain that produces PE executables.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum Toolchain {
+    /// Microsoft Visual C++; the release is derived from the linker version.
+    VisualCpp { major_linker_version: u8, minor_linker_version: u8 },
+
+    /// The Microsoft Macro Assembler along with the Microsoft linker, without any C or C++ code.
+    Masm,
+
+    /// Borland Delphi, C++Builder or Borland C++.
+    Borland,
+
+    /// MinGW or another toolchain using the GNU linker.
+    MinGw,
+
+    Unknown,
+}
+impl fmt::Display for Toolchain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::VisualCpp { major_linker_version, minor_linker_version } => {
+                match visual_cpp_release(*major_linker_version, *minor_linker_version) {
+                    Some(release) => write!(f, "Visual C++ {} (linker {}.{:02})", release, major_linker_version, minor_linker_version),
+                    None => write!(f, "Visual C++ (linker {}.{:02})", major_linker_version, minor_linker_version),
+                }
+            },
+            Self::Masm => write!(f, "MASM"),
+            Self::Borland => write!(f, "Borland"),
+            Self::MinGw => write!(f, "MinGW"),
+            Self::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Returns the name of the Visual C++ release that shipped the linker with the given version.
+fn visual_cpp_release(major_linker_version: u8, minor_linker_version: u8) -> Option<&'static str> {
+    match (major_linker_version, minor_linker_version) {
+        (4, _) => Some("4.x"),
+        (5, _) => Some("5.0"),
+        (6, _) => Some("6.0"),
+        (7, 0) => Some(".NET 2002"),
+        (7, _) => Some(".NET 2003"),
+        (8, _) => Some("2005"),
+        (9, _) => Some("2008"),
+        (10, _) => Some("2010"),
+        (11, _) => Some("2012"),
+        (12, _) => Some("2013"),
+        (14, 0..=9) => Some("2015"),
+        (14, 10..=19) => Some("2017"),
+        (14, 20..=29) => Some("2019"),
+        (14, _) => Some("2022"),
+        _ => None,
+    }
+}
+
+
+/// The guessed toolchain along with the traces it was guessed from.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Fingerprint {
+    pub toolchain: Toolchain,
+
+    /// Human-readable descriptions of the traces that were taken into account.
+    pub evidence: Vec<String>,
+}
+
+/// Guesses the toolchain from the traces it has left in an executable.
+pub fn fingerprint(traits: &BuildTraits) -> Fingerprint {
+    let mut evidence = vec![format!("linker version {}.{:02}", traits.major_linker_version, traits.minor_linker_version)];
+    let visual_cpp = Toolchain::VisualCpp {
+        major_linker_version: traits.major_linker_version,
+        minor_linker_version: traits.minor_linker_version,
+    };
+
+    let has_security_cookie = traits.load_config.is_some_and(|lc| lc.has_security_cookie());
+    if has_security_cookie {
+        evidence.push("security cookie (/GS, Visual C++ .NET 2002 or later)".to_owned());
+    }
+    if traits.load_config.is_some_and(|lc| lc.has_safe_seh()) {
+        evidence.push("safe exception handler table (/SAFESEH, Visual C++ .NET 2003 or later)".to_owned());
+    }
+    if traits.has_exception_directory {
+        evidence.push("exception directory".to_owned());
+    }
+
+    let gnu_sections: Vec<&str> = traits.section_names.iter()
+        .map(|n| n.as_str())
+        .filter(|n| GNU_SECTION_NAMES.contains(n) || is_long_section_name_reference(n))
+        .collect();
+    let borland_sections: Vec<&str> = traits.section_names.iter()
+        .map(|n| n.as_str())
+        .filter(|n| BORLAND_SECTION_NAMES.contains(n))
+        .collect();
+
+    let toolchain = if let Some(entries) = traits.rich_entries.as_ref() {
+        evidence.push(format!("Rich header with {} entries", entries.len()));
+        let has_masm = entries.iter().any(|e| MASM_PRODUCT_IDS.contains(&e.product_id));
+        let only_masm = entries.iter()
+            .all(|e| MASM_PRODUCT_IDS.contains(&e.product_id) || NON_COMPILER_PRODUCT_IDS.contains(&e.product_id));
+        if has_masm && only_masm {
+            evidence.push("only assembler objects in the Rich header".to_owned());
+            Toolchain::Masm
+        } else {
+            visual_cpp
+        }
+    } else if !gnu_sections.is_empty() {
+        evidence.push(format!("sections named by the GNU linker: {}", gnu_sections.join(", ")));
+        Toolchain::MinGw
+    } else if traits.borland_stub || !borland_sections.is_empty() {
+        if traits.borland_stub {
+            evidence.push("Borland DOS stub".to_owned());
+        }
+        if !borland_sections.is_empty() {
+            evidence.push(format!("sections named by Delphi: {}", borland_sections.join(", ")));
+        }
+        Toolchain::Borland
+    } else if (traits.major_linker_version, traits.minor_linker_version) == (2, 25) {
+        evidence.push("linker version of Borland TLINK32".to_owned());
+        Toolchain::Borland
+    } else if traits.major_linker_version >= 6 && visual_cpp_release(traits.major_linker_version, traits.minor_linker_version).is_some() {
+        evidence.push("no Rich header (removed, or not a Microsoft linker)".to_owned());
+        visual_cpp
+    } else if has_security_cookie {
+        visual_cpp
+    } else {
+        Toolchain::Unknown
+    };
+
+    Fingerprint {
+        toolchain,
+        evidence,
+    }
+}
+
+/// Whether the section name is a reference into the string table (e.g. `/4`), which the GNU linker
+/// uses for names longer than eight bytes.
+fn is_long_section_name_reference(name: &str) -> bool {
+    name.strip_prefix('/')
+        .is_some_and(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{fingerprint, BuildTraits, Toolchain};
+    use crate::mz::{decode_rich_header, RichEntry};
+    use crate::pe::LoadConfigDirectory;
+    use testgen::pe;
+
+    fn encrypted_rich_header(entries: &[RichEntry], key: u32) -> Vec<u8> {
+        let mut words = vec![u32::from_le_bytes(*b"DanS"), 0, 0, 0];
+        for entry in entries {
+            words.push((u32::from(entry.product_id) << 16) | u32::from(entry.build));
+            words.push(entry.count);
+        }
+        let mut bytes: Vec<u8> = words.iter()
+            .flat_map(|w| (w ^ key).to_le_bytes())
+            .collect();
+        bytes.extend_from_slice(b"Rich");
+        bytes.extend_from_slice(&key.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_rich_header_toolchains() {
+        let linker = RichEntry { product_id: 0x0102, build: 30729, count: 1 };
+        let masm = RichEntry { product_id: 0x0103, build: 30729, count: 4 };
+        let c_compiler = RichEntry { product_id: 0x0104, build: 30729, count: 12 };
+
+        let c_header = encrypted_rich_header(&[c_compiler, masm, linker], 0x5EED_1234);
+        let c_entries = decode_rich_header(&c_header).unwrap();
+        assert_eq!(c_entries, vec![c_compiler, masm, linker]);
+        let c_traits = BuildTraits {
+            major_linker_version: 14,
+            minor_linker_version: 29,
+            rich_entries: Some(c_entries),
+            load_config: Some(LoadConfigDirectory { size: 0x48, security_cookie: Some(0x1000_3000), se_handler_table: Some(0x1000_2000), se_handler_count: Some(3) }),
+            ..BuildTraits::default()
+        };
+        let c_fingerprint = fingerprint(&c_traits);
+        assert_eq!(c_fingerprint.toolchain.to_string(), "Visual C++ 2019 (linker 14.29)");
+        assert!(c_fingerprint.evidence.iter().any(|e| e.starts_with("security cookie")));
+        assert!(c_fingerprint.evidence.iter().any(|e| e.starts_with("safe exception handler table")));
+
+        let masm_traits = BuildTraits {
+            rich_entries: decode_rich_header(&encrypted_rich_header(&[masm, linker], 0xCAFE_F00D)),
+            ..c_traits
+        };
+        assert_eq!(fingerprint(&masm_traits).toolchain, Toolchain::Masm);
+
+        let mut corrupted_header = c_header.clone();
+        corrupted_header[4] ^= 0x01;
+        assert_eq!(decode_rich_header(&corrupted_header), None);
+    }
+
+    #[test]
+    fn test_section_toolchains() {
+        let section_traits = |names: &[&str], major_linker_version, minor_linker_version| BuildTraits {
+            major_linker_version,
+            minor_linker_version,
+            section_names: names.iter().map(|n| (*n).to_owned()).collect(),
+            ..BuildTraits::default()
+        };
+        assert_eq!(fingerprint(&section_traits(&[".text", ".data", ".rdata", ".eh_fram", ".bss", ".idata", "/4"], 2, 30)).toolchain, Toolchain::MinGw);
+        assert_eq!(fingerprint(&section_traits(&["CODE", "DATA", "BSS", ".idata", ".tls", ".rdata", ".reloc", ".rsrc"], 2, 25)).toolchain, Toolchain::Borland);
+        assert_eq!(fingerprint(&section_traits(&[".text", ".rdata", ".data", ".rsrc"], 6, 0)).toolchain.to_string(), "Visual C++ 6.0 (linker 6.00)");
+        assert_eq!(fingerprint(&section_traits(&[".text"], 0, 0)).toolchain, Toolchain::Unknown);
+    }
+
+    #[test]
+    fn test_read_synthesized_traits() {
+        let library = pe::PortableExecutable::new("SYNTH.DLL").to_bytes();
+        let traits = BuildTraits::read(&mut Cursor::new(&library)).unwrap();
+        assert_eq!(traits.rich_entries, None);
+        assert_eq!(traits.section_names, vec![".text".to_owned()]);
+        assert_eq!(traits.load_config, None);
+        assert!(!traits.has_exception_directory);
+        assert_eq!(fingerprint(&traits).toolchain, Toolchain::Unknown);
+    }
+}
@@ -0,0 +1,680 @@
+//! Windows CE ROM images.
+//!
+//! Windows CE device images are distributed either as raw images of the ROM (`.nb0`) or in the
+//! record-based `.bin` format produced by `romimage`, which starts with the signature `B000FF\n`
+//! followed by the start address and length of the image and then a sequence of records:
+//!
+//! ```plain
+//! struct BinRecord {
+//!     pub address: u32, // 0 for the last record
+//!     pub length: u32, // entry point for the last record
+//!     pub checksum: u32, // sum of the data bytes
+//!     pub data: [u8; length], // absent for the last record
+//! }
+//! ```
+//!
+//! Within the image, the signature `ECEC` at offset 0x40 is followed by the address of the ROM
+//! header, which is immediately followed by the table of contents of the modules and then that of
+//! the other files. Modules (executables and libraries) are not stored as PE files; their headers
+//! are condensed into an `e32_rom` structure followed by an `o32_rom` structure for each section,
+//! while the section data is placed wherever `romimage` saw fit.
+
+
+use std::io::{self, Read};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::collect_nul_terminated_ascii_string;
+use crate::int_from_byte_slice::IntFromByteSlice;
+use crate::pe::{DataDirectoryEntry, MachineType, SectionCharacteristics};
+
+
+/// The signature at the start of a `.bin` file.
+pub const BIN_SIGNATURE: &[u8; 7] = b"B000FF\n";
+
+/// The offset of the `ECEC` signature from the start of the ROM image.
+pub const ROM_SIGNATURE_OFFSET: usize = 0x40;
+
+/// The number of data directory entries in an `e32_rom` structure.
+pub const ROM_EXTRA: usize = 9;
+
+const ROM_HEADER_SIZE: usize = 84;
+const TOC_ENTRY_SIZE: usize = 32;
+const FILES_ENTRY_SIZE: usize = 28;
+const O32_ROM_SIZE: usize = 24;
+
+/// How many times larger than the data stored in its records a `.bin` image may be.
+const MAX_BIN_GAP_FACTOR: usize = 4;
+
+/// Section flag marking section data compressed by `romimage`.
+pub const SECTION_COMPRESSED: u32 = 0x0000_2000;
+
+
+/// The contents of a ROM image, placed at the address at which they are mapped into memory.
+#[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct RomImage {
+    pub base_address: u32,
+    pub data: Vec<u8>,
+}
+impl RomImage {
+    /// Reads a ROM image in the `.bin` format.
+    pub fn read_bin<R: Read>(reader: &mut R) -> Result<Self, io::Error> {
+        let mut header_buf = [0u8; 15];
+        reader.read_exact(&mut header_buf)?;
+        if &header_buf[0..7] != BIN_SIGNATURE {
+            debug!("Windows CE .bin signature is {:?}, expected {:?}", &header_buf[0..7], BIN_SIGNATURE);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let base_address = u32::from_le_byte_slice(&header_buf[7..11]);
+        let length = u32::from_le_byte_slice(&header_buf[11..15]);
+
+        // collect the records first; the header's length alone is no indication of how much
+        // memory the image actually needs
+        let mut records: Vec<(usize, Vec<u8>)> = Vec::new();
+        let mut covered: usize = 0;
+        let mut end: usize = 0;
+        loop {
+            let mut record_buf = [0u8; 12];
+            reader.read_exact(&mut record_buf)?;
+            let address = u32::from_le_byte_slice(&record_buf[0..4]);
+            let record_length = u32::from_le_byte_slice(&record_buf[4..8]);
+            let checksum = u32::from_le_byte_slice(&record_buf[8..12]);
+            if address == 0 && checksum == 0 {
+                // final record; the length is the entry point
+                break;
+            }
+
+            let start = address.checked_sub(base_address)
+                .filter(|s| s.checked_add(record_length).is_some_and(|e| e <= length))
+                .map(|s| usize::try_from(s).unwrap());
+            let Some(start) = start else {
+                debug!("record at {:#010X} is outside the image at {:#010X}", address, base_address);
+                return Err(io::ErrorKind::InvalidData.into());
+            };
+
+            let record_length_usize = usize::try_from(record_length).unwrap();
+            let mut record_data = Vec::new();
+            reader.by_ref().take(u64::from(record_length)).read_to_end(&mut record_data)?;
+            if record_data.len() != record_length_usize {
+                debug!("record at {:#010X} is truncated", address);
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let actual_checksum = record_data.iter()
+                .fold(0u32, |sum, b| sum.wrapping_add(u32::from(*b)));
+            if actual_checksum != checksum {
+                debug!("record at {:#010X} has checksum {:#010X}, expected {:#010X}", address, actual_checksum, checksum);
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+
+            covered += record_data.len();
+            end = end.max(start + record_data.len());
+            records.push((start, record_data));
+        }
+
+        // gaps between records are zero-filled; refuse to blow up a handful of records into an
+        // image that is mostly gaps
+        if end > covered.saturating_mul(MAX_BIN_GAP_FACTOR) {
+            debug!("records cover only {} of {} bytes of the image", covered, end);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let mut data = vec![0u8; end];
+        for (start, record_data) in records {
+            data[start..start+record_data.len()].copy_from_slice(&record_data);
+        }
+
+        Ok(Self {
+            base_address,
+            data,
+        })
+    }
+
+    /// Interprets the raw contents of a ROM (`.nb0`).
+    ///
+    /// Raw images do not store their base address; it is derived from the ROM header, which
+    /// knows the address of the start of the image.
+    pub fn from_raw(data: Vec<u8>) -> Result<Self, io::Error> {
+        let header_address = Self::rom_header_address(&data)?;
+
+        // find a ROM header whose first physical address places it at the referenced address
+        let mut offset = 0;
+        while offset + ROM_HEADER_SIZE <= data.len() {
+            let physical_first = u32::from_le_byte_slice(&data[offset+8..offset+12]);
+            let physical_last = u32::from_le_byte_slice(&data[offset+12..offset+16]);
+            let offset_u32 = u32::try_from(offset).unwrap();
+            let plausible =
+                physical_first.checked_add(offset_u32) == Some(header_address)
+                && physical_last > physical_first
+                && usize::try_from(physical_last - physical_first).unwrap() <= data.len();
+            if plausible {
+                return Ok(Self {
+                    base_address: physical_first,
+                    data,
+                });
+            }
+            offset += 4;
+        }
+
+        debug!("no ROM header matching address {:#010X} found", header_address);
+        Err(io::ErrorKind::InvalidData.into())
+    }
+
+    fn rom_header_address(data: &[u8]) -> Result<u32, io::Error> {
+        let Some(signature_and_address) = data.get(ROM_SIGNATURE_OFFSET..ROM_SIGNATURE_OFFSET+8) else {
+            debug!("image too short for the ROM signature");
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        };
+        if &signature_and_address[0..4] != b"ECEC" {
+            debug!("ROM signature is {:?}, expected b\"ECEC\"", &signature_and_address[0..4]);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        Ok(u32::from_le_byte_slice(&signature_and_address[4..8]))
+    }
+
+    /// Returns the data at the given address, if it is within the image.
+    pub fn slice(&self, address: u32, length: u32) -> Option<&[u8]> {
+        let start = usize::try_from(address.checked_sub(self.base_address)?).unwrap();
+        let end = start.checked_add(usize::try_from(length).unwrap())?;
+        self.data.get(start..end)
+    }
+
+    /// Returns the NUL-terminated ASCII string at the given address.
+    pub fn string(&self, address: u32) -> Option<String> {
+        let start = usize::try_from(address.checked_sub(self.base_address)?).unwrap();
+        collect_nul_terminated_ascii_string(self.data.get(start..)?)
+    }
+
+    fn slice_or_error(&self, address: u32, length: usize, what: &str) -> Result<&[u8], io::Error> {
+        let Ok(length) = u32::try_from(length) else {
+            debug!("{} at {:#010X} is too long", what, address);
+            return Err(io::ErrorKind::InvalidData.into());
+        };
+        self.slice(address, length)
+            .ok_or(io::ErrorKind::UnexpectedEof)
+            .inspect_err(|_| debug!("{} at {:#010X} is outside the image", what, address))
+            .map_err(io::Error::from)
+    }
+
+    fn string_or_error(&self, address: u32) -> Result<String, io::Error> {
+        self.string(address)
+            .ok_or(io::ErrorKind::InvalidData)
+            .inspect_err(|_| debug!("string at {:#010X} is outside the image or invalid", address))
+            .map_err(io::Error::from)
+    }
+}
+impl std::fmt::Debug for RomImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RomImage")
+            .field("base_address", &self.base_address)
+            .field("data", &format_args!("[{} bytes]", self.data.len()))
+            .finish()
+    }
+}
+
+
+fn checked_address(address: u32, offset: usize, what: &str) -> Result<u32, io::Error> {
+    u32::try_from(offset).ok()
+        .and_then(|offset| address.checked_add(offset))
+        .ok_or(io::ErrorKind::InvalidData)
+        .inspect_err(|_| debug!("address of {} overflows", what))
+        .map_err(io::Error::from)
+}
+
+fn checked_table_length(count: usize, entry_size: usize, what: &str) -> Result<usize, io::Error> {
+    count.checked_mul(entry_size)
+        .ok_or(io::ErrorKind::InvalidData)
+        .inspect_err(|_| debug!("length of {} overflows", what))
+        .map_err(io::Error::from)
+}
+
+
+/// The structure of a ROM: the header and the tables of contents.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Rom {
+    pub header: RomHeader,
+    pub modules: Vec<RomModule>, // [TocEntry; header.module_count] after the header
+    pub files: Vec<RomFile>, // [FilesEntry; header.file_count] after the modules
+}
+impl Rom {
+    pub fn read(image: &RomImage) -> Result<Self, io::Error> {
+        let header_address = RomImage::rom_header_address(&image.data)?;
+        let header_bytes = image.slice_or_error(header_address, ROM_HEADER_SIZE, "ROM header")?;
+        let header = RomHeader::from_bytes(header_bytes.try_into().unwrap());
+
+        let module_count = usize::try_from(header.module_count).unwrap();
+        let file_count = usize::try_from(header.file_count).unwrap();
+        let toc_address = checked_address(header_address, ROM_HEADER_SIZE, "module table")?;
+        let toc_length = checked_table_length(module_count, TOC_ENTRY_SIZE, "module table")?;
+        let toc_bytes = image.slice_or_error(toc_address, toc_length, "module table")?;
+        let files_address = checked_address(toc_address, toc_bytes.len(), "file table")?;
+        let files_length = checked_table_length(file_count, FILES_ENTRY_SIZE, "file table")?;
+        let files_bytes = image.slice_or_error(files_address, files_length, "file table")?;
+
+        // both tables are within the image, which bounds the counts
+        let mut modules = Vec::with_capacity(module_count);
+        for entry_bytes in toc_bytes.chunks_exact(TOC_ENTRY_SIZE) {
+            modules.push(RomModule::read(image, entry_bytes)?);
+        }
+
+        let mut files = Vec::with_capacity(file_count);
+        for entry_bytes in files_bytes.chunks_exact(FILES_ENTRY_SIZE) {
+            files.push(RomFile::read(image, entry_bytes)?);
+        }
+
+        Ok(Self {
+            header,
+            modules,
+            files,
+        })
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct RomHeader {
+    pub dll_first: u32,
+    pub dll_last: u32,
+    pub physical_first: u32,
+    pub physical_last: u32,
+    pub module_count: u32,
+    pub ram_start: u32,
+    pub ram_free: u32,
+    pub ram_end: u32,
+    pub copy_entry_count: u32,
+    pub copy_offset: u32,
+    pub profile_length: u32,
+    pub profile_offset: u32,
+    pub file_count: u32,
+    pub kernel_flags: u32,
+    pub file_system_ram_percent: u32,
+    pub driver_globals_start: u32,
+    pub driver_globals_length: u32,
+    pub cpu_type: MachineType, // u16
+    pub misc_flags: u16,
+    pub extensions: u32,
+    pub tracking_start: u32,
+    pub tracking_length: u32,
+}
+impl RomHeader {
+    pub fn from_bytes(buf: &[u8; ROM_HEADER_SIZE]) -> Self {
+        Self {
+            dll_first: u32::from_le_byte_slice(&buf[0..4]),
+            dll_last: u32::from_le_byte_slice(&buf[4..8]),
+            physical_first: u32::from_le_byte_slice(&buf[8..12]),
+            physical_last: u32::from_le_byte_slice(&buf[12..16]),
+            module_count: u32::from_le_byte_slice(&buf[16..20]),
+            ram_start: u32::from_le_byte_slice(&buf[20..24]),
+            ram_free: u32::from_le_byte_slice(&buf[24..28]),
+            ram_end: u32::from_le_byte_slice(&buf[28..32]),
+            copy_entry_count: u32::from_le_byte_slice(&buf[32..36]),
+            copy_offset: u32::from_le_byte_slice(&buf[36..40]),
+            profile_length: u32::from_le_byte_slice(&buf[40..44]),
+            profile_offset: u32::from_le_byte_slice(&buf[44..48]),
+            file_count: u32::from_le_byte_slice(&buf[48..52]),
+            kernel_flags: u32::from_le_byte_slice(&buf[52..56]),
+            file_system_ram_percent: u32::from_le_byte_slice(&buf[56..60]),
+            driver_globals_start: u32::from_le_byte_slice(&buf[60..64]),
+            driver_globals_length: u32::from_le_byte_slice(&buf[64..68]),
+            cpu_type: MachineType::from_base_type(u16::from_le_byte_slice(&buf[68..70])),
+            misc_flags: u16::from_le_byte_slice(&buf[70..72]),
+            extensions: u32::from_le_byte_slice(&buf[72..76]),
+            tracking_start: u32::from_le_byte_slice(&buf[76..80]),
+            tracking_length: u32::from_le_byte_slice(&buf[80..84]),
+        }
+    }
+}
+
+
+/// An executable or library in the ROM (`TOCentry`), along with its headers.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct RomModule {
+    pub attributes: u32,
+    pub file_time: u64,
+    pub file_size: u32,
+    pub name: String, // u32 address of NUL-terminated string
+    pub e32_address: u32,
+    pub o32_address: u32,
+    pub load_address: u32,
+    pub e32: E32Rom, // at e32_address
+    pub sections: Vec<O32Rom>, // [O32Rom; e32.section_count] at o32_address
+}
+impl RomModule {
+    fn read(image: &RomImage, buf: &[u8]) -> Result<Self, io::Error> {
+        let attributes = u32::from_le_byte_slice(&buf[0..4]);
+        let file_time = u64::from_le_byte_slice(&buf[4..12]);
+        let file_size = u32::from_le_byte_slice(&buf[12..16]);
+        let name_address = u32::from_le_byte_slice(&buf[16..20]);
+        let e32_address = u32::from_le_byte_slice(&buf[20..24]);
+        let o32_address = u32::from_le_byte_slice(&buf[24..28]);
+        let load_address = u32::from_le_byte_slice(&buf[28..32]);
+
+        let name = image.string_or_error(name_address)?;
+        let e32_bytes = image.slice_or_error(e32_address, E32Rom::SIZE_WITH_TIMESTAMP, "e32 header")?;
+        let e32 = E32Rom::from_bytes(e32_bytes, true);
+        let e32 = if e32.is_plausible() {
+            e32
+        } else {
+            // Windows CE 3.0 and earlier have no timestamp
+            E32Rom::from_bytes(e32_bytes, false)
+        };
+
+        let section_count = usize::from(e32.section_count);
+        let o32_length = checked_table_length(section_count, O32_ROM_SIZE, "o32 headers")?;
+        let o32_bytes = image.slice_or_error(o32_address, o32_length, "o32 headers")?;
+        let sections = o32_bytes.chunks_exact(O32_ROM_SIZE)
+            .map(O32Rom::from_bytes)
+            .collect();
+
+        Ok(Self {
+            attributes,
+            file_time,
+            file_size,
+            name,
+            e32_address,
+            o32_address,
+            load_address,
+            e32,
+            sections,
+        })
+    }
+}
+
+
+/// The condensed PE headers of a module (`e32_rom`).
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct E32Rom {
+    pub section_count: u16,
+    pub image_flags: u16, // PE characteristics
+    pub entry_point_rva: u32,
+    pub virtual_base: u32,
+    pub subsystem_major: u16,
+    pub subsystem_minor: u16,
+    pub stack_max: u32,
+    pub virtual_size: u32,
+    pub section14_rva: u32,
+    pub section14_size: u32,
+    pub timestamp: Option<u32>, // not present before Windows CE 4.0
+    pub units: Vec<DataDirectoryEntry>, // [DataDirectoryEntry; ROM_EXTRA]; same order as PE data directories
+    pub subsystem: u16,
+}
+impl E32Rom {
+    const SIZE_WITH_TIMESTAMP: usize = 38 + ROM_EXTRA * 8;
+
+    fn from_bytes(buf: &[u8], with_timestamp: bool) -> Self {
+        let (timestamp, units_offset) = if with_timestamp {
+            (Some(u32::from_le_byte_slice(&buf[32..36])), 36)
+        } else {
+            (None, 32)
+        };
+        let units = buf[units_offset..units_offset+ROM_EXTRA*8]
+            .chunks_exact(8)
+            .map(|unit| DataDirectoryEntry {
+                address: u32::from_le_byte_slice(&unit[0..4]),
+                size: u32::from_le_byte_slice(&unit[4..8]),
+            })
+            .collect();
+        let subsystem_offset = units_offset + ROM_EXTRA * 8;
+
+        Self {
+            section_count: u16::from_le_byte_slice(&buf[0..2]),
+            image_flags: u16::from_le_byte_slice(&buf[2..4]),
+            entry_point_rva: u32::from_le_byte_slice(&buf[4..8]),
+            virtual_base: u32::from_le_byte_slice(&buf[8..12]),
+            subsystem_major: u16::from_le_byte_slice(&buf[12..14]),
+            subsystem_minor: u16::from_le_byte_slice(&buf[14..16]),
+            stack_max: u32::from_le_byte_slice(&buf[16..20]),
+            virtual_size: u32::from_le_byte_slice(&buf[20..24]),
+            section14_rva: u32::from_le_byte_slice(&buf[24..28]),
+            section14_size: u32::from_le_byte_slice(&buf[28..32]),
+            timestamp,
+            units,
+            subsystem: u16::from_le_byte_slice(&buf[subsystem_offset..subsystem_offset+2]),
+        }
+    }
+
+    /// Whether all the data directory entries lie within the image, which is unlikely if the
+    /// structure has been read using the wrong layout.
+    fn is_plausible(&self) -> bool {
+        self.units.iter().all(|unit| {
+            unit.address.checked_add(unit.size)
+                .is_some_and(|end| end <= self.virtual_size)
+        })
+    }
+}
+
+
+/// A section of a module (`o32_rom`).
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct O32Rom {
+    pub virtual_size: u32,
+    pub rva: u32,
+    pub physical_size: u32,
+
+    /// The address of the section's data in the ROM.
+    pub data_address: u32,
+
+    /// The address at which the section is placed in memory.
+    pub real_address: u32,
+
+    pub flags: u32, // SectionCharacteristics | SECTION_COMPRESSED
+}
+impl O32Rom {
+    fn from_bytes(buf: &[u8]) -> Self {
+        Self {
+            virtual_size: u32::from_le_byte_slice(&buf[0..4]),
+            rva: u32::from_le_byte_slice(&buf[4..8]),
+            physical_size: u32::from_le_byte_slice(&buf[8..12]),
+            data_address: u32::from_le_byte_slice(&buf[12..16]),
+            real_address: u32::from_le_byte_slice(&buf[16..20]),
+            flags: u32::from_le_byte_slice(&buf[20..24]),
+        }
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.flags & SECTION_COMPRESSED != 0
+    }
+
+    /// The section characteristics, without the ROM-specific compression flag.
+    pub fn characteristics(&self) -> SectionCharacteristics {
+        SectionCharacteristics::from_bits_retain(self.flags & !SECTION_COMPRESSED)
+    }
+}
+
+
+/// A file other than a module in the ROM (`FILESentry`).
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct RomFile {
+    pub attributes: u32,
+    pub file_time: u64,
+    pub real_size: u32,
+    pub compressed_size: u32,
+    pub name: String, // u32 address of NUL-terminated string
+    pub load_address: u32,
+}
+impl RomFile {
+    fn read(image: &RomImage, buf: &[u8]) -> Result<Self, io::Error> {
+        let name_address = u32::from_le_byte_slice(&buf[20..24]);
+        Ok(Self {
+            attributes: u32::from_le_byte_slice(&buf[0..4]),
+            file_time: u64::from_le_byte_slice(&buf[4..12]),
+            real_size: u32::from_le_byte_slice(&buf[12..16]),
+            compressed_size: u32::from_le_byte_slice(&buf[16..20]),
+            name: image.string_or_error(name_address)?,
+            load_address: u32::from_le_byte_slice(&buf[24..28]),
+        })
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.compressed_size != self.real_size
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{BIN_SIGNATURE, Rom, RomImage};
+    use std::io::Cursor;
+
+    const BASE: u32 = 0x8000_0000;
+    const HEADER_OFFSET: usize = 0x100;
+
+    fn put_u32(data: &mut [u8], offset: usize, value: u32) {
+        data[offset..offset+4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn put_u16(data: &mut [u8], offset: usize, value: u16) {
+        data[offset..offset+2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// A raw image with one module with one section and one file.
+    fn synthesize_raw() -> Vec<u8> {
+        let mut data = vec![0u8; 0x400];
+        data[0x40..0x44].copy_from_slice(b"ECEC");
+        put_u32(&mut data, 0x44, BASE + u32::try_from(HEADER_OFFSET).unwrap());
+
+        // ROM header
+        put_u32(&mut data, HEADER_OFFSET + 8, BASE);
+        put_u32(&mut data, HEADER_OFFSET + 12, BASE + 0x400);
+        put_u32(&mut data, HEADER_OFFSET + 16, 1); // modules
+        put_u32(&mut data, HEADER_OFFSET + 48, 1); // files
+        put_u16(&mut data, HEADER_OFFSET + 68, 0x01C2); // Thumb
+
+        // module TOC entry
+        let toc = HEADER_OFFSET + 84;
+        put_u32(&mut data, toc + 12, 0x1234);
+        put_u32(&mut data, toc + 16, BASE + 0x300);
+        put_u32(&mut data, toc + 20, BASE + 0x200);
+        put_u32(&mut data, toc + 24, BASE + 0x280);
+
+        // file entry
+        let files = toc + 32;
+        put_u32(&mut data, files + 12, 5);
+        put_u32(&mut data, files + 16, 5);
+        put_u32(&mut data, files + 20, BASE + 0x310);
+
+        // e32_rom
+        put_u16(&mut data, 0x200, 1);
+        put_u32(&mut data, 0x200 + 20, 0x2000);
+
+        // o32_rom
+        put_u32(&mut data, 0x280, 0x100);
+        put_u32(&mut data, 0x280 + 4, 0x1000);
+        put_u32(&mut data, 0x280 + 8, 0x100);
+        put_u32(&mut data, 0x280 + 12, BASE + 0x380);
+        put_u32(&mut data, 0x280 + 20, 0x6000_0020);
+
+        data[0x300..0x30A].copy_from_slice(b"synth.dll\0");
+        data[0x310..0x31A].copy_from_slice(b"synth.txt\0");
+        data
+    }
+
+    fn to_bin(data: &[u8], record_size: usize) -> Vec<u8> {
+        let mut bin = BIN_SIGNATURE.to_vec();
+        bin.extend_from_slice(&BASE.to_le_bytes());
+        bin.extend_from_slice(&u32::try_from(data.len()).unwrap().to_le_bytes());
+        for (i, chunk) in data.chunks(record_size).enumerate() {
+            let address = BASE + u32::try_from(i * record_size).unwrap();
+            let checksum = chunk.iter().fold(0u32, |sum, b| sum.wrapping_add(u32::from(*b)));
+            bin.extend_from_slice(&address.to_le_bytes());
+            bin.extend_from_slice(&u32::try_from(chunk.len()).unwrap().to_le_bytes());
+            bin.extend_from_slice(&checksum.to_le_bytes());
+            bin.extend_from_slice(chunk);
+        }
+        bin.extend_from_slice(&[0u8; 12]);
+        bin
+    }
+
+    #[test]
+    fn test_read_synthesized_rom() {
+        let image = RomImage::from_raw(synthesize_raw()).unwrap();
+        assert_eq!(image.base_address, BASE);
+
+        let rom = Rom::read(&image).unwrap();
+        assert_eq!(rom.header.module_count, 1);
+        assert_eq!(rom.modules.len(), 1);
+        assert_eq!(rom.modules[0].name, "synth.dll");
+        assert_eq!(rom.modules[0].file_size, 0x1234);
+        assert_eq!(rom.modules[0].sections.len(), 1);
+        assert_eq!(rom.modules[0].sections[0].rva, 0x1000);
+        assert_eq!(rom.modules[0].sections[0].data_address, BASE + 0x380);
+        assert_eq!(rom.files.len(), 1);
+        assert_eq!(rom.files[0].name, "synth.txt");
+        assert!(!rom.files[0].is_compressed());
+    }
+
+    #[test]
+    fn test_reject_huge_counts() {
+        for count_offset in [16, 48] {
+            let mut data = synthesize_raw();
+            put_u32(&mut data, HEADER_OFFSET + count_offset, 1 << 27);
+            let image = RomImage::from_raw(data).unwrap();
+            assert!(Rom::read(&image).is_err());
+        }
+
+        let mut data = synthesize_raw();
+        put_u16(&mut data, 0x200, 0xFFFF);
+        let image = RomImage::from_raw(data).unwrap();
+        assert!(Rom::read(&image).is_err());
+
+        // header address at the very end of the address space
+        let mut data = synthesize_raw();
+        put_u32(&mut data, 0x44, u32::MAX - 4);
+        let image = RomImage { base_address: BASE, data };
+        assert!(Rom::read(&image).is_err());
+    }
+
+    #[test]
+    fn test_read_bin() {
+        let raw = synthesize_raw();
+        let bin = to_bin(&raw, 0x90);
+        let image = RomImage::read_bin(&mut Cursor::new(&bin)).unwrap();
+        assert_eq!(image.base_address, BASE);
+        assert_eq!(image.data, raw);
+        assert_eq!(Rom::read(&image).unwrap().modules[0].name, "synth.dll");
+
+        // wrong checksum
+        let mut broken = bin.clone();
+        broken[15 + 8] ^= 0x01;
+        assert!(RomImage::read_bin(&mut Cursor::new(&broken)).is_err());
+
+        // truncated record
+        assert!(RomImage::read_bin(&mut Cursor::new(&bin[..15 + 12 + 0x10])).is_err());
+    }
+
+    #[test]
+    fn test_reject_huge_bin_lengths() {
+        // a record claiming far more data than the file contains
+        let mut bin = BIN_SIGNATURE.to_vec();
+        bin.extend_from_slice(&BASE.to_le_bytes());
+        bin.extend_from_slice(&u32::MAX.to_le_bytes());
+        bin.extend_from_slice(&BASE.to_le_bytes());
+        bin.extend_from_slice(&0xFFFF_0000u32.to_le_bytes());
+        bin.extend_from_slice(&1u32.to_le_bytes());
+        bin.push(1);
+        assert!(RomImage::read_bin(&mut Cursor::new(&bin)).is_err());
+
+        // a tiny record at the end of a huge image
+        let mut bin = BIN_SIGNATURE.to_vec();
+        bin.extend_from_slice(&BASE.to_le_bytes());
+        bin.extend_from_slice(&0x7800_0000u32.to_le_bytes());
+        bin.extend_from_slice(&(BASE + 0x7000_0000).to_le_bytes());
+        bin.extend_from_slice(&1u32.to_le_bytes());
+        bin.extend_from_slice(&1u32.to_le_bytes());
+        bin.push(1);
+        bin.extend_from_slice(&[0u8; 12]);
+        assert!(RomImage::read_bin(&mut Cursor::new(&bin)).is_err());
+
+        // records outside the announced length
+        let raw = synthesize_raw();
+        let mut bin = to_bin(&raw, 0x400);
+        bin[11..15].copy_from_slice(&0x3FFu32.to_le_bytes());
+        assert!(RomImage::read_bin(&mut Cursor::new(&bin)).is_err());
+    }
+}
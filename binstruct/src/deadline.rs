@@ -0,0 +1,113 @@
+//! A time limit after which the readers of the current thread give up.
+//!
+//! Malformed files can make some readers run practically forever, e.g. walking trees whose nodes
+//! point at each other or cluster chains that loop back onto themselves. Instead of passing a
+//! limit to every reader, the caller sets a limit for the current thread using [`set_time_limit`]
+//! and the loops that can run away call [`check`] every now and then.
+
+
+use std::cell::Cell;
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+
+
+thread_local! {
+    static DEADLINE: Cell<Option<(Instant, Duration)>> = const { Cell::new(None) };
+}
+
+
+/// Sets a time limit for the current thread, after which [`check`] fails.
+///
+/// The limit holds until the returned guard is dropped, which restores the limit that was set
+/// before. Without a limit, the readers may take as long as they like. Limits do not nest; an
+/// inner limit replaces the outer one while its guard lives.
+#[must_use = "the time limit is lifted as soon as the guard is dropped"]
+pub fn set_time_limit(limit: Option<Duration>) -> DeadlineGuard {
+    let deadline = limit.map(|l| (Instant::now() + l, l));
+    DeadlineGuard {
+        previous: DEADLINE.replace(deadline),
+        not_send: PhantomData,
+    }
+}
+
+/// Returns whether the time limit set by [`set_time_limit`] has been exceeded.
+pub fn exceeded() -> bool {
+    DEADLINE.get()
+        .is_some_and(|(deadline, _limit)| Instant::now() >= deadline)
+}
+
+/// Fails with an error of kind [`TimedOut`](io::ErrorKind::TimedOut) wrapping
+/// [`TimeLimitExceeded`] if the time limit set by [`set_time_limit`] has been exceeded.
+pub fn check() -> Result<(), io::Error> {
+    match DEADLINE.get() {
+        Some((deadline, limit)) if Instant::now() >= deadline
+            => Err(io::Error::new(io::ErrorKind::TimedOut, TimeLimitExceeded { limit })),
+        _ => Ok(()),
+    }
+}
+
+
+/// Restores the previous time limit of the thread when dropped.
+pub struct DeadlineGuard {
+    previous: Option<(Instant, Duration)>,
+
+    // the limit belongs to the thread that set it
+    not_send: PhantomData<*const ()>,
+}
+impl Drop for DeadlineGuard {
+    fn drop(&mut self) {
+        DEADLINE.set(self.previous);
+    }
+}
+
+
+/// The error within the error returned by [`check`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct TimeLimitExceeded {
+    /// The time limit that has been exceeded.
+    pub limit: Duration,
+}
+impl fmt::Display for TimeLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "time limit of {:?} exceeded", self.limit)
+    }
+}
+impl std::error::Error for TimeLimitExceeded {
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::time::Duration;
+    use super::{check, exceeded, set_time_limit, TimeLimitExceeded};
+
+    #[test]
+    fn test_time_limit() {
+        assert!(check().is_ok());
+        {
+            let _outer = set_time_limit(Some(Duration::ZERO));
+            assert!(exceeded());
+            let error = check().unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+            assert_eq!(
+                error.get_ref().and_then(|e| e.downcast_ref::<TimeLimitExceeded>()),
+                Some(&TimeLimitExceeded { limit: Duration::ZERO }),
+            );
+            {
+                let _inner = set_time_limit(None);
+                assert!(check().is_ok());
+            }
+            assert!(check().is_err());
+            {
+                let _inner = set_time_limit(Some(Duration::from_secs(3600)));
+                assert!(!exceeded());
+            }
+            assert!(exceeded());
+        }
+        assert!(!exceeded());
+        assert!(check().is_ok());
+    }
+}
@@ -0,0 +1,193 @@
+//! Generates readers and writers for fixed-layout little-endian binary structures.
+//!
+//! Many on-disk headers are a flat sequence of integers and byte arrays. Instead of slicing a
+//! buffer by hand for every field, such structures can be declared using [`binread_struct!`],
+//! which generates `read`, `from_bytes`, `to_bytes` and `write` functions as well as the
+//! documentation of each field's offset.
+//!
+//! The [`deadline`] that keeps these readers from running away on malformed files lives here as
+//! well, as all the crates with readers depend on this one.
+
+
+pub mod deadline;
+
+
+use display_bytes::DisplayBytes;
+
+
+/// A value that can be stored as a field of a structure declared using [`binread_struct!`].
+pub trait BinaryField : Sized {
+    /// The size of the field in bytes.
+    const SIZE: usize;
+
+    /// Decodes the field from exactly [`BinaryField::SIZE`] bytes.
+    fn from_le_slice(bytes: &[u8]) -> Self;
+
+    /// Encodes the field into exactly [`BinaryField::SIZE`] bytes.
+    fn write_le_slice(&self, bytes: &mut [u8]);
+}
+
+macro_rules! impl_binary_field_int {
+    ($type:ty) => {
+        impl BinaryField for $type {
+            const SIZE: usize = core::mem::size_of::<$type>();
+
+            fn from_le_slice(bytes: &[u8]) -> Self {
+                Self::from_le_bytes(bytes.try_into().unwrap())
+            }
+
+            fn write_le_slice(&self, bytes: &mut [u8]) {
+                bytes.copy_from_slice(&self.to_le_bytes());
+            }
+        }
+    };
+}
+impl_binary_field_int!(u8);
+impl_binary_field_int!(u16);
+impl_binary_field_int!(u32);
+impl_binary_field_int!(u64);
+impl_binary_field_int!(i8);
+impl_binary_field_int!(i16);
+impl_binary_field_int!(i32);
+impl_binary_field_int!(i64);
+
+impl<const SIZE: usize> BinaryField for [u8; SIZE] {
+    const SIZE: usize = SIZE;
+
+    fn from_le_slice(bytes: &[u8]) -> Self {
+        bytes.try_into().unwrap()
+    }
+
+    fn write_le_slice(&self, bytes: &mut [u8]) {
+        bytes.copy_from_slice(self);
+    }
+}
+
+impl<const SIZE: usize> BinaryField for DisplayBytes<SIZE> {
+    const SIZE: usize = SIZE;
+
+    fn from_le_slice(bytes: &[u8]) -> Self {
+        bytes.try_into().unwrap()
+    }
+
+    fn write_le_slice(&self, bytes: &mut [u8]) {
+        bytes.copy_from_slice(self.as_ref());
+    }
+}
+
+
+/// Implements [`BinaryField`] for a `bitflags` type, storing it as its underlying integer type.
+///
+/// Unknown bits are retained.
+#[macro_export]
+macro_rules! impl_bitflags_field {
+    ($type:ty, $base_type:ty) => {
+        impl $crate::BinaryField for $type {
+            const SIZE: usize = <$base_type as $crate::BinaryField>::SIZE;
+
+            fn from_le_slice(bytes: &[u8]) -> Self {
+                Self::from_bits_retain(<$base_type as $crate::BinaryField>::from_le_slice(bytes))
+            }
+
+            fn write_le_slice(&self, bytes: &mut [u8]) {
+                $crate::BinaryField::write_le_slice(&self.bits(), bytes)
+            }
+        }
+    };
+}
+
+/// Declares a structure with a fixed binary layout along with functions to read and write it.
+///
+/// Each field is prefixed with its offset from the beginning of the structure, which is added to
+/// the field's documentation. The fields must directly follow each other; this is verified at
+/// compile time. Every field type must implement [`BinaryField`].
+///
+/// ```
+/// binstruct::binread_struct! {
+///     #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+///     pub struct Example {
+///         0x00 => pub signature: [u8; 2],
+///         /// The number of entries.
+///         0x02 => pub count: u16,
+///         0x04 => pub offset: u32,
+///     }
+/// }
+///
+/// let example = Example::from_bytes(b"EX\x03\x00\x10\x00\x00\x00");
+/// assert_eq!(example.count, 3);
+/// assert_eq!(example.offset, 0x10);
+/// assert_eq!(Example::SIZE, 8);
+/// ```
+#[macro_export]
+macro_rules! binread_struct {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                $offset:literal => $field_vis:vis $field:ident : $type:ty
+            ),*
+            $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                #[doc = ""]
+                #[doc = concat!("Offset `", stringify!($offset), "`.")]
+                $field_vis $field: $type,
+            )*
+        }
+        impl $name {
+            /// The size of the structure in bytes.
+            pub const SIZE: usize = 0 $(+ <$type as $crate::BinaryField>::SIZE)*;
+
+            /// Reads the structure from `reader`.
+            pub fn read<R: ::std::io::Read>(reader: &mut R) -> Result<Self, ::std::io::Error> {
+                let mut buf = [0u8; Self::SIZE];
+                reader.read_exact(&mut buf)?;
+                Ok(Self::from_bytes(&buf))
+            }
+
+            /// Decodes the structure from its bytes.
+            pub fn from_bytes(bytes: &[u8; Self::SIZE]) -> Self {
+                Self {
+                    $(
+                        $field: <$type as $crate::BinaryField>::from_le_slice(
+                            &bytes[$offset..$offset + <$type as $crate::BinaryField>::SIZE]
+                        ),
+                    )*
+                }
+            }
+
+            /// Encodes the structure into its bytes.
+            pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+                let mut bytes = [0u8; Self::SIZE];
+                $(
+                    $crate::BinaryField::write_le_slice(
+                        &self.$field,
+                        &mut bytes[$offset..$offset + <$type as $crate::BinaryField>::SIZE],
+                    );
+                )*
+                bytes
+            }
+
+            /// Writes the structure to `writer`.
+            pub fn write<W: ::std::io::Write>(&self, writer: &mut W) -> Result<(), ::std::io::Error> {
+                writer.write_all(&self.to_bytes())
+            }
+        }
+        const _: () = {
+            let mut expected_offset = 0;
+            $(
+                assert!(
+                    $offset == expected_offset,
+                    concat!("offset of ", stringify!($name), "::", stringify!($field), " does not follow the previous field"),
+                );
+                expected_offset += <$type as $crate::BinaryField>::SIZE;
+            )*
+            let _ = expected_offset;
+        };
+    };
+}
@@ -0,0 +1,257 @@
+//! The escaping rules shared by all textual output of byte strings and names.
+//!
+//! Both byte strings and text use the following escape sequences:
+//!
+//! | character                       | escaped as        |
+//! | ------------------------------- | ----------------- |
+//! | NUL (0x00)                      | `\0`              |
+//! | tab (0x09)                      | `\t`              |
+//! | line feed (0x0A)                | `\n`              |
+//! | carriage return (0x0D)          | `\r`              |
+//! | backslash (0x5C)                | `\\`              |
+//! | other ASCII control characters  | `\x01` to `\x1F`, `\x7F` |
+//!
+//! Byte strings are additionally enclosed in `b"` and `"`; within them, `"` is escaped as `\"` and
+//! all bytes outside of the ASCII range are escaped as `\x80` to `\xFF`.
+//!
+//! Text (e.g. the names of exported symbols) is not enclosed in anything, so `"` is output
+//! verbatim. Characters outside of the ASCII range are output verbatim as well, except for control
+//! characters (U+0080 to U+009F) and the line and paragraph separators (U+2028 and U+2029), which
+//! would otherwise break up lines in some editors; these are escaped as `\u{85}` and similar.
+//!
+//! Hex digits are output in uppercase. When unescaping, `\'`, `\"` and lowercase hex digits are
+//! accepted everywhere.
+
+
+use std::fmt;
+
+
+/// An error that occurred while parsing the escaped representation of a byte string or text.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ParseError {
+    /// The string does not start with `b"`.
+    MissingPrefix,
+
+    /// The string does not end with `"`.
+    MissingSuffix,
+
+    /// The byte at the given offset is not allowed unescaped within a byte string.
+    UnescapedCharacter { offset: usize },
+
+    /// The escape sequence at the given offset is invalid.
+    InvalidEscape { offset: usize },
+
+    /// The byte string has the wrong length for a fixed-size byte array.
+    WrongLength { expected: usize, obtained: usize },
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingPrefix => write!(f, "byte string does not start with b\""),
+            Self::MissingSuffix => write!(f, "byte string does not end with \""),
+            Self::UnescapedCharacter { offset } => write!(f, "character at offset {} must be escaped", offset),
+            Self::InvalidEscape { offset } => write!(f, "invalid escape sequence at offset {}", offset),
+            Self::WrongLength { expected, obtained } => write!(f, "byte string has {} bytes, expected {}", obtained, expected),
+        }
+    }
+}
+impl std::error::Error for ParseError {
+}
+
+
+/// Returns the escape sequence with a name of its own for the given ASCII character, if any.
+fn named_escape(b: u8) -> Option<&'static str> {
+    match b {
+        0x00 => Some("\\0"),
+        0x09 => Some("\\t"),
+        0x0A => Some("\\n"),
+        0x0D => Some("\\r"),
+        0x5C => Some("\\\\"),
+        _ => None,
+    }
+}
+
+
+/// Writes the escaped representation of a byte string, e.g. `b"MZ\x90\0"`.
+pub(crate) fn write_escaped_bytes(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    write!(f, "b\"")?;
+    for &b in bytes {
+        if let Some(escape) = named_escape(b) {
+            write!(f, "{}", escape)?;
+        } else if b == b'"' {
+            write!(f, "\\\"")?;
+        } else if (0x20..=0x7E).contains(&b) {
+            write!(f, "{}", char::from(b))?;
+        } else {
+            write!(f, "\\x{:02X}", b)?;
+        }
+    }
+    write!(f, "\"")
+}
+
+
+/// Escapes text such as the name of a symbol so that it does not contain any control characters.
+pub fn escape_str(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for c in s.chars() {
+        if let Some(escape) = u8::try_from(c).ok().and_then(named_escape) {
+            ret.push_str(escape);
+        } else if c.is_ascii_control() {
+            ret.push_str(&format!("\\x{:02X}", u32::from(c)));
+        } else if c.is_control() || c == '\u{2028}' || c == '\u{2029}' {
+            ret.push_str(&format!("\\u{{{:X}}}", u32::from(c)));
+        } else {
+            ret.push(c);
+        }
+    }
+    ret
+}
+
+
+/// Parses text escaped using [`escape_str`].
+///
+/// Characters that would have been escaped are also accepted verbatim.
+pub fn unescape_str(s: &str) -> Result<String, ParseError> {
+    let mut ret = String::with_capacity(s.len());
+    let mut chars = s.char_indices();
+    while let Some((offset, c)) = chars.next() {
+        if c != '\\' {
+            ret.push(c);
+            continue;
+        }
+
+        let invalid_escape = ParseError::InvalidEscape { offset };
+        let (_, escaped) = chars.next().ok_or(invalid_escape)?;
+        let unescaped = match escaped {
+            '0' => '\0',
+            't' => '\t',
+            'n' => '\n',
+            'r' => '\r',
+            '"' | '\'' | '\\' => escaped,
+            'x' => {
+                let hex_digits: String = chars.by_ref()
+                    .take(2)
+                    .map(|(_, d)| d)
+                    .collect();
+                if hex_digits.len() != 2 || !hex_digits.bytes().all(|d| d.is_ascii_hexdigit()) {
+                    return Err(invalid_escape);
+                }
+                // only ASCII characters are escaped this way in text
+                let value = u8::from_str_radix(&hex_digits, 16).unwrap();
+                if !value.is_ascii() {
+                    return Err(invalid_escape);
+                }
+                char::from(value)
+            },
+            'u' => {
+                if chars.next().map(|(_, b)| b) != Some('{') {
+                    return Err(invalid_escape);
+                }
+                let mut hex_digits = String::with_capacity(6);
+                loop {
+                    match chars.next() {
+                        Some((_, '}')) => break,
+                        Some((_, d)) if d.is_ascii_hexdigit() && hex_digits.len() < 6 => hex_digits.push(d),
+                        _ => return Err(invalid_escape),
+                    }
+                }
+                u32::from_str_radix(&hex_digits, 16).ok()
+                    .and_then(char::from_u32)
+                    .ok_or(invalid_escape)?
+            },
+            _ => return Err(invalid_escape),
+        };
+        ret.push(unescaped);
+    }
+    Ok(ret)
+}
+
+
+/// Parses a byte string in the escaped representation output by the `Display` implementations in
+/// this crate, e.g. `b"MZ\x90\0"`.
+pub fn unescape(s: &str) -> Result<Vec<u8>, ParseError> {
+    let inner = s.strip_prefix("b\"")
+        .ok_or(ParseError::MissingPrefix)?;
+    let inner = inner.strip_suffix('"')
+        .ok_or(ParseError::MissingSuffix)?;
+    let inner = inner.as_bytes();
+
+    // offsets in errors are relative to the whole string
+    const PREFIX_LENGTH: usize = 2;
+
+    let mut ret = Vec::with_capacity(inner.len());
+    let mut i = 0;
+    while i < inner.len() {
+        let b = inner[i];
+        if b != b'\\' {
+            if b == b'"' || !(0x20..=0x7E).contains(&b) {
+                return Err(ParseError::UnescapedCharacter { offset: PREFIX_LENGTH + i });
+            }
+            ret.push(b);
+            i += 1;
+            continue;
+        }
+
+        let invalid_escape = ParseError::InvalidEscape { offset: PREFIX_LENGTH + i };
+        let escaped = *inner.get(i + 1).ok_or(invalid_escape)?;
+        match escaped {
+            b'0' => ret.push(0x00),
+            b't' => ret.push(0x09),
+            b'n' => ret.push(0x0A),
+            b'r' => ret.push(0x0D),
+            b'"' | b'\'' | b'\\' => ret.push(escaped),
+            b'x' => {
+                let hex_digits = inner.get(i+2..i+4).ok_or(invalid_escape)?;
+                let hex_str = std::str::from_utf8(hex_digits).map_err(|_| invalid_escape)?;
+                if !hex_str.bytes().all(|d| d.is_ascii_hexdigit()) {
+                    return Err(invalid_escape);
+                }
+                ret.push(u8::from_str_radix(hex_str, 16).unwrap());
+                i += 2;
+            },
+            _ => return Err(invalid_escape),
+        }
+        i += 2;
+    }
+    Ok(ret)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_str, unescape_str, ParseError};
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_str_round_trip(s in any::<String>()) {
+            let escaped = escape_str(&s);
+            prop_assert!(!escaped.chars().any(|c| c.is_control()));
+            prop_assert_eq!(unescape_str(&escaped).unwrap(), s);
+        }
+
+        #[test]
+        fn test_unescape_str_never_panics(s in "\\PC*") {
+            let _ = unescape_str(&s);
+        }
+    }
+
+    #[test]
+    fn test_escape_str() {
+        assert_eq!(escape_str("Foo@4"), "Foo@4");
+        assert_eq!(escape_str("a\tb\\c\0"), "a\\tb\\\\c\\0");
+        assert_eq!(escape_str("\x1B[0m\x7F"), "\\x1B[0m\\x7F");
+        assert_eq!(escape_str("\"Grüße\""), "\"Grüße\"");
+        assert_eq!(escape_str("\u{85}\u{2028}"), "\\u{85}\\u{2028}");
+    }
+
+    #[test]
+    fn test_unescape_str_errors() {
+        assert_eq!(unescape_str("a\\"), Err(ParseError::InvalidEscape { offset: 1 }));
+        assert_eq!(unescape_str("ä\\q"), Err(ParseError::InvalidEscape { offset: 2 }));
+        assert_eq!(unescape_str("\\xC4"), Err(ParseError::InvalidEscape { offset: 0 }));
+        assert_eq!(unescape_str("\\u{D800}"), Err(ParseError::InvalidEscape { offset: 0 }));
+        assert_eq!(unescape_str("\\u{1234567}"), Err(ParseError::InvalidEscape { offset: 0 }));
+        assert_eq!(unescape_str("\\u{e4}\\x1b\\'").unwrap(), "ä\x1B'");
+    }
+}
@@ -1,6 +1,12 @@
 use std::array::TryFromSliceError;
 use std::fmt;
 use std::ops::{Index, IndexMut};
+use std::str::FromStr;
+
+pub mod escape;
+
+use crate::escape::write_escaped_bytes;
+pub use crate::escape::{unescape, ParseError};
 
 
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -18,21 +24,16 @@ impl<const SIZE: usize> fmt::Debug for DisplayBytes<SIZE> {
 }
 impl<const SIZE: usize> fmt::Display for DisplayBytes<SIZE> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "b\"")?;
-        for &b in &self.0 {
-            match b {
-                0x00 => write!(f, "\\0")?,
-                0x09 => write!(f, "\\t")?,
-                0x0A => write!(f, "\\n")?,
-                0x0D => write!(f, "\\r")?,
-                0x22 => write!(f, "\\\"")?,
-                // no need to escape 0x27
-                0x5C => write!(f, "\\\\")?,
-                0x20..=0x7E => write!(f, "{}", char::from_u32(b.into()).unwrap())?,
-                other => write!(f, "\\x{:02X}", other)?,
-            }
-        }
-        write!(f, "\"")
+        write_escaped_bytes(f, &self.0)
+    }
+}
+impl<const SIZE: usize> FromStr for DisplayBytes<SIZE> {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = unescape(s)?;
+        let buf: [u8; SIZE] = bytes.as_slice().try_into()
+            .map_err(|_| ParseError::WrongLength { expected: SIZE, obtained: bytes.len() })?;
+        Ok(Self(buf))
     }
 }
 impl<const SIZE: usize> From<[u8; SIZE]> for DisplayBytes<SIZE> {
@@ -106,21 +107,13 @@ impl fmt::Debug for DisplayBytesVec {
 }
 impl fmt::Display for DisplayBytesVec {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "b\"")?;
-        for &b in &self.0 {
-            match b {
-                0x00 => write!(f, "\\0")?,
-                0x09 => write!(f, "\\t")?,
-                0x0A => write!(f, "\\n")?,
-                0x0D => write!(f, "\\r")?,
-                0x22 => write!(f, "\\\"")?,
-                // no need to escape 0x27
-                0x5C => write!(f, "\\\\")?,
-                0x20..=0x7E => write!(f, "{}", char::from_u32(b.into()).unwrap())?,
-                other => write!(f, "\\x{:02X}", other)?,
-            }
-        }
-        write!(f, "\"")
+        write_escaped_bytes(f, &self.0)
+    }
+}
+impl FromStr for DisplayBytesVec {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        unescape(s).map(Self)
     }
 }
 impl From<Vec<u8>> for DisplayBytesVec {
@@ -177,21 +170,7 @@ impl<'a> fmt::Debug for DisplayBytesSlice<'a> {
 }
 impl<'a> fmt::Display for DisplayBytesSlice<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "b\"")?;
-        for &b in self.0 {
-            match b {
-                0x00 => write!(f, "\\0")?,
-                0x09 => write!(f, "\\t")?,
-                0x0A => write!(f, "\\n")?,
-                0x0D => write!(f, "\\r")?,
-                0x22 => write!(f, "\\\"")?,
-                // no need to escape 0x27
-                0x5C => write!(f, "\\\\")?,
-                0x20..=0x7E => write!(f, "{}", char::from_u32(b.into()).unwrap())?,
-                other => write!(f, "\\x{:02X}", other)?,
-            }
-        }
-        write!(f, "\"")
+        write_escaped_bytes(f, self.0)
     }
 }
 impl<'a> From<DisplayBytesSlice<'a>> for &'a [u8] {
@@ -256,3 +235,53 @@ impl<'a> Index<usize> for HexBytesSlice<'a> {
         &self.0[index]
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::{unescape, DisplayBytes, DisplayBytesSlice, DisplayBytesVec, ParseError};
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_vec_round_trip(bytes in proptest::collection::vec(any::<u8>(), 0..64)) {
+            let escaped = DisplayBytesVec::from(bytes.clone()).to_string();
+            let parsed: DisplayBytesVec = escaped.parse().unwrap();
+            prop_assert_eq!(Vec::from(parsed), bytes);
+        }
+
+        #[test]
+        fn test_array_round_trip(bytes in any::<[u8; 8]>()) {
+            let escaped = DisplayBytes::from(bytes).to_string();
+            let parsed: DisplayBytes<8> = escaped.parse().unwrap();
+            prop_assert_eq!(<[u8; 8]>::from(parsed), bytes);
+        }
+
+        #[test]
+        fn test_slice_matches_vec(bytes in proptest::collection::vec(any::<u8>(), 0..64)) {
+            prop_assert_eq!(
+                DisplayBytesSlice::from(bytes.as_slice()).to_string(),
+                DisplayBytesVec::from(bytes.clone()).to_string(),
+            );
+        }
+
+        #[test]
+        fn test_unescape_never_panics(s in "\\PC*") {
+            let _ = unescape(&s);
+        }
+    }
+
+    #[test]
+    fn test_unescape_errors() {
+        assert_eq!(unescape("\"MZ\""), Err(ParseError::MissingPrefix));
+        assert_eq!(unescape("b\"MZ"), Err(ParseError::MissingSuffix));
+        assert_eq!(unescape("b\"M\"Z\""), Err(ParseError::UnescapedCharacter { offset: 3 }));
+        assert_eq!(unescape("b\"MZ\\x9\""), Err(ParseError::InvalidEscape { offset: 4 }));
+        assert_eq!(unescape("b\"\\q\""), Err(ParseError::InvalidEscape { offset: 2 }));
+        assert_eq!(unescape("b\"\\'\\xff\""), Ok(vec![b'\'', 0xFF]));
+        assert_eq!(
+            "b\"MZ\"".parse::<DisplayBytes<4>>(),
+            Err(ParseError::WrongLength { expected: 4, obtained: 2 }),
+        );
+    }
+}
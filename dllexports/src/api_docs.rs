@@ -0,0 +1,123 @@
+//! User-provided links from exported symbols to their documentation.
+//!
+//! The mapping is read from a file, keyed by `dll!name` (e.g. `kernel32.dll!CreateFileW`). The
+//! value is arbitrary text, usually the URL of the documentation or the name of the API set
+//! containing the function. Module names are matched case-insensitively and with or without their
+//! extension; symbol names are matched exactly.
+//!
+//! Files ending in `.json` contain an object mapping keys to values; all other files contain one
+//! `key,value` pair per line, with empty lines and lines starting with `#` being ignored.
+
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use tracing::error;
+
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct ApiDocs {
+    /// Maps the normalized module name and the symbol name to the documentation.
+    key_to_doc: BTreeMap<(String, String), String>,
+}
+impl ApiDocs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the mapping from a JSON or CSV file.
+    pub fn load(path: &Path) -> Result<Self, io::Error> {
+        let text = std::fs::read_to_string(path)?;
+        let is_json = path.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+        let pairs: Vec<(String, String)> = if is_json {
+            let map: BTreeMap<String, String> = serde_json::from_str(&text)?;
+            map.into_iter().collect()
+        } else {
+            let mut pairs = Vec::new();
+            for (index, line) in text.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let Some((key, doc)) = line.split_once(',') else {
+                    error!("line {} of {} is not a key,value pair", index + 1, path.display());
+                    return Err(io::ErrorKind::InvalidData.into());
+                };
+                pairs.push((key.trim().to_owned(), doc.trim().to_owned()));
+            }
+            pairs
+        };
+
+        let mut docs = Self::new();
+        for (key, doc) in pairs {
+            if !docs.insert(&key, doc) {
+                error!("key {:?} in {} is not of the form dll!name", key, path.display());
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+        }
+        Ok(docs)
+    }
+
+    /// Adds the documentation for the symbol given as `dll!name`. Returns `false` if the key is
+    /// not of that form.
+    pub fn insert(&mut self, key: &str, doc: String) -> bool {
+        let Some((module, name)) = key.split_once('!') else { return false };
+        if module.is_empty() || name.is_empty() {
+            return false;
+        }
+        self.key_to_doc.insert((normalize_module(module), name.to_owned()), doc);
+        true
+    }
+
+    /// Returns the documentation for the symbol with the given name exported by the module with
+    /// the given file name.
+    pub fn lookup(&self, module_file_name: &str, name: &str) -> Option<&str> {
+        self.key_to_doc
+            .get(&(normalize_module(module_file_name), name.to_owned()))
+            .map(|d| d.as_str())
+    }
+
+    /// Returns the number of symbols with documentation.
+    pub fn len(&self) -> usize {
+        self.key_to_doc.len()
+    }
+
+    /// Returns whether there is no documentation for any symbol.
+    pub fn is_empty(&self) -> bool {
+        self.key_to_doc.is_empty()
+    }
+}
+
+/// Lowercases the module name and strips its extension.
+fn normalize_module(module: &str) -> String {
+    let stem = match module.rsplit_once('.') {
+        Some((stem, _extension)) if !stem.is_empty() => stem,
+        _ => module,
+    };
+    stem.to_lowercase()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::ApiDocs;
+
+    #[test]
+    fn test_lookup() {
+        let mut docs = ApiDocs::new();
+        assert!(docs.is_empty());
+        assert!(docs.insert("KERNEL32.dll!CreateFileW", "https://example.com/createfilew".to_owned()));
+        assert!(docs.insert("ntdll!NtClose", "api-ms-win-core-handle-l1-1-0".to_owned()));
+        assert!(!docs.insert("CreateFileA", String::new()));
+        assert!(!docs.is_empty());
+        assert_eq!(docs.len(), 2);
+
+        assert_eq!(docs.lookup("kernel32.dll", "CreateFileW"), Some("https://example.com/createfilew"));
+        assert_eq!(docs.lookup("KERNEL32", "CreateFileW"), Some("https://example.com/createfilew"));
+        assert_eq!(docs.lookup("kernel32.dll", "createfilew"), None);
+        assert_eq!(docs.lookup("NTDLL.DLL", "NtClose"), Some("api-ms-win-core-handle-l1-1-0"));
+    }
+}
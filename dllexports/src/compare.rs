@@ -0,0 +1,335 @@
+//! Comparison of the files in two containers.
+
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use binstruct::deadline;
+use tracing::{debug, warn};
+
+use crate::data_mgmt::{Error, IdentifiedFile};
+use crate::extract::single_file_name;
+use crate::formats::interpret_container_volume;
+use crate::hashing::{sha256, Sha256Digest};
+use crate::volumes::{is_cabinet_continuation, ContainerSiblings, NoSiblings, Siblings};
+
+
+/// How a file differs between two containers.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) enum Change {
+    /// The file is only in the second container.
+    Added,
+
+    /// The file is only in the first container.
+    Removed,
+
+    /// The file is in both containers but its contents differ.
+    Changed,
+
+    /// The file is in both containers but could not be read from at least one of them, so whether
+    /// it differs is unknown.
+    Unreadable,
+}
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added => write!(f, "added"),
+            Self::Removed => write!(f, "removed"),
+            Self::Changed => write!(f, "changed"),
+            Self::Unreadable => write!(f, "unreadable"),
+        }
+    }
+}
+
+
+/// A file that differs between two containers.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) struct Difference {
+    /// The path of the file, starting with its path within the outermost container and followed
+    /// by its paths within any nested containers.
+    pub path: Vec<PathBuf>,
+
+    pub change: Change,
+}
+
+
+/// The state of [`hash_files`] while it descends into nested containers.
+struct HashState {
+    parent_path: Vec<PathBuf>,
+
+    /// The digests of the containers being descended into, to avoid descending into a container
+    /// that (through some compression trickery) contains itself.
+    ancestor_digests: Vec<Sha256Digest>,
+
+    time_limit: Option<Duration>,
+    path_to_digest: BTreeMap<Vec<PathBuf>, Option<Sha256Digest>>,
+}
+
+
+/// Obtains the SHA-256 digest of each file in the container, descending into nested containers up
+/// to `depth` levels deep.
+///
+/// The files are keyed by their path (see [`Difference::path`]). Nested containers are hashed
+/// themselves as well as descended into. The single file in a single-file container is named
+/// `single_file_name` (see [`single_file_name`]).
+///
+/// A file that cannot be read is recorded without a digest and does not stop the process; neither
+/// does a nested container whose files cannot be listed. Hashing each file of the container (along
+/// with the files within it) stops once `time_limit` has elapsed, in which case the file is
+/// recorded without a digest too, since the files within it may be incomplete.
+pub(crate) fn hash_files(
+    container: &IdentifiedFile,
+    single_file_name: &Path,
+    depth: usize,
+    time_limit: Option<Duration>,
+) -> Result<BTreeMap<Vec<PathBuf>, Option<Sha256Digest>>, Error> {
+    let mut state = HashState {
+        parent_path: Vec::new(),
+        ancestor_digests: Vec::new(),
+        time_limit,
+        path_to_digest: BTreeMap::new(),
+    };
+    hash_files_into(container, single_file_name, depth, &mut state)?;
+    Ok(state.path_to_digest)
+}
+
+fn hash_files_into(container: &IdentifiedFile, single_file_name: &Path, depth: usize, state: &mut HashState) -> Result<(), Error> {
+    match container {
+        IdentifiedFile::SingleFileContainer(sfc) => {
+            match sfc.read_file() {
+                Ok(data) => hash_child(single_file_name.to_owned(), &data, depth, &NoSiblings, state),
+                Err(e) => record_unreadable(single_file_name.to_owned(), &e, state),
+            }
+            Ok(())
+        },
+        IdentifiedFile::MultiFileContainer(mfc) => {
+            let files = mfc.list_files()?;
+            for file in &files {
+                if let Some(time_limit) = state.time_limit
+                    && deadline::exceeded()
+                {
+                    // not even read, so that the files left do not all run into the limit
+                    record_unreadable(file.clone(), &Error::TimeLimitExceeded(time_limit), state);
+                    continue;
+                }
+                match mfc.read_file(file) {
+                    Ok(data) => {
+                        let siblings = ContainerSiblings::new(mfc.as_ref(), &files, file);
+                        hash_child(file.clone(), &data, depth, &siblings, state);
+                    },
+                    Err(e) => record_unreadable(file.clone(), &e, state),
+                }
+            }
+            Ok(())
+        },
+        _ => Err(Error::NotAContainer),
+    }
+}
+
+fn record_unreadable(path: PathBuf, error: &Error, state: &mut HashState) {
+    state.parent_path.push(path);
+    warn!("failed to read {:?}: {}", state.parent_path, error);
+    state.path_to_digest.insert(state.parent_path.clone(), None);
+    state.parent_path.pop();
+}
+
+fn hash_child(path: PathBuf, data: &[u8], depth: usize, siblings: &dyn Siblings, state: &mut HashState) {
+    if state.parent_path.is_empty() {
+        // each file of the outermost container gets its own time limit
+        let _deadline = deadline::set_time_limit(state.time_limit);
+        hash_child_within_limit(path, data, depth, siblings, state);
+    } else {
+        hash_child_within_limit(path, data, depth, siblings, state);
+    }
+}
+
+fn hash_child_within_limit(path: PathBuf, data: &[u8], depth: usize, siblings: &dyn Siblings, state: &mut HashState) {
+    let name = path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    state.parent_path.push(path);
+    let digest = sha256(data);
+
+    if depth > 0 && state.ancestor_digests.contains(&digest) {
+        debug!("not descending into {:?} again, as it contains itself", state.parent_path);
+    } else if depth > 0 && is_cabinet_continuation(data, siblings) {
+        debug!("not descending into {:?}, as its files are hashed with the first cabinet of its set", state.parent_path);
+    } else if depth > 0 && !deadline::exceeded() {
+        let interpreted = match interpret_container_volume(data, &name, siblings) {
+            Ok(i) => i,
+            Err(e) => {
+                // the detection of some formats is rather optimistic
+                debug!("failed to interpret {:?}: {}", state.parent_path, e);
+                IdentifiedFile::Unidentified
+            },
+        };
+        let is_container = matches!(
+            interpreted,
+            IdentifiedFile::SingleFileContainer(_) | IdentifiedFile::MultiFileContainer(_)
+        );
+        if is_container {
+            let child_single_file_name = PathBuf::from(single_file_name(&name, data));
+            state.ancestor_digests.push(digest);
+            if let Err(e) = hash_files_into(&interpreted, &child_single_file_name, depth - 1, state) {
+                warn!("failed to hash the files in {:?}: {}", state.parent_path, e);
+            }
+            state.ancestor_digests.pop();
+        }
+    }
+
+    if deadline::exceeded() {
+        warn!("ran out of time while hashing {:?}", state.parent_path);
+        state.path_to_digest.insert(state.parent_path.clone(), None);
+    } else {
+        state.path_to_digest.insert(state.parent_path.clone(), Some(digest));
+    }
+    state.parent_path.pop();
+}
+
+
+/// Compares the digests of the files in two containers, as obtained using [`hash_files`].
+///
+/// The differences are returned sorted by path.
+pub(crate) fn compare(
+    old: &BTreeMap<Vec<PathBuf>, Option<Sha256Digest>>,
+    new: &BTreeMap<Vec<PathBuf>, Option<Sha256Digest>>,
+) -> Vec<Difference> {
+    let all_paths: BTreeSet<&Vec<PathBuf>> = old.keys()
+        .chain(new.keys())
+        .collect();
+    let mut differences = Vec::new();
+    for path in all_paths {
+        let change = match (old.get(path), new.get(path)) {
+            (Some(Some(old_digest)), Some(Some(new_digest))) => {
+                if old_digest == new_digest {
+                    continue;
+                }
+                Change::Changed
+            },
+            (Some(_), Some(_)) => Change::Unreadable,
+            (Some(_), None) => Change::Removed,
+            (None, Some(_)) => Change::Added,
+            (None, None) => unreachable!(),
+        };
+        differences.push(Difference {
+            path: path.clone(),
+            change,
+        });
+    }
+    differences
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+    use testgen::{cab, iso9660, sfx};
+    use super::{compare, hash_files, Change, Difference};
+    use crate::formats::{interpret_container, interpret_file};
+    use crate::hashing::Sha256Digest;
+
+    fn hash_volume(volume: &iso9660::Volume, depth: usize) -> BTreeMap<Vec<PathBuf>, Option<Sha256Digest>> {
+        let container = interpret_container(volume.to_bytes()).unwrap();
+        hash_files(&container, Path::new("unused"), depth, None).unwrap()
+    }
+
+    #[test]
+    fn test_compare_nested() {
+        let volume = |inner_data: &[u8], extra: Option<&str>| {
+            let mut inner = iso9660::Volume::new("INNER");
+            inner.files.push(iso9660::File { name: "DATA.BIN".to_owned(), data: inner_data.to_vec() });
+            let mut outer = iso9660::Volume::new("OUTER");
+            outer.files.push(iso9660::File { name: "INNER.ISO".to_owned(), data: inner.to_bytes() });
+            outer.files.push(iso9660::File { name: "SAME.TXT".to_owned(), data: b"same".to_vec() });
+            if let Some(extra) = extra {
+                outer.files.push(iso9660::File { name: extra.to_owned(), data: b"extra".to_vec() });
+            }
+            outer
+        };
+        let old_volume = volume(b"old", Some("OLD.TXT"));
+        let new_volume = volume(b"new", Some("NEW.TXT"));
+
+        // without descending, only the inner image differs
+        let old = hash_volume(&old_volume, 0);
+        assert_eq!(old.len(), 3);
+        let new = hash_volume(&new_volume, 0);
+        let changes: Vec<Change> = compare(&old, &new).into_iter()
+            .map(|d| d.change)
+            .collect();
+        assert_eq!(changes, [Change::Changed, Change::Added, Change::Removed]);
+
+        // with descending, the file within it does too
+        let old = hash_volume(&old_volume, 1);
+        let new = hash_volume(&new_volume, 1);
+        // ISO 9660 file names end in a version number
+        let path = |parts: &[&str]| parts.iter().map(PathBuf::from).collect::<Vec<_>>();
+        assert_eq!(
+            compare(&old, &new),
+            vec![
+                Difference { path: path(&["INNER.ISO;1"]), change: Change::Changed },
+                Difference { path: path(&["INNER.ISO;1", "DATA.BIN;1"]), change: Change::Changed },
+                Difference { path: path(&["NEW.TXT;1"]), change: Change::Added },
+                Difference { path: path(&["OLD.TXT;1"]), change: Change::Removed },
+            ],
+        );
+        assert!(compare(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_spanned_cabinet_set() {
+        let files = [cab::File { name: "README.TXT".to_owned(), data: (0..=255u8).cycle().take(700).collect() }];
+        let cabinet_name = |i: usize| format!("SYNTH{}.CAB", i + 1);
+        let volumes = cab::spanned_set(&files, 256, 1, cabinet_name);
+        let names: Vec<String> = (0..volumes.len()).map(cabinet_name).collect();
+        let archive_files: Vec<(&str, &[u8])> = names.iter()
+            .map(|n| n.as_str())
+            .zip(volumes.iter().map(|v| v.as_slice()))
+            .collect();
+        let container = interpret_file(sfx::self_extracting(&sfx::zip(&archive_files))).unwrap();
+
+        // the files of the set are only hashed within its first volume
+        let hashed = hash_files(&container, Path::new("unused"), 1, None).unwrap();
+        let paths: Vec<Vec<PathBuf>> = hashed.into_keys().collect();
+        let mut expected: Vec<Vec<PathBuf>> = names.iter()
+            .map(|n| vec![PathBuf::from(n)])
+            .collect();
+        expected.insert(1, vec![PathBuf::from("SYNTH1.CAB"), PathBuf::from("README.TXT")]);
+        assert_eq!(paths, expected);
+    }
+
+    #[test]
+    fn test_unreadable_and_timed_out_files() {
+        let readme: &[u8] = b"Read me!";
+        let archive = |damaged: bool| {
+            let mut archive = sfx::zip(&[("README.TXT", readme), ("OTHER.TXT", b"other")]);
+            if damaged {
+                // the data of the second file, which is the first one in the archive
+                archive[30 + 10] ^= 0xFF;
+            }
+            sfx::self_extracting(&archive)
+        };
+        let hash = |damaged: bool, time_limit: Option<Duration>| {
+            let container = interpret_file(archive(damaged)).unwrap();
+            hash_files(&container, Path::new("unused"), 1, time_limit).unwrap()
+        };
+
+        // one bad file does not keep the others from being compared
+        let intact = hash(false, None);
+        let damaged = hash(true, None);
+        assert_eq!(damaged.get(&vec![PathBuf::from("README.TXT")]), Some(&None));
+        assert!(damaged.get(&vec![PathBuf::from("OTHER.TXT")]).unwrap().is_some());
+        assert_eq!(
+            compare(&intact, &damaged),
+            vec![Difference { path: vec![PathBuf::from("README.TXT")], change: Change::Unreadable }],
+        );
+
+        // files hashed after the time limit are recorded without digests
+        let timed_out = hash(false, Some(Duration::ZERO));
+        assert_eq!(timed_out.len(), 2);
+        assert!(timed_out.values().all(|digest| digest.is_none()));
+    }
+}
@@ -0,0 +1,456 @@
+//! Comparison of the exports of two versions of a PE file.
+//!
+//! Besides exports that have been added, removed or moved, the first bytes of each exported
+//! function can be compared. This finds functions that have been patched at runtime, e.g. when
+//! comparing a DLL against a dump of the same DLL taken from the memory of a process in which it
+//! has been hot-patched or detoured.
+
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+
+use binms::pe::{
+    self, ExportAddressTableEntry, ExportData, KnownDataDirectoryEntry, MachineType,
+    OptionalHeader, SectionClass, SectionTable, SectionTableEntry, VirtualReader,
+};
+
+
+/// The number of bytes before a hot-patchable function that are reserved for a near jump.
+const HOT_PATCH_PADDING_LENGTH: u32 = 5;
+
+/// The number of bytes at the start of a function examined for jumps inserted by patching.
+const PATCH_WINDOW_LENGTH: u64 = 12;
+
+
+/// The address a patched function jumps to.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) enum Target {
+    /// An address within the image, relative to its base.
+    Rva(u32),
+
+    /// An address outside of the image, e.g. within a trampoline allocated by the patcher.
+    Va(u64),
+}
+impl Target {
+    fn from_va(va: u64, image_base: u64, image_size: u32) -> Self {
+        match va.checked_sub(image_base).and_then(|rva| u32::try_from(rva).ok()) {
+            Some(rva) if rva < image_size => Self::Rva(rva),
+            _ => Self::Va(va),
+        }
+    }
+}
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rva(rva) => write!(f, "RVA {:#010X}", rva),
+            Self::Va(va) => write!(f, "VA {:#X}", va),
+        }
+    }
+}
+
+
+/// A jump found at the start of an exported function, as inserted by hot-patching or detouring.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) enum Patch {
+    /// The two-byte `mov edi, edi` at the start of the function has been replaced by a short jump
+    /// into the padding before the function, which in turn contains a near jump to the target (if
+    /// it could be read).
+    HotPatched { target: Option<Target> },
+
+    /// The function starts with a near jump.
+    Jump { target: Target },
+
+    /// The function starts with a jump through a pointer stored at the given address.
+    IndirectJump { pointer: Target },
+
+    /// The function starts with a jump to an absolute address, e.g. `push` followed by `ret` or
+    /// `mov rax` followed by `jmp rax`.
+    AbsoluteJump { target: Target },
+}
+impl Patch {
+    /// Detects a patch at the start of the function at `rva`.
+    ///
+    /// `padding` contains the bytes directly before the function, if they could be read; `window`
+    /// contains the first bytes of the function. `image_base` and `image_size` are used to convert
+    /// absolute addresses within the image into relative ones.
+    pub fn detect(machine_type: MachineType, image_base: u64, image_size: u32, rva: u32, padding: Option<&[u8]>, window: &[u8]) -> Option<Self> {
+        if !matches!(machine_type, MachineType::I386 | MachineType::Amd64) {
+            return None;
+        }
+
+        let i32_at = |bytes: &[u8], offset: usize| bytes.get(offset..offset+4)
+            .map(|b| i32::from_le_bytes(b.try_into().unwrap()));
+        match window {
+            [0xEB, 0xF9, ..] => {
+                // the near jump in the padding is relative to the start of the function
+                let target = padding
+                    .filter(|p| p.first() == Some(&0xE9))
+                    .and_then(|p| i32_at(p, 1))
+                    .map(|rel| Target::Rva(rva.wrapping_add_signed(rel)));
+                Some(Self::HotPatched { target })
+            },
+            [0xE9, ..] => {
+                let rel = i32_at(window, 1)?;
+                Some(Self::Jump { target: Target::Rva(rva.wrapping_add(5).wrapping_add_signed(rel)) })
+            },
+            [0xFF, 0x25, ..] => {
+                let displacement = i32_at(window, 2)?;
+                let pointer = if machine_type == MachineType::Amd64 {
+                    // relative to the next instruction
+                    Target::Rva(rva.wrapping_add(6).wrapping_add_signed(displacement))
+                } else {
+                    // absolute
+                    Target::from_va((displacement as u32).into(), image_base, image_size)
+                };
+                Some(Self::IndirectJump { pointer })
+            },
+            [0x68, a, b, c, d, 0xC3, ..] if machine_type == MachineType::I386 => {
+                let va = u32::from_le_bytes([*a, *b, *c, *d]);
+                Some(Self::AbsoluteJump { target: Target::from_va(va.into(), image_base, image_size) })
+            },
+            [0x48, 0xB8, rest @ ..] if machine_type == MachineType::Amd64 && rest.get(8..10) == Some(&[0xFF, 0xE0]) => {
+                let va = u64::from_le_bytes(rest[0..8].try_into().unwrap());
+                Some(Self::AbsoluteJump { target: Target::from_va(va, image_base, image_size) })
+            },
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HotPatched { target: Some(target) } => write!(f, "hot-patched, jumps to {}", target),
+            Self::HotPatched { target: None } => write!(f, "hot-patched"),
+            Self::Jump { target } => write!(f, "jumps to {}", target),
+            Self::IndirectJump { pointer } => write!(f, "jumps via pointer at {}", pointer),
+            Self::AbsoluteJump { target } => write!(f, "jumps to absolute {}", target),
+        }
+    }
+}
+
+
+/// An exported symbol as found in one version of a PE file.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) struct Export {
+    pub ordinal: u32,
+    pub target: ExportAddressTableEntry,
+
+    /// The first bytes of the exported function; empty for forwarders.
+    pub entry_bytes: Vec<u8>,
+
+    /// The patch detected at the start of the exported function; always `None` for exported
+    /// variables.
+    pub patch: Option<Patch>,
+}
+
+
+/// Reads the exports of a PE file, keyed by their names; exports without a name are keyed by
+/// `#` followed by their ordinal.
+///
+/// If `mapped` is set, the file is a memory dump of the image, i.e. each section is found at its
+/// relative virtual address instead of at the offset of its raw data. If `entry_byte_count` is not
+/// zero, up to this many bytes at the start of each exported function are read and examined for
+/// patches. Exports pointing into a section that does not contain code are variables, whose bytes
+/// are compared but not examined for patches.
+pub(crate) fn read_exports(data: &[u8], mapped: bool, entry_byte_count: usize) -> Result<BTreeMap<String, Export>, io::Error> {
+    let mut cursor = Cursor::new(data);
+    let executable = pe::Executable::read(&mut cursor)?;
+    let section_table = if mapped {
+        mapped_section_table(&executable.section_table)
+    } else {
+        executable.section_table.clone()
+    };
+
+    let windows_header = match &executable.optional_header {
+        Some(OptionalHeader::Coff(coff)) => coff.optional_windows_header.as_ref(),
+        _ => None,
+    };
+    let Some(windows_header) = windows_header else {
+        return Ok(BTreeMap::new())
+    };
+    let export_directory_entry = windows_header.known_data_directory_entry(KnownDataDirectoryEntry::ExportTable)
+        .filter(|e| e.address != 0);
+    let Some(export_directory_entry) = export_directory_entry else {
+        return Ok(BTreeMap::new())
+    };
+    let export_data = ExportData::read(&mut cursor, &export_directory_entry, &section_table)?;
+
+    let mut ordinal_to_names: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+    for (name, ordinal) in &export_data.name_to_ordinal {
+        ordinal_to_names.entry(*ordinal)
+            .or_default()
+            .push(name.clone());
+    }
+
+    let mut reader = VirtualReader::new(&mut cursor, &section_table);
+    let mut exports = BTreeMap::new();
+    for (ordinal, target) in &export_data.ordinal_to_address {
+        let (entry_bytes, patch) = match target {
+            ExportAddressTableEntry::Skip => continue,
+            ExportAddressTableEntry::Code { code_rva } if entry_byte_count > 0 => {
+                let entry_bytes = read_at(&mut reader, *code_rva, u64::try_from(entry_byte_count).unwrap())?;
+                let window = read_at(&mut reader, *code_rva, PATCH_WINDOW_LENGTH)?;
+                let padding = code_rva.checked_sub(HOT_PATCH_PADDING_LENGTH)
+                    .map(|padding_rva| read_at(&mut reader, padding_rva, HOT_PATCH_PADDING_LENGTH.into()))
+                    .transpose()?;
+                let patch = match section_table.class_for_virtual(*code_rva) {
+                    Some(SectionClass::Code) | None => Patch::detect(
+                        executable.machine_type,
+                        windows_header.image_base,
+                        windows_header.image_size,
+                        *code_rva,
+                        padding.as_deref(),
+                        &window,
+                    ),
+                    Some(_) => None,
+                };
+                (entry_bytes, patch)
+            },
+            _ => (Vec::new(), None),
+        };
+        let export = Export {
+            ordinal: *ordinal,
+            target: target.clone(),
+            entry_bytes,
+            patch,
+        };
+
+        match ordinal_to_names.get(ordinal) {
+            Some(names) => {
+                for name in names {
+                    exports.insert(name.clone(), export.clone());
+                }
+            },
+            None => {
+                exports.insert(format!("#{}", ordinal), export);
+            },
+        }
+    }
+    Ok(exports)
+}
+
+/// Returns the section table of a memory dump of the image described by `section_table`.
+fn mapped_section_table(section_table: &SectionTable) -> SectionTable {
+    let entries: Vec<SectionTableEntry> = section_table.as_entries()
+        .iter()
+        .map(|entry| SectionTableEntry {
+            raw_data_pointer: entry.virtual_address,
+            raw_data_size: entry.virtual_size,
+            ..*entry
+        })
+        .collect();
+    SectionTable::from(entries)
+}
+
+/// Reads up to `count` bytes at the given relative virtual address.
+fn read_at<R: Read + Seek>(reader: &mut VirtualReader<'_, R>, rva: u32, count: u64) -> Result<Vec<u8>, io::Error> {
+    reader.seek(SeekFrom::Start(rva.into()))?;
+    let mut bytes = Vec::new();
+    reader.take(count).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+
+/// How an export differs between two versions of a PE file.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) enum Change {
+    /// The export is only in the second version.
+    Added,
+
+    /// The export is only in the first version.
+    Removed,
+
+    /// The exported function is at a different address.
+    Moved,
+
+    /// The export has changed between a function and a forwarder or is forwarded elsewhere.
+    Retargeted,
+
+    /// The exported function is at the same address, but its first bytes differ.
+    EntryChanged,
+}
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added => write!(f, "added"),
+            Self::Removed => write!(f, "removed"),
+            Self::Moved => write!(f, "moved"),
+            Self::Retargeted => write!(f, "retargeted"),
+            Self::EntryChanged => write!(f, "entry-changed"),
+        }
+    }
+}
+
+
+/// An export that differs between two versions of a PE file.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) struct ExportDifference<'a> {
+    pub name: &'a str,
+    pub change: Change,
+    pub old: Option<&'a Export>,
+    pub new: Option<&'a Export>,
+}
+impl fmt::Display for ExportDifference<'_> {
+    /// Outputs the difference as tab-separated change, name, old target, new target and remark.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\t{}\t{}\t{}\t", self.change, self.name, DisplayTarget(self.old), DisplayTarget(self.new))?;
+
+        let old_patch = self.old.and_then(|e| e.patch);
+        let new_patch = self.new.and_then(|e| e.patch);
+        match (old_patch, new_patch) {
+            (Some(old_patch), Some(new_patch)) => write!(f, "old {}; new {}", old_patch, new_patch),
+            (Some(old_patch), None) => write!(f, "old {}", old_patch),
+            (None, Some(new_patch)) => write!(f, "new {}", new_patch),
+            (None, None) => {
+                if let (Change::EntryChanged, Some(old), Some(new)) = (self.change, self.old, self.new) {
+                    write!(f, "{} -> {}", HexString(&old.entry_bytes), HexString(&new.entry_bytes))?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+struct DisplayTarget<'a>(Option<&'a Export>);
+impl fmt::Display for DisplayTarget<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.map(|e| &e.target) {
+            Some(ExportAddressTableEntry::Code { code_rva }) => write!(f, "{:#010X}", code_rva),
+            Some(ExportAddressTableEntry::Forwarder { target }) => write!(f, "-> {}", target),
+            Some(ExportAddressTableEntry::Skip) | None => write!(f, "-"),
+        }
+    }
+}
+
+struct HexString<'a>(&'a [u8]);
+impl fmt::Display for HexString<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for b in self.0 {
+            write!(f, "{:02X}", b)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Compares the exports of two versions of a PE file, as obtained using [`read_exports`].
+///
+/// The differences are returned sorted by name.
+pub(crate) fn compare_exports<'a>(old: &'a BTreeMap<String, Export>, new: &'a BTreeMap<String, Export>) -> Vec<ExportDifference<'a>> {
+    let all_names: BTreeSet<&String> = old.keys()
+        .chain(new.keys())
+        .collect();
+    let mut differences = Vec::new();
+    for name in all_names {
+        let old_export = old.get(name);
+        let new_export = new.get(name);
+        let change = match (old_export, new_export) {
+            (Some(o), Some(n)) => {
+                match (&o.target, &n.target) {
+                    (ExportAddressTableEntry::Code { code_rva: old_rva }, ExportAddressTableEntry::Code { code_rva: new_rva }) => {
+                        if old_rva != new_rva {
+                            Change::Moved
+                        } else if o.entry_bytes != n.entry_bytes || o.patch != n.patch {
+                            Change::EntryChanged
+                        } else {
+                            continue;
+                        }
+                    },
+                    (old_target, new_target) => {
+                        if old_target == new_target {
+                            continue;
+                        }
+                        Change::Retargeted
+                    },
+                }
+            },
+            (Some(_), None) => Change::Removed,
+            (None, Some(_)) => Change::Added,
+            (None, None) => unreachable!(),
+        };
+        differences.push(ExportDifference {
+            name,
+            change,
+            old: old_export,
+            new: new_export,
+        });
+    }
+    differences
+}
+
+
+#[cfg(test)]
+mod tests {
+    use binms::pe::MachineType;
+    use testgen::pe;
+    use super::{compare_exports, read_exports, Change, Patch, Target};
+
+    #[test]
+    fn test_detect_patch() {
+        let detect = |machine_type, padding: Option<&[u8]>, window: &[u8]| Patch::detect(machine_type, 0x1000_0000, 0x8000, 0x2000, padding, window);
+        assert_eq!(detect(MachineType::I386, Some(&[0xCC; 5]), &[0x8B, 0xFF, 0x55, 0x8B, 0xEC]), None);
+        assert_eq!(
+            detect(MachineType::I386, Some(&[0xE9, 0x00, 0x01, 0x00, 0x00]), &[0xEB, 0xF9, 0x55]),
+            Some(Patch::HotPatched { target: Some(Target::Rva(0x2100)) }),
+        );
+        assert_eq!(
+            detect(MachineType::I386, None, &[0xE9, 0xFB, 0xEF, 0xFF, 0xFF]),
+            Some(Patch::Jump { target: Target::Rva(0x1000) }),
+        );
+        assert_eq!(
+            detect(MachineType::I386, None, &[0xFF, 0x25, 0x00, 0x30, 0x00, 0x10]),
+            Some(Patch::IndirectJump { pointer: Target::Rva(0x3000) }),
+        );
+        assert_eq!(
+            detect(MachineType::Amd64, None, &[0xFF, 0x25, 0x00, 0x10, 0x00, 0x00]),
+            Some(Patch::IndirectJump { pointer: Target::Rva(0x3006) }),
+        );
+        assert_eq!(
+            detect(MachineType::Amd64, None, &[0x48, 0xB8, 0x00, 0x00, 0x00, 0x7F, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xE0]),
+            Some(Patch::AbsoluteJump { target: Target::Va(0x7F00_0000) }),
+        );
+        assert_eq!(detect(MachineType::Arm64, None, &[0xE9, 0x00, 0x00, 0x00, 0x00]), None);
+    }
+
+    #[test]
+    fn test_compare_hot_patched_exports() {
+        let mut library = pe::PortableExecutable::new("SYNTH.DLL");
+        for (ordinal, name, offset) in [(1, "Patched", 0x10), (2, "Same", 0x20), (3, "Moved", 0x30)] {
+            library.exports.push(pe::Export {
+                ordinal,
+                name: Some(name.to_owned()),
+                target: pe::ExportTarget::Code { rva: pe::TEXT_RVA + offset },
+            });
+        }
+        let old_bytes = library.to_bytes();
+        library.exports[2].target = pe::ExportTarget::Code { rva: pe::TEXT_RVA + 0x40 };
+        let mut new_bytes = library.to_bytes();
+
+        // hot-patch the first function to jump to the second one
+        let header_offset = new_bytes.windows(8).position(|w| w == b".text\0\0\0").unwrap();
+        let raw_pointer = u32::from_le_bytes(new_bytes[header_offset+20..header_offset+24].try_into().unwrap());
+        let function_offset = usize::try_from(raw_pointer).unwrap() + 0x10;
+        new_bytes[function_offset-5..function_offset+2].copy_from_slice(&[0xE9, 0x10, 0x00, 0x00, 0x00, 0xEB, 0xF9]);
+
+        let old = read_exports(&old_bytes, false, 4).unwrap();
+        let new = read_exports(&new_bytes, false, 4).unwrap();
+        let differences = compare_exports(&old, &new);
+        assert_eq!(differences.len(), 2);
+        assert_eq!((differences[0].name, differences[0].change), ("Moved", Change::Moved));
+        assert_eq!((differences[1].name, differences[1].change), ("Patched", Change::EntryChanged));
+        assert_eq!(
+            differences[1].new.unwrap().patch,
+            Some(Patch::HotPatched { target: Some(Target::Rva(pe::TEXT_RVA + 0x20)) }),
+        );
+        assert_eq!(
+            differences[1].to_string(),
+            "entry-changed\tPatched\t0x00001010\t0x00001010\tnew hot-patched, jumps to RVA 0x00001020",
+        );
+
+        // without entry bytes, only the move is noticed
+        let old = read_exports(&old_bytes, false, 0).unwrap();
+        let new = read_exports(&new_bytes, false, 0).unwrap();
+        assert_eq!(compare_exports(&old, &new).len(), 1);
+    }
+}
@@ -1,7 +1,23 @@
+//! The interfaces between file formats and the code that scans and extracts them.
+//!
+//! A format is supported by implementing one of [`MultiFileContainer`], [`SingleFileContainer`]
+//! and [`SymbolExporter`] and returning it as an [`IdentifiedFile`], either from the built-in
+//! detectors in [`formats`](crate::formats) or from a [`FormatPlugin`](crate::plugin::FormatPlugin)
+//! for formats that are not built in. Everything else (recursion into nested containers, caching,
+//! spilling large files to disk, time limits, statistics) is taken care of by the consumers of
+//! these traits.
+
+
+use std::borrow::Cow;
 use std::fmt;
+use std::io::{self, Cursor, Read};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use binms::pe::SectionClass;
+use binstruct::deadline::TimeLimitExceeded;
 use expandms::DecompressionError;
+use serde::{Deserialize, Serialize};
 
 
 /// A sequence of paths, possibly through multiple file systems.
@@ -43,40 +59,316 @@ impl AsRef<[PathBuf]> for PathSequence {
 ///
 /// This can be a compression-enabled archive format like PKZIP, an uncompressed archive format like
 /// TAR, or a file system image like FAT or ISO9660.
+///
+/// Files that are stored verbatim and contiguously within the container's data are returned as
+/// borrowed slices of that data; all others (e.g. decompressed or reassembled files) are returned
+/// owned.
+///
+/// Constructing the container should only parse the container's directory structure; the contents
+/// of the files should only be decoded once they are requested using
+/// [`read_file`](Self::read_file). Consumers may request only some of the files, request them in
+/// any order and hold on to the data of multiple files at once.
 pub trait MultiFileContainer : fmt::Debug {
+    /// A short name of the container format, e.g. `CAB` or `FAT`.
+    ///
+    /// Scan summaries count containers by this name, so all containers of one format should
+    /// return the same name.
+    fn format_name(&self) -> &str;
+
+    /// A remark on the container as a whole that consumers should be aware of, e.g. which system
+    /// formatted a disk image.
+    fn remark(&self) -> Option<String> {
+        None
+    }
+
+    /// Lists the files in the container.
+    ///
+    /// The paths are relative to the root of the container and consist of the directory names
+    /// leading to the file followed by the name of the file itself; directories are not listed as
+    /// entries of their own. Each path must be unique within the container and must be the same
+    /// every time the same container data is interpreted, since paths are stored in the scan cache
+    /// and compared between containers. The order of the files is irrelevant.
+    ///
+    /// An error means that none of the files can be accessed; files that cannot be read
+    /// individually should be listed anyway and fail in [`read_file`](Self::read_file).
     fn list_files(&self) -> Result<Vec<PathBuf>, Error>;
-    fn read_file(&self, file_path: &Path) -> Result<Vec<u8>, Error>;
+
+    /// Reads the file at the given path, which is one of the paths returned by
+    /// [`list_files`](Self::list_files).
+    ///
+    /// Returns [`Error::FileNotFound`] if the path has not been listed, [`Error::SpannedFile`] if
+    /// the file continues in another container, [`Error::Decompression`] if the file is compressed
+    /// and fails to decompress and [`Error::Io`] if the container's data is truncated or otherwise
+    /// damaged. An error only affects this file; the consumer continues with the other ones.
+    fn read_file(&self, file_path: &Path) -> Result<Cow<'_, [u8]>, Error>;
+
+    /// Iterates over the files in the container, yielding each path along with a reader that only
+    /// reads the file once it is first used.
+    ///
+    /// Consumers that process every file should prefer this over [`list_files`](Self::list_files)
+    /// followed by [`read_file`](Self::read_file) for each path. The default implementation does
+    /// exactly that; containers that can hand out their files without looking each path up again
+    /// (and know the sizes from their directory structure) should override it.
+    fn entries(&self) -> Result<Box<dyn Iterator<Item = ContainerEntry<'_>> + '_>, Error> {
+        let paths = self.list_files()?;
+        Ok(Box::new(paths.into_iter().map(move |path| {
+            let lookup_path = path.clone();
+            ContainerEntry::new(path, None, move || self.read_file(&lookup_path))
+        })))
+    }
 }
 
 /// A container file that contains a single file.
 ///
 /// Generally a single-file compression format such as gzip or KWAJ.
+///
+/// The file has no path of its own; it is referred to using an empty path (see [`PathSequence`]).
+/// As with [`MultiFileContainer`], the file should only be decoded once it is requested.
 pub trait SingleFileContainer : fmt::Debug {
-    fn read_file(&self) -> Result<Vec<u8>, Error>;
+    /// A short name of the container format, e.g. `KWAJ`.
+    ///
+    /// Scan summaries count containers by this name, so all containers of one format should
+    /// return the same name.
+    fn format_name(&self) -> &str;
+
+    /// Reads the file from the container.
+    ///
+    /// Returns the same errors as [`MultiFileContainer::read_file`] apart from
+    /// [`Error::FileNotFound`].
+    fn read_file(&self) -> Result<Cow<'_, [u8]>, Error>;
+
+    /// The file as a [`ContainerEntry`] with an empty path, which is only read once it is first
+    /// used.
+    ///
+    /// Containers whose headers record the size of the file should override this to provide it.
+    fn entry(&self) -> ContainerEntry<'_> {
+        ContainerEntry::new(PathBuf::new(), None, move || self.read_file())
+    }
+}
+
+/// A file in a container, as yielded by [`MultiFileContainer::entries`] and
+/// [`SingleFileContainer::entry`].
+///
+/// The file is only read (and decompressed, if necessary) once its data is requested, either
+/// using [`into_data`](Self::into_data) or through the [`Read`] implementation.
+pub struct ContainerEntry<'c> {
+    /// The path of the file within the container, as returned by
+    /// [`MultiFileContainer::list_files`].
+    pub path: PathBuf,
+
+    /// The size of the file in bytes, if the container knows it without reading the file.
+    pub size: Option<u64>,
+
+    state: EntryState<'c>,
+}
+enum EntryState<'c> {
+    Unread(Box<dyn FnOnce() -> Result<Cow<'c, [u8]>, Error> + 'c>),
+    Reading(Cursor<Cow<'c, [u8]>>),
+    Failed,
+}
+impl<'c> ContainerEntry<'c> {
+    /// Creates an entry whose data is obtained by calling `read` once it is first requested.
+    pub fn new<F: FnOnce() -> Result<Cow<'c, [u8]>, Error> + 'c>(path: PathBuf, size: Option<u64>, read: F) -> Self {
+        Self {
+            path,
+            size,
+            state: EntryState::Unread(Box::new(read)),
+        }
+    }
+
+    /// Reads the file and returns all of its data, borrowed from the container if it is stored
+    /// verbatim.
+    ///
+    /// Returns the same errors as [`MultiFileContainer::read_file`]. Data that has already been
+    /// consumed through the [`Read`] implementation is included anyway.
+    pub fn into_data(self) -> Result<Cow<'c, [u8]>, Error> {
+        match self.state {
+            EntryState::Unread(read) => read(),
+            EntryState::Reading(cursor) => Ok(cursor.into_inner()),
+            EntryState::Failed => Err(Error::Io(io::ErrorKind::InvalidData.into())),
+        }
+    }
+}
+impl<'c> fmt::Debug for ContainerEntry<'c> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match &self.state {
+            EntryState::Unread(_) => "unread",
+            EntryState::Reading(_) => "reading",
+            EntryState::Failed => "failed",
+        };
+        f.debug_struct("ContainerEntry")
+            .field("path", &self.path)
+            .field("size", &self.size)
+            .field("state", &state)
+            .finish()
+    }
+}
+impl<'c> Read for ContainerEntry<'c> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let EntryState::Unread(_) = self.state {
+            let EntryState::Unread(read) = std::mem::replace(&mut self.state, EntryState::Failed) else { unreachable!() };
+            match read() {
+                Ok(data) => self.state = EntryState::Reading(Cursor::new(data)),
+                Err(Error::Io(e)) => return Err(e),
+                Err(e) => return Err(io::Error::other(e)),
+            }
+        }
+        match &mut self.state {
+            EntryState::Reading(cursor) => cursor.read(buf),
+            EntryState::Unread(_) => unreachable!(),
+            EntryState::Failed => Err(io::ErrorKind::InvalidData.into()),
+        }
+    }
 }
 
 /// A file that exports symbols.
 ///
 /// This is generally a dynamic-link library format like NE or PE.
+///
+/// Unlike containers, symbol exporters do not borrow the data they have been interpreted from, so
+/// they must copy whatever they need to return their symbols.
 pub trait SymbolExporter : fmt::Debug {
+    /// A short name of the format, e.g. `NE` or `PE`.
+    fn format_name(&self) -> &str;
+
+    /// Reads the exported symbols.
+    ///
+    /// An empty list is not an error; it is recorded as a file that exports nothing. An error is
+    /// reported and the file is skipped.
     fn read_symbols(&self) -> Result<Vec<Symbol>, Error>;
+
+    /// A remark on the exported symbols that consumers should be aware of, e.g. that they are
+    /// generated by a runtime and do not constitute a stable API.
+    fn remark(&self) -> Option<String> {
+        None
+    }
+
+    /// A reason to distrust the exported symbols, e.g. that the file loads its own code, which is
+    /// logged along with the path of the file.
+    fn warning(&self) -> Option<String> {
+        None
+    }
+
+    /// The architecture of the code in the file, e.g. `I386` or `Amd64`, if the format records it.
+    fn architecture(&self) -> Option<String> {
+        None
+    }
+
+    /// The build time of the file in RFC 3339 format, or a note that the field usually containing
+    /// it contains a reproducible-build hash instead, if the format records one.
+    fn timestamp(&self) -> Option<String> {
+        None
+    }
+
+    /// The toolchain that probably produced the file, e.g. `Visual C++ 6.0 (linker 6.00)`, if it
+    /// can be guessed.
+    fn toolchain(&self) -> Option<String> {
+        None
+    }
+
+    /// The address of the exported code or data relative to the image base, if the format
+    /// records one for this symbol.
+    fn address(&self, _symbol: &Symbol) -> Option<u32> {
+        None
+    }
+
+    /// The class of the section containing the exported code or data, which tells exported
+    /// functions apart from exported variables, if the format has sections.
+    fn section_class(&self, _symbol: &Symbol) -> Option<SectionClass> {
+        None
+    }
+
+    /// The export to which the symbol is forwarded, as stored in the file (e.g.
+    /// `NTOSKRNL.KeGetCurrentIrql`), if it is a forwarder.
+    fn forwarder(&self, _symbol: &Symbol) -> Option<String> {
+        None
+    }
 }
 
 /// A file with its contents interpreted.
+///
+/// Containers retain the data they have been interpreted from, which is either borrowed from the
+/// caller (the lifetime `'d`) or owned by the container, depending on whether the data was passed
+/// to [`interpret_file`](crate::formats::interpret_file) as a slice or as a vector. Borrowing
+/// avoids copying the data, while handing over ownership allows the container to outlive the
+/// buffer and frees the data as soon as the container is dropped. Files read from a container in
+/// turn borrow from the container wherever possible.
 #[derive(Debug)]
-pub enum IdentifiedFile {
-    MultiFileContainer(Box<dyn MultiFileContainer>),
-    SingleFileContainer(Box<dyn SingleFileContainer>),
+pub enum IdentifiedFile<'d> {
+    MultiFileContainer(Box<dyn MultiFileContainer + 'd>),
+    SingleFileContainer(Box<dyn SingleFileContainer + 'd>),
     SymbolExporter(Box<dyn SymbolExporter>),
+    Metadata(Metadata),
     Unidentified,
 }
 
+/// Information from a text file that does not export symbols but describes other files.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, Serialize)]
+pub struct Metadata {
+    pub kind: MetadataKind,
+
+    /// The sections of the file in the order in which they appear.
+    ///
+    /// Batch files are represented as a single unnamed section containing each line as a value.
+    pub sections: Vec<MetadataSection>,
+
+    /// The files listed in the `[SourceDisksFiles]` sections of an INF file.
+    pub source_disks_files: Vec<String>,
+
+    /// The files copied by the `CopyFiles` directives of an INF file.
+    pub copied_files: Vec<CopiedFile>,
+}
+
+/// The kind of text file from which metadata has been extracted.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum MetadataKind {
+    /// Setup information file.
+    Inf,
+
+    /// Initialization file.
+    #[default] Ini,
+
+    /// Registry export file.
+    Reg,
+
+    /// Batch file.
+    Batch,
+}
+
+/// A section of a metadata file.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct MetadataSection {
+    pub name: String,
+
+    /// The entries of the section; lines without an equals sign have no key.
+    pub entries: Vec<(Option<String>, String)>,
+}
+
+/// A file copied during installation, as described by an INF file.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct CopiedFile {
+    /// The name of the file once it is installed.
+    pub destination: String,
+
+    /// The name of the file on the installation media (before any compression suffix is applied).
+    pub source: String,
+}
+
 /// A single exported symbol.
-#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum Symbol {
     ByName { name: String },
     ByOrdinal { ordinal: u32 },
     ByNameAndOrdinal { name: String, ordinal: u32 },
+
+    /// A type or method described by Windows Runtime metadata, e.g. `Windows.Foundation.Uri` or
+    /// `Windows.Foundation.Uri.get_Host`.
+    WinRt { name: String },
+
+    /// A server of a COM class registered in a registry hive, named after the class, the kind of
+    /// server and the server's path, e.g.
+    /// `{00021401-0000-0000-C000-000000000046}\InprocServer32=%SystemRoot%\system32\shell32.dll`.
+    ComServer { name: String },
 }
 impl Symbol {
     pub fn name(&self) -> Option<&str> {
@@ -84,6 +376,8 @@ impl Symbol {
             Self::ByName { name } => Some(name),
             Self::ByOrdinal { .. } => None,
             Self::ByNameAndOrdinal { name, .. } => Some(name),
+            Self::WinRt { name } => Some(name),
+            Self::ComServer { name } => Some(name),
         }
     }
 
@@ -92,11 +386,25 @@ impl Symbol {
             Self::ByName { .. } => None,
             Self::ByOrdinal { ordinal } => Some(*ordinal),
             Self::ByNameAndOrdinal { ordinal, .. } => Some(*ordinal),
+            Self::WinRt { .. } => None,
+            Self::ComServer { .. } => None,
+        }
+    }
+
+    /// The kind of symbol as it is output by the scan: `export`, `winrt` or `com_server`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ByName { .. } | Self::ByOrdinal { .. } | Self::ByNameAndOrdinal { .. } => "export",
+            Self::WinRt { .. } => "winrt",
+            Self::ComServer { .. } => "com_server",
         }
     }
 }
 
 /// Sometimes things go wrong.
+///
+/// Formats report failures that have no variant of their own (e.g. invalid headers) as
+/// [`Error::Io`] with an error of kind [`InvalidData`](std::io::ErrorKind::InvalidData).
 #[derive(Debug)]
 pub enum Error {
     Io(std::io::Error),
@@ -104,6 +412,9 @@ pub enum Error {
     Decompression(DecompressionError),
     InvalidUtf8FileName(Vec<u8>),
     SpannedFile,
+    MemoryNotCaptured(u64),
+    NotAContainer,
+    TimeLimitExceeded(Duration),
 }
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -118,6 +429,12 @@ impl fmt::Display for Error {
                 => write!(f, "invalid UTF-8 file name: {:?}", e),
             Self::SpannedFile
                 => write!(f, "file spans multiple containers"),
+            Self::MemoryNotCaptured(address)
+                => write!(f, "memory at {:#X} has not been captured", address),
+            Self::NotAContainer
+                => write!(f, "file is not a recognized container"),
+            Self::TimeLimitExceeded(limit)
+                => write!(f, "time limit of {:?} exceeded", limit),
         }
     }
 }
@@ -129,11 +446,20 @@ impl std::error::Error for Error {
             Self::Decompression(e) => Some(e),
             Self::InvalidUtf8FileName(_) => None,
             Self::SpannedFile => None,
+            Self::MemoryNotCaptured(_) => None,
+            Self::NotAContainer => None,
+            Self::TimeLimitExceeded(_) => None,
         }
     }
 }
 impl From<std::io::Error> for Error {
-    fn from(value: std::io::Error) -> Self { Self::Io(value) }
+    fn from(value: std::io::Error) -> Self {
+        // readers that ran out of time say so within an I/O error
+        match value.get_ref().and_then(|e| e.downcast_ref::<TimeLimitExceeded>()) {
+            Some(exceeded) => Self::TimeLimitExceeded(exceeded.limit),
+            None => Self::Io(value),
+        }
+    }
 }
 impl From<DecompressionError> for Error {
     fn from(value: DecompressionError) -> Self { Self::Decompression(value) }
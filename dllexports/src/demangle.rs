@@ -0,0 +1,238 @@
+//! Undecoration of exported symbol names.
+//!
+//! Only the (qualified) name of the symbol is recovered; type information, calling conventions and
+//! storage classes encoded in a decorated name are dropped. Names that cannot be undecorated (e.g.
+//! because they contain templates) are left alone.
+
+
+/// Attempts to undecorate the given symbol name.
+///
+/// Recognizes Microsoft C++ names (`?name@Scope@@...`), `__stdcall` names (`_name@12`) and
+/// `__fastcall` names (`@name@12`). Returns `None` if the name is not decorated in one of these
+/// ways or if the decoration is not understood.
+pub(crate) fn undecorate(name: &str) -> Option<String> {
+    if let Some(rest) = name.strip_prefix('?') {
+        return undecorate_msvc(rest);
+    }
+
+    if let Some(rest) = name.strip_prefix('@') {
+        // __fastcall: @name@argbytes
+        return strip_arg_bytes(rest).map(|s| s.to_owned());
+    }
+
+    if let Some(rest) = name.strip_prefix('_') {
+        // __stdcall: _name@argbytes
+        return strip_arg_bytes(rest).map(|s| s.to_owned());
+    }
+
+    // __stdcall without the leading underscore (as often found in export tables)
+    strip_arg_bytes(name).map(|s| s.to_owned())
+}
+
+/// Strips the `@argbytes` suffix from a `__stdcall` or `__fastcall` name.
+fn strip_arg_bytes(name: &str) -> Option<&str> {
+    let (base, arg_bytes) = name.rsplit_once('@')?;
+    if base.is_empty() || base.contains('@') {
+        return None;
+    }
+    if arg_bytes.is_empty() || !arg_bytes.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(base)
+}
+
+
+/// Undecorates a Microsoft C++ name whose leading `?` has already been stripped.
+fn undecorate_msvc(mut rest: &str) -> Option<String> {
+    let mut back_refs: Vec<String> = Vec::new();
+
+    // the innermost fragment (the name itself) comes first
+    let mut special: Option<Special> = None;
+    let innermost = if let Some(special_rest) = rest.strip_prefix('?') {
+        let (s, new_rest) = Special::parse(special_rest)?;
+        rest = new_rest;
+        special = Some(s);
+        None
+    } else {
+        let (fragment, new_rest) = take_fragment(rest, &mut back_refs)?;
+        rest = new_rest;
+        Some(fragment)
+    };
+
+    // then the enclosing scopes, innermost to outermost, terminated by an '@'
+    let mut scopes: Vec<String> = Vec::new();
+    loop {
+        if rest.starts_with('@') {
+            // the type information follows; we ignore it
+            break;
+        }
+        let (fragment, new_rest) = take_fragment(rest, &mut back_refs)?;
+        rest = new_rest;
+        scopes.push(fragment);
+    }
+
+    let name = match (special, innermost) {
+        (None, Some(n)) => n,
+        (Some(s), None) => {
+            // constructors and destructors are named after the class
+            let class_name = scopes.first().map(|s| s.as_str());
+            s.to_name(class_name)?
+        },
+        _ => return None,
+    };
+
+    let mut ret = String::new();
+    for scope in scopes.iter().rev() {
+        ret.push_str(scope);
+        ret.push_str("::");
+    }
+    ret.push_str(&name);
+    Some(ret)
+}
+
+/// Takes a single name fragment (a simple name terminated by `@` or a back-reference digit) from
+/// the beginning of the string.
+fn take_fragment<'a>(rest: &'a str, back_refs: &mut Vec<String>) -> Option<(String, &'a str)> {
+    let first = rest.bytes().next()?;
+    if first.is_ascii_digit() {
+        let index = usize::from(first - b'0');
+        let fragment = back_refs.get(index)?.clone();
+        return Some((fragment, &rest[1..]));
+    }
+    if first == b'?' {
+        // templates, anonymous namespaces, nested names etc.; not supported
+        return None;
+    }
+
+    let (fragment, new_rest) = rest.split_once('@')?;
+    if fragment.is_empty() {
+        return None;
+    }
+    if back_refs.len() < 10 {
+        back_refs.push(fragment.to_owned());
+    }
+    Some((fragment.to_owned(), new_rest))
+}
+
+
+/// A special name in the Microsoft C++ name decoration scheme.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Special {
+    Constructor,
+    Destructor,
+    Fixed(&'static str),
+}
+impl Special {
+    /// Parses a special name whose leading `??` has been reduced to a single stripped `?`.
+    fn parse(rest: &str) -> Option<(Self, &str)> {
+        let mut chars = rest.char_indices();
+        let (_, c) = chars.next()?;
+        let (special, consumed) = if c == '_' {
+            let (_, c2) = chars.next()?;
+            let name = match c2 {
+                '0' => "operator/=",
+                '1' => "operator%=",
+                '2' => "operator>>=",
+                '3' => "operator<<=",
+                '4' => "operator&=",
+                '5' => "operator|=",
+                '6' => "operator^=",
+                '7' => "`vftable'",
+                '8' => "`vbtable'",
+                '9' => "`vcall'",
+                'A' => "`typeof'",
+                'B' => "`local static guard'",
+                'D' => "`vbase destructor'",
+                'E' => "`vector deleting destructor'",
+                'F' => "`default constructor closure'",
+                'G' => "`scalar deleting destructor'",
+                'H' => "`vector constructor iterator'",
+                'I' => "`vector destructor iterator'",
+                'J' => "`vector vbase constructor iterator'",
+                'K' => "`virtual displacement map'",
+                'L' => "`eh vector constructor iterator'",
+                'M' => "`eh vector destructor iterator'",
+                'N' => "`eh vector vbase constructor iterator'",
+                'O' => "`copy constructor closure'",
+                'S' => "`local vftable'",
+                'T' => "`local vftable constructor closure'",
+                'U' => "operator new[]",
+                'V' => "operator delete[]",
+                'X' => "`placement delete closure'",
+                'Y' => "`placement delete[] closure'",
+                _ => return None,
+            };
+            (Self::Fixed(name), 2)
+        } else {
+            let special = match c {
+                '0' => Self::Constructor,
+                '1' => Self::Destructor,
+                '2' => Self::Fixed("operator new"),
+                '3' => Self::Fixed("operator delete"),
+                '4' => Self::Fixed("operator="),
+                '5' => Self::Fixed("operator>>"),
+                '6' => Self::Fixed("operator<<"),
+                '7' => Self::Fixed("operator!"),
+                '8' => Self::Fixed("operator=="),
+                '9' => Self::Fixed("operator!="),
+                'A' => Self::Fixed("operator[]"),
+                'B' => Self::Fixed("operator cast"),
+                'C' => Self::Fixed("operator->"),
+                'D' => Self::Fixed("operator*"),
+                'E' => Self::Fixed("operator++"),
+                'F' => Self::Fixed("operator--"),
+                'G' => Self::Fixed("operator-"),
+                'H' => Self::Fixed("operator+"),
+                'I' => Self::Fixed("operator&"),
+                'J' => Self::Fixed("operator->*"),
+                'K' => Self::Fixed("operator/"),
+                'L' => Self::Fixed("operator%"),
+                'M' => Self::Fixed("operator<"),
+                'N' => Self::Fixed("operator<="),
+                'O' => Self::Fixed("operator>"),
+                'P' => Self::Fixed("operator>="),
+                'Q' => Self::Fixed("operator,"),
+                'R' => Self::Fixed("operator()"),
+                'S' => Self::Fixed("operator~"),
+                'T' => Self::Fixed("operator^"),
+                'U' => Self::Fixed("operator|"),
+                'V' => Self::Fixed("operator&&"),
+                'W' => Self::Fixed("operator||"),
+                'X' => Self::Fixed("operator*="),
+                'Y' => Self::Fixed("operator+="),
+                'Z' => Self::Fixed("operator-="),
+                _ => return None,
+            };
+            (special, 1)
+        };
+        Some((special, &rest[consumed..]))
+    }
+
+    fn to_name(self, class_name: Option<&str>) -> Option<String> {
+        match self {
+            Self::Constructor => class_name.map(|c| c.to_owned()),
+            Self::Destructor => class_name.map(|c| format!("~{}", c)),
+            Self::Fixed(name) => Some(name.to_owned()),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::undecorate;
+
+    #[test]
+    fn test_undecorate() {
+        assert_eq!(undecorate("GetVersion"), None);
+        assert_eq!(undecorate("_GetVersion@0").as_deref(), Some("GetVersion"));
+        assert_eq!(undecorate("@FastFunc@8").as_deref(), Some("FastFunc"));
+        assert_eq!(undecorate("?Foo@@YAXXZ").as_deref(), Some("Foo"));
+        assert_eq!(undecorate("?Method@Class@Namespace@@QAEHH@Z").as_deref(), Some("Namespace::Class::Method"));
+        assert_eq!(undecorate("??0CWnd@@QAE@XZ").as_deref(), Some("CWnd::CWnd"));
+        assert_eq!(undecorate("??1CWnd@@UAE@XZ").as_deref(), Some("CWnd::~CWnd"));
+        assert_eq!(undecorate("??4Inner@Outer@@QAEAAV01@ABV01@@Z").as_deref(), Some("Outer::Inner::operator="));
+        assert_eq!(undecorate("??_7CWnd@@6B@").as_deref(), Some("CWnd::`vftable'"));
+        assert_eq!(undecorate("??$Tmpl@H@@YAXXZ"), None);
+    }
+}
@@ -0,0 +1,122 @@
+//! The exit statuses of the command-line tool.
+//!
+//! Batch pipelines use the exit status to tell apart a file that simply was not interesting from
+//! one that could not be processed and from a crash of the tool itself:
+//!
+//! | status | name                  | meaning                                                      |
+//! | ------ | --------------------- | ------------------------------------------------------------ |
+//! | 0      | `ok`                  | the command succeeded                                        |
+//! | 1      | `partial-with-errors` | the command finished, but some files could not be processed  |
+//! | 2      |                       | the command line is invalid (reported by the argument parser) |
+//! | 3      | `unidentified-format` | the format of the input file has not been recognized         |
+//! | 4      | `parse-error`         | the input file has been recognized but could not be read     |
+//! | 5      | `io-error`            | a file could not be read from or written to disk             |
+//! | 101    |                       | the tool crashed                                             |
+//!
+//! Failures of the low-level `poke` commands generally still lead to a crash.
+
+
+use std::fmt;
+use std::io;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::data_mgmt::Error;
+
+
+/// The outcome of a command, as reflected in the exit status of the tool.
+#[derive(Clone, Copy, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExitStatus {
+    Ok,
+    PartialWithErrors,
+    UnidentifiedFormat,
+    ParseError,
+    IoError,
+}
+impl ExitStatus {
+    /// The numeric exit status.
+    pub fn code(self) -> i32 {
+        match self {
+            Self::Ok => 0,
+            Self::PartialWithErrors => 1,
+            Self::UnidentifiedFormat => 3,
+            Self::ParseError => 4,
+            Self::IoError => 5,
+        }
+    }
+
+    /// The status corresponding to a failure to read or write a file.
+    ///
+    /// Data that is invalid or ends prematurely counts as a parse error, since the formats report
+    /// such problems as I/O errors.
+    pub fn of_io_error(error: &io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::InvalidData|io::ErrorKind::UnexpectedEof => Self::ParseError,
+            _ => Self::IoError,
+        }
+    }
+
+    /// The status corresponding to a failure to interpret a file.
+    pub fn of_error(error: &Error) -> Self {
+        match error {
+            Error::Io(e) => Self::of_io_error(e),
+            Error::NotAContainer => Self::UnidentifiedFormat,
+            Error::TimeLimitExceeded(_) => Self::PartialWithErrors,
+            Error::FileNotFound(_)|Error::Decompression(_)|Error::InvalidUtf8FileName(_)
+                |Error::SpannedFile|Error::MemoryNotCaptured(_)
+                => Self::ParseError,
+        }
+    }
+
+    /// Exits the process with this status.
+    pub fn exit(self) -> ! {
+        std::process::exit(self.code())
+    }
+}
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ok => write!(f, "ok"),
+            Self::PartialWithErrors => write!(f, "partial-with-errors"),
+            Self::UnidentifiedFormat => write!(f, "unidentified-format"),
+            Self::ParseError => write!(f, "parse-error"),
+            Self::IoError => write!(f, "io-error"),
+        }
+    }
+}
+
+
+/// Reports a failure that ends the command and exits with the given status.
+pub(crate) fn fail<E: fmt::Display>(status: ExitStatus, what: &str, error: E) -> ! {
+    eprintln!("{}: {} ({})", what, error, status);
+    status.exit()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::time::Duration;
+    use super::ExitStatus;
+    use crate::data_mgmt::Error;
+
+    #[test]
+    fn test_status_of_error() {
+        assert_eq!(ExitStatus::of_error(&Error::NotAContainer), ExitStatus::UnidentifiedFormat);
+        assert_eq!(ExitStatus::of_error(&Error::SpannedFile), ExitStatus::ParseError);
+        assert_eq!(ExitStatus::of_error(&Error::Io(io::ErrorKind::InvalidData.into())), ExitStatus::ParseError);
+        assert_eq!(ExitStatus::of_error(&Error::Io(io::ErrorKind::PermissionDenied.into())), ExitStatus::IoError);
+        assert_eq!(ExitStatus::of_error(&Error::TimeLimitExceeded(Duration::from_secs(1))), ExitStatus::PartialWithErrors);
+
+        // readers that run out of time report it through an I/O error
+        let timed_out = {
+            let _deadline = binstruct::deadline::set_time_limit(Some(Duration::ZERO));
+            Error::from(binstruct::deadline::check().unwrap_err())
+        };
+        assert!(matches!(timed_out, Error::TimeLimitExceeded(limit) if limit == Duration::ZERO));
+        assert_eq!(ExitStatus::of_error(&timed_out), ExitStatus::PartialWithErrors);
+        assert_eq!(ExitStatus::ParseError.to_string(), serde_json::to_value(ExitStatus::ParseError).unwrap());
+    }
+}
@@ -0,0 +1,591 @@
+//! Listing and extraction of files in containers.
+
+
+use std::collections::BTreeSet;
+use std::fs::{self, create_dir_all, File};
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+
+use clap::ValueEnum;
+use serde::Serialize;
+use tracing::{debug, error, info, warn};
+
+use crate::data_mgmt::{Error, IdentifiedFile};
+use crate::formats::interpret_container_volume;
+use crate::hashing::FileDigests;
+use crate::volumes::{is_cabinet_continuation, ContainerSiblings, NoSiblings, Siblings};
+
+
+/// How the paths of extracted files are mapped to the output directory.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) enum PathMode {
+    /// Recreate the directory structure of the container.
+    #[default] Preserve,
+
+    /// Place all files directly in the output directory.
+    Flatten,
+}
+
+
+/// How [`expand_directory`] handles files that are not compressed.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd, ValueEnum)]
+pub(crate) enum UncompressedMode {
+    /// Copy the file into the output directory.
+    #[default] Copy,
+
+    /// Create a symbolic link to the original file in the output directory.
+    Symlink,
+
+    /// Leave the file out.
+    Skip,
+}
+
+
+/// What [`expand_directory`] has done.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) struct ExpandSummary {
+    /// The number of compressed files that have been expanded.
+    pub expanded: usize,
+
+    /// The number of uncompressed files that have been copied or linked.
+    pub copied: usize,
+
+    /// The number of files that have been left out, either due to [`UncompressedMode::Skip`] or
+    /// because a file with the same output path had already been written.
+    pub skipped: usize,
+
+    /// The number of files that could not be read or expanded.
+    pub failed: usize,
+}
+
+
+/// Checks whether a path within a container matches a wildcard pattern.
+///
+/// `*` matches any sequence of characters except the path separator, `?` matches a single such
+/// character. The comparison is case-insensitive for ASCII characters, as in DOS and Windows file
+/// systems. A pattern matching a directory matches every file within it.
+pub(crate) fn path_matches(pattern: &str, path: &Path) -> bool {
+    let pattern_parts: Vec<&str> = pattern.split(['/', '\\'])
+        .filter(|p| !p.is_empty())
+        .collect();
+    let path_parts: Vec<String> = path.components()
+        .filter_map(|c| match c {
+            Component::Normal(n) => Some(n.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    if pattern_parts.len() > path_parts.len() {
+        return false;
+    }
+    pattern_parts.iter()
+        .zip(path_parts.iter())
+        .all(|(pat, part)| wildcard_matches(pat.as_bytes(), part.as_bytes()))
+}
+
+fn wildcard_matches(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| wildcard_matches(rest, &text[skip..])),
+        Some((b'?', rest)) => !text.is_empty() && wildcard_matches(rest, &text[1..]),
+        Some((p, rest)) => text.first().is_some_and(|t| t.eq_ignore_ascii_case(p)) && wildcard_matches(rest, &text[1..]),
+    }
+}
+
+/// Derives the name of the file within a single-file container from the container's name.
+///
+/// KWAJ and SZDD files often store enough information to restore the original name (see
+/// [`expandms::original_file_name`]); for other containers, `.out` is appended to the name to
+/// avoid overwriting the container.
+pub(crate) fn single_file_name(container_name: &str, data: &[u8]) -> String {
+    expandms::original_file_name(container_name, data)
+        .unwrap_or_else(|| format!("{}.out", container_name))
+}
+
+/// Converts a path within a container into a relative path that cannot escape the output
+/// directory.
+fn sanitize_path(path: &Path) -> Option<PathBuf> {
+    let mut ret = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(n) => ret.push(n),
+            Component::CurDir|Component::RootDir => {},
+            Component::ParentDir|Component::Prefix(_) => return None,
+        }
+    }
+    if ret.as_os_str().is_empty() {
+        None
+    } else {
+        Some(ret)
+    }
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<(), io::Error> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+    let mut file = File::create(path)?;
+    file.write_all(data)
+}
+
+
+/// Extracts the files matching any of the patterns (or all files if no patterns are given) from
+/// the container into the output directory.
+///
+/// The single file in a single-file container is named `single_file_name` (see
+/// [`single_file_name`]). Returns the number of extracted files.
+pub(crate) fn extract(
+    container: &IdentifiedFile,
+    single_file_name: &Path,
+    patterns: &[String],
+    output_dir: &Path,
+    path_mode: PathMode,
+) -> Result<usize, Error> {
+    match container {
+        IdentifiedFile::SingleFileContainer(sfc) => {
+            let data = sfc.read_file()?;
+            let output_path = output_dir.join(single_file_name);
+            info!("extracting to {}", output_path.display());
+            write_file(&output_path, &data)?;
+            Ok(1)
+        },
+        IdentifiedFile::MultiFileContainer(mfc) => {
+            let mut extracted = 0;
+            let mut output_paths = BTreeSet::new();
+            for file in mfc.list_files()? {
+                if !patterns.is_empty() && !patterns.iter().any(|p| path_matches(p, &file)) {
+                    continue;
+                }
+
+                let Some(relative_path) = sanitize_path(&file) else {
+                    error!("refusing to extract {:?} as it would end up outside the output directory", file);
+                    continue;
+                };
+                let relative_path = match path_mode {
+                    PathMode::Preserve => relative_path,
+                    PathMode::Flatten => PathBuf::from(relative_path.file_name().unwrap()),
+                };
+                let output_path = output_dir.join(&relative_path);
+                if !output_paths.insert(output_path.clone()) {
+                    warn!("skipping {:?} as {} has already been extracted", file, output_path.display());
+                    continue;
+                }
+
+                let data = mfc.read_file(&file)?;
+                info!("extracting {:?} to {}", file, output_path.display());
+                write_file(&output_path, &data)?;
+                extracted += 1;
+            }
+            Ok(extracted)
+        },
+        _ => Err(Error::NotAContainer),
+    }
+}
+
+
+/// Expands all KWAJ, SZDD and SZ files in the input directory and its subdirectories into the
+/// output directory, as when expanding a whole setup disk.
+///
+/// The directory structure is preserved. Expanded files are given their original names if these
+/// can be determined (see [`expandms::original_file_name`]) and keep their compressed names
+/// otherwise. Uncompressed files (including CAB files, which contain multiple files) are handled
+/// according to `uncompressed_mode`.
+///
+/// Files that cannot be read or expanded are logged and counted but do not stop the process;
+/// failures to write to the output directory do. The output directory may not be within the input
+/// directory, where the expanded files would be expanded again.
+pub(crate) fn expand_directory(input_dir: &Path, output_dir: &Path, uncompressed_mode: UncompressedMode) -> Result<ExpandSummary, io::Error> {
+    if resolve_path(output_dir)?.starts_with(input_dir.canonicalize()?) {
+        error!("the output directory {} is within the input directory {}", output_dir.display(), input_dir.display());
+        return Err(io::ErrorKind::InvalidInput.into());
+    }
+
+    let mut summary = ExpandSummary::default();
+    let mut output_paths = BTreeSet::new();
+    expand_directory_into(input_dir, output_dir, uncompressed_mode, &mut output_paths, &mut summary)?;
+    Ok(summary)
+}
+
+fn expand_directory_into(
+    input_dir: &Path,
+    output_dir: &Path,
+    uncompressed_mode: UncompressedMode,
+    output_paths: &mut BTreeSet<PathBuf>,
+    summary: &mut ExpandSummary,
+) -> Result<(), io::Error> {
+    let mut entries = fs::read_dir(input_dir)?
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let input_path = entry.path();
+        let name = entry.file_name();
+        if entry.file_type()?.is_dir() {
+            expand_directory_into(&input_path, &output_dir.join(&name), uncompressed_mode, output_paths, summary)?;
+            continue;
+        }
+
+        let data = match fs::read(&input_path) {
+            Ok(d) => d,
+            Err(e) => {
+                error!("failed to read {}: {}", input_path.display(), e);
+                summary.failed += 1;
+                continue;
+            },
+        };
+
+        if !expandms::is_compressed(&data) {
+            if uncompressed_mode == UncompressedMode::Skip {
+                debug!("skipping uncompressed {}", input_path.display());
+                summary.skipped += 1;
+                continue;
+            }
+            let output_path = output_dir.join(&name);
+            if !output_paths.insert(output_path.clone()) {
+                warn!("skipping {} as {} has already been written", input_path.display(), output_path.display());
+                summary.skipped += 1;
+                continue;
+            }
+            if uncompressed_mode == UncompressedMode::Symlink {
+                info!("linking {} to {}", output_path.display(), input_path.display());
+                create_dir_all(output_dir)?;
+                symlink_file(&input_path.canonicalize()?, &output_path)?;
+            } else {
+                info!("copying {} to {}", input_path.display(), output_path.display());
+                write_file(&output_path, &data)?;
+            }
+            summary.copied += 1;
+            continue;
+        }
+
+        let name_string = name.to_string_lossy();
+        let output_name = expandms::original_file_name(&name_string, &data)
+            .filter(|n| {
+                // a restored name must not lead out of the directory
+                let is_directory_reference = n == "." || n == "..";
+                if is_directory_reference {
+                    warn!("ignoring the original name {:?} of {}", n, input_path.display());
+                }
+                !is_directory_reference
+            })
+            .unwrap_or_else(|| name_string.into_owned());
+        let output_path = output_dir.join(output_name);
+        if output_paths.contains(&output_path) {
+            warn!("skipping {} as {} has already been written", input_path.display(), output_path.display());
+            summary.skipped += 1;
+            continue;
+        }
+
+        let mut expanded = Vec::new();
+        if let Err(e) = expandms::decompress(&mut data.as_slice(), &mut expanded) {
+            error!("failed to expand {}: {}", input_path.display(), e);
+            summary.failed += 1;
+            continue;
+        }
+        info!("expanding {} to {}", input_path.display(), output_path.display());
+        write_file(&output_path, &expanded)?;
+        output_paths.insert(output_path);
+        summary.expanded += 1;
+    }
+    Ok(())
+}
+
+/// Returns the canonical form of a path that may not exist yet, canonicalizing the part that does.
+fn resolve_path(path: &Path) -> Result<PathBuf, io::Error> {
+    let path = std::path::absolute(path)?;
+    let mut missing = Vec::new();
+    let mut existing = path.as_path();
+    loop {
+        match existing.canonicalize() {
+            Ok(canonical) => return Ok(missing.iter().rev().fold(canonical, |p, c| p.join(c))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else { return Err(e) };
+                missing.push(name);
+                existing = parent;
+            },
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn symlink_file(original: &Path, link: &Path) -> Result<(), io::Error> {
+    std::os::unix::fs::symlink(original, link)
+}
+
+#[cfg(windows)]
+fn symlink_file(original: &Path, link: &Path) -> Result<(), io::Error> {
+    std::os::windows::fs::symlink_file(original, link)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn symlink_file(_original: &Path, _link: &Path) -> Result<(), io::Error> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+
+/// A file found within a container.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub(crate) struct ListedFile {
+    /// The path of the file within its container.
+    pub path: PathBuf,
+
+    /// The size of the file in bytes, unless it could not be read.
+    pub size: Option<usize>,
+
+    /// A short description of the detected format of the file.
+    pub format: String,
+
+    /// The digests of the file, if they have been requested.
+    #[serde(flatten)]
+    pub digests: Option<FileDigests>,
+
+    /// The error encountered while reading the file or listing the files within it, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// The files contained within this file, if it is a container and has been descended into.
+    pub children: Vec<ListedFile>,
+}
+impl ListedFile {
+    /// A file that could not be read from its container.
+    fn unreadable(path: PathBuf, error: &Error) -> Self {
+        Self {
+            path,
+            size: None,
+            format: "unreadable".to_owned(),
+            digests: None,
+            error: Some(error.to_string()),
+            children: Vec::new(),
+        }
+    }
+}
+
+
+/// Describes the format of an interpreted file in a few words, e.g. `CAB container` or `PE`.
+pub(crate) fn describe_format(file: &IdentifiedFile) -> String {
+    match file {
+        IdentifiedFile::MultiFileContainer(mfc) => format!("{} container", mfc.format_name()),
+        IdentifiedFile::SingleFileContainer(sfc) => format!("{} compressed file", sfc.format_name()),
+        IdentifiedFile::SymbolExporter(exporter) => exporter.format_name().to_owned(),
+        IdentifiedFile::Metadata(metadata) => format!("{:?} metadata", metadata.kind),
+        IdentifiedFile::Unidentified => "unknown".to_owned(),
+    }
+}
+
+
+/// Lists the files in the container, descending into nested containers up to `depth` levels deep.
+///
+/// The single file in a single-file container is listed as `single_file_name`. If `with_digests`
+/// is set, the SHA-1 and SHA-256 digests of each file are calculated as well.
+///
+/// A file that cannot be read, or whose files cannot be listed, is listed with the error and does
+/// not stop the listing; only failing to list the files of `container` itself does.
+pub(crate) fn list(container: &IdentifiedFile, single_file_name: &Path, depth: usize, with_digests: bool) -> Result<Vec<ListedFile>, Error> {
+    let mut listed = Vec::new();
+    match container {
+        IdentifiedFile::SingleFileContainer(sfc) => {
+            match sfc.read_file() {
+                Ok(data) => listed.push(list_child(single_file_name.to_owned(), &data, depth, with_digests, &NoSiblings)),
+                Err(e) => {
+                    warn!("failed to read {:?}: {}", single_file_name, e);
+                    listed.push(ListedFile::unreadable(single_file_name.to_owned(), &e));
+                },
+            }
+        },
+        IdentifiedFile::MultiFileContainer(mfc) => {
+            let files = mfc.list_files()?;
+            for file in &files {
+                match mfc.read_file(file) {
+                    Ok(data) => {
+                        let siblings = ContainerSiblings::new(mfc.as_ref(), &files, file);
+                        listed.push(list_child(file.clone(), &data, depth, with_digests, &siblings));
+                    },
+                    Err(e) => {
+                        warn!("failed to read {:?}: {}", file, e);
+                        listed.push(ListedFile::unreadable(file.clone(), &e));
+                    },
+                }
+            }
+        },
+        _ => return Err(Error::NotAContainer),
+    }
+    Ok(listed)
+}
+
+fn list_child(path: PathBuf, data: &[u8], depth: usize, with_digests: bool, siblings: &dyn Siblings) -> ListedFile {
+    let name = path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if is_cabinet_continuation(data, siblings) {
+        // its files are listed with the first cabinet of the set
+        return ListedFile {
+            path,
+            size: Some(data.len()),
+            format: "CAB continuation volume".to_owned(),
+            digests: with_digests.then(|| FileDigests::of(data)),
+            error: None,
+            children: Vec::new(),
+        };
+    }
+    let interpreted = match interpret_container_volume(data, &name, siblings) {
+        Ok(i) => i,
+        Err(e) => {
+            // the detection of some formats is rather optimistic
+            debug!("failed to interpret {:?}: {}", path, e);
+            IdentifiedFile::Unidentified
+        },
+    };
+    let format = describe_format(&interpreted);
+
+    let is_container = matches!(
+        interpreted,
+        IdentifiedFile::SingleFileContainer(_) | IdentifiedFile::MultiFileContainer(_)
+    );
+    let mut error = None;
+    let children = if depth > 0 && is_container {
+        let child_single_file_name = PathBuf::from(single_file_name(&name, data));
+        match list(&interpreted, &child_single_file_name, depth - 1, with_digests) {
+            Ok(children) => children,
+            Err(e) => {
+                warn!("failed to list the files in {:?}: {}", path, e);
+                error = Some(e.to_string());
+                Vec::new()
+            },
+        }
+    } else {
+        Vec::new()
+    };
+
+    ListedFile {
+        path,
+        size: Some(data.len()),
+        format,
+        digests: with_digests.then(|| FileDigests::of(data)),
+        error,
+        children,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use testgen::{cab, kwaj, ne, pe, sfx, szdd};
+    use super::{expand_directory, list, path_matches, ExpandSummary, UncompressedMode};
+    use crate::formats::interpret_file;
+
+    #[test]
+    fn test_path_matches() {
+        assert!(path_matches("SYSTEM/USER.EXE", Path::new("SYSTEM/USER.EXE")));
+        assert!(path_matches("system/user.exe", Path::new("SYSTEM/USER.EXE")));
+        assert!(path_matches("SYSTEM", Path::new("SYSTEM/USER.EXE")));
+        assert!(path_matches("SYSTEM/*.EXE", Path::new("SYSTEM/USER.EXE")));
+        assert!(path_matches("*/US?R.*", Path::new("SYSTEM/USER.EXE")));
+        assert!(path_matches("*", Path::new("SYSTEM/USER.EXE")));
+        assert!(!path_matches("*.EXE", Path::new("SYSTEM/USER.EXE")));
+        assert!(!path_matches("SYSTEM/*.DLL", Path::new("SYSTEM/USER.EXE")));
+        assert!(!path_matches("SYSTEM/USER.EXE/MORE", Path::new("SYSTEM/USER.EXE")));
+    }
+
+    #[test]
+    fn test_expand_directory() {
+        let plaintext = b"The quick brown fox jumps over the lazy dog.";
+        let input_dir = tempfile::tempdir().unwrap();
+        let output_dir = tempfile::tempdir().unwrap();
+        fs::create_dir(input_dir.path().join("SYSTEM")).unwrap();
+        fs::write(input_dir.path().join("SYSTEM/FOX.TX_"), kwaj::compress(plaintext, kwaj::Method::Xored, Some("FOX.TXT"))).unwrap();
+        fs::write(input_dir.path().join("SYSTEM/dog.tx_"), szdd::compress(plaintext, b'T')).unwrap();
+        fs::write(input_dir.path().join("SETUP.INF"), b"[setup]").unwrap();
+        fs::write(input_dir.path().join("BROKEN.EX_"), b"KWAJ\x88\xF0\x27\xD1\x09\x00").unwrap();
+
+        let summary = expand_directory(input_dir.path(), output_dir.path(), UncompressedMode::Copy).unwrap();
+        assert_eq!(summary, ExpandSummary { expanded: 2, copied: 1, skipped: 0, failed: 1 });
+        assert_eq!(fs::read(output_dir.path().join("SYSTEM/FOX.TXT")).unwrap(), plaintext);
+        assert_eq!(fs::read(output_dir.path().join("SYSTEM/dog.txt")).unwrap(), plaintext);
+        assert_eq!(fs::read(output_dir.path().join("SETUP.INF")).unwrap(), b"[setup]");
+        assert!(!output_dir.path().join("BROKEN.EX_").exists());
+    }
+
+    #[test]
+    fn test_expand_directory_rejections() {
+        let plaintext = b"The quick brown fox jumps over the lazy dog.";
+        let input_dir = tempfile::tempdir().unwrap();
+        fs::write(input_dir.path().join("FOX.TX_"), kwaj::compress(plaintext, kwaj::Method::Xored, Some(".."))).unwrap();
+        fs::write(input_dir.path().join("._"), szdd::compress(plaintext, b'.')).unwrap();
+
+        // the output directory must not be within the input directory, even if it does not exist yet
+        for inner in [input_dir.path().to_owned(), input_dir.path().join("OUT"), input_dir.path().join("OUT/MORE")] {
+            let error = expand_directory(input_dir.path(), &inner, UncompressedMode::Copy).unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        }
+        assert!(!input_dir.path().join("OUT").exists());
+
+        // restored names that refer to directories are not used
+        let output_dir = tempfile::tempdir().unwrap();
+        let summary = expand_directory(input_dir.path(), &output_dir.path().join("OUT"), UncompressedMode::Copy).unwrap();
+        assert_eq!(summary, ExpandSummary { expanded: 2, copied: 0, skipped: 0, failed: 0 });
+        assert_eq!(fs::read(output_dir.path().join("OUT/FOX.TX_")).unwrap(), plaintext);
+        assert_eq!(fs::read(output_dir.path().join("OUT/._")).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_list() {
+        let readme: &[u8] = b"Read me!";
+        let library = pe::PortableExecutable::new("SYNTH.DLL").to_bytes();
+        let driver = ne::NewExecutable::new("SYNTH").to_bytes();
+        let compressed_readme = kwaj::compress(readme, kwaj::Method::Stored, Some("README.TXT"));
+        let mut archive = sfx::zip(&[
+            ("README.TXT", readme),
+            ("README.TX_", &compressed_readme),
+            ("SYNTH.DLL", &library),
+            ("SYNTH.DRV", &driver),
+        ]);
+        // damage the data of the first file, after its local header and name
+        archive[30 + 10] ^= 0xFF;
+        let container = interpret_file(sfx::self_extracting(&archive)).unwrap();
+
+        let listed = list(&container, Path::new("SFX.OUT"), 1, false).unwrap();
+        let summary: Vec<(PathBuf, Option<usize>, &str, bool)> = listed.iter()
+            .map(|f| (f.path.clone(), f.size, f.format.as_str(), f.error.is_some()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (PathBuf::from("README.TXT"), None, "unreadable", true),
+                (PathBuf::from("README.TX_"), Some(compressed_readme.len()), "KWAJ compressed file", false),
+                (PathBuf::from("SYNTH.DLL"), Some(library.len()), "PE", false),
+                (PathBuf::from("SYNTH.DRV"), Some(driver.len()), "NE", false),
+            ],
+        );
+        assert_eq!(listed[1].children.len(), 1);
+        assert_eq!(listed[1].children[0].path, PathBuf::from("README.TXT"));
+        assert_eq!(listed[1].children[0].size, Some(readme.len()));
+    }
+
+    #[test]
+    fn test_list_spanned_cabinet_set() {
+        let readme: Vec<u8> = (0..=255u8).cycle().take(700).collect();
+        let files = [cab::File { name: "README.TXT".to_owned(), data: readme.clone() }];
+        let cabinet_name = |i: usize| format!("SYNTH{}.CAB", i + 1);
+        let volumes = cab::spanned_set(&files, 256, 1, cabinet_name);
+        assert!(volumes.len() >= 3);
+        let names: Vec<String> = (0..volumes.len()).map(cabinet_name).collect();
+        let archive_files: Vec<(&str, &[u8])> = names.iter()
+            .map(|n| n.as_str())
+            .zip(volumes.iter().map(|v| v.as_slice()))
+            .collect();
+        let container = interpret_file(sfx::self_extracting(&sfx::zip(&archive_files))).unwrap();
+
+        let listed = list(&container, Path::new("SFX.OUT"), 1, false).unwrap();
+        assert_eq!(listed.len(), volumes.len());
+        assert_eq!(listed[0].format, "CAB container");
+        assert_eq!(listed[0].children.len(), 1);
+        assert_eq!(listed[0].children[0].path, PathBuf::from("README.TXT"));
+        assert_eq!(listed[0].children[0].size, Some(readme.len()));
+        for continuation in &listed[1..] {
+            assert_eq!(continuation.format, "CAB continuation volume");
+            assert!(continuation.children.is_empty());
+        }
+    }
+}
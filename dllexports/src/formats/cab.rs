@@ -1,9 +1,11 @@
+use std::borrow::Cow;
 use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
 use std::io::{self, Cursor, Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
 
 use expandms::DecompressionError;
-use expandms::cab::{CabData, CabFolder, CabHeader, CompressionType, FileInCab, FileInCabAttributes, FolderIndex};
+use expandms::cab::{CabData, CabFlags, CabFolder, CabHeader, CompressionType, FileInCab, FileInCabAttributes, FolderIndex};
 use expandms::inflate::{Inflater, MAX_LOOKBACK_DISTANCE};
 use expandms::ring_buffer::RingBuffer;
 use lzxd::Lzxd;
@@ -14,82 +16,165 @@ use crate::read_ext::ReadExt;
 
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub(crate) struct Cabinet {
-    bytes: Vec<u8>,
+pub(crate) struct Cabinet<'d> {
+    bytes: Cow<'d, [u8]>,
     header: CabHeader,
     folders: Vec<CabFolder>,
     folder_data: Vec<Vec<CabData>>,
     files: Vec<FileInCab>,
     path_to_index: BTreeMap<PathBuf, usize>,
 }
-impl Cabinet {
-    pub fn new(bytes: &[u8]) -> Result<Self, crate::data_mgmt::Error> {
-        let mut reader = Cursor::new(bytes);
-        let header = CabHeader::read(&mut reader)?;
-
-        let mut folders = Vec::with_capacity(header.folder_count.into());
-        for _ in 0..header.folder_count {
-            let folder = CabFolder::read(&mut reader, &header)?;
-            folders.push(folder);
+impl<'d> Cabinet<'d> {
+    pub fn new(bytes: Cow<'d, [u8]>) -> Result<Self, crate::data_mgmt::Error> {
+        let Volume { header, folders, folder_data, files } = Volume::read(bytes.as_ref())?;
+        let mut path_to_index = BTreeMap::new();
+        for (index, file) in files.iter().enumerate() {
+            path_to_index.insert(file_path(file)?, index);
         }
 
-        let mut files = Vec::with_capacity(header.file_count.into());
-        for _ in 0..header.file_count {
-            let file = FileInCab::read(&mut reader)?;
-            files.push(file);
-        }
+        Ok(Self {
+            bytes,
+            header,
+            folders,
+            folder_data,
+            files,
+            path_to_index,
+        })
+    }
 
-        let mut folder_data = Vec::with_capacity(folders.len());
-        for folder in &folders {
-            reader.seek(SeekFrom::Start(folder.start_offset.into()))?;
-            let mut data_vec = Vec::with_capacity(folder.data_count.into());
-            for _ in 0..folder.data_count {
-                let data = CabData::read(&mut reader, &header)?;
-                let compressed_byte_count = data.compressed_byte_count;
-                data_vec.push(data);
+    /// Reads a set of cabinets, e.g. one from each setup disk, as a single cabinet.
+    ///
+    /// The volumes must be given in order. A folder continues into the next volume if one of its
+    /// files does or if its last data block is split (marked by an uncompressed size of 0); the
+    /// first folder of the next volume then holds the rest of its data, and the parts of the split
+    /// block are joined. Files listed in multiple volumes are only listed once.
+    ///
+    /// Files whose data begins before the first volume or ends after the last one still cannot be
+    /// read.
+    pub fn new_spanned(volumes: &[&[u8]]) -> Result<Cabinet<'static>, crate::data_mgmt::Error> {
+        let mut header = None;
+        let mut bytes = Vec::new();
+        let mut folders = Vec::new();
+        let mut folder_data: Vec<Vec<CabData>> = Vec::new();
+        let mut files = Vec::new();
+
+        let mut split_block: Option<Vec<u8>> = None;
+        let mut continues_to_next = false;
+        for (volume_index, volume_bytes) in volumes.iter().enumerate() {
+            let volume = Volume::read(volume_bytes)?;
+            let is_last_volume = volume_index == volumes.len() - 1;
+
+            // the data of the first folder of the first volume may have begun in a cabinet we
+            // do not have; since file offsets are counted from the start of the whole folder,
+            // none of the files in it can be read then
+            let is_missing_start = volume_index == 0
+                && volume.header.flags.contains(CabFlags::PREV_CABINET)
+                && volume.files.iter().any(|f| matches!(
+                    f.folder_index,
+                    FolderIndex::ContinuedFromPrevious | FolderIndex::ContinuedPreviousAndNext,
+                ));
+
+            // map the folders of this volume to those of the whole set
+            let mut set_folder_indexes = Vec::with_capacity(volume.folders.len());
+            for (folder_index, (folder, data_blocks)) in volume.folders.into_iter().zip(volume.folder_data).enumerate() {
+                if folder_index == 0 && continues_to_next && !folders.is_empty() {
+                    set_folder_indexes.push(folders.len() - 1);
+                } else {
+                    set_folder_indexes.push(folders.len());
+                    folders.push(folder);
+                    folder_data.push(Vec::new());
+                }
 
-                // skip the compressed data
-                reader.seek(SeekFrom::Current(compressed_byte_count.into()))?;
+                let set_data_blocks = folder_data.last_mut().unwrap();
+                for data_block in data_blocks {
+                    let data_range = data_block.data_offset..data_block.data_offset+usize::from(data_block.compressed_byte_count);
+                    let Some(block_bytes) = volume_bytes.get(data_range) else {
+                        return Err(crate::data_mgmt::Error::Io(io::ErrorKind::UnexpectedEof.into()));
+                    };
+                    let mut joined_bytes = split_block.take().unwrap_or_default();
+                    joined_bytes.extend_from_slice(block_bytes);
+                    if data_block.uncompressed_byte_count == 0 {
+                        // the rest of this block is at the start of the next volume
+                        split_block = Some(joined_bytes);
+                        continue;
+                    }
+
+                    let compressed_byte_count = u16::try_from(joined_bytes.len())
+                        .map_err(|_| crate::data_mgmt::Error::Io(io::ErrorKind::InvalidData.into()))?;
+                    set_data_blocks.push(CabData {
+                        compressed_byte_count,
+                        data_offset: bytes.len(),
+                        ..data_block
+                    });
+                    bytes.extend_from_slice(&joined_bytes);
+                }
+            }
+
+            continues_to_next = split_block.is_some() || volume.files.iter().any(|f| matches!(
+                f.folder_index,
+                FolderIndex::ContinuedToNext | FolderIndex::ContinuedPreviousAndNext,
+            ));
+
+            for mut file in volume.files {
+                let set_folder_index = match file.folder_index {
+                    FolderIndex::RegularIndex(0)|FolderIndex::ContinuedFromPrevious if is_missing_start
+                        => None,
+                    FolderIndex::ContinuedToNext|FolderIndex::ContinuedPreviousAndNext if is_last_volume
+                        => None,
+                    FolderIndex::ContinuedPreviousAndNext if is_missing_start
+                        => None,
+                    FolderIndex::RegularIndex(i) => Some(set_folder_indexes.get(usize::from(i)).copied()
+                        .ok_or_else(|| crate::data_mgmt::Error::Io(io::ErrorKind::InvalidData.into()))?),
+                    FolderIndex::ContinuedFromPrevious|FolderIndex::ContinuedPreviousAndNext
+                        => set_folder_indexes.first().copied(),
+                    FolderIndex::ContinuedToNext
+                        => set_folder_indexes.last().copied(),
+                };
+                match set_folder_index {
+                    Some(i) => {
+                        let i = u16::try_from(i)
+                            .map_err(|_| crate::data_mgmt::Error::Io(io::ErrorKind::InvalidData.into()))?;
+                        file.folder_index = FolderIndex::RegularIndex(i);
+                    },
+                    None => {
+                        // keep the file listed, but reading it fails with SpannedFile
+                        file.folder_index = FolderIndex::ContinuedPreviousAndNext;
+                    },
+                }
+                files.push(file);
+            }
+
+            if header.is_none() {
+                header = Some(volume.header);
             }
-            folder_data.push(data_vec);
         }
 
         let mut path_to_index = BTreeMap::new();
         for (index, file) in files.iter().enumerate() {
-            let path_string = if file.attributes.contains(FileInCabAttributes::UTF8_NAME) {
-                // convert from UTF-8 bytes to PathBuf
-                String::from_utf8(file.name.clone())
-                    .map_err(|e| crate::data_mgmt::Error::InvalidUtf8FileName(e.into_bytes()))?
-            } else {
-                // locale-specific encoding, great
-                // just do the naive ISO 8859-1 thing
-                file.name.iter()
-                    .map(|b| char::from_u32((*b).into()).unwrap())
-                    .collect()
-            };
-            let path = PathBuf::from(path_string);
-            path_to_index.insert(path, index);
+            // a file spanning volumes is listed in each of them; the first entry suffices unless
+            // another one shows that the file cannot be completed
+            let is_incomplete = !matches!(file.folder_index, FolderIndex::RegularIndex(_));
+            match path_to_index.entry(file_path(file)?) {
+                Entry::Vacant(entry) => {
+                    entry.insert(index);
+                },
+                Entry::Occupied(mut entry) => if is_incomplete {
+                    entry.insert(index);
+                },
+            }
         }
 
-        Ok(Self {
-            bytes: bytes.to_vec(),
-            header,
+        Ok(Cabinet {
+            bytes: Cow::Owned(bytes),
+            header: header.unwrap_or_default(),
             folders,
             folder_data,
             files,
             path_to_index,
         })
     }
-}
-impl MultiFileContainer for Cabinet {
-    fn list_files(&self) -> Result<Vec<PathBuf>, crate::data_mgmt::Error> {
-        let files = self.path_to_index.keys()
-            .map(|k| k.clone())
-            .collect();
-        Ok(files)
-    }
 
-    fn read_file(&self, file_path: &Path) -> Result<Vec<u8>, crate::data_mgmt::Error> {
+    fn decompress_file(&self, file_path: &Path) -> Result<Vec<u8>, crate::data_mgmt::Error> {
         let Some(&index) = self.path_to_index.get(file_path) else {
             return Err(crate::data_mgmt::Error::FileNotFound(file_path.to_owned()));
         };
@@ -109,6 +194,8 @@ impl MultiFileContainer for Cabinet {
                     file.uncompressed_size_bytes.try_into().unwrap(),
                 );
                 for data_block in &self.folder_data[folder_index] {
+                    binstruct::deadline::check()?;
+
                     // make a "decompressor"
                     let data_slice_length = usize::from(data_block.compressed_byte_count);
                     let slice = &self.bytes[data_block.data_offset..data_block.data_offset+data_slice_length];
@@ -142,6 +229,8 @@ impl MultiFileContainer for Cabinet {
                 );
                 let mut lookback = RingBuffer::new(0x00, MAX_LOOKBACK_DISTANCE);
                 for data_block in &self.folder_data[folder_index] {
+                    binstruct::deadline::check()?;
+
                     // make a decompressor
 
                     let data_slice_length = usize::from(data_block.compressed_byte_count);
@@ -169,7 +258,7 @@ impl MultiFileContainer for Cabinet {
                         }
                     }
                     // we will need this for the next pass
-                    lookback = block_decompressor.inflater().unwrap().lookback().clone();
+                    lookback = block_decompressor.into_inflater().unwrap().into_lookback();
                 }
 
                 // see above
@@ -185,7 +274,7 @@ impl MultiFileContainer for Cabinet {
 
                 // make the magic reader that reads all blocks
                 let all_blocks_reader = CabFolderReader::new(
-                    Cursor::new(self.bytes.as_slice()),
+                    Cursor::new(self.bytes.as_ref()),
                     self.folder_data[folder_index].clone(),
                 );
 
@@ -209,10 +298,11 @@ impl MultiFileContainer for Cabinet {
 
                 let mut block_decompressor = FileDecompressor::Lzx {
                     decompressor,
-                    reader: Cursor::new(self.bytes.as_slice()),
+                    reader: Cursor::new(self.bytes.as_ref()),
                     folder_data: self.folder_data[folder_index].clone(),
                 };
                 loop {
+                    binstruct::deadline::check()?;
                     match collector.read(&mut block_decompressor)? {
                         FileReadStatus::ReadProgress => {},
                         FileReadStatus::FileComplete => return Ok(collector.decompressed_buffer),
@@ -228,6 +318,86 @@ impl MultiFileContainer for Cabinet {
         }
     }
 }
+impl<'d> MultiFileContainer for Cabinet<'d> {
+    fn format_name(&self) -> &str {
+        "CAB"
+    }
+
+    fn list_files(&self) -> Result<Vec<PathBuf>, crate::data_mgmt::Error> {
+        let files = self.path_to_index.keys()
+            .map(|k| k.clone())
+            .collect();
+        Ok(files)
+    }
+
+    fn read_file(&self, file_path: &Path) -> Result<Cow<'_, [u8]>, crate::data_mgmt::Error> {
+        self.decompress_file(file_path)
+            .map(Cow::Owned)
+    }
+}
+
+/// The structures of a single cabinet file.
+struct Volume {
+    header: CabHeader,
+    folders: Vec<CabFolder>,
+    folder_data: Vec<Vec<CabData>>,
+    files: Vec<FileInCab>,
+}
+impl Volume {
+    fn read(bytes: &[u8]) -> Result<Self, crate::data_mgmt::Error> {
+        let mut reader = Cursor::new(bytes);
+        let header = CabHeader::read(&mut reader)?;
+
+        let mut folders = Vec::with_capacity(header.folder_count.into());
+        for _ in 0..header.folder_count {
+            let folder = CabFolder::read(&mut reader, &header)?;
+            folders.push(folder);
+        }
+
+        let mut files = Vec::with_capacity(header.file_count.into());
+        for _ in 0..header.file_count {
+            let file = FileInCab::read(&mut reader)?;
+            files.push(file);
+        }
+
+        let mut folder_data = Vec::with_capacity(folders.len());
+        for folder in &folders {
+            reader.seek(SeekFrom::Start(folder.start_offset.into()))?;
+            let mut data_vec = Vec::with_capacity(folder.data_count.into());
+            for _ in 0..folder.data_count {
+                let data = CabData::read(&mut reader, &header)?;
+                let compressed_byte_count = data.compressed_byte_count;
+                data_vec.push(data);
+
+                // skip the compressed data
+                reader.seek(SeekFrom::Current(compressed_byte_count.into()))?;
+            }
+            folder_data.push(data_vec);
+        }
+
+        Ok(Self {
+            header,
+            folders,
+            folder_data,
+            files,
+        })
+    }
+}
+
+fn file_path(file: &FileInCab) -> Result<PathBuf, crate::data_mgmt::Error> {
+    let path_string = if file.attributes.contains(FileInCabAttributes::UTF8_NAME) {
+        // convert from UTF-8 bytes to PathBuf
+        String::from_utf8(file.name.clone())
+            .map_err(|e| crate::data_mgmt::Error::InvalidUtf8FileName(e.into_bytes()))?
+    } else {
+        // locale-specific encoding, great
+        // just do the naive ISO 8859-1 thing
+        file.name.iter()
+            .map(|b| char::from_u32((*b).into()).unwrap())
+            .collect()
+    };
+    Ok(PathBuf::from(path_string))
+}
 
 struct FileCollector {
     pub file_start: usize,
@@ -328,7 +498,7 @@ impl<'r> FileDecompressor<'r> {
         }
     }
 
-    pub fn inflater(&self) -> Option<&Inflater<'r, Cursor<&'r [u8]>>> {
+    pub fn into_inflater(self) -> Option<Inflater<'r, Cursor<&'r [u8]>>> {
         match self {
             Self::NoCompression(_) => None,
             Self::MsZip { inflater, .. } => Some(inflater),
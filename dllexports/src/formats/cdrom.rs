@@ -1,21 +1,27 @@
+use std::borrow::Cow;
 use std::collections::BTreeMap;
-use std::io::{Cursor, Error, Read, Seek, SeekFrom};
+use std::io::{Cursor, Error, ErrorKind, Read, Seek, SeekFrom};
 use std::path::PathBuf;
 
-use expandms::iso9660::{DirectoryRecord, FileFlags, VolumeDescriptor};
+use expandms::iso9660::{
+    BootRecord, DescriptorType, DirectoryRecord, FileFlags, PartitionDescriptor, VolumeDescriptor,
+    BYTES_PER_LOGICAL_SECTOR, DATA_AREA_OFFSET,
+};
+use tracing::warn;
 
-use crate::data_mgmt::MultiFileContainer;
+use crate::data_mgmt::{ContainerEntry, MultiFileContainer};
+use crate::schema::{CdDescriptorKind, CdVolumeDescriptor};
 
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub struct Cdrom {
-    pub data: Vec<u8>,
+pub struct Cdrom<'d> {
+    pub data: Cow<'d, [u8]>,
     pub path_to_entry: BTreeMap<PathBuf, FileEntry>,
 }
-impl Cdrom {
-    fn new_from_data(data: &[u8], is_high_sierra: bool) -> Result<Self, Error> {
+impl<'d> Cdrom<'d> {
+    fn new_from_data(data: Cow<'d, [u8]>, is_high_sierra: bool) -> Result<Self, Error> {
         // read basic volume descriptor
-        let mut reader = Cursor::new(data);
+        let mut reader = Cursor::new(data.as_ref());
         reader.seek(SeekFrom::Start(0x8000))?;
         let vd = VolumeDescriptor::read(&mut reader, is_high_sierra)?;
         let block_size = vd.logical_block_size.little_endian;
@@ -28,6 +34,7 @@ impl Cdrom {
         }];
         let mut path_to_entry = BTreeMap::new();
         while let Some(directory) = directory_stack.pop() {
+            binstruct::deadline::check()?;
             reader.seek(SeekFrom::Start(directory.offset))?;
             let mut directory_bytes = vec![0u8; directory.size];
             reader.read_exact(&mut directory_bytes)?;
@@ -79,20 +86,33 @@ impl Cdrom {
             }
         }
         Ok(Cdrom {
-            data: data.to_vec(),
+            data,
             path_to_entry,
         })
     }
 
-    pub fn new_from_iso9660_data(data: &[u8]) -> Result<Self, Error> {
+    pub fn new_from_iso9660_data(data: Cow<'d, [u8]>) -> Result<Self, Error> {
         Self::new_from_data(data, false)
     }
 
-    pub fn new_from_high_sierra_data(data: &[u8]) -> Result<Self, Error> {
+    pub fn new_from_high_sierra_data(data: Cow<'d, [u8]>) -> Result<Self, Error> {
         Self::new_from_data(data, true)
     }
+
+    /// Returns a view of the image containing the file, as files are stored contiguously.
+    fn entry_data(&self, entry: &FileEntry) -> Result<&[u8], Error> {
+        let start = usize::try_from(entry.offset)
+            .map_err(|_| Error::from(ErrorKind::UnexpectedEof))?;
+        start.checked_add(entry.size)
+            .and_then(|end| self.data.get(start..end))
+            .ok_or_else(|| Error::from(ErrorKind::UnexpectedEof))
+    }
 }
-impl MultiFileContainer for Cdrom {
+impl<'d> MultiFileContainer for Cdrom<'d> {
+    fn format_name(&self) -> &str {
+        "CD-ROM"
+    }
+
     fn list_files(&self) -> Result<Vec<PathBuf>, crate::data_mgmt::Error> {
         let files = self.path_to_entry
             .keys()
@@ -101,14 +121,20 @@ impl MultiFileContainer for Cdrom {
         Ok(files)
     }
 
-    fn read_file(&self, file_path: &std::path::Path) -> Result<Vec<u8>, crate::data_mgmt::Error> {
+    fn read_file(&self, file_path: &std::path::Path) -> Result<Cow<'_, [u8]>, crate::data_mgmt::Error> {
         let entry = self.path_to_entry.get(file_path)
             .ok_or_else(|| crate::data_mgmt::Error::FileNotFound(file_path.to_owned()))?;
-        let mut cursor = Cursor::new(&self.data);
-        cursor.seek(SeekFrom::Start(entry.offset))?;
-        let mut buf = vec![0u8; entry.size];
-        cursor.read_exact(&mut buf)?;
-        Ok(buf)
+        Ok(Cow::Borrowed(self.entry_data(entry)?))
+    }
+
+    fn entries(&self) -> Result<Box<dyn Iterator<Item = ContainerEntry<'_>> + '_>, crate::data_mgmt::Error> {
+        Ok(Box::new(self.path_to_entry.iter().map(move |(path, entry)| {
+            ContainerEntry::new(
+                path.clone(),
+                Some(u64::try_from(entry.size).unwrap()),
+                move || Ok(Cow::Borrowed(self.entry_data(entry)?)),
+            )
+        })))
     }
 }
 
@@ -124,3 +150,143 @@ pub struct FileEntry {
     pub offset: u64,
     pub size: usize,
 }
+
+
+/// The byte offset of the descriptor with the given number in the volume descriptor sequence of a
+/// CD image.
+pub(crate) fn descriptor_offset(number: u64) -> u64 {
+    DATA_AREA_OFFSET + number * BYTES_PER_LOGICAL_SECTOR
+}
+
+
+/// Reads the volume descriptor sequence of a CD image up to and including the set terminator.
+///
+/// If the image ends or a sector lacks the standard identifier before the terminator is reached,
+/// the descriptors read until then are returned.
+pub(crate) fn read_volume_descriptors<R: Read + Seek>(reader: &mut R, is_high_sierra: bool) -> Result<Vec<CdVolumeDescriptor>, Error> {
+    let mut descriptors = Vec::new();
+    for number in 0.. {
+        let offset = descriptor_offset(number);
+        reader.seek(SeekFrom::Start(offset))?;
+        let mut sector = vec![0u8; 2048];
+        if let Err(e) = reader.read_exact(&mut sector) {
+            if e.kind() == ErrorKind::UnexpectedEof {
+                warn!("image ends before the set terminator");
+                break;
+            }
+            return Err(e);
+        }
+        let Some((vd_type, version)) = DescriptorType::of_descriptor(&sector, is_high_sierra) else {
+            warn!("descriptor {} lacks the standard identifier; the sequence is not terminated", number);
+            break;
+        };
+
+        let kind = CdDescriptorKind::new(vd_type, version);
+        let (system_identifier, identifier, block_count) = match kind {
+            CdDescriptorKind::BootRecord => {
+                let br = BootRecord::read(&mut sector.as_slice(), is_high_sierra)?;
+                (
+                    Some(identifier_text(br.boot_system_identifier.as_ref(), false)),
+                    Some(identifier_text(br.boot_identifier.as_ref(), false)),
+                    None,
+                )
+            },
+            CdDescriptorKind::Primary|CdDescriptorKind::Supplementary|CdDescriptorKind::Enhanced => {
+                let vd = VolumeDescriptor::read(&mut sector.as_slice(), is_high_sierra)?;
+                // Joliet stores identifiers as UCS-2 and announces this with an escape sequence
+                let is_ucs2 = kind != CdDescriptorKind::Primary
+                    && vd.escape_sequences.as_ref().starts_with(b"%/");
+                (
+                    Some(identifier_text(vd.system_identifier.as_ref(), is_ucs2)),
+                    Some(identifier_text(vd.volume_identifier.as_ref(), is_ucs2)),
+                    Some(vd.volume_space_size.little_endian),
+                )
+            },
+            CdDescriptorKind::Partition => {
+                let pd = PartitionDescriptor::read(&mut sector.as_slice(), is_high_sierra)?;
+                (
+                    Some(identifier_text(pd.system_identifier.as_ref(), false)),
+                    Some(identifier_text(pd.partition_identifier.as_ref(), false)),
+                    Some(pd.partition_size.little_endian),
+                )
+            },
+            CdDescriptorKind::Terminator|CdDescriptorKind::Other => (None, None, None),
+        };
+
+        descriptors.push(CdVolumeDescriptor {
+            number,
+            offset,
+            kind,
+            type_code: vd_type.to_base_type(),
+            version,
+            system_identifier,
+            identifier,
+            block_count,
+        });
+        if kind == CdDescriptorKind::Terminator {
+            break;
+        }
+    }
+    Ok(descriptors)
+}
+
+
+/// Decodes an identifier from a CD volume descriptor, removing the padding.
+fn identifier_text(bytes: &[u8], is_ucs2: bool) -> String {
+    let text = if is_ucs2 {
+        let units: Vec<u16> = bytes.chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        String::from_utf8_lossy(bytes).into_owned()
+    };
+    text.trim_end_matches([' ', '\0'])
+        .to_owned()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use testgen::iso9660;
+
+    use super::{read_volume_descriptors, Cdrom};
+    use crate::data_mgmt::MultiFileContainer;
+    use crate::schema::CdDescriptorKind;
+
+    #[test]
+    fn test_read_volume_descriptors() {
+        let mut volume = iso9660::Volume::new("DESCRIPTORS");
+        volume.files.push(iso9660::File { name: "README.TXT".to_owned(), data: b"read me".to_vec() });
+        volume.boot_record = Some(iso9660::BootRecord {
+            boot_system_id: "EL TORITO SPECIFICATION".to_owned(),
+            boot_id: String::new(),
+        });
+        volume.joliet_volume_id = Some("Descriptors".to_owned());
+        let image = volume.to_bytes();
+        let block_count = u32::try_from(image.len() / 2048).unwrap();
+
+        let descriptors = read_volume_descriptors(&mut Cursor::new(&image), false).unwrap();
+        let summary: Vec<_> = descriptors.iter()
+            .map(|d| (d.number, d.offset, d.kind, d.type_code, d.identifier.as_deref(), d.block_count))
+            .collect();
+        assert_eq!(summary, [
+            (0, 0x8000, CdDescriptorKind::Primary, 0x01, Some("DESCRIPTORS"), Some(block_count)),
+            (1, 0x8800, CdDescriptorKind::BootRecord, 0x00, Some(""), None),
+            (2, 0x9000, CdDescriptorKind::Supplementary, 0x02, Some("Descriptors"), Some(block_count)),
+            (3, 0x9800, CdDescriptorKind::Terminator, 0xFF, None, None),
+        ]);
+        assert_eq!(descriptors[1].system_identifier.as_deref(), Some("EL TORITO SPECIFICATION"));
+        assert_eq!(descriptors[2].system_identifier.as_deref(), Some(""));
+
+        // the additional descriptors do not get in the way of the files
+        let cdrom = Cdrom::new_from_data(image.as_slice().into(), false).unwrap();
+        assert_eq!(cdrom.read_file("README.TXT;1".as_ref()).unwrap().as_ref(), b"read me");
+
+        // a sequence cut short ends with the last complete descriptor
+        let descriptors = read_volume_descriptors(&mut Cursor::new(&image[..0x9400]), false).unwrap();
+        assert_eq!(descriptors.len(), 2);
+    }
+}
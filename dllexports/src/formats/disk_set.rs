@@ -0,0 +1,362 @@
+//! Sets of numbered floppy disk images, e.g. `DISK01.IMG` to `DISK13.IMG`.
+//!
+//! Setup programs split large cabinets and compressed files across disks. Reading the images of a
+//! set together allows these to be reassembled from the pieces on each disk.
+
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use tracing::debug;
+
+use crate::data_mgmt::{Error, MultiFileContainer, SingleFileContainer};
+use crate::formats::cab::Cabinet;
+use crate::formats::fat::FatFileSystem;
+use crate::formats::single_compression::KwajOrSz;
+use crate::volumes::{cabinet_set, is_cabinet_continuation, szdd_parts, ContainerSiblings};
+
+
+/// The extensions of floppy disk image files, in lowercase.
+const IMAGE_EXTENSIONS: [&str; 4] = ["img", "ima", "vfd", "dsk"];
+
+
+/// Groups the floppy disk images among the given paths into sets, each ordered by disk number.
+///
+/// Images belong to the same set if they are in the same directory and their names only differ in
+/// the number before the extension (compared case-insensitively). Images without a number and sets
+/// of a single image are left out.
+pub(crate) fn find_disk_sets(paths: &[PathBuf]) -> Vec<Vec<PathBuf>> {
+    let mut key_to_images: BTreeMap<(PathBuf, String, String), Vec<(u32, PathBuf)>> = BTreeMap::new();
+    for path in paths {
+        let Some(extension) = path.extension().map(|e| e.to_string_lossy().to_ascii_lowercase()) else {
+            continue;
+        };
+        if !IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+            continue;
+        }
+        let Some(stem) = path.file_stem().map(|s| s.to_string_lossy()) else {
+            continue;
+        };
+        let prefix = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+        let Ok(number) = stem[prefix.len()..].parse::<u32>() else {
+            continue;
+        };
+        let dir = path.parent().unwrap_or(Path::new("")).to_owned();
+        key_to_images.entry((dir, prefix.to_ascii_lowercase(), extension))
+            .or_default()
+            .push((number, path.clone()));
+    }
+
+    key_to_images.into_values()
+        .filter(|images| images.len() > 1)
+        .map(|mut images| {
+            images.sort();
+            images.into_iter().map(|(_, path)| path).collect()
+        })
+        .collect()
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum Entry {
+    /// A file stored whole on one disk.
+    Stored { image_index: usize, path: PathBuf },
+
+    /// A file in a set of cabinets spanning multiple disks.
+    InSpannedCabinet { cabinet_index: usize, path: PathBuf },
+
+    /// A compressed file split into parts on multiple disks.
+    Split { split_index: usize },
+}
+
+
+/// The files on a set of floppy disk images.
+///
+/// Each file is listed under the name of the image it is on, followed by its path within the image.
+/// The files in a set of cabinets spanning multiple disks are listed below the first cabinet of the
+/// set (e.g. `DISK01.IMG/SETUP.CAB/SETUP.EXE`), with the cabinets themselves left out. An SZDD file
+/// split into parts is listed under the name of its first part and read expanded, since it cannot
+/// be expanded from the data on any single disk; its later parts are left out.
+pub(crate) struct DiskSet {
+    image_names: Vec<String>,
+    images: Vec<FatFileSystem<'static>>,
+    cabinets: Vec<Cabinet<'static>>,
+    split_files: Vec<KwajOrSz<'static>>,
+    path_to_entry: BTreeMap<PathBuf, Entry>,
+}
+impl DiskSet {
+    /// Reads a set from the names and data of its images, which must be given in disk order.
+    ///
+    /// Images that cannot be read as FAT file systems are left out of the set, so that the files on
+    /// the other disks can still be read; they are returned along with their errors, by their index
+    /// in `images`.
+    pub fn new(images: Vec<(String, Vec<u8>)>) -> (Self, Vec<(usize, Error)>) {
+        let mut image_names = Vec::with_capacity(images.len());
+        let mut file_systems = Vec::with_capacity(images.len());
+        let mut image_files = Vec::with_capacity(images.len());
+        let mut failed_images = Vec::new();
+        for (index, (name, data)) in images.into_iter().enumerate() {
+            let file_system_and_files = FatFileSystem::new(Cow::Owned(data))
+                .and_then(|fs| fs.list_files().map(|files| (fs, files)));
+            match file_system_and_files {
+                Ok((file_system, files)) => {
+                    image_names.push(name);
+                    file_systems.push(file_system);
+                    image_files.push(files);
+                },
+                Err(e) => {
+                    debug!("leaving {:?} out of the disk set: {}", name, e);
+                    failed_images.push((index, e));
+                },
+            }
+        }
+
+        let mut cabinets = Vec::new();
+        let mut split_files = Vec::new();
+        let mut path_to_entry = BTreeMap::new();
+        // the directories (on any disk) and lowercase names of the later parts of split files
+        let mut continuations: BTreeSet<(PathBuf, String)> = BTreeSet::new();
+        for (image_index, image_name) in image_names.iter().enumerate() {
+            for path in &image_files[image_index] {
+                let set_path = Path::new(image_name).join(path);
+                let name = path.file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let dir = path.parent().unwrap_or(Path::new("")).to_owned();
+                if continuations.contains(&(dir.clone(), name.to_ascii_lowercase())) {
+                    // listed along with the first part
+                    continue;
+                }
+
+                let data = match file_systems[image_index].read_file(path) {
+                    Ok(d) => d,
+                    Err(e) => {
+                        // leave reporting the error to whoever reads the file
+                        debug!("failed to read {:?} while looking for split files: {}", set_path, e);
+                        path_to_entry.insert(set_path, Entry::Stored { image_index, path: path.clone() });
+                        continue;
+                    },
+                };
+
+                // look in the same directory on this disk, then on all the others in order
+                let siblings: Vec<ContainerSiblings> = std::iter::once(image_index)
+                    .chain((0..file_systems.len()).filter(|i| *i != image_index))
+                    .map(|i| ContainerSiblings::new(&file_systems[i], &image_files[i], path))
+                    .collect();
+
+                if data.starts_with(b"MSCF") {
+                    if is_cabinet_continuation(&data, &siblings) {
+                        // listed along with the first cabinet of the set
+                        continue;
+                    }
+                    if let Some(volumes) = cabinet_set(&data, &name, &siblings) {
+                        let volume_slices: Vec<&[u8]> = volumes.iter()
+                            .map(|v| v.as_slice())
+                            .collect();
+                        let cabinet_and_files = Cabinet::new_spanned(&volume_slices)
+                            .and_then(|cabinet| cabinet.list_files().map(|files| (cabinet, files)));
+                        match cabinet_and_files {
+                            Ok((cabinet, cabinet_files)) => {
+                                let cabinet_index = cabinets.len();
+                                for cabinet_path in cabinet_files {
+                                    path_to_entry.insert(
+                                        set_path.join(&cabinet_path),
+                                        Entry::InSpannedCabinet { cabinet_index, path: cabinet_path },
+                                    );
+                                }
+                                cabinets.push(cabinet);
+                                continue;
+                            },
+                            Err(e) => {
+                                // list the cabinet itself instead, which reports the error when read
+                                debug!("failed to read the cabinet set starting with {:?}: {}", set_path, e);
+                            },
+                        }
+                    }
+                } else if let Some(joined) = szdd_parts(&data, &name, &siblings) {
+                    for continuation_name in joined.continuation_names {
+                        continuations.insert((dir.clone(), continuation_name.to_ascii_lowercase()));
+                    }
+                    let split_index = split_files.len();
+                    split_files.push(KwajOrSz::new(joined.data));
+                    path_to_entry.insert(set_path, Entry::Split { split_index });
+                    continue;
+                }
+
+                path_to_entry.insert(set_path, Entry::Stored { image_index, path: path.clone() });
+            }
+        }
+
+        if cabinets.is_empty() && split_files.is_empty() {
+            debug!("no files spanning multiple disks found in the set {:?}", image_names);
+        }
+        let disk_set = Self {
+            image_names,
+            images: file_systems,
+            cabinets,
+            split_files,
+            path_to_entry,
+        };
+        (disk_set, failed_images)
+    }
+}
+impl fmt::Debug for DiskSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DiskSet")
+            .field("path_to_entry", &self.path_to_entry)
+            .finish_non_exhaustive()
+    }
+}
+impl MultiFileContainer for DiskSet {
+    fn format_name(&self) -> &str {
+        "disk set"
+    }
+
+    /// The boot sector fingerprint of the disks, along with the name of each disk if they differ.
+    fn remark(&self) -> Option<String> {
+        let remarks: Vec<Option<String>> = self.images.iter()
+            .map(|image| image.remark())
+            .collect();
+        let first_remark = remarks.first()?.clone();
+        if remarks.iter().all(|remark| *remark == first_remark) {
+            return first_remark;
+        }
+        let per_disk: Vec<String> = self.image_names.iter()
+            .zip(remarks)
+            .filter_map(|(name, remark)| Some(format!("{}: {}", name, remark?)))
+            .collect();
+        Some(per_disk.join("; "))
+    }
+
+    fn list_files(&self) -> Result<Vec<PathBuf>, Error> {
+        Ok(self.path_to_entry.keys().cloned().collect())
+    }
+
+    fn read_file(&self, file_path: &Path) -> Result<Cow<'_, [u8]>, Error> {
+        let entry = self.path_to_entry.get(file_path)
+            .ok_or_else(|| Error::FileNotFound(file_path.to_owned()))?;
+        match entry {
+            Entry::Stored { image_index, path }
+                => self.images[*image_index].read_file(path),
+            Entry::InSpannedCabinet { cabinet_index, path }
+                => self.cabinets[*cabinet_index].read_file(path),
+            Entry::Split { split_index }
+                => self.split_files[*split_index].read_file(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{find_disk_sets, DiskSet};
+    use crate::data_mgmt::MultiFileContainer;
+    use testgen::{cab, fat, szdd};
+
+    #[test]
+    fn test_find_disk_sets() {
+        let paths: Vec<PathBuf> = [
+            "win31/DISK02.IMG", "win31/disk01.img", "win31/DISK10.IMG", "win31/README.TXT",
+            "win31/BOOT.IMG", "office/DISK1.VFD", "office/DISK2.VFD", "office/DISK1.IMG",
+        ].into_iter().map(PathBuf::from).collect();
+        assert_eq!(
+            find_disk_sets(&paths),
+            vec![
+                vec![PathBuf::from("office/DISK1.VFD"), PathBuf::from("office/DISK2.VFD")],
+                vec![PathBuf::from("win31/disk01.img"), PathBuf::from("win31/DISK02.IMG"), PathBuf::from("win31/DISK10.IMG")],
+            ],
+        );
+    }
+
+    #[test]
+    fn test_reassemble_across_disks() {
+        let library: Vec<u8> = (0..=255u8).cycle().take(1500).collect();
+        let big: Vec<u8> = (0..=255u8).rev().cycle().take(900).collect();
+        let cabinet_name = |i: usize| if i == 0 { "SETUP.CAB".to_owned() } else { format!("SETUP{}.CAB", i + 1) };
+        let cabinets = cab::spanned_set(
+            &[cab::File { name: "SYNTH.DLL".to_owned(), data: library.clone() }],
+            256, 3, cabinet_name,
+        );
+        assert_eq!(cabinets.len(), 2);
+        let compressed = szdd::compress(&big, b'L');
+        let (big_first, big_second) = compressed.split_at(compressed.len() / 2);
+
+        let file = |name: &str, data: &[u8]| fat::File { name: name.to_owned(), data: data.to_vec() };
+        let images = vec![
+            ("DISK1.IMG".to_owned(), fat::floppy("DISK1", &[file("README.TXT", b"hello"), file("SETUP.CAB", &cabinets[0])])),
+            ("DISK2.IMG".to_owned(), fat::floppy("DISK2", &[file("SETUP2.CAB", &cabinets[1]), file("BIG.DL_", big_first)])),
+            ("DISK3.IMG".to_owned(), fat::floppy("DISK3", &[file("BIG2.DL_", big_second)])),
+        ];
+        let (disk_set, failed_images) = DiskSet::new(images);
+        assert!(failed_images.is_empty());
+
+        assert_eq!(
+            disk_set.list_files().unwrap(),
+            vec![
+                PathBuf::from("DISK1.IMG/README.TXT"),
+                PathBuf::from("DISK1.IMG/SETUP.CAB/SYNTH.DLL"),
+                PathBuf::from("DISK2.IMG/BIG.DL_"),
+            ],
+        );
+        assert_eq!(disk_set.read_file(Path::new("DISK1.IMG/README.TXT")).unwrap().as_ref(), b"hello");
+        assert_eq!(disk_set.read_file(Path::new("DISK1.IMG/SETUP.CAB/SYNTH.DLL")).unwrap().as_ref(), library.as_slice());
+        assert_eq!(disk_set.read_file(Path::new("DISK2.IMG/BIG.DL_")).unwrap().as_ref(), big.as_slice());
+    }
+
+    #[test]
+    fn test_skip_broken_image() {
+        let compressed = szdd::compress(&[0x55; 700], b'L');
+        let (first_part, second_part) = compressed.split_at(compressed.len() / 2);
+        let file = |name: &str, data: &[u8]| fat::File { name: name.to_owned(), data: data.to_vec() };
+        let images = vec![
+            ("DISK1.IMG".to_owned(), fat::floppy("DISK1", &[file("README.TXT", b"hello"), file("BIG.DL_", first_part)])),
+            ("DISK2.IMG".to_owned(), b"not a disk image".to_vec()),
+            ("DISK3.IMG".to_owned(), fat::floppy("DISK3", &[file("BIG2.DL_", second_part), file("OTHER.TXT", b"other")])),
+        ];
+        let (disk_set, failed_images) = DiskSet::new(images);
+        assert_eq!(failed_images.len(), 1);
+        assert_eq!(failed_images[0].0, 1);
+
+        assert_eq!(
+            disk_set.list_files().unwrap(),
+            vec![
+                PathBuf::from("DISK1.IMG/BIG.DL_"),
+                PathBuf::from("DISK1.IMG/README.TXT"),
+                PathBuf::from("DISK3.IMG/OTHER.TXT"),
+            ],
+        );
+        assert_eq!(disk_set.read_file(Path::new("DISK1.IMG/BIG.DL_")).unwrap().as_ref(), [0x55; 700].as_slice());
+        assert_eq!(disk_set.read_file(Path::new("DISK3.IMG/OTHER.TXT")).unwrap().as_ref(), b"other");
+    }
+
+    #[test]
+    fn test_boot_sector_remark() {
+        let mut boot_disk = fat::floppy("DISK1", &[]);
+        boot_disk[0x100..0x116].copy_from_slice(b"IO      SYSMSDOS   SYS");
+        let images = vec![
+            ("DISK1.IMG".to_owned(), boot_disk),
+            ("DISK2.IMG".to_owned(), fat::floppy("DISK2", &[])),
+        ];
+        let (disk_set, _) = DiskSet::new(images);
+        assert_eq!(
+            disk_set.remark().as_deref(),
+            Some(concat!(
+                "DISK1.IMG: boot sector: OEM name \"MSDOS5.0\" (MS-DOS 5.0 or later, or Windows NT), boots MS-DOS (IO.SYS); ",
+                "DISK2.IMG: boot sector: OEM name \"MSDOS5.0\" (MS-DOS 5.0 or later, or Windows NT), boot code not recognized",
+            )),
+        );
+
+        let images = vec![
+            ("DISK1.IMG".to_owned(), fat::floppy("DISK1", &[])),
+            ("DISK2.IMG".to_owned(), fat::floppy("DISK2", &[])),
+        ];
+        assert_eq!(
+            DiskSet::new(images).0.remark().as_deref(),
+            Some("boot sector: OEM name \"MSDOS5.0\" (MS-DOS 5.0 or later, or Windows NT), boot code not recognized"),
+        );
+    }
+}
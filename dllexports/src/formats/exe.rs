@@ -1,23 +1,218 @@
+use std::collections::BTreeMap;
+
+use binms::clr::header::{ClrFlags, StrongNameStatus};
+use binms::clr::metadata::Assembly;
+use binms::clr::ready_to_run::NativeImageKind;
+use binms::clr::winmd::WinmdType;
+use binms::pe::{MachineType, SectionClass, SectionTable, TimeDateStamp};
+use binms::toolchain::Toolchain;
+
 use crate::data_mgmt::{Symbol, SymbolExporter};
 
 
 #[derive(Debug)]
 pub struct NewExecutable {
     pub exports: Vec<Symbol>,
+
+    /// Whether the Windows application loads its own segments, in which case the entry table need
+    /// not describe the code that is actually run.
+    pub self_loading: bool,
+
+    /// Whether the OS/2 application is a family application, which has been bound to the API
+    /// emulation that lets it run under DOS as well.
+    pub bound_family_application: bool,
 }
 impl SymbolExporter for NewExecutable {
+    fn format_name(&self) -> &str {
+        "NE"
+    }
+
     fn read_symbols(&self) -> Result<Vec<Symbol>, crate::data_mgmt::Error> {
         Ok(self.exports.clone())
     }
+
+    fn remark(&self) -> Option<String> {
+        if self.self_loading {
+            Some("self-loading".to_owned())
+        } else if self.bound_family_application {
+            Some("bound family application".to_owned())
+        } else {
+            None
+        }
+    }
+
+    fn warning(&self) -> Option<String> {
+        self.self_loading
+            .then(|| "self-loading NE executable; its entry table might not match the code that is actually run".to_owned())
+    }
+}
+
+
+/// Information about the managed part of a .NET image.
+#[derive(Debug)]
+pub struct ClrInfo {
+    /// The flags of the COR20 header.
+    pub flags: ClrFlags,
+
+    pub native_image_kind: NativeImageKind,
+    pub assembly: Option<Assembly>,
+    pub strong_name_status: StrongNameStatus,
+
+    /// The types described by the file if it contains Windows Runtime metadata.
+    pub winrt_types: Option<Vec<WinmdType>>,
+}
+impl ClrInfo {
+    /// Summarizes the COR20 header, e.g. `managed (IL-only, 32-bit required, strong-named)`.
+    pub fn summary(&self) -> String {
+        let mut traits = Vec::new();
+        if self.flags.contains(ClrFlags::CLR_ONLY) {
+            traits.push("IL-only");
+        }
+        if self.flags.contains(ClrFlags::REQUIRES_32_BIT) {
+            // the "prefers" flag is only honored along with the "requires" flag
+            if self.flags.contains(ClrFlags::PREFERS_32_BIT) {
+                traits.push("32-bit preferred");
+            } else {
+                traits.push("32-bit required");
+            }
+        }
+        match self.strong_name_status {
+            StrongNameStatus::Unsigned => {},
+            StrongNameStatus::DelaySigned => traits.push("delay-signed"),
+            StrongNameStatus::Signed => traits.push("strong-named"),
+        }
+
+        if traits.is_empty() {
+            "managed".to_owned()
+        } else {
+            format!("managed ({})", traits.join(", "))
+        }
+    }
 }
 
 
 #[derive(Debug)]
 pub struct PortableExecutable {
     pub exports: Vec<Symbol>,
+    pub clr: Option<ClrInfo>,
+    pub machine_type: MachineType,
+    pub time_date_stamp: TimeDateStamp,
+
+    /// The toolchain guessed from the traces it left in the file.
+    pub toolchain: Toolchain,
+
+    /// The RVAs of the exports that are not forwarders, by ordinal.
+    pub ordinal_to_rva: BTreeMap<u32, u32>,
+
+    /// The targets of the exports that are forwarders, by ordinal.
+    pub ordinal_to_forwarder: BTreeMap<u32, String>,
+
+    /// The sections of the file, to find out what the exports point to.
+    pub section_table: SectionTable,
 }
 impl SymbolExporter for PortableExecutable {
+    fn format_name(&self) -> &str {
+        "PE"
+    }
+
     fn read_symbols(&self) -> Result<Vec<Symbol>, crate::data_mgmt::Error> {
-        Ok(self.exports.clone())
+        let mut symbols = self.exports.clone();
+        let winrt_types = self.clr.as_ref()
+            .and_then(|clr| clr.winrt_types.as_ref());
+        for winrt_type in winrt_types.into_iter().flatten() {
+            let type_name = winrt_type.full_name();
+            let mut method_names: Vec<&String> = winrt_type.methods.iter().collect();
+            // overloads share their name
+            method_names.sort_unstable();
+            method_names.dedup();
+            for method_name in method_names {
+                symbols.push(Symbol::WinRt { name: format!("{}.{}", type_name, method_name) });
+            }
+            symbols.push(Symbol::WinRt { name: type_name });
+        }
+        Ok(symbols)
+    }
+
+    fn remark(&self) -> Option<String> {
+        let clr = self.clr.as_ref()?;
+        let mut remarks = vec![clr.summary()];
+        if clr.winrt_types.is_some() {
+            remarks.push("Windows Runtime metadata".to_owned());
+        }
+        if let Some(assembly) = clr.assembly.as_ref() {
+            remarks.push(format!("assembly {}", assembly));
+        }
+        match clr.native_image_kind {
+            NativeImageKind::None => {},
+            NativeImageKind::ReadyToRun => remarks.push("runtime-generated (ReadyToRun)".to_owned()),
+            NativeImageKind::Ngen => remarks.push("runtime-generated (NGEN)".to_owned()),
+        }
+        Some(remarks.join("; "))
+    }
+
+    fn architecture(&self) -> Option<String> {
+        Some(format!("{:?}", self.machine_type))
+    }
+
+    fn timestamp(&self) -> Option<String> {
+        match self.time_date_stamp {
+            TimeDateStamp::Unset => None,
+            other => Some(other.to_string()),
+        }
+    }
+
+    fn toolchain(&self) -> Option<String> {
+        match self.toolchain {
+            Toolchain::Unknown => None,
+            other => Some(other.to_string()),
+        }
+    }
+
+    fn address(&self, symbol: &Symbol) -> Option<u32> {
+        self.ordinal_to_rva.get(&symbol.ordinal()?).copied()
+    }
+
+    fn section_class(&self, symbol: &Symbol) -> Option<SectionClass> {
+        self.section_table.class_for_virtual(self.address(symbol)?)
+    }
+
+    fn forwarder(&self, symbol: &Symbol) -> Option<String> {
+        self.ordinal_to_forwarder.get(&symbol.ordinal()?).cloned()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use binms::clr::header::{ClrFlags, StrongNameStatus};
+    use binms::clr::ready_to_run::NativeImageKind;
+
+    use super::ClrInfo;
+
+    fn clr_info(flags: ClrFlags, strong_name_status: StrongNameStatus) -> ClrInfo {
+        ClrInfo {
+            flags,
+            native_image_kind: NativeImageKind::None,
+            assembly: None,
+            strong_name_status,
+            winrt_types: None,
+        }
+    }
+
+    #[test]
+    fn test_clr_summary() {
+        assert_eq!(
+            clr_info(ClrFlags::CLR_ONLY | ClrFlags::STRONG_NAME_SIGNED, StrongNameStatus::Signed).summary(),
+            "managed (IL-only, strong-named)",
+        );
+        assert_eq!(
+            clr_info(ClrFlags::CLR_ONLY | ClrFlags::REQUIRES_32_BIT, StrongNameStatus::DelaySigned).summary(),
+            "managed (IL-only, 32-bit required, delay-signed)",
+        );
+        assert_eq!(
+            clr_info(ClrFlags::CLR_ONLY | ClrFlags::REQUIRES_32_BIT | ClrFlags::PREFERS_32_BIT, StrongNameStatus::Unsigned).summary(),
+            "managed (IL-only, 32-bit preferred)",
+        );
+        assert_eq!(clr_info(ClrFlags::empty(), StrongNameStatus::Unsigned).summary(), "managed");
     }
 }
@@ -1,27 +1,29 @@
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::fmt;
-use std::io::{Cursor, ErrorKind, Read, Seek, SeekFrom};
+use std::io::{Cursor, ErrorKind, Read};
 use std::path::{Path, PathBuf};
 
+use expandms::boot_sector::BootSectorFingerprint;
 use expandms::fat::{
-    AllocationTable, Attributes, DirectoryEntry, DIRECTORY_ENTRY_SIZE_BYTES, FatEntry, FatHeader,
-    read_cluster_chain_into, RootDirectoryLocation,
+    AllocationTableCopies, Attributes, DirectoryEntry, DIRECTORY_ENTRY_SIZE_BYTES, FatEntry,
+    FatHeader, RootDirectoryLocation,
 };
-use tracing::debug;
+use tracing::{debug, warn};
 
-use crate::data_mgmt::{Error, MultiFileContainer};
+use crate::data_mgmt::{ContainerEntry, Error, MultiFileContainer};
 
 
 #[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub struct FatFileSystem {
-    data: Vec<u8>,
+pub struct FatFileSystem<'d> {
+    data: Cow<'d, [u8]>,
     header: FatHeader,
-    fat: AllocationTable,
-    file_path_to_first_cluster: BTreeMap<PathBuf, u32>,
+    fats: AllocationTableCopies,
+    file_path_to_entry: BTreeMap<PathBuf, DirectoryEntry>,
 }
-impl FatFileSystem {
-    pub fn new(data: Vec<u8>) -> Result<Self, Error> {
-        let mut cursor = Cursor::new(&data);
+impl<'d> FatFileSystem<'d> {
+    pub fn new(data: Cow<'d, [u8]>) -> Result<Self, Error> {
+        let mut cursor = Cursor::new(data.as_ref());
 
         // read header
         let header = FatHeader::read(&mut cursor)?;
@@ -31,16 +33,12 @@ impl FatFileSystem {
             return Err(Error::Io(ErrorKind::InvalidData.into()));
         }
 
-        // skip over reserved sectors
-        let reserved_bytes = u64::from(header.reserved_sector_count) * u64::from(header.bytes_per_sector);
-        cursor.seek(SeekFrom::Start(reserved_bytes))?;
-
-        // read FAT
-        let fat = AllocationTable::read(
-            &mut cursor,
-            header.variant(),
-            header.fat_bytes(),
-        )?;
+        // read FATs; if the first one is damaged, one of the others stands in
+        let fats = AllocationTableCopies::read(&mut cursor, &header)?;
+        let (primary_copy, fat) = fats.primary();
+        if primary_copy != 0 {
+            warn!("first allocation table is damaged; using copy {}", primary_copy);
+        }
 
         let Some(first_fat_entry) = fat.entries.get(0) else {
             debug!("FAT is missing entry at index 0");
@@ -76,10 +74,9 @@ impl FatFileSystem {
             },
             RootDirectoryLocation::Cluster(first_cluster_index) => {
                 let mut output = Vec::new();
-                expandms::fat::read_cluster_chain_into(
+                fats.read_cluster_chain_into(
                     &mut cursor,
                     &header,
-                    &fat,
                     first_cluster_index,
                     &mut output,
                 )?;
@@ -89,20 +86,24 @@ impl FatFileSystem {
 
         // recursively add all files in the file system
         let mut me = Self {
-            data: data.clone(),
+            data,
             header,
-            fat,
-            file_path_to_first_cluster: BTreeMap::new(),
+            fats,
+            file_path_to_entry: BTreeMap::new(),
         };
-        me.process_directory(Path::new(&""), &mut cursor, &root_directory_bytes)?;
+        let mut file_path_to_entry = BTreeMap::new();
+        me.process_directory(Path::new(&""), &root_directory_bytes, &mut file_path_to_entry)?;
+        me.file_path_to_entry = file_path_to_entry;
 
         Ok(me)
     }
 
-    fn process_directory<R: Read + Seek>(&mut self, path_prefix: &Path, reader: &mut R, directory_bytes: &[u8]) -> Result<(), Error> {
+    fn process_directory(&self, path_prefix: &Path, directory_bytes: &[u8], file_path_to_entry: &mut BTreeMap<PathBuf, DirectoryEntry>) -> Result<(), Error> {
         // run through the directory
         let mut cursor = Cursor::new(directory_bytes);
         loop {
+            binstruct::deadline::check()?;
+
             let entree = match DirectoryEntry::read(&mut cursor, self.header.variant()) {
                 Ok(e) => e,
                 Err(e) => {
@@ -149,6 +150,10 @@ impl FatFileSystem {
             while name.ends_with(" ") {
                 name.pop();
             }
+            if name.ends_with(".") {
+                // no extension
+                name.pop();
+            }
 
             let mut subpath = path_prefix.to_owned();
             subpath.push(&name);
@@ -156,58 +161,96 @@ impl FatFileSystem {
             if entree.attributes.contains(Attributes::SUBDIRECTORY) {
                 // curses! recursion!
                 let mut subdir_data = Vec::new();
-                read_cluster_chain_into(
-                    reader,
+                self.fats.read_cluster_chain_into(
+                    &mut Cursor::new(self.data.as_ref()),
                     &self.header,
-                    &self.fat,
                     entree.first_cluster_number,
                     &mut subdir_data,
                 )?;
                 self.process_directory(
                     &subpath,
-                    reader,
                     &subdir_data,
+                    file_path_to_entry,
                 )?;
             } else {
                 // remember this one
-                self.file_path_to_first_cluster.insert(subpath, entree.first_cluster_number);
+                file_path_to_entry.insert(subpath, entree);
             }
         }
         Ok(())
     }
+
+    fn read_entry(&self, file_path: &Path, entry: &DirectoryEntry) -> Result<Cow<'_, [u8]>, Error> {
+        if entry.file_size_bytes == 0 {
+            // empty files have no clusters allocated
+            return Ok(Cow::Borrowed(&[]));
+        }
+
+        // clusters need not be contiguous, so the file is assembled in a buffer of its own
+        let mut cursor = Cursor::new(self.data.as_ref());
+        let mut data = Vec::new();
+        let copy = self.fats.read_cluster_chain_into(
+            &mut cursor,
+            &self.header,
+            entry.first_cluster_number,
+            &mut data,
+        )?;
+        if copy != self.fats.primary().0 {
+            warn!("chain of {:?} is damaged in the first allocation table; recovered it using copy {}", file_path, copy);
+        }
+
+        // the last cluster is generally only partially occupied
+        let file_size = usize::try_from(entry.file_size_bytes).unwrap();
+        if data.len() > file_size {
+            data.truncate(file_size);
+        }
+        Ok(Cow::Owned(data))
+    }
 }
-impl fmt::Debug for FatFileSystem {
+impl<'d> fmt::Debug for FatFileSystem<'d> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("FatFileSystem")
             .field("data", &"[removed]")
             .field("header", &self.header)
-            .field("fat", &self.fat)
-            .field("file_path_to_first_cluster", &self.file_path_to_first_cluster)
+            .field("fats", &self.fats)
+            .field("file_path_to_entry", &self.file_path_to_entry)
             .finish()
     }
 }
-impl MultiFileContainer for FatFileSystem {
+impl<'d> MultiFileContainer for FatFileSystem<'d> {
+    fn format_name(&self) -> &str {
+        "FAT"
+    }
+
+    fn remark(&self) -> Option<String> {
+        let reserved_bytes = usize::from(self.header.reserved_sector_count) * usize::from(self.header.bytes_per_sector);
+        let reserved_sectors = &self.data[..reserved_bytes.min(self.data.len())];
+        BootSectorFingerprint::from_reserved_sectors(reserved_sectors)
+            .map(|fingerprint| format!("boot sector: {}", fingerprint))
+    }
+
     fn list_files(&self) -> Result<Vec<PathBuf>, Error> {
-        let mut ret = Vec::with_capacity(self.file_path_to_first_cluster.len());
-        for path in self.file_path_to_first_cluster.keys() {
+        let mut ret = Vec::with_capacity(self.file_path_to_entry.len());
+        for path in self.file_path_to_entry.keys() {
             ret.push(path.clone());
         }
         Ok(ret)
     }
 
-    fn read_file(&self, file_path: &std::path::Path) -> Result<Vec<u8>, Error> {
-        let first_cluster_index = *self.file_path_to_first_cluster
+    fn read_file(&self, file_path: &std::path::Path) -> Result<Cow<'_, [u8]>, Error> {
+        let entry = self.file_path_to_entry
             .get(file_path)
             .ok_or_else(|| Error::FileNotFound(file_path.to_owned()))?;
-        let mut cursor = Cursor::new(&self.data);
-        let mut data = Vec::new();
-        expandms::fat::read_cluster_chain_into(
-            &mut cursor,
-            &self.header,
-            &self.fat,
-            first_cluster_index,
-            &mut data,
-        )?;
-        Ok(data)
+        self.read_entry(file_path, entry)
+    }
+
+    fn entries(&self) -> Result<Box<dyn Iterator<Item = ContainerEntry<'_>> + '_>, Error> {
+        Ok(Box::new(self.file_path_to_entry.iter().map(move |(path, entry)| {
+            ContainerEntry::new(
+                path.clone(),
+                Some(entry.file_size_bytes.into()),
+                move || self.read_entry(path, entry),
+            )
+        })))
     }
 }
@@ -0,0 +1,317 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use binms::minidump::{Minidump, Module};
+use binms::pe::{self, OptionalHeader};
+use tracing::debug;
+
+use crate::data_mgmt::{Error, MultiFileContainer};
+
+
+/// A crash dump, interpreted as a container of the modules loaded into the crashed process.
+///
+/// Since a dump only contains the memory of a module in its loaded (section-aligned) layout, each
+/// module is reassembled into the file layout expected by the executable parsers. Parts of the
+/// module that have not been captured in the dump are filled with zeroes.
+pub(crate) struct MinidumpContainer<'d> {
+    bytes: Cow<'d, [u8]>,
+    dump: Minidump,
+}
+impl<'d> MinidumpContainer<'d> {
+    pub fn new(bytes: Cow<'d, [u8]>) -> Result<Self, Error> {
+        let mut reader = Cursor::new(bytes.as_ref());
+        let dump = Minidump::read(&mut reader)?;
+        Ok(Self {
+            bytes,
+            dump,
+        })
+    }
+
+    /// Copies the captured memory at the given address into the buffer, leaving the parts of the
+    /// buffer corresponding to uncaptured memory untouched. Returns whether any memory was copied.
+    fn copy_memory(&self, address: u64, buf: &mut [u8]) -> bool {
+        let buf_len: u64 = buf.len().try_into().unwrap();
+        let end_address = address.saturating_add(buf_len);
+
+        let mut copied_any = false;
+        for range in &self.dump.memory_ranges {
+            let range_end = range.start_address.saturating_add(range.size);
+            let overlap_start = range.start_address.max(address);
+            let overlap_end = range_end.min(end_address);
+            if overlap_start >= overlap_end {
+                continue;
+            }
+
+            let file_start = range.file_offset + (overlap_start - range.start_address);
+            let file_end = file_start + (overlap_end - overlap_start);
+            let (Ok(file_start), Ok(file_end)) = (usize::try_from(file_start), usize::try_from(file_end)) else {
+                continue;
+            };
+            if file_end > self.bytes.len() {
+                debug!("memory range at {:#X} extends past the end of the dump", range.start_address);
+                continue;
+            }
+
+            let buf_start = usize::try_from(overlap_start - address).unwrap();
+            let buf_end = usize::try_from(overlap_end - address).unwrap();
+            buf[buf_start..buf_end].copy_from_slice(&self.bytes[file_start..file_end]);
+            copied_any = true;
+        }
+        copied_any
+    }
+
+    /// Returns the end of the highest captured memory between the given addresses, or `address` if
+    /// none of it has been captured.
+    fn captured_end(&self, address: u64, end_address: u64) -> u64 {
+        let dump_len: u64 = self.bytes.len().try_into().unwrap();
+        self.dump.memory_ranges
+            .iter()
+            .filter(|range| range.file_offset.checked_add(range.size).is_some_and(|end| end <= dump_len))
+            .filter_map(|range| {
+                let range_end = range.start_address.saturating_add(range.size);
+                let overlap_start = range.start_address.max(address);
+                let overlap_end = range_end.min(end_address);
+                (overlap_start < overlap_end).then_some(overlap_end)
+            })
+            .max()
+            .unwrap_or(address)
+    }
+
+    /// Reassembles the file layout of the given module from its memory image.
+    fn reassemble_module(&self, module: &Module) -> Result<Vec<u8>, Error> {
+        // the header's image size is not to be trusted; never allocate beyond the captured memory,
+        // but keep gaps between captured ranges so that the image can be indexed by RVA
+        let image_end = module.image_base.saturating_add(module.image_size.into());
+        let captured_end = self.captured_end(module.image_base, image_end);
+        let image_size = usize::try_from(captured_end - module.image_base).unwrap();
+        let mut image = vec![0u8; image_size];
+        if !self.copy_memory(module.image_base, &mut image) {
+            return Err(Error::MemoryNotCaptured(module.image_base));
+        }
+
+        // the headers are at the same location in both layouts
+        let executable = pe::Executable::read(&mut Cursor::new(&image))?;
+        let headers_size = match &executable.optional_header {
+            Some(OptionalHeader::Coff(coff)) => coff.optional_windows_header
+                .as_ref()
+                .map(|owh| owh.headers_size),
+            _ => None,
+        };
+        let Some(headers_size) = headers_size else {
+            debug!("module {:?} has no Windows-specific optional header", module.name);
+            return Err(Error::MemoryNotCaptured(module.image_base));
+        };
+        let headers_size = usize::try_from(headers_size).unwrap().min(image.len());
+
+        let mut file_bytes = image[..headers_size].to_vec();
+        for section in executable.section_table.as_entries() {
+            let raw_start = usize::try_from(section.raw_data_pointer).unwrap();
+            let raw_size = usize::try_from(section.raw_data_size).unwrap();
+            if raw_size == 0 {
+                continue;
+            }
+            let raw_end = raw_start.saturating_add(raw_size);
+            if raw_end > image.len() {
+                debug!("section {} of module {:?} ends beyond the captured image", section.name, module.name);
+                continue;
+            }
+            if file_bytes.len() < raw_end {
+                file_bytes.resize(raw_end, 0x00);
+            }
+
+            // data beyond the virtual size has not been loaded into memory
+            let virtual_start = usize::try_from(section.virtual_address).unwrap();
+            let copy_size = if section.virtual_size == 0 {
+                raw_size
+            } else {
+                raw_size.min(usize::try_from(section.virtual_size).unwrap())
+            };
+            let virtual_end = (virtual_start + copy_size).min(image.len());
+            if virtual_start >= virtual_end {
+                continue;
+            }
+            file_bytes[raw_start..raw_start+(virtual_end-virtual_start)]
+                .copy_from_slice(&image[virtual_start..virtual_end]);
+        }
+
+        Ok(file_bytes)
+    }
+}
+impl<'d> fmt::Debug for MinidumpContainer<'d> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the dumped memory is too large to be worth outputting
+        f.debug_struct("MinidumpContainer")
+            .field("dump", &self.dump)
+            .finish_non_exhaustive()
+    }
+}
+impl<'d> MultiFileContainer for MinidumpContainer<'d> {
+    fn format_name(&self) -> &str {
+        "minidump"
+    }
+
+    fn list_files(&self) -> Result<Vec<PathBuf>, Error> {
+        let files = self.dump.modules
+            .iter()
+            .map(|m| PathBuf::from(&m.name))
+            .collect();
+        Ok(files)
+    }
+
+    fn read_file(&self, file_path: &Path) -> Result<Cow<'_, [u8]>, Error> {
+        let module = self.dump.modules
+            .iter()
+            .find(|m| Path::new(&m.name) == file_path)
+            .ok_or_else(|| Error::FileNotFound(file_path.to_owned()))?;
+        self.reassemble_module(module)
+            .map(Cow::Owned)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::io::Cursor;
+    use std::path::Path;
+
+    use binms::pe::Executable;
+    use testgen::pe;
+
+    use super::MinidumpContainer;
+    use crate::data_mgmt::MultiFileContainer;
+
+    const IMAGE_BASE: u64 = 0x7FF0_0000;
+
+    /// Lays out a PE file as it would be loaded into memory.
+    fn load(file_bytes: &[u8]) -> Vec<u8> {
+        let executable = Executable::read(&mut Cursor::new(file_bytes)).unwrap();
+        let mut image = file_bytes[..0x200].to_vec();
+        for section in executable.section_table.as_entries() {
+            let virtual_start = usize::try_from(section.virtual_address).unwrap();
+            let raw_start = usize::try_from(section.raw_data_pointer).unwrap();
+            let raw_size = usize::try_from(section.raw_data_size).unwrap();
+            image.resize(image.len().max(virtual_start + raw_size), 0x00);
+            image[virtual_start..virtual_start+raw_size].copy_from_slice(&file_bytes[raw_start..raw_start+raw_size]);
+        }
+        image
+    }
+
+    /// Synthesizes a dump with one module whose memory has been captured in the given ranges, each
+    /// of which is given as an offset from the image base and the captured bytes.
+    fn synthesize_dump(ranges: &[(u64, &[u8])], image_size: u32) -> Vec<u8> {
+        let name: Vec<u8> = "C:\\SYNTH.DLL".encode_utf16()
+            .flat_map(|w| w.to_le_bytes())
+            .collect();
+        let range_count = u32::try_from(ranges.len()).unwrap();
+
+        let directory_rva = 32u32;
+        let module_list_rva = directory_rva + 2 * 12;
+        let memory_list_rva = module_list_rva + 4 + 108;
+        let name_rva = memory_list_rva + 4 + 16 * range_count;
+        let mut memory_rva = name_rva + 4 + u32::try_from(name.len()).unwrap();
+
+        let mut dump = Vec::new();
+        dump.extend_from_slice(b"MDMP");
+        dump.extend_from_slice(&0xA793u32.to_le_bytes());
+        dump.extend_from_slice(&2u32.to_le_bytes());
+        dump.extend_from_slice(&directory_rva.to_le_bytes());
+        dump.extend_from_slice(&[0u8; 16]);
+
+        for (stream_type, rva) in [(4u32, module_list_rva), (5, memory_list_rva)] {
+            dump.extend_from_slice(&stream_type.to_le_bytes());
+            dump.extend_from_slice(&0u32.to_le_bytes());
+            dump.extend_from_slice(&rva.to_le_bytes());
+        }
+
+        dump.extend_from_slice(&1u32.to_le_bytes());
+        let mut module = [0u8; 108];
+        module[0..8].copy_from_slice(&IMAGE_BASE.to_le_bytes());
+        module[8..12].copy_from_slice(&image_size.to_le_bytes());
+        module[20..24].copy_from_slice(&name_rva.to_le_bytes());
+        dump.extend_from_slice(&module);
+
+        dump.extend_from_slice(&range_count.to_le_bytes());
+        for (offset, data) in ranges {
+            let size = u32::try_from(data.len()).unwrap();
+            dump.extend_from_slice(&(IMAGE_BASE + offset).to_le_bytes());
+            dump.extend_from_slice(&size.to_le_bytes());
+            dump.extend_from_slice(&memory_rva.to_le_bytes());
+            memory_rva += size;
+        }
+
+        dump.extend_from_slice(&u32::try_from(name.len()).unwrap().to_le_bytes());
+        dump.extend_from_slice(&name);
+        for (_offset, data) in ranges {
+            dump.extend_from_slice(data);
+        }
+        dump
+    }
+
+    fn synthesized_image() -> Vec<u8> {
+        let mut library = pe::PortableExecutable::new("SYNTH.DLL");
+        library.exports = vec![
+            pe::Export { ordinal: 1, name: Some("Alpha".to_owned()), target: pe::ExportTarget::Code { rva: pe::TEXT_RVA } },
+        ];
+        load(&library.to_bytes())
+    }
+
+    #[test]
+    fn test_reassemble_module() {
+        let image = synthesized_image();
+        let dump = synthesize_dump(&[(0, &image)], u32::try_from(image.len()).unwrap());
+        let container = MinidumpContainer::new(Cow::Owned(dump)).unwrap();
+        let reassembled = container.read_file(Path::new("C:\\SYNTH.DLL")).unwrap();
+
+        let executable = Executable::read(&mut Cursor::new(reassembled.as_ref())).unwrap();
+        let text = &executable.section_table.as_entries()[0];
+        let raw_start = usize::try_from(text.raw_data_pointer).unwrap();
+        assert_eq!(reassembled[raw_start], 0xC3);
+    }
+
+    #[test]
+    fn test_huge_image_size_is_clamped() {
+        let image = synthesized_image();
+        let dump = synthesize_dump(&[(0, &image)], u32::MAX);
+        let dump_len = dump.len();
+        let container = MinidumpContainer::new(Cow::Owned(dump)).unwrap();
+        let reassembled = container.read_file(Path::new("C:\\SYNTH.DLL")).unwrap();
+        assert!(reassembled.len() <= dump_len);
+
+        // a memory range pointing beyond the dump contributes nothing
+        let mut dump = synthesize_dump(&[(0, &image)], u32::MAX);
+        let memory_size_offset = 32 + 2 * 12 + 4 + 108 + 4 + 8;
+        dump[memory_size_offset..memory_size_offset+4].copy_from_slice(&0xFFFF_0000u32.to_le_bytes());
+        let container = MinidumpContainer::new(Cow::Owned(dump)).unwrap();
+        assert!(container.read_file(Path::new("C:\\SYNTH.DLL")).is_err());
+    }
+
+    #[test]
+    fn test_reassemble_sparse_module() {
+        // capture the headers and the export section, but not the code section in between
+        let image = synthesized_image();
+        let executable = Executable::read(&mut Cursor::new(&image)).unwrap();
+        let edata = &executable.section_table.as_entries()[1];
+        let edata_start = usize::try_from(edata.virtual_address).unwrap();
+        let ranges: [(u64, &[u8]); 2] = [
+            (0, &image[..0x200]),
+            (edata.virtual_address.into(), &image[edata_start..]),
+        ];
+        let dump = synthesize_dump(&ranges, u32::try_from(image.len()).unwrap());
+        let container = MinidumpContainer::new(Cow::Owned(dump)).unwrap();
+        let reassembled = container.read_file(Path::new("C:\\SYNTH.DLL")).unwrap();
+
+        let executable = Executable::read(&mut Cursor::new(reassembled.as_ref())).unwrap();
+        let sections = executable.section_table.as_entries();
+        let text_start = usize::try_from(sections[0].raw_data_pointer).unwrap();
+        assert_eq!(reassembled[text_start], 0x00);
+        let edata_raw_start = usize::try_from(sections[1].raw_data_pointer).unwrap();
+        let edata_size = usize::try_from(sections[1].raw_data_size).unwrap();
+        assert_eq!(
+            reassembled[edata_raw_start..edata_raw_start+edata_size],
+            image[edata_start..edata_start+edata_size],
+        );
+    }
+}
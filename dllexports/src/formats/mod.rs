@@ -1,24 +1,136 @@
 mod cab;
 mod cdrom;
+mod disk_set;
 mod exe;
 mod fat;
+mod minidump;
+mod regf;
+mod sfx;
 mod single_compression;
+mod tar;
+mod text;
+mod wince_rom;
 
 
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::io::Cursor;
 
-use binms::ne::{self, SegmentEntryFlags};
-use binms::pe::{self, ExportData, KnownDataDirectoryEntry, OptionalHeader};
+use binms::decode_byte_name;
+use binms::clr::AddressAndLength32;
+use binms::clr::header::ClrHeader;
+use binms::clr::metadata::ClrMetadata;
+use binms::clr::ready_to_run::NativeImageKind;
+use binms::clr::winmd;
+use binms::ne::{self, ExeFlags, SegmentEntryFlags};
+use binms::pe::{
+    self, ExportAddressTableEntry, ExportData, KnownDataDirectoryEntry, OptionalHeader, OptionalWindowsHeader,
+    SectionTable,
+};
+use binms::toolchain::{self, BuildTraits, Toolchain};
+use tracing::debug;
 
 use crate::data_mgmt::{Error, IdentifiedFile, Symbol};
 use crate::formats::cab::Cabinet;
-use crate::formats::exe::{NewExecutable, PortableExecutable};
+use crate::formats::exe::{ClrInfo, NewExecutable, PortableExecutable};
 use crate::formats::fat::FatFileSystem;
+use crate::formats::minidump::MinidumpContainer;
+use crate::formats::regf::RegistryHive;
+use crate::formats::sfx::{find_archive, SelfExtractingArchive};
 use crate::formats::single_compression::KwajOrSz;
+use crate::formats::tar::{is_tarball, Tarball};
+use crate::formats::wince_rom::WinCeRom;
+use crate::volumes::{cabinet_set, szdd_parts, Siblings};
 
+pub(crate) use crate::formats::cdrom::{descriptor_offset as cd_descriptor_offset, read_volume_descriptors as read_cd_volume_descriptors};
+pub(crate) use crate::formats::disk_set::{find_disk_sets, DiskSet};
 
-fn interpret_ne_pe(data: &[u8]) -> Option<Result<IdentifiedFile, Error>> {
+
+/// The target operating system stored in the headers of NE files built for OS/2.
+const NE_TARGET_OS2: u8 = 0x01;
+
+
+/// Guesses the toolchain that produced a PE file; see [`binms::toolchain`].
+fn guess_toolchain(data: &[u8]) -> Toolchain {
+    match BuildTraits::read(&mut Cursor::new(data)) {
+        Ok(traits) => toolchain::fingerprint(&traits).toolchain,
+        Err(e) => {
+            debug!("failed to collect the traces of the toolchain: {}", e);
+            Toolchain::Unknown
+        },
+    }
+}
+
+/// Reads information about the managed part of a PE file, if it is a .NET image.
+fn read_clr_info(data: &[u8], windows: &OptionalWindowsHeader, section_table: &SectionTable) -> Option<ClrInfo> {
+    let clr_entry = windows.known_data_directory_entry(KnownDataDirectoryEntry::ClrRuntimeHeader)?;
+    if clr_entry.address == 0 || clr_entry.size == 0 {
+        return None;
+    }
+    let Some(clr_header_bytes) = virtual_slice(data, section_table, clr_entry.address, clr_entry.size) else {
+        debug!("CLR header at {:#010X} is outside the file", clr_entry.address);
+        return None;
+    };
+    let clr_header = match ClrHeader::take_from_bytes(clr_header_bytes) {
+        Ok((_, ch)) => ch,
+        Err(e) => {
+            debug!("failed to read CLR header: {}", e);
+            return None;
+        },
+    };
+
+    let range_slice = |range: AddressAndLength32| if range.address != 0 && range.length != 0 {
+        virtual_slice(data, section_table, range.address, range.length)
+    } else {
+        None
+    };
+
+    // runtime-generated exports?
+    let native_image_kind = NativeImageKind::detect(&clr_header, range_slice(clr_header.managed_native_header_range));
+
+    // assembly identity
+    let strong_name_status = clr_header.strong_name_status(range_slice(clr_header.strong_name_signature_range));
+    let metadata = match range_slice(clr_header.metadata_range).map(ClrMetadata::take_from_bytes) {
+        Some(Ok((_, metadata))) => Some(metadata),
+        Some(Err(e)) => {
+            debug!("failed to read CLR metadata: {}", e);
+            None
+        },
+        None => None,
+    };
+    let assembly = metadata.as_ref()
+        .and_then(|md| md.assembly()
+            .inspect_err(|e| debug!("failed to read assembly metadata: {}", e))
+            .ok()
+            .flatten()
+        );
+
+    // Windows Runtime API surface
+    let winrt_types = metadata.as_ref()
+        .filter(|md| winmd::is_winmd(md))
+        .and_then(|md| winmd::collect_types(md)
+            .inspect_err(|e| debug!("failed to collect Windows Runtime types: {}", e))
+            .ok()
+        );
+
+    Some(ClrInfo {
+        flags: clr_header.flags,
+        native_image_kind,
+        assembly,
+        strong_name_status,
+        winrt_types,
+    })
+}
+
+/// Returns the slice of the file's data that corresponds to the given virtual address range.
+fn virtual_slice<'d>(data: &'d [u8], section_table: &SectionTable, address: u32, size: u32) -> Option<&'d [u8]> {
+    let start = usize::try_from(section_table.virtual_to_raw(address)?).unwrap();
+    let end = start.checked_add(usize::try_from(size).unwrap())?;
+    data.get(start..end)
+}
+
+
+fn interpret_ne_pe(data: &[u8]) -> Option<Result<IdentifiedFile<'static>, Error>> {
     if data.len() < 64 {
         // not NE/PE
         return None;
@@ -50,6 +162,13 @@ fn interpret_ne_pe(data: &[u8]) -> Option<Result<IdentifiedFile, Error>> {
                             // no exports
                             return Some(Ok(IdentifiedFile::SymbolExporter(Box::new(PortableExecutable {
                                 exports: Vec::with_capacity(0),
+                                clr: read_clr_info(data, windows, &portable_executable.section_table),
+                                machine_type: portable_executable.machine_type,
+                                time_date_stamp: portable_executable.decoded_time_date_stamp(),
+                                toolchain: guess_toolchain(data),
+                                ordinal_to_rva: BTreeMap::new(),
+                                ordinal_to_forwarder: BTreeMap::new(),
+                                section_table: portable_executable.section_table.clone(),
                             }))));
                         }
                         let export_data_res = ExportData::read(
@@ -81,9 +200,31 @@ fn interpret_ne_pe(data: &[u8]) -> Option<Result<IdentifiedFile, Error>> {
                             }
                         }
 
+                        let ordinal_to_rva = export_data.ordinal_to_address
+                            .iter()
+                            .filter_map(|(ordinal, address)| match address {
+                                ExportAddressTableEntry::Code { code_rva } => Some((*ordinal, *code_rva)),
+                                _ => None,
+                            })
+                            .collect();
+                        let ordinal_to_forwarder = export_data.ordinal_to_address
+                            .iter()
+                            .filter_map(|(ordinal, address)| match address {
+                                ExportAddressTableEntry::Forwarder { target } => Some((*ordinal, target.clone())),
+                                _ => None,
+                            })
+                            .collect();
+
                         let exports: Vec<Symbol> = ordinal_to_symbol.into_values().collect();
                         return Some(Ok(IdentifiedFile::SymbolExporter(Box::new(PortableExecutable {
                             exports,
+                            clr: read_clr_info(data, windows, &portable_executable.section_table),
+                            machine_type: portable_executable.machine_type,
+                            time_date_stamp: portable_executable.decoded_time_date_stamp(),
+                            toolchain: guess_toolchain(data),
+                            ordinal_to_rva,
+                            ordinal_to_forwarder,
+                            section_table: portable_executable.section_table.clone(),
                         }))));
                     }
                 }
@@ -96,43 +237,24 @@ fn interpret_ne_pe(data: &[u8]) -> Option<Result<IdentifiedFile, Error>> {
             Ok(ne) => ne,
             Err(e) => return Some(Err(Error::Io(e))),
         };
+        // OS/2 uses the self-loading flag to mark bound family applications, whose entry tables
+        // can be trusted; the API emulation bound into them is only used when running under DOS
+        let is_os2 = new_executable.executable_type == NE_TARGET_OS2;
+        let self_loading = new_executable.flags.contains(ExeFlags::SELF_LOADING) && !is_os2;
+        let bound_family_application = new_executable.flags.contains(ExeFlags::SELF_LOADING) && is_os2;
 
         // collect exported entry points and their ordinals
-        let mut exports = Vec::new();
-        let mut ordinal: u32 = 0;
-        for entry in &new_executable.entry_table {
-            match entry {
-                ne::EntryBundle::Unused { entry_count } => {
-                    ordinal += u32::from(*entry_count);
-                },
-                ne::EntryBundle::Fixed { entries, .. } => {
-                    for entry in entries {
-                        if entry.flags.contains(SegmentEntryFlags::EXPORTED) {
-                            exports.push(Symbol::ByOrdinal {
-                                ordinal: ordinal,
-                            });
-                        }
-                        ordinal += 1;
-                    }
-                },
-                ne::EntryBundle::Moveable { entries } => {
-                    for entry in entries {
-                        if entry.flags.contains(SegmentEntryFlags::EXPORTED) {
-                            exports.push(Symbol::ByOrdinal {
-                                ordinal: ordinal,
-                            });
-                        }
-                        ordinal += 1;
-                    }
-                },
-            }
-        }
+        let mut exports: Vec<Symbol> = new_executable.entries()
+            .into_iter()
+            .filter(|entry| entry.flags.contains(SegmentEntryFlags::EXPORTED))
+            .map(|entry| Symbol::ByOrdinal { ordinal: entry.ordinal.into() })
+            .collect();
 
         // run through resident and nonresident name tables to enrich ordinals with names
         for table in &[&new_executable.resident_name_table, &new_executable.non_resident_name_table] {
             for entry in *table {
                 let name_bytes: &[u8] = entry.name.as_ref();
-                let Ok(name) = String::from_utf8(name_bytes.to_vec()) else { continue };
+                let name = decode_byte_name(name_bytes);
 
                 let symbol_opt = exports.iter_mut()
                     .filter(|s| s.ordinal() == Some(entry.ordinal_number.into()))
@@ -147,6 +269,8 @@ fn interpret_ne_pe(data: &[u8]) -> Option<Result<IdentifiedFile, Error>> {
 
         Some(Ok(IdentifiedFile::SymbolExporter(Box::new(NewExecutable {
             exports,
+            self_loading,
+            bound_family_application,
         }))))
     } else {
         None
@@ -155,12 +279,54 @@ fn interpret_ne_pe(data: &[u8]) -> Option<Result<IdentifiedFile, Error>> {
 
 
 /// Interprets a file's data.
-pub(crate) fn interpret_file(data: &[u8]) -> Result<IdentifiedFile, Error> {
+///
+/// If the data is passed as a slice, any returned container borrows it; if it is passed as a
+/// vector, the container takes ownership of it (see [`IdentifiedFile`]). Either way, the data is
+/// not copied.
+pub(crate) fn interpret_file<'d, D: Into<Cow<'d, [u8]>>>(data: D) -> Result<IdentifiedFile<'d>, Error> {
+    let data = data.into();
     if data.starts_with(b"MZ") {
         // perhaps NE or PE
-        if let Some(ne_pe) = interpret_ne_pe(data) {
+        if let Some(ne_pe) = interpret_ne_pe(&data) {
             return ne_pe;
         }
+
+        // perhaps a DOS self-extracting archive; treat the archived files as files
+        if let Some(index) = find_archive(&data) {
+            let archive = SelfExtractingArchive::new(data, index);
+            return Ok(IdentifiedFile::MultiFileContainer(Box::new(archive)));
+        }
+    }
+
+    if data.starts_with(b"MDMP") {
+        // crash dump; treat the loaded modules as files
+        let dump = MinidumpContainer::new(data)?;
+        return Ok(IdentifiedFile::MultiFileContainer(Box::new(dump)));
+    }
+
+    if data.starts_with(binms::wince_rom::BIN_SIGNATURE) {
+        // Windows CE ROM image; treat the modules and files as files
+        let rom = WinCeRom::new_from_bin(&data)?;
+        return Ok(IdentifiedFile::MultiFileContainer(Box::new(rom)));
+    }
+    if data.get(0x40..0x44) == Some(b"ECEC") {
+        // perhaps a raw Windows CE ROM image
+        match WinCeRom::new_from_raw(&data) {
+            Ok(rom) => return Ok(IdentifiedFile::MultiFileContainer(Box::new(rom))),
+            Err(e) => debug!("not a raw Windows CE ROM image: {}", e),
+        }
+    }
+
+    if data.starts_with(b"regf") {
+        // registry hive; export the COM servers registered in it
+        let hive = RegistryHive::new(&data)?;
+        return Ok(IdentifiedFile::SymbolExporter(Box::new(hive)));
+    }
+
+    if is_tarball(&data) {
+        // e.g. a container image layer; treat the files as files
+        let tarball = Tarball::new(data)?;
+        return Ok(IdentifiedFile::MultiFileContainer(Box::new(tarball)));
     }
 
     // ignore compressed files/containers
@@ -213,5 +379,423 @@ pub(crate) fn interpret_file(data: &[u8]) -> Result<IdentifiedFile, Error> {
     // * possibly NTFS (m.f.c.)
     */
 
+    // last resort: text files describing other files
+    if let Some(metadata) = crate::formats::text::interpret_text(&data) {
+        return Ok(IdentifiedFile::Metadata(metadata));
+    }
+
     Ok(IdentifiedFile::Unidentified)
 }
+
+
+/// Interprets a file's data, also recognizing the compressed files and file system images that
+/// [`interpret_file`] ignores during a scan.
+///
+/// Like [`interpret_file`], borrows a slice and takes ownership of a vector.
+pub(crate) fn interpret_container<'d, D: Into<Cow<'d, [u8]>>>(data: D) -> Result<IdentifiedFile<'d>, Error> {
+    let data = data.into();
+    let is_kwaj_or_sz =
+        data.starts_with(b"KWAJ\x88\xF0\x27\xD1")
+        || data.starts_with(b"SZDD\x88\xF0\x27\x33")
+        || data.starts_with(b"SZ \x88\xF0\x27\x33\xD1");
+    if is_kwaj_or_sz {
+        // single-file KWAJ, SZDD or SZ container
+        return Ok(IdentifiedFile::SingleFileContainer(Box::new(KwajOrSz::new(data))));
+    }
+
+    if data.starts_with(b"MSCF") {
+        let cab = Cabinet::new(data)?;
+        return Ok(IdentifiedFile::MultiFileContainer(Box::new(cab)));
+    }
+
+    // for CD-ROMs, we need to look a bit further
+    if data.len() >= 0x8006 && &data[0x8001..0x8006] == b"CD001" {
+        let cd = crate::formats::cdrom::Cdrom::new_from_iso9660_data(data)?;
+        return Ok(IdentifiedFile::MultiFileContainer(Box::new(cd)));
+    }
+    if data.len() >= 0x800E && &data[0x8009..0x800E] == b"CDROM" {
+        let cd = crate::formats::cdrom::Cdrom::new_from_high_sierra_data(data)?;
+        return Ok(IdentifiedFile::MultiFileContainer(Box::new(cd)));
+    }
+
+    // FAT is checked last as its signature is the weakest
+    let looks_like_fat = data.len() > 2 && (
+        // jmp short XX, nop
+        (data[0] == 0xEB && data[2] == 0x90)
+        // jmp near XX
+        || data[0] == 0xE9
+    );
+    if !looks_like_fat {
+        return interpret_file(data);
+    }
+
+    // only hand the data over once we know it is not something else
+    if !matches!(interpret_file(data.as_ref())?, IdentifiedFile::Unidentified) {
+        return interpret_file(data);
+    }
+    let thicc = FatFileSystem::new(data)?;
+    Ok(IdentifiedFile::MultiFileContainer(Box::new(thicc)))
+}
+
+
+/// Interprets a container like [`interpret_container`], joining it with its other volumes if it is
+/// part of a set of cabinets or an SZDD file split into parts.
+///
+/// `name` is the file name of the container; its other volumes are looked for among `siblings`.
+pub(crate) fn interpret_container_volume<'d, D: Into<Cow<'d, [u8]>>>(data: D, name: &str, siblings: &dyn Siblings) -> Result<IdentifiedFile<'d>, Error> {
+    let data = data.into();
+    if data.starts_with(b"MSCF") {
+        if let Some(volumes) = cabinet_set(&data, name, siblings) {
+            debug!("joining {} volumes of the cabinet set containing {:?}", volumes.len(), name);
+            let volume_slices: Vec<&[u8]> = volumes.iter()
+                .map(|v| v.as_slice())
+                .collect();
+            let cab = Cabinet::new_spanned(&volume_slices)?;
+            return Ok(IdentifiedFile::MultiFileContainer(Box::new(cab)));
+        }
+    } else if let Some(joined) = szdd_parts(&data, name, siblings) {
+        return Ok(IdentifiedFile::SingleFileContainer(Box::new(KwajOrSz::new(joined.data))));
+    }
+    interpret_container(data)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::io::Read;
+    use std::path::{Path, PathBuf};
+
+    use super::{interpret_container, interpret_container_volume, interpret_file};
+    use crate::data_mgmt::{Error, IdentifiedFile, Symbol};
+    use crate::volumes::Siblings;
+    use testgen::{cab, iso9660, kwaj, ne, pe, regf, sfx, szdd, tar};
+
+    fn read_symbols(identified: IdentifiedFile) -> Vec<Symbol> {
+        match identified {
+            IdentifiedFile::SymbolExporter(exporter) => exporter.read_symbols()
+                .expect("failed to read symbols"),
+            _ => panic!("file not identified as a symbol exporter"),
+        }
+    }
+
+    fn synthesized_pe() -> Vec<u8> {
+        let mut library = pe::PortableExecutable::new("SYNTH.DLL");
+        library.exports = vec![
+            pe::Export { ordinal: 2, name: Some("Beta".to_owned()), target: pe::ExportTarget::Code { rva: pe::TEXT_RVA + 0x10 } },
+            pe::Export { ordinal: 3, name: None, target: pe::ExportTarget::Code { rva: pe::TEXT_RVA + 0x20 } },
+            pe::Export { ordinal: 5, name: Some("Alpha".to_owned()), target: pe::ExportTarget::Code { rva: pe::TEXT_RVA } },
+            pe::Export { ordinal: 6, name: Some("Gamma".to_owned()), target: pe::ExportTarget::Forwarder { target: "OTHER.Gamma".to_owned() } },
+        ];
+        library.to_bytes()
+    }
+
+    #[test]
+    fn test_interpret_synthesized_pe() {
+        let symbols = read_symbols(interpret_file(synthesized_pe()).unwrap());
+        assert_eq!(
+            symbols,
+            vec![
+                Symbol::ByNameAndOrdinal { name: "Beta".to_owned(), ordinal: 2 },
+                Symbol::ByOrdinal { ordinal: 3 },
+                Symbol::ByNameAndOrdinal { name: "Alpha".to_owned(), ordinal: 5 },
+                Symbol::ByNameAndOrdinal { name: "Gamma".to_owned(), ordinal: 6 },
+            ],
+        );
+    }
+
+    #[test]
+    fn test_synthesized_pe_forwarders() {
+        let Ok(IdentifiedFile::SymbolExporter(symex)) = interpret_file(synthesized_pe()) else {
+            panic!("file not identified as a symbol exporter");
+        };
+        let alpha = Symbol::ByNameAndOrdinal { name: "Alpha".to_owned(), ordinal: 5 };
+        let gamma = Symbol::ByNameAndOrdinal { name: "Gamma".to_owned(), ordinal: 6 };
+        assert_eq!(symex.forwarder(&alpha), None);
+        assert_eq!(symex.section_class(&alpha), Some(binms::pe::SectionClass::Code));
+        assert_eq!(symex.forwarder(&gamma).as_deref(), Some("OTHER.Gamma"));
+        assert_eq!(symex.section_class(&gamma), None);
+    }
+
+    #[test]
+    fn test_interpret_synthesized_ne() {
+        let mut library = ne::NewExecutable::new("SYNTH");
+        library.description = "Synthesized library".to_owned();
+        library.exports = vec![
+            ne::Export { ordinal: 1, name: Some("WEP".to_owned()), resident: true },
+            ne::Export { ordinal: 2, name: Some("SYNTHPROC".to_owned()), resident: false },
+            ne::Export { ordinal: 4, name: None, resident: false },
+        ];
+        let symbols = read_symbols(interpret_file(library.to_bytes()).unwrap());
+        assert_eq!(
+            symbols,
+            vec![
+                Symbol::ByNameAndOrdinal { name: "WEP".to_owned(), ordinal: 1 },
+                Symbol::ByNameAndOrdinal { name: "SYNTHPROC".to_owned(), ordinal: 2 },
+                Symbol::ByOrdinal { ordinal: 4 },
+            ],
+        );
+    }
+
+    #[test]
+    fn test_interpret_self_loading_ne() {
+        let library = ne::NewExecutable::new("SYNTH").to_bytes();
+        let flags_offset = 0x40 + 12;
+        let target_os_offset = 0x40 + 54;
+        let remark_and_warning = |flags: u16, target_os: u8| {
+            let mut bytes = library.clone();
+            bytes[flags_offset..flags_offset+2].copy_from_slice(&flags.to_le_bytes());
+            bytes[target_os_offset] = target_os;
+            let Ok(IdentifiedFile::SymbolExporter(exporter)) = interpret_file(bytes) else { panic!("not a symbol exporter") };
+            (exporter.remark(), exporter.warning())
+        };
+
+        assert_eq!(remark_and_warning(0x8000, 0x02), (None, None));
+        let (remark, warning) = remark_and_warning(0x8800, 0x02);
+        assert_eq!(remark.as_deref(), Some("self-loading"));
+        assert!(warning.is_some());
+
+        // OS/2 uses the flag for bound family applications
+        assert_eq!(remark_and_warning(0x8800, 0x01), (Some("bound family application".to_owned()), None));
+    }
+
+    #[test]
+    fn test_interpret_compressed_pe() {
+        let compressed = kwaj::compress(&synthesized_pe(), kwaj::Method::Stored, Some("SYNTH.DLL"));
+        let decompressed = match interpret_container(&compressed).unwrap() {
+            IdentifiedFile::SingleFileContainer(container) => container.read_file()
+                .expect("failed to decompress")
+                .into_owned(),
+            _ => panic!("file not identified as a single-file container"),
+        };
+        assert_eq!(decompressed, synthesized_pe());
+    }
+
+    #[test]
+    fn test_interpret_registry_hive() {
+        let mut server = regf::Key::new("InprocServer32");
+        server.values = vec![(String::new(), "C:\\Windows\\System32\\synth.dll".to_owned())];
+        let mut class = regf::Key::new("{12345678-1234-1234-1234-123456789ABC}");
+        class.subkeys = vec![server];
+        let mut clsid = regf::Key::new("CLSID");
+        clsid.subkeys = vec![class];
+        let mut classes = regf::Key::new("Classes");
+        classes.subkeys = vec![clsid];
+        let mut root = regf::Key::new("ROOT");
+        root.subkeys = vec![classes];
+
+        let symbols = read_symbols(interpret_file(regf::hive(&root)).unwrap());
+        assert_eq!(
+            symbols,
+            vec![
+                Symbol::ComServer { name: "{12345678-1234-1234-1234-123456789ABC}\\InprocServer32=C:\\Windows\\System32\\synth.dll".to_owned() },
+            ],
+        );
+    }
+
+    #[test]
+    fn test_nested_iso_is_borrowed() {
+        let mut inner = iso9660::Volume::new("INNER");
+        inner.files.push(iso9660::File { name: "SYNTH.DLL".to_owned(), data: synthesized_pe() });
+        let mut outer = iso9660::Volume::new("OUTER");
+        outer.files.push(iso9660::File { name: "INNER.ISO".to_owned(), data: inner.to_bytes() });
+        let outer_bytes = outer.to_bytes();
+        let outer_range = outer_bytes.as_ptr_range();
+
+        let IdentifiedFile::MultiFileContainer(outer_container) = interpret_container(&outer_bytes).unwrap() else {
+            panic!("outer image not identified as a multi-file container");
+        };
+        let inner_bytes = outer_container.read_file(Path::new("INNER.ISO;1")).unwrap();
+        assert!(matches!(inner_bytes, Cow::Borrowed(_)));
+
+        let IdentifiedFile::MultiFileContainer(inner_container) = interpret_container(inner_bytes.as_ref()).unwrap() else {
+            panic!("inner image not identified as a multi-file container");
+        };
+        let library = inner_container.read_file(Path::new("SYNTH.DLL;1")).unwrap();
+        let Cow::Borrowed(library) = library else {
+            panic!("library has been copied out of the image");
+        };
+        assert!(outer_range.contains(&library.as_ptr()));
+        assert_eq!(read_symbols(interpret_file(library).unwrap()).len(), 4);
+    }
+
+    #[test]
+    fn test_interpret_windows_layer_tarball() {
+        let tarball = tar::tarball(
+            &[
+                tar::Entry::directory("Files/"),
+                tar::Entry::file("Files/Windows/System32/synth.dll", &synthesized_pe()),
+                tar::Entry::file("Files/Windows/System32/.wh.gone.dll", b""),
+                tar::Entry::file("Hives/Software_Delta", b"regf"),
+            ],
+            tar::LongNames::Pax,
+        );
+
+        let IdentifiedFile::MultiFileContainer(container) = interpret_file(&tarball).unwrap() else {
+            panic!("tarball not identified as a multi-file container");
+        };
+        assert_eq!(
+            container.list_files().unwrap(),
+            vec![Path::new("Hives/Software_Delta").to_owned(), Path::new("Windows/System32/synth.dll").to_owned()],
+        );
+        let library = container.read_file(Path::new("Windows/System32/synth.dll")).unwrap();
+        assert!(matches!(library, Cow::Borrowed(_)));
+        assert_eq!(read_symbols(interpret_file(library).unwrap()).len(), 4);
+    }
+
+    struct NamedFiles(Vec<(String, Vec<u8>)>);
+    impl Siblings for NamedFiles {
+        fn read_sibling(&self, name: &str) -> Option<Vec<u8>> {
+            self.0.iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, d)| d.clone())
+        }
+    }
+
+    #[test]
+    fn test_interpret_spanned_cabinet_set() {
+        let readme: Vec<u8> = (0..=255u8).cycle().take(700).collect();
+        let files = [
+            cab::File { name: "README.TXT".to_owned(), data: readme.clone() },
+            cab::File { name: "SYNTH.DLL".to_owned(), data: synthesized_pe() },
+        ];
+        let cabinet_name = |i: usize| if i == 0 { "SYNTH.CAB".to_owned() } else { format!("SYNTH{}.CAB", i + 1) };
+        let volumes = cab::spanned_set(&files, 256, 2, cabinet_name);
+        assert!(volumes.len() >= 3);
+        let mut siblings = NamedFiles(volumes.iter()
+            .enumerate()
+            .map(|(i, v)| (cabinet_name(i).to_lowercase(), v.clone()))
+            .collect());
+
+        // opening any volume opens the whole set
+        let IdentifiedFile::MultiFileContainer(set) = interpret_container_volume(&volumes[1], "synth2.cab", &siblings).unwrap() else {
+            panic!("cabinet not identified as a multi-file container");
+        };
+        assert_eq!(
+            set.list_files().unwrap(),
+            vec![Path::new("README.TXT").to_owned(), Path::new("SYNTH.DLL").to_owned()],
+        );
+        assert_eq!(set.read_file(Path::new("README.TXT")).unwrap().as_ref(), readme.as_slice());
+        let library = set.read_file(Path::new("SYNTH.DLL")).unwrap();
+        assert_eq!(read_symbols(interpret_file(library).unwrap()).len(), 4);
+
+        // without the last volume, the library cannot be completed
+        siblings.0.pop();
+        let IdentifiedFile::MultiFileContainer(set) = interpret_container_volume(&volumes[0], "synth.cab", &siblings).unwrap() else {
+            panic!("cabinet not identified as a multi-file container");
+        };
+        assert_eq!(set.read_file(Path::new("README.TXT")).unwrap().as_ref(), readme.as_slice());
+        assert!(matches!(set.read_file(Path::new("SYNTH.DLL")), Err(Error::SpannedFile)));
+    }
+
+    #[test]
+    fn test_interpret_split_szdd() {
+        let compressed = szdd::compress(&synthesized_pe(), b'L');
+        let (first, second) = compressed.split_at(compressed.len() / 2);
+        let siblings = NamedFiles(vec![("SYNTH2.DL_".to_owned(), second.to_vec())]);
+
+        let IdentifiedFile::SingleFileContainer(container) = interpret_container_volume(first, "SYNTH.DL_", &siblings).unwrap() else {
+            panic!("file not identified as a single-file container");
+        };
+        assert_eq!(container.read_file().unwrap().as_ref(), synthesized_pe().as_slice());
+
+        // later parts may come with an SZDD header of their own
+        let third_length = compressed.len() / 3;
+        let mut siblings = NamedFiles(Vec::new());
+        for (i, part) in compressed[third_length..].chunks(third_length).enumerate() {
+            let mut part_with_header = compressed[..14].to_vec();
+            part_with_header.extend_from_slice(part);
+            siblings.0.push((format!("SYNTH{}.DL_", i + 2), part_with_header));
+        }
+        assert!(siblings.0.len() >= 2);
+        let IdentifiedFile::SingleFileContainer(container) = interpret_container_volume(&compressed[..third_length], "SYNTH.DL_", &siblings).unwrap() else {
+            panic!("file not identified as a single-file container");
+        };
+        assert_eq!(container.read_file().unwrap().as_ref(), synthesized_pe().as_slice());
+    }
+
+    #[test]
+    fn test_container_entries() {
+        let mut volume = iso9660::Volume::new("ENTRIES");
+        volume.files.push(iso9660::File { name: "README.TXT".to_owned(), data: b"Read me!".to_vec() });
+        volume.files.push(iso9660::File { name: "SYNTH.DL_".to_owned(), data: szdd::compress(&synthesized_pe(), b'L') });
+        let volume_bytes = volume.to_bytes();
+
+        let IdentifiedFile::MultiFileContainer(container) = interpret_container(&volume_bytes).unwrap() else {
+            panic!("image not identified as a multi-file container");
+        };
+        let mut entries: Vec<_> = container.entries().unwrap().collect();
+        entries.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, Path::new("README.TXT;1"));
+        assert_eq!(entries[0].size, Some(8));
+
+        let mut readme = String::new();
+        entries[0].read_to_string(&mut readme).unwrap();
+        assert_eq!(readme, "Read me!");
+
+        let compressed = entries.pop().unwrap().into_data().unwrap();
+        assert!(matches!(compressed, Cow::Borrowed(_)));
+        let IdentifiedFile::SingleFileContainer(single) = interpret_container(compressed).unwrap() else {
+            panic!("file not identified as a single-file container");
+        };
+        let entry = single.entry();
+        assert_eq!(entry.size, Some(u64::try_from(synthesized_pe().len()).unwrap()));
+        assert_eq!(entry.into_data().unwrap().as_ref(), synthesized_pe().as_slice());
+    }
+
+    #[test]
+    fn test_self_extracting_archives() {
+        let readme: &[u8] = b"Read me!";
+        let pe_bytes = synthesized_pe();
+        let files = [("README.TXT", readme), ("SYSTEM/SYNTH.DLL", pe_bytes.as_slice())];
+        let archives = [
+            ("ZIP SFX", sfx::zip(&files)),
+            ("LHA SFX", sfx::lha(&files)),
+            ("LHA SFX", sfx::lha_level_1(&files)),
+            ("ARJ SFX", sfx::arj(&files)),
+            ("ARJ SFX", sfx::arj_with_method(&files, 1)),
+            ("ARJ SFX", sfx::arj_with_method(&files, 2)),
+            ("ARJ SFX", sfx::arj_with_method(&files, 3)),
+        ];
+        for (format_name, archive) in archives {
+            let IdentifiedFile::MultiFileContainer(container) = interpret_file(sfx::self_extracting(&archive)).unwrap() else {
+                panic!("{} not identified as a multi-file container", format_name);
+            };
+            assert_eq!(container.format_name(), format_name);
+            assert_eq!(
+                container.list_files().unwrap(),
+                vec![PathBuf::from("README.TXT"), PathBuf::from("SYSTEM/SYNTH.DLL")],
+            );
+            assert_eq!(container.read_file(Path::new("README.TXT")).unwrap().as_ref(), readme);
+            let library = container.read_file(Path::new("SYSTEM/SYNTH.DLL")).unwrap();
+            assert_eq!(read_symbols(interpret_file(library).unwrap()).len(), 4);
+        }
+    }
+
+    #[test]
+    fn test_self_extracting_archive_after_false_header() {
+        // a level-0 LHA header whose checksum matches but whose file name exceeds the header
+        let mut bogus = vec![22, 0];
+        bogus.extend_from_slice(b"-lh0-");
+        bogus.extend_from_slice(&[0; 13]);
+        bogus.push(0); // header level
+        bogus.push(200); // file name length
+        bogus.extend_from_slice(&[0; 2]);
+        bogus[1] = bogus[2..24].iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+
+        let readme: &[u8] = b"Read me!";
+        let mut overlay = bogus;
+        overlay.extend_from_slice(&sfx::lha(&[("README.TXT", readme)]));
+        let IdentifiedFile::MultiFileContainer(container) = interpret_file(sfx::self_extracting(&overlay)).unwrap() else {
+            panic!("not identified as a multi-file container");
+        };
+        assert_eq!(container.format_name(), "LHA SFX");
+        assert_eq!(container.read_file(Path::new("README.TXT")).unwrap().as_ref(), readme);
+
+        // without an archive, the executable is not a container, but reading it does not fail
+        let mut overlay_only = overlay.clone();
+        overlay_only.truncate(24);
+        assert!(!matches!(interpret_file(sfx::self_extracting(&overlay_only)), Ok(IdentifiedFile::MultiFileContainer(_))));
+    }
+}
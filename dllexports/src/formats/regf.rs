@@ -0,0 +1,39 @@
+use std::io::Cursor;
+
+use binms::regf::{ComServer, Hive};
+
+use crate::data_mgmt::{Error, Symbol, SymbolExporter};
+
+
+/// A registry hive, interpreted as exporting the servers of the COM classes registered in it.
+///
+/// Installation media contain hive templates (e.g. `SOFTWARE`) that register the DLLs and
+/// executables implementing COM classes; these complement the exports of the DLLs themselves.
+#[derive(Clone, Debug)]
+pub(crate) struct RegistryHive {
+    servers: Vec<ComServer>,
+}
+impl RegistryHive {
+    pub fn new(bytes: &[u8]) -> Result<Self, Error> {
+        let hive = Hive::read(&mut Cursor::new(bytes))?;
+        let servers = hive.com_servers()?;
+        Ok(Self {
+            servers,
+        })
+    }
+}
+impl SymbolExporter for RegistryHive {
+    fn format_name(&self) -> &str {
+        "registry hive"
+    }
+
+    fn read_symbols(&self) -> Result<Vec<Symbol>, Error> {
+        let symbols = self.servers
+            .iter()
+            .map(|server| Symbol::ComServer {
+                name: format!("{}\\{}={}", server.clsid, server.server_kind, server.server_path),
+            })
+            .collect();
+        Ok(symbols)
+    }
+}
@@ -0,0 +1,175 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+
+use binms::decode_byte_name;
+use expandms::arj::{self, ArjEntry, FileType};
+use expandms::lha::{self, LhaEntry};
+use expandms::zip::{self, ZipEntry};
+use tracing::debug;
+
+use crate::data_mgmt::{Error, MultiFileContainer};
+
+
+/// The kind of archive appended to a DOS executable and the offset at which it starts.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum ArchiveStart {
+    /// A ZIP archive, which is found through the end of its central directory instead.
+    Zip,
+    Lha(usize),
+    Arj(usize),
+}
+
+
+/// Looks for an archive appended to the image of a DOS executable, as done by self-extractors such
+/// as PKZIP's `ZIP2EXE`, `LHA /S` and `ARJ -je`, and reads its entries.
+///
+/// Only the overlay (the data after the end of the image according to the MZ header) is searched
+/// for LHA and ARJ headers, since the extraction code may contain their magic values. Since the
+/// overlay may contain data that only looks like a header, each candidate is read in turn until
+/// one of them turns out to be an archive.
+pub(crate) fn find_archive(data: &[u8]) -> Option<ArchiveIndex> {
+    let executable = match binms::mz::Executable::read(&mut Cursor::new(data)) {
+        Ok(e) => e,
+        Err(e) => {
+            debug!("failed to read MZ header: {}", e);
+            return None;
+        },
+    };
+    let overlay_offset = usize::try_from(executable.image_end_offset()).ok()?;
+    if overlay_offset >= data.len() {
+        return None;
+    }
+
+    let zip_candidate = zip::find_end_of_central_directory(data)
+        .map(|_| ArchiveStart::Zip);
+    let overlay_candidates = (overlay_offset..data.len())
+        .filter_map(|offset| {
+            if lha::looks_like_header(data, offset) {
+                Some(ArchiveStart::Lha(offset))
+            } else if arj::looks_like_archive(data, offset) {
+                Some(ArchiveStart::Arj(offset))
+            } else {
+                None
+            }
+        });
+    for start in zip_candidate.into_iter().chain(overlay_candidates) {
+        match ArchiveIndex::read(data, start) {
+            Ok(index) => return Some(index),
+            Err(e) => debug!("no archive at {:?}: {}", start, e),
+        }
+    }
+    None
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum Entries {
+    Zip(Vec<ZipEntry>),
+    Lha(Vec<LhaEntry>),
+    Arj(Vec<ArjEntry>),
+}
+
+
+/// The entries of an archive appended to a DOS executable, along with the index of the entry of
+/// each path.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) struct ArchiveIndex {
+    entries: Entries,
+    path_to_index: BTreeMap<PathBuf, usize>,
+}
+impl ArchiveIndex {
+    fn read(data: &[u8], start: ArchiveStart) -> Result<Self, io::Error> {
+        let mut path_to_index = BTreeMap::new();
+        let entries = match start {
+            ArchiveStart::Zip => {
+                let (entries, prefix_length) = zip::read_central_directory(data)?;
+                debug!("ZIP archive starts at {:#X}", prefix_length);
+                for (index, entry) in entries.iter().enumerate() {
+                    if !entry.is_directory() {
+                        path_to_index.insert(PathBuf::from(decode_byte_name(&entry.path)), index);
+                    }
+                }
+                Entries::Zip(entries)
+            },
+            ArchiveStart::Lha(offset) => {
+                let entries = lha::read_entries(data, offset)?;
+                for (index, entry) in entries.iter().enumerate() {
+                    if !entry.is_directory() {
+                        path_to_index.insert(PathBuf::from(decode_byte_name(&entry.path)), index);
+                    }
+                }
+                Entries::Lha(entries)
+            },
+            ArchiveStart::Arj(offset) => {
+                let (_main_header, entries) = arj::read_entries(data, offset)?;
+                for (index, entry) in entries.iter().enumerate() {
+                    if matches!(entry.file_type, FileType::Binary | FileType::Text) {
+                        path_to_index.insert(PathBuf::from(decode_byte_name(&entry.path)), index);
+                    }
+                }
+                Entries::Arj(entries)
+            },
+        };
+        Ok(Self {
+            entries,
+            path_to_index,
+        })
+    }
+}
+
+
+/// A DOS self-extracting archive: a ZIP, LHA or ARJ archive appended to the extraction program.
+///
+/// Directories are not listed. If a path appears multiple times, the last entry wins, as when the
+/// archive is extracted.
+pub(crate) struct SelfExtractingArchive<'d> {
+    bytes: Cow<'d, [u8]>,
+    entries: Entries,
+    path_to_index: BTreeMap<PathBuf, usize>,
+}
+impl<'d> SelfExtractingArchive<'d> {
+    /// Wraps the data of a self-extractor whose archive has been found using [`find_archive`].
+    pub fn new(bytes: Cow<'d, [u8]>, index: ArchiveIndex) -> Self {
+        Self {
+            bytes,
+            entries: index.entries,
+            path_to_index: index.path_to_index,
+        }
+    }
+}
+impl<'d> fmt::Debug for SelfExtractingArchive<'d> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SelfExtractingArchive")
+            .field("entries", &self.entries)
+            .field("path_to_index", &self.path_to_index)
+            .finish_non_exhaustive()
+    }
+}
+impl<'d> MultiFileContainer for SelfExtractingArchive<'d> {
+    fn format_name(&self) -> &str {
+        match &self.entries {
+            Entries::Zip(_) => "ZIP SFX",
+            Entries::Lha(_) => "LHA SFX",
+            Entries::Arj(_) => "ARJ SFX",
+        }
+    }
+
+    fn list_files(&self) -> Result<Vec<PathBuf>, Error> {
+        Ok(self.path_to_index.keys().cloned().collect())
+    }
+
+    fn read_file(&self, file_path: &Path) -> Result<Cow<'_, [u8]>, Error> {
+        let index = *self.path_to_index.get(file_path)
+            .ok_or_else(|| Error::FileNotFound(file_path.to_owned()))?;
+        let mut data = Vec::new();
+        match &self.entries {
+            Entries::Zip(entries) => zip::decompress(&self.bytes, &entries[index], &mut data)?,
+            Entries::Lha(entries) => lha::decompress(&self.bytes, &entries[index], &mut data)?,
+            Entries::Arj(entries) => arj::decompress(&self.bytes, &entries[index], &mut data)?,
+        }
+        Ok(Cow::Owned(data))
+    }
+}
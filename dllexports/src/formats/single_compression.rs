@@ -1,24 +1,42 @@
+use std::borrow::Cow;
 use std::io::Cursor;
+use std::path::PathBuf;
 
-use crate::data_mgmt::SingleFileContainer;
+use crate::data_mgmt::{ContainerEntry, SingleFileContainer};
 
 
 #[derive(Debug)]
-pub(crate) struct KwajOrSz {
-    compressed_data: Vec<u8>,
+pub(crate) struct KwajOrSz<'d> {
+    compressed_data: Cow<'d, [u8]>,
 }
-impl KwajOrSz {
-    pub fn new<B: Into<Vec<u8>>>(compressed_data: B) -> Self {
+impl<'d> KwajOrSz<'d> {
+    pub fn new<B: Into<Cow<'d, [u8]>>>(compressed_data: B) -> Self {
         Self {
             compressed_data: compressed_data.into(),
         }
     }
 }
-impl SingleFileContainer for KwajOrSz {
-    fn read_file(&self) -> Result<Vec<u8>, crate::data_mgmt::Error> {
-        let mut reader = Cursor::new(&self.compressed_data);
+impl<'d> SingleFileContainer for KwajOrSz<'d> {
+    fn format_name(&self) -> &str {
+        if self.compressed_data.starts_with(b"KWAJ") {
+            "KWAJ"
+        } else if self.compressed_data.starts_with(b"SZDD") {
+            "SZDD"
+        } else {
+            "SZ"
+        }
+    }
+
+    fn read_file(&self) -> Result<Cow<'_, [u8]>, crate::data_mgmt::Error> {
+        let mut reader = Cursor::new(self.compressed_data.as_ref());
         let mut decompressed_data = Vec::new();
         expandms::decompress(&mut reader, &mut decompressed_data)?;
-        Ok(decompressed_data)
+        Ok(Cow::Owned(decompressed_data))
+    }
+
+    fn entry(&self) -> ContainerEntry<'_> {
+        let size = expandms::decompressed_size(&self.compressed_data)
+            .map(u64::from);
+        ContainerEntry::new(PathBuf::new(), size, move || self.read_file())
     }
 }
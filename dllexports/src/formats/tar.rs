@@ -0,0 +1,274 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use tracing::debug;
+
+use crate::data_mgmt::{Error, MultiFileContainer};
+
+
+const BLOCK_LENGTH: usize = 512;
+
+/// The prefix of the name of a file that marks the file without the prefix as deleted in a
+/// container image layer.
+const WHITEOUT_PREFIX: &str = ".wh.";
+
+/// The directory containing the file system in a Windows container image layer.
+const WINDOWS_LAYER_FILES_DIR: &str = "Files/";
+
+/// The directory containing the registry hives in a Windows container image layer.
+const WINDOWS_LAYER_HIVES_DIR: &str = "Hives/";
+
+
+/// Returns whether the data looks like a POSIX (ustar) or GNU tarball.
+pub(crate) fn is_tarball(data: &[u8]) -> bool {
+    data.get(257..262) == Some(b"ustar")
+}
+
+
+/// A tarball, e.g. a layer of a container image.
+///
+/// Only regular files are listed. Whiteout files, which mark files as deleted in a container image
+/// layer, are skipped. If the tarball is a layer of a Windows container image (with the file system
+/// in `Files/` and the registry hives in `Hives/`), the `Files/` prefix is stripped so that the
+/// paths match those on an installed system.
+pub(crate) struct Tarball<'d> {
+    bytes: Cow<'d, [u8]>,
+    path_to_range: BTreeMap<PathBuf, Range<usize>>,
+}
+impl<'d> Tarball<'d> {
+    pub fn new(bytes: Cow<'d, [u8]>) -> Result<Self, Error> {
+        let entries = read_entries(&bytes)?;
+        let is_windows_layer = entries.keys().any(|p| p.starts_with(WINDOWS_LAYER_FILES_DIR))
+            && entries.keys().any(|p| p.starts_with(WINDOWS_LAYER_HIVES_DIR));
+
+        let mut path_to_range = BTreeMap::new();
+        for (path, range) in entries {
+            let file_name = path.rsplit('/').next().unwrap();
+            if file_name.starts_with(WHITEOUT_PREFIX) {
+                debug!("skipping whiteout {:?}", path);
+                continue;
+            }
+            let path = match path.strip_prefix(WINDOWS_LAYER_FILES_DIR) {
+                Some(stripped) if is_windows_layer => stripped,
+                _ => path.as_str(),
+            };
+            path_to_range.insert(PathBuf::from(path), range);
+        }
+
+        Ok(Self {
+            bytes,
+            path_to_range,
+        })
+    }
+}
+impl<'d> fmt::Debug for Tarball<'d> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tarball")
+            .field("path_to_range", &self.path_to_range)
+            .finish_non_exhaustive()
+    }
+}
+impl<'d> MultiFileContainer for Tarball<'d> {
+    fn format_name(&self) -> &str {
+        "TAR"
+    }
+
+    fn list_files(&self) -> Result<Vec<PathBuf>, Error> {
+        Ok(self.path_to_range.keys().cloned().collect())
+    }
+
+    fn read_file(&self, file_path: &Path) -> Result<Cow<'_, [u8]>, Error> {
+        let range = self.path_to_range.get(file_path)
+            .ok_or_else(|| Error::FileNotFound(file_path.to_owned()))?;
+        Ok(Cow::Borrowed(&self.bytes[range.clone()]))
+    }
+}
+
+
+/// Reads the regular files of a tarball along with the ranges of their data, keyed by their paths
+/// (with `/` as separator and without a leading `./`).
+///
+/// Hard links are listed with the data of their target, which must precede them. If a path appears
+/// multiple times, the last entry wins, as when the tarball is extracted.
+fn read_entries(bytes: &[u8]) -> Result<BTreeMap<String, Range<usize>>, Error> {
+    let mut entries: BTreeMap<String, Range<usize>> = BTreeMap::new();
+    let mut next_path: Option<String> = None;
+    let mut next_link_name: Option<String> = None;
+    let mut offset = 0;
+    while let Some(header) = bytes.get(offset..offset+BLOCK_LENGTH) {
+        if header.iter().all(|b| *b == 0x00) {
+            // end of archive
+            break;
+        }
+
+        let Some(size) = parse_size(&header[124..136]) else {
+            debug!("invalid size in tar header at {:#X}", offset);
+            return Err(Error::Io(std::io::ErrorKind::InvalidData.into()));
+        };
+        let data_start = offset + BLOCK_LENGTH;
+        let Some(data_end) = data_start.checked_add(size).filter(|end| *end <= bytes.len()) else {
+            debug!("data of tar entry at {:#X} extends past the end of the file", offset);
+            return Err(Error::Io(std::io::ErrorKind::UnexpectedEof.into()));
+        };
+        let data = &bytes[data_start..data_end];
+
+        let type_flag = header[156];
+        match type_flag {
+            b'L' => {
+                // GNU long name of the next entry
+                next_path = Some(String::from_utf8_lossy(nul_terminated(data)).into_owned());
+            },
+            b'K' => {
+                // GNU long link target of the next entry
+                next_link_name = Some(String::from_utf8_lossy(nul_terminated(data)).into_owned());
+            },
+            b'x' => {
+                // pax extended header of the next entry
+                if let Some(path) = pax_record(data, "path") {
+                    next_path = Some(path);
+                }
+                if let Some(link_name) = pax_record(data, "linkpath") {
+                    next_link_name = Some(link_name);
+                }
+            },
+            b'0' | b'\0' | b'7' => {
+                let path = next_path.take().unwrap_or_else(|| header_path(header));
+                next_link_name = None;
+                entries.insert(normalize_path(&path), data_start..data_end);
+            },
+            b'1' => {
+                let path = next_path.take().unwrap_or_else(|| header_path(header));
+                let link_name = next_link_name.take()
+                    .unwrap_or_else(|| String::from_utf8_lossy(nul_terminated(&header[157..257])).into_owned());
+                match entries.get(&normalize_path(&link_name)) {
+                    Some(target_range) => {
+                        let target_range = target_range.clone();
+                        entries.insert(normalize_path(&path), target_range);
+                    },
+                    None => debug!("target {:?} of hard link {:?} not found", link_name, path),
+                }
+            },
+            _ => {
+                // directory, symbolic link, device etc.
+                next_path = None;
+                next_link_name = None;
+            },
+        }
+
+        offset = data_start + size.next_multiple_of(BLOCK_LENGTH);
+    }
+    Ok(entries)
+}
+
+fn normalize_path(path: &str) -> String {
+    path.trim_start_matches("./").to_owned()
+}
+
+fn nul_terminated(bytes: &[u8]) -> &[u8] {
+    match bytes.iter().position(|b| *b == 0x00) {
+        Some(nul_index) => &bytes[..nul_index],
+        None => bytes,
+    }
+}
+
+/// Returns the path stored in a tar header, joining the ustar prefix and the name.
+fn header_path(header: &[u8]) -> String {
+    let name = String::from_utf8_lossy(nul_terminated(&header[0..100]));
+    let prefix = if &header[257..263] == b"ustar\0" {
+        String::from_utf8_lossy(nul_terminated(&header[345..500]))
+    } else {
+        // GNU tarballs store other information there
+        Cow::Borrowed("")
+    };
+    if prefix.is_empty() {
+        name.into_owned()
+    } else {
+        format!("{}/{}", prefix, name)
+    }
+}
+
+/// Parses the size field of a tar header, which is stored either as octal digits or, for large
+/// sizes, as a big-endian binary number with the top bit of the first byte set.
+fn parse_size(field: &[u8]) -> Option<usize> {
+    if field[0] & 0x80 != 0 {
+        let mut value: u64 = u64::from(field[0] & 0x7F);
+        for b in &field[1..] {
+            value = value.checked_mul(256)?.checked_add(u64::from(*b))?;
+        }
+        return usize::try_from(value).ok();
+    }
+
+    let digits = std::str::from_utf8(field).ok()?
+        .trim_matches(|c| c == ' ' || c == '\0');
+    if digits.is_empty() {
+        return Some(0);
+    }
+    usize::from_str_radix(digits, 8).ok()
+}
+
+/// Returns the value of the record with the given key in a pax extended header.
+///
+/// Each record has the form `<length> <key>=<value>\n`, with the length counting the whole record.
+fn pax_record(data: &[u8], key: &str) -> Option<String> {
+    let mut rest = data;
+    while !rest.is_empty() {
+        let space_index = rest.iter().position(|b| *b == b' ')?;
+        let length: usize = std::str::from_utf8(&rest[..space_index]).ok()?.parse().ok()?;
+        if length <= space_index || length > rest.len() {
+            return None;
+        }
+        let record = &rest[space_index+1..length];
+        let record = record.strip_suffix(b"\n").unwrap_or(record);
+        if let Some(value) = record.strip_prefix(key.as_bytes()).and_then(|r| r.strip_prefix(b"=")) {
+            return Some(String::from_utf8_lossy(value).into_owned());
+        }
+        rest = &rest[length..];
+    }
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::path::{Path, PathBuf};
+    use testgen::tar::{tarball, Entry, EntryType, LongNames};
+    use super::Tarball;
+    use crate::data_mgmt::MultiFileContainer;
+
+    #[test]
+    fn test_long_names_and_hard_links() {
+        let long_dir = "Windows/WinSxS/amd64_microsoft-windows-synthesized-library_31bf3856ad364e35_10.0.19041.1_none_0123456789abcdef";
+        let long_path = format!("{}/synth.dll", long_dir);
+        let long_link = format!("{}/synth-link.dll", long_dir);
+        assert!(long_path.len() > 100);
+        let entries = [
+            Entry::directory("./Windows/"),
+            Entry::file("./Windows/readme.txt", b"Read me!"),
+            Entry::file(&long_path, b"MZ"),
+            Entry::link(EntryType::HardLink, "./Windows/readme-again.txt", "Windows/readme.txt"),
+            Entry::link(EntryType::HardLink, &long_link, &long_path),
+            Entry::link(EntryType::HardLink, "Windows/dangling.txt", "Windows/missing.txt"),
+            Entry::link(EntryType::SymbolicLink, "Windows/symlink.txt", "readme.txt"),
+        ];
+
+        for long_names in [LongNames::Gnu, LongNames::Pax] {
+            let tar = Tarball::new(Cow::Owned(tarball(&entries, long_names))).unwrap();
+            assert_eq!(
+                tar.list_files().unwrap(),
+                vec![
+                    PathBuf::from(&long_link),
+                    PathBuf::from(&long_path),
+                    PathBuf::from("Windows/readme-again.txt"),
+                    PathBuf::from("Windows/readme.txt"),
+                ],
+            );
+            assert_eq!(tar.read_file(Path::new("Windows/readme-again.txt")).unwrap().as_ref(), b"Read me!");
+            assert_eq!(tar.read_file(Path::new(&long_path)).unwrap().as_ref(), b"MZ");
+            assert_eq!(tar.read_file(Path::new(&long_link)).unwrap().as_ref(), b"MZ");
+        }
+    }
+}
@@ -0,0 +1,410 @@
+//! Heuristic classification of text files that describe other files.
+
+
+use crate::data_mgmt::{CopiedFile, Metadata, MetadataKind, MetadataSection};
+
+
+/// Text files larger than this are not considered for classification.
+const MAX_TEXT_SIZE: usize = 4*1024*1024;
+
+/// How much of the start of a file is decoded to decide whether (and which kind of) text it is.
+///
+/// Only files that look like text at the start are decoded in full.
+const CLASSIFICATION_PREFIX_SIZE: usize = 8*1024;
+
+/// Commands that commonly start a line in a batch file.
+const BATCH_COMMANDS: [&str; 22] = [
+    "@", "call", "cd", "cls", "copy", "del", "echo", "erase", "exit", "for", "goto", "if", "md",
+    "mkdir", "move", "path", "pause", "rd", "rem", "ren", "set", "shift",
+];
+
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum TextKind {
+    Reg,
+    Ini,
+    Batch,
+}
+
+
+/// Attempts to interpret the data as an INF, REG, INI or batch file.
+pub(crate) fn interpret_text(data: &[u8]) -> Option<Metadata> {
+    if data.len() > MAX_TEXT_SIZE {
+        return None;
+    }
+    let kind = classify(data)?;
+
+    let text = decode_text(data)?;
+    let lines: Vec<&str> = text.lines()
+        .map(|l| l.trim())
+        .collect();
+
+    match kind {
+        TextKind::Reg => {
+            let first_line_index = lines.iter()
+                .position(|l| !l.is_empty())?;
+            let sections = parse_sections(&lines[first_line_index+1..], false);
+            Some(Metadata {
+                kind: MetadataKind::Reg,
+                sections,
+                ..Default::default()
+            })
+        },
+        TextKind::Ini => {
+            if !looks_like_ini(&lines) {
+                return None;
+            }
+            let sections = parse_sections(&lines, true);
+            if is_inf(&sections) {
+                let source_disks_files = collect_source_disks_files(&sections);
+                let copied_files = collect_copied_files(&sections);
+                return Some(Metadata {
+                    kind: MetadataKind::Inf,
+                    sections,
+                    source_disks_files,
+                    copied_files,
+                });
+            }
+            Some(Metadata {
+                kind: MetadataKind::Ini,
+                sections,
+                ..Default::default()
+            })
+        },
+        TextKind::Batch => {
+            let entries = lines.iter()
+                .filter(|l| !l.is_empty())
+                .map(|l| (None, (*l).to_owned()))
+                .collect();
+            Some(Metadata {
+                kind: MetadataKind::Batch,
+                sections: vec![MetadataSection {
+                    name: String::new(),
+                    entries,
+                }],
+                ..Default::default()
+            })
+        },
+    }
+}
+
+
+/// Decides from the start of the data which kind of text file it is, if any.
+fn classify(data: &[u8]) -> Option<TextKind> {
+    let text = decode_text(text_prefix(data))?;
+    let lines: Vec<&str> = text.lines()
+        .map(|l| l.trim())
+        .collect();
+    let first_line = lines.iter()
+        .find(|l| !l.is_empty())?;
+
+    if first_line.eq_ignore_ascii_case("REGEDIT4") || first_line.starts_with("Windows Registry Editor Version ") {
+        Some(TextKind::Reg)
+    } else if looks_like_ini(&lines) {
+        Some(TextKind::Ini)
+    } else if looks_like_batch(&lines) {
+        Some(TextKind::Batch)
+    } else {
+        None
+    }
+}
+
+/// Returns the first [`CLASSIFICATION_PREFIX_SIZE`] bytes of the data (or a little less, so as not
+/// to split a character).
+fn text_prefix(data: &[u8]) -> &[u8] {
+    if data.len() <= CLASSIFICATION_PREFIX_SIZE {
+        return data;
+    }
+    let mut end = CLASSIFICATION_PREFIX_SIZE;
+    if data.starts_with(b"\xFF\xFE") {
+        // the prefix size is even, so only a surrogate pair may be split
+        if (0xD8..=0xDB).contains(&data[end - 1]) {
+            end -= 2;
+        }
+    } else {
+        // back up to the start of a UTF-8 sequence
+        while end > 0 && data[end] & 0b1100_0000 == 0b1000_0000 {
+            end -= 1;
+        }
+    }
+    &data[..end]
+}
+
+/// Decodes the data as UTF-16LE (if it has a byte order mark) or as a single-byte encoding, making
+/// sure it doesn't contain binary data.
+fn decode_text(data: &[u8]) -> Option<String> {
+    let text = if let Some(utf16_data) = data.strip_prefix(b"\xFF\xFE") {
+        if !utf16_data.len().is_multiple_of(2) {
+            return None;
+        }
+        let words: Vec<u16> = utf16_data
+            .chunks(2)
+            .map(|ch| u16::from_le_bytes(ch.try_into().unwrap()))
+            .collect();
+        String::from_utf16(&words).ok()?
+    } else {
+        let data = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+        match std::str::from_utf8(data) {
+            Ok(s) => s.to_owned(),
+            // assume a single-byte code page; the characters we care about are ASCII anyway
+            Err(_) => data.iter().map(|b| char::from(*b)).collect(),
+        }
+    };
+
+    // DOS text files often end with a Ctrl+Z
+    let text = text.trim_end_matches('\u{1A}');
+
+    let is_binary = text.chars()
+        .any(|c| c.is_control() && c != '\r' && c != '\n' && c != '\t' && c != '\u{0C}');
+    if is_binary || text.trim().is_empty() {
+        None
+    } else {
+        Some(text.to_owned())
+    }
+}
+
+/// Removes a comment (starting with a semicolon outside of quotes) from the line.
+fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => return line[..i].trim_end(),
+            _ => {},
+        }
+    }
+    line
+}
+
+fn section_name(line: &str) -> Option<&str> {
+    line.strip_prefix('[')
+        .and_then(|l| l.strip_suffix(']'))
+}
+
+fn looks_like_ini(lines: &[&str]) -> bool {
+    let mut seen_section = false;
+    for line in lines {
+        let line = strip_comment(line);
+        if line.is_empty() {
+            continue;
+        }
+        if section_name(line).is_some() {
+            seen_section = true;
+        } else if !seen_section {
+            // INI files start with a section
+            return false;
+        }
+    }
+    seen_section
+}
+
+fn looks_like_batch(lines: &[&str]) -> bool {
+    let mut non_empty = 0usize;
+    let mut commands = 0usize;
+    for line in lines {
+        if line.is_empty() {
+            continue;
+        }
+        non_empty += 1;
+
+        let lower = line.to_ascii_lowercase();
+        let is_command = lower.starts_with(':')
+            || BATCH_COMMANDS.iter().any(|cmd| {
+                lower.strip_prefix(cmd)
+                    .map(|rest| *cmd == "@" || rest.is_empty() || rest.starts_with([' ', '.', '%']))
+                    .unwrap_or(false)
+            });
+        if is_command {
+            commands += 1;
+        }
+    }
+    non_empty > 0 && commands * 2 >= non_empty
+}
+
+fn parse_sections(lines: &[&str], strip_comments: bool) -> Vec<MetadataSection> {
+    let mut sections: Vec<MetadataSection> = Vec::new();
+    for line in lines {
+        let line = if strip_comments { strip_comment(line) } else { line };
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = section_name(line) {
+            sections.push(MetadataSection {
+                name: name.trim().to_owned(),
+                entries: Vec::new(),
+            });
+            continue;
+        }
+
+        // lines before the first section end up in an unnamed section
+        if sections.is_empty() {
+            sections.push(MetadataSection::default());
+        }
+        let entry = match line.split_once('=') {
+            Some((key, value)) => (Some(key.trim().to_owned()), value.trim().to_owned()),
+            None => (None, line.to_owned()),
+        };
+        sections.last_mut().unwrap().entries.push(entry);
+    }
+    sections
+}
+
+fn unquote(value: &str) -> &str {
+    value.strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn is_inf(sections: &[MetadataSection]) -> bool {
+    for section in sections {
+        if section.name.eq_ignore_ascii_case("Version") {
+            let has_signature = section.entries.iter()
+                .filter(|(k, _)| k.as_deref().is_some_and(|k| k.eq_ignore_ascii_case("Signature")))
+                .any(|(_, v)| unquote(v).starts_with('$'));
+            if has_signature {
+                return true;
+            }
+        }
+        if section.name.to_ascii_lowercase().starts_with("sourcedisksfiles") {
+            return true;
+        }
+    }
+    false
+}
+
+fn collect_source_disks_files(sections: &[MetadataSection]) -> Vec<String> {
+    // [SourceDisksFiles], [SourceDisksFiles.x86] etc.
+    let mut files = Vec::new();
+    for section in sections {
+        if !section.name.to_ascii_lowercase().starts_with("sourcedisksfiles") {
+            continue;
+        }
+        for (key, value) in &section.entries {
+            let name = key.as_deref().unwrap_or(value.as_str());
+            let name = unquote(name.trim());
+            if !name.is_empty() && !files.iter().any(|f: &String| f.eq_ignore_ascii_case(name)) {
+                files.push(name.to_owned());
+            }
+        }
+    }
+    files
+}
+
+fn collect_copied_files(sections: &[MetadataSection]) -> Vec<CopiedFile> {
+    let mut copied_files: Vec<CopiedFile> = Vec::new();
+    let mut push = |destination: &str, source: &str| {
+        let copied_file = CopiedFile {
+            destination: destination.to_owned(),
+            source: source.to_owned(),
+        };
+        if !copied_files.contains(&copied_file) {
+            copied_files.push(copied_file);
+        }
+    };
+
+    for section in sections {
+        for (key, value) in &section.entries {
+            if !key.as_deref().is_some_and(|k| k.eq_ignore_ascii_case("CopyFiles")) {
+                continue;
+            }
+
+            for list_name in value.split(',').map(|v| v.trim()) {
+                if let Some(file_name) = list_name.strip_prefix('@') {
+                    // single file, copied directly
+                    push(file_name, file_name);
+                    continue;
+                }
+
+                let Some(list_section) = sections.iter().find(|s| s.name.eq_ignore_ascii_case(list_name)) else {
+                    continue;
+                };
+                for (list_key, list_value) in &list_section.entries {
+                    if list_key.is_some() {
+                        // not a file entry
+                        continue;
+                    }
+
+                    // destination[,source[,temporary[,flags]]]
+                    let mut fields = list_value.split(',').map(|f| unquote(f.trim()));
+                    let Some(destination) = fields.next() else { continue };
+                    if destination.is_empty() {
+                        continue;
+                    }
+                    let source = fields.next()
+                        .filter(|s| !s.is_empty())
+                        .unwrap_or(destination);
+                    push(destination, source);
+                }
+            }
+        }
+    }
+    copied_files
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::data_mgmt::MetadataKind;
+    use super::{interpret_text, text_prefix, CLASSIFICATION_PREFIX_SIZE};
+
+
+    #[test]
+    fn test_interpret_text() {
+        let inf = b"[Version]\r\nSignature=\"$Windows NT$\"\r\n\r\n[SourceDisksFiles]\r\nsynth.dll=1\r\n";
+        let metadata = interpret_text(inf).unwrap();
+        assert_eq!(metadata.kind, MetadataKind::Inf);
+        assert_eq!(metadata.source_disks_files, vec!["synth.dll".to_owned()]);
+
+        let metadata = interpret_text(b"REGEDIT4\r\n\r\n[HKEY_CLASSES_ROOT\\.txt]\r\n@=\"txtfile\"\r\n").unwrap();
+        assert_eq!(metadata.kind, MetadataKind::Reg);
+        assert_eq!(metadata.sections[0].name, "HKEY_CLASSES_ROOT\\.txt");
+
+        let metadata = interpret_text(b"@echo off\r\ncopy a.dll c:\\windows\r\n\x1A").unwrap();
+        assert_eq!(metadata.kind, MetadataKind::Batch);
+        assert_eq!(metadata.sections[0].entries.len(), 2);
+
+        assert!(interpret_text(b"MZ\x90\x00\x03\x00").is_none());
+    }
+
+    #[test]
+    fn test_classify_from_prefix() {
+        // an INI file whose sections continue beyond the prefix
+        let mut ini = b"[Section]\r\n".to_vec();
+        while ini.len() < 3 * CLASSIFICATION_PREFIX_SIZE {
+            ini.extend_from_slice(b"Key=Value\r\n");
+        }
+        ini.extend_from_slice(b"[Last]\r\nKey=Value\r\n");
+        let metadata = interpret_text(&ini).unwrap();
+        assert_eq!(metadata.kind, MetadataKind::Ini);
+        assert_eq!(metadata.sections.last().unwrap().name, "Last");
+
+        // binary data beyond the prefix is still rejected
+        let mut damaged = ini.clone();
+        damaged.extend_from_slice(b"\x00\x01\x02");
+        assert!(interpret_text(&damaged).is_none());
+    }
+
+    #[test]
+    fn test_text_prefix() {
+        // a multi-byte UTF-8 sequence straddling the end of the prefix
+        let mut utf8 = vec![b'a'; CLASSIFICATION_PREFIX_SIZE - 1];
+        utf8.extend_from_slice("\u{E9}".as_bytes());
+        assert_eq!(text_prefix(&utf8).len(), CLASSIFICATION_PREFIX_SIZE - 1);
+
+        // a UTF-16 surrogate pair straddling the end of the prefix
+        let mut utf16 = b"\xFF\xFE".to_vec();
+        while utf16.len() < CLASSIFICATION_PREFIX_SIZE - 2 {
+            utf16.extend_from_slice(&u16::from(b'a').to_le_bytes());
+        }
+        for unit in "\u{1F600}".encode_utf16() {
+            utf16.extend_from_slice(&unit.to_le_bytes());
+        }
+        assert_eq!(text_prefix(&utf16).len(), CLASSIFICATION_PREFIX_SIZE - 2);
+        assert!(String::from_utf16(
+            &text_prefix(&utf16)[2..].chunks(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect::<Vec<_>>()
+        ).is_ok());
+
+        let short = b"[Section]";
+        assert_eq!(text_prefix(short), short);
+    }
+}
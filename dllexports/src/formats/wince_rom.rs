@@ -0,0 +1,185 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use binms::pe::SectionCharacteristics;
+use binms::wince_rom::{O32Rom, Rom, RomImage, RomModule};
+use tracing::{debug, warn};
+
+use crate::data_mgmt::{Error, MultiFileContainer};
+
+
+const PE_HEADER_OFFSET: usize = 0x40;
+const OPTIONAL_HEADER_SIZE: usize = 224;
+const FILE_ALIGNMENT: usize = 0x200;
+const SECTION_ALIGNMENT: u32 = 0x1000;
+
+
+/// A Windows CE ROM image, interpreted as a container of the modules and files it contains.
+///
+/// Since modules in ROM only retain a condensed form of their headers, each module is reassembled
+/// into a PE file. Sections compressed by `romimage` are not supported and are left empty.
+pub(crate) struct WinCeRom {
+    image: RomImage,
+    rom: Rom,
+}
+impl WinCeRom {
+    pub fn new_from_bin(bytes: &[u8]) -> Result<Self, Error> {
+        let image = RomImage::read_bin(&mut Cursor::new(bytes))?;
+        Self::new(image)
+    }
+
+    pub fn new_from_raw(bytes: &[u8]) -> Result<Self, Error> {
+        let image = RomImage::from_raw(bytes.to_vec())?;
+        Self::new(image)
+    }
+
+    fn new(image: RomImage) -> Result<Self, Error> {
+        let rom = Rom::read(&image)?;
+        Ok(Self {
+            image,
+            rom,
+        })
+    }
+
+    /// Guesses the name of a section, which is not stored in the ROM.
+    fn section_name(module: &RomModule, section: &O32Rom) -> &'static [u8; 8] {
+        let characteristics = section.characteristics();
+        let resources = &module.e32.units[2];
+        if resources.address != 0 && resources.address == section.rva {
+            b".rsrc\0\0\0"
+        } else if characteristics.contains(SectionCharacteristics::CONTAINS_CODE) {
+            b".text\0\0\0"
+        } else if characteristics.contains(SectionCharacteristics::CONTAINS_UNINITIALIZED_DATA) {
+            b".bss\0\0\0\0"
+        } else if characteristics.contains(SectionCharacteristics::MEM_WRITE) {
+            b".data\0\0\0"
+        } else {
+            b".rdata\0\0"
+        }
+    }
+
+    /// Reassembles a PE file from the condensed headers and the sections of a module.
+    fn reassemble_module(&self, module: &RomModule) -> Vec<u8> {
+        let e32 = &module.e32;
+        let section_table_offset = PE_HEADER_OFFSET + 4 + 20 + OPTIONAL_HEADER_SIZE;
+        let headers_size = (section_table_offset + 40 * module.sections.len()).next_multiple_of(FILE_ALIGNMENT);
+
+        let mut bytes = vec![0u8; headers_size];
+
+        // MZ header, without a stub
+        bytes[0..2].copy_from_slice(b"MZ");
+        bytes[8..10].copy_from_slice(&4u16.to_le_bytes()); // header size in paragraphs
+        bytes[24..26].copy_from_slice(&0x0040u16.to_le_bytes()); // relocation table offset
+        bytes[60..64].copy_from_slice(&u32::try_from(PE_HEADER_OFFSET).unwrap().to_le_bytes());
+
+        // PE header
+        let pe = &mut bytes[PE_HEADER_OFFSET..];
+        pe[0..4].copy_from_slice(b"PE\0\0");
+        pe[4..6].copy_from_slice(&self.rom.header.cpu_type.to_base_type().to_le_bytes());
+        pe[6..8].copy_from_slice(&u16::try_from(module.sections.len()).unwrap().to_le_bytes());
+        pe[8..12].copy_from_slice(&e32.timestamp.unwrap_or(0).to_le_bytes());
+        pe[20..22].copy_from_slice(&u16::try_from(OPTIONAL_HEADER_SIZE).unwrap().to_le_bytes());
+        pe[22..24].copy_from_slice(&e32.image_flags.to_le_bytes());
+
+        // optional header (PE32)
+        let opt = &mut pe[24..24+OPTIONAL_HEADER_SIZE];
+        opt[0..2].copy_from_slice(&0x010Bu16.to_le_bytes());
+        opt[16..20].copy_from_slice(&e32.entry_point_rva.to_le_bytes());
+        opt[28..32].copy_from_slice(&e32.virtual_base.to_le_bytes());
+        opt[32..36].copy_from_slice(&SECTION_ALIGNMENT.to_le_bytes());
+        opt[36..40].copy_from_slice(&u32::try_from(FILE_ALIGNMENT).unwrap().to_le_bytes());
+        opt[48..50].copy_from_slice(&e32.subsystem_major.to_le_bytes());
+        opt[50..52].copy_from_slice(&e32.subsystem_minor.to_le_bytes());
+        opt[56..60].copy_from_slice(&e32.virtual_size.to_le_bytes());
+        opt[60..64].copy_from_slice(&u32::try_from(headers_size).unwrap().to_le_bytes());
+        opt[68..70].copy_from_slice(&e32.subsystem.to_le_bytes());
+        opt[72..76].copy_from_slice(&e32.stack_max.to_le_bytes());
+        opt[92..96].copy_from_slice(&16u32.to_le_bytes()); // number of data directory entries
+        for (i, unit) in e32.units.iter().enumerate() {
+            let entry_offset = 96 + 8*i;
+            opt[entry_offset..entry_offset+4].copy_from_slice(&unit.address.to_le_bytes());
+            opt[entry_offset+4..entry_offset+8].copy_from_slice(&unit.size.to_le_bytes());
+        }
+
+        // sections
+        for (i, section) in module.sections.iter().enumerate() {
+            let data = if section.physical_size == 0 {
+                None
+            } else if section.is_compressed() {
+                warn!("section at RVA {:#010X} of {} is compressed; leaving it empty", section.rva, module.name);
+                None
+            } else {
+                let data = self.image.slice(section.data_address, section.physical_size);
+                if data.is_none() {
+                    debug!("section at RVA {:#010X} of {} is outside the image", section.rva, module.name);
+                }
+                data
+            };
+            let (raw_pointer, raw_size) = match data {
+                Some(d) => {
+                    let raw_pointer = bytes.len();
+                    bytes.extend_from_slice(d);
+                    bytes.resize(bytes.len().next_multiple_of(FILE_ALIGNMENT), 0x00);
+                    (u32::try_from(raw_pointer).unwrap(), u32::try_from(d.len()).unwrap())
+                },
+                None => (0, 0),
+            };
+
+            let header_offset = section_table_offset + 40*i;
+            let header = &mut bytes[header_offset..header_offset+40];
+            header[0..8].copy_from_slice(Self::section_name(module, section));
+            header[8..12].copy_from_slice(&section.virtual_size.to_le_bytes());
+            header[12..16].copy_from_slice(&section.rva.to_le_bytes());
+            header[16..20].copy_from_slice(&raw_size.to_le_bytes());
+            header[20..24].copy_from_slice(&raw_pointer.to_le_bytes());
+            header[36..40].copy_from_slice(&section.characteristics().bits().to_le_bytes());
+        }
+
+        bytes
+    }
+}
+impl fmt::Debug for WinCeRom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the image is too large to be worth outputting
+        f.debug_struct("WinCeRom")
+            .field("base_address", &self.image.base_address)
+            .field("rom", &self.rom)
+            .finish_non_exhaustive()
+    }
+}
+impl MultiFileContainer for WinCeRom {
+    fn format_name(&self) -> &str {
+        "Windows CE ROM"
+    }
+
+    fn list_files(&self) -> Result<Vec<PathBuf>, Error> {
+        let modules = self.rom.modules
+            .iter()
+            .map(|m| PathBuf::from(&m.name));
+        let files = self.rom.files
+            .iter()
+            .map(|f| PathBuf::from(&f.name));
+        Ok(modules.chain(files).collect())
+    }
+
+    fn read_file(&self, file_path: &Path) -> Result<Cow<'_, [u8]>, Error> {
+        if let Some(module) = self.rom.modules.iter().find(|m| Path::new(&m.name) == file_path) {
+            return Ok(Cow::Owned(self.reassemble_module(module)));
+        }
+
+        let file = self.rom.files
+            .iter()
+            .find(|f| Path::new(&f.name) == file_path)
+            .ok_or_else(|| Error::FileNotFound(file_path.to_owned()))?;
+        if file.is_compressed() {
+            warn!("{} is compressed, which is not supported", file.name);
+            return Err(std::io::Error::from(std::io::ErrorKind::Unsupported).into());
+        }
+        let data = self.image.slice(file.load_address, file.real_size)
+            .ok_or_else(|| Error::FileNotFound(file_path.to_owned()))
+            .inspect_err(|_| debug!("{} is outside the image", file.name))?;
+        Ok(Cow::Borrowed(data))
+    }
+}
@@ -0,0 +1,138 @@
+//! SHA-1 and SHA-256 digests of file data.
+//!
+//! SHA-256 identifies files throughout the program, e.g. in the scan cache and when comparing
+//! containers. SHA-1 is additionally offered in listings because Windows catalog files and many
+//! file databases identify files by it.
+
+
+use std::io::{self, Write};
+
+use serde::{Serialize, Serializer};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+
+/// A SHA-1 digest.
+pub(crate) type Sha1Digest = [u8; 20];
+
+/// A SHA-256 digest.
+pub(crate) type Sha256Digest = [u8; 32];
+
+
+/// Calculates the SHA-256 digest of the data.
+pub(crate) fn sha256(data: &[u8]) -> Sha256Digest {
+    Sha256::digest(data).into()
+}
+
+
+/// Formats a digest as lowercase hexadecimal.
+pub(crate) fn to_hex(digest: &[u8]) -> String {
+    let mut ret = String::with_capacity(2*digest.len());
+    for b in digest {
+        ret.push_str(&format!("{:02x}", b));
+    }
+    ret
+}
+
+/// Parses a digest formatted using [`to_hex`] (or in uppercase).
+pub(crate) fn from_hex<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    if hex.len() != 2*N || !hex.is_ascii() {
+        return None;
+    }
+    let mut ret = [0u8; N];
+    for (i, b) in ret.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&hex[2*i..2*i+2], 16).ok()?;
+    }
+    Some(ret)
+}
+
+fn serialize_hex<S: Serializer>(digest: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&to_hex(digest))
+}
+
+
+/// The digests of a file.
+///
+/// Serialized with each digest in lowercase hexadecimal.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub(crate) struct FileDigests {
+    #[serde(serialize_with = "serialize_hex")]
+    pub sha1: Sha1Digest,
+
+    #[serde(serialize_with = "serialize_hex")]
+    pub sha256: Sha256Digest,
+}
+impl FileDigests {
+    /// Calculates the digests of data that is already in memory.
+    pub fn of(data: &[u8]) -> Self {
+        let mut hasher = Hasher::new();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+
+/// Calculates [`FileDigests`] over data that is supplied piece by piece.
+///
+/// Writing to the hasher is equivalent to [`update`](Self::update), so a file can be hashed while
+/// it is read from a container entry using [`std::io::copy`], without holding all of it in memory.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Hasher {
+    sha1: Sha1,
+    sha256: Sha256,
+}
+impl Hasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the data to the calculation.
+    pub fn update(&mut self, data: &[u8]) {
+        self.sha1.update(data);
+        self.sha256.update(data);
+    }
+
+    /// The digests of the data supplied so far.
+    pub fn finalize(self) -> FileDigests {
+        FileDigests {
+            sha1: self.sha1.finalize().into(),
+            sha256: self.sha256.finalize().into(),
+        }
+    }
+}
+impl Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::io;
+    use std::path::PathBuf;
+
+    use super::{from_hex, to_hex, FileDigests, Hasher};
+    use crate::data_mgmt::ContainerEntry;
+
+    #[test]
+    fn test_streamed_entry() {
+        let data = b"The quick brown fox jumps over the lazy dog";
+        let mut entry = ContainerEntry::new(PathBuf::from("FOX.TXT"), None, || Ok(Cow::Borrowed(&data[..])));
+        let mut hasher = Hasher::new();
+        io::copy(&mut entry, &mut hasher).unwrap();
+        let digests = hasher.finalize();
+
+        assert_eq!(digests, FileDigests::of(data));
+        assert_eq!(to_hex(&digests.sha1), "2fd4e1c67a2d28fced849ee1bb76e7391b93eb12");
+        assert_eq!(to_hex(&digests.sha256), "d7a8fbb307d7809469ca9abcb0082e4f8d5651e46d3cdb762d02d0bf37c9e592");
+        assert_eq!(from_hex(&to_hex(&digests.sha256)), Some(digests.sha256));
+        assert_eq!(from_hex::<20>("2fd4e1c67a2d28fced849ee1bb76e7391b93eb1"), None);
+    }
+}
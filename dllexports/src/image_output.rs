@@ -0,0 +1,192 @@
+//! Writing RGBA images into files.
+//!
+//! PNG files are written using the `png` crate; BMP and PPM files are simple enough to be written
+//! directly, which makes them handy for diffing or for piping into other tools. Images stored as
+//! PNG (such as large icons) can be decoded into RGBA pixels to be written in the other formats.
+
+
+use std::io::{self, Write};
+
+
+/// A format into which an RGBA image can be written.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) enum RasterFormat {
+    Png,
+    Bmp,
+    Ppm,
+}
+impl RasterFormat {
+    /// Writes the image, given as rows of RGBA pixels with 8 bits per channel, top row first.
+    pub fn write_rgba8<W: Write>(&self, writer: W, width: u32, height: u32, rgba: &[u8]) -> Result<(), io::Error> {
+        assert_eq!(rgba.len(), 4 * usize::try_from(width).unwrap() * usize::try_from(height).unwrap());
+        match self {
+            Self::Png => write_png(writer, width, height, rgba),
+            Self::Bmp => write_bmp(writer, width, height, rgba),
+            Self::Ppm => write_ppm(writer, width, height, rgba),
+        }
+    }
+}
+
+
+/// Decodes a PNG image into its width, its height and its rows of RGBA pixels with 8 bits per
+/// channel, top row first.
+pub(crate) fn read_png_rgba8(data: &[u8]) -> Result<(u32, u32, Vec<u8>), io::Error> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()
+        .map_err(io::Error::other)?;
+    let mut buf = vec![0u8; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buf)
+        .map_err(io::Error::other)?;
+    buf.truncate(frame.buffer_size());
+
+    let rgba = match frame.color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::Rgb => buf.chunks(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 0xFF])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buf.chunks(2)
+            .flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]])
+            .collect(),
+        png::ColorType::Grayscale => buf.iter()
+            .flat_map(|g| [*g, *g, *g, 0xFF])
+            .collect(),
+        // palettes have been expanded by the transformations
+        png::ColorType::Indexed => return Err(io::ErrorKind::InvalidData.into()),
+    };
+    Ok((frame.width, frame.height, rgba))
+}
+
+fn write_png<W: Write>(writer: W, width: u32, height: u32, rgba: &[u8]) -> Result<(), io::Error> {
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut png_writer = encoder.write_header()
+        .map_err(io::Error::other)?;
+    png_writer.write_image_data(rgba)
+        .map_err(io::Error::other)?;
+    png_writer.finish()
+        .map_err(io::Error::other)
+}
+
+/// Writes a 32-bit BMP file with a `BITMAPINFOHEADER`.
+///
+/// BMP files store their rows bottom-up and their pixels in BGRA order; rows of 32-bit pixels need
+/// no padding.
+fn write_bmp<W: Write>(mut writer: W, width: u32, height: u32, rgba: &[u8]) -> Result<(), io::Error> {
+    const FILE_HEADER_SIZE: u32 = 14;
+    const INFO_HEADER_SIZE: u32 = 40;
+    const BI_RGB: u32 = 0;
+    const PIXELS_PER_METER: i32 = 2835; // 72 DPI
+
+    let signed_width = i32::try_from(width).map_err(|_| io::ErrorKind::InvalidInput)?;
+    let signed_height = i32::try_from(height).map_err(|_| io::ErrorKind::InvalidInput)?;
+    let image_size = u32::try_from(rgba.len()).map_err(|_| io::ErrorKind::InvalidInput)?;
+    let data_offset = FILE_HEADER_SIZE + INFO_HEADER_SIZE;
+    let file_size = data_offset.checked_add(image_size).ok_or(io::ErrorKind::InvalidInput)?;
+
+    let mut header = Vec::with_capacity(data_offset.try_into().unwrap());
+    header.extend_from_slice(b"BM");
+    header.extend_from_slice(&file_size.to_le_bytes());
+    header.extend_from_slice(&0u32.to_le_bytes()); // reserved
+    header.extend_from_slice(&data_offset.to_le_bytes());
+
+    header.extend_from_slice(&INFO_HEADER_SIZE.to_le_bytes());
+    header.extend_from_slice(&signed_width.to_le_bytes());
+    header.extend_from_slice(&signed_height.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes()); // planes
+    header.extend_from_slice(&32u16.to_le_bytes()); // bits per pixel
+    header.extend_from_slice(&BI_RGB.to_le_bytes());
+    header.extend_from_slice(&image_size.to_le_bytes());
+    header.extend_from_slice(&PIXELS_PER_METER.to_le_bytes());
+    header.extend_from_slice(&PIXELS_PER_METER.to_le_bytes());
+    header.extend_from_slice(&0u32.to_le_bytes()); // colors used
+    header.extend_from_slice(&0u32.to_le_bytes()); // important colors
+    writer.write_all(&header)?;
+
+    if width == 0 {
+        return Ok(());
+    }
+    let row_length = 4 * usize::try_from(width).unwrap();
+    let mut row_buf = vec![0u8; row_length];
+    for row in rgba.chunks(row_length).rev() {
+        for (bgra, pixel) in row_buf.chunks_mut(4).zip(row.chunks(4)) {
+            bgra.copy_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+        }
+        writer.write_all(&row_buf)?;
+    }
+    Ok(())
+}
+
+/// Writes a binary (`P6`) PPM file, dropping the alpha channel.
+fn write_ppm<W: Write>(mut writer: W, width: u32, height: u32, rgba: &[u8]) -> Result<(), io::Error> {
+    write!(writer, "P6\n{} {}\n255\n", width, height)?;
+    let rgb: Vec<u8> = rgba.chunks(4)
+        .flat_map(|pixel| &pixel[..3])
+        .copied()
+        .collect();
+    writer.write_all(&rgb)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{read_png_rgba8, RasterFormat};
+
+    #[test]
+    fn test_write_rgba8() {
+        // 2x2: red, green on top; blue, transparent white at the bottom
+        let rgba = [
+            0xFF, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF,
+            0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00,
+        ];
+
+        let mut ppm = Vec::new();
+        RasterFormat::Ppm.write_rgba8(&mut ppm, 2, 2, &rgba).unwrap();
+        let mut expected_ppm = b"P6\n2 2\n255\n".to_vec();
+        expected_ppm.extend_from_slice(&[
+            0xFF, 0x00, 0x00, 0x00, 0xFF, 0x00,
+            0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+        ]);
+        assert_eq!(ppm, expected_ppm);
+
+        let mut bmp = Vec::new();
+        RasterFormat::Bmp.write_rgba8(&mut bmp, 2, 2, &rgba).unwrap();
+        assert_eq!(bmp.len(), 54 + 16);
+        assert_eq!(&bmp[0..2], b"BM");
+        assert_eq!(&bmp[2..6], &70u32.to_le_bytes());
+        assert_eq!(&bmp[10..14], &54u32.to_le_bytes());
+        assert_eq!(&bmp[28..30], &32u16.to_le_bytes());
+        assert_eq!(&bmp[54..], &[
+            0xFF, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x00,
+            0x00, 0x00, 0xFF, 0xFF, 0x00, 0xFF, 0x00, 0xFF,
+        ]);
+    }
+
+    #[test]
+    fn test_read_png_rgba8() {
+        let rgba = [
+            0xFF, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0x80,
+            0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00,
+        ];
+        let mut png = Vec::new();
+        RasterFormat::Png.write_rgba8(&mut png, 2, 2, &rgba).unwrap();
+        assert_eq!(read_png_rgba8(&png).unwrap(), (2, 2, rgba.to_vec()));
+
+        // grayscale is expanded to opaque RGBA
+        let mut gray = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut gray, 3, 1);
+            encoder.set_color(png::ColorType::Grayscale);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[0x00, 0x80, 0xFF]).unwrap();
+        }
+        assert_eq!(
+            read_png_rgba8(&gray).unwrap(),
+            (3, 1, vec![0x00, 0x00, 0x00, 0xFF, 0x80, 0x80, 0x80, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+        );
+
+        assert!(read_png_rgba8(b"\x89PNG\r\n\x1A\n").is_err());
+    }
+}
@@ -0,0 +1,369 @@
+//! One-page summaries of files of any supported format.
+
+
+use std::fmt;
+use std::io::{Cursor, Seek, SeekFrom};
+
+use binms::ne::ExeFlags;
+use binms::nt4dbg::DebugDirectory;
+use binms::pe::{
+    self, ImportDescriptor, KnownDataDirectoryEntry, OptionalHeader, ResourceDirectoryTable,
+};
+use serde::Serialize;
+use tracing::debug;
+
+use crate::data_mgmt::{Error, IdentifiedFile};
+
+
+/// The size of an entry in the debug directory of a PE file.
+const DEBUG_DIRECTORY_ENTRY_SIZE: u32 = 28;
+
+
+/// A summary of a file.
+///
+/// Fields that do not apply to the format of the file (or could not be read) are `None`.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub(crate) struct FileInfo {
+    /// A short description of the format, e.g. `PE32+ DLL` or `CAB`.
+    pub format: String,
+
+    pub architecture: Option<String>,
+    pub subsystem: Option<String>,
+    pub timestamp: Option<String>,
+    pub toolchain: Option<String>,
+    pub remark: Option<String>,
+
+    /// The number of sections (PE) or segments (NE).
+    pub section_count: Option<usize>,
+
+    pub export_count: Option<usize>,
+
+    /// The number of modules from which functions are imported.
+    pub imported_module_count: Option<usize>,
+
+    pub import_count: Option<usize>,
+    pub resource_count: Option<usize>,
+
+    /// The number of files within a container.
+    pub file_count: Option<usize>,
+
+    /// The kinds of debug information referenced by the debug directory, e.g. `CodeView`.
+    pub debug_info: Option<Vec<String>>,
+
+    /// Whether the file contains an Authenticode signature.
+    pub signed: Option<bool>,
+}
+impl fmt::Display for FileInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn line<T: fmt::Display>(f: &mut fmt::Formatter<'_>, key: &str, value: Option<T>) -> fmt::Result {
+            match value {
+                Some(v) => writeln!(f, "{:<16} {}", format!("{}:", key), v),
+                None => Ok(()),
+            }
+        }
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+
+        line(f, "format", Some(&self.format))?;
+        line(f, "architecture", self.architecture.as_ref())?;
+        line(f, "subsystem", self.subsystem.as_ref())?;
+        line(f, "timestamp", self.timestamp.as_ref())?;
+        line(f, "toolchain", self.toolchain.as_ref())?;
+        line(f, "remark", self.remark.as_ref())?;
+        let sections = if self.format.starts_with("NE") { "segments" } else { "sections" };
+        line(f, sections, self.section_count)?;
+        line(f, "exports", self.export_count)?;
+        line(f, "imports", self.import_count.map(|count| match self.imported_module_count {
+            Some(modules) => format!("{} from {} modules", count, modules),
+            None => count.to_string(),
+        }))?;
+        line(f, "resources", self.resource_count)?;
+        line(f, "files", self.file_count)?;
+        line(f, "debug info", self.debug_info.as_ref().map(|kinds| if kinds.is_empty() {
+            "none".to_owned()
+        } else {
+            kinds.join(", ")
+        }))?;
+        line(f, "signed", self.signed.map(yes_no))?;
+        Ok(())
+    }
+}
+
+
+/// Summarizes a file based on the format it has been identified as.
+pub(crate) fn summarize(data: &[u8]) -> Result<FileInfo, Error> {
+    let mut identified = crate::formats::interpret_file(data)?;
+    if matches!(identified, IdentifiedFile::Unidentified) {
+        identified = crate::formats::interpret_container(data)?;
+    }
+    summarize_identified(data, &identified)
+}
+
+/// Summarizes a file that has already been identified.
+///
+/// If the files of a container or the symbols of an exporter cannot be read, the failure is noted
+/// in the remark and the rest of the summary is filled in anyway.
+fn summarize_identified(data: &[u8], identified: &IdentifiedFile) -> Result<FileInfo, Error> {
+    let mut info = FileInfo::default();
+    match identified {
+        IdentifiedFile::MultiFileContainer(container) => {
+            info.format = container.format_name().to_owned();
+            match container.list_files() {
+                Ok(files) => info.file_count = Some(files.len()),
+                Err(e) => add_remark(&mut info, format!("failed to list files: {}", e)),
+            }
+        },
+        IdentifiedFile::SingleFileContainer(container) => {
+            info.format = container.format_name().to_owned();
+            info.file_count = Some(1);
+        },
+        IdentifiedFile::SymbolExporter(exporter) => {
+            info.architecture = exporter.architecture();
+            info.timestamp = exporter.timestamp();
+            info.toolchain = exporter.toolchain();
+            info.remark = exporter.remark();
+            match exporter.read_symbols() {
+                Ok(symbols) => info.export_count = Some(symbols.len()),
+                Err(e) => add_remark(&mut info, format!("failed to read symbols: {}", e)),
+            }
+            let new_header_signature = data.get(0x3C..0x40)
+                .and_then(|offset| usize::try_from(u32::from_le_bytes(offset.try_into().unwrap())).ok())
+                .and_then(|offset| data.get(offset..offset+2));
+            match new_header_signature {
+                Some(b"PE") => summarize_pe(data, &mut info)?,
+                Some(b"NE") => summarize_ne(data, &mut info)?,
+                _ => info.format = "symbol exporter".to_owned(),
+            }
+        },
+        IdentifiedFile::Metadata(metadata) => {
+            info.format = format!("{:?} metadata", metadata.kind);
+        },
+        IdentifiedFile::Unidentified => {
+            info.format = "unknown".to_owned();
+        },
+    }
+    Ok(info)
+}
+
+/// Appends a remark to those already collected.
+fn add_remark(info: &mut FileInfo, remark: String) {
+    info.remark = Some(match info.remark.take() {
+        Some(previous) => format!("{}; {}", previous, remark),
+        None => remark,
+    });
+}
+
+fn summarize_pe(data: &[u8], info: &mut FileInfo) -> Result<(), Error> {
+    let mut reader = Cursor::new(data);
+    let executable = pe::Executable::read(&mut reader)?;
+    let Some(OptionalHeader::Coff(coff)) = executable.optional_header.as_ref() else {
+        info.format = "PE".to_owned();
+        return Ok(());
+    };
+    let is_pe32_plus = coff.magic == pe::PE32_PLUS_MAGIC;
+    let kind = if executable.characteristics.contains(pe::Characteristics::DLL) { "DLL" } else { "executable" };
+    info.format = format!("{} {}", if is_pe32_plus { "PE32+" } else { "PE32" }, kind);
+    info.section_count = Some(executable.section_table.as_entries().len());
+
+    let Some(windows) = coff.optional_windows_header.as_ref() else { return Ok(()) };
+    info.subsystem = Some(format!("{:?}", windows.subsystem));
+    let section_table = &executable.section_table;
+
+    if let Some(import_entry) = windows.known_data_directory_entry(KnownDataDirectoryEntry::ImportTable).filter(|e| e.size > 0) {
+        match ImportDescriptor::read_all(&mut reader, &import_entry, section_table) {
+            Ok(descriptors) => {
+                let mut import_count = 0;
+                for descriptor in &descriptors {
+                    match descriptor.read_lookup_entries(&mut reader, section_table, is_pe32_plus) {
+                        Ok(entries) => import_count += entries.len(),
+                        Err(e) => debug!("failed to read imports from {:?}: {}", descriptor.name, e),
+                    }
+                }
+                info.imported_module_count = Some(descriptors.len());
+                info.import_count = Some(import_count);
+            },
+            Err(e) => debug!("failed to read import descriptors: {}", e),
+        }
+    } else {
+        info.imported_module_count = Some(0);
+        info.import_count = Some(0);
+    }
+
+    if let Some(resource_entry) = windows.known_data_directory_entry(KnownDataDirectoryEntry::ResourceTable).filter(|e| e.size > 0) {
+        match ResourceDirectoryTable::read_root_from_pe(&mut reader, &resource_entry, section_table) {
+            Ok(resources) => info.resource_count = Some(resources.data_entries().len()),
+            Err(e) => debug!("failed to read resources: {}", e),
+        }
+    } else {
+        info.resource_count = Some(0);
+    }
+
+    let mut debug_info = Vec::new();
+    if let Some(debug_entry) = windows.known_data_directory_entry(KnownDataDirectoryEntry::Debug).filter(|e| e.size > 0) {
+        let debug_offset = section_table.virtual_to_raw(debug_entry.address)
+            .ok_or(std::io::ErrorKind::InvalidData.into())
+            .and_then(|offset| reader.seek(SeekFrom::Start(offset.into())));
+        match debug_offset {
+            Ok(_) => {
+                for _ in 0..debug_entry.size / DEBUG_DIRECTORY_ENTRY_SIZE {
+                    match DebugDirectory::read(&mut reader) {
+                        Ok(directory) => {
+                            let kind = format!("{:?}", directory.kind);
+                            if !debug_info.contains(&kind) {
+                                debug_info.push(kind);
+                            }
+                        },
+                        Err(e) => {
+                            debug!("failed to read debug directory entry: {}", e);
+                            break;
+                        },
+                    }
+                }
+            },
+            Err(e) => debug!("failed to find debug directory: {}", e),
+        }
+    }
+    info.debug_info = Some(debug_info);
+
+    // the address of the certificate table is a file offset
+    info.signed = Some(
+        windows.known_data_directory_entry(KnownDataDirectoryEntry::CertificateTable)
+            .is_some_and(|e| e.address > 0 && e.size > 0)
+    );
+    Ok(())
+}
+
+fn summarize_ne(data: &[u8], info: &mut FileInfo) -> Result<(), Error> {
+    let executable = binms::ne::Executable::read(&mut Cursor::new(data))?;
+    let kind = if executable.flags.contains(ExeFlags::LIBRARY_MODULE) { "DLL" } else { "executable" };
+    info.format = format!("NE {}", kind);
+    info.subsystem = Some(match executable.executable_type {
+        0x01 => "OS/2".to_owned(),
+        0x02 => "Windows".to_owned(),
+        0x03 => "European MS-DOS 4".to_owned(),
+        0x04 => "Windows/386".to_owned(),
+        0x05 => "Borland Operating System Services".to_owned(),
+        other => format!("unknown ({:#04X})", other),
+    });
+    info.section_count = Some(executable.segment_table.len());
+    info.resource_count = Some(
+        executable.resource_table.id_to_type.values()
+            .map(|resource_type| resource_type.resources.len())
+            .sum()
+    );
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::path::{Path, PathBuf};
+    use testgen::{cab, ne, pe};
+    use super::{summarize, summarize_identified};
+    use crate::data_mgmt::{Error, IdentifiedFile, MultiFileContainer, Symbol, SymbolExporter};
+
+    /// A container and exporter whose contents cannot be read.
+    #[derive(Debug)]
+    struct Unreadable;
+    impl MultiFileContainer for Unreadable {
+        fn format_name(&self) -> &str {
+            "unreadable"
+        }
+
+        fn list_files(&self) -> Result<Vec<PathBuf>, Error> {
+            Err(Error::Io(std::io::ErrorKind::InvalidData.into()))
+        }
+
+        fn read_file(&self, _file_path: &Path) -> Result<Cow<'_, [u8]>, Error> {
+            Err(Error::Io(std::io::ErrorKind::InvalidData.into()))
+        }
+    }
+    impl SymbolExporter for Unreadable {
+        fn format_name(&self) -> &str {
+            "unreadable"
+        }
+
+        fn read_symbols(&self) -> Result<Vec<Symbol>, Error> {
+            Err(Error::Io(std::io::ErrorKind::InvalidData.into()))
+        }
+
+        fn remark(&self) -> Option<String> {
+            Some("synthesized".to_owned())
+        }
+    }
+
+    #[test]
+    fn test_summarize_pe() {
+        let mut library = pe::PortableExecutable::new("SYNTH.DLL");
+        library.time_date_stamp = 0x3A00_0000;
+        for (ordinal, name) in [(1, "First"), (2, "Second")] {
+            library.exports.push(pe::Export {
+                ordinal,
+                name: Some(name.to_owned()),
+                target: pe::ExportTarget::Code { rva: pe::TEXT_RVA },
+            });
+        }
+        for language_id in [0x0407, 0x0409] {
+            library.resources.push(pe::Resource { type_id: 6, name_id: 1, language_id, data: vec![0; 16] });
+        }
+
+        let info = summarize(&library.to_bytes()).unwrap();
+        assert_eq!(info.format, "PE32 DLL");
+        assert_eq!(info.architecture.as_deref(), Some("I386"));
+        assert_eq!(info.timestamp.as_deref(), Some("2000-11-01T11:35:28Z"));
+        assert_eq!(info.export_count, Some(2));
+        assert_eq!(info.import_count, Some(0));
+        assert_eq!(info.resource_count, Some(2));
+        assert_eq!(info.debug_info, Some(Vec::new()));
+        assert_eq!(info.signed, Some(false));
+        assert_eq!(info.file_count, None);
+    }
+
+    #[test]
+    fn test_summarize_ne() {
+        let mut library = ne::NewExecutable::new("SYNTH");
+        library.exports = vec![
+            ne::Export { ordinal: 1, name: Some("WEP".to_owned()), resident: true },
+            ne::Export { ordinal: 2, name: Some("SYNTHPROC".to_owned()), resident: false },
+        ];
+        library.resources = vec![
+            ne::Resource { type_id: 6, name_id: 1, data: b"\x05Hello".to_vec() },
+        ];
+
+        let info = summarize(&library.to_bytes()).unwrap();
+        assert_eq!(info.format, "NE DLL");
+        assert_eq!(info.subsystem.as_deref(), Some("Windows"));
+        assert_eq!(info.export_count, Some(2));
+        assert_eq!(info.resource_count, Some(1));
+        assert_eq!(info.import_count, None);
+        assert!(info.to_string().contains("segments:"));
+    }
+
+    #[test]
+    fn test_summarize_container() {
+        let files = [
+            cab::File { name: "README.TXT".to_owned(), data: b"Read me!".to_vec() },
+            cab::File { name: "SYNTH.DLL".to_owned(), data: pe::PortableExecutable::new("SYNTH.DLL").to_bytes() },
+        ];
+        let cabinets = cab::spanned_set(&files, 0x8000, 1, |_| "SYNTH.CAB".to_owned());
+        assert_eq!(cabinets.len(), 1);
+
+        let info = summarize(&cabinets[0]).unwrap();
+        assert_eq!(info.format, "CAB");
+        assert_eq!(info.file_count, Some(2));
+        assert_eq!(info.export_count, None);
+    }
+
+    #[test]
+    fn test_summarize_unreadable() {
+        let info = summarize_identified(b"", &IdentifiedFile::MultiFileContainer(Box::new(Unreadable))).unwrap();
+        assert_eq!(info.format, "unreadable");
+        assert_eq!(info.file_count, None);
+        assert!(info.remark.unwrap().starts_with("failed to list files: "));
+
+        let info = summarize_identified(b"", &IdentifiedFile::SymbolExporter(Box::new(Unreadable))).unwrap();
+        assert_eq!(info.format, "symbol exporter");
+        assert_eq!(info.export_count, None);
+        assert!(info.remark.unwrap().starts_with("synthesized; failed to read symbols: "));
+    }
+}
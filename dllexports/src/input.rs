@@ -0,0 +1,137 @@
+//! Opening input files, with `-` standing for standard input.
+//!
+//! Standard input cannot seek, so commands that need to seek in their input receive a copy of it in
+//! memory instead.
+
+
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+
+/// Whether the path stands for standard input.
+pub(crate) fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+
+/// Reads the entire input file (or standard input) into memory.
+pub(crate) fn read(path: &Path) -> Result<Vec<u8>, io::Error> {
+    if is_stdin(path) {
+        let mut data = Vec::new();
+        io::stdin().lock().read_to_end(&mut data)?;
+        Ok(data)
+    } else {
+        std::fs::read(path)
+    }
+}
+
+
+/// Opens the input file (or standard input) for reading from start to end.
+pub(crate) fn open(path: &Path) -> Result<Box<dyn Read>, io::Error> {
+    if is_stdin(path) {
+        Ok(Box::new(io::stdin().lock()))
+    } else {
+        Ok(Box::new(File::open(path)?))
+    }
+}
+
+
+/// Opens the input file for reading and seeking, reading standard input into memory first.
+pub(crate) fn open_seekable(path: &Path) -> Result<SeekableInput, io::Error> {
+    if is_stdin(path) {
+        Ok(SeekableInput::Memory(Cursor::new(read(path)?)))
+    } else {
+        Ok(SeekableInput::File(File::open(path)?))
+    }
+}
+
+
+/// An input that supports seeking.
+#[derive(Debug)]
+pub(crate) enum SeekableInput {
+    File(File),
+    Memory(Cursor<Vec<u8>>),
+}
+impl Read for SeekableInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::File(f) => f.read(buf),
+            Self::Memory(c) => c.read(buf),
+        }
+    }
+}
+impl Seek for SeekableInput {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Self::File(f) => f.seek(pos),
+            Self::Memory(c) => c.seek(pos),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+    use std::path::Path;
+
+    use super::{is_stdin, open, open_seekable, read, SeekableInput};
+
+    fn check_seek_and_read(input: &mut SeekableInput) {
+        let mut buf = [0u8; 4];
+        input.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"0123");
+
+        assert_eq!(input.seek(SeekFrom::Start(6)).unwrap(), 6);
+        input.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"6789");
+
+        assert_eq!(input.seek(SeekFrom::Current(-6)).unwrap(), 4);
+        input.read_exact(&mut buf[..2]).unwrap();
+        assert_eq!(&buf[..2], b"45");
+
+        assert_eq!(input.seek(SeekFrom::End(-1)).unwrap(), 9);
+        let mut rest = Vec::new();
+        input.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"9");
+
+        // reading past the end returns nothing
+        assert_eq!(input.seek(SeekFrom::Start(20)).unwrap(), 20);
+        assert_eq!(input.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_is_stdin() {
+        assert!(is_stdin(Path::new("-")));
+        assert!(!is_stdin(Path::new("./-")));
+        assert!(!is_stdin(Path::new("--")));
+    }
+
+    #[test]
+    fn test_memory_input() {
+        let mut input = SeekableInput::Memory(Cursor::new(b"0123456789".to_vec()));
+        check_seek_and_read(&mut input);
+    }
+
+    #[test]
+    fn test_file_input() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"0123456789").unwrap();
+        file.flush().unwrap();
+
+        assert_eq!(read(file.path()).unwrap(), b"0123456789");
+
+        let mut data = Vec::new();
+        open(file.path()).unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"0123456789");
+
+        let mut input = open_seekable(file.path()).unwrap();
+        assert!(matches!(input, SeekableInput::File(_)));
+        check_seek_and_read(&mut input);
+
+        let missing = file.path().with_extension("missing");
+        assert_eq!(read(&missing).unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(open_seekable(&missing).unwrap_err().kind(), std::io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,633 @@
+//! Consistency checks for executables.
+
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{read_dir, File};
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+
+use binms::pe::{
+    self, BoundImportDescriptor, ExportAddressTableEntry, ExportData, ImportDescriptor,
+    ImportLookupEntry, KnownDataDirectoryEntry, NEW_STYLE_BINDING_TIMESTAMP, OptionalHeader,
+    PE32_PLUS_MAGIC, ResourceChild, ResourceDirectoryTable, ResourceIdentifier,
+};
+use serde::Serialize;
+use tracing::error;
+
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Info => write!(f, "info"),
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+
+/// A single finding of the linter.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub(crate) struct Diagnostic {
+    pub severity: Severity,
+
+    /// A short, stable, machine-readable identifier of the kind of finding.
+    pub code: &'static str,
+
+    pub message: String,
+}
+impl Diagnostic {
+    fn new(severity: Severity, code: &'static str, message: String) -> Self {
+        Self {
+            severity,
+            code,
+            message,
+        }
+    }
+}
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\t{}\t{}", self.severity, self.code, self.message)
+    }
+}
+
+
+/// Checks the export table of a PE file for inconsistencies.
+///
+/// If `search_dirs` is not empty, forwarders are checked against the DLLs found directly within
+/// these directories.
+pub(crate) fn lint_pe_exports(data: &[u8], search_dirs: &[PathBuf]) -> Result<Vec<Diagnostic>, io::Error> {
+    let mut cursor = Cursor::new(data);
+    let executable = pe::Executable::read(&mut cursor)?;
+
+    let export_directory_entry = match &executable.optional_header {
+        Some(OptionalHeader::Coff(coff)) => coff.optional_windows_header
+            .as_ref()
+            .and_then(|owh| owh.known_data_directory_entry(KnownDataDirectoryEntry::ExportTable)),
+        _ => None,
+    };
+    let Some(export_directory_entry) = export_directory_entry else {
+        return Ok(Vec::new())
+    };
+    if export_directory_entry.address == 0 && export_directory_entry.size == 0 {
+        return Ok(Vec::new());
+    }
+
+    let export_data = ExportData::read(&mut cursor, &export_directory_entry, &executable.section_table)?;
+
+    let mut diagnostics = Vec::new();
+    check_addresses(&export_data, &executable.section_table, &mut diagnostics);
+    check_names(&export_data, &mut diagnostics);
+    check_ordinal_gaps(&export_data, &mut diagnostics);
+    if !search_dirs.is_empty() {
+        check_forwarders(&export_data, search_dirs, &mut diagnostics);
+    }
+    Ok(diagnostics)
+}
+
+
+/// Checks how the imports of a PE file are bound and summarizes the binding.
+///
+/// If `search_dirs` is not empty, the timestamps recorded during binding are compared against those
+/// of the DLLs found directly within these directories; the loader ignores bindings to DLLs whose
+/// timestamp has changed.
+pub(crate) fn lint_pe_bound_imports(data: &[u8], search_dirs: &[PathBuf]) -> Result<Vec<Diagnostic>, io::Error> {
+    let mut cursor = Cursor::new(data);
+    let executable = pe::Executable::read(&mut cursor)?;
+
+    let windows_header = match &executable.optional_header {
+        Some(OptionalHeader::Coff(coff)) => coff.optional_windows_header.as_ref(),
+        _ => None,
+    };
+    let Some(windows_header) = windows_header else {
+        return Ok(Vec::new())
+    };
+    let present_entry = |entry: KnownDataDirectoryEntry| windows_header.known_data_directory_entry(entry)
+        .filter(|e| e.address != 0);
+
+    let import_descriptors = match present_entry(KnownDataDirectoryEntry::ImportTable) {
+        Some(entry) => ImportDescriptor::read_all(&mut cursor, &entry, &executable.section_table)?,
+        None => Vec::new(),
+    };
+    let bound_import_descriptors = match present_entry(KnownDataDirectoryEntry::BoundImport) {
+        Some(entry) => BoundImportDescriptor::read_all(&mut cursor, &entry, &executable.section_table)?,
+        None => Vec::new(),
+    };
+
+    let mut diagnostics = Vec::new();
+    let old_style_count = import_descriptors.iter()
+        .filter(|d| d.time_date_stamp != 0 && d.time_date_stamp != NEW_STYLE_BINDING_TIMESTAMP)
+        .count();
+    let new_style_count = import_descriptors.iter()
+        .filter(|d| d.time_date_stamp == NEW_STYLE_BINDING_TIMESTAMP)
+        .count();
+    diagnostics.push(Diagnostic::new(
+        Severity::Info,
+        "binding-summary",
+        format!(
+            "{} of {} imported DLLs are bound ({} old-style, {} new-style)",
+            old_style_count + new_style_count, import_descriptors.len(), old_style_count, new_style_count,
+        ),
+    ));
+
+    // (module name, timestamp) of each binding
+    let mut bindings: Vec<(&str, u32)> = Vec::new();
+    for descriptor in &import_descriptors {
+        if descriptor.time_date_stamp == NEW_STYLE_BINDING_TIMESTAMP {
+            let has_bound_entry = bound_import_descriptors.iter()
+                .any(|b| b.module_name.eq_ignore_ascii_case(&descriptor.name));
+            if !has_bound_entry {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Warning,
+                    "missing-bound-import-entry",
+                    format!("imports from {} are marked as bound but the bound import directory does not mention it", descriptor.name),
+                ));
+            }
+        } else if descriptor.time_date_stamp != 0 {
+            bindings.push((&descriptor.name, descriptor.time_date_stamp));
+        }
+    }
+    for descriptor in &bound_import_descriptors {
+        bindings.push((&descriptor.module_name, descriptor.time_date_stamp));
+        for forwarder_ref in &descriptor.forwarder_refs {
+            bindings.push((&forwarder_ref.module_name, forwarder_ref.time_date_stamp));
+        }
+    }
+
+    if !search_dirs.is_empty() {
+        let available_files = collect_available_files(search_dirs);
+        for (module_name, bound_time_date_stamp) in bindings {
+            let Some(path) = available_files.get(&module_name.to_ascii_lowercase()) else {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Info,
+                    "bound-dll-not-found",
+                    format!("imports are bound to {} but it was not found", module_name),
+                ));
+                continue;
+            };
+            let actual_time_date_stamp = match File::open(path).and_then(|mut f| pe::Executable::read(&mut f)) {
+                Ok(dll) => dll.time_date_stamp,
+                Err(e) => {
+                    error!("failed to read PE header of {}: {}", path.display(), e);
+                    continue;
+                },
+            };
+            if actual_time_date_stamp != bound_time_date_stamp {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Warning,
+                    "stale-binding",
+                    format!(
+                        "imports are bound to {} with timestamp {:#010X} but {} has timestamp {:#010X}",
+                        module_name, bound_time_date_stamp, path.display(), actual_time_date_stamp,
+                    ),
+                ));
+            }
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+
+/// Checks the hints of the functions a PE file imports by name against the name pointer tables of
+/// the DLLs found directly within the given directories.
+///
+/// A hint that does not match makes the loader fall back to a binary search of the name pointer
+/// table, which only costs time; a name that is not exported at all makes loading fail.
+pub(crate) fn lint_pe_import_hints(data: &[u8], search_dirs: &[PathBuf]) -> Result<Vec<Diagnostic>, io::Error> {
+    let mut cursor = Cursor::new(data);
+    let executable = pe::Executable::read(&mut cursor)?;
+
+    let Some(OptionalHeader::Coff(coff)) = &executable.optional_header else {
+        return Ok(Vec::new())
+    };
+    let import_entry = coff.optional_windows_header
+        .as_ref()
+        .and_then(|owh| owh.known_data_directory_entry(KnownDataDirectoryEntry::ImportTable))
+        .filter(|e| e.address != 0);
+    let Some(import_entry) = import_entry else {
+        return Ok(Vec::new())
+    };
+    let is_pe32_plus = coff.magic == PE32_PLUS_MAGIC;
+    let import_descriptors = ImportDescriptor::read_all(&mut cursor, &import_entry, &executable.section_table)?;
+
+    let available_files = collect_available_files(search_dirs);
+    let mut diagnostics = Vec::new();
+    let mut checked_count = 0;
+    let mut matching_count = 0;
+    for descriptor in &import_descriptors {
+        let lookup_entries = descriptor.read_lookup_entries(&mut cursor, &executable.section_table, is_pe32_plus)?;
+
+        let Some(path) = available_files.get(&descriptor.name.to_ascii_lowercase()) else {
+            diagnostics.push(Diagnostic::new(
+                Severity::Info,
+                "imported-dll-not-found",
+                format!("functions are imported from {} but it was not found", descriptor.name),
+            ));
+            continue;
+        };
+        let export_data = match read_export_data(path) {
+            Ok(Some(ed)) => ed,
+            Ok(None) => {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    "imported-dll-without-exports",
+                    format!("functions are imported from {} but {} exports nothing", descriptor.name, path.display()),
+                ));
+                continue;
+            },
+            Err(e) => {
+                error!("failed to read exports of {}: {}", path.display(), e);
+                continue;
+            },
+        };
+
+        for lookup_entry in &lookup_entries {
+            let ImportLookupEntry::ByName { hint, name } = lookup_entry else { continue };
+            checked_count += 1;
+            match check_hint(&export_data, *hint, name) {
+                Some(diagnostic) => diagnostics.push(diagnostic),
+                None => matching_count += 1,
+            }
+        }
+    }
+
+    diagnostics.insert(0, Diagnostic::new(
+        Severity::Info,
+        "hint-summary",
+        format!("{} of {} checked imports by name have a matching hint", matching_count, checked_count),
+    ));
+    Ok(diagnostics)
+}
+
+
+/// Checks that the resource tree of a PE file is fully backed by raw data and that no resource
+/// directory lists the same identifier more than once.
+///
+/// The loader fills the part of a section beyond its raw data with zeroes, and packed executables
+/// sometimes rely on this for their resources. Reading the resources succeeds regardless, but such
+/// files are reported since other tools may fail to read them.
+pub(crate) fn lint_pe_resources(data: &[u8]) -> Result<Vec<Diagnostic>, io::Error> {
+    let mut cursor = Cursor::new(data);
+    let executable = pe::Executable::read(&mut cursor)?;
+
+    let resource_entry = match &executable.optional_header {
+        Some(OptionalHeader::Coff(coff)) => coff.optional_windows_header.as_ref()
+            .and_then(|wh| wh.known_data_directory_entry(KnownDataDirectoryEntry::ResourceTable))
+            .filter(|e| e.address != 0),
+        _ => None,
+    };
+    let Some(resource_entry) = resource_entry else {
+        return Ok(Vec::new())
+    };
+
+    let (root, zero_filled) = ResourceDirectoryTable::read_root_from_pe_with_zero_fill(&mut cursor, &resource_entry, &executable.section_table)?;
+    let mut diagnostics: Vec<Diagnostic> = zero_filled.into_iter()
+        .map(|range| Diagnostic::new(
+            Severity::Warning,
+            "resources-beyond-raw-data",
+            format!(
+                "resource bytes at RVA {:#010X}..{:#010X} are not backed by raw data and read as zeroes",
+                range.start, range.end,
+            ),
+        ))
+        .collect();
+    check_duplicate_resource_ids(&root, &mut Vec::new(), &mut diagnostics);
+    Ok(diagnostics)
+}
+
+/// Reports the identifiers listed more than once in the resource directory and, recursively, in
+/// its subdirectories; `path` contains the identifiers leading to the directory.
+fn check_duplicate_resource_ids(table: &ResourceDirectoryTable, path: &mut Vec<String>, diagnostics: &mut Vec<Diagnostic>) {
+    let id_string = |id: &ResourceIdentifier| match id {
+        ResourceIdentifier::Integer(number) => number.to_string(),
+        ResourceIdentifier::Name(name) => format!("{:?}", name),
+    };
+    for id in table.id_to_entry.duplicate_ids() {
+        diagnostics.push(Diagnostic::new(
+            Severity::Error,
+            "duplicate-resource-id",
+            format!(
+                "resource directory /{} lists {} {} times; lookups find the first of them in the file",
+                path.join("/"), id_string(id), table.id_to_entry.get_all(id).len(),
+            ),
+        ));
+    }
+    for (id, child) in &table.id_to_entry {
+        if let ResourceChild::Subdirectory(subdirectory) = child {
+            path.push(id_string(id));
+            check_duplicate_resource_ids(subdirectory, path, diagnostics);
+            path.pop();
+        }
+    }
+}
+
+/// Reads the export directory of the PE file at the given path, if it has one.
+fn read_export_data(path: &Path) -> Result<Option<ExportData>, io::Error> {
+    let mut file = File::open(path)?;
+    let executable = pe::Executable::read(&mut file)?;
+    let export_directory_entry = match &executable.optional_header {
+        Some(OptionalHeader::Coff(coff)) => coff.optional_windows_header
+            .as_ref()
+            .and_then(|owh| owh.known_data_directory_entry(KnownDataDirectoryEntry::ExportTable))
+            .filter(|e| e.address != 0),
+        _ => None,
+    };
+    let Some(export_directory_entry) = export_directory_entry else {
+        return Ok(None)
+    };
+    let export_data = ExportData::read(&mut file, &export_directory_entry, &executable.section_table)?;
+    Ok(Some(export_data))
+}
+
+/// Checks the hint of a function imported by name against the exporting DLL's name pointer table.
+/// Returns `None` if the hint matches.
+fn check_hint(export_data: &ExportData, hint: u16, name: &str) -> Option<Diagnostic> {
+    let name_at_hint = export_data.name_table
+        .get(usize::from(hint))
+        .map(|(n, _ordinal)| n.as_str());
+    if name_at_hint == Some(name) {
+        return None;
+    }
+
+    let Some(actual_hint) = export_data.hint_for_name(name) else {
+        return Some(Diagnostic::new(
+            Severity::Error,
+            "import-not-exported",
+            format!("{:?} is imported from {} but not exported by it", name, export_data.name),
+        ));
+    };
+    let sorted_note = if export_data.is_name_table_sorted() {
+        ""
+    } else {
+        "; the name table is not sorted, so the binary search may fail"
+    };
+    Some(Diagnostic::new(
+        Severity::Info,
+        "hint-mismatch",
+        format!(
+            "{:?} is imported from {} with hint {} but is at index {}{}",
+            name, export_data.name, hint, actual_hint, sorted_note,
+        ),
+    ))
+}
+
+
+/// Collects the files found directly within the given directories, keyed by their lowercased
+/// names. If a name appears in multiple directories, the first directory wins.
+fn collect_available_files(search_dirs: &[PathBuf]) -> BTreeMap<String, PathBuf> {
+    let mut available_files = BTreeMap::new();
+    for dir in search_dirs {
+        let entries = match read_dir(dir) {
+            Ok(e) => e,
+            Err(e) => {
+                error!("failed to read directory {}: {}", dir.display(), e);
+                continue;
+            },
+        };
+        for entry in entries.flatten() {
+            if let Some(name) = entry.file_name().to_str() {
+                available_files.entry(name.to_ascii_lowercase())
+                    .or_insert_with(|| entry.path());
+            }
+        }
+    }
+    available_files
+}
+
+
+fn check_addresses(export_data: &ExportData, section_table: &pe::SectionTable, diagnostics: &mut Vec<Diagnostic>) {
+    for (ordinal, entry) in &export_data.ordinal_to_address {
+        let ExportAddressTableEntry::Code { code_rva } = entry else { continue };
+        if section_table.section_for_virtual(*code_rva).is_none() {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                "export-outside-section",
+                format!("export with ordinal {} points to {:#010X}, which is not within any section", ordinal, code_rva),
+            ));
+        }
+    }
+}
+
+fn check_names(export_data: &ExportData, diagnostics: &mut Vec<Diagnostic>) {
+    // the loader looks up names using a binary search with byte-wise comparison
+    for (index, pair) in export_data.raw_name_table.windows(2).enumerate() {
+        if pair[0] > pair[1] {
+            let (prev_name, _) = &export_data.name_table[index];
+            let (this_name, _) = &export_data.name_table[index + 1];
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                "names-unsorted",
+                format!("name {:?} at index {} sorts before the preceding name {:?}; lookups by name may fail", this_name, index + 1, prev_name),
+            ));
+        }
+    }
+
+    let mut name_to_ordinals: BTreeMap<&str, Vec<u32>> = BTreeMap::new();
+    for (name, ordinal) in &export_data.name_table {
+        name_to_ordinals.entry(name.as_str())
+            .or_default()
+            .push(*ordinal);
+    }
+    for (name, ordinals) in &name_to_ordinals {
+        if ordinals.len() > 1 {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                "duplicate-name",
+                format!("name {:?} appears {} times (ordinals {:?})", name, ordinals.len(), ordinals),
+            ));
+        }
+    }
+
+    for (name, ordinal) in &export_data.name_table {
+        if !export_data.ordinal_to_address.contains_key(ordinal) {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                "name-without-address",
+                format!("name {:?} refers to ordinal {}, which has no address", name, ordinal),
+            ));
+        }
+    }
+}
+
+fn check_ordinal_gaps(export_data: &ExportData, diagnostics: &mut Vec<Diagnostic>) {
+    let Some(max_ordinal) = export_data.ordinal_to_address.keys().last() else { return };
+
+    let mut gap_start: Option<u32> = None;
+    for ordinal in export_data.ordinal_base..=*max_ordinal {
+        let present = export_data.ordinal_to_address.contains_key(&ordinal);
+        match (gap_start, present) {
+            (None, false) => gap_start = Some(ordinal),
+            (Some(start), true) => {
+                diagnostics.push(ordinal_gap_diagnostic(start, ordinal - 1));
+                gap_start = None;
+            },
+            _ => {},
+        }
+    }
+}
+
+fn ordinal_gap_diagnostic(first: u32, last: u32) -> Diagnostic {
+    let message = if first == last {
+        format!("ordinal {} is unused", first)
+    } else {
+        format!("ordinals {} through {} are unused", first, last)
+    };
+    Diagnostic::new(Severity::Info, "ordinal-gap", message)
+}
+
+fn check_forwarders(export_data: &ExportData, search_dirs: &[PathBuf], diagnostics: &mut Vec<Diagnostic>) {
+    // collect the available file names once
+    let available_files = collect_available_files(search_dirs);
+
+    for (ordinal, entry) in &export_data.ordinal_to_address {
+        let ExportAddressTableEntry::Forwarder { target } = entry else { continue };
+        let Some((module, _function)) = target.rsplit_once('.') else {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                "malformed-forwarder",
+                format!("export with ordinal {} forwards to {:?}, which does not name a module", ordinal, target),
+            ));
+            continue;
+        };
+
+        let module_lower = module.to_ascii_lowercase();
+        if module_lower.starts_with("api-ms-") || module_lower.starts_with("ext-ms-") {
+            // API sets are resolved by the loader and do not exist as files
+            continue;
+        }
+
+        let file_name = format!("{}.dll", module_lower);
+        if !available_files.contains_key(&file_name) {
+            diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                "missing-forwarder-dll",
+                format!("export with ordinal {} forwards to {:?}, but {} was not found", ordinal, target, file_name),
+            ));
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use binms::pe::{ExportData, ResourceChild, ResourceDirectoryTable, ResourceIdentifier};
+    use testgen::pe;
+    use super::{check_duplicate_resource_ids, check_hint, lint_pe_bound_imports, Severity};
+
+    #[test]
+    fn test_check_hint() {
+        let name_table: Vec<(String, u32)> = ["Alpha", "Beta", "Gamma"].into_iter()
+            .enumerate()
+            .map(|(i, n)| (n.to_owned(), u32::try_from(i).unwrap() + 1))
+            .collect();
+        let mut export_data = ExportData {
+            export_flags: 0,
+            time_date_stamp: 0,
+            major_version: 0,
+            minor_version: 0,
+            name: "SYNTH.DLL".to_owned(),
+            ordinal_base: 1,
+            ordinal_to_address: BTreeMap::new(),
+            name_to_ordinal: name_table.iter().cloned().collect(),
+            raw_name_table: name_table.iter().map(|(n, _)| n.as_bytes().to_vec()).collect(),
+            name_table,
+        };
+        assert_eq!(export_data.hint_for_name("Gamma"), Some(2));
+        assert_eq!(check_hint(&export_data, 1, "Beta"), None);
+        assert_eq!(check_hint(&export_data, 0, "Beta").unwrap().code, "hint-mismatch");
+        assert_eq!(check_hint(&export_data, 7, "Beta").unwrap().code, "hint-mismatch");
+        assert_eq!(check_hint(&export_data, 0, "Delta").unwrap().code, "import-not-exported");
+
+        assert!(export_data.is_name_table_sorted());
+        export_data.name_table.swap(0, 2);
+        export_data.raw_name_table.swap(0, 2);
+        assert!(!export_data.is_name_table_sorted());
+        assert!(check_hint(&export_data, 2, "Beta").unwrap().message.contains("not sorted"));
+    }
+
+    #[test]
+    fn test_check_duplicate_resource_ids() {
+        fn table(entries: Vec<(ResourceIdentifier, ResourceChild)>) -> ResourceDirectoryTable {
+            ResourceDirectoryTable {
+                characteristics: 0,
+                timestamp: 0,
+                major_version: 0,
+                minor_version: 0,
+                id_to_entry: entries.into_iter().collect(),
+            }
+        }
+        let languages = |ids: &[u32]| ResourceChild::Subdirectory(table(
+            ids.iter()
+                .map(|id| (ResourceIdentifier::Integer(*id), ResourceChild::Subdirectory(table(Vec::new()))))
+                .collect()
+        ));
+        let root = table(vec![
+            (ResourceIdentifier::Integer(6), ResourceChild::Subdirectory(table(vec![
+                (ResourceIdentifier::Integer(1), languages(&[0x0409, 0x0407, 0x0409, 0x0409])),
+                (ResourceIdentifier::Name("ABOUT".to_owned()), languages(&[0x0409])),
+                (ResourceIdentifier::Name("ABOUT".to_owned()), languages(&[0x0407])),
+            ]))),
+        ]);
+
+        let mut diagnostics = Vec::new();
+        check_duplicate_resource_ids(&root, &mut Vec::new(), &mut diagnostics);
+        let messages: Vec<&str> = diagnostics.iter()
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "resource directory /6 lists \"ABOUT\" 2 times; lookups find the first of them in the file",
+                "resource directory /6/1 lists 1033 3 times; lookups find the first of them in the file",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_lint_pe_bound_imports() {
+        let mut library = pe::PortableExecutable::new("SYNTH.DLL");
+        library.imports = vec![
+            pe::Import {
+                module_name: "KERNEL32.dll".to_owned(),
+                functions: vec![pe::ImportedFunction::ByName { hint: 0, name: "GetVersion".to_owned() }],
+            },
+            pe::Import {
+                module_name: "USER32.dll".to_owned(),
+                functions: vec![pe::ImportedFunction::ByOrdinal(1)],
+            },
+        ];
+        library.bound_imports = vec![pe::BoundImport {
+            module_name: "KERNEL32.dll".to_owned(),
+            time_date_stamp: 0x3B7DFE0E,
+            forwarder_refs: vec![("NTDLL.DLL".to_owned(), 0x3B7DFE0F)],
+        }];
+        let bytes = library.to_bytes();
+
+        let diagnostics = lint_pe_bound_imports(&bytes, &[]).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Info);
+        assert_eq!(diagnostics[0].code, "binding-summary");
+        assert_eq!(diagnostics[0].message, "1 of 2 imported DLLs are bound (0 old-style, 1 new-style)");
+
+        // KERNEL32.DLL has been updated since binding; NTDLL.DLL is not there at all
+        let search_dir = tempfile::tempdir().unwrap();
+        let mut kernel32 = pe::PortableExecutable::new("KERNEL32.dll");
+        kernel32.time_date_stamp = 0x3B7DFE10;
+        std::fs::write(search_dir.path().join("kernel32.dll"), kernel32.to_bytes()).unwrap();
+        let diagnostics = lint_pe_bound_imports(&bytes, &[search_dir.path().to_owned()]).unwrap();
+        let codes: Vec<&str> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(codes, ["binding-summary", "stale-binding", "bound-dll-not-found"]);
+        assert!(diagnostics[1].message.contains("0x3B7DFE0E"));
+        assert!(diagnostics[2].message.contains("NTDLL.DLL"));
+    }
+}
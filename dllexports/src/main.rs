@@ -1,23 +1,72 @@
+mod api_docs;
+mod compare;
+mod compare_exports;
 mod data_mgmt;
+mod demangle;
+mod exit_status;
+mod extract;
 mod formats;
+mod hashing;
+mod image_output;
+mod info;
+mod input;
+mod lint;
+mod mun;
+mod plugin;
 mod read_ext;
+mod scan;
+mod schema;
+mod strings;
+mod verify;
+mod volumes;
 
 
 use std::ffi::OsString;
 use std::fs::{read_dir, File};
-use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::io::{BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use binms::regf::Hive;
 use clap::{Parser, ValueEnum};
 use display_bytes::DisplayBytesSlice;
-use expandms::fat::{AllocationTable, FatHeader, RootDirectoryLocation};
+use display_bytes::escape::escape_str;
+use expandms::fat::{AllocationTableCopies, FatHeader, RootDirectoryLocation};
 use expandms::inflate::{Inflater, MAX_LOOKBACK_DISTANCE};
-use expandms::iso9660::VolumeDescriptor;
-use tracing::{debug, error, info};
+use expandms::iso9660::{BootRecord, DescriptorType, PartitionDescriptor, VolumeDescriptor};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tracing::{debug, error, info, warn};
+
+use crate::data_mgmt::{IdentifiedFile, PathSequence};
+use crate::exit_status::{fail, ExitStatus};
+use crate::schema::{Document, DocumentKind};
+
+
+/// The description of the exit statuses (see [`ExitStatus`]) appended to the help text.
+const EXIT_STATUS_HELP: &str = "\
+Exit statuses:
+  0    success
+  1    some files could not be processed (partial-with-errors)
+  2    invalid command line
+  3    the format of the input file has not been recognized (unidentified-format)
+  4    the input file could not be read (parse-error)
+  5    a file could not be read from or written to disk (io-error)
+  101  crash";
 
-use crate::data_mgmt::{IdentifiedFile, PathSequence, Symbol};
-use crate::formats::interpret_file;
+#[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true, arg_required_else_help = true, after_long_help = EXIT_STATUS_HELP)]
+struct ProgArgs {
+    /// Output the JSON schema of the documents output by the `--json-output` options and exit.
+    ///
+    /// Every document contains a `schema_version` field, which is incremented on incompatible
+    /// changes to the structure of the documents.
+    #[arg(long)]
+    pub schema: bool,
 
+    #[command(subcommand)]
+    pub mode: Option<ProgMode>,
+}
 
 #[derive(Parser)]
 enum ProgMode {
@@ -25,10 +74,103 @@ enum ProgMode {
     #[command(subcommand)] Poke(PokeMode),
 
     /// Attempts to ascertain what kind of a file this is.
-    Interpret(InputFileOnlyArgs),
+    Interpret(InterpretArgs),
+
+    /// Summarizes a file on one page: its format, architecture, subsystem and timestamp, the
+    /// numbers of sections, exports, imports, resources or contained files, and whether it
+    /// contains debug information or a signature.
+    ///
+    /// Only the details that apply to the file's format are output; `poke` offers the full
+    /// information.
+    Info(InputFileJsonOutputArgs),
 
     /// Scans a directory and attempts to recursively extract all exports from all exporting files.
+    ///
+    /// Each symbol is output as a tab-separated line containing the path, ordinal and name. If the
+    /// exporting file warrants a remark (e.g. the identity of a .NET assembly), it is appended as
+    /// the last column; files with a remark but no exports are output with an empty ordinal and
+    /// name.
+    ///
+    /// With `--output-format csv`, the symbols are output as comma-separated values with a header
+    /// row instead.
+    ///
+    /// Files in symbol-server layouts (`foo.dll/<index>/foo.dll`, also within tarballs such as
+    /// container image layers) are only scanned once per name and index; the CSV output records the
+    /// index in the `symbol_store_index` column. The `section_class` column tells exported
+    /// functions (`code`) apart from exported variables (e.g. `initialized-data`).
+    ///
+    /// With `--disk-sets`, numbered floppy disk images are read as sets so that cabinets and
+    /// compressed files split across disks are reassembled and scanned.
     Scan(ScanArgs),
+
+    /// Checks the export table of a PE (32-bit/64-bit Windows executable) file for
+    /// inconsistencies.
+    ///
+    /// Each finding is output on its own line as tab-separated severity, code and message.
+    Lint(LintArgs),
+
+    /// Extracts files from a container such as a FAT or ISO9660 image, a cabinet or a file
+    /// compressed using KWAJ or SZDD.
+    ///
+    /// If paths are given, only the matching files are extracted; a path matching a directory
+    /// selects all files within it. Paths may contain the wildcards `*` and `?` and are matched
+    /// case-insensitively.
+    ///
+    /// A cabinet that is part of a set spanning multiple disks is joined with the other cabinets of
+    /// the set found in the same directory, as is an SZDD file split into numbered parts.
+    Extract(ExtractArgs),
+
+    /// Lists the files in a container along with their sizes and detected formats and, with
+    /// `--digests`, their SHA-1 and SHA-256 digests.
+    ///
+    /// With `--depth`, nested containers are listed as well, indented below the container.
+    Ls(LsArgs),
+
+    /// Compares the files in two containers (e.g. the installation media of two builds) by their
+    /// SHA-256 digests.
+    ///
+    /// Each file that has been added, removed or changed is output as a tab-separated line
+    /// containing the kind of change and the path. With `--depth`, nested containers are compared
+    /// as well; the paths of the files within them are appended to the container's path,
+    /// separated by `|`.
+    CmpContainers(CmpContainersArgs),
+
+    /// Compares the exports of two versions of a PE (32-bit/64-bit Windows executable) file, e.g.
+    /// a DLL and a dump of the same DLL taken from the memory of a process.
+    ///
+    /// Each export that has been added, removed, moved or retargeted is output as a tab-separated
+    /// line containing the kind of change, the name, the old and new addresses or forwarder targets
+    /// and a remark. With `--entry-bytes`, exports whose first bytes differ are output as well,
+    /// and jumps inserted at the start of functions by hot-patching or detouring are described in
+    /// the remark.
+    CmpExports(CmpExportsArgs),
+
+    /// Lists the ASCII and UTF-16LE strings in a file, e.g. to find the names of undocumented
+    /// functions that are looked up at runtime.
+    ///
+    /// Each string is output as a tab-separated line containing the file offset, the relative
+    /// virtual address, the encoding, where the string is stored (for PE files: the headers, a
+    /// section, a resource or the overlay) and the escaped string.
+    Strings(StringsArgs),
+
+    /// Reads a JSON document output by one of the `--json-output` options back into the structure
+    /// it was serialized from and outputs it again.
+    ///
+    /// Meant for regression tests of parser changes: a document that is imported and output again
+    /// must not differ from the original, and comparing it with the output of a newer version
+    /// shows what the changes affect. Only the unstable document kinds that are serialized
+    /// directly from the internal structures (`pe-unwind`, `dbg-container`, `coff-debug-info` and
+    /// `code-view-debug-info`) can be imported.
+    ImportJson(ImportJsonArgs),
+
+    /// Runs every parser that applies to each file in a directory tree (a corpus of sample files)
+    /// and reports how often each parser succeeds, grouping the failures by category.
+    ///
+    /// Parsers that panic are counted as failing instead of crashing the tool. Meant for
+    /// developers who want to make sure that changes to the parsers do not introduce failures;
+    /// exits with status 1 if any parser has panicked.
+    #[command(hide = true)]
+    VerifyFixtures(VerifyFixturesArgs),
 }
 
 #[derive(Parser)]
@@ -36,6 +178,10 @@ enum PokeMode {
     /// Expands a file compressed with a Microsoft compression like KWAJ, SZDD or CAB.
     Expand(ExpandArgs),
 
+    /// Expands all KWAJ, SZDD and SZ files in a directory tree, e.g. an entire setup disk,
+    /// restoring their original names.
+    ExpandDir(ExpandDirArgs),
+
     /// Obtains low-level information about a File Allocation Table file system.
     #[command(subcommand)] Fat(PokeFatMode),
 
@@ -45,6 +191,9 @@ enum PokeMode {
     /// Obtains low-level information about ISO9660 CD images.
     #[command(subcommand)] Cd(PokeCdMode),
 
+    /// Obtains information from Windows NT registry hives.
+    #[command(subcommand)] Reg(PokeRegMode),
+
     /// Decompresses DEFLATE-compressed data.
     Inflate(ExpandArgs),
 }
@@ -59,6 +208,24 @@ enum PokeFatMode {
 
     /// Outputs the data contained in a file in a File Allocation Table file system.
     FatData(InputFileAndIndexArgs),
+
+    /// Stores a file in a File Allocation Table file system image, replacing any existing file of
+    /// the same name and creating missing directories.
+    ///
+    /// The file is timestamped 1980-01-01 00:00:00 so that the resulting images are reproducible.
+    FatPut(FatPutArgs),
+
+    /// Removes a file from a File Allocation Table file system image.
+    FatRm(FatRmArgs),
+
+    /// Checks a File Allocation Table file system image for inconsistencies without modifying it.
+    ///
+    /// The copies of the allocation table are compared and the cluster chains of all files and
+    /// directories are checked for breaks, loops, cross-links and mismatches with the file sizes.
+    /// Allocated clusters that belong to no file are reported as orphaned chains. Each finding is
+    /// output along with a suggestion on how to repair it.
+    #[command(alias = "fsck")]
+    FatFsck(InputFileOnlyArgs),
 }
 
 #[derive(Parser)]
@@ -66,9 +233,27 @@ enum PokeExeMode {
     /// Outputs the header of an MZ (DOS executable) file.
     MzHeader(InputFileOnlyArgs),
 
+    /// Outputs information about the DOS stub of an MZ (DOS executable) file.
+    ///
+    /// Reports whether the stub is the canonical one placed by Microsoft linkers and whether a
+    /// Rich header follows it.
+    MzStub(MzStubArgs),
+
     /// Outputs the header of an NE (16-bit Windows executable) file.
     NeHeader(InputFileJsonOutputArgs),
 
+    /// Outputs the entry points of an NE (16-bit Windows executable) file along with their ordinals.
+    NeEntries(InputFileJsonOutputArgs),
+
+    /// Outputs the segment table of an NE (16-bit Windows executable) file.
+    NeSegments(InputFileJsonOutputArgs),
+
+    /// Lists the resources in an NE (16-bit Windows executable) file.
+    ///
+    /// If an output file is given, the raw bytes of the single selected resource are written into
+    /// it.
+    NeResources(NeResourcesArgs),
+
     /// Lists icon groups in an NE (16-bit Windows executable) file.
     NeIconGroups(InputFileJsonOutputArgs),
 
@@ -82,11 +267,33 @@ enum PokeExeMode {
     /// versions.
     NeFonts(FontArgs),
 
+    /// Outputs the string table (RT_STRING) of an NE (16-bit Windows executable) file.
+    ///
+    /// Each string is output on its own line as its ID and the escaped string, separated by a tab.
+    /// The strings are decoded as Windows-1252; the JSON output also contains the raw bytes for
+    /// modules localized for other codepages.
+    NeStrings(InputFileJsonOutputArgs),
+
     /// Outputs the header of a PE (32-bit/64-bit Windows executable) file.
     PeHeader(InputFileOnlyArgs),
 
+    /// Outputs the section table of a PE (32-bit/64-bit Windows executable) file.
+    ///
+    /// Each section is classified as code, initialized or uninitialized data, resources,
+    /// relocations or debug information according to its characteristics and name.
+    PeSections(InputFileJsonOutputArgs),
+
+    /// Outputs the data directory entries of a PE (32-bit/64-bit Windows executable) file.
+    PeDatadirs(InputFileJsonOutputArgs),
+
+    /// Locates a relative virtual address in a PE (32-bit/64-bit Windows executable) file.
+    ///
+    /// Outputs the section containing the address, the corresponding file offset and the data
+    /// directory entries (exports, imports, resources etc.) whose data contains the address.
+    PeRva(PeRvaArgs),
+
     /// Outputs the resources in a PE (32-bit/64-bit Windows executable) file.
-    PeResources(InputFileJsonOutputArgs),
+    PeResources(PeResourcesArgs),
 
     /// Lists icon groups in a PE (32-bit/64-bit Windows executable) file.
     PeIconGroups(InputFileJsonOutputArgs),
@@ -94,23 +301,85 @@ enum PokeExeMode {
     /// Outputs icons in a PE (32-bit/64-bit Windows executable) file.
     PeIcons(InputFilePeResourceGraphicsArgs),
 
+    /// Outputs the exception directory (.pdata) of an x64 or ARM64 PE file.
+    ///
+    /// Lists the extent of each non-leaf function along with its unwind information; this also
+    /// covers functions that are not exported.
+    PeUnwind(InputFileJsonOutputArgs),
+
+    /// Outputs how the imports of a PE file are bound.
+    ///
+    /// Lists the binding state of each imported DLL (unbound, old-style with the timestamp in the
+    /// import descriptor, or new-style) followed by the bound import directory.
+    PeBoundImports(InputFileJsonOutputArgs),
+
+    /// Guesses the toolchain (Visual C++ release, MASM, Borland or MinGW) that produced a PE
+    /// (32-bit/64-bit Windows executable) file.
+    ///
+    /// The guess is based on the Rich header, the linker version, the section names and the
+    /// security features recorded in the load configuration directory (stack cookies and safe
+    /// exception handler tables); the traces taken into account are listed below the guess.
+    PeFingerprint(InputFileJsonOutputArgs),
+
     /// Outputs general information about an NT4-era .DBG file.
     Nt4DbgInfo(DebugFileArgs),
 
+    /// Lists the namespaces, types and methods described by a Windows Runtime metadata (.winmd)
+    /// file.
+    Winmd(InputFileJsonOutputArgs),
+
     /// Outputs Common Language Runtime resources.
-    ClrResources(InputFileOptionalCborOutputArgs),
+    ///
+    /// Lists the manifest resources of a managed assembly along with the entries of each embedded
+    /// `.resources` file, and optionally extracts them.
+    ClrResources(ClrResourcesArgs),
 }
 
 #[derive(Parser)]
 enum PokeCdMode {
-    /// Outputs the first volume descriptor of an ISO9660 or similar image.
+    /// Outputs a volume descriptor of an ISO9660 or similar image; the first one unless
+    /// `--number` is given.
     Vol(CdInputFileArgs),
+
+    /// Lists the descriptors in the volume descriptor sequence of an ISO9660 or similar image up
+    /// to the set terminator, along with their types and identifiers.
+    Vols(CdJsonOutputArgs),
+}
+
+#[derive(Parser)]
+enum PokeRegMode {
+    /// Lists the subkeys of a key in a registry hive.
+    Keys(RegKeyArgs),
+
+    /// Lists the values of a key in a registry hive.
+    Values(RegKeyArgs),
+
+    /// Lists the COM classes registered in a registry hive along with their server DLLs and
+    /// executables.
+    ComServers(InputFileJsonOutputArgs),
 }
 
 #[derive(Parser)]
 struct ExpandArgs {
+    /// Only decompress the file and report its decompressed size, without writing any output.
+    #[arg(long)]
+    pub check: bool,
+
+    /// The compressed file; `-` reads it from standard input.
     pub input_file: PathBuf,
-    pub output_file: PathBuf,
+
+    #[arg(required_unless_present = "check")]
+    pub output_file: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+struct ExpandDirArgs {
+    /// What to do with files that are not compressed.
+    #[arg(short, long, default_value = "copy")]
+    pub uncompressed: crate::extract::UncompressedMode,
+
+    pub input_dir: PathBuf,
+    pub output_dir: PathBuf,
 }
 
 #[derive(Parser)]
@@ -118,6 +387,12 @@ struct InputFileOnlyArgs {
     pub input_file: PathBuf,
 }
 
+#[derive(Parser)]
+struct InterpretArgs {
+    /// The file to interpret; `-` reads it from standard input.
+    pub input_file: PathBuf,
+}
+
 #[derive(Clone, Copy, Default, Eq, Hash, Ord, PartialEq, PartialOrd, ValueEnum)]
 enum FontFormat {
     #[default] Bdf,
@@ -127,28 +402,106 @@ enum FontFormat {
 
 #[derive(Parser)]
 struct FontArgs {
+    /// Output the header fields of each font as JSON instead of converting the fonts.
+    #[arg(short, long, conflicts_with_all = ["output_prefix", "font_dir"])]
+    pub json_output: bool,
+
+    #[command(flatten)]
+    pub json_format: JsonFormatArgs,
+
     #[arg(short, long, default_value = "bdf")]
     pub font_format: FontFormat,
 
+    /// Only output the font with this numeric resource ID (with or without the 0x8000 flag).
+    #[arg(short = 'i', long)]
+    pub res_id: Option<u16>,
+
+    /// Only output the font with this resource name (compared case-insensitively).
+    #[arg(short = 'I', long = "name", conflicts_with = "res_id")]
+    pub res_name: Option<String>,
+
+    /// Also output the font directory (RT_FONTDIR) as a table of the face names and sizes of the
+    /// fonts it lists.
+    #[arg(short = 'd', long)]
+    pub font_dir: bool,
+
     pub input_file: PathBuf,
     pub output_prefix: Option<PathBuf>,
 }
 
+/// Options controlling how JSON output is written, shared by all subcommands that can output
+/// JSON.
+#[derive(Parser)]
+struct JsonFormatArgs {
+    /// Write the JSON output into this file instead of stdout.
+    #[arg(long, requires = "json_output")]
+    pub output: Option<PathBuf>,
+
+    /// Output compact JSON on a single line instead of pretty-printing it.
+    #[arg(long, requires = "json_output")]
+    pub compact: bool,
+}
+impl JsonFormatArgs {
+    /// Serializes the value as a JSON document of the given kind directly into the output file or
+    /// stdout, without assembling the whole document in memory first.
+    pub fn write<T: Serialize + ?Sized>(&self, kind: DocumentKind, value: &T) {
+        let document = Document::new(kind, value);
+        let writer: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(File::create(path).expect("failed to create JSON output file")),
+            None => Box::new(std::io::stdout().lock()),
+        };
+        let mut writer = BufWriter::new(writer);
+        if self.compact {
+            serde_json::to_writer(&mut writer, &document)
+        } else {
+            serde_json::to_writer_pretty(&mut writer, &document)
+        }.expect("failed to JSONify");
+        writer.write_all(b"\n")
+            .and_then(|_| writer.flush())
+            .expect("failed to write JSON output");
+    }
+
+    /// Deserializes the data of a document into the structure it was serialized from and writes it
+    /// out again as a document of the same kind.
+    pub fn rewrite<T: DeserializeOwned + Serialize>(&self, kind: DocumentKind, data: serde_json::Value) -> Result<(), serde_json::Error> {
+        let value: T = serde_json::from_value(data)?;
+        self.write(kind, &value);
+        Ok(())
+    }
+}
+
 #[derive(Parser)]
 struct InputFileJsonOutputArgs {
     /// Output the collected information as JSON.
     #[arg(short, long)]
     pub json_output: bool,
 
+    #[command(flatten)]
+    pub json_format: JsonFormatArgs,
+
     pub input_file: PathBuf,
 }
 
 #[derive(Parser)]
-struct InputFileOptionalCborOutputArgs {
+struct ClrResourcesArgs {
+    /// Output the collected information as JSON.
+    #[arg(short, long)]
+    pub json_output: bool,
+
+    #[command(flatten)]
+    pub json_format: JsonFormatArgs,
+
+    /// Extract the embedded resources into this directory.
+    ///
+    /// Each `.resources` file is extracted as a directory containing its byte array and stream
+    /// entries; other resources are extracted as files.
+    #[arg(short = 'x', long)]
+    pub extract_dir: Option<PathBuf>,
+
     pub input_file: PathBuf,
 
-    /// If specified, the data is not output in a human-redable format on stdout but as CBOR into
-    /// the given file.
+    /// If specified, the decoded `.resources` files are not output in a human-redable format on
+    /// stdout but as CBOR into the given file.
     pub cbor_output: Option<PathBuf>,
 }
 
@@ -159,10 +512,22 @@ struct DebugFileArgs {
     #[arg(short, long)]
     pub container: bool,
 
+    /// Output the COFF symbol table and line numbers instead of the CodeView information.
+    #[arg(long)]
+    pub coff: bool,
+
+    /// Output the functions known from the CodeView and COFF symbols, with their addresses
+    /// relative to the image base, instead of the CodeView information.
+    #[arg(long, conflicts_with = "coff")]
+    pub functions: bool,
+
     /// Output the collected information as JSON.
     #[arg(short, long)]
     pub json_output: bool,
 
+    #[command(flatten)]
+    pub json_format: JsonFormatArgs,
+
     pub input_file: PathBuf,
 }
 
@@ -172,6 +537,19 @@ struct InputFileAndIndexArgs {
     pub index: u32,
 }
 
+#[derive(Parser)]
+struct FatPutArgs {
+    pub image_file: PathBuf,
+    pub path_in_image: String,
+    pub input_file: PathBuf,
+}
+
+#[derive(Parser)]
+struct FatRmArgs {
+    pub image_file: PathBuf,
+    pub path_in_image: String,
+}
+
 #[derive(Parser)]
 struct InputFileAndOptIndexArgs {
     pub input_file: PathBuf,
@@ -183,6 +561,50 @@ enum GraphicsOutputFormat {
     #[default] Sixel,
     Ascii,
     Png,
+    Bmp,
+    Ppm,
+}
+impl GraphicsOutputFormat {
+    /// Returns the raster format corresponding to this output format, if there is one.
+    fn raster_format(self) -> Option<crate::image_output::RasterFormat> {
+        match self {
+            Self::Sixel|Self::Ascii => None,
+            Self::Png => Some(crate::image_output::RasterFormat::Png),
+            Self::Bmp => Some(crate::image_output::RasterFormat::Bmp),
+            Self::Ppm => Some(crate::image_output::RasterFormat::Ppm),
+        }
+    }
+}
+
+/// The formats into which images can be written as files.
+#[derive(Clone, Copy, Default, Eq, Hash, Ord, PartialEq, PartialOrd, ValueEnum)]
+enum RasterOutputFormat {
+    #[default] Png,
+    Bmp,
+    Ppm,
+}
+impl RasterOutputFormat {
+    fn raster_format(self) -> crate::image_output::RasterFormat {
+        match self {
+            Self::Png => crate::image_output::RasterFormat::Png,
+            Self::Bmp => crate::image_output::RasterFormat::Bmp,
+            Self::Ppm => crate::image_output::RasterFormat::Ppm,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd, ValueEnum)]
+enum IconVariantArg {
+    DeviceIndependent,
+    DeviceDependent,
+}
+impl IconVariantArg {
+    fn to_icon1_variant(self) -> binms::ico1::Icon1Variant {
+        match self {
+            Self::DeviceIndependent => binms::ico1::Icon1Variant::DeviceIndependent,
+            Self::DeviceDependent => binms::ico1::Icon1Variant::DeviceDependent,
+        }
+    }
 }
 
 #[derive(Parser)]
@@ -196,12 +618,81 @@ struct InputFileNeResourceGraphicsArgs {
     #[arg(short = 'i', long)]
     pub res_id: Option<u16>,
 
+    /// Only output this variant of Windows 1.0 icons.
+    ///
+    /// If multiple variants are output, the name of the variant is inserted before the extension
+    /// of the output file name (e.g. `icon.device-dependent.png`).
+    #[arg(short, long)]
+    pub variant: Option<IconVariantArg>,
+
     pub input_file: PathBuf,
     pub output_file: PathBuf,
 }
 
+#[derive(Parser)]
+struct NeResourcesArgs {
+    /// Output the collected information as JSON.
+    #[arg(short, long)]
+    pub json_output: bool,
+
+    #[command(flatten)]
+    pub json_format: JsonFormatArgs,
+
+    #[arg(short = 't', long = "type")] pub res_type: Option<u16>,
+    #[arg(short = 'i', long = "id")] pub res_id: Option<u16>,
+    #[arg(short = 'I', long = "id-name")] pub res_id_name: Option<String>,
+
+    /// Write the raw bytes of the selected resource into this file.
+    #[arg(short, long)]
+    pub output_file: Option<PathBuf>,
+
+    pub input_file: PathBuf,
+}
+
+#[derive(Parser)]
+struct PeRvaArgs {
+    pub input_file: PathBuf,
+
+    /// The relative virtual address, in decimal or with a `0x` prefix in hexadecimal.
+    #[arg(value_parser = parse_u32_maybe_hex)]
+    pub rva: u32,
+}
+
+#[derive(Parser)]
+struct PeResourcesArgs {
+    /// Output the collected information as JSON.
+    #[arg(short, long)]
+    pub json_output: bool,
+
+    #[command(flatten)]
+    pub json_format: JsonFormatArgs,
+
+    /// Output the directory structure as an indented tree, with the header fields of each
+    /// directory but without the resource data.
+    #[arg(short, long, conflicts_with = "json_output")]
+    pub tree: bool,
+
+    /// With --json-output, output each distinct piece of resource data once, along with the type,
+    /// resource and language IDs of all the resources containing it, instead of the tree.
+    #[arg(short, long, requires = "json_output")]
+    pub dedup: bool,
+
+    /// Keep the bytes of resources compressed using LZ77+Huffman (XPRESS) as they are stored
+    /// instead of decompressing them.
+    #[arg(long)]
+    pub raw: bool,
+
+    /// Do not merge in the resources of the library's `.mun` file (e.g.
+    /// `SystemResources\imageres.dll.mun` for `System32\imageres.dll`).
+    #[arg(long)]
+    pub no_mun: bool,
+
+    pub input_file: PathBuf,
+}
+
 #[derive(Parser)]
 struct InputFilePeResourceGraphicsArgs {
+    #[arg(short, long, default_value = "png")] pub format: RasterOutputFormat,
     #[arg(short = 't', long = "type")] pub res_type: Option<u32>,
     #[arg(short = 'i', long = "id")] pub res_id: Option<u32>,
     #[arg(short = 'I', long = "id-name")] pub res_id_name: Option<String>,
@@ -211,128 +702,454 @@ struct InputFilePeResourceGraphicsArgs {
 }
 
 #[derive(Parser)]
-struct ScanArgs {
-    pub dir: Option<PathBuf>,
+struct RegKeyArgs {
+    /// Output the collected information as JSON.
+    #[arg(short, long)]
+    pub json_output: bool,
+
+    #[command(flatten)]
+    pub json_format: JsonFormatArgs,
+
+    /// Also descend into the subkeys of the subkeys.
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// The backslash-separated path of the key, relative to the root key of the hive.
+    #[arg(short, long, default_value = "")]
+    pub path: String,
+
+    pub input_file: PathBuf,
 }
 
 #[derive(Parser)]
-struct CdInputFileArgs {
-    #[arg(short = 'H', long)] pub high_sierra: bool,
-    #[arg(short = 'n', long)] pub number: Option<u64>,
+struct MzStubArgs {
+    /// Output the collected information as JSON.
+    #[arg(short, long)]
+    pub json_output: bool,
+
+    #[command(flatten)]
+    pub json_format: JsonFormatArgs,
+
+    /// Write the raw bytes of the stub (including any Rich header) into this file.
+    #[arg(short, long, visible_alias = "out")]
+    pub output_file: Option<PathBuf>,
+
     pub input_file: PathBuf,
 }
 
+#[derive(Parser)]
+struct LintArgs {
+    /// Output the findings as JSON.
+    #[arg(short, long)]
+    pub json_output: bool,
 
-fn set_up_tracing() {
-    use tracing_subscriber::EnvFilter;
-    use tracing_subscriber::fmt;
-    use tracing_subscriber::layer::SubscriberExt;
-    use tracing_subscriber::util::SubscriberInitExt;
+    #[command(flatten)]
+    pub json_format: JsonFormatArgs,
 
-    tracing_subscriber::registry()
-        .with(
-            fmt::layer()
-                .with_writer(std::io::stderr)
-        )
-        .with(EnvFilter::from_default_env())
-        .init();
-}
+    /// Check that the DLLs referenced by forwarders exist in this directory. Can be passed
+    /// multiple times.
+    #[arg(short, long)]
+    pub search_dir: Vec<PathBuf>,
+
+    /// Also summarize how the imports are bound and, if search directories are given, check that
+    /// the bindings are not stale.
+    #[arg(short, long)]
+    pub bound_imports: bool,
 
+    /// Also check the hints of the functions imported by name against the name pointer tables of
+    /// the DLLs in the search directories.
+    #[arg(short = 'H', long)]
+    pub import_hints: bool,
 
-fn escape_name(name: &str) -> String {
-    let mut ret = String::with_capacity(name.len());
-    for c in name.chars() {
-        if c == '\r' {
-            ret.push_str("\\r");
-        } else if c == '\n' {
-            ret.push_str("\\n");
-        } else if c == '\t' {
-            ret.push_str("\\t");
-        } else if c == '\\' {
-            ret.push_str("\\\\");
-        } else {
-            ret.push(c);
-        }
-    }
-    ret
+    /// Also report resources that are not fully backed by data in the file and are only readable
+    /// because the loader fills the rest of their section with zeroes, as well as resource
+    /// directories that list the same identifier more than once.
+    #[arg(short = 'r', long)]
+    pub strict_resources: bool,
+
+    pub input_file: PathBuf,
 }
 
+#[derive(Parser)]
+struct ScanArgs {
+    /// Remember the results of scanning each file by its SHA-256 digest, skipping the
+    /// interpretation of files identical to one that has already been scanned.
+    #[arg(short, long)]
+    pub cache: bool,
 
-fn main() {
-    set_up_tracing();
+    /// Load the scan cache from this file (if it exists) and store it back after the scan.
+    ///
+    /// A cache stored by a version of this program with a different cache format is discarded.
+    /// Implies `--cache`.
+    #[arg(short = 'C', long)]
+    pub cache_file: Option<PathBuf>,
 
-    let mode = ProgMode::parse();
-    match mode {
-        ProgMode::Poke(poke_mode) => {
-            match poke_mode {
-                PokeMode::Expand(args) => {
-                    let mut input_file = File::open(&args.input_file)
-                        .expect("failed to open input file");
-                    let mut output = Vec::new();
-                    expandms::decompress(&mut input_file, &mut output)
-                        .expect("failed to decompress");
-                    std::fs::write(&args.output_file, &output)
-                        .expect("failed to write output file");
-                },
-                PokeMode::Fat(poke_fat_mode) => {
-                    match poke_fat_mode {
-                        PokeFatMode::FatHeader(args) => {
-                            let mut input_file = File::open(&args.input_file)
-                                .expect("failed to open input file");
+    /// Files larger than this many bytes that are extracted from a container are written to a
+    /// temporary file and only scanned once the container has been released from memory.
+    #[arg(short, long)]
+    pub max_in_memory_size: Option<usize>,
 
-                            // read header
-                            let fat_header = FatHeader::read(&mut input_file)
-                                .expect("failed to read FAT header");
-                            println!("{:#?}", fat_header);
-                            println!("{:?}", fat_header.variant());
+    /// Output an additional column containing the undecorated (demangled) name of each symbol.
+    #[arg(short, long)]
+    pub demangle: bool,
 
-                            // skip over reserved sectors
-                            let reserved_bytes = u64::from(fat_header.reserved_sector_count) * u64::from(fat_header.bytes_per_sector);
-                            input_file.seek(SeekFrom::Start(reserved_bytes))
-                                .expect("failed to seek to start of allocation table");
+    /// Name the files extracted from single-file containers (e.g. `foo.dl_`) according to the INF
+    /// files in the same directory.
+    #[arg(short, long)]
+    pub inf_names: bool,
 
-                            // read allocation table
-                            let fat_length = usize::try_from(fat_header.sectors_per_fat).unwrap() * usize::from(fat_header.bytes_per_sector);
-                            let allocation_table = AllocationTable::read(&mut input_file, fat_header.variant(), fat_length)
-                                .expect("failed to read in allocation table");
+    /// An external program to consult for files that the built-in detectors do not recognize or fail
+    /// to read.
+    ///
+    /// The program is called with the path of a file containing the data and the path of an empty
+    /// output directory, and must output a JSON object describing the symbols exported by the
+    /// file or the files it contains (extracted into the output directory). May be given multiple
+    /// times; the programs are consulted in order.
+    #[arg(short, long = "plugin")]
+    pub plugins: Vec<PathBuf>,
+
+    /// The format in which to output the symbols.
+    #[arg(long, default_value = "tsv")]
+    pub output_format: crate::scan::OutputFormat,
+
+    /// Do not scan the contents of files nested in more than this many containers.
+    ///
+    /// Files that (directly or indirectly) contain themselves are never descended into again.
+    #[arg(long, default_value_t = crate::scan::DEFAULT_MAX_DEPTH)]
+    pub max_depth: usize,
 
-                            println!("{:?}", allocation_table);
-                        },
-                        PokeFatMode::FatDirectory(args) => {
-                            let mut input_file = File::open(&args.input_file)
-                                .expect("failed to open input file");
+    /// Output an additional column containing the documentation of each symbol, as looked up in
+    /// this file.
+    ///
+    /// The file maps symbols given as `dll!name` (e.g. `kernel32.dll!CreateFileW`) to arbitrary
+    /// text such as a URL. Files ending in `.json` must contain a JSON object; all other files
+    /// must contain one comma-separated `dll!name,text` pair per line.
+    #[arg(long)]
+    pub api_docs: Option<PathBuf>,
+
+    /// Pass each symbol through this Rhai script, which decides whether it is output and may
+    /// rename it and its exporting file, as `filtexp` does with the output of a previous scan.
+    ///
+    /// Symbols for which the script fails are dropped and counted as errors.
+    #[arg(long)]
+    pub filter_script: Option<PathBuf>,
 
-                            // read header
-                            let fat_header = FatHeader::read(&mut input_file)
-                                .expect("failed to read FAT header");
+    /// Instead of outputting the symbols, pair the files in `System32` directories with those in
+    /// the corresponding `SysWoW64` directories and output the exports that only one file of each
+    /// pair offers.
+    ///
+    /// Each export is output as a tab-separated line containing the path of the file offering it,
+    /// the name of the directory it is in, its architecture and the export's name (or `#` and
+    /// its ordinal). Pairs whose files have the same architecture are skipped.
+    #[arg(long)]
+    pub wow64_report: bool,
+
+    /// Treat the variants of the NT kernel (`ntoskrnl.exe`, `ntkrnlmp.exe`, `ntkrnlpa.exe`, ...)
+    /// and of the HAL (`hal.dll`, `halacpi.dll`, ...) as one file named `kernel` in the same
+    /// directory.
+    ///
+    /// Each of their exports is output once by name and without ordinal; forwarders from one of
+    /// these files to another are left out. This keeps functions that have moved between the
+    /// kernel and the HAL from appearing as removed and added when comparing Windows versions.
+    #[arg(long)]
+    pub kernel_namespace: bool,
+
+    /// Stop scanning a file (and the files it contains) after this many seconds, outputting the
+    /// symbols found until then along with a remark that they are incomplete.
+    #[arg(long)]
+    pub time_limit: Option<u64>,
+
+    /// Also store the summary statistics of the scan, which are always output to standard error,
+    /// as a JSON document in this file.
+    #[arg(long)]
+    pub summary: Option<PathBuf>,
+
+    /// Write a JSON document describing each failure (the file, what was being done and the
+    /// error message) into this file.
+    ///
+    /// If any failures occur, the scan still runs to completion but the tool exits with status 1.
+    #[arg(long)]
+    pub errors_json: Option<PathBuf>,
 
-                            // skip over reserved sectors
-                            let reserved_bytes = u64::from(fat_header.reserved_sector_count) * u64::from(fat_header.bytes_per_sector);
-                            input_file.seek(SeekFrom::Start(reserved_bytes))
-                                .expect("failed to seek to start of allocation table");
+    /// Treat floppy disk images in the same directory whose names only differ in a number (e.g.
+    /// `DISK01.IMG` to `DISK13.IMG`) as an ordered set, reassembling the cabinets and SZDD files
+    /// split across them before scanning their contents.
+    ///
+    /// The files of a set are output below the path of its directory, prefixed with the name of the
+    /// image they are on.
+    #[arg(long)]
+    pub disk_sets: bool,
 
-                            // read allocation table
-                            let fat_length = usize::try_from(fat_header.sectors_per_fat).unwrap() * usize::from(fat_header.bytes_per_sector);
-                            let allocation_table = AllocationTable::read(&mut input_file, fat_header.variant(), fat_length)
-                                .expect("failed to read in allocation table");
+    pub dir: Option<PathBuf>,
+}
 
-                            let mut dir_data = Vec::new();
-                            if let Some(subdirectory_cluster_index) = args.index {
-                                // read the chain of clusters
-                                expandms::fat::read_cluster_chain_into(&mut input_file, &fat_header, &allocation_table, subdirectory_cluster_index, &mut dir_data)
-                                    .expect("failed to read cluster chain");
-                            } else {
-                                match fat_header.root_directory_location {
-                                    RootDirectoryLocation::Sector(sector) => {
-                                        let sector_count = u32::from(fat_header.max_root_dir_entries) * 32 / u32::from(fat_header.bytes_per_sector);
-                                        for i in 0..sector_count {
-                                            expandms::fat::read_sector_into(&mut input_file, &fat_header, sector + i, &mut dir_data)
-                                                .expect("failed to read sector");
-                                        }
-                                    },
-                                    RootDirectoryLocation::Cluster(cluster) => {
-                                        expandms::fat::read_cluster_chain_into(&mut input_file, &fat_header, &allocation_table, cluster, &mut dir_data)
-                                            .expect("failed to read cluster chain");
+#[derive(Parser)]
+struct ExtractArgs {
+    /// The directory into which to extract the files.
+    #[arg(short, long, default_value = ".")]
+    pub output_dir: PathBuf,
+
+    /// Place all extracted files directly into the output directory, dropping their paths within
+    /// the container.
+    #[arg(short, long, conflicts_with = "preserve_paths")]
+    pub flatten: bool,
+
+    /// Recreate the directory structure of the container in the output directory (the default).
+    #[arg(short, long)]
+    pub preserve_paths: bool,
+
+    pub container: PathBuf,
+    pub paths: Vec<String>,
+}
+
+#[derive(Parser)]
+struct LsArgs {
+    /// How many levels of nested containers to descend into.
+    #[arg(short, long, default_value = "0")]
+    pub depth: usize,
+
+    /// Output the listing as JSON.
+    #[arg(short, long)]
+    pub json_output: bool,
+
+    #[command(flatten)]
+    pub json_format: JsonFormatArgs,
+
+    /// Also output the SHA-1 and SHA-256 digest of each file.
+    #[arg(long)]
+    pub digests: bool,
+
+    pub container: PathBuf,
+}
+
+#[derive(Parser)]
+struct CmpContainersArgs {
+    /// How many levels of nested containers to descend into.
+    #[arg(short, long, default_value = "0")]
+    pub depth: usize,
+
+    /// Stop hashing a file of a container (and the files it contains) after this many seconds,
+    /// reporting it as unreadable.
+    #[arg(long)]
+    pub time_limit: Option<u64>,
+
+    pub old_container: PathBuf,
+    pub new_container: PathBuf,
+}
+
+#[derive(Parser)]
+struct CmpExportsArgs {
+    /// How many bytes at the start of each exported function to compare.
+    #[arg(short, long, default_value = "0")]
+    pub entry_bytes: usize,
+
+    /// The old file is a memory dump in which each section is at its relative virtual address.
+    #[arg(long)]
+    pub old_mapped: bool,
+
+    /// The new file is a memory dump in which each section is at its relative virtual address.
+    #[arg(long)]
+    pub new_mapped: bool,
+
+    pub old_file: PathBuf,
+    pub new_file: PathBuf,
+}
+
+#[derive(Parser)]
+struct StringsArgs {
+    /// Output the strings as JSON.
+    #[arg(short, long)]
+    pub json_output: bool,
+
+    #[command(flatten)]
+    pub json_format: JsonFormatArgs,
+
+    /// The minimum number of characters in a string.
+    #[arg(short = 'n', long, default_value = "4")]
+    pub min_length: usize,
+
+    /// The encodings of the strings to look for.
+    #[arg(short, long, default_value = "all")]
+    pub encoding: crate::strings::EncodingFilter,
+
+    pub input_file: PathBuf,
+}
+
+#[derive(Parser)]
+struct ImportJsonArgs {
+    /// Write the JSON output into this file instead of stdout.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Output compact JSON on a single line instead of pretty-printing it.
+    #[arg(long)]
+    pub compact: bool,
+
+    pub input_file: PathBuf,
+}
+
+#[derive(Parser)]
+struct VerifyFixturesArgs {
+    /// Output the report, including every failure, as JSON.
+    #[arg(short, long)]
+    pub json_output: bool,
+
+    #[command(flatten)]
+    pub json_format: JsonFormatArgs,
+
+    /// Output every failure after the statistics.
+    #[arg(long, conflicts_with = "json_output")]
+    pub failures: bool,
+
+    /// Stop running the parsers over a file after this many seconds; the parsers that run out of
+    /// time fail with the category `timeout`.
+    #[arg(long)]
+    pub time_limit: Option<u64>,
+
+    /// The directory containing the corpus.
+    pub corpus: PathBuf,
+}
+
+#[derive(Parser)]
+struct CdInputFileArgs {
+    #[arg(short = 'H', long)] pub high_sierra: bool,
+    #[arg(short = 'n', long)] pub number: Option<u64>,
+    pub input_file: PathBuf,
+}
+
+#[derive(Parser)]
+struct CdJsonOutputArgs {
+    #[arg(short = 'H', long)] pub high_sierra: bool,
+
+    /// Output the collected information as JSON.
+    #[arg(short, long)]
+    pub json_output: bool,
+
+    #[command(flatten)]
+    pub json_format: JsonFormatArgs,
+
+    pub input_file: PathBuf,
+}
+
+
+fn set_up_tracing() {
+    use tracing_subscriber::EnvFilter;
+    use tracing_subscriber::fmt;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    tracing_subscriber::registry()
+        .with(
+            fmt::layer()
+                .with_writer(std::io::stderr)
+        )
+        .with(EnvFilter::from_default_env())
+        .init();
+}
+
+
+fn main() {
+    set_up_tracing();
+
+    let prog_args = ProgArgs::parse();
+    if prog_args.schema {
+        let schema = crate::schema::json_schema();
+        println!("{}", serde_json::to_string_pretty(&schema).expect("failed to JSONify"));
+        return;
+    }
+    let mode = prog_args.mode
+        .expect("no mode given");
+    match mode {
+        ProgMode::Poke(poke_mode) => {
+            match poke_mode {
+                PokeMode::Expand(args) => {
+                    let mut input_file = crate::input::open_seekable(&args.input_file)
+                        .expect("failed to open input file");
+
+                    let mut magic = [0u8; 8];
+                    input_file.read_exact(&mut magic)
+                        .expect("failed to read magic value");
+                    if let Some(variant) = expandms::szdd::Variant::from_magic(&magic) {
+                        println!("{}: {}", variant, variant.window_parameters());
+                    }
+                    input_file.seek(SeekFrom::Start(0))
+                        .expect("failed to seek back to the beginning");
+
+                    if args.check {
+                        let mut sink = expandms::CountingWriter::new(std::io::sink());
+                        let result = expandms::decompress(&mut input_file, &mut sink);
+                        report_check(&args.input_file, sink.count(), result);
+                        return;
+                    }
+
+                    let mut output = Vec::new();
+                    if let Err(e) = expandms::decompress(&mut input_file, &mut output) {
+                        panic!("failed to decompress: {}", e);
+                    }
+                    std::fs::write(args.output_file.as_ref().unwrap(), &output)
+                        .expect("failed to write output file");
+                },
+                PokeMode::ExpandDir(args) => {
+                    let summary = crate::extract::expand_directory(&args.input_dir, &args.output_dir, args.uncompressed)
+                        .expect("failed to expand directory");
+                    println!(
+                        "{} expanded, {} copied, {} skipped, {} failed",
+                        summary.expanded, summary.copied, summary.skipped, summary.failed,
+                    );
+                    if summary.failed > 0 {
+                        ExitStatus::PartialWithErrors.exit();
+                    }
+                },
+                PokeMode::Fat(poke_fat_mode) => {
+                    match poke_fat_mode {
+                        PokeFatMode::FatHeader(args) => {
+                            let mut input_file = File::open(&args.input_file)
+                                .expect("failed to open input file");
+
+                            // read header
+                            let fat_header = FatHeader::read(&mut input_file)
+                                .expect("failed to read FAT header");
+                            println!("{:#?}", fat_header);
+                            println!("{:?}", fat_header.variant());
+
+                            // read allocation tables
+                            let allocation_tables = AllocationTableCopies::read(&mut input_file, &fat_header)
+                                .expect("failed to read in allocation table");
+                            let (primary_copy, allocation_table) = allocation_tables.primary();
+                            println!("readable allocation table copies: {:?}", allocation_tables.readable_copies());
+                            println!("using allocation table copy {}", primary_copy);
+
+                            println!("{:?}", allocation_table);
+                        },
+                        PokeFatMode::FatDirectory(args) => {
+                            let mut input_file = File::open(&args.input_file)
+                                .expect("failed to open input file");
+
+                            // read header
+                            let fat_header = FatHeader::read(&mut input_file)
+                                .expect("failed to read FAT header");
+
+                            // read allocation tables
+                            let allocation_tables = AllocationTableCopies::read(&mut input_file, &fat_header)
+                                .expect("failed to read in allocation table");
+
+                            let mut dir_data = Vec::new();
+                            if let Some(subdirectory_cluster_index) = args.index {
+                                // read the chain of clusters
+                                allocation_tables.read_cluster_chain_into(&mut input_file, &fat_header, subdirectory_cluster_index, &mut dir_data)
+                                    .expect("failed to read cluster chain");
+                            } else {
+                                match fat_header.root_directory_location {
+                                    RootDirectoryLocation::Sector(sector) => {
+                                        let sector_count = u32::from(fat_header.max_root_dir_entries) * 32 / u32::from(fat_header.bytes_per_sector);
+                                        for i in 0..sector_count {
+                                            expandms::fat::read_sector_into(&mut input_file, &fat_header, sector + i, &mut dir_data)
+                                                .expect("failed to read sector");
+                                        }
+                                    },
+                                    RootDirectoryLocation::Cluster(cluster) => {
+                                        allocation_tables.read_cluster_chain_into(&mut input_file, &fat_header, cluster, &mut dir_data)
+                                            .expect("failed to read cluster chain");
                                     },
                                 }
                             }
@@ -358,22 +1175,54 @@ fn main() {
                             let fat_header = FatHeader::read(&mut input_file)
                                 .expect("failed to read FAT header");
 
-                            // skip over reserved sectors
-                            let reserved_bytes = u64::from(fat_header.reserved_sector_count) * u64::from(fat_header.bytes_per_sector);
-                            input_file.seek(SeekFrom::Start(reserved_bytes))
-                                .expect("failed to seek to start of allocation table");
-
-                            // read allocation table
-                            let fat_length = usize::try_from(fat_header.sectors_per_fat).unwrap() * usize::from(fat_header.bytes_per_sector);
-                            let allocation_table = AllocationTable::read(&mut input_file, fat_header.variant(), fat_length)
+                            // read allocation tables
+                            let allocation_tables = AllocationTableCopies::read(&mut input_file, &fat_header)
                                 .expect("failed to read in allocation table");
 
                             // read a chain of clusters
                             let mut data = Vec::new();
-                            expandms::fat::read_cluster_chain_into(&mut input_file, &fat_header, &allocation_table, args.index, &mut data)
+                            let copy = allocation_tables.read_cluster_chain_into(&mut input_file, &fat_header, args.index, &mut data)
                                 .expect("failed to read cluster chain");
+                            if copy != allocation_tables.primary().0 {
+                                eprintln!("chain is damaged in the first allocation table; read it using copy {}", copy);
+                            }
                             println!("{:?}", data);
                         },
+                        PokeFatMode::FatPut(args) => {
+                            let data = std::fs::read(&args.input_file)
+                                .expect("failed to read input file");
+                            let mut image_file = File::options()
+                                .read(true)
+                                .write(true)
+                                .open(&args.image_file)
+                                .expect("failed to open image file");
+
+                            // 1980-01-01 00:00:00
+                            expandms::fat::put_file(&mut image_file, &args.path_in_image, &data, 0x0021, 0x0000)
+                                .expect("failed to store file");
+                        },
+                        PokeFatMode::FatRm(args) => {
+                            let mut image_file = File::options()
+                                .read(true)
+                                .write(true)
+                                .open(&args.image_file)
+                                .expect("failed to open image file");
+                            expandms::fat::remove_file(&mut image_file, &args.path_in_image)
+                                .expect("failed to remove file");
+                        },
+                        PokeFatMode::FatFsck(args) => {
+                            let mut input_file = File::open(&args.input_file)
+                                .expect("failed to open input file");
+                            let inconsistencies = expandms::fat::check_consistency(&mut input_file)
+                                .expect("failed to check file system");
+                            if inconsistencies.is_empty() {
+                                println!("no inconsistencies found");
+                            }
+                            for inconsistency in &inconsistencies {
+                                println!("{}", inconsistency);
+                                println!("  suggestion: {}", inconsistency.suggestion());
+                            }
+                        },
                     }
                 },
                 PokeMode::Exe(poke_exe_mode) => {
@@ -386,6 +1235,41 @@ fn main() {
                                 .expect("failed to read MZ header");
                             println!("{:#?}", mz);
                         },
+                        PokeExeMode::MzStub(args) => {
+                            let mut input_file = File::open(&args.input_file)
+                                .expect("failed to open input file");
+
+                            let mz = binms::mz::Executable::read(&mut input_file)
+                                .expect("failed to read MZ header");
+                            let stub = mz.read_stub(&mut input_file)
+                                .expect("failed to read stub");
+                            let (code, rich) = binms::mz::split_rich_header(&stub);
+                            let comparison = binms::mz::compare_stub(&stub);
+
+                            if let Some(output_file) = args.output_file.as_ref() {
+                                std::fs::write(output_file, &stub)
+                                    .expect("failed to write stub");
+                            }
+
+                            if args.json_output {
+                                let json_stub = serde_json::json!({
+                                    "offset": mz.image_offset(),
+                                    "length": stub.len(),
+                                    "code_length": code.len(),
+                                    "rich_header_length": rich.map(|r| r.len()),
+                                    "comparison": comparison,
+                                });
+                                args.json_format.write(DocumentKind::MzStub, &json_stub);
+                            } else {
+                                println!("stub at {:#X}, {} bytes", mz.image_offset(), stub.len());
+                                match rich {
+                                    Some(r) => println!("Rich header: {} bytes after {} bytes of stub", r.len(), code.len()),
+                                    None => println!("Rich header: none"),
+                                }
+                                println!("{:?}", comparison);
+                                println!("{}", DisplayBytesSlice::from(code));
+                            }
+                        },
                         PokeExeMode::NeHeader(args) => {
                             let mut input_file = File::open(&args.input_file)
                                 .expect("failed to open input file");
@@ -394,9 +1278,133 @@ fn main() {
                                 .expect("failed to read NE header");
 
                             if args.json_output {
-                                println!("{}", serde_json::to_string_pretty(&ne).expect("failed to JSONify"));
+                                let json_ne = crate::schema::NeHeader::from(&ne);
+                                args.json_format.write(DocumentKind::NeHeader, &json_ne);
                             } else {
                                 println!("{:#?}", ne);
+                                println!("application type: {:?}", ne.flags.application_type());
+                            }
+                        },
+                        PokeExeMode::NeEntries(args) => {
+                            let mut input_file = File::open(&args.input_file)
+                                .expect("failed to open input file");
+                            let ne = binms::ne::Executable::read(&mut input_file)
+                                .expect("failed to read NE header");
+
+                            let entries = ne.entries();
+                            if args.json_output {
+                                let json_entries: Vec<crate::schema::NeEntry> = entries.iter()
+                                    .map(crate::schema::NeEntry::from)
+                                    .collect();
+                                args.json_format.write(DocumentKind::NeEntries, &json_entries);
+                            } else {
+                                for entry in &entries {
+                                    println!(
+                                        "@{}\t{:#04X}:{:#06X}\t{}\t{:?}",
+                                        entry.ordinal,
+                                        entry.segment_number,
+                                        entry.entry_point_offset,
+                                        if entry.moveable { "moveable" } else { "fixed" },
+                                        entry.flags,
+                                    );
+                                }
+                            }
+                        },
+                        PokeExeMode::NeSegments(args) => {
+                            let mut input_file = File::open(&args.input_file)
+                                .expect("failed to open input file");
+                            let ne = binms::ne::Executable::read(&mut input_file)
+                                .expect("failed to read NE header");
+                            let shift = ne.logical_sector_alignment_shift_count;
+
+                            // segment numbers are 1-based
+                            let json_segments: Vec<crate::schema::NeSegment> = ne.segment_table.iter()
+                                .zip(1u32..)
+                                .map(|(segment, number)| crate::schema::NeSegment {
+                                    number,
+                                    offset_bytes: segment.file_offset_bytes(shift),
+                                    length_bytes: segment.length_bytes(),
+                                    min_allocation_size_bytes: segment.min_allocation_size_bytes,
+                                    flags: segment.flags.bits(),
+                                    relocation_count: segment.relocation_entries.len(),
+                                })
+                                .collect();
+                            if args.json_output {
+                                args.json_format.write(DocumentKind::NeSegments, &json_segments);
+                            } else {
+                                for (segment, number) in ne.segment_table.iter().zip(1u32..) {
+                                    println!(
+                                        "segment {}: offset {:#X}, length {:#X}, min alloc {:#X}, {} relocations, flags {:?}",
+                                        number,
+                                        segment.file_offset_bytes(shift),
+                                        segment.length_bytes(),
+                                        segment.min_allocation_size_bytes,
+                                        segment.relocation_entries.len(),
+                                        segment.flags,
+                                    );
+                                }
+                            }
+                        },
+                        PokeExeMode::NeResources(args) => {
+                            let mut input_file = File::open(&args.input_file)
+                                .expect("failed to open input file");
+                            let ne = binms::ne::Executable::read(&mut input_file)
+                                .expect("failed to read NE header");
+                            let shift = ne.resource_table.alignment_shift_count;
+
+                            let mut selected = Vec::new();
+                            for (type_id, res_type) in &ne.resource_table.id_to_type {
+                                if let Some(want_type) = args.res_type {
+                                    if *type_id != binms::ne::ResourceId::Numbered(want_type) {
+                                        continue;
+                                    }
+                                }
+
+                                for (res_id, res) in &res_type.resources {
+                                    if let Some(want_id) = args.res_id {
+                                        if *res_id != binms::ne::ResourceId::Numbered(want_id) {
+                                            continue;
+                                        }
+                                    } else if let Some(want_id) = args.res_id_name.as_ref() {
+                                        let binms::ne::ResourceId::Named(this_id) = res_id
+                                            else { continue };
+                                        let this_id_bytes: &[u8] = this_id.as_ref();
+                                        if want_id.as_bytes() != this_id_bytes {
+                                            continue;
+                                        }
+                                    }
+                                    selected.push((type_id, res));
+                                }
+                            }
+
+                            if let Some(output_file) = args.output_file.as_ref() {
+                                if selected.len() != 1 {
+                                    panic!("{} resources match; narrow the selection down to exactly one", selected.len());
+                                }
+                                let (_type_id, res) = selected[0];
+                                let data: &[u8] = res.data.as_ref();
+                                std::fs::write(output_file, data)
+                                    .expect("failed to write output file");
+                            } else if args.json_output {
+                                let json_table = crate::schema::NeResourceTable {
+                                    alignment_shift_count: shift,
+                                    resources: selected.iter()
+                                        .map(|(type_id, res)| crate::schema::NeResource::new(type_id, res))
+                                        .collect(),
+                                };
+                                args.json_format.write(DocumentKind::NeResources, &json_table);
+                            } else {
+                                println!("alignment shift count: {}", shift);
+                                for (type_id, res) in &selected {
+                                    println!(
+                                        "{:?}/{:?}: offset {:#X}, length {:#X}, flags {:?}",
+                                        type_id,
+                                        res.resource_id,
+                                        res.file_offset_bytes(),
+                                        res.length_bytes(),
+                                        res.flags,
+                                    );
+                                }
                             }
                         },
                         PokeExeMode::NeIconGroups(args) => {
@@ -408,43 +1416,59 @@ fn main() {
                             for (type_id, res_type) in &ne.resource_table.id_to_type {
                                 const CURSOR_LIST: u16 = 0x8000 | 12;
                                 const ICON_LIST: u16 = 0x8000 | 14;
-                                match type_id {
-                                    binms::ne::ResourceId::Numbered(CURSOR_LIST) => {
-                                    },
-                                    binms::ne::ResourceId::Numbered(ICON_LIST) => {
-                                    },
+                                const CURSOR: u16 = 0x8000 | 1;
+                                const ICON: u16 = 0x8000 | 3;
+                                let member_type_id = match type_id {
+                                    binms::ne::ResourceId::Numbered(CURSOR_LIST) => CURSOR,
+                                    binms::ne::ResourceId::Numbered(ICON_LIST) => ICON,
                                     _ => continue,
-                                }
+                                };
+
+                                // which variants do the icons (if they are in Windows 1.0 format) have?
+                                let member_type = ne.resource_table.id_to_type.get(&binms::ne::ResourceId::Numbered(member_type_id));
+                                let variants_of = |icon_id: u16| -> Vec<binms::ico1::Icon1Variant> {
+                                    member_type
+                                        .and_then(|mt| mt.resources.get(&binms::ne::ResourceId::Numbered(0x8000 | icon_id)))
+                                        .and_then(|res| binms::ico1::Icon1::take_from_bytes(res.data.as_ref()).ok())
+                                        .map(|(_rest, icon)| icon.variants().into_iter().map(|(v, _data)| v).collect())
+                                        .unwrap_or_default()
+                                };
 
                                 let mut json_groups = Vec::with_capacity(res_type.resources.len());
                                 for (res_id, res) in &res_type.resources {
                                     let data: &[u8] = res.data.as_ref();
                                     let Ok((_rest, icon_group)) = binms::icon_group::IconGroup::take_from_bytes(data)
                                         else { continue; };
-                                    if !args.json_output {
+                                    if args.json_output {
+                                        let icon_variants = icon_group.icons.iter()
+                                            .map(|icon| crate::schema::NeIconVariants {
+                                                id: icon.id,
+                                                variants: variants_of(icon.id).into_iter().map(|v| v.into()).collect(),
+                                            })
+                                            .filter(|iv| !iv.variants.is_empty())
+                                            .collect();
+                                        json_groups.push(crate::schema::NeIconGroup {
+                                            group_id: res_id.into(),
+                                            icon_group: (&icon_group).into(),
+                                            icon_variants,
+                                        });
+                                    } else {
                                         println!("icon group {:?}:", res_id);
-                                    }
-                                    let mut json_icons = Vec::with_capacity(icon_group.icons.len());
-                                    for icon in icon_group.icons {
-                                        if args.json_output {
-                                            let mut icon_value = serde_json::to_value(&icon)
-                                                .expect("failed to serialize icon");
-                                            icon_value["raw_id"] = serde_json::Value::from(icon.id + 0x8000);
-                                            json_icons.push(icon_value);
-                                        } else {
-                                            println!("  {:?} ({})", icon, icon.id + 0x8000);
+                                        for icon in &icon_group.icons {
+                                            let variant_names: Vec<&str> = variants_of(icon.id).iter()
+                                                .map(|v| v.name())
+                                                .collect();
+                                            if variant_names.is_empty() {
+                                                println!("  {:?} ({})", icon, icon.id + 0x8000);
+                                            } else {
+                                                println!("  {:?} ({}) [{}]", icon, icon.id + 0x8000, variant_names.join(", "));
+                                            }
                                         }
                                     }
-                                    if args.json_output {
-                                        json_groups.push(serde_json::json!({
-                                            "group_id": res_id,
-                                            "icons": json_icons,
-                                        }));
-                                    }
                                 }
 
                                 if args.json_output {
-                                    println!("{}", serde_json::to_string_pretty(&json_groups).expect("failed to JSONify"));
+                                    args.json_format.write(DocumentKind::NeIconGroups, &json_groups);
                                 }
                             }
                         },
@@ -478,17 +1502,29 @@ fn main() {
 
                                             // try parsing as Ico1
                                             if let Ok((_rest, icon)) = binms::ico1::Icon1::take_from_bytes(data_bytes) {
-                                                let variants = [
-                                                    ("device-dependent", icon.device_dependent.as_ref()),
-                                                    ("device-independent", icon.device_independent.as_ref()),
-                                                ];
-                                                for (variant_name, variant_icon_opt) in variants {
-                                                    let Some(variant_icon) = variant_icon_opt
-                                                        else { continue };
+                                                let variants: Vec<_> = icon.variants()
+                                                    .into_iter()
+                                                    .filter(|(v, _data)| args.variant.is_none_or(|sel| sel.to_icon1_variant() == *v))
+                                                    .collect();
+                                                if variants.is_empty() {
+                                                    println!("icon does not contain the selected variant; skipping");
+                                                    continue;
+                                                }
+
+                                                // don't let the variants overwrite each other
+                                                let name_by_variant = variants.len() > 1;
+                                                for (variant, variant_icon) in variants {
+                                                    let variant_name = variant.name();
+                                                    let output_path = if name_by_variant {
+                                                        variant_output_path(&args.output_file, variant_name)
+                                                    } else {
+                                                        args.output_file.clone()
+                                                    };
+                                                    println!("writing {} variant to {}", variant_name, output_path.display());
 
                                                     match args.format {
                                                         GraphicsOutputFormat::Sixel => {
-                                                            let mut f = File::create(&args.output_file)
+                                                            let mut f = File::create(&output_path)
                                                                 .expect("failed to open output file");
 
                                                             // spit out the sixel streams
@@ -502,7 +1538,7 @@ fn main() {
                                                                 .expect("failed to flush output file");
                                                         },
                                                         GraphicsOutputFormat::Ascii => {
-                                                            let mut f = File::create(&args.output_file)
+                                                            let mut f = File::create(&output_path)
                                                                 .expect("failed to open output file");
 
                                                             // ASCII-only output
@@ -539,7 +1575,7 @@ fn main() {
                                                                 .expect("failed to flush output file");
                                                         },
                                                         GraphicsOutputFormat::Png => {
-                                                            let f = File::create(&args.output_file)
+                                                            let f = File::create(&output_path)
                                                                 .expect("failed to open output file");
 
                                                             let width = usize::try_from(variant_icon.width_bytes).unwrap();
@@ -603,34 +1639,39 @@ fn main() {
                                                             writer.write_image_data(&png_data)
                                                                 .expect("failed to write PNG data");
                                                         },
+                                                        GraphicsOutputFormat::Bmp|GraphicsOutputFormat::Ppm => {
+                                                            let raster_format = args.format.raster_format().unwrap();
+                                                            let f = File::create(&output_path)
+                                                                .expect("failed to open output file");
+                                                            raster_format.write_rgba8(
+                                                                BufWriter::new(f),
+                                                                variant_icon.width_pixels.into(),
+                                                                variant_icon.height_pixels.into(),
+                                                                &variant_icon.to_rgba8(),
+                                                            )
+                                                                .expect("failed to write image");
+                                                        },
                                                     }
                                                 }
                                             } else if let Ok((_rest, icon)) = binms::bitmap::Bitmap::take_from_bytes(data_bytes, true) {
-                                                if args.format != GraphicsOutputFormat::Png {
-                                                    println!("can only output as PNG; skipping");
+                                                let Some(raster_format) = args.format.raster_format() else {
+                                                    println!("can only output as PNG, BMP or PPM; skipping");
                                                     continue;
-                                                }
+                                                };
 
                                                 println!("original bit depth: {}", icon.header.bit_count);
 
+                                                // output as RGBA
+                                                // (run optipng on the result if you don't like that)
                                                 let f = File::create(&args.output_file)
                                                     .expect("failed to open output file");
-
-                                                let mut encoder = png::Encoder::new(
-                                                    f,
+                                                raster_format.write_rgba8(
+                                                    BufWriter::new(f),
                                                     icon.actual_width(),
                                                     icon.actual_height(),
-                                                );
-
-                                                // output as RGBA
-                                                // (run optipng on the result if you don't like that)
-                                                encoder.set_color(png::ColorType::Rgba);
-                                                encoder.set_depth(png::BitDepth::Eight);
-
-                                                let mut writer = encoder.write_header()
-                                                    .expect("failed to write PNG header");
-                                                writer.write_image_data(&icon.to_rgba8())
-                                                    .expect("failed to write PNG data");
+                                                    &icon.to_rgba8(),
+                                                )
+                                                    .expect("failed to write image");
                                             } else {
                                                 println!("parses as neither ICO1 nor bitmap");
                                             }
@@ -644,18 +1685,79 @@ fn main() {
                                 .expect("failed to open input file");
                             let ne = binms::ne::Executable::read(&mut input_file)
                                 .expect("failed to read NE header");
+                            const NE_RT_FONTDIR: u16 = 0x8000 | 7;
                             const NE_RT_FONT: u16 = 0x8000 | 8;
-                            let res_type = ne.resource_table.id_to_type.get(&binms::ne::ResourceId::Numbered(NE_RT_FONT))
-                                .expect("no fonts in NE file");
-                            for font_resource in res_type.resources.values() {
-                                println!("font {:?}:", font_resource.resource_id);
-                                let (_rest, font) = binms::bitmap_font::Font::take_from_bytes(font_resource.data.as_ref())
-                                    .expect("failed to load bitmap font");
-
-                                let ext = match args.font_format {
-                                    FontFormat::Bdf => "bdf",
-                                    FontFormat::Fnt => "fnt",
-                                    FontFormat::Fd => "fd",
+                            let wanted = |res_id: &binms::ne::ResourceId| match (res_id, args.res_id, args.res_name.as_ref()) {
+                                (binms::ne::ResourceId::Numbered(num), Some(want_id), _)
+                                    => num & 0x7FFF == want_id & 0x7FFF,
+                                (binms::ne::ResourceId::Named(_), Some(_), _)
+                                    => false,
+                                (binms::ne::ResourceId::Named(name), None, Some(want_name)) => {
+                                    let name_bytes: &[u8] = name.as_ref();
+                                    name_bytes.eq_ignore_ascii_case(want_name.as_bytes())
+                                },
+                                (binms::ne::ResourceId::Numbered(_), None, Some(_))
+                                    => false,
+                                (_, None, None)
+                                    => true,
+                            };
+
+                            if args.font_dir {
+                                let font_dirs = ne.resource_table.id_to_type.get(&binms::ne::ResourceId::Numbered(NE_RT_FONTDIR));
+                                for font_dir_resource in font_dirs.into_iter().flat_map(|rt| rt.resources.values()) {
+                                    let font_dir = match binms::bitmap_font::FontDirectory::take_from_bytes(font_dir_resource.data.as_ref()) {
+                                        Ok((_rest, fd)) => fd,
+                                        Err(e) => {
+                                            error!("failed to read font directory {:?}: {}", font_dir_resource.resource_id, e);
+                                            continue;
+                                        },
+                                    };
+                                    println!("font directory {:?}:", font_dir_resource.resource_id);
+                                    println!("ordinal\tpoints\twidth\theight\tweight\tcharset\tface");
+                                    for entry in &font_dir.entries {
+                                        // the directory refers to the fonts by their ordinals
+                                        if !wanted(&binms::ne::ResourceId::Numbered(0x8000 | entry.ordinal)) {
+                                            continue;
+                                        }
+                                        println!(
+                                            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                                            entry.ordinal, entry.point_size, entry.pixel_width, entry.pixel_height,
+                                            entry.weight, entry.char_set, entry.name,
+                                        );
+                                    }
+                                }
+                            }
+
+                            if args.json_output {
+                                let fonts = ne.resource_table.id_to_type.get(&binms::ne::ResourceId::Numbered(NE_RT_FONT));
+                                let mut json_fonts = Vec::new();
+                                for font_resource in fonts.into_iter().flat_map(|rt| rt.resources.values()) {
+                                    if !wanted(&font_resource.resource_id) {
+                                        continue;
+                                    }
+                                    match binms::bitmap_font::Font::take_from_bytes(font_resource.data.as_ref()) {
+                                        Ok((_rest, font)) => json_fonts.push(crate::schema::NeFont::new(&font_resource.resource_id, &font)),
+                                        Err(e) => error!("failed to load bitmap font {:?}: {}", font_resource.resource_id, e),
+                                    }
+                                }
+                                args.json_format.write(DocumentKind::NeFonts, &json_fonts);
+                                return;
+                            }
+
+                            let res_type = ne.resource_table.id_to_type.get(&binms::ne::ResourceId::Numbered(NE_RT_FONT))
+                                .expect("no fonts in NE file");
+                            for font_resource in res_type.resources.values() {
+                                if !wanted(&font_resource.resource_id) {
+                                    continue;
+                                }
+                                println!("font {:?}:", font_resource.resource_id);
+                                let (_rest, font) = binms::bitmap_font::Font::take_from_bytes(font_resource.data.as_ref())
+                                    .expect("failed to load bitmap font");
+
+                                let ext = match args.font_format {
+                                    FontFormat::Bdf => "bdf",
+                                    FontFormat::Fnt => "fnt",
+                                    FontFormat::Fd => "fd",
                                 };
 
                                 if let Some(mut output_path) = args.output_prefix.clone() {
@@ -700,39 +1802,191 @@ fn main() {
                                 }
                             }
                         },
+                        PokeExeMode::NeStrings(args) => {
+                            let mut input_file = File::open(&args.input_file)
+                                .expect("failed to open input file");
+                            let ne = binms::ne::Executable::read(&mut input_file)
+                                .expect("failed to read NE header");
+                            const NE_RT_STRING: u16 = 0x8000 | 6;
+
+                            let mut json_strings = Vec::new();
+                            let res_type = ne.resource_table.id_to_type.get(&binms::ne::ResourceId::Numbered(NE_RT_STRING));
+                            for (res_id, res) in res_type.into_iter().flat_map(|rt| &rt.resources) {
+                                let binms::ne::ResourceId::Numbered(numbered_id) = res_id else {
+                                    warn!("skipping string table with non-numeric ID {:?}", res_id);
+                                    continue;
+                                };
+                                let block_id = numbered_id & 0x7FFF;
+                                let block = match binms::string_table::StringBlock::from_ne_bytes(block_id, res.data.as_ref()) {
+                                    Ok(b) => b,
+                                    Err(e) => {
+                                        error!("failed to read string table {}: {}", block_id, e);
+                                        continue;
+                                    },
+                                };
+                                for (id, bytes) in block.entries() {
+                                    let text = binms::string_table::decode_windows_1252(bytes);
+                                    if args.json_output {
+                                        json_strings.push(crate::schema::NeString {
+                                            id,
+                                            block_id,
+                                            text,
+                                            bytes: bytes.to_vec(),
+                                        });
+                                    } else {
+                                        println!("{}\t{}", id, escape_str(&text));
+                                    }
+                                }
+                            }
+
+                            if args.json_output {
+                                args.json_format.write(DocumentKind::NeStrings, &json_strings);
+                            }
+                        },
                         PokeExeMode::PeHeader(args) => {
                             let mut input_file = File::open(&args.input_file)
                                 .expect("failed to open input file");
                             let pe = binms::pe::Executable::read(&mut input_file)
                                 .expect("failed to read PE header");
                             println!("{:#?}", pe);
+                            println!("time/date stamp: {}", pe.decoded_time_date_stamp());
                         },
-                        PokeExeMode::PeResources(args) => {
+                        PokeExeMode::PeSections(args) => {
                             let mut input_file = File::open(&args.input_file)
                                 .expect("failed to open input file");
+                            let file_size = input_file.metadata()
+                                .expect("failed to obtain input file metadata")
+                                .len();
                             let pe = binms::pe::Executable::read(&mut input_file)
                                 .expect("failed to read PE header");
-                            let optional_header = pe.optional_header.as_ref()
-                                .expect("PE file is missing optional header");
-                            let binms::pe::OptionalHeader::Coff(cough) = optional_header
-                                else { panic!("PE file's optional header is not COFF") };
-                            let optional_win_header = cough.optional_windows_header.as_ref()
-                                .expect("PE file's COFF optional header does not contain the optional Windows header");
-                            let res_entry = optional_win_header.known_data_directory_entry(binms::pe::KnownDataDirectoryEntry::ResourceTable)
-                                .expect("PE file does not have a resource directory entry");
-                            let resources = binms::pe::ResourceDirectoryTable::read_root_from_pe(&mut input_file, &res_entry, &pe.section_table)
-                                .expect("failed to read resources");
+
+                            // raw sizes are padded to the file alignment; don't warn about that
+                            let file_alignment = match pe.optional_header.as_ref() {
+                                Some(binms::pe::OptionalHeader::Coff(cough)) => cough.optional_windows_header
+                                    .as_ref()
+                                    .map(|wh| wh.file_alignment.max(1))
+                                    .unwrap_or(1),
+                                _ => 1,
+                            };
+
+                            let mut json_sections = Vec::new();
+                            if !args.json_output {
+                                println!("{:<8}  {:>10}  {:>10}  {:>10}  {:>10}  {:<18}  characteristics", "name", "raw start", "raw end", "virt start", "virt end", "class");
+                            }
+                            for section in pe.section_table.as_entries() {
+                                let name_bytes: &[u8] = section.name.as_ref();
+                                let name = String::from_utf8_lossy(name_bytes)
+                                    .trim_end_matches('\0')
+                                    .to_owned();
+                                let raw_end = u64::from(section.raw_data_pointer) + u64::from(section.raw_data_size);
+                                let virtual_end = u64::from(section.virtual_address) + u64::from(section.virtual_size);
+
+                                let aligned_virtual_size = u64::from(section.virtual_size)
+                                    .div_ceil(file_alignment.into()) * u64::from(file_alignment);
+                                let mut warnings = Vec::new();
+                                if u64::from(section.raw_data_size) > aligned_virtual_size {
+                                    warnings.push(format!(
+                                        "raw size {:#X} exceeds virtual size {:#X}; the excess is not mapped",
+                                        section.raw_data_size, section.virtual_size,
+                                    ));
+                                } else if section.raw_data_size > 0 && section.raw_data_size < section.virtual_size {
+                                    warnings.push(format!(
+                                        "virtual size {:#X} exceeds raw size {:#X}; the remainder is zero-filled",
+                                        section.virtual_size, section.raw_data_size,
+                                    ));
+                                }
+                                if section.raw_data_size > 0 && raw_end > file_size {
+                                    warnings.push(format!(
+                                        "raw data ends at {:#X}, beyond the end of the file at {:#X}",
+                                        raw_end, file_size,
+                                    ));
+                                }
+
+                                if args.json_output {
+                                    json_sections.push(crate::schema::PeSection {
+                                        name,
+                                        raw_start: section.raw_data_pointer,
+                                        raw_end,
+                                        virtual_start: section.virtual_address,
+                                        virtual_end,
+                                        class: section.class().into(),
+                                        characteristics: section.characteristics.bits(),
+                                        warnings,
+                                    });
+                                } else {
+                                    println!(
+                                        "{:<8}  {:>#10X}  {:>#10X}  {:>#10X}  {:>#10X}  {:<18}  {:?}",
+                                        name,
+                                        section.raw_data_pointer,
+                                        raw_end,
+                                        section.virtual_address,
+                                        virtual_end,
+                                        section.class().to_string(),
+                                        section.characteristics,
+                                    );
+                                    for warning in &warnings {
+                                        println!("  warning: {}", warning);
+                                    }
+                                }
+                            }
+
                             if args.json_output {
+                                args.json_format.write(DocumentKind::PeSections, &json_sections);
+                            }
+                        },
+                        PokeExeMode::PeRva(args) => {
+                            let mut input_file = File::open(&args.input_file)
+                                .expect("failed to open input file");
+                            let pe = binms::pe::Executable::read(&mut input_file)
+                                .expect("failed to read PE header");
+                            let optional_win_header = match pe.optional_header.as_ref() {
+                                Some(binms::pe::OptionalHeader::Coff(cough)) => cough.optional_windows_header.as_ref(),
+                                _ => None,
+                            };
+
+                            println!("RVA {:#010X}", args.rva);
+                            if let Some(section) = pe.section_table.section_for_virtual(args.rva) {
+                                let name_bytes: &[u8] = section.name.as_ref();
+                                let name = String::from_utf8_lossy(name_bytes)
+                                    .trim_end_matches('\0')
+                                    .to_owned();
                                 println!(
-                                    "{}",
-                                    serde_json::to_string_pretty(&resources)
-                                        .expect("failed to serialize ResourceDirectoryTable to JSON"),
+                                    "section: {} (virt {:#010X}..{:#010X}), offset {:#X} into the section",
+                                    name,
+                                    section.virtual_address,
+                                    u64::from(section.virtual_address) + u64::from(section.virtual_size),
+                                    args.rva - section.virtual_address,
                                 );
+                                match pe.section_table.virtual_to_raw(args.rva) {
+                                    Some(raw) => println!("file offset: {:#010X}", raw),
+                                    None => println!("file offset: none (zero-filled part of the section)"),
+                                }
+                            } else if optional_win_header.is_some_and(|wh| args.rva < wh.headers_size) {
+                                // the headers are mapped verbatim
+                                println!("section: none (headers)");
+                                println!("file offset: {:#010X}", args.rva);
                             } else {
-                                println!("{:#?}", resources);
+                                println!("section: none (not mapped)");
+                            }
+
+                            if let Some(wh) = optional_win_header {
+                                for index in wh.data_directory_entries_for_virtual(args.rva) {
+                                    let entry = &wh.data_directory_entries[index];
+                                    let name = match binms::pe::KnownDataDirectoryEntry::try_from(index) {
+                                        Ok(known) => format!("{:?}", known),
+                                        Err(_) => format!("#{}", index),
+                                    };
+                                    println!(
+                                        "data directory: {} (virt {:#010X}..{:#010X}), offset {:#X} into the data",
+                                        name,
+                                        entry.address,
+                                        u64::from(entry.address) + u64::from(entry.size),
+                                        args.rva - entry.address,
+                                    );
+                                }
                             }
                         },
-                        PokeExeMode::PeIconGroups(args) => {
+                        PokeExeMode::PeDatadirs(args) => {
                             let mut input_file = File::open(&args.input_file)
                                 .expect("failed to open input file");
                             let pe = binms::pe::Executable::read(&mut input_file)
@@ -743,10 +1997,107 @@ fn main() {
                                 else { panic!("PE file's optional header is not COFF") };
                             let optional_win_header = cough.optional_windows_header.as_ref()
                                 .expect("PE file's COFF optional header does not contain the optional Windows header");
-                            let res_entry = optional_win_header.known_data_directory_entry(binms::pe::KnownDataDirectoryEntry::ResourceTable)
-                                .expect("PE file does not have a resource directory entry");
-                            let resources = binms::pe::ResourceDirectoryTable::read_root_from_pe(&mut input_file, &res_entry, &pe.section_table)
+
+                            let mut json_entries = Vec::new();
+                            if !args.json_output {
+                                println!("{:>2}  {:<21}  {:>10}  {:>10}  {:>10}  {:>10}  section", "#", "name", "address", "size", "raw start", "raw end");
+                            }
+                            for (index, entry) in optional_win_header.data_directory_entries.iter().enumerate() {
+                                let known = binms::pe::KnownDataDirectoryEntry::try_from(index).ok();
+                                let name = match known {
+                                    Some(k) => format!("{:?}", k),
+                                    None => String::new(),
+                                };
+                                let is_file_offset = known
+                                    .map(|k| k.address_is_file_offset())
+                                    .unwrap_or(false);
+
+                                let present = entry.address != 0 || entry.size != 0;
+                                let (raw_start, section_name) = if !present {
+                                    (None, None)
+                                } else if is_file_offset {
+                                    (Some(entry.address), None)
+                                } else {
+                                    let raw_start = pe.section_table.virtual_to_raw(entry.address);
+                                    let section_name = pe.section_table.section_for_virtual(entry.address)
+                                        .map(|section| {
+                                            let name_bytes: &[u8] = section.name.as_ref();
+                                            String::from_utf8_lossy(name_bytes)
+                                                .trim_end_matches('\0')
+                                                .to_owned()
+                                        });
+                                    (raw_start, section_name)
+                                };
+                                let raw_end = raw_start
+                                    .map(|rs| u64::from(rs) + u64::from(entry.size));
+
+                                if args.json_output {
+                                    json_entries.push(crate::schema::PeDataDirectory {
+                                        index,
+                                        name: known.map(|_| name),
+                                        address: entry.address,
+                                        size: entry.size,
+                                        address_is_file_offset: is_file_offset,
+                                        raw_start,
+                                        raw_end,
+                                        section: section_name,
+                                    });
+                                } else {
+                                    let raw_start_string = raw_start
+                                        .map(|rs| format!("{:#X}", rs))
+                                        .unwrap_or_else(|| "-".to_owned());
+                                    let raw_end_string = raw_end
+                                        .map(|re| format!("{:#X}", re))
+                                        .unwrap_or_else(|| "-".to_owned());
+                                    println!(
+                                        "{:>2}  {:<21}  {:>#10X}  {:>#10X}  {:>10}  {:>10}  {}",
+                                        index,
+                                        name,
+                                        entry.address,
+                                        entry.size,
+                                        raw_start_string,
+                                        raw_end_string,
+                                        section_name.as_deref().unwrap_or("-"),
+                                    );
+                                }
+                            }
+
+                            if args.json_output {
+                                args.json_format.write(DocumentKind::PeDataDirectories, &json_entries);
+                            }
+                        },
+                        PokeExeMode::PeResources(args) => {
+                            let (resources, mun_path) = crate::mun::read_resources_with_mun(&args.input_file, !args.no_mun)
+                                .expect("failed to read resources");
+                            let mut resources = resources
+                                .expect("PE file does not have resources");
+                            if let Some(mun_path) = mun_path.as_ref() {
+                                info!("merged resources from {}", mun_path.display());
+                            }
+                            if !args.raw {
+                                decompress_resources(&mut resources);
+                            }
+                            if args.json_output && args.dedup {
+                                let json_blobs = crate::schema::PeResourceBlobs::from(&resources);
+                                args.json_format.write(DocumentKind::PeResourceBlobs, &json_blobs);
+                            } else if args.json_output {
+                                let json_resources = crate::schema::PeResourceDirectory::from(&resources);
+                                args.json_format.write(DocumentKind::PeResources, &json_resources);
+                            } else if args.tree {
+                                if let Some(mun_path) = mun_path.as_ref() {
+                                    println!("merged with resources from {}", mun_path.display());
+                                }
+                                print_resource_tree(&resources, 0);
+                            } else {
+                                println!("{:#?}", resources);
+                            }
+                        },
+                        PokeExeMode::PeIconGroups(args) => {
+                            let (resources, _mun_path) = crate::mun::read_resources_with_mun(&args.input_file, true)
                                 .expect("failed to read resources");
+                            let mut resources = resources
+                                .expect("PE file does not have resources");
+                            decompress_resources(&mut resources);
 
                             // abide by the three-layer structure: type -> resource -> language
                             let relevant_resource_types = [
@@ -777,10 +2128,10 @@ fn main() {
                                             else { continue };
 
                                         if args.json_output {
-                                            json_langs.push(serde_json::json!({
-                                                "lang_id": lang_id,
-                                                "icon_group": ig,
-                                            }));
+                                            json_langs.push(crate::schema::PeIconGroupLanguage {
+                                                language_id: lang_id.into(),
+                                                icon_group: (&ig).into(),
+                                            });
                                         } else {
                                             println!("{:?}/{:?}/{:?}:", resource_type, resource_id, lang_id);
                                             for icon in &ig.icons {
@@ -789,43 +2140,31 @@ fn main() {
                                         }
                                     }
                                     if args.json_output {
-                                        json_resources_langs.push(serde_json::json!({
-                                            "resource_id": resource_id,
-                                            "langs": json_langs,
-                                        }));
+                                        json_resources_langs.push(crate::schema::PeIconGroupResource {
+                                            resource_id: resource_id.into(),
+                                            languages: json_langs,
+                                        });
                                     }
                                 }
                                 if args.json_output {
-                                    json_types_resources_langs.push(serde_json::json!({
-                                        "type_id": resource_type,
-                                        "resources": json_resources_langs,
-                                    }));
+                                    json_types_resources_langs.push(crate::schema::PeIconGroupType {
+                                        type_id: (&resource_type).into(),
+                                        resources: json_resources_langs,
+                                    });
                                 }
                             }
 
                             if args.json_output {
-                                println!(
-                                    "{}",
-                                    serde_json::to_string_pretty(&json_types_resources_langs)
-                                        .expect("failed to serialize serde_json::Value?!"),
-                                );
+                                args.json_format.write(DocumentKind::PeIconGroups, &json_types_resources_langs);
                             }
                         },
                         PokeExeMode::PeIcons(args) => {
-                            let mut input_file = File::open(&args.input_file)
-                                .expect("failed to open input file");
-                            let pe = binms::pe::Executable::read(&mut input_file)
-                                .expect("failed to read PE header");
-                            let optional_header = pe.optional_header.as_ref()
-                                .expect("PE file is missing optional header");
-                            let binms::pe::OptionalHeader::Coff(cough) = optional_header
-                                else { panic!("PE file's optional header is not COFF") };
-                            let optional_win_header = cough.optional_windows_header.as_ref()
-                                .expect("PE file's COFF optional header does not contain the optional Windows header");
-                            let res_entry = optional_win_header.known_data_directory_entry(binms::pe::KnownDataDirectoryEntry::ResourceTable)
-                                .expect("PE file does not have a resource directory entry");
-                            let resources = binms::pe::ResourceDirectoryTable::read_root_from_pe(&mut input_file, &res_entry, &pe.section_table)
+                            let (resources, _mun_path) = crate::mun::read_resources_with_mun(&args.input_file, true)
                                 .expect("failed to read resources");
+                            let mut resources = resources
+                                .expect("PE file does not have resources");
+                            decompress_resources(&mut resources);
+                            let raster_format = args.format.raster_format();
 
                             // abide by the three-layer structure: type -> resource -> language
                             let relevant_resource_types = [
@@ -879,15 +2218,30 @@ fn main() {
                                             else { continue };
 
                                         if data_bytes.starts_with(b"\x89PNG\r\n\x1A\n") {
-                                            // sweet, it's already a PNG, just dump it out
                                             println!("{:?}/{:?}/{:?} PNG", resource_type, resource_id, lang_id);
+                                            if raster_format == crate::image_output::RasterFormat::Png {
+                                                // sweet, it's already a PNG, just dump it out
+                                                let mut f = File::create(&args.output_file)
+                                                    .expect("failed to open output file");
+                                                f.write_all(data_bytes)
+                                                    .expect("failed to write output file");
+                                                f.flush()
+                                                    .expect("failed to flush output file");
+                                                continue;
+                                            }
 
-                                            let mut f = File::create(&args.output_file)
+                                            let (width, height, rgba) = match crate::image_output::read_png_rgba8(data_bytes) {
+                                                Ok(image) => image,
+                                                Err(e) => {
+                                                    println!("failed to decode PNG icon: {}", e);
+                                                    continue;
+                                                },
+                                            };
+                                            let f = File::create(&args.output_file)
                                                 .expect("failed to open output file");
-                                            f.write_all(data_bytes)
-                                                .expect("failed to write output file");
-                                            f.flush()
-                                                .expect("failed to flush output file");
+                                            raster_format.write_rgba8(BufWriter::new(f), width, height, &rgba)
+                                                .expect("failed to write image");
+                                            continue;
                                         }
 
                                         // try interpreting this as a standard Windows icon (v3 bitmap)
@@ -896,22 +2250,167 @@ fn main() {
 
                                         println!("{:?}/{:?}/{:?} BMP", resource_type, resource_id, lang_id);
 
-                                        // spit it out as a PNG
+                                        // spit it out in the requested format
                                         let f = File::create(&args.output_file)
                                             .expect("failed to open output file");
-                                        let mut png = png::Encoder::new(
-                                            f,
+                                        raster_format.write_rgba8(
+                                            BufWriter::new(f),
                                             bmp.actual_width(),
                                             bmp.actual_height(),
-                                        );
-                                        png.set_color(png::ColorType::Rgba);
-                                        png.set_depth(png::BitDepth::Eight);
-                                        let mut writer = png.write_header()
-                                            .expect("failed to write PNG header");
-                                        writer.write_image_data(&bmp.to_rgba8())
-                                            .expect("failed to write PNG data");
-                                        writer.finish()
-                                            .expect("failed to finish PNG");
+                                            &bmp.to_rgba8(),
+                                        )
+                                            .expect("failed to write image");
+                                    }
+                                }
+                            }
+                        },
+                        PokeExeMode::PeUnwind(args) => {
+                            let mut input_file = File::open(&args.input_file)
+                                .expect("failed to open input file");
+                            let pe = binms::pe::Executable::read(&mut input_file)
+                                .expect("failed to read PE header");
+                            let optional_header = pe.optional_header.as_ref()
+                                .expect("PE file is missing optional header");
+                            let binms::pe::OptionalHeader::Coff(cough) = optional_header
+                                else { panic!("PE file's optional header is not COFF") };
+                            let optional_win_header = cough.optional_windows_header.as_ref()
+                                .expect("PE file's COFF optional header does not contain the optional Windows header");
+                            let exception_entry = optional_win_header.known_data_directory_entry(binms::pe::KnownDataDirectoryEntry::ExceptionTable)
+                                .expect("PE file does not have an exception directory entry");
+                            let exception_data = binms::pe::ExceptionData::read(&mut input_file, pe.machine_type, &exception_entry, &pe.section_table)
+                                .expect("failed to read exception directory");
+
+                            if args.json_output {
+                                args.json_format.write(DocumentKind::PeUnwind, &exception_data);
+                            } else {
+                                for function in &exception_data.runtime_functions {
+                                    print!("{:#010X}-{:#010X}", function.begin_address, function.end_address);
+                                    match &function.unwind {
+                                        binms::pe::UnwindData::X64 { unwind_info_address, unwind_info } => {
+                                            println!("  unwind info at {:#010X}", unwind_info_address);
+                                            let Some(unwind_info) = unwind_info else {
+                                                println!("  (failed to read unwind info)");
+                                                continue;
+                                            };
+                                            println!(
+                                                "  version {}, flags {:?}, prolog size {:#X}",
+                                                unwind_info.version, unwind_info.flags, unwind_info.prolog_size,
+                                            );
+                                            if unwind_info.frame_register != 0 {
+                                                println!(
+                                                    "  frame register {} at offset {:#X}",
+                                                    binms::pe::x64_register_name(unwind_info.frame_register),
+                                                    u32::from(unwind_info.frame_offset) * 16,
+                                                );
+                                            }
+                                            for code in &unwind_info.unwind_codes {
+                                                println!("    {:#04X}: {}", code.prolog_offset, x64_unwind_operation_to_string(&code.operation));
+                                            }
+                                            if let Some(handler_address) = unwind_info.handler_address {
+                                                println!("  handler at {:#010X}", handler_address);
+                                            }
+                                            if let Some((begin, end, _unwind)) = unwind_info.chained_function {
+                                                println!("  chained to {:#010X}-{:#010X}", begin, end);
+                                            }
+                                        },
+                                        binms::pe::UnwindData::Arm64 { unwind_info_address, unwind_info } => {
+                                            println!("  unwind info at {:#010X}", unwind_info_address);
+                                            let Some(unwind_info) = unwind_info else {
+                                                println!("  (failed to read unwind info)");
+                                                continue;
+                                            };
+                                            println!("  version {}, unwind codes {}", unwind_info.version, unwind_info.unwind_codes);
+                                            if let Some(index) = unwind_info.packed_epilog_index {
+                                                println!("    single epilog at code index {}", index);
+                                            }
+                                            for scope in &unwind_info.epilog_scopes {
+                                                println!("    epilog at {:#X}, code index {}", scope.start_offset, scope.start_index);
+                                            }
+                                            if let Some(handler_address) = unwind_info.handler_address {
+                                                println!("  handler at {:#010X}", handler_address);
+                                            }
+                                        },
+                                        binms::pe::UnwindData::Arm64Packed(packed) => {
+                                            println!(
+                                                "  packed: flag {}, frame size {:#X}, int regs {}, fp regs {}, homes params {}, CR {}",
+                                                packed.flag, packed.frame_size, packed.saved_int_registers,
+                                                packed.saved_fp_registers, packed.homes_parameters, packed.cr,
+                                            );
+                                        },
+                                    }
+                                }
+                            }
+                        },
+                        PokeExeMode::PeBoundImports(args) => {
+                            let mut input_file = File::open(&args.input_file)
+                                .expect("failed to open input file");
+                            let pe = binms::pe::Executable::read(&mut input_file)
+                                .expect("failed to read PE header");
+                            let optional_header = pe.optional_header.as_ref()
+                                .expect("PE file is missing optional header");
+                            let binms::pe::OptionalHeader::Coff(cough) = optional_header
+                                else { panic!("PE file's optional header is not COFF") };
+                            let optional_win_header = cough.optional_windows_header.as_ref()
+                                .expect("PE file's COFF optional header does not contain the optional Windows header");
+
+                            let import_descriptors = match optional_win_header.known_data_directory_entry(binms::pe::KnownDataDirectoryEntry::ImportTable) {
+                                Some(entry) if entry.address != 0 => binms::pe::ImportDescriptor::read_all(&mut input_file, &entry, &pe.section_table)
+                                    .expect("failed to read import directory"),
+                                _ => Vec::new(),
+                            };
+                            let bound_import_descriptors = match optional_win_header.known_data_directory_entry(binms::pe::KnownDataDirectoryEntry::BoundImport) {
+                                Some(entry) if entry.address != 0 => binms::pe::BoundImportDescriptor::read_all(&mut input_file, &entry, &pe.section_table)
+                                    .expect("failed to read bound import directory"),
+                                _ => Vec::new(),
+                            };
+
+                            if args.json_output {
+                                let json = serde_json::json!({
+                                    "import_descriptors": import_descriptors,
+                                    "bound_import_descriptors": bound_import_descriptors,
+                                });
+                                args.json_format.write(DocumentKind::PeBoundImports, &json);
+                            } else {
+                                println!("imports:");
+                                for descriptor in &import_descriptors {
+                                    match descriptor.time_date_stamp {
+                                        0 => println!("  {}\tunbound", descriptor.name),
+                                        binms::pe::NEW_STYLE_BINDING_TIMESTAMP => println!("  {}\tbound (new-style)", descriptor.name),
+                                        timestamp => println!("  {}\tbound to {:#010X} (old-style)", descriptor.name, timestamp),
+                                    }
+                                }
+                                println!("bound import directory:");
+                                for descriptor in &bound_import_descriptors {
+                                    println!("  {}\t{:#010X}", descriptor.module_name, descriptor.time_date_stamp);
+                                    for forwarder_ref in &descriptor.forwarder_refs {
+                                        println!("    forwards to {}\t{:#010X}", forwarder_ref.module_name, forwarder_ref.time_date_stamp);
+                                    }
+                                }
+                            }
+                        },
+                        PokeExeMode::PeFingerprint(args) => {
+                            let mut input_file = File::open(&args.input_file)
+                                .expect("failed to open input file");
+                            let traits = binms::toolchain::BuildTraits::read(&mut input_file)
+                                .expect("failed to read PE file");
+                            let fingerprint = binms::toolchain::fingerprint(&traits);
+
+                            if args.json_output {
+                                let json = serde_json::json!({
+                                    "toolchain": fingerprint.toolchain.to_string(),
+                                    "evidence": fingerprint.evidence,
+                                    "traits": traits,
+                                });
+                                args.json_format.write(DocumentKind::PeFingerprint, &json);
+                            } else {
+                                println!("{}", fingerprint.toolchain);
+                                for evidence in &fingerprint.evidence {
+                                    println!("  {}", evidence);
+                                }
+                                if let Some(rich_entries) = traits.rich_entries.as_ref() {
+                                    println!("Rich header:");
+                                    for entry in rich_entries {
+                                        println!("  product {:#06X}\tbuild {}\tcount {}", entry.product_id, entry.build, entry.count);
                                     }
                                 }
                             }
@@ -925,13 +2424,118 @@ fn main() {
 
                             if args.container {
                                 if args.json_output {
-                                    println!("{}", serde_json::to_string_pretty(&dbg_file).expect("failed to JSONify"));
+                                    args.json_format.write(DocumentKind::DbgContainer, &dbg_file);
                                 } else {
                                     println!("{:#?}", dbg_file);
                                 }
                                 return;
                             }
 
+                            if args.functions {
+                                let mut read_debug_data = |kind| {
+                                    let entry = dbg_file.debug_directories.iter()
+                                        .find(|entry| entry.kind == kind)?;
+                                    input_file.seek(SeekFrom::Start(entry.raw_data_pointer.into()))
+                                        .expect("failed to seek to debug data");
+                                    let mut buf = vec![0u8; entry.size.try_into().unwrap()];
+                                    input_file.read_exact(&mut buf)
+                                        .expect("failed to read debug data");
+                                    Some(buf)
+                                };
+
+                                let mut function_map = binms::function_map::FunctionMap::new();
+                                if let Some(cv_buf) = read_debug_data(binms::nt4dbg::DebugType::CodeView) {
+                                    let code_view = binms::code_view::DebugInfo::read(&mut Cursor::new(&cv_buf))
+                                        .expect("failed to read CodeView debug info");
+                                    let segment_layout = code_view.segment_layout_for_pe(&dbg_file.section_table);
+                                    function_map.add_code_view_symbols(code_view.symbols(), &segment_layout);
+                                }
+                                if let Some(coff_buf) = read_debug_data(binms::nt4dbg::DebugType::Coff) {
+                                    let coff_debug_info = binms::coff::CoffDebugInfo::read(&mut Cursor::new(&coff_buf))
+                                        .expect("failed to read COFF debug info");
+                                    function_map.add_coff_symbols(&coff_debug_info.symbols, &dbg_file.section_table);
+                                }
+
+                                let functions: Vec<&binms::function_map::MappedFunction> = function_map.functions().collect();
+                                if args.json_output {
+                                    args.json_format.write(DocumentKind::DbgFunctions, &functions);
+                                    return;
+                                }
+                                println!("{:>10}  {:>10}  {:<22}  name", "begin", "end", "sources");
+                                for function in functions {
+                                    let end = function.end_address
+                                        .map(|end| format!("{:#010X}", end))
+                                        .unwrap_or_default();
+                                    println!(
+                                        "{:#010X}  {:>10}  {:<22}  {}",
+                                        function.begin_address,
+                                        end,
+                                        format!("{:?}", function.sources),
+                                        function.name.as_deref().unwrap_or(""),
+                                    );
+                                }
+                                return;
+                            }
+
+                            if args.coff {
+                                let coff_info = dbg_file.debug_directories
+                                    .iter()
+                                    .find(|entry| entry.kind == binms::nt4dbg::DebugType::Coff)
+                                    .expect(".dbg file contains no COFF section");
+
+                                let coff_size: usize = coff_info.size.try_into().unwrap();
+                                input_file.seek(SeekFrom::Start(coff_info.raw_data_pointer.into()))
+                                    .expect("failed to seek to COFF data");
+                                let mut coff_buf = vec![0u8; coff_size];
+                                input_file.read_exact(&mut coff_buf)
+                                    .expect("failed to read COFF data");
+
+                                let coff_debug_info = binms::coff::CoffDebugInfo::read(&mut Cursor::new(&coff_buf))
+                                    .expect("failed to read COFF debug info");
+                                if args.json_output {
+                                    args.json_format.write(DocumentKind::CoffDebugInfo, &coff_debug_info);
+                                    return;
+                                }
+
+                                let symbol_name = |symbol: &binms::coff::CoffSymbol| {
+                                    let name_bytes: &[u8] = symbol.name.as_ref();
+                                    String::from_utf8_lossy(name_bytes).into_owned()
+                                };
+
+                                println!("{:>6}  {:>10}  {:>7}  {:>6}  {:<18}  name", "index", "value", "section", "type", "storage class");
+                                for symbol in &coff_debug_info.symbols {
+                                    println!(
+                                        "{:>6}  {:>#10X}  {:>7}  {:>#6X}  {:<18}  {}",
+                                        symbol.index,
+                                        symbol.value,
+                                        symbol.section_number,
+                                        symbol.symbol_type,
+                                        format!("{:?}", symbol.storage_class),
+                                        symbol_name(symbol),
+                                    );
+                                }
+
+                                if !coff_debug_info.line_numbers.is_empty() {
+                                    println!();
+                                    println!("line numbers:");
+                                }
+                                for line_number in &coff_debug_info.line_numbers {
+                                    match line_number {
+                                        binms::coff::CoffLineNumber::FunctionStart { symbol_index } => {
+                                            let name = coff_debug_info.symbols.iter()
+                                                .find(|s| s.index == *symbol_index)
+                                                .map(symbol_name)
+                                                .unwrap_or_else(|| format!("symbol {}", symbol_index));
+                                            println!("  {}", name);
+                                        },
+                                        binms::coff::CoffLineNumber::Line { virtual_address, line_number } => {
+                                            println!("    {:>#10X}  line +{}", virtual_address, line_number);
+                                        },
+                                    }
+                                }
+                                return;
+                            }
+
                             let code_view_info = dbg_file.debug_directories
                                 .iter()
                                 .filter(|entry| entry.kind == binms::nt4dbg::DebugType::CodeView)
@@ -949,59 +2553,107 @@ fn main() {
                             let cv_header = binms::code_view::DebugInfo::read(&mut cv_reader)
                                 .expect("failed to read CodeView debug info");
                             if args.json_output {
-                                println!("{}", serde_json::to_string_pretty(&cv_header).expect("failed to JSONify"));
+                                args.json_format.write(DocumentKind::CodeViewDebugInfo, &cv_header);
                             } else {
                                 println!("{:#?}", cv_header);
                             }
                         },
                         PokeExeMode::ClrResources(args) => {
-                            let mut input_file = File::open(&args.input_file)
-                                .expect("failed to open input file");
-                            let pe = binms::pe::Executable::read(&mut input_file)
+                            let data = std::fs::read(&args.input_file)
+                                .expect("failed to read input file");
+                            let pe = binms::pe::Executable::read(&mut Cursor::new(&data))
                                 .expect("failed to read PE header");
-                            let optional_header = pe.optional_header.as_ref()
-                                .expect("PE file is missing optional header");
-                            let binms::pe::OptionalHeader::Coff(cough) = optional_header
-                                else { panic!("PE file's optional header is not COFF") };
-                            let optional_win_header = cough.optional_windows_header.as_ref()
-                                .expect("PE file's COFF optional header does not contain the optional Windows header");
-                            let clr_entry = optional_win_header.known_data_directory_entry(binms::pe::KnownDataDirectoryEntry::ClrRuntimeHeader)
-                                .expect("PE file does not have a CLR header directory entry");
-
-                            let clr_header_file_offset = pe.section_table.virtual_to_raw(clr_entry.address)
-                                .expect("failed to map CLR header virtual to raw address");
-                            input_file.seek(SeekFrom::Start(clr_header_file_offset.into()))
-                                .expect("failed to seek to CLR header");
-                            let mut buf = vec![0u8; clr_entry.size.try_into().unwrap()];
-                            input_file.read_exact(&mut buf)
-                                .expect("failed to read CLR header");
-                            let (_, clr_header) = binms::clr::header::ClrHeader::take_from_bytes(&buf)
-                                .expect("failed to decode CLR header");
-
-                            let clr_res_file_offset = pe.section_table.virtual_to_raw(clr_header.resources_range.address)
-                                .expect("failed to map CLR resources virtual to raw address");
-                            input_file.seek(SeekFrom::Start(clr_res_file_offset.into()))
-                                .expect("failed to seek to CLR resources");
-                            let mut buf = vec![0u8; clr_header.resources_range.length.try_into().unwrap()];
-                            input_file.read_exact(&mut buf)
-                                .expect("failed to read CLR resources");
+                            let clr_header = read_clr_header(&data, &pe);
+                            let (_, metadata) = binms::clr::metadata::ClrMetadata::take_from_bytes(pe_virtual_slice(&data, &pe, clr_header.metadata_range.address, clr_header.metadata_range.length))
+                                .expect("failed to decode CLR metadata");
+                            let manifest_resources = metadata.manifest_resources()
+                                .expect("failed to read manifest resources");
+                            let resources_bytes = if clr_header.resources_range.length > 0 {
+                                pe_virtual_slice(&data, &pe, clr_header.resources_range.address, clr_header.resources_range.length)
+                            } else {
+                                &[]
+                            };
+
+                            if let Some(extract_dir) = args.extract_dir.as_ref() {
+                                std::fs::create_dir_all(extract_dir)
+                                    .expect("failed to create extraction directory");
+                            }
 
                             let mut resource_containers = Vec::new();
-                            let resources = binms::clr::resources::collect_wrapped_resource_containers(&buf);
-                            for res in resources.iter() {
-                                if res.len() == 0 {
-                                    // probably just padding
-                                    continue;
-                                }
-                                let (_, resource_container) = binms::clr::resources::ClrResourceContainer::take_from_bytes(res)
-                                    .expect("failed to decode resource container");
-                                if args.cbor_output.is_some() {
-                                    resource_containers.push(resource_container);
+                            let mut json_resources = Vec::new();
+                            for manifest_resource in &manifest_resources {
+                                let location = match manifest_resource.implementation {
+                                    None => "embedded".to_owned(),
+                                    Some(binms::clr::metadata::TableRow { table, row }) => format!("{:?} {}", table, row),
+                                };
+                                let resource_data = if manifest_resource.implementation.is_none() {
+                                    binms::clr::resources::wrapped_resource_at(resources_bytes, manifest_resource.offset)
                                 } else {
-                                    println!("{:#?}", resource_container);
+                                    None
+                                };
+
+                                let container = resource_data
+                                    .filter(|rd| rd.starts_with(&0xBEEFCACEu32.to_le_bytes()))
+                                    .map(|rd| binms::clr::resources::ClrResourceContainer::take_from_bytes(rd)
+                                        .expect("failed to decode resource container").1);
+                                let mut entries = Vec::new();
+                                if let Some(container) = container.as_ref() {
+                                    for resource in &container.resources {
+                                        let value = resource.decode_value(&container.custom_resource_types)
+                                            .expect("failed to decode resource value");
+                                        entries.push((resource.name.clone(), value));
+                                    }
+                                }
+
+                                if let (Some(extract_dir), Some(resource_data)) = (args.extract_dir.as_ref(), resource_data) {
+                                    let resource_path = extract_dir.join(file_name_for_extraction(&manifest_resource.name));
+                                    if container.is_some() {
+                                        std::fs::create_dir_all(&resource_path)
+                                            .expect("failed to create resource directory");
+                                        for (name, value) in &entries {
+                                            let Some(file_data) = value.as_file_data() else { continue };
+                                            std::fs::write(resource_path.join(file_name_for_extraction(name)), file_data)
+                                                .expect("failed to write resource entry");
+                                        }
+                                    } else {
+                                        std::fs::write(&resource_path, resource_data)
+                                            .expect("failed to write resource");
+                                    }
+                                }
+
+                                if args.json_output {
+                                    let json_entries: Vec<serde_json::Value> = entries.iter()
+                                        .map(|(name, value)| serde_json::json!({
+                                            "name": name,
+                                            "value": value,
+                                        }))
+                                        .collect();
+                                    json_resources.push(serde_json::json!({
+                                        "name": manifest_resource.name,
+                                        "public": manifest_resource.flags.contains(binms::clr::metadata::ManifestResourceFlags::PUBLIC),
+                                        "location": location,
+                                        "size": resource_data.map(|rd| rd.len()),
+                                        "entries": json_entries,
+                                    }));
+                                } else if args.cbor_output.is_none() {
+                                    let size_string = resource_data
+                                        .map(|rd| rd.len().to_string())
+                                        .unwrap_or_default();
+                                    println!("{}\t{}\t{}\t{:?}", escape_str(&manifest_resource.name), size_string, location, manifest_resource.flags);
+                                    for (name, value) in &entries {
+                                        println!("  {}\t{:?}", escape_str(name), value);
+                                    }
+                                }
+
+                                if let Some(container) = container {
+                                    resource_containers.push(container);
                                 }
                             }
 
+                            if args.json_output {
+                                args.json_format.write(DocumentKind::ClrResources, &json_resources);
+                            }
+
                             if let Some(cbor_path) = args.cbor_output.as_ref() {
                                 let mut cbor_file = File::create(cbor_path)
                                     .expect("failed to open output CBOR file");
@@ -1011,6 +2663,49 @@ fn main() {
                                     .expect("failed to flush output CBOR file");
                             }
                         },
+                        PokeExeMode::Winmd(args) => {
+                            let data = std::fs::read(&args.input_file)
+                                .expect("failed to read input file");
+                            let pe = binms::pe::Executable::read(&mut Cursor::new(&data))
+                                .expect("failed to read PE header");
+                            let clr_header = read_clr_header(&data, &pe);
+                            let (_, metadata) = binms::clr::metadata::ClrMetadata::take_from_bytes(pe_virtual_slice(&data, &pe, clr_header.metadata_range.address, clr_header.metadata_range.length))
+                                .expect("failed to decode CLR metadata");
+                            if !binms::clr::winmd::is_winmd(&metadata) {
+                                warn!("metadata version is {:?}; this does not seem to be a Windows Runtime metadata file", metadata.version);
+                            }
+                            let mut types = binms::clr::winmd::collect_types(&metadata)
+                                .expect("failed to collect types");
+                            types.sort_unstable_by(|a, b| a.namespace.cmp(&b.namespace).then_with(|| a.name.cmp(&b.name)));
+
+                            if args.json_output {
+                                let json_types: Vec<serde_json::Value> = types.iter()
+                                    .map(|t| serde_json::json!({
+                                        "namespace": t.namespace,
+                                        "name": t.name,
+                                        "kind": format!("{:?}", t.kind),
+                                        "guid": t.guid.map(|g| g.to_string()),
+                                        "methods": t.methods,
+                                    }))
+                                    .collect();
+                                args.json_format.write(DocumentKind::Winmd, &json_types);
+                            } else {
+                                let mut current_namespace = None;
+                                for t in &types {
+                                    if current_namespace != Some(&t.namespace) {
+                                        println!("namespace {}", t.namespace);
+                                        current_namespace = Some(&t.namespace);
+                                    }
+                                    match t.guid {
+                                        Some(guid) => println!("  {:?} {} {}", t.kind, t.name, guid),
+                                        None => println!("  {:?} {}", t.kind, t.name),
+                                    }
+                                    for method in &t.methods {
+                                        println!("    {}", method);
+                                    }
+                                }
+                            }
+                        },
                     }
                 },
                 PokeMode::Cd(poke_cd_mode) => {
@@ -1018,20 +2713,201 @@ fn main() {
                         PokeCdMode::Vol(args) => {
                             let mut input_file = File::open(&args.input_file)
                                 .expect("failed to open input file");
-                            input_file.seek(SeekFrom::Start(0x8000))
+                            input_file.seek(SeekFrom::Start(crate::formats::cd_descriptor_offset(args.number.unwrap_or(0))))
                                 .expect("failed to seek to volume descriptor");
-                            let vd = VolumeDescriptor::read(&mut input_file, args.high_sierra)
+                            let mut sector = vec![0u8; 2048];
+                            input_file.read_exact(&mut sector)
                                 .expect("failed to read volume descriptor");
-                            println!("{:#?}", vd);
+                            let (vd_type, _version) = DescriptorType::of_descriptor(&sector, args.high_sierra)
+                                .expect("no volume descriptor found");
+                            match vd_type {
+                                DescriptorType::BootRecord => {
+                                    let br = BootRecord::read(&mut sector.as_slice(), args.high_sierra)
+                                        .expect("failed to read boot record");
+                                    println!("{:#?}", br);
+                                },
+                                DescriptorType::VolumePartitionDescriptor => {
+                                    let pd = PartitionDescriptor::read(&mut sector.as_slice(), args.high_sierra)
+                                        .expect("failed to read partition descriptor");
+                                    println!("{:#?}", pd);
+                                },
+                                _ => {
+                                    let vd = VolumeDescriptor::read(&mut sector.as_slice(), args.high_sierra)
+                                        .expect("failed to read volume descriptor");
+                                    println!("{:#?}", vd);
+                                },
+                            }
+                        },
+                        PokeCdMode::Vols(args) => {
+                            let mut input_file = File::open(&args.input_file)
+                                .expect("failed to open input file");
+
+                            let descriptors = crate::formats::read_cd_volume_descriptors(&mut input_file, args.high_sierra)
+                                .expect("failed to read volume descriptors");
+                            if args.json_output {
+                                args.json_format.write(DocumentKind::CdVolumeDescriptors, &descriptors);
+                            } else {
+                                println!("{:>3}  {:>10}  {:<13}  {:>3}  {:<32}  {:<32}  {:>10}", "#", "offset", "kind", "ver", "system", "identifier", "blocks");
+                                for descriptor in &descriptors {
+                                    println!(
+                                        "{:>3}  {:>#10X}  {:<13}  {:>3}  {:<32}  {:<32}  {:>10}",
+                                        descriptor.number,
+                                        descriptor.offset,
+                                        descriptor.kind.name(),
+                                        descriptor.version,
+                                        descriptor.system_identifier.as_deref().unwrap_or(""),
+                                        descriptor.identifier.as_deref().unwrap_or(""),
+                                        descriptor.block_count.map(|c| c.to_string()).unwrap_or_default(),
+                                    );
+                                }
+                            }
+                        },
+                    }
+                },
+                PokeMode::Reg(poke_reg_mode) => {
+                    match poke_reg_mode {
+                        PokeRegMode::Keys(args) => {
+                            let mut input_file = File::open(&args.input_file)
+                                .expect("failed to open input file");
+                            let hive = Hive::read(&mut input_file)
+                                .expect("failed to read registry hive");
+                            let key = hive.key_by_path(&args.path)
+                                .expect("failed to look up key")
+                                .expect("key not found");
+
+                            let mut subkeys = Vec::new();
+                            collect_reg_subkeys(&hive, &key, &args.path, args.recursive, 0, &mut subkeys);
+
+                            if args.json_output {
+                                let json_keys: Vec<serde_json::Value> = subkeys.iter()
+                                    .map(|(path, subkey)| serde_json::json!({
+                                        "path": path,
+                                        "key": subkey,
+                                    }))
+                                    .collect();
+                                args.json_format.write(DocumentKind::RegistryKeys, &json_keys);
+                            } else {
+                                for (path, subkey) in &subkeys {
+                                    println!("{}\t{} subkeys\t{} values", path, subkey.subkey_count, subkey.value_count);
+                                }
+                            }
+                        },
+                        PokeRegMode::Values(args) => {
+                            let mut input_file = File::open(&args.input_file)
+                                .expect("failed to open input file");
+                            let hive = Hive::read(&mut input_file)
+                                .expect("failed to read registry hive");
+                            let key = hive.key_by_path(&args.path)
+                                .expect("failed to look up key")
+                                .expect("key not found");
+
+                            let mut keys = vec![(args.path.clone(), key.clone())];
+                            if args.recursive {
+                                collect_reg_subkeys(&hive, &key, &args.path, true, 0, &mut keys);
+                            }
+
+                            let mut json_keys = Vec::new();
+                            for (path, key) in &keys {
+                                let values = match hive.values(key) {
+                                    Ok(v) => v,
+                                    Err(e) => {
+                                        error!("failed to read values of {:?}: {}", path, e);
+                                        continue;
+                                    },
+                                };
+                                if !args.json_output {
+                                    println!("[{}]", path);
+                                }
+                                let mut json_values = Vec::new();
+                                for value in &values {
+                                    let data = match hive.decoded_value_data(value) {
+                                        Ok(d) => d,
+                                        Err(e) => {
+                                            error!("failed to read data of value {:?} of {:?}: {}", value.name, path, e);
+                                            continue;
+                                        },
+                                    };
+                                    if args.json_output {
+                                        json_values.push(serde_json::json!({
+                                            "name": value.name,
+                                            "type": value.data_type,
+                                            "data": data,
+                                        }));
+                                    } else {
+                                        let name = if value.name.is_empty() { "@" } else { value.name.as_str() };
+                                        println!("{}\t{:?}\t{:?}", name, value.data_type, data);
+                                    }
+                                }
+                                if args.json_output {
+                                    json_keys.push(serde_json::json!({
+                                        "path": path,
+                                        "values": json_values,
+                                    }));
+                                } else {
+                                    println!();
+                                }
+                            }
+                            if args.json_output {
+                                args.json_format.write(DocumentKind::RegistryValues, &json_keys);
+                            }
+                        },
+                        PokeRegMode::ComServers(args) => {
+                            let mut input_file = File::open(&args.input_file)
+                                .expect("failed to open input file");
+                            let hive = Hive::read(&mut input_file)
+                                .expect("failed to read registry hive");
+
+                            let servers = hive.com_servers()
+                                .expect("failed to read COM classes");
+                            let mut json_servers = Vec::new();
+                            for server in servers {
+                                if args.json_output {
+                                    json_servers.push(serde_json::json!({
+                                        "clsid": server.clsid,
+                                        "class_name": server.class_name,
+                                        "server_kind": server.server_kind,
+                                        "server_path": server.server_path,
+                                    }));
+                                } else {
+                                    println!(
+                                        "{}\t{}\t{}\t{}",
+                                        server.clsid, server.server_kind, server.server_path,
+                                        server.class_name.as_deref().unwrap_or(""),
+                                    );
+                                }
+                            }
+                            if args.json_output {
+                                args.json_format.write(DocumentKind::ComServers, &json_servers);
+                            }
                         },
                     }
                 },
                 PokeMode::Inflate(args) => {
-                    let mut input_file = File::open(&args.input_file)
+                    let mut input_file = crate::input::open(&args.input_file)
                         .expect("failed to open input file");
                     let mut inflater = Inflater::new(&mut input_file, MAX_LOOKBACK_DISTANCE);
                     let mut output = Vec::new();
-                    let mut output_file = File::create(&args.output_file)
+
+                    if args.check {
+                        let mut sink = expandms::CountingWriter::new(std::io::sink());
+                        let result = loop {
+                            output.clear();
+                            match inflater.inflate_block(&mut output) {
+                                Ok(last_block) => {
+                                    sink.write_all(&output)
+                                        .expect("failed to write to sink");
+                                    if last_block {
+                                        break Ok(());
+                                    }
+                                },
+                                Err(e) => break Err(e),
+                            }
+                        };
+                        report_check(&args.input_file, sink.count(), result);
+                        return;
+                    }
+
+                    let mut output_file = File::create(args.output_file.as_ref().unwrap())
                         .expect("failed to create output file");
                     loop {
                         output.clear();
@@ -1047,11 +2923,189 @@ fn main() {
             }
         },
         ProgMode::Interpret(args) => {
-            let input_bytes = std::fs::read(&args.input_file)
-                .expect("failed to read input file");
+            let input_bytes = crate::input::read(&args.input_file)
+                .unwrap_or_else(|e| fail(ExitStatus::of_io_error(&e), "failed to read input file", e));
             let interpreted = crate::formats::interpret_file(&input_bytes)
-                .expect("failed to interpret input file");
+                .unwrap_or_else(|e| fail(ExitStatus::of_error(&e), "failed to interpret input file", e));
             println!("{:#?}", interpreted);
+            if matches!(interpreted, IdentifiedFile::Unidentified) {
+                ExitStatus::UnidentifiedFormat.exit();
+            }
+        },
+        ProgMode::Info(args) => {
+            let input_bytes = std::fs::read(&args.input_file)
+                .unwrap_or_else(|e| fail(ExitStatus::of_io_error(&e), "failed to read input file", e));
+            let info = crate::info::summarize(&input_bytes)
+                .unwrap_or_else(|e| fail(ExitStatus::of_error(&e), "failed to interpret input file", e));
+            if args.json_output {
+                args.json_format.write(DocumentKind::Info, &info);
+            } else {
+                print!("{}", info);
+            }
+        },
+        ProgMode::Extract(args) => {
+            let input_bytes = std::fs::read(&args.container)
+                .unwrap_or_else(|e| fail(ExitStatus::of_io_error(&e), "failed to read container", e));
+            let container_name = args.container.file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "extracted".to_owned());
+            let single_file_name = PathBuf::from(crate::extract::single_file_name(&container_name, &input_bytes));
+            let siblings = crate::volumes::DirectorySiblings::of_file(&args.container);
+            let container = crate::formats::interpret_container_volume(input_bytes, &container_name, &siblings)
+                .unwrap_or_else(|e| fail(ExitStatus::of_error(&e), "failed to interpret container", e));
+
+            let path_mode = if args.flatten {
+                crate::extract::PathMode::Flatten
+            } else {
+                crate::extract::PathMode::Preserve
+            };
+            let count = crate::extract::extract(&container, &single_file_name, &args.paths, &args.output_dir, path_mode)
+                .unwrap_or_else(|e| fail(ExitStatus::of_error(&e), "failed to extract files", e));
+            info!("extracted {} files", count);
+        },
+        ProgMode::Ls(args) => {
+            let input_bytes = std::fs::read(&args.container)
+                .unwrap_or_else(|e| fail(ExitStatus::of_io_error(&e), "failed to read container", e));
+            let container_name = args.container.file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "extracted".to_owned());
+            let single_file_name = PathBuf::from(crate::extract::single_file_name(&container_name, &input_bytes));
+            let siblings = crate::volumes::DirectorySiblings::of_file(&args.container);
+            let container = crate::formats::interpret_container_volume(input_bytes, &container_name, &siblings)
+                .unwrap_or_else(|e| fail(ExitStatus::of_error(&e), "failed to interpret container", e));
+
+            let listed = crate::extract::list(&container, &single_file_name, args.depth, args.digests)
+                .unwrap_or_else(|e| fail(ExitStatus::of_error(&e), "failed to list files", e));
+            if args.json_output {
+                args.json_format.write(DocumentKind::Listing, &listed);
+            } else {
+                output_listed_files(&listed, 0);
+            }
+        },
+        ProgMode::CmpContainers(args) => {
+            let mut hashes = Vec::with_capacity(2);
+            for container_path in [&args.old_container, &args.new_container] {
+                let input_bytes = std::fs::read(container_path)
+                    .unwrap_or_else(|e| fail(ExitStatus::of_io_error(&e), "failed to read container", e));
+                let container_name = container_path.file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "extracted".to_owned());
+                let single_file_name = PathBuf::from(crate::extract::single_file_name(&container_name, &input_bytes));
+                let siblings = crate::volumes::DirectorySiblings::of_file(container_path);
+                let container = crate::formats::interpret_container_volume(input_bytes, &container_name, &siblings)
+                    .unwrap_or_else(|e| fail(ExitStatus::of_error(&e), "failed to interpret container", e));
+                let path_to_digest = crate::compare::hash_files(&container, &single_file_name, args.depth, args.time_limit.map(Duration::from_secs))
+                    .unwrap_or_else(|e| fail(ExitStatus::of_error(&e), "failed to hash files", e));
+                hashes.push(path_to_digest);
+            }
+
+            for difference in crate::compare::compare(&hashes[0], &hashes[1]) {
+                let path_strings: Vec<String> = difference.path.iter()
+                    .map(|p| p.display().to_string())
+                    .collect();
+                println!("{}\t{}", difference.change, path_strings.join("|"));
+            }
+        },
+        ProgMode::CmpExports(args) => {
+            let mut exports = Vec::with_capacity(2);
+            for (path, mapped) in [(&args.old_file, args.old_mapped), (&args.new_file, args.new_mapped)] {
+                let input_bytes = std::fs::read(path)
+                    .unwrap_or_else(|e| fail(ExitStatus::of_io_error(&e), "failed to read input file", e));
+                let file_exports = crate::compare_exports::read_exports(&input_bytes, mapped, args.entry_bytes)
+                    .unwrap_or_else(|e| fail(ExitStatus::of_io_error(&e), "failed to read exports", e));
+                exports.push(file_exports);
+            }
+
+            for difference in crate::compare_exports::compare_exports(&exports[0], &exports[1]) {
+                println!("{}", difference);
+            }
+        },
+        ProgMode::ImportJson(args) => {
+            let input_file = File::open(&args.input_file)
+                .unwrap_or_else(|e| fail(ExitStatus::of_io_error(&e), "failed to open input file", e));
+            let document: crate::schema::ImportedDocument = serde_json::from_reader(BufReader::new(input_file))
+                .unwrap_or_else(|e| fail(ExitStatus::ParseError, "failed to read JSON document", e));
+            if document.schema_version != crate::schema::SCHEMA_VERSION {
+                warn!("document has schema version {}, expected {}", document.schema_version, crate::schema::SCHEMA_VERSION);
+            }
+
+            let json_format = JsonFormatArgs {
+                output: args.output.clone(),
+                compact: args.compact,
+            };
+            let kind = document.kind;
+            let rewritten = match kind {
+                DocumentKind::PeUnwind
+                    => json_format.rewrite::<binms::pe::ExceptionData>(kind, document.data),
+                DocumentKind::DbgContainer
+                    => json_format.rewrite::<binms::nt4dbg::DbgFile>(kind, document.data),
+                DocumentKind::CoffDebugInfo
+                    => json_format.rewrite::<binms::coff::CoffDebugInfo>(kind, document.data),
+                DocumentKind::CodeViewDebugInfo
+                    => json_format.rewrite::<binms::code_view::DebugInfo>(kind, document.data),
+                other => fail(
+                    ExitStatus::UnidentifiedFormat,
+                    "cannot import document",
+                    format!("documents of kind {:?} are assembled for output only", other),
+                ),
+            };
+            rewritten.unwrap_or_else(|e| fail(ExitStatus::ParseError, "failed to import document data", e));
+        },
+        ProgMode::Strings(args) => {
+            let input_bytes = std::fs::read(&args.input_file)
+                .unwrap_or_else(|e| fail(ExitStatus::of_io_error(&e), "failed to read input file", e));
+            let strings = crate::strings::find_located_strings(&input_bytes, args.min_length, args.encoding.encodings());
+            if args.json_output {
+                args.json_format.write(DocumentKind::Strings, &strings);
+            } else {
+                for string in &strings {
+                    println!("{}", string);
+                }
+            }
+        },
+        ProgMode::VerifyFixtures(args) => {
+            let report = crate::verify::verify_corpus(&args.corpus, args.time_limit.map(Duration::from_secs));
+            if args.json_output {
+                args.json_format.write(DocumentKind::VerifyReport, &report);
+            } else {
+                print!("{}", report);
+                if args.failures {
+                    for failure in &report.failures {
+                        println!("{}\t{}\t{}\t{}", failure.path.display(), failure.parser, failure.category, failure.message);
+                    }
+                }
+            }
+            if report.has_panics() {
+                ExitStatus::PartialWithErrors.exit();
+            }
+        },
+        ProgMode::Lint(args) => {
+            let input_bytes = std::fs::read(&args.input_file)
+                .unwrap_or_else(|e| fail(ExitStatus::of_io_error(&e), "failed to read input file", e));
+            let mut diagnostics = crate::lint::lint_pe_exports(&input_bytes, &args.search_dir)
+                .unwrap_or_else(|e| fail(ExitStatus::of_io_error(&e), "failed to read export table", e));
+            if args.bound_imports {
+                let binding_diagnostics = crate::lint::lint_pe_bound_imports(&input_bytes, &args.search_dir)
+                    .unwrap_or_else(|e| fail(ExitStatus::of_io_error(&e), "failed to read imports", e));
+                diagnostics.extend(binding_diagnostics);
+            }
+            if args.import_hints {
+                let hint_diagnostics = crate::lint::lint_pe_import_hints(&input_bytes, &args.search_dir)
+                    .unwrap_or_else(|e| fail(ExitStatus::of_io_error(&e), "failed to read imports", e));
+                diagnostics.extend(hint_diagnostics);
+            }
+            if args.strict_resources {
+                let resource_diagnostics = crate::lint::lint_pe_resources(&input_bytes)
+                    .unwrap_or_else(|e| fail(ExitStatus::of_io_error(&e), "failed to read resources", e));
+                diagnostics.extend(resource_diagnostics);
+            }
+            if args.json_output {
+                args.json_format.write(DocumentKind::LintFindings, &diagnostics);
+            } else {
+                for diagnostic in &diagnostics {
+                    println!("{}", diagnostic);
+                }
+            }
         },
         ProgMode::Scan(args) => {
             // scan the file system recursively
@@ -1059,6 +3113,10 @@ fn main() {
             let top_path = args.dir.as_deref()
                 .unwrap_or(dot_path);
 
+            let mut summary = crate::scan::ScanSummary::new();
+            if args.errors_json.is_some() {
+                summary.error_records = Some(Vec::new());
+            }
             let mut file_list: Vec<PathBuf> = Vec::new();
             let mut dir_stack: Vec<PathBuf> = vec![top_path.to_owned()];
             while let Some(path) = dir_stack.pop() {
@@ -1066,6 +3124,7 @@ fn main() {
                     Ok(e) => e,
                     Err(e) => {
                         error!("failed to read directory {}: {}", path.display(), e);
+                        summary.record_error(crate::scan::ErrorCategory::ReadFromDisk, std::slice::from_ref(&path), e);
                         continue;
                     },
                 };
@@ -1075,6 +3134,7 @@ fn main() {
                         Ok(e) => e,
                         Err(e) => {
                             error!("failed to read directory entry from {}: {}", path.display(), e);
+                            summary.record_error(crate::scan::ErrorCategory::ReadFromDisk, std::slice::from_ref(&path), e);
                             continue;
                         },
                     };
@@ -1083,6 +3143,7 @@ fn main() {
                         Ok(e) => e,
                         Err(e) => {
                             error!("failed to read type of {}: {}", entry.path().display(), e);
+                            summary.record_error(crate::scan::ErrorCategory::ReadFromDisk, &[entry.path()], e);
                             continue;
                         },
                     };
@@ -1095,95 +3156,376 @@ fn main() {
                 }
             }
 
-            // run through the files
-            for file_path in file_list {
-                let file_data = match std::fs::read(&file_path) {
-                    Ok(fd) => fd,
-                    Err(e) => {
-                        error!("failed to read {}: {}", file_path.display(), e);
-                        continue;
-                    },
-                };
-                let path_sequence: PathSequence = vec![file_path].into();
-                scan_file(&path_sequence, &file_data);
-            }
-        },
-    }
-}
+            // keep the output stable across runs
+            file_list.sort_unstable();
 
+            let disk_sets = if args.disk_sets {
+                let disk_sets = crate::formats::find_disk_sets(&file_list);
+                file_list.retain(|p| !disk_sets.iter().any(|set| set.contains(p)));
+                info!("found {} sets of disk images", disk_sets.len());
+                disk_sets
+            } else {
+                Vec::new()
+            };
 
-fn scan_file(parent_path_sequence: &PathSequence, data: &[u8]) {
-    info!("interpreting {:?}", parent_path_sequence);
-    match interpret_file(data) {
-        Ok(IdentifiedFile::MultiFileContainer(mfc)) => {
-            // scan each child file
-            let files = match mfc.list_files() {
-                Ok(fs) => fs,
-                Err(e) => {
-                    error!("failed to list files of {:?}: {}", parent_path_sequence, e);
-                    return;
-                },
+            let mut scanner = crate::scan::Scanner::new();
+            scanner.summary = summary;
+            scanner.max_in_memory_size = args.max_in_memory_size;
+            scanner.demangle = args.demangle;
+            scanner.output_format = args.output_format;
+            scanner.max_depth = args.max_depth;
+            scanner.time_limit = args.time_limit.map(Duration::from_secs);
+            if let Some(api_docs_path) = args.api_docs.as_ref() {
+                let api_docs = crate::api_docs::ApiDocs::load(api_docs_path)
+                    .expect("failed to load API documentation mapping");
+                if api_docs.is_empty() {
+                    warn!("{} does not document any symbols", api_docs_path.display());
+                } else {
+                    info!("loaded documentation for {} symbols", api_docs.len());
+                }
+                scanner.api_docs = Some(api_docs);
+            }
+            if let Some(filter_script_path) = args.filter_script.as_ref() {
+                let filter = filtexp::ExportFilter::compile_file(filter_script_path)
+                    .unwrap_or_else(|e| fail(ExitStatus::ParseError, "failed to compile filter script", e));
+                scanner.filter = Some(filter);
+            }
+            if args.wow64_report {
+                scanner.wow64_pairs = Some(crate::scan::Wow64Pairs::new());
+            }
+            if args.kernel_namespace {
+                scanner.kernel_namespace = Some(crate::scan::KernelNamespace::new());
+            }
+            for plugin in &args.plugins {
+                scanner.plugins.register(Box::new(crate::plugin::ExternalPlugin::new(plugin.clone())));
+            }
+            if args.inf_names {
+                // collect the INF files first
+                let mut inf_names = crate::scan::InfNames::new();
+                for file_path in &file_list {
+                    let is_inf = file_path.extension()
+                        .and_then(|e| e.to_str())
+                        .is_some_and(|e| e.eq_ignore_ascii_case("inf"));
+                    if !is_inf {
+                        continue;
+                    }
+                    let file_data = match std::fs::read(file_path) {
+                        Ok(fd) => fd,
+                        Err(e) => {
+                            error!("failed to read {}: {}", file_path.display(), e);
+                            continue;
+                        },
+                    };
+                    if let Ok(IdentifiedFile::Metadata(metadata)) = crate::formats::interpret_file(&file_data) {
+                        let dir = file_path.parent().unwrap_or(dot_path);
+                        inf_names.register(dir, &metadata);
+                    }
+                }
+                info!("collected {} file names from INF files", inf_names.len());
+                scanner.inf_names = Some(inf_names);
+            }
+            scanner.cache = if let Some(cache_file) = args.cache_file.as_ref() {
+                if cache_file.exists() {
+                    let cache = crate::scan::ScanCache::load(cache_file)
+                        .expect("failed to load scan cache");
+                    info!("loaded {} entries from scan cache", cache.len());
+                    Some(cache)
+                } else {
+                    Some(crate::scan::ScanCache::new())
+                }
+            } else if args.cache {
+                Some(crate::scan::ScanCache::new())
+            } else {
+                None
             };
-            for file in files {
-                let mut child_path_sequence = parent_path_sequence.clone();
-                child_path_sequence.push(&file);
 
-                let file_data = match mfc.read_file(&file) {
+            // run through the files
+            scanner.output_header();
+            for file_path in file_list {
+                if scanner.is_symbol_store_duplicate(&file_path) {
+                    continue;
+                }
+                let file_data = match std::fs::read(&file_path) {
                     Ok(fd) => {
-                        if fd.len() < 24 {
-                            debug!("{}", DisplayBytesSlice::from(fd.as_slice()));
-                        } else {
-                            debug!("{}...{}", DisplayBytesSlice::from(&fd[..16]), DisplayBytesSlice::from(&fd[fd.len()-16..]));
-                        }
+                        scanner.remember_symbol_store_file(&file_path);
                         fd
                     },
                     Err(e) => {
-                        error!("failed to obtain {:?}: {}", child_path_sequence, e);
+                        error!("failed to read {}: {}", file_path.display(), e);
+                        scanner.summary.record_error(crate::scan::ErrorCategory::ReadFromDisk, std::slice::from_ref(&file_path), e);
                         continue;
                     },
                 };
-                scan_file(&child_path_sequence, &file_data);
+                let path_sequence: PathSequence = vec![file_path].into();
+                scanner.scan_file(&path_sequence, file_data);
             }
-        },
-        Ok(IdentifiedFile::SingleFileContainer(sfc)) => {
-            let mut child_path_sequence = parent_path_sequence.clone();
-            child_path_sequence.push(PathBuf::new());
-
-            let file_data = match sfc.read_file() {
-                Ok(fd) => fd,
-                Err(e) => {
-                    error!("failed to obtain {:?}: {}", child_path_sequence, e);
-                    return;
-                },
-            };
-            scan_file(&child_path_sequence, &file_data);
-        },
-        Ok(IdentifiedFile::SymbolExporter(symex)) => {
-            let symbols = match symex.read_symbols() {
-                Ok(s) => s,
-                Err(e) => {
-                    error!("failed to read symbols from {:?}: {}", parent_path_sequence, e);
-                    return;
-                },
-            };
-            let path_sequence: &[PathBuf] = parent_path_sequence.as_ref();
-            for symbol in symbols {
-                match symbol {
-                    Symbol::ByName { name }
-                        => println!("{:?}\t\t{}", &*path_sequence, escape_name(&name)),
-                    Symbol::ByOrdinal { ordinal }
-                        => println!("{:?}\t{}\t", &*path_sequence, ordinal),
-                    Symbol::ByNameAndOrdinal { name, ordinal }
-                        => println!("{:?}\t{}\t{}", &*path_sequence, ordinal, escape_name(&name)),
+            for image_paths in disk_sets {
+                let mut images = Vec::with_capacity(image_paths.len());
+                let mut read_image_paths = Vec::with_capacity(image_paths.len());
+                for image_path in &image_paths {
+                    match std::fs::read(image_path) {
+                        Ok(data) => {
+                            let name = image_path.file_name().unwrap().to_string_lossy().into_owned();
+                            images.push((name, data));
+                            read_image_paths.push(image_path);
+                        },
+                        Err(e) => {
+                            // the other disks may still be of use
+                            error!("failed to read {}: {}", image_path.display(), e);
+                            scanner.summary.record_error(crate::scan::ErrorCategory::ReadFromDisk, std::slice::from_ref(image_path), e);
+                        },
+                    }
                 }
+                let set_dir = image_paths[0].parent().unwrap_or(dot_path).to_owned();
+                let (disk_set, failed_images) = crate::formats::DiskSet::new(images);
+                for (index, e) in failed_images {
+                    // the other disks have still been read
+                    let image_path = read_image_paths[index];
+                    error!("failed to read the disk image {}: {}", image_path.display(), e);
+                    scanner.summary.record_error(crate::scan::ErrorCategory::Interpret, std::slice::from_ref(image_path), e);
+                }
+                let path_sequence: PathSequence = vec![set_dir].into();
+                scanner.scan_container(&path_sequence, &disk_set);
+            }
+            if let Some(wow64_pairs) = scanner.wow64_pairs.as_ref() {
+                wow64_pairs.output_report();
+            }
+            scanner.summary.output();
+            if let Some(summary_path) = args.summary.as_ref() {
+                let summary_json_format = JsonFormatArgs {
+                    output: Some(summary_path.clone()),
+                    compact: false,
+                };
+                summary_json_format.write(DocumentKind::ScanSummary, &scanner.summary);
+            }
+
+            if let Some(errors_json_path) = args.errors_json.as_ref() {
+                let error_records = scanner.summary.error_records.as_deref().unwrap();
+                let errors_json_format = JsonFormatArgs {
+                    output: Some(errors_json_path.clone()),
+                    compact: false,
+                };
+                errors_json_format.write(DocumentKind::ErrorRecords, error_records);
+            }
+
+            if let Some(cache_file) = args.cache_file.as_ref() {
+                let cache = scanner.cache.as_ref().unwrap();
+                cache.store(cache_file)
+                    .expect("failed to store scan cache");
+            }
+
+            let exit_status = scanner.summary.exit_status();
+            if exit_status != ExitStatus::Ok {
+                exit_status.exit();
             }
         },
-        Ok(IdentifiedFile::Unidentified) => {
-            // guess this one's not that interesting
-            return;
+    }
+}
+
+
+/// Reports the outcome of decompressing a file in `--check` mode, exiting with a failure status
+/// if decompression failed.
+fn report_check<E: std::fmt::Display>(input_file: &Path, decompressed_size: u64, result: Result<(), E>) {
+    match result {
+        Ok(()) => println!("{}: OK, {} bytes", input_file.display(), decompressed_size),
+        Err(e) => {
+            println!("{}: failed after {} bytes: {}", input_file.display(), decompressed_size, e);
+            ExitStatus::ParseError.exit();
         },
+    }
+}
+
+fn collect_reg_subkeys(
+    hive: &Hive,
+    key: &binms::regf::Key,
+    path: &str,
+    recursive: bool,
+    depth: usize,
+    subkeys: &mut Vec<(String, binms::regf::Key)>,
+) {
+    // guard against cycles in damaged hives
+    const MAX_DEPTH: usize = 512;
+    if depth > MAX_DEPTH {
+        error!("keys below {:?} are nested too deeply", path);
+        return;
+    }
+
+    let children = match hive.subkeys(key) {
+        Ok(c) => c,
         Err(e) => {
-            error!("failed to interpret file at {:?}: {}", parent_path_sequence, e);
+            error!("failed to read subkeys of {:?}: {}", path, e);
+            return;
         },
+    };
+    for child in children {
+        let child_path = if path.is_empty() {
+            child.name.clone()
+        } else {
+            format!("{}\\{}", path, child.name)
+        };
+        subkeys.push((child_path.clone(), child.clone()));
+        if recursive {
+            collect_reg_subkeys(hive, &child, &child_path, true, depth + 1, subkeys);
+        }
+    }
+}
+
+
+/// Returns the part of the PE file's data that corresponds to the given virtual address range.
+fn pe_virtual_slice<'d>(data: &'d [u8], pe: &binms::pe::Executable, address: u32, length: u32) -> &'d [u8] {
+    let start: usize = pe.section_table.virtual_to_raw(address)
+        .expect("failed to map virtual to raw address")
+        .try_into().unwrap();
+    let end = start + usize::try_from(length).unwrap();
+    data.get(start..end)
+        .expect("structure extends beyond the end of the file")
+}
+
+fn read_clr_header(data: &[u8], pe: &binms::pe::Executable) -> binms::clr::header::ClrHeader {
+    let optional_header = pe.optional_header.as_ref()
+        .expect("PE file is missing optional header");
+    let binms::pe::OptionalHeader::Coff(cough) = optional_header
+        else { panic!("PE file's optional header is not COFF") };
+    let optional_win_header = cough.optional_windows_header.as_ref()
+        .expect("PE file's COFF optional header does not contain the optional Windows header");
+    let clr_entry = optional_win_header.known_data_directory_entry(binms::pe::KnownDataDirectoryEntry::ClrRuntimeHeader)
+        .expect("PE file does not have a CLR header directory entry");
+    let (_, clr_header) = binms::clr::header::ClrHeader::take_from_bytes(pe_virtual_slice(data, pe, clr_entry.address, clr_entry.size))
+        .expect("failed to decode CLR header");
+    clr_header
+}
+
+/// Turns a resource name into a name that can safely be used as a file name within an extraction
+/// directory.
+fn file_name_for_extraction(name: &str) -> String {
+    let sanitized: String = name.chars()
+        .map(|c| if c == '/' || c == '\\' || c == ':' || c.is_control() { '_' } else { c })
+        .collect();
+    if sanitized.is_empty() || sanitized == "." || sanitized == ".." {
+        format!("_{}", sanitized)
+    } else {
+        sanitized
+    }
+}
+
+fn print_resource_tree(table: &binms::pe::ResourceDirectoryTable, depth: usize) {
+    let indent = "  ".repeat(depth);
+    // seconds since the Unix epoch, along with the time they stand for if it is plausible
+    let timestamp = match binms::pe::TimeDateStamp::decode(table.timestamp).to_rfc3339() {
+        Some(time) => format!("{} ({})", table.timestamp, time),
+        None => table.timestamp.to_string(),
+    };
+    println!(
+        "{}directory: characteristics {:#010X}, timestamp {}, version {}.{}",
+        indent, table.characteristics, timestamp, table.major_version, table.minor_version,
+    );
+    for (id, child) in &table.id_to_entry {
+        match child {
+            binms::pe::ResourceChild::Subdirectory(subdir) => {
+                println!("{}{:?}:", indent, id);
+                print_resource_tree(subdir, depth + 1);
+            },
+            binms::pe::ResourceChild::Data(data) => {
+                println!(
+                    "{}{:?}: data at {:#010X}, size {:#X}, codepage {}{}",
+                    indent, id, data.data_rva, data.size, data.codepage,
+                    if data.data.is_some() { "" } else { " (unreadable)" },
+                );
+            },
+        }
+    }
+}
+
+/// Replaces the data of resources that has been compressed using LZ77+Huffman (XPRESS) with the
+/// decompressed data.
+///
+/// Data is only replaced if it starts with a valid table of code lengths and decompresses cleanly
+/// up to an end-of-stream symbol at the very end, which uncompressed data practically never does.
+fn decompress_resources(table: &mut binms::pe::ResourceDirectoryTable) {
+    for (id, child) in table.id_to_entry.iter_mut() {
+        match child {
+            binms::pe::ResourceChild::Subdirectory(subdir) => decompress_resources(subdir),
+            binms::pe::ResourceChild::Data(data) => {
+                let Some(bytes) = data.data.as_ref() else { continue };
+                let bytes: &[u8] = bytes.as_ref();
+                if !expandms::xpress::looks_compressed(bytes) {
+                    continue;
+                }
+                match expandms::xpress::decompress(bytes) {
+                    Ok(decompressed) => {
+                        debug!("decompressed resource {:?} from {} to {} bytes", id, bytes.len(), decompressed.len());
+                        data.data = Some(decompressed.into());
+                    },
+                    Err(e) => debug!("resource {:?} looks XPRESS-compressed but is not: {}", id, e),
+                }
+            },
+        }
+    }
+}
+
+
+fn x64_unwind_operation_to_string(operation: &binms::pe::X64UnwindOperation) -> String {
+    use binms::pe::{x64_register_name, X64UnwindOperation};
+    match operation {
+        X64UnwindOperation::PushNonVolatile { register }
+            => format!("push {}", x64_register_name(*register)),
+        X64UnwindOperation::Allocate { size }
+            => format!("alloc {:#X}", size),
+        X64UnwindOperation::SetFramePointer
+            => "set frame pointer".to_owned(),
+        X64UnwindOperation::SaveNonVolatile { register, offset }
+            => format!("save {} at {:#X}", x64_register_name(*register), offset),
+        X64UnwindOperation::Epilog { info, data }
+            => format!("epilog (info {}, data {:#06X})", info, data),
+        X64UnwindOperation::SaveXmm128 { register, offset }
+            => format!("save xmm{} at {:#X}", register, offset),
+        X64UnwindOperation::PushMachineFrame { with_error_code }
+            => format!("push machine frame{}", if *with_error_code { " with error code" } else { "" }),
+        X64UnwindOperation::Other { op, info }
+            => format!("unknown operation {} (info {})", op, info),
+    }
+}
+
+
+fn output_listed_files(listed: &[crate::extract::ListedFile], level: usize) {
+    for file in listed {
+        let digests = match &file.digests {
+            Some(d) => format!("{}  {}  ", crate::hashing::to_hex(&d.sha1), crate::hashing::to_hex(&d.sha256)),
+            None => String::new(),
+        };
+        let size = match file.size {
+            Some(s) => s.to_string(),
+            None => "?".to_owned(),
+        };
+        let error = match &file.error {
+            Some(e) => format!("  ({})", e),
+            None => String::new(),
+        };
+        println!("{:>10}  {:<20}  {}{:indent$}{}{}", size, file.format, digests, "", file.path.display(), error, indent = 2*level);
+        output_listed_files(&file.children, level + 1);
+    }
+}
+
+
+/// Inserts the name of an icon variant before the extension of the output file name.
+fn variant_output_path(output_file: &Path, variant_name: &str) -> PathBuf {
+    let mut file_name = output_file.file_stem()
+        .unwrap_or_default()
+        .to_owned();
+    file_name.push(".");
+    file_name.push(variant_name);
+    if let Some(extension) = output_file.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    output_file.with_file_name(file_name)
+}
+
+
+/// Parses a 32-bit unsigned integer in decimal or, with a `0x` prefix, in hexadecimal.
+fn parse_u32_maybe_hex(value: &str) -> Result<u32, std::num::ParseIntError> {
+    match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
     }
 }
@@ -0,0 +1,167 @@
+//! Resources split off into `.mun` files.
+//!
+//! Starting with Windows 10 1903, many system libraries (e.g. `System32\imageres.dll`) only keep
+//! their version information and MUI configuration; the rest of their resources is moved into a
+//! resource-only PE file in the `SystemResources` directory next to `System32`, named like the
+//! library with `.mun` appended (`SystemResources\imageres.dll.mun`).
+
+
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+
+use binms::pe::{self, KnownDataDirectoryEntry, OptionalHeader, ResourceDirectoryTable};
+use tracing::{debug, warn};
+
+
+/// The name of the directory containing the `.mun` files, next to `System32`.
+const SYSTEM_RESOURCES_DIR_NAME: &str = "SystemResources";
+
+/// The extension appended to the name of a library to obtain the name of its `.mun` file.
+const MUN_EXTENSION: &str = ".mun";
+
+
+/// If the file name is that of a `.mun` file, returns the name of the library whose resources it
+/// contains.
+pub(crate) fn paired_library_name(file_name: &str) -> Option<&str> {
+    let split = file_name.len().checked_sub(MUN_EXTENSION.len())?;
+    if split == 0 || !file_name.is_char_boundary(split) || !file_name[split..].eq_ignore_ascii_case(MUN_EXTENSION) {
+        return None;
+    }
+    Some(&file_name[..split])
+}
+
+/// Returns a remark describing the file as the `.mun` file of a library if its path says so.
+pub(crate) fn split_off_remark(path_sequence: &[PathBuf]) -> Option<String> {
+    let file_name = path_sequence.last()?.file_name()?.to_str()?;
+    let library_name = paired_library_name(file_name)?;
+    Some(format!("resources split off from {}", library_name))
+}
+
+
+/// Returns the entry of the directory whose name matches the given one, ignoring ASCII case as
+/// Windows does.
+fn find_entry(dir: &Path, name: &str) -> Option<PathBuf> {
+    fs::read_dir(dir).ok()?
+        .filter_map(|entry| entry.ok())
+        .find(|entry| entry.file_name().to_str().is_some_and(|n| n.eq_ignore_ascii_case(name)))
+        .map(|entry| entry.path())
+}
+
+/// Returns the path of the `.mun` file containing the split-off resources of the library at the
+/// given path, if there is one.
+///
+/// The `.mun` file is looked for in the `SystemResources` directory next to the directory
+/// containing the library (generally `System32`).
+pub(crate) fn find_mun_file(library_path: &Path) -> Option<PathBuf> {
+    let library_path = std::path::absolute(library_path).ok()?;
+    let file_name = library_path.file_name()?.to_str()?;
+    if paired_library_name(file_name).is_some() {
+        return None;
+    }
+    let windows_dir = library_path.parent()?.parent()?;
+    let resources_dir = find_entry(windows_dir, SYSTEM_RESOURCES_DIR_NAME)?;
+    find_entry(&resources_dir, &format!("{}{}", file_name, MUN_EXTENSION))
+        .filter(|path| path.is_file())
+}
+
+
+/// Reads the resource tree of a PE file; returns `None` if the file has no resources.
+pub(crate) fn read_resources(data: &[u8]) -> Result<Option<ResourceDirectoryTable>, io::Error> {
+    let mut cursor = Cursor::new(data);
+    let executable = pe::Executable::read(&mut cursor)?;
+    let Some(OptionalHeader::Coff(coff)) = executable.optional_header.as_ref() else {
+        debug!("PE file does not have a COFF optional header");
+        return Err(io::ErrorKind::InvalidData.into());
+    };
+    let resource_entry = coff.optional_windows_header.as_ref()
+        .and_then(|wh| wh.known_data_directory_entry(KnownDataDirectoryEntry::ResourceTable))
+        .filter(|e| e.address != 0 && e.size > 0);
+    let Some(resource_entry) = resource_entry else {
+        return Ok(None);
+    };
+    ResourceDirectoryTable::read_root_from_pe(&mut cursor, &resource_entry, &executable.section_table)
+        .map(Some)
+}
+
+/// Reads the resource tree of the PE file at the given path and, if `follow_mun` is set and the
+/// file has a `.mun` file, merges the resources of the `.mun` file into it.
+///
+/// Returns the (possibly merged) resource tree along with the path of the `.mun` file whose
+/// resources were merged in. Resources present in both files are taken from the library itself.
+pub(crate) fn read_resources_with_mun(path: &Path, follow_mun: bool) -> Result<(Option<ResourceDirectoryTable>, Option<PathBuf>), io::Error> {
+    let data = fs::read(path)?;
+    let mut resources = read_resources(&data)?;
+    if !follow_mun {
+        return Ok((resources, None));
+    }
+    let Some(mun_path) = find_mun_file(path) else {
+        return Ok((resources, None));
+    };
+
+    debug!("merging resources from {}", mun_path.display());
+    match fs::read(&mun_path).and_then(|mun_data| read_resources(&mun_data)) {
+        Ok(Some(mun_resources)) => {
+            match resources.as_mut() {
+                Some(resources) => resources.merge(mun_resources),
+                None => resources = Some(mun_resources),
+            }
+            Ok((resources, Some(mun_path)))
+        },
+        Ok(None) => {
+            debug!("{} does not contain resources", mun_path.display());
+            Ok((resources, None))
+        },
+        Err(e) => {
+            warn!("failed to read resources from {}: {}", mun_path.display(), e);
+            Ok((resources, None))
+        },
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use testgen::pe;
+    use super::{find_mun_file, paired_library_name, read_resources_with_mun};
+
+    #[test]
+    fn test_read_resources_with_mun() {
+        assert_eq!(paired_library_name("imageres.dll.MUN"), Some("imageres.dll"));
+        assert_eq!(paired_library_name(".mun"), None);
+        assert_eq!(paired_library_name("imageres.dll"), None);
+
+        let windows = tempfile::tempdir().unwrap();
+        fs::create_dir(windows.path().join("System32")).unwrap();
+        fs::create_dir(windows.path().join("SystemResources")).unwrap();
+
+        // the library keeps its version information; the icon lives in the .mun file
+        let mut library = pe::PortableExecutable::new("IMAGERES.DLL");
+        library.resources.push(pe::Resource { type_id: 16, name_id: 1, language_id: 0x0409, data: vec![1; 16] });
+        let mut mun = pe::PortableExecutable::new("IMAGERES.DLL");
+        mun.resources.push(pe::Resource { type_id: 3, name_id: 1, language_id: 0x0409, data: vec![3; 16] });
+        mun.resources.push(pe::Resource { type_id: 16, name_id: 1, language_id: 0x0409, data: vec![2; 16] });
+        let library_path = windows.path().join("System32").join("imageres.dll");
+        let mun_path = windows.path().join("SystemResources").join("ImageRes.dll.mun");
+        fs::write(&library_path, library.to_bytes()).unwrap();
+        fs::write(&mun_path, mun.to_bytes()).unwrap();
+
+        assert_eq!(find_mun_file(&library_path).as_deref(), Some(mun_path.as_path()));
+        assert_eq!(find_mun_file(&mun_path), None);
+
+        let (resources, merged_from) = read_resources_with_mun(&library_path, true).unwrap();
+        assert_eq!(merged_from.as_deref(), Some(mun_path.as_path()));
+        let data: Vec<Vec<u8>> = resources.unwrap().data_entries().into_iter()
+            .map(|(_path, data)| {
+                let bytes: &[u8] = data.data.as_ref().unwrap().as_ref();
+                bytes.to_vec()
+            })
+            .collect();
+        assert_eq!(data, vec![vec![3; 16], vec![1; 16]]);
+
+        let (resources, merged_from) = read_resources_with_mun(&library_path, false).unwrap();
+        assert_eq!(merged_from, None);
+        assert_eq!(resources.unwrap().data_entries().len(), 1);
+    }
+}
@@ -0,0 +1,328 @@
+//! Extension point for handling formats not supported by the built-in detectors.
+//!
+//! Plugins are consulted in order of registration once the built-in detectors have failed to
+//! identify or read a file. Apart from implementing [`FormatPlugin`] directly, formats can be
+//! handled by an external program (see [`ExternalPlugin`]), which allows adding handlers without
+//! rebuilding.
+
+
+use std::borrow::Cow;
+use std::fmt;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::Deserialize;
+use tempfile::TempDir;
+use tracing::{debug, error};
+
+use crate::data_mgmt::{Error, IdentifiedFile, MultiFileContainer, Symbol, SymbolExporter};
+use crate::hashing::{self, Sha256Digest};
+
+
+/// A handler for an additional file format.
+///
+/// This is how formats that are not built in, e.g. proprietary game archives containing DLLs,
+/// participate in scanning: the plugin recognizes the format and returns one of the types
+/// described in [`data_mgmt`](crate::data_mgmt), whose contracts it must uphold.
+pub trait FormatPlugin : fmt::Debug {
+    /// The name of the plugin, for diagnostic output.
+    fn name(&self) -> String;
+
+    /// The version of the plugin.
+    ///
+    /// Scan results are cached separately for each set of plugins; a plugin whose behavior changes
+    /// should return a different version so that results obtained with the old behavior are not
+    /// reused.
+    fn version(&self) -> String {
+        String::new()
+    }
+
+    /// Attempts to interpret the file's data; returns `Ok(None)` if the format is not recognized.
+    ///
+    /// Plugins are offered every file that the built-in detectors do not recognize or fail to
+    /// read, so this should check a magic number or similar before doing anything expensive. Since
+    /// the data is only borrowed for the duration of the call, containers returned by the plugin
+    /// must own (a copy of) the data they need.
+    fn interpret(&self, data: &[u8]) -> Result<Option<IdentifiedFile<'static>>, Error>;
+}
+
+
+/// The plugins consulted for files that the built-in detectors do not recognize.
+#[derive(Debug, Default)]
+pub(crate) struct PluginRegistry {
+    plugins: Vec<Box<dyn FormatPlugin>>,
+}
+impl PluginRegistry {
+    pub fn register(&mut self, plugin: Box<dyn FormatPlugin>) {
+        self.plugins.push(plugin);
+    }
+
+    /// Offers the data to each plugin in turn, returning the first successful interpretation.
+    ///
+    /// Plugins that fail are skipped after logging the error.
+    pub fn interpret(&self, data: &[u8]) -> Option<IdentifiedFile<'static>> {
+        for plugin in &self.plugins {
+            match plugin.interpret(data) {
+                Ok(Some(identified)) => {
+                    debug!("plugin {} identified the file", plugin.name());
+                    return Some(identified);
+                },
+                Ok(None) => {},
+                Err(e) => {
+                    error!("plugin {} failed: {}", plugin.name(), e);
+                },
+            }
+        }
+        None
+    }
+
+    /// Combines a digest of scanned data with the names and versions of the registered plugins,
+    /// obtaining the key under which the results of scanning the data are cached.
+    ///
+    /// Without plugins, the key is the digest itself.
+    pub fn cache_key(&self, digest: &Sha256Digest) -> Sha256Digest {
+        if self.plugins.is_empty() {
+            return *digest;
+        }
+
+        let mut plugin_ids: Vec<String> = self.plugins
+            .iter()
+            .map(|p| format!("{}\0{}\0", p.name(), p.version()))
+            .collect();
+        plugin_ids.sort_unstable();
+
+        let mut key_data = digest.to_vec();
+        for plugin_id in plugin_ids {
+            key_data.extend_from_slice(plugin_id.as_bytes());
+        }
+        hashing::sha256(&key_data)
+    }
+}
+
+
+/// A plugin implemented by an external program.
+///
+/// The program is called with two arguments: the path of a file containing the data to interpret
+/// and the path of an empty directory. It must output a single JSON object on standard output,
+/// which is one of:
+///
+/// * `{"kind": "unidentified"}` if the format is not recognized;
+/// * `{"kind": "symbols", "symbols": [...], "remark": "..."}` if the file exports symbols, where
+///   each symbol is encoded like in the scan cache (e.g. `{"ByName": {"name": "Foo"}}`) and the
+///   remark is optional;
+/// * `{"kind": "container", "files": ["a.dll", "sub/b.dll"]}` if the file contains other files,
+///   which the program has extracted into the directory given as the second argument.
+///
+/// A non-zero exit code is treated as an error.
+///
+/// The version of the plugin is the SHA-256 digest of the program file, so that changing the
+/// program invalidates the results cached with it.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) struct ExternalPlugin {
+    pub command: PathBuf,
+    pub version: String,
+}
+impl ExternalPlugin {
+    pub fn new(command: PathBuf) -> Self {
+        let version = match std::fs::read(&command) {
+            Ok(program) => hashing::to_hex(&hashing::sha256(&program)),
+            Err(e) => {
+                // e.g. a program found via PATH
+                debug!("failed to read plugin program {}: {}", command.display(), e);
+                String::new()
+            },
+        };
+        Self {
+            command,
+            version,
+        }
+    }
+}
+impl FormatPlugin for ExternalPlugin {
+    fn name(&self) -> String {
+        self.command.display().to_string()
+    }
+
+    fn version(&self) -> String {
+        self.version.clone()
+    }
+
+    fn interpret(&self, data: &[u8]) -> Result<Option<IdentifiedFile<'static>>, Error> {
+        let mut input_file = tempfile::NamedTempFile::new()?;
+        input_file.write_all(data)?;
+        input_file.flush()?;
+        let output_dir = tempfile::tempdir()?;
+
+        let output = Command::new(&self.command)
+            .arg(input_file.path())
+            .arg(output_dir.path())
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit())
+            .output()?;
+        if !output.status.success() {
+            error!("plugin {} exited with {}", self.command.display(), output.status);
+            return Err(std::io::Error::other("plugin failed").into());
+        }
+
+        let plugin_output: PluginOutput = serde_json::from_slice(&output.stdout)
+            .map_err(std::io::Error::from)?;
+        match plugin_output {
+            PluginOutput::Unidentified => Ok(None),
+            PluginOutput::Symbols { symbols, remark } => {
+                let exporter = PluginSymbolExporter {
+                    symbols,
+                    remark,
+                };
+                Ok(Some(IdentifiedFile::SymbolExporter(Box::new(exporter))))
+            },
+            PluginOutput::Container { files } => {
+                for file in &files {
+                    let is_relative = file.components()
+                        .all(|c| matches!(c, Component::Normal(_)));
+                    if !is_relative {
+                        error!("plugin {} returned invalid file path {:?}", self.command.display(), file);
+                        return Err(Error::FileNotFound(file.clone()));
+                    }
+                }
+                let container = PluginContainer {
+                    dir: output_dir,
+                    files,
+                };
+                Ok(Some(IdentifiedFile::MultiFileContainer(Box::new(container))))
+            },
+        }
+    }
+}
+
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum PluginOutput {
+    Unidentified,
+    Symbols {
+        symbols: Vec<Symbol>,
+        #[serde(default)] remark: Option<String>,
+    },
+    Container {
+        files: Vec<PathBuf>,
+    },
+}
+
+
+#[derive(Clone, Debug)]
+struct PluginSymbolExporter {
+    symbols: Vec<Symbol>,
+    remark: Option<String>,
+}
+impl SymbolExporter for PluginSymbolExporter {
+    fn format_name(&self) -> &str {
+        "plugin"
+    }
+
+    fn read_symbols(&self) -> Result<Vec<Symbol>, Error> {
+        Ok(self.symbols.clone())
+    }
+
+    fn remark(&self) -> Option<String> {
+        self.remark.clone()
+    }
+}
+
+
+/// The files extracted by a plugin; deleted along with the container.
+#[derive(Debug)]
+struct PluginContainer {
+    dir: TempDir,
+    files: Vec<PathBuf>,
+}
+impl MultiFileContainer for PluginContainer {
+    fn format_name(&self) -> &str {
+        "plugin"
+    }
+
+    fn list_files(&self) -> Result<Vec<PathBuf>, Error> {
+        Ok(self.files.clone())
+    }
+
+    fn read_file(&self, file_path: &Path) -> Result<Cow<'_, [u8]>, Error> {
+        if !self.files.iter().any(|f| f == file_path) {
+            return Err(Error::FileNotFound(file_path.to_owned()));
+        }
+        let data = std::fs::read(self.dir.path().join(file_path))?;
+        Ok(Cow::Owned(data))
+    }
+}
+
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::{Path, PathBuf};
+
+    use super::{ExternalPlugin, FormatPlugin, PluginRegistry};
+    use crate::data_mgmt::{IdentifiedFile, Symbol};
+
+    const STUB_PLUGIN: &str = r#"#!/bin/sh
+case "$(head -c 4 "$1")" in
+    SYMS) printf '{"kind": "symbols", "symbols": [{"ByName": {"name": "Foo"}}], "remark": "stub"}' ;;
+    CONT) printf 'inner' > "$2/inner.bin"; printf '{"kind": "container", "files": ["inner.bin"]}' ;;
+    ESCP) printf '{"kind": "container", "files": ["../escape.bin"]}' ;;
+    FAIL) exit 1 ;;
+    *) printf '{"kind": "unidentified"}' ;;
+esac
+"#;
+
+    fn write_stub(dir: &Path, contents: &str) -> PathBuf {
+        let path = dir.join("stub-plugin.sh");
+        std::fs::write(&path, contents).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_external_plugin() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = ExternalPlugin::new(write_stub(dir.path(), STUB_PLUGIN));
+
+        match plugin.interpret(b"SYMS").unwrap() {
+            Some(IdentifiedFile::SymbolExporter(exporter)) => {
+                assert_eq!(exporter.read_symbols().unwrap(), vec![Symbol::ByName { name: "Foo".to_owned() }]);
+                assert_eq!(exporter.remark().as_deref(), Some("stub"));
+            },
+            other => panic!("unexpected interpretation {:?}", other),
+        }
+
+        match plugin.interpret(b"CONT").unwrap() {
+            Some(IdentifiedFile::MultiFileContainer(container)) => {
+                assert_eq!(container.list_files().unwrap(), vec![PathBuf::from("inner.bin")]);
+                assert_eq!(container.read_file(Path::new("inner.bin")).unwrap().as_ref(), b"inner");
+                assert!(container.read_file(Path::new("other.bin")).is_err());
+            },
+            other => panic!("unexpected interpretation {:?}", other),
+        }
+
+        assert!(plugin.interpret(b"NOPE").unwrap().is_none());
+        assert!(plugin.interpret(b"ESCP").is_err());
+        assert!(plugin.interpret(b"FAIL").is_err());
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let digest = [0x42u8; 32];
+        let mut registry = PluginRegistry::default();
+        assert_eq!(registry.cache_key(&digest), digest);
+
+        let dir = tempfile::tempdir().unwrap();
+        let stub_path = write_stub(dir.path(), STUB_PLUGIN);
+        registry.register(Box::new(ExternalPlugin::new(stub_path.clone())));
+        let key = registry.cache_key(&digest);
+        assert_ne!(key, digest);
+
+        // a changed program is a different version
+        let mut changed_registry = PluginRegistry::default();
+        write_stub(dir.path(), &format!("{}# changed\n", STUB_PLUGIN));
+        changed_registry.register(Box::new(ExternalPlugin::new(stub_path)));
+        assert_ne!(changed_registry.cache_key(&digest), key);
+    }
+}
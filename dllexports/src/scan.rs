@@ -0,0 +1,1845 @@
+//! Recursive scanning of files for exported symbols.
+
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use binms::pe::SectionClass;
+use binstruct::deadline;
+use display_bytes::DisplayBytesSlice;
+use display_bytes::escape::escape_str;
+use clap::ValueEnum;
+use filtexp::ExportFilter;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info, warn};
+
+use crate::api_docs::ApiDocs;
+use crate::data_mgmt::{IdentifiedFile, Metadata, MultiFileContainer, PathSequence, Symbol};
+use crate::demangle::undecorate;
+use crate::exit_status::ExitStatus;
+use crate::formats::interpret_file;
+use crate::hashing::{self, Sha256Digest};
+use crate::plugin::PluginRegistry;
+
+
+/// The default maximum number of containers a file may be nested in before the scan stops
+/// descending.
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 32;
+
+
+/// A symbol found during a scan, relative to the blob in which the scan started.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+struct FoundSymbol {
+    /// The path within the scanned blob at which the symbol-exporting file is found.
+    ///
+    /// Empty if the scanned blob itself exports the symbol.
+    sub_path: Vec<PathBuf>,
+
+    /// The symbol; `None` if the file does not export any symbols but has a remark.
+    symbol: Option<Symbol>,
+
+    /// A remark on the exporting file that consumers should be aware of, e.g. that its export table
+    /// is generated by a runtime.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    remark: Option<String>,
+
+    /// The address of the symbol relative to the image base, if the exporting file records one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    address: Option<u32>,
+
+    /// The class of the section containing the symbol, which tells functions apart from variables,
+    /// if the exporting file has sections.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    section_class: Option<SectionClass>,
+
+    /// The export to which the symbol is forwarded, if it is a forwarder.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    forwarder: Option<String>,
+
+    /// The architecture of the exporting file, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    architecture: Option<String>,
+
+    /// The build time of the exporting file or a note that it has been replaced by a
+    /// reproducible-build hash, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<String>,
+
+    /// The toolchain that probably produced the exporting file, if it can be guessed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    toolchain: Option<String>,
+}
+impl FoundSymbol {
+    /// An entry for the scanned blob itself that only carries a remark.
+    fn remark_only(remark: String) -> Self {
+        Self {
+            sub_path: Vec::with_capacity(0),
+            symbol: None,
+            remark: Some(remark),
+            address: None,
+            section_class: None,
+            forwarder: None,
+            architecture: None,
+            timestamp: None,
+            toolchain: None,
+        }
+    }
+}
+
+
+/// How the symbols found during a scan are output.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd, ValueEnum)]
+pub(crate) enum OutputFormat {
+    /// Tab-separated columns: path, ordinal, name, the undecorated name (with `--demangle`) and
+    /// the remark (if any).
+    #[default] Tsv,
+
+    /// Comma-separated values with a header row and a fixed set of columns, for import into
+    /// spreadsheets and other analysis tools.
+    Csv,
+}
+
+
+/// The version of the format of stored scan caches.
+///
+/// Increase this whenever [`FoundSymbol`] or the interpretation of any format changes in a way that
+/// changes the results of a scan; caches stored with a different version are discarded on loading.
+const SCAN_CACHE_VERSION: u32 = 1;
+
+
+/// A cache of scan results, keyed by the SHA-256 digest of the scanned data (combined with the
+/// names and versions of the plugins, if any; see [`PluginRegistry::cache_key`]).
+///
+/// Disk images tend to contain many byte-identical copies of the same file; with a cache, each copy
+/// is only parsed once.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct ScanCache {
+    digest_to_symbols: BTreeMap<Sha256Digest, Vec<FoundSymbol>>,
+}
+impl ScanCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads a cache that has previously been stored using [`ScanCache::store`].
+    ///
+    /// A cache stored with a different version of the format (see [`SCAN_CACHE_VERSION`]) is
+    /// discarded, returning an empty cache.
+    pub fn load(path: &Path) -> Result<Self, io::Error> {
+        let bytes = fs::read(path)?;
+
+        // check the version first, as the entries of other versions may not even be readable
+        let version: StoredCacheVersion = serde_json::from_slice(&bytes)?;
+        if version.version != SCAN_CACHE_VERSION {
+            warn!(
+                "discarding scan cache {} of version {} (expected version {})",
+                path.display(), version.version, SCAN_CACHE_VERSION,
+            );
+            return Ok(Self::new());
+        }
+
+        let stored: StoredCache<Vec<FoundSymbol>> = serde_json::from_slice(&bytes)?;
+        let mut digest_to_symbols = BTreeMap::new();
+        for (hex, symbols) in stored.entries {
+            let Some(digest) = hashing::from_hex(&hex) else {
+                error!("invalid digest {:?} in scan cache {}", hex, path.display());
+                return Err(io::ErrorKind::InvalidData.into());
+            };
+            digest_to_symbols.insert(digest, symbols);
+        }
+        Ok(Self {
+            digest_to_symbols,
+        })
+    }
+
+    /// Stores the cache in a file, from which it can be loaded using [`ScanCache::load`].
+    pub fn store(&self, path: &Path) -> Result<(), io::Error> {
+        let stored = StoredCache {
+            version: SCAN_CACHE_VERSION,
+            entries: self.digest_to_symbols
+                .iter()
+                .map(|(digest, symbols)| (hashing::to_hex(digest), symbols))
+                .collect(),
+        };
+
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, &stored)?;
+        writer.flush()
+    }
+
+    pub fn len(&self) -> usize {
+        self.digest_to_symbols.len()
+    }
+}
+
+
+/// A scan cache as stored in a file.
+#[derive(Deserialize, Serialize)]
+struct StoredCache<S> {
+    version: u32,
+    entries: BTreeMap<String, S>,
+}
+
+/// The version of a stored scan cache, read before the rest of it.
+///
+/// Caches stored before the format had a version consist of the entries alone, which reads as
+/// version 0.
+#[derive(Deserialize)]
+struct StoredCacheVersion {
+    #[serde(default)]
+    version: u32,
+}
+
+
+/// The names under which files are installed, as collected from INF files.
+///
+/// Driver and installation media often contain files compressed into single-file containers whose
+/// names (e.g. `foo.dl_`) only hint at the name of the file within. The INF files next to them
+/// describe which name each file is installed as.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct InfNames {
+    /// Maps each directory to a mapping of lowercase file names on the media to installed names.
+    dir_to_names: BTreeMap<PathBuf, BTreeMap<String, String>>,
+}
+impl InfNames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the file names from an INF file found in the given directory.
+    pub fn register(&mut self, dir: &Path, metadata: &Metadata) {
+        let names = self.dir_to_names
+            .entry(dir.to_owned())
+            .or_default();
+
+        for copied_file in &metadata.copied_files {
+            let source_lower = copied_file.source.to_ascii_lowercase();
+            names.entry(compressed_name(&source_lower))
+                .or_insert_with(|| copied_file.destination.clone());
+            names.entry(source_lower)
+                .or_insert_with(|| copied_file.destination.clone());
+        }
+        for source_file in &metadata.source_disks_files {
+            let source_lower = source_file.to_ascii_lowercase();
+            names.entry(compressed_name(&source_lower))
+                .or_insert_with(|| source_file.clone());
+        }
+    }
+
+    /// Returns the name under which the file with the given name in the given directory is
+    /// installed.
+    pub fn resolve(&self, dir: &Path, file_name: &str) -> Option<&str> {
+        self.dir_to_names
+            .get(dir)?
+            .get(&file_name.to_ascii_lowercase())
+            .map(|n| n.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.dir_to_names
+            .values()
+            .map(|names| names.len())
+            .sum()
+    }
+
+    /// Replaces the file within a single-file container on disk (denoted by an empty path part)
+    /// with the installed name of the container, if known.
+    ///
+    /// Only INF files on disk are collected, and they only describe the files next to them;
+    /// single-file containers nested deeper within other containers are therefore left alone.
+    fn rename(&self, path: &mut [PathBuf]) {
+        let [host_path, inner_path, ..] = path else { return };
+        if !inner_path.as_os_str().is_empty() {
+            return;
+        }
+        let Some(host_dir) = host_path.parent() else { return };
+        let Some(container_name) = host_path.file_name().and_then(|n| n.to_str()) else { return };
+        if let Some(installed_name) = self.resolve(host_dir, container_name) {
+            *inner_path = PathBuf::from(installed_name);
+        }
+    }
+}
+
+/// The system directory a file was found in on a Windows installation with WOW64.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum Wow64Side {
+    /// `System32`, which contains the native (generally 64-bit) files.
+    System32,
+
+    /// `SysWoW64`, which contains the 32-bit files.
+    SysWow64,
+}
+impl Wow64Side {
+    fn from_dir_name(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("system32") {
+            Some(Self::System32)
+        } else if name.eq_ignore_ascii_case("syswow64") {
+            Some(Self::SysWow64)
+        } else {
+            None
+        }
+    }
+
+    fn dir_name(&self) -> &'static str {
+        match self {
+            Self::System32 => "System32",
+            Self::SysWow64 => "SysWoW64",
+        }
+    }
+}
+
+/// The exports of a file found in `System32` or `SysWoW64`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct Wow64File {
+    path: Vec<PathBuf>,
+    architectures: BTreeSet<String>,
+    exports: BTreeSet<String>,
+}
+
+/// The exports of the files found in `System32` and `SysWoW64`, paired by the rest of their paths.
+///
+/// On 64-bit Windows, the same DLL generally exists twice: natively in `System32` and as a 32-bit
+/// build in `SysWoW64`. Exports that only one of them offers hint at APIs that are only available
+/// to processes of one bitness.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct Wow64Pairs {
+    /// Maps the lowercase path components (with the system directory left out) to the files on
+    /// each side.
+    key_to_sides: BTreeMap<Vec<String>, BTreeMap<Wow64Side, Wow64File>>,
+}
+impl Wow64Pairs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the symbol if the exporting file is (possibly indirectly) found in a `System32` or
+    /// `SysWoW64` directory.
+    fn record(&mut self, path_sequence: &[PathBuf], found_symbol: &FoundSymbol) {
+        let components: Vec<String> = path_sequence.iter()
+            .flat_map(|part| part.components())
+            .map(|c| c.as_os_str().to_string_lossy().to_lowercase())
+            .collect();
+        let Some((side_index, side)) = components.iter()
+            .enumerate()
+            .rev()
+            .find_map(|(i, c)| Wow64Side::from_dir_name(c).map(|side| (i, side)))
+            else { return };
+        let mut key = components;
+        key.remove(side_index);
+
+        let file = self.key_to_sides
+            .entry(key)
+            .or_default()
+            .entry(side)
+            .or_default();
+        if file.path.is_empty() {
+            file.path = path_sequence.to_vec();
+        }
+        if let Some(architecture) = found_symbol.architecture.as_ref() {
+            file.architectures.insert(architecture.clone());
+        }
+        if let Some(symbol) = found_symbol.symbol.as_ref() {
+            let export = match (symbol.name(), symbol.ordinal()) {
+                (Some(name), _) => escape_str(name),
+                (None, Some(ordinal)) => format!("#{}", ordinal),
+                (None, None) => return,
+            };
+            file.exports.insert(export);
+        }
+    }
+
+    /// Returns the exports that only one file of each pair offers, along with the path of that
+    /// file, the directory it is in and its architecture.
+    ///
+    /// Pairs whose files have the same architecture are skipped, since they are not a native and a
+    /// 32-bit build of the same file.
+    fn differences(&self) -> Vec<(&[PathBuf], Wow64Side, String, &str)> {
+        let mut differences = Vec::new();
+        for sides in self.key_to_sides.values() {
+            let (Some(native), Some(wow64)) = (sides.get(&Wow64Side::System32), sides.get(&Wow64Side::SysWow64))
+                else { continue };
+            if !native.architectures.is_empty() && native.architectures == wow64.architectures {
+                warn!(
+                    native = ?native.path, wow64 = ?wow64.path, architectures = ?native.architectures,
+                    "files in System32 and SysWoW64 have the same architecture; not comparing them",
+                );
+                continue;
+            }
+
+            for (side, file, other) in [(Wow64Side::System32, native, wow64), (Wow64Side::SysWow64, wow64, native)] {
+                let architecture = file.architectures.iter()
+                    .map(|a| a.as_str())
+                    .next()
+                    .unwrap_or("");
+                for export in file.exports.difference(&other.exports) {
+                    differences.push((file.path.as_slice(), side, export.clone(), architecture));
+                }
+            }
+        }
+        differences
+    }
+
+    /// Outputs the exports that only one file of each pair offers as tab-separated columns: the
+    /// path of the file, the directory it is in, its architecture and the export.
+    pub fn output_report(&self) {
+        for (path, side, export, architecture) in self.differences() {
+            println!("{:?}\t{}\t{}\t{}", path, side.dir_name(), architecture, export);
+        }
+    }
+}
+
+
+/// The file name under which the exports of the kernel and the HALs are output by
+/// [`KernelNamespace`].
+const KERNEL_NAMESPACE_FILE_NAME: &str = "kernel";
+
+/// The base names of the variants of the NT kernel: uniprocessor, multiprocessor, with PAE
+/// (`pa`) and with five-level paging (`la57`).
+const KERNEL_BASE_NAMES: [&str; 5] = ["ntoskrnl", "ntkrnlmp", "ntkrnlpa", "ntkrpamp", "ntkrla57"];
+
+/// The base names of the x86 HALs shipped with Windows NT: the standard PC (`hal`), ACPI and APIC
+/// PCs, and the multiprocessor machines of various vendors. HAL extensions (`halext*`) and other
+/// modules whose names merely start with `hal` are not among them.
+const HAL_BASE_NAMES: [&str; 17] = [
+    "hal", "hal486c", "halaacpi", "halacpi", "halapic", "halast", "halborg", "halcbus", "halcbusm",
+    "halmacpi", "halmca", "halmps", "halmpsm", "halncr", "haloli", "halsp", "halwyse7",
+];
+
+/// Whether the module is a variant of the NT kernel or a hardware abstraction layer (HAL).
+///
+/// `name` is a file name (which may be compressed, e.g. `NTOSKRNL.EX_`) or the module part of a
+/// forwarder, which lacks the extension.
+fn is_kernel_module(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    let (base, extension) = name.split_once('.').unwrap_or((&name, ""));
+    if KERNEL_BASE_NAMES.contains(&base) {
+        matches!(extension, "" | "exe" | "ex_")
+    } else {
+        HAL_BASE_NAMES.contains(&base) && matches!(extension, "" | "dll" | "dl_")
+    }
+}
+
+/// Merges the exports of the kernel and the HALs.
+///
+/// Drivers link against the kernel (`ntoskrnl.exe`) and the HAL (`hal.dll`), each of which is
+/// installed as one of several variants, and functions have moved between the two over time, with
+/// forwarders left behind in their old home. Tracking the evolution of the kernel API is easier if
+/// all of these files are treated as one: their exports are output as those of a file named
+/// [`KERNEL_NAMESPACE_FILE_NAME`] in the same directory (or container), each name only once and
+/// without ordinals, which differ between the variants. Forwarders to the kernel or a HAL are
+/// dropped in favor of the export they point to.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct KernelNamespace {
+    /// The logical paths and exports that have already been output.
+    output: BTreeSet<(Vec<PathBuf>, String)>,
+}
+impl KernelNamespace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the symbol into the kernel namespace if it is exported by the kernel or a HAL.
+    ///
+    /// Returns whether the symbol is to be output, which is not the case for forwarders within the
+    /// namespace and exports that have already been output.
+    fn unify(&mut self, full_path: &mut Vec<PathBuf>, found_symbol: &mut FoundSymbol) -> bool {
+        let Some(file_index) = full_path.iter().rposition(|p| !p.as_os_str().is_empty()) else { return true };
+        let is_kernel = full_path[file_index].file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(is_kernel_module);
+        if !is_kernel {
+            return true;
+        }
+        let Some(symbol) = found_symbol.symbol.as_ref() else { return true };
+
+        let forwarded_within = found_symbol.forwarder.as_deref()
+            .and_then(|f| f.split_once('.'))
+            .is_some_and(|(module, _)| is_kernel_module(module));
+        if forwarded_within {
+            return false;
+        }
+
+        let (export, unified_symbol) = match symbol.name() {
+            Some(name) => (name.to_owned(), Symbol::ByName { name: name.to_owned() }),
+            None => (format!("#{}", symbol.ordinal().unwrap_or_default()), symbol.clone()),
+        };
+        full_path.truncate(file_index + 1);
+        full_path[file_index].set_file_name(KERNEL_NAMESPACE_FILE_NAME);
+        if !self.output.insert((full_path.clone(), export)) {
+            return false;
+        }
+
+        // the address is that of one variant only
+        found_symbol.symbol = Some(unified_symbol);
+        found_symbol.address = None;
+        true
+    }
+}
+
+
+/// Returns the name of a file once it has been compressed using `COMPRESS.EXE -r`, which replaces
+/// the last character of the extension with an underscore.
+fn compressed_name(name: &str) -> String {
+    match name.rsplit_once('.') {
+        Some((base, ext)) if ext.chars().count() >= 3 => {
+            let mut ext_chars: Vec<char> = ext.chars().collect();
+            *ext_chars.last_mut().unwrap() = '_';
+            format!("{}.{}", base, ext_chars.into_iter().collect::<String>())
+        },
+        Some((base, ext)) => format!("{}.{}_", base, ext),
+        None => format!("{}._", name),
+    }
+}
+
+
+/// The identity of a file stored in a symbol-server layout (`foo.dll/<index>/foo.dll`).
+///
+/// For executables, the index is the hexadecimal build timestamp followed by the hexadecimal size
+/// of the image, which identifies the build; copies of the same build within a scan share a key.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) struct SymbolStoreKey {
+    /// The name of the file, in lowercase.
+    pub file_name: String,
+
+    /// The index of the build, in uppercase.
+    pub index: String,
+}
+impl SymbolStoreKey {
+    /// Recognizes a path ending in `<name>/<index>/<name>`, where the last component may also be
+    /// the compressed form of the name (see [`compressed_name`]).
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let mut components = path.components()
+            .rev()
+            .map(|c| c.as_os_str().to_str());
+        let stored_name = components.next()??;
+        let index = components.next()??;
+        let file_name = components.next()??;
+
+        // timestamp (8 digits) and image size; the size is not zero-padded
+        let index_is_hex = (9..=16).contains(&index.len())
+            && index.bytes().all(|b| b.is_ascii_hexdigit());
+        if !index_is_hex {
+            return None;
+        }
+        let name_matches = stored_name.eq_ignore_ascii_case(file_name)
+            || stored_name.eq_ignore_ascii_case(&compressed_name(file_name));
+        if !name_matches {
+            return None;
+        }
+
+        Some(Self {
+            file_name: file_name.to_lowercase(),
+            index: index.to_uppercase(),
+        })
+    }
+}
+
+
+/// The category of a failure encountered during a scan.
+#[derive(Clone, Copy, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ErrorCategory {
+    /// A file or directory on disk could not be read.
+    ReadFromDisk,
+
+    /// The files in a container could not be listed.
+    ListFiles,
+
+    /// A file could not be obtained from its container.
+    ExtractFile,
+
+    /// A large file could not be written to or read back from a temporary file.
+    TemporaryFile,
+
+    /// A file was recognized, but its data could not be interpreted.
+    Interpret,
+
+    /// The exported symbols of a file could not be read.
+    ReadSymbols,
+
+    /// The filter script failed for a symbol.
+    FilterScript,
+}
+impl ErrorCategory {
+    fn description(&self) -> &'static str {
+        match self {
+            Self::ReadFromDisk => "reading from disk",
+            Self::ListFiles => "listing files in containers",
+            Self::ExtractFile => "extracting files from containers",
+            Self::TemporaryFile => "spilling to temporary files",
+            Self::Interpret => "interpreting files",
+            Self::ReadSymbols => "reading exported symbols",
+            Self::FilterScript => "evaluating the filter script",
+        }
+    }
+
+    /// The kind of failure, named like the corresponding exit status.
+    pub fn status(&self) -> ExitStatus {
+        match self {
+            Self::ReadFromDisk|Self::TemporaryFile => ExitStatus::IoError,
+            Self::ListFiles|Self::ExtractFile|Self::Interpret|Self::ReadSymbols|Self::FilterScript => ExitStatus::ParseError,
+        }
+    }
+}
+
+
+/// Aggregate statistics of a scan, showing how complete it was.
+///
+/// The files whose results are taken from the scan cache are not interpreted again, so they only
+/// count towards `files_visited`, `files_from_cache` and `symbols`.
+#[derive(Clone, Debug, Default, Eq, JsonSchema, PartialEq, Serialize)]
+pub(crate) struct ScanSummary {
+    /// The number of files examined, including those within containers.
+    pub files_visited: u64,
+
+    /// The number of files whose results were taken from the scan cache.
+    pub files_from_cache: u64,
+
+    /// The number of containers opened, by format.
+    pub containers_by_format: BTreeMap<String, u64>,
+
+    /// The number of files exporting symbols.
+    pub exporters: u64,
+
+    /// The number of files skipped because a file with the same name and index in a
+    /// symbol-server layout had already been scanned.
+    pub symbol_store_duplicates: u64,
+
+    /// The number of exported symbols found.
+    pub symbols: u64,
+
+    /// The number of files that were not scanned completely, because they contained themselves,
+    /// were nested too deeply or exceeded the time limit.
+    pub incomplete_files: u64,
+
+    /// The number of failures, by category.
+    pub errors_by_category: BTreeMap<ErrorCategory, u64>,
+
+    /// The individual failures; only collected if set to `Some` before the scan.
+    #[serde(skip)]
+    pub error_records: Option<Vec<crate::schema::ErrorRecord>>,
+}
+impl ScanSummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a failure that occurred while processing the file at the given path.
+    pub fn record_error<E: fmt::Display>(&mut self, category: ErrorCategory, path: &[PathBuf], error: E) {
+        *self.errors_by_category.entry(category).or_insert(0) += 1;
+        if let Some(error_records) = self.error_records.as_mut() {
+            error_records.push(crate::schema::ErrorRecord {
+                path: path.iter()
+                    .map(|p| p.display().to_string())
+                    .collect(),
+                category,
+                status: category.status(),
+                message: error.to_string(),
+            });
+        }
+    }
+
+    /// The exit status befitting the outcome of the scan.
+    pub fn exit_status(&self) -> ExitStatus {
+        if self.errors_by_category.is_empty() {
+            ExitStatus::Ok
+        } else {
+            ExitStatus::PartialWithErrors
+        }
+    }
+
+    fn record_container(&mut self, format_name: &str) {
+        *self.containers_by_format.entry(format_name.to_owned()).or_insert(0) += 1;
+    }
+
+    /// Outputs the summary in human-readable form to standard error, keeping it apart from the
+    /// symbols.
+    pub fn output(&self) {
+        eprintln!("scan summary:");
+        eprintln!("  files visited: {} ({} from cache)", self.files_visited, self.files_from_cache);
+        for (format_name, count) in &self.containers_by_format {
+            eprintln!("  {} containers: {}", format_name, count);
+        }
+        eprintln!("  exporting files: {}", self.exporters);
+        eprintln!("  symbol-server duplicates skipped: {}", self.symbol_store_duplicates);
+        eprintln!("  symbols: {}", self.symbols);
+        eprintln!("  incompletely scanned files: {}", self.incomplete_files);
+        let error_count: u64 = self.errors_by_category.values().sum();
+        eprintln!("  errors: {}", error_count);
+        for (category, count) in &self.errors_by_category {
+            eprintln!("    {}: {}", category.description(), count);
+        }
+    }
+}
+
+
+/// Recursively scans files for exported symbols.
+#[derive(Debug)]
+pub(crate) struct Scanner {
+    /// The cache of previous scan results, if any.
+    pub cache: Option<ScanCache>,
+
+    /// The maximum size of a file extracted from a container that is kept in memory while the
+    /// container's other files are being extracted.
+    ///
+    /// Larger files are written out to temporary files and only scanned once the container itself
+    /// has been released, so that the container and its large children never have to be kept in
+    /// memory at the same time.
+    pub max_in_memory_size: Option<usize>,
+
+    /// Whether to output an additional column containing the undecorated name of each symbol.
+    pub demangle: bool,
+
+    /// If set, the contents of single-file containers are named according to the INF files found
+    /// next to them instead of remaining unnamed.
+    pub inf_names: Option<InfNames>,
+
+    /// The plugins consulted for files that are not recognized by the built-in detectors.
+    pub plugins: PluginRegistry,
+
+    /// The format in which the symbols are output.
+    pub output_format: OutputFormat,
+
+    /// The maximum number of containers a file may be nested in; the contents of files nested
+    /// more deeply are not scanned.
+    pub max_depth: usize,
+
+    /// If set, each symbol is output with an additional column containing its documentation (or
+    /// an empty column if there is none).
+    pub api_docs: Option<ApiDocs>,
+
+    /// If set, each symbol is passed through this filter script before it is output, which may
+    /// drop it or rename it and its exporting file.
+    pub filter: Option<ExportFilter>,
+
+    /// If set, the symbols are collected into WOW64 pairs instead of being output.
+    pub wow64_pairs: Option<Wow64Pairs>,
+
+    /// If set, the exports of the kernel and the HALs are output as those of one logical file per
+    /// directory.
+    pub kernel_namespace: Option<KernelNamespace>,
+
+    /// How long the scan of a single file (including the files it contains) may take; `None` for
+    /// no limit.
+    ///
+    /// Once the limit is exceeded, the scan of the file is cut short and the symbols found so far
+    /// are output along with a remark that they are incomplete.
+    pub time_limit: Option<Duration>,
+
+    /// The statistics collected so far.
+    pub summary: ScanSummary,
+
+    /// The SHA-256 digests of the containers enclosing the file currently being scanned, outermost
+    /// first.
+    ///
+    /// A file whose digest is already on this stack contains itself (possibly via other files), so
+    /// descending into it again would never end.
+    ancestor_digests: Vec<Sha256Digest>,
+
+    /// The number of files so far that were not scanned because they contain themselves or are
+    /// nested too deeply.
+    ///
+    /// These results depend on where the file is nested, so neither they nor the results of the
+    /// files containing them may be cached.
+    nesting_dependent_results: u64,
+
+    /// The files in symbol-server layouts that have been scanned so far.
+    symbol_store_keys: BTreeSet<SymbolStoreKey>,
+}
+impl Default for Scanner {
+    fn default() -> Self {
+        Self {
+            cache: None,
+            max_in_memory_size: None,
+            demangle: false,
+            inf_names: None,
+            plugins: PluginRegistry::default(),
+            output_format: OutputFormat::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            api_docs: None,
+            filter: None,
+            wow64_pairs: None,
+            kernel_namespace: None,
+            time_limit: None,
+            summary: ScanSummary::default(),
+            ancestor_digests: Vec::new(),
+            nesting_dependent_results: 0,
+            symbol_store_keys: BTreeSet::new(),
+        }
+    }
+}
+impl Scanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Outputs the header row, if the output format has one.
+    pub fn output_header(&self) {
+        if self.wow64_pairs.is_some() {
+            return;
+        }
+        if self.output_format == OutputFormat::Csv {
+            let mut columns = CSV_COLUMNS.to_vec();
+            if self.api_docs.is_some() {
+                columns.push(CSV_DOCUMENTATION_COLUMN);
+            }
+            println!("{}", columns.join(","));
+        }
+    }
+
+    /// Returns whether the file at the given path (on disk or within a container) is stored in a
+    /// symbol-server layout under the same name and index as a file scanned before, in which case
+    /// it is the same build and need not be scanned again.
+    ///
+    /// Files are only known once they have been passed to
+    /// [`remember_symbol_store_file`](Self::remember_symbol_store_file).
+    pub fn is_symbol_store_duplicate(&mut self, path: &Path) -> bool {
+        let Some(key) = SymbolStoreKey::from_path(path) else {
+            return false;
+        };
+        if !self.symbol_store_keys.contains(&key) {
+            return false;
+        }
+        debug!("{} is a build that has already been scanned", path.display());
+        self.summary.symbol_store_duplicates += 1;
+        true
+    }
+
+    /// Remembers that the file at the given path has been read, so that further copies of the same
+    /// build in a symbol-server layout are skipped.
+    ///
+    /// This is only called once the file has been read successfully; if the first copy cannot be
+    /// read, the next one is scanned instead.
+    pub fn remember_symbol_store_file(&mut self, path: &Path) {
+        if let Some(key) = SymbolStoreKey::from_path(path) {
+            self.symbol_store_keys.insert(key);
+        }
+    }
+
+    /// Scans the given file data recursively and outputs all the symbols exported by it or by the
+    /// files contained within it.
+    ///
+    /// The symbols are output sorted by path, then by ordinal, then by name.
+    pub fn scan_file(&mut self, path_sequence: &PathSequence, data: Vec<u8>) {
+        let (found_symbols, timed_out) = {
+            let _deadline = deadline::set_time_limit(self.time_limit);
+            let found_symbols = self.collect_symbols(path_sequence, Cow::Owned(data));
+            (found_symbols, deadline::exceeded())
+        };
+        self.output_found_symbols(path_sequence, found_symbols, timed_out);
+    }
+
+    /// Scans the files of a container that is not stored as a single file (e.g. a set of disk
+    /// images) like [`scan_file`](Self::scan_file) scans those of a container file.
+    pub fn scan_container(&mut self, path_sequence: &PathSequence, container: &dyn MultiFileContainer) {
+        let (found_symbols, timed_out) = {
+            let _deadline = deadline::set_time_limit(self.time_limit);
+            self.summary.files_visited += 1;
+            let mut spilled_files = Vec::new();
+            let mut found_symbols = self.collect_container_symbols(path_sequence, container, &mut spilled_files);
+            found_symbols.extend(self.collect_spilled_symbols(spilled_files));
+            (found_symbols, deadline::exceeded())
+        };
+        self.output_found_symbols(path_sequence, found_symbols, timed_out);
+    }
+
+    fn output_found_symbols(&mut self, path_sequence: &PathSequence, mut found_symbols: Vec<FoundSymbol>, timed_out: bool) {
+        if timed_out {
+            let limit = self.time_limit.unwrap();
+            warn!(path = ?path_sequence, ?limit, "time limit exceeded; the results for this file are incomplete");
+            self.summary.incomplete_files += 1;
+            found_symbols.push(FoundSymbol::remark_only(format!("incompletely scanned: time limit of {:?} exceeded", limit)));
+        }
+        found_symbols.sort_by(|a, b| a.sub_path.cmp(&b.sub_path)
+            .then_with(|| a.symbol.as_ref().and_then(|s| s.ordinal()).cmp(&b.symbol.as_ref().and_then(|s| s.ordinal())))
+            .then_with(|| a.symbol.as_ref().and_then(|s| s.name()).cmp(&b.symbol.as_ref().and_then(|s| s.name())))
+        );
+
+        let symbol_count = found_symbols.iter()
+            .filter(|fs| fs.symbol.is_some())
+            .count();
+        self.summary.symbols += u64::try_from(symbol_count).unwrap();
+
+        let parent_parts: &[PathBuf] = path_sequence.as_ref();
+        for mut found_symbol in found_symbols {
+            let mut full_path = parent_parts.to_vec();
+            full_path.extend(found_symbol.sub_path.iter().cloned());
+            if let Some(inf_names) = self.inf_names.as_ref() {
+                inf_names.rename(&mut full_path);
+            }
+            let dropped_by_kernel_namespace = self.kernel_namespace.as_mut()
+                .is_some_and(|kn| !kn.unify(&mut full_path, &mut found_symbol));
+            if dropped_by_kernel_namespace {
+                continue;
+            }
+            if let Some(filter) = self.filter.as_ref() {
+                match apply_filter(filter, &mut full_path, &mut found_symbol) {
+                    Ok(true) => {},
+                    Ok(false) => continue,
+                    Err(e) => {
+                        error!(path = ?full_path, "failed to evaluate filter script: {}", e);
+                        self.summary.record_error(ErrorCategory::FilterScript, &full_path, e);
+                        continue;
+                    },
+                }
+            }
+            if let Some(wow64_pairs) = self.wow64_pairs.as_mut() {
+                wow64_pairs.record(&full_path, &found_symbol);
+                continue;
+            }
+            let documentation = self.api_docs.as_ref().map(|api_docs| {
+                let name = found_symbol.symbol.as_ref().and_then(|s| s.name());
+                name.and_then(|n| api_docs.lookup(&exporting_file_name(&full_path), n))
+                    .unwrap_or("")
+            });
+            match self.output_format {
+                OutputFormat::Tsv => output_symbol(&full_path, found_symbol.symbol.as_ref(), found_symbol.remark.as_deref(), self.demangle, documentation),
+                OutputFormat::Csv => println!("{}", csv_line(&full_path, &found_symbol, documentation)),
+            }
+        }
+    }
+
+    fn collect_symbols(&mut self, parent_path_sequence: &PathSequence, data: Cow<'_, [u8]>) -> Vec<FoundSymbol> {
+        let digest = hashing::sha256(&data);
+        self.summary.files_visited += 1;
+
+        // don't descend forever; the results depend on the nesting, so they are not cached
+        let depth = self.ancestor_digests.len();
+        if self.ancestor_digests.contains(&digest) {
+            warn!(path = ?parent_path_sequence, depth, "file contains itself; not descending into it again");
+            self.summary.incomplete_files += 1;
+            self.nesting_dependent_results += 1;
+            return vec![FoundSymbol::remark_only("not scanned: the file contains itself".to_owned())];
+        }
+        if depth > self.max_depth {
+            warn!(path = ?parent_path_sequence, depth, max_depth = self.max_depth, "file is nested too deeply; not scanning it");
+            self.summary.incomplete_files += 1;
+            self.nesting_dependent_results += 1;
+            return vec![FoundSymbol::remark_only(format!("not scanned: nested more than {} levels deep", self.max_depth))];
+        }
+
+        // plugins may interpret files differently from the built-in detectors
+        let cache_key = self.plugins.cache_key(&digest);
+        if let Some(cache) = self.cache.as_ref()
+            && let Some(found_symbols) = cache.digest_to_symbols.get(&cache_key)
+        {
+            debug!("{:?} has been scanned before", parent_path_sequence);
+            self.summary.files_from_cache += 1;
+            return found_symbols.clone();
+        }
+
+        let nesting_dependent_before = self.nesting_dependent_results;
+        self.ancestor_digests.push(digest);
+        let found_symbols = self.interpret_and_collect_symbols(parent_path_sequence, data);
+        self.ancestor_digests.pop();
+
+        // incomplete results must not be reused, and neither may results that would differ if the
+        // file were nested elsewhere
+        if let Some(cache) = self.cache.as_mut()
+            && !deadline::exceeded()
+            && self.nesting_dependent_results == nesting_dependent_before
+        {
+            cache.digest_to_symbols.insert(cache_key, found_symbols.clone());
+        }
+
+        found_symbols
+    }
+
+    fn interpret_and_collect_symbols(&mut self, parent_path_sequence: &PathSequence, data: Cow<'_, [u8]>) -> Vec<FoundSymbol> {
+        let mut spilled_files = Vec::new();
+        let mut found_symbols = self.collect_symbols_in_memory(parent_path_sequence, &data, &mut spilled_files);
+
+        // let go of the data (and everything borrowing it) before reading the spilled files back
+        drop(data);
+
+        found_symbols.extend(self.collect_spilled_symbols(spilled_files));
+        found_symbols
+    }
+
+    /// Collects the symbols of the files that have been spilled into temporary files.
+    fn collect_spilled_symbols(&mut self, spilled_files: Vec<(PathBuf, PathSequence, File)>) -> Vec<FoundSymbol> {
+        let mut found_symbols = Vec::new();
+        for (file, child_path_sequence, mut spilled) in spilled_files {
+            if deadline::exceeded() {
+                break;
+            }
+            let mut file_data = Vec::new();
+            let read_result = spilled.seek(SeekFrom::Start(0))
+                .and_then(|_| spilled.read_to_end(&mut file_data));
+            if let Err(e) = read_result {
+                error!("failed to read back {:?} from a temporary file: {}", child_path_sequence, e);
+                self.summary.record_error(ErrorCategory::TemporaryFile, child_path_sequence.as_ref(), e);
+                continue;
+            }
+            drop(spilled);
+
+            for mut child_symbol in self.collect_symbols(&child_path_sequence, Cow::Owned(file_data)) {
+                child_symbol.sub_path.insert(0, file.clone());
+                found_symbols.push(child_symbol);
+            }
+        }
+
+        found_symbols
+    }
+
+    /// Collects the symbols of the file and those of its children that are small enough to be
+    /// scanned while the data is held in memory; the others are spilled into `spilled_files`.
+    fn collect_symbols_in_memory(
+        &mut self,
+        parent_path_sequence: &PathSequence,
+        data: &[u8],
+        spilled_files: &mut Vec<(PathBuf, PathSequence, File)>,
+    ) -> Vec<FoundSymbol> {
+        info!("interpreting {:?}", parent_path_sequence);
+        // children borrow from the data where the format allows it
+        let mut interpreted = interpret_file(data);
+        if matches!(interpreted, Ok(IdentifiedFile::Unidentified) | Err(_))
+            && let Some(identified) = self.plugins.interpret(data)
+        {
+            // a built-in detector may also have mistaken the plugin's format for its own
+            interpreted = Ok(identified);
+        }
+
+        let mut found_symbols = Vec::new();
+        match interpreted {
+            Ok(IdentifiedFile::MultiFileContainer(mfc)) => {
+                found_symbols = self.collect_container_symbols(parent_path_sequence, mfc.as_ref(), spilled_files);
+            },
+            Ok(IdentifiedFile::SingleFileContainer(sfc)) => {
+                self.summary.record_container(sfc.format_name());
+
+                let mut child_path_sequence = parent_path_sequence.clone();
+                child_path_sequence.push(PathBuf::new());
+
+                let file_data = match sfc.entry().into_data() {
+                    Ok(fd) => fd,
+                    Err(e) => {
+                        error!("failed to obtain {:?}: {}", child_path_sequence, e);
+                        self.summary.record_error(ErrorCategory::ExtractFile, child_path_sequence.as_ref(), e);
+                        return found_symbols;
+                    },
+                };
+
+                for mut child_symbol in self.collect_symbols(&child_path_sequence, file_data) {
+                    child_symbol.sub_path.insert(0, PathBuf::new());
+                    found_symbols.push(child_symbol);
+                }
+            },
+            Ok(IdentifiedFile::SymbolExporter(symex)) => {
+                self.summary.exporters += 1;
+
+                let symbols = match symex.read_symbols() {
+                    Ok(s) => s,
+                    Err(e) => {
+                        error!("failed to read symbols from {:?}: {}", parent_path_sequence, e);
+                        self.summary.record_error(ErrorCategory::ReadSymbols, parent_path_sequence.as_ref(), e);
+                        return found_symbols;
+                    },
+                };
+                if let Some(warning) = symex.warning() {
+                    warn!("{:?}: {}", parent_path_sequence, warning);
+                }
+                let remark = symex.remark()
+                    .or_else(|| crate::mun::split_off_remark(parent_path_sequence.as_ref()));
+                let architecture = symex.architecture();
+                let timestamp = symex.timestamp();
+                let toolchain = symex.toolchain();
+                if symbols.is_empty() && remark.is_some() {
+                    // don't lose the remark
+                    found_symbols.push(FoundSymbol {
+                        sub_path: Vec::with_capacity(0),
+                        symbol: None,
+                        remark,
+                        address: None,
+                        section_class: None,
+                        forwarder: None,
+                        architecture,
+                        timestamp,
+                        toolchain,
+                    });
+                    return found_symbols;
+                }
+                for symbol in symbols {
+                    found_symbols.push(FoundSymbol {
+                        sub_path: Vec::with_capacity(0),
+                        address: symex.address(&symbol),
+                        section_class: symex.section_class(&symbol),
+                        forwarder: symex.forwarder(&symbol),
+                        symbol: Some(symbol),
+                        remark: remark.clone(),
+                        architecture: architecture.clone(),
+                        timestamp: timestamp.clone(),
+                        toolchain: toolchain.clone(),
+                    });
+                }
+            },
+            Ok(IdentifiedFile::Metadata(metadata)) => {
+                // doesn't export anything itself
+                debug!("{:?} is a {:?} file", parent_path_sequence, metadata.kind);
+            },
+            Ok(IdentifiedFile::Unidentified) => {
+                // guess this one's not that interesting
+            },
+            Err(e) => {
+                error!("failed to interpret file at {:?}: {}", parent_path_sequence, e);
+                self.summary.record_error(ErrorCategory::Interpret, parent_path_sequence.as_ref(), e);
+            },
+        }
+        found_symbols
+    }
+
+    /// Collects the symbols of the files in a container, spilling those that are too large to be
+    /// held in memory along with the container into `spilled_files`.
+    fn collect_container_symbols(
+        &mut self,
+        parent_path_sequence: &PathSequence,
+        container: &dyn MultiFileContainer,
+        spilled_files: &mut Vec<(PathBuf, PathSequence, File)>,
+    ) -> Vec<FoundSymbol> {
+        let mut found_symbols = Vec::new();
+        self.summary.record_container(container.format_name());
+        if let Some(remark) = container.remark() {
+            found_symbols.push(FoundSymbol::remark_only(remark));
+        }
+
+        // scan each child file
+        let entries = match container.entries() {
+            Ok(es) => es,
+            Err(e) => {
+                error!("failed to list files of {:?}: {}", parent_path_sequence, e);
+                self.summary.record_error(ErrorCategory::ListFiles, parent_path_sequence.as_ref(), e);
+                return found_symbols;
+            },
+        };
+        for entry in entries {
+            if deadline::exceeded() {
+                // scan_file reports this
+                break;
+            }
+
+            if self.is_symbol_store_duplicate(&entry.path) {
+                continue;
+            }
+
+            let file = entry.path.clone();
+            let mut child_path_sequence = parent_path_sequence.clone();
+            child_path_sequence.push(&file);
+
+            let file_data = match entry.into_data() {
+                Ok(fd) => {
+                    self.remember_symbol_store_file(&file);
+                    if fd.len() < 24 {
+                        debug!("{}", DisplayBytesSlice::from(fd.as_ref()));
+                    } else {
+                        debug!("{}...{}", DisplayBytesSlice::from(&fd[..16]), DisplayBytesSlice::from(&fd[fd.len()-16..]));
+                    }
+                    fd
+                },
+                Err(e) => {
+                    error!("failed to obtain {:?}: {}", child_path_sequence, e);
+                    self.summary.record_error(ErrorCategory::ExtractFile, child_path_sequence.as_ref(), e);
+                    continue;
+                },
+            };
+
+            if let Some(max_size) = self.max_in_memory_size {
+                if file_data.len() > max_size {
+                    // scan it later, once the container is out of memory
+                    match spill(&file_data) {
+                        Ok(spilled) => {
+                            debug!("spilled {:?} ({} bytes) to a temporary file", child_path_sequence, file_data.len());
+                            spilled_files.push((file, child_path_sequence, spilled));
+                            continue;
+                        },
+                        Err(e) => {
+                            // scan it now, then
+                            error!("failed to spill {:?} to a temporary file: {}", child_path_sequence, e);
+                            self.summary.record_error(ErrorCategory::TemporaryFile, child_path_sequence.as_ref(), e);
+                        },
+                    }
+                }
+            }
+
+            for mut child_symbol in self.collect_symbols(&child_path_sequence, file_data) {
+                child_symbol.sub_path.insert(0, file.clone());
+                found_symbols.push(child_symbol);
+            }
+        }
+        found_symbols
+    }
+}
+
+
+/// Passes a symbol through the filter script, replacing the path and the symbol with the values
+/// assigned by the script.
+///
+/// Returns `false` if the script drops the symbol.
+fn apply_filter(filter: &ExportFilter, full_path: &mut Vec<PathBuf>, found_symbol: &mut FoundSymbol) -> Result<bool, filtexp::Error> {
+    let path_parts: Vec<String> = full_path.iter()
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect();
+    let ordinal = found_symbol.symbol.as_ref().and_then(|s| s.ordinal());
+    let name = found_symbol.symbol.as_ref().and_then(|s| s.name());
+    let Some(filtered) = filter.filter(&path_parts, ordinal, name)? else {
+        return Ok(false)
+    };
+
+    *full_path = vec![PathBuf::from(filtered.filename)];
+    if filtered.ordinal != ordinal || filtered.name.as_deref() != name {
+        found_symbol.symbol = match (filtered.name, filtered.ordinal) {
+            (Some(name), Some(ordinal)) => Some(Symbol::ByNameAndOrdinal { name, ordinal }),
+            (Some(name), None) => match found_symbol.symbol {
+                Some(Symbol::WinRt { .. }) => Some(Symbol::WinRt { name }),
+                Some(Symbol::ComServer { .. }) => Some(Symbol::ComServer { name }),
+                _ => Some(Symbol::ByName { name }),
+            },
+            (None, Some(ordinal)) => Some(Symbol::ByOrdinal { ordinal }),
+            (None, None) => None,
+        };
+    }
+    Ok(true)
+}
+
+
+/// Outputs a symbol as tab-separated columns: path, ordinal, name, the undecorated name (if
+/// `demangle` is set), the documentation (if given, even if empty) and the remark (if there is
+/// one).
+///
+/// If there is no symbol, the ordinal and name columns are empty.
+fn output_symbol(path_sequence: &[PathBuf], symbol: Option<&Symbol>, remark: Option<&str>, demangle: bool, documentation: Option<&str>) {
+    let ordinal_string = symbol.and_then(|s| s.ordinal())
+        .map(|o| o.to_string())
+        .unwrap_or_default();
+    // WinRT types and COM servers are told apart from exports by prefixing their kind
+    let kind_prefix = symbol.map(|s| s.kind())
+        .filter(|k| *k != "export")
+        .map(|k| format!("{}:", k))
+        .unwrap_or_default();
+    let name_string = symbol.and_then(|s| s.name())
+        .map(escape_str)
+        .unwrap_or_default();
+    let mut line = format!("{:?}\t{}\t{}{}", path_sequence, ordinal_string, kind_prefix, name_string);
+    if demangle {
+        let undecorated_string = symbol.and_then(|s| s.name())
+            .map(|n| undecorate(n).unwrap_or_else(|| n.to_owned()))
+            .map(|n| escape_str(&n))
+            .unwrap_or_default();
+        line.push('\t');
+        line.push_str(&undecorated_string);
+    }
+    if let Some(documentation) = documentation {
+        line.push('\t');
+        line.push_str(documentation);
+    }
+    if let Some(remark) = remark {
+        line.push('\t');
+        line.push_str(remark);
+    }
+    println!("{}", line);
+}
+
+
+/// The columns of the CSV output.
+const CSV_COLUMNS: [&str; 14] = [
+    "path_sequence", "container_chain", "file_name", "ordinal", "name", "demangled_name", "rva",
+    "architecture", "timestamp", "remark", "symbol_store_index", "toolchain", "section_class",
+    "symbol_kind",
+];
+
+/// The column of the CSV output that is appended if documentation has been provided.
+const CSV_DOCUMENTATION_COLUMN: &str = "documentation";
+
+/// Returns the name of the exporting file or, if it is the unnamed content of a single-file
+/// container, that of the container.
+fn exporting_file_name(path_sequence: &[PathBuf]) -> String {
+    path_sequence.iter()
+        .rev()
+        .find(|p| !p.as_os_str().is_empty())
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Formats a symbol as a line of CSV with the columns in [`CSV_COLUMNS`], followed by the
+/// documentation column if `documentation` is given.
+///
+/// The parts of the path sequence and of the container chain (the path sequence without the
+/// exporting file) are separated by `|`. The file name is that of the exporting file or, if it is
+/// the unnamed content of a single-file container, that of the container. Names are output
+/// verbatim and the demangled name is always filled in. The symbol-server index is taken from the
+/// path of the exporting file (or its container) if it is stored in a symbol-server layout.
+fn csv_line(path_sequence: &[PathBuf], found_symbol: &FoundSymbol, documentation: Option<&str>) -> String {
+    let join_parts = |parts: &[PathBuf]| parts.iter()
+        .map(|p| p.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("|");
+    let full_path = join_parts(path_sequence);
+    let container_chain = match path_sequence.split_last() {
+        Some((_, containers)) => join_parts(containers),
+        None => String::new(),
+    };
+    let file_name = exporting_file_name(path_sequence);
+
+    let symbol = found_symbol.symbol.as_ref();
+    let ordinal = symbol.and_then(|s| s.ordinal())
+        .map(|o| o.to_string())
+        .unwrap_or_default();
+    let name = symbol.and_then(|s| s.name())
+        .unwrap_or_default();
+    let demangled_name = symbol.and_then(|s| s.name())
+        .map(|n| undecorate(n).unwrap_or_else(|| n.to_owned()))
+        .unwrap_or_default();
+    let rva = found_symbol.address
+        .map(|a| format!("0x{:08X}", a))
+        .unwrap_or_default();
+    let symbol_store_index = path_sequence.iter()
+        .rev()
+        .find(|p| !p.as_os_str().is_empty())
+        .and_then(|p| SymbolStoreKey::from_path(p))
+        .map(|k| k.index)
+        .unwrap_or_default();
+    let section_class = found_symbol.section_class
+        .map(|c| c.to_string())
+        .unwrap_or_default();
+    let symbol_kind = symbol.map(|s| s.kind())
+        .unwrap_or_default();
+
+    let mut fields = vec![
+        &full_path,
+        &container_chain,
+        &file_name,
+        &ordinal,
+        name,
+        &demangled_name,
+        &rva,
+        found_symbol.architecture.as_deref().unwrap_or(""),
+        found_symbol.timestamp.as_deref().unwrap_or(""),
+        found_symbol.remark.as_deref().unwrap_or(""),
+        &symbol_store_index,
+        found_symbol.toolchain.as_deref().unwrap_or(""),
+        &section_class,
+        symbol_kind,
+    ];
+    fields.extend(documentation);
+    fields.iter()
+        .map(|f| csv_field(f))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break, doubling any quotes within.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+
+/// Writes the data into an anonymous temporary file.
+fn spill(data: &[u8]) -> Result<File, io::Error> {
+    let mut file = tempfile::tempfile()?;
+    file.write_all(data)?;
+    file.flush()?;
+    Ok(file)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+    use testgen::pe;
+    use std::io::Write;
+    use binms::pe::SectionClass;
+    use super::{apply_filter, compressed_name, csv_line, is_kernel_module, ErrorCategory, FoundSymbol, InfNames, KernelNamespace, ScanCache, ScanSummary, Scanner, SymbolStoreKey, Wow64Pairs, Wow64Side, SCAN_CACHE_VERSION};
+    use binstruct::deadline;
+    use crate::exit_status::ExitStatus;
+    use crate::hashing;
+    use crate::data_mgmt::{CopiedFile, Error, IdentifiedFile, Metadata, MultiFileContainer, PathSequence, Symbol};
+    use crate::plugin::FormatPlugin;
+    use crate::schema::{Document, DocumentKind};
+
+    /// Wraps data prefixed with `WRAP` or `MDMPWRAP` into a container holding the rest of the data
+    /// and data prefixed with `SELF` into a container holding the data itself.
+    #[derive(Debug)]
+    struct WrapperPlugin;
+    impl FormatPlugin for WrapperPlugin {
+        fn name(&self) -> String {
+            "wrapper".to_owned()
+        }
+
+        fn interpret(&self, data: &[u8]) -> Result<Option<IdentifiedFile<'static>>, Error> {
+            let child = if let Some(rest) = data.strip_prefix(b"WRAP") {
+                rest.to_vec()
+            } else if let Some(rest) = data.strip_prefix(b"MDMPWRAP") {
+                // looks like a crash dump to the built-in detectors
+                rest.to_vec()
+            } else if data.starts_with(b"SELF") {
+                data.to_vec()
+            } else {
+                return Ok(None);
+            };
+            Ok(Some(IdentifiedFile::MultiFileContainer(Box::new(Wrapped { child }))))
+        }
+    }
+
+    #[derive(Debug)]
+    struct Wrapped {
+        child: Vec<u8>,
+    }
+    impl MultiFileContainer for Wrapped {
+        fn format_name(&self) -> &str {
+            "wrapper"
+        }
+
+        fn list_files(&self) -> Result<Vec<PathBuf>, Error> {
+            Ok(vec![PathBuf::from("child")])
+        }
+
+        fn read_file(&self, _file_path: &Path) -> Result<Cow<'_, [u8]>, Error> {
+            Ok(Cow::Borrowed(&self.child))
+        }
+    }
+
+    fn wrapper_scanner() -> Scanner {
+        let mut scanner = Scanner::new();
+        scanner.plugins.register(Box::new(WrapperPlugin));
+        scanner
+    }
+
+    fn path_sequence() -> PathSequence {
+        PathSequence::from(vec![PathBuf::from("wrapped.bin")])
+    }
+
+    #[test]
+    fn test_csv_line() {
+        let path = vec![PathBuf::from("disk01.img"), PathBuf::from("SYSTEM/USER.EX_"), PathBuf::new()];
+        let found_symbol = FoundSymbol {
+            sub_path: Vec::new(),
+            symbol: Some(Symbol::ByNameAndOrdinal { name: "Say \"Hi\", World".to_owned(), ordinal: 7 }),
+            remark: None,
+            address: Some(0x1234),
+            section_class: Some(SectionClass::Code),
+            forwarder: None,
+            architecture: Some("I386".to_owned()),
+            timestamp: Some("1995-08-24T00:00:00Z".to_owned()),
+            toolchain: None,
+        };
+        assert_eq!(
+            csv_line(&path, &found_symbol, None),
+            "disk01.img|SYSTEM/USER.EX_|,disk01.img|SYSTEM/USER.EX_,USER.EX_,7,\"Say \"\"Hi\"\", World\",\"Say \"\"Hi\"\", World\",0x00001234,I386,1995-08-24T00:00:00Z,,,,code,export",
+        );
+        assert!(csv_line(&path, &found_symbol, Some("https://example.com/user")).ends_with(",I386,1995-08-24T00:00:00Z,,,,code,export,https://example.com/user"));
+
+        let stored_path = vec![PathBuf::from("store/user32.dll/2A425E19A5000/user32.dl_"), PathBuf::new()];
+        assert!(csv_line(&stored_path, &found_symbol, None).ends_with(",I386,1995-08-24T00:00:00Z,,2A425E19A5000,,code,export"));
+
+        let winrt_symbol = FoundSymbol {
+            symbol: Some(Symbol::WinRt { name: "Windows.Foundation.Uri.get_Host".to_owned() }),
+            section_class: None,
+            ..found_symbol.clone()
+        };
+        assert!(csv_line(&path, &winrt_symbol, None).ends_with(",Windows.Foundation.Uri.get_Host,Windows.Foundation.Uri.get_Host,0x00001234,I386,1995-08-24T00:00:00Z,,,,,winrt"));
+        let remark = FoundSymbol::remark_only("not scanned".to_owned());
+        assert!(csv_line(&path, &remark, None).ends_with(",,,,,,,not scanned,,,,"));
+    }
+
+    #[test]
+    fn test_compressed_name() {
+        assert_eq!(compressed_name("user.exe"), "user.ex_");
+        assert_eq!(compressed_name("readme.c"), "readme.c_");
+        assert_eq!(compressed_name("setup.in"), "setup.in_");
+        assert_eq!(compressed_name("makefile"), "makefile._");
+        assert_eq!(compressed_name("archive.tar.gz"), "archive.tar.gz_");
+        assert_eq!(compressed_name("caf\u{E9}.\u{E9}\u{E9}"), "caf\u{E9}.\u{E9}\u{E9}_");
+        assert_eq!(compressed_name("caf\u{E9}.d\u{E9}j"), "caf\u{E9}.d\u{E9}_");
+    }
+
+    #[test]
+    fn test_symbol_store_key() {
+        let key = SymbolStoreKey::from_path(Path::new("symbols/User32.dll/5A1B2C3D9F000/user32.dll")).unwrap();
+        assert_eq!(key.file_name, "user32.dll");
+        assert_eq!(key.index, "5A1B2C3D9F000");
+        let compressed = SymbolStoreKey::from_path(Path::new("USER32.DLL/5a1b2c3d9f000/user32.dl_")).unwrap();
+        assert_eq!(compressed, key);
+
+        // not an index, or not the same name
+        assert_eq!(SymbolStoreKey::from_path(Path::new("user32.dll/system32/user32.dll")), None);
+        assert_eq!(SymbolStoreKey::from_path(Path::new("user32.dll/5A1B2C3D/user32.dll")), None);
+        assert_eq!(SymbolStoreKey::from_path(Path::new("user32.dll/5A1B2C3D9F000/gdi32.dll")), None);
+        assert_eq!(SymbolStoreKey::from_path(Path::new("5A1B2C3D9F000/user32.dll")), None);
+    }
+
+    #[test]
+    fn test_symbol_store_duplicates() {
+        let first = Path::new("store/user32.dll/5A1B2C3D9F000/user32.dll");
+        let second = Path::new("other-store/user32.dll/5a1b2c3d9f000/user32.dl_");
+        let other_build = Path::new("store/user32.dll/5A1B2C3DA0000/user32.dll");
+        let mut scanner = Scanner::new();
+
+        // a copy that could not be read does not count
+        assert!(!scanner.is_symbol_store_duplicate(first));
+        assert!(!scanner.is_symbol_store_duplicate(second));
+        scanner.remember_symbol_store_file(second);
+
+        assert!(scanner.is_symbol_store_duplicate(first));
+        assert!(!scanner.is_symbol_store_duplicate(other_build));
+        assert!(!scanner.is_symbol_store_duplicate(Path::new("system32/user32.dll")));
+        assert_eq!(scanner.summary.symbol_store_duplicates, 1);
+    }
+
+    #[test]
+    fn test_inf_names_rename() {
+        let metadata = Metadata {
+            copied_files: vec![CopiedFile { destination: "driver.sys".to_owned(), source: "DRIVER.SYS".to_owned() }],
+            source_disks_files: vec!["Helper.dll".to_owned()],
+            ..Metadata::default()
+        };
+        let mut inf_names = InfNames::new();
+        inf_names.register(Path::new("media"), &metadata);
+        assert_eq!(inf_names.resolve(Path::new("media"), "driver.sy_"), Some("driver.sys"));
+        assert_eq!(inf_names.resolve(Path::new("media"), "HELPER.DL_"), Some("Helper.dll"));
+        assert_eq!(inf_names.resolve(Path::new("elsewhere"), "driver.sy_"), None);
+
+        let mut path = vec![PathBuf::from("media/DRIVER.SY_"), PathBuf::new()];
+        inf_names.rename(&mut path);
+        assert_eq!(path, vec![PathBuf::from("media/DRIVER.SY_"), PathBuf::from("driver.sys")]);
+
+        // a file of the same name nested within a container is not described by the INF file
+        let mut path = vec![PathBuf::from("media/disk.iso"), PathBuf::from("driver.sy_"), PathBuf::new()];
+        inf_names.rename(&mut path);
+        assert_eq!(path, vec![PathBuf::from("media/disk.iso"), PathBuf::from("driver.sy_"), PathBuf::new()]);
+
+        // unknown files keep their empty part
+        let mut path = vec![PathBuf::from("media/other.dl_"), PathBuf::new()];
+        inf_names.rename(&mut path);
+        assert_eq!(path, vec![PathBuf::from("media/other.dl_"), PathBuf::new()]);
+    }
+
+    #[test]
+    fn test_apply_filter() {
+        let mut script = tempfile::NamedTempFile::new().unwrap();
+        script.write_all(br#"
+            let filename = join("/", path_parts).to_lower();
+            if ordinal_opt == 2 { ordinal_opt = (); }
+            name_opt != "Hidden"
+        "#).unwrap();
+        let filter = filtexp::ExportFilter::compile_file(script.path()).unwrap();
+
+        let filter_symbol = |symbol: Symbol| {
+            let mut full_path = vec![PathBuf::from("DISK1.IMG"), PathBuf::from("SYSTEM/USER.EXE")];
+            let mut found_symbol = FoundSymbol::remark_only(String::new());
+            found_symbol.symbol = Some(symbol);
+            apply_filter(&filter, &mut full_path, &mut found_symbol).unwrap()
+                .then(|| (full_path, found_symbol.symbol.unwrap()))
+        };
+
+        assert_eq!(
+            filter_symbol(Symbol::ByNameAndOrdinal { name: "Shown".to_owned(), ordinal: 1 }),
+            Some((vec![PathBuf::from("disk1.img/system/user.exe")], Symbol::ByNameAndOrdinal { name: "Shown".to_owned(), ordinal: 1 })),
+        );
+        assert_eq!(
+            filter_symbol(Symbol::ByNameAndOrdinal { name: "Unnumbered".to_owned(), ordinal: 2 }),
+            Some((vec![PathBuf::from("disk1.img/system/user.exe")], Symbol::ByName { name: "Unnumbered".to_owned() })),
+        );
+        assert_eq!(filter_symbol(Symbol::ByNameAndOrdinal { name: "Hidden".to_owned(), ordinal: 3 }), None);
+    }
+
+    #[test]
+    fn test_wow64_pairs() {
+        let found_symbol = |name: &str, architecture: &str| FoundSymbol {
+            sub_path: Vec::new(),
+            symbol: Some(Symbol::ByNameAndOrdinal { name: name.to_owned(), ordinal: 1 }),
+            remark: None,
+            address: None,
+            section_class: None,
+            forwarder: None,
+            architecture: Some(architecture.to_owned()),
+            timestamp: None,
+            toolchain: None,
+        };
+        let native = vec![PathBuf::from("Windows/System32/KERNEL32.DLL")];
+        let wow64 = vec![PathBuf::from("windows/SysWOW64/kernel32.dll")];
+        let other = vec![PathBuf::from("Windows/kernel32.dll")];
+
+        let mut pairs = Wow64Pairs::new();
+        pairs.record(&native, &found_symbol("Both", "Amd64"));
+        pairs.record(&native, &found_symbol("NativeOnly", "Amd64"));
+        pairs.record(&wow64, &found_symbol("Both", "I386"));
+        pairs.record(&wow64, &found_symbol("Wow64Only", "I386"));
+        pairs.record(&other, &found_symbol("Unpaired", "I386"));
+        assert_eq!(
+            pairs.differences(),
+            [
+                (native.as_slice(), Wow64Side::System32, "NativeOnly".to_owned(), "Amd64"),
+                (wow64.as_slice(), Wow64Side::SysWow64, "Wow64Only".to_owned(), "I386"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_kernel_namespace() {
+        let found_symbol = |name: &str, ordinal: u32, forwarder: Option<&str>| {
+            let mut found_symbol = FoundSymbol::remark_only(String::new());
+            found_symbol.remark = None;
+            found_symbol.symbol = Some(Symbol::ByNameAndOrdinal { name: name.to_owned(), ordinal });
+            found_symbol.address = forwarder.is_none().then_some(0x1000);
+            found_symbol.forwarder = forwarder.map(|f| f.to_owned());
+            found_symbol
+        };
+        let mut namespace = KernelNamespace::new();
+        let mut unify = |path: &[&str], found_symbol: FoundSymbol| {
+            let mut full_path: Vec<PathBuf> = path.iter().map(PathBuf::from).collect();
+            let mut found_symbol = found_symbol;
+            namespace.unify(&mut full_path, &mut found_symbol)
+                .then(|| (full_path, found_symbol.symbol.unwrap(), found_symbol.address))
+        };
+        let kernel = |name: &str| Some((vec![PathBuf::from("win/kernel")], Symbol::ByName { name: name.to_owned() }, None));
+
+        assert_eq!(unify(&["win/ntoskrnl.exe"], found_symbol("KeBugCheck", 1, None)), kernel("KeBugCheck"));
+        assert_eq!(unify(&["win/ntkrnlpa.exe"], found_symbol("KeBugCheck", 2, None)), None);
+        assert_eq!(unify(&["win/ntkrnlpa.exe"], found_symbol("KePaeOnly", 3, None)), kernel("KePaeOnly"));
+        assert_eq!(unify(&["win/HAL.DL_", ""], found_symbol("KeGetCurrentIrql", 1, Some("NTOSKRNL.KeGetCurrentIrql"))), None);
+        assert_eq!(unify(&["win/hal.dll"], found_symbol("HalDisplayString", 2, None)), kernel("HalDisplayString"));
+        assert_eq!(unify(&["win/hal.dll"], found_symbol("SomethingElse", 3, Some("OTHER.SomethingElse"))), kernel("SomethingElse"));
+        assert_eq!(
+            unify(&["win/kernel32.dll"], found_symbol("Sleep", 1, None)),
+            Some((vec![PathBuf::from("win/kernel32.dll")], Symbol::ByNameAndOrdinal { name: "Sleep".to_owned(), ordinal: 1 }, Some(0x1000))),
+        );
+    }
+
+    #[test]
+    fn test_is_kernel_module() {
+        for name in ["NTOSKRNL.EXE", "ntkrnlpa.ex_", "NTOSKRNL", "hal.dll", "HALMACPI.DL_", "halaacpi", "halmps.dll"] {
+            assert!(is_kernel_module(name), "{:?} is not a kernel module", name);
+        }
+        for name in ["ntoskrnl.dll", "hal.exe", "halextintcuartdma.dll", "halftone.dll", "HALPAL.DLL", "kernel32.dll"] {
+            assert!(!is_kernel_module(name), "{:?} is a kernel module", name);
+        }
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let mut library = pe::PortableExecutable::new("SYNTH.DLL");
+        library.exports.push(pe::Export { ordinal: 1, name: Some("Alpha".to_owned()), target: pe::ExportTarget::Code { rva: pe::TEXT_RVA } });
+        let mut data = b"WRAPWRAP".to_vec();
+        data.extend_from_slice(&library.to_bytes());
+
+        let mut scanner = wrapper_scanner();
+        scanner.max_depth = 2;
+        let found = scanner.collect_symbols(&path_sequence(), Cow::Borrowed(&data));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].symbol, Some(Symbol::ByNameAndOrdinal { name: "Alpha".to_owned(), ordinal: 1 }));
+
+        scanner.max_depth = 1;
+        let found = scanner.collect_symbols(&path_sequence(), Cow::Borrowed(&data));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].sub_path, vec![PathBuf::from("child"), PathBuf::from("child")]);
+        assert_eq!(found[0].symbol, None);
+        assert!(found[0].remark.as_deref().unwrap().contains("nested"));
+
+        // both runs are counted
+        assert_eq!(scanner.summary.files_visited, 6);
+        assert_eq!(scanner.summary.containers_by_format.get("wrapper"), Some(&4));
+        assert_eq!(scanner.summary.exporters, 1);
+        assert_eq!(scanner.summary.incomplete_files, 1);
+        assert!(scanner.summary.errors_by_category.is_empty());
+    }
+
+    #[test]
+    fn test_cache_versions() {
+        let mut data = b"WRAP".to_vec();
+        data.extend_from_slice(&pe::PortableExecutable::new("SYNTH.DLL").to_bytes());
+        let mut scanner = wrapper_scanner();
+        scanner.cache = Some(ScanCache::default());
+        scanner.scan_file(&path_sequence(), data);
+        let cache = scanner.cache.unwrap();
+        assert!(cache.len() > 0);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        cache.store(&path).unwrap();
+        assert_eq!(ScanCache::load(&path).unwrap(), cache);
+
+        // caches of other versions are discarded, even if their entries cannot be read
+        let other_version = serde_json::json!({
+            "version": SCAN_CACHE_VERSION + 1,
+            "entries": { "not a digest": "not a list of symbols" },
+        });
+        std::fs::write(&path, other_version.to_string()).unwrap();
+        assert_eq!(ScanCache::load(&path).unwrap(), ScanCache::new());
+
+        // as are caches from before the format had a version
+        let unversioned = serde_json::json!({ hashing::to_hex(&[0; 32]): [] });
+        std::fs::write(&path, unversioned.to_string()).unwrap();
+        assert_eq!(ScanCache::load(&path).unwrap(), ScanCache::new());
+    }
+
+    #[test]
+    fn test_nesting_dependent_results_are_not_cached() {
+        let mut library = pe::PortableExecutable::new("SYNTH.DLL");
+        library.exports.push(pe::Export { ordinal: 1, name: Some("Alpha".to_owned()), target: pe::ExportTarget::Code { rva: pe::TEXT_RVA } });
+        let mut inner = b"WRAP".to_vec();
+        inner.extend_from_slice(&library.to_bytes());
+        let mut outer = b"WRAP".to_vec();
+        outer.extend_from_slice(&inner);
+
+        // the inner wrapper is cut off when nested within the outer one...
+        let mut scanner = wrapper_scanner();
+        scanner.cache = Some(ScanCache::default());
+        scanner.max_depth = 1;
+        let found = scanner.collect_symbols(&path_sequence(), Cow::Borrowed(&outer));
+        assert_eq!(found.len(), 1);
+        assert!(found[0].remark.as_deref().unwrap().contains("nested"));
+        assert!(scanner.cache.as_ref().unwrap().digest_to_symbols.is_empty());
+
+        // ...but not when scanned on its own
+        let found = scanner.collect_symbols(&path_sequence(), Cow::Borrowed(&inner));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].symbol, Some(Symbol::ByNameAndOrdinal { name: "Alpha".to_owned(), ordinal: 1 }));
+        assert_eq!(scanner.summary.files_from_cache, 0);
+
+        // files that contain themselves are not cached either
+        let found = scanner.collect_symbols(&path_sequence(), Cow::Borrowed(b"SELF"));
+        assert!(found[0].remark.as_deref().unwrap().contains("contains itself"));
+        let cached_digests = scanner.cache.as_ref().unwrap().digest_to_symbols.len();
+        scanner.collect_symbols(&path_sequence(), Cow::Borrowed(b"SELF"));
+        assert_eq!(scanner.cache.as_ref().unwrap().digest_to_symbols.len(), cached_digests);
+        assert_eq!(scanner.summary.files_from_cache, 0);
+    }
+
+    #[test]
+    fn test_self_containing_file() {
+        let mut scanner = wrapper_scanner();
+        let found = scanner.collect_symbols(&path_sequence(), Cow::Borrowed(b"SELF"));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].sub_path, vec![PathBuf::from("child")]);
+        assert!(found[0].remark.as_deref().unwrap().contains("contains itself"));
+        assert!(scanner.ancestor_digests.is_empty());
+    }
+
+    #[test]
+    fn test_plugins() {
+        let mut library = pe::PortableExecutable::new("SYNTH.DLL");
+        library.exports.push(pe::Export { ordinal: 1, name: Some("Alpha".to_owned()), target: pe::ExportTarget::Code { rva: pe::TEXT_RVA } });
+        let mut data = b"WRAP".to_vec();
+        data.extend_from_slice(&library.to_bytes());
+
+        // results obtained without the plugin do not hide the file from it
+        let mut scanner = Scanner::new();
+        scanner.cache = Some(ScanCache::default());
+        assert_eq!(scanner.collect_symbols(&path_sequence(), Cow::Borrowed(&data)), Vec::new());
+        scanner.plugins.register(Box::new(WrapperPlugin));
+        let found = scanner.collect_symbols(&path_sequence(), Cow::Borrowed(&data));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].symbol, Some(Symbol::ByNameAndOrdinal { name: "Alpha".to_owned(), ordinal: 1 }));
+        assert_eq!(scanner.summary.files_from_cache, 0);
+
+        // files that the built-in detectors fail to read are offered to the plugins
+        let mut data = b"MDMPWRAP".to_vec();
+        data.extend_from_slice(&library.to_bytes());
+        assert!(crate::formats::interpret_file(&data).is_err());
+        let found = scanner.collect_symbols(&path_sequence(), Cow::Borrowed(&data));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].symbol, Some(Symbol::ByNameAndOrdinal { name: "Alpha".to_owned(), ordinal: 1 }));
+        assert!(scanner.summary.errors_by_category.is_empty());
+    }
+
+    #[test]
+    fn test_time_limit() {
+        let mut data = b"WRAP".to_vec();
+        data.extend_from_slice(&pe::PortableExecutable::new("SYNTH.DLL").to_bytes());
+
+        let mut scanner = wrapper_scanner();
+        scanner.cache = Some(ScanCache::default());
+        let found = {
+            let _deadline = deadline::set_time_limit(Some(Duration::ZERO));
+            scanner.collect_symbols(&path_sequence(), Cow::Borrowed(&data))
+        };
+        assert_eq!(found, Vec::new());
+        assert!(scanner.cache.unwrap().digest_to_symbols.is_empty());
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut library = pe::PortableExecutable::new("SYNTH.DLL");
+        library.exports.push(pe::Export { ordinal: 1, name: Some("Alpha".to_owned()), target: pe::ExportTarget::Code { rva: pe::TEXT_RVA } });
+        library.exports.push(pe::Export { ordinal: 2, name: Some("Beta".to_owned()), target: pe::ExportTarget::Code { rva: pe::TEXT_RVA } });
+        let mut data = b"WRAP".to_vec();
+        data.extend_from_slice(&library.to_bytes());
+
+        let mut scanner = wrapper_scanner();
+        scanner.cache = Some(ScanCache::default());
+        scanner.scan_file(&path_sequence(), data.clone());
+        assert_eq!(scanner.summary.files_visited, 2);
+        assert_eq!(scanner.summary.files_from_cache, 0);
+        assert_eq!(scanner.summary.containers_by_format.get("wrapper"), Some(&1));
+        assert_eq!(scanner.summary.exporters, 1);
+        assert_eq!(scanner.summary.symbols, 2);
+
+        // the second time, the results come from the cache
+        scanner.scan_file(&path_sequence(), data);
+        assert_eq!(scanner.summary.files_visited, 3);
+        assert_eq!(scanner.summary.files_from_cache, 1);
+        assert_eq!(scanner.summary.containers_by_format.get("wrapper"), Some(&1));
+        assert_eq!(scanner.summary.exporters, 1);
+        assert_eq!(scanner.summary.symbols, 4);
+
+        // a self-containing file is incomplete but not an error
+        scanner.scan_file(&path_sequence(), b"SELF".to_vec());
+        assert_eq!(scanner.summary.incomplete_files, 1);
+        assert_eq!(scanner.summary.exit_status(), ExitStatus::Ok);
+
+        let document = serde_json::to_value(Document::new(DocumentKind::ScanSummary, &scanner.summary)).unwrap();
+        assert_eq!(document["kind"], "scan-summary");
+        assert_eq!(document["data"]["files_visited"], 5);
+        assert_eq!(document["data"]["containers_by_format"]["wrapper"], 2);
+        assert!(document["data"].get("error_records").is_none());
+    }
+
+    #[test]
+    fn test_summary_exit_status() {
+        let mut summary = ScanSummary::new();
+        summary.files_visited = 10;
+        summary.incomplete_files = 3;
+        assert_eq!(summary.exit_status(), ExitStatus::Ok);
+
+        for category in [ErrorCategory::ReadFromDisk, ErrorCategory::Interpret, ErrorCategory::FilterScript] {
+            let mut summary = summary.clone();
+            summary.record_error(category, &[PathBuf::from("A.DLL")], "failure");
+            assert_eq!(summary.exit_status(), ExitStatus::PartialWithErrors, "{:?}", category);
+        }
+    }
+
+    #[test]
+    fn test_error_records() {
+        let mut summary = ScanSummary::new();
+        assert_eq!(summary.exit_status(), ExitStatus::Ok);
+        summary.record_error(ErrorCategory::ReadFromDisk, &[PathBuf::from("A.DLL")], "unreadable");
+        assert!(summary.error_records.is_none());
+
+        summary.error_records = Some(Vec::new());
+        summary.record_error(ErrorCategory::ExtractFile, &[PathBuf::from("DISK.IMG"), PathBuf::from("B.EX_")], Error::SpannedFile);
+        assert_eq!(summary.errors_by_category.values().sum::<u64>(), 2);
+        assert_eq!(summary.exit_status(), ExitStatus::PartialWithErrors);
+        assert_eq!(
+            serde_json::to_value(summary.error_records.as_ref().unwrap()).unwrap(),
+            serde_json::json!([{
+                "path": ["DISK.IMG", "B.EX_"],
+                "category": "extract_file",
+                "status": "parse-error",
+                "message": "file spans multiple containers",
+            }]),
+        );
+    }
+}
@@ -0,0 +1,981 @@
+//! The structure of the JSON documents output by the `--json-output` options.
+//!
+//! The structures in binms mirror the layout of the files they are read from and change whenever
+//! that code is refactored. The JSON output is therefore assembled from the types in this module,
+//! which only change in a compatible manner: adding a field or a document kind is allowed, while
+//! renaming or removing a field or changing its meaning requires incrementing
+//! [`SCHEMA_VERSION`].
+//!
+//! Every document is an object with the following fields:
+//!
+//! * `schema_version`: the value of [`SCHEMA_VERSION`] at the time the document was written
+//! * `kind`: the [`DocumentKind`], which determines the structure of `data`
+//! * `data`: the actual information
+//!
+//! Flags are output as their numeric value. Resource identifiers are output as `{"number": 3}` or
+//! `{"name": "FOO"}`; NE resource numbers are output without their high bit.
+//!
+//! The data of the document kinds marked as unstable is serialized directly from the internal
+//! structures and is not covered by the schema version.
+
+
+use std::collections::BTreeMap;
+
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use schemars::generate::SchemaSettings;
+use serde::{Deserialize, Serialize};
+
+
+/// The version of the structure of the JSON documents.
+pub const SCHEMA_VERSION: u32 = 1;
+
+
+/// The kind of information contained in a JSON document.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DocumentKind {
+    /// Unstable.
+    MzStub,
+    NeHeader,
+    NeEntries,
+    NeSegments,
+    NeResources,
+    NeIconGroups,
+    NeStrings,
+    NeFonts,
+    PeSections,
+    PeDataDirectories,
+    PeResources,
+    PeResourceBlobs,
+    PeIconGroups,
+    /// Unstable.
+    PeUnwind,
+    /// Unstable.
+    PeBoundImports,
+    /// Unstable.
+    PeFingerprint,
+    /// Unstable.
+    DbgContainer,
+    /// Unstable.
+    CoffDebugInfo,
+    /// Unstable.
+    CodeViewDebugInfo,
+    /// Unstable.
+    DbgFunctions,
+    /// Unstable.
+    ClrResources,
+    /// Unstable.
+    Winmd,
+    /// Unstable.
+    RegistryKeys,
+    /// Unstable.
+    RegistryValues,
+    /// Unstable.
+    ComServers,
+    /// Unstable.
+    Listing,
+    /// Unstable.
+    LintFindings,
+    CdVolumeDescriptors,
+    ErrorRecords,
+    ScanSummary,
+    /// Unstable.
+    Strings,
+    /// Unstable.
+    Info,
+    /// Unstable.
+    VerifyReport,
+}
+impl DocumentKind {
+    pub const ALL: [Self; 33] = [
+        Self::MzStub,
+        Self::NeHeader,
+        Self::NeEntries,
+        Self::NeSegments,
+        Self::NeResources,
+        Self::NeIconGroups,
+        Self::NeStrings,
+        Self::NeFonts,
+        Self::PeSections,
+        Self::PeDataDirectories,
+        Self::PeResources,
+        Self::PeResourceBlobs,
+        Self::PeIconGroups,
+        Self::PeUnwind,
+        Self::PeBoundImports,
+        Self::PeFingerprint,
+        Self::DbgContainer,
+        Self::CoffDebugInfo,
+        Self::CodeViewDebugInfo,
+        Self::DbgFunctions,
+        Self::ClrResources,
+        Self::Winmd,
+        Self::RegistryKeys,
+        Self::RegistryValues,
+        Self::ComServers,
+        Self::Listing,
+        Self::LintFindings,
+        Self::CdVolumeDescriptors,
+        Self::ErrorRecords,
+        Self::ScanSummary,
+        Self::Strings,
+        Self::Info,
+        Self::VerifyReport,
+    ];
+
+    /// Returns the schema of the `data` field of documents of this kind, or `None` if the kind is
+    /// unstable.
+    fn data_schema(self, generator: &mut SchemaGenerator) -> Option<Schema> {
+        match self {
+            Self::NeHeader => Some(generator.subschema_for::<NeHeader>()),
+            Self::NeEntries => Some(generator.subschema_for::<Vec<NeEntry>>()),
+            Self::NeSegments => Some(generator.subschema_for::<Vec<NeSegment>>()),
+            Self::NeResources => Some(generator.subschema_for::<NeResourceTable>()),
+            Self::NeIconGroups => Some(generator.subschema_for::<Vec<NeIconGroup>>()),
+            Self::NeStrings => Some(generator.subschema_for::<Vec<NeString>>()),
+            Self::NeFonts => Some(generator.subschema_for::<Vec<NeFont>>()),
+            Self::PeSections => Some(generator.subschema_for::<Vec<PeSection>>()),
+            Self::PeDataDirectories => Some(generator.subschema_for::<Vec<PeDataDirectory>>()),
+            Self::PeResources => Some(generator.subschema_for::<PeResourceDirectory>()),
+            Self::PeResourceBlobs => Some(generator.subschema_for::<PeResourceBlobs>()),
+            Self::PeIconGroups => Some(generator.subschema_for::<Vec<PeIconGroupType>>()),
+            Self::CdVolumeDescriptors => Some(generator.subschema_for::<Vec<CdVolumeDescriptor>>()),
+            Self::ErrorRecords => Some(generator.subschema_for::<Vec<ErrorRecord>>()),
+            Self::ScanSummary => Some(generator.subschema_for::<crate::scan::ScanSummary>()),
+            Self::MzStub|Self::PeUnwind|Self::PeBoundImports|Self::PeFingerprint|Self::DbgContainer|Self::CoffDebugInfo
+                |Self::CodeViewDebugInfo|Self::DbgFunctions|Self::ClrResources|Self::Winmd|Self::RegistryKeys
+                |Self::RegistryValues|Self::ComServers|Self::Listing|Self::LintFindings
+                |Self::Strings|Self::Info|Self::VerifyReport
+                => None,
+        }
+    }
+}
+
+
+/// A JSON document as it is output.
+#[derive(Serialize)]
+pub struct Document<'a, T: Serialize + ?Sized> {
+    pub schema_version: u32,
+    pub kind: DocumentKind,
+    pub data: &'a T,
+}
+impl<'a, T: Serialize + ?Sized> Document<'a, T> {
+    pub fn new(kind: DocumentKind, data: &'a T) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            kind,
+            data,
+        }
+    }
+}
+
+
+/// A JSON document as it is read back, with its data not yet interpreted.
+#[derive(Deserialize)]
+pub struct ImportedDocument {
+    pub schema_version: u32,
+    pub kind: DocumentKind,
+    pub data: serde_json::Value,
+}
+
+
+/// Returns the JSON schema describing all the documents that can be output.
+pub fn json_schema() -> Schema {
+    let mut generator = SchemaSettings::draft2020_12()
+        .for_serialize()
+        .into_generator();
+
+    let mut documents = Vec::with_capacity(DocumentKind::ALL.len());
+    for kind in DocumentKind::ALL {
+        let data_schema = kind.data_schema(&mut generator)
+            .unwrap_or_else(|| json_schema!({
+                "description": "Unstable; not covered by the schema version.",
+            }));
+        documents.push(json_schema!({
+            "type": "object",
+            "properties": {
+                "schema_version": { "const": SCHEMA_VERSION },
+                "kind": { "const": kind },
+                "data": data_schema,
+            },
+            "required": ["schema_version", "kind", "data"],
+        }));
+    }
+
+    let definitions = generator.take_definitions(true);
+    json_schema!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "dllexports JSON output",
+        "oneOf": documents,
+        "$defs": definitions,
+    })
+}
+
+
+/// The identifier of a resource, its type or its language.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceId {
+    Number(u32),
+    Name(String),
+}
+impl From<&binms::ne::ResourceId> for ResourceId {
+    fn from(value: &binms::ne::ResourceId) -> Self {
+        match value {
+            binms::ne::ResourceId::Numbered(number) => Self::Number((number & 0x7FFF).into()),
+            binms::ne::ResourceId::Named(name) => {
+                let name_bytes: &[u8] = name.as_ref();
+                Self::Name(String::from_utf8_lossy(name_bytes).into_owned())
+            },
+        }
+    }
+}
+impl From<&binms::pe::ResourceIdentifier> for ResourceId {
+    fn from(value: &binms::pe::ResourceIdentifier) -> Self {
+        match value {
+            binms::pe::ResourceIdentifier::Integer(number) => Self::Number(*number),
+            binms::pe::ResourceIdentifier::Name(name) => Self::Name(name.clone()),
+        }
+    }
+}
+
+
+/// A segment number and an offset within that segment.
+#[derive(Clone, Copy, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct SegmentAndOffset {
+    pub segment_number: u16,
+    pub offset: u16,
+}
+impl From<binms::ne::SegmentAndOffset> for SegmentAndOffset {
+    fn from(value: binms::ne::SegmentAndOffset) -> Self {
+        Self {
+            segment_number: value.segment_number,
+            offset: value.offset,
+        }
+    }
+}
+
+/// An entry of an NE resident-name or nonresident-name table.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct NeName {
+    pub name: String,
+
+    /// The ordinal of the entry point with this name; 0 for the module name or description.
+    pub ordinal: u16,
+}
+impl From<&binms::ne::NameTableEntry> for NeName {
+    fn from(value: &binms::ne::NameTableEntry) -> Self {
+        let name_bytes: &[u8] = value.name.as_ref();
+        Self {
+            name: String::from_utf8_lossy(name_bytes).into_owned(),
+            ordinal: value.ordinal_number,
+        }
+    }
+}
+
+/// The header of an NE (16-bit Windows) executable.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct NeHeader {
+    pub linker_version: u8,
+    pub linker_revision: u8,
+    pub crc32: u32,
+    pub flags: u16,
+
+    /// The application type stored in bits 8 to 10 of the flags.
+    pub application_type: u8,
+
+    pub auto_data_segment_number: u16,
+    pub initial_heap_size: u16,
+    pub initial_stack_size: u16,
+    pub entry_point: SegmentAndOffset,
+    pub initial_stack_pointer: SegmentAndOffset,
+    pub alignment_shift_count: u16,
+    pub target_os: u8,
+    pub segment_count: usize,
+    pub resource_type_count: usize,
+    pub resident_names: Vec<NeName>,
+    pub non_resident_names: Vec<NeName>,
+}
+impl From<&binms::ne::Executable> for NeHeader {
+    fn from(value: &binms::ne::Executable) -> Self {
+        Self {
+            linker_version: value.linker_version,
+            linker_revision: value.linker_revision,
+            crc32: value.crc32,
+            flags: value.flags.bits(),
+            application_type: value.flags.application_type().to_base_type(),
+            auto_data_segment_number: value.auto_data_segment_number,
+            initial_heap_size: value.initial_heap_size,
+            initial_stack_size: value.initial_stack_size,
+            entry_point: value.cs_ip.into(),
+            initial_stack_pointer: value.ss_sp.into(),
+            alignment_shift_count: value.logical_sector_alignment_shift_count,
+            target_os: value.executable_type,
+            segment_count: value.segment_table.len(),
+            resource_type_count: value.resource_table.id_to_type.len(),
+            resident_names: value.resident_name_table.iter().map(NeName::from).collect(),
+            non_resident_names: value.non_resident_name_table.iter().map(NeName::from).collect(),
+        }
+    }
+}
+
+/// An entry point of an NE executable.
+#[derive(Clone, Copy, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct NeEntry {
+    pub ordinal: u16,
+    pub segment_number: u8,
+    pub offset: u16,
+    pub moveable: bool,
+    pub flags: u8,
+}
+impl From<&binms::ne::Entry> for NeEntry {
+    fn from(value: &binms::ne::Entry) -> Self {
+        Self {
+            ordinal: value.ordinal,
+            segment_number: value.segment_number,
+            offset: value.entry_point_offset,
+            moveable: value.moveable,
+            flags: value.flags.bits(),
+        }
+    }
+}
+
+/// A segment of an NE executable.
+#[derive(Clone, Copy, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct NeSegment {
+    /// The 1-based number of the segment.
+    pub number: u32,
+
+    pub offset_bytes: u64,
+    pub length_bytes: u32,
+    pub min_allocation_size_bytes: u16,
+    pub flags: u16,
+    pub relocation_count: usize,
+}
+
+/// The resource table of an NE executable.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct NeResourceTable {
+    pub alignment_shift_count: u16,
+    pub resources: Vec<NeResource>,
+}
+
+/// A resource of an NE executable.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct NeResource {
+    pub type_id: ResourceId,
+    pub resource_id: ResourceId,
+
+    /// The offset as stored, in units of `1 << alignment_shift_count` bytes.
+    pub offset_units: u16,
+
+    /// The length as stored, in units of `1 << alignment_shift_count` bytes.
+    pub length_units: u16,
+
+    /// The offset of the data from the beginning of the file, in bytes.
+    pub offset_bytes: u64,
+
+    /// The length of the data in bytes, including any padding up to the alignment.
+    pub length_bytes: usize,
+
+    pub flags: u16,
+    pub reserved: u32,
+}
+impl NeResource {
+    pub fn new(type_id: &binms::ne::ResourceId, resource: &binms::ne::Resource) -> Self {
+        Self {
+            type_id: type_id.into(),
+            resource_id: (&resource.resource_id).into(),
+            offset_units: resource.resource_offset_units,
+            length_units: resource.resource_length_units,
+            offset_bytes: resource.file_offset_bytes(),
+            length_bytes: resource.length_bytes(),
+            flags: resource.flags.bits(),
+            reserved: resource.reserved,
+        }
+    }
+}
+
+/// An icon or cursor group of an NE executable.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct NeIconGroup {
+    pub group_id: ResourceId,
+
+    #[serde(flatten)]
+    pub icon_group: IconGroup,
+
+    /// The variants of the icons or cursors stored in the Windows 1.0 format; those in other
+    /// formats or whose resource is missing are not listed.
+    pub icon_variants: Vec<NeIconVariants>,
+}
+
+/// A string from the string table of an NE executable.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct NeString {
+    pub id: u16,
+
+    /// The ID of the string table resource containing the string.
+    pub block_id: u16,
+
+    /// The string, decoded as Windows-1252.
+    pub text: String,
+
+    /// The raw bytes of the string, for decoding it in the codepage the module was localized for.
+    pub bytes: Vec<u8>,
+}
+
+/// The header fields of a bitmap or vector font resource (`.fnt`) of an NE executable.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct NeFont {
+    pub id: ResourceId,
+
+    /// The version of the `.fnt` format: 0x0100, 0x0200 or 0x0300.
+    pub version: u16,
+
+    pub size: u32,
+
+    /// The copyright notice, decoded as Windows-1252 with the trailing NUL bytes removed.
+    pub copyright: String,
+
+    /// The face name, e.g. `MS Sans Serif`.
+    pub face_name: String,
+
+    /// The name of the device the font is intended for; empty for generic fonts.
+    pub device_name: String,
+
+    pub font_type: u16,
+    pub point_size: u16,
+    pub vertical_dpi: u16,
+    pub horizontal_dpi: u16,
+    pub ascent: u16,
+    pub internal_leading: u16,
+    pub external_leading: u16,
+    pub italic: bool,
+    pub underline: bool,
+    pub strike_out: bool,
+    pub weight: u16,
+    pub char_set: u8,
+
+    /// The width of each character in pixels, or 0 if the font is proportional.
+    pub pixel_width: u16,
+
+    pub pixel_height: u16,
+    pub pitch_and_family: u8,
+    pub average_width: u16,
+    pub max_width: u16,
+    pub first_char: u8,
+    pub last_char: u8,
+
+    /// The character substituted for characters outside the font, relative to `first_char`.
+    pub default_char: u8,
+
+    /// The character denoting word breaks, relative to `first_char`.
+    pub break_char: u8,
+}
+impl NeFont {
+    pub fn new(id: &binms::ne::ResourceId, font: &binms::bitmap_font::Font) -> Self {
+        let copyright_bytes: &[u8] = font.copyright.as_ref();
+        let copyright_length = copyright_bytes.iter()
+            .rposition(|b| *b != 0x00)
+            .map_or(0, |last| last + 1);
+        Self {
+            id: id.into(),
+            version: font.version,
+            size: font.size,
+            copyright: binms::string_table::decode_windows_1252(&copyright_bytes[..copyright_length]),
+            face_name: font.name.clone(),
+            device_name: font.device_name.clone(),
+            font_type: font.font_type.bits(),
+            point_size: font.point_size,
+            vertical_dpi: font.vertical_dpi,
+            horizontal_dpi: font.horizontal_dpi,
+            ascent: font.ascent,
+            internal_leading: font.internal_leading,
+            external_leading: font.external_leading,
+            italic: font.italic.contains(binms::bitmap_font::Italic::ITALIC),
+            underline: font.underline.contains(binms::bitmap_font::Underline::UNDERLINE),
+            strike_out: font.strike_out.contains(binms::bitmap_font::StrikeOut::STRIKE_OUT),
+            weight: font.weight,
+            char_set: font.char_set,
+            pixel_width: font.pixel_width,
+            pixel_height: font.pixel_height,
+            pitch_and_family: font.pitch_and_family.into(),
+            average_width: font.average_width,
+            max_width: font.max_width,
+            first_char: font.first_char,
+            last_char: font.last_char,
+            default_char: font.default_char,
+            break_char: font.break_char_relative,
+        }
+    }
+}
+
+/// The variants stored in a Windows 1.0 icon or cursor resource.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct NeIconVariants {
+    /// The number of the icon or cursor resource.
+    pub id: u16,
+
+    pub variants: Vec<IconVariant>,
+}
+
+/// A variant of a Windows 1.0 icon or cursor.
+#[derive(Clone, Copy, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IconVariant {
+    DeviceIndependent,
+    DeviceDependent,
+}
+impl From<binms::ico1::Icon1Variant> for IconVariant {
+    fn from(value: binms::ico1::Icon1Variant) -> Self {
+        match value {
+            binms::ico1::Icon1Variant::DeviceIndependent => Self::DeviceIndependent,
+            binms::ico1::Icon1Variant::DeviceDependent => Self::DeviceDependent,
+        }
+    }
+}
+
+/// The contents of an icon or cursor group resource.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct IconGroup {
+    /// 1 for icons, 2 for cursors.
+    pub group_type: u16,
+
+    pub icons: Vec<GroupIcon>,
+}
+impl From<&binms::icon_group::IconGroup> for IconGroup {
+    fn from(value: &binms::icon_group::IconGroup) -> Self {
+        Self {
+            group_type: value.group_type,
+            icons: value.icons.iter().map(GroupIcon::from).collect(),
+        }
+    }
+}
+
+/// An icon or cursor in an icon or cursor group.
+#[derive(Clone, Copy, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct GroupIcon {
+    pub width: u8,
+    pub height: u8,
+    pub color_count: u8,
+    pub planes: u16,
+    pub bit_count: u16,
+    pub byte_count: u32,
+
+    /// The number of the icon or cursor resource.
+    pub id: u16,
+}
+impl From<&binms::icon_group::GroupIcon> for GroupIcon {
+    fn from(value: &binms::icon_group::GroupIcon) -> Self {
+        Self {
+            width: value.width,
+            height: value.height,
+            color_count: value.color_count,
+            planes: value.planes,
+            bit_count: value.bit_count,
+            byte_count: value.byte_count,
+            id: value.id,
+        }
+    }
+}
+
+/// A section of a PE executable.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct PeSection {
+    pub name: String,
+    pub raw_start: u32,
+    pub raw_end: u64,
+    pub virtual_start: u32,
+    pub virtual_end: u64,
+
+    /// What the section contains, according to its characteristics and name.
+    pub class: SectionClass,
+
+    pub characteristics: u32,
+
+    /// Human-readable descriptions of inconsistencies between the sizes of the section.
+    pub warnings: Vec<String>,
+}
+
+/// What a section of a PE executable contains.
+#[derive(Clone, Copy, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SectionClass {
+    Code,
+    InitializedData,
+    UninitializedData,
+    Resource,
+    Relocation,
+    Debug,
+    Other,
+}
+impl From<binms::pe::SectionClass> for SectionClass {
+    fn from(value: binms::pe::SectionClass) -> Self {
+        match value {
+            binms::pe::SectionClass::Code => Self::Code,
+            binms::pe::SectionClass::InitializedData => Self::InitializedData,
+            binms::pe::SectionClass::UninitializedData => Self::UninitializedData,
+            binms::pe::SectionClass::Resource => Self::Resource,
+            binms::pe::SectionClass::Relocation => Self::Relocation,
+            binms::pe::SectionClass::Debug => Self::Debug,
+            binms::pe::SectionClass::Other => Self::Other,
+        }
+    }
+}
+
+/// An entry of the data directory of a PE executable.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct PeDataDirectory {
+    pub index: usize,
+
+    /// The name of the entry, if it is known.
+    pub name: Option<String>,
+
+    pub address: u32,
+    pub size: u32,
+
+    /// Whether the address is a file offset instead of a relative virtual address.
+    pub address_is_file_offset: bool,
+
+    pub raw_start: Option<u32>,
+    pub raw_end: Option<u64>,
+
+    /// The name of the section containing the data.
+    pub section: Option<String>,
+}
+
+/// A directory in the resource tree of a PE executable.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct PeResourceDirectory {
+    pub characteristics: u32,
+    pub timestamp: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub entries: Vec<PeResourceEntry>,
+}
+impl From<&binms::pe::ResourceDirectoryTable> for PeResourceDirectory {
+    fn from(value: &binms::pe::ResourceDirectoryTable) -> Self {
+        Self {
+            characteristics: value.characteristics,
+            timestamp: value.timestamp,
+            major_version: value.major_version,
+            minor_version: value.minor_version,
+            entries: value.id_to_entry.iter()
+                .map(|(id, child)| PeResourceEntry {
+                    id: id.into(),
+                    child: child.into(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// An entry in a directory in the resource tree of a PE executable.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct PeResourceEntry {
+    pub id: ResourceId,
+
+    #[serde(flatten)]
+    pub child: PeResourceChild,
+}
+
+/// The target of an entry in the resource tree of a PE executable.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PeResourceChild {
+    Data(PeResourceData),
+    Directory(PeResourceDirectory),
+}
+impl From<&binms::pe::ResourceChild> for PeResourceChild {
+    fn from(value: &binms::pe::ResourceChild) -> Self {
+        match value {
+            binms::pe::ResourceChild::Data(data) => Self::Data(PeResourceData {
+                data_rva: data.data_rva,
+                size: data.size,
+                codepage: data.codepage,
+                data: data.data.as_ref().map(|d| {
+                    let data_bytes: &[u8] = d.as_ref();
+                    data_bytes.to_vec()
+                }),
+            }),
+            binms::pe::ResourceChild::Subdirectory(directory) => Self::Directory(directory.into()),
+        }
+    }
+}
+
+/// A resource of a PE executable.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct PeResourceData {
+    pub data_rva: u32,
+    pub size: u32,
+    pub codepage: u32,
+
+    /// The bytes of the resource; `None` if they could not be read.
+    pub data: Option<Vec<u8>>,
+}
+
+/// The resources of a PE executable, grouped by their data.
+///
+/// Localized executables often contain the same bytes (e.g. icons or version-independent dialogs)
+/// for every language. Here, the data is output once, along with all the resources that contain
+/// it. Only the resources at the usual depth (type, resource, language) are included.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct PeResourceBlobs {
+    /// The distinct resource data, in the order in which it first appears in the tree.
+    pub blobs: Vec<PeResourceBlob>,
+
+    /// The resources whose data could not be read.
+    pub unreadable: Vec<PeResourceLocation>,
+}
+impl From<&binms::pe::ResourceDirectoryTable> for PeResourceBlobs {
+    fn from(value: &binms::pe::ResourceDirectoryTable) -> Self {
+        let mut blobs: Vec<PeResourceBlob> = Vec::new();
+        let mut digest_to_index: BTreeMap<crate::hashing::Sha256Digest, usize> = BTreeMap::new();
+        let mut unreadable = Vec::new();
+        for (path, data) in value.data_entries() {
+            let [type_id, resource_id, language_id] = path.as_slice() else {
+                continue;
+            };
+            let location = PeResourceLocation {
+                type_id: (*type_id).into(),
+                resource_id: (*resource_id).into(),
+                language_id: (*language_id).into(),
+                data_rva: data.data_rva,
+                codepage: data.codepage,
+            };
+            let Some(bytes) = data.data.as_ref() else {
+                unreadable.push(location);
+                continue;
+            };
+            let bytes: &[u8] = bytes.as_ref();
+            let digest = crate::hashing::sha256(bytes);
+            let index = *digest_to_index.entry(digest)
+                .or_insert_with(|| {
+                    blobs.push(PeResourceBlob {
+                        sha256: crate::hashing::to_hex(&digest),
+                        data: bytes.to_vec(),
+                        resources: Vec::new(),
+                    });
+                    blobs.len() - 1
+                });
+            blobs[index].resources.push(location);
+        }
+        Self {
+            blobs,
+            unreadable,
+        }
+    }
+}
+
+/// Data shared by one or more resources of a PE executable.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct PeResourceBlob {
+    /// The SHA-256 digest of the data, in lowercase hexadecimal.
+    pub sha256: String,
+
+    pub data: Vec<u8>,
+
+    /// The resources containing this data, in tree order.
+    pub resources: Vec<PeResourceLocation>,
+}
+
+/// The position of a resource in the resource tree of a PE executable.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct PeResourceLocation {
+    pub type_id: ResourceId,
+    pub resource_id: ResourceId,
+    pub language_id: ResourceId,
+    pub data_rva: u32,
+    pub codepage: u32,
+}
+
+/// The icon or cursor groups of one resource type of a PE executable.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct PeIconGroupType {
+    pub type_id: ResourceId,
+    pub resources: Vec<PeIconGroupResource>,
+}
+
+/// An icon or cursor group resource of a PE executable in all its languages.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct PeIconGroupResource {
+    pub resource_id: ResourceId,
+    pub languages: Vec<PeIconGroupLanguage>,
+}
+
+/// An icon or cursor group resource of a PE executable in one language.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct PeIconGroupLanguage {
+    pub language_id: ResourceId,
+    pub icon_group: IconGroup,
+}
+
+
+/// A descriptor in the volume descriptor sequence of a CD image.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct CdVolumeDescriptor {
+    /// The position of the descriptor in the sequence, starting at 0.
+    pub number: u64,
+
+    /// The byte offset of the descriptor within the image.
+    pub offset: u64,
+
+    pub kind: CdDescriptorKind,
+
+    /// The raw type of the descriptor.
+    pub type_code: u8,
+
+    pub version: u8,
+
+    /// The system identifier of volume and partition descriptors or the boot system identifier
+    /// of boot records.
+    pub system_identifier: Option<String>,
+
+    /// The volume identifier of volume descriptors, the partition identifier of partition
+    /// descriptors or the boot identifier of boot records.
+    pub identifier: Option<String>,
+
+    /// The size of the volume or partition in logical blocks.
+    pub block_count: Option<u32>,
+}
+
+/// The kind of a descriptor in the volume descriptor sequence of a CD image.
+#[derive(Clone, Copy, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CdDescriptorKind {
+    BootRecord,
+    Primary,
+    Supplementary,
+    Enhanced,
+    Partition,
+    Terminator,
+    Other,
+}
+impl CdDescriptorKind {
+    pub fn new(vd_type: expandms::iso9660::DescriptorType, version: u8) -> Self {
+        use expandms::iso9660::DescriptorType;
+        match vd_type {
+            DescriptorType::BootRecord => Self::BootRecord,
+            DescriptorType::PrimaryVolumeDescriptor => Self::Primary,
+            DescriptorType::SupplementaryOrEnhancedVolumeDescriptor if version == 2 => Self::Enhanced,
+            DescriptorType::SupplementaryOrEnhancedVolumeDescriptor => Self::Supplementary,
+            DescriptorType::VolumePartitionDescriptor => Self::Partition,
+            DescriptorType::SetTerminator => Self::Terminator,
+            DescriptorType::Other(_) => Self::Other,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::BootRecord => "boot record",
+            Self::Primary => "primary",
+            Self::Supplementary => "supplementary",
+            Self::Enhanced => "enhanced",
+            Self::Partition => "partition",
+            Self::Terminator => "terminator",
+            Self::Other => "other",
+        }
+    }
+}
+
+
+/// A failure to process a file, as written by `scan --errors-json`.
+#[derive(Clone, Debug, Eq, Hash, JsonSchema, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct ErrorRecord {
+    /// The path of the file on disk, followed by its paths within the containers it is nested in.
+    ///
+    /// An empty path denotes the file within a single-file container.
+    pub path: Vec<String>,
+
+    /// What was being done when the failure occurred, as counted in the scan summary.
+    pub category: crate::scan::ErrorCategory,
+
+    /// The kind of failure, named like the corresponding exit status.
+    pub status: crate::exit_status::ExitStatus,
+
+    pub message: String,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_schema_covers_all_kinds() {
+        let schema = json_schema();
+        let schema_value = schema.as_value();
+        let documents = schema_value["oneOf"].as_array().unwrap();
+        assert_eq!(documents.len(), DocumentKind::ALL.len());
+        assert_eq!(documents[1]["properties"]["kind"]["const"], "ne-header");
+        assert!(schema_value["$defs"].get("NeHeader").is_some());
+        assert!(schema_value["$defs"].get("PeResourceDirectory").is_some());
+    }
+
+    #[test]
+    fn test_resource_blobs() {
+        use binms::pe::{ResourceChild, ResourceData, ResourceDirectoryTable, ResourceIdentifier};
+
+        fn table(entries: Vec<(ResourceIdentifier, ResourceChild)>) -> ResourceDirectoryTable {
+            ResourceDirectoryTable {
+                characteristics: 0,
+                timestamp: 0,
+                major_version: 0,
+                minor_version: 0,
+                id_to_entry: entries.into_iter().collect(),
+            }
+        }
+        fn data(data_rva: u32, bytes: Option<&[u8]>) -> ResourceChild {
+            ResourceChild::Data(ResourceData {
+                data_rva,
+                size: bytes.map_or(0, |b| b.len() as u32),
+                codepage: 0,
+                reserved: 0,
+                data: bytes.map(|b| b.into()),
+            })
+        }
+
+        let icon = table(vec![
+            (ResourceIdentifier::Integer(0x0407), data(0x1000, Some(b"icon"))),
+            (ResourceIdentifier::Integer(0x0409), data(0x1000, Some(b"icon"))),
+        ]);
+        let about = table(vec![
+            (ResourceIdentifier::Integer(0x0407), data(0x2000, Some(b"Info"))),
+            (ResourceIdentifier::Integer(0x0409), data(0x2010, Some(b"About"))),
+            (ResourceIdentifier::Integer(0x040C), data(0x9000, None)),
+        ]);
+        let root = table(vec![
+            (ResourceIdentifier::Integer(3), ResourceChild::Subdirectory(table(vec![
+                (ResourceIdentifier::Integer(1), ResourceChild::Subdirectory(icon)),
+            ]))),
+            (ResourceIdentifier::Integer(5), ResourceChild::Subdirectory(table(vec![
+                (ResourceIdentifier::Name("ABOUT".to_owned()), ResourceChild::Subdirectory(about)),
+            ]))),
+        ]);
+
+        let blobs = PeResourceBlobs::from(&root);
+        let blob_summary: Vec<(&[u8], Vec<ResourceId>)> = blobs.blobs.iter()
+            .map(|b| (b.data.as_slice(), b.resources.iter().map(|r| r.language_id.clone()).collect()))
+            .collect();
+        assert_eq!(blob_summary, vec![
+            (&b"icon"[..], vec![ResourceId::Number(0x0407), ResourceId::Number(0x0409)]),
+            (&b"Info"[..], vec![ResourceId::Number(0x0407)]),
+            (&b"About"[..], vec![ResourceId::Number(0x0409)]),
+        ]);
+        assert_eq!(blobs.blobs[0].sha256.len(), 64);
+        assert_eq!(blobs.unreadable.len(), 1);
+        assert_eq!(blobs.unreadable[0].resource_id, ResourceId::Name("ABOUT".to_owned()));
+        assert_eq!(blobs.unreadable[0].language_id, ResourceId::Number(0x040C));
+    }
+
+    #[test]
+    fn test_document_envelope() {
+        let id = ResourceId::Number(3);
+        let value = serde_json::to_value(Document::new(DocumentKind::NeResources, &id)).unwrap();
+        assert_eq!(value, serde_json::json!({
+            "schema_version": SCHEMA_VERSION,
+            "kind": "ne-resources",
+            "data": { "number": 3 },
+        }));
+    }
+}
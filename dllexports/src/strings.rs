@@ -0,0 +1,306 @@
+//! Enumeration of the strings in a file along with where they are stored.
+
+
+use std::fmt;
+use std::io::Cursor;
+use std::ops::Range;
+
+use binms::pe::{
+    self, KnownDataDirectoryEntry, OptionalHeader, ResourceChild, ResourceDirectoryTable,
+    SectionTable,
+};
+use binms::strings::{find_strings, StringEncoding};
+use clap::ValueEnum;
+use display_bytes::escape::escape_str;
+use serde::Serialize;
+use tracing::debug;
+
+use crate::schema::ResourceId;
+
+
+/// Which encodings of strings to look for.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, ValueEnum)]
+pub(crate) enum EncodingFilter {
+    Ascii,
+    Utf16le,
+    All,
+}
+impl EncodingFilter {
+    pub fn encodings(self) -> &'static [StringEncoding] {
+        match self {
+            Self::Ascii => &[StringEncoding::Ascii],
+            Self::Utf16le => &[StringEncoding::Utf16Le],
+            Self::All => &[StringEncoding::Ascii, StringEncoding::Utf16Le],
+        }
+    }
+}
+
+
+/// Where in a PE file a string is stored.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub(crate) enum Provenance {
+    /// The headers before the first section.
+    Header,
+
+    /// A section other than the data of a resource.
+    Section { name: String },
+
+    /// The data of a resource, identified by its type, name and language.
+    Resource { path: Vec<ResourceId> },
+
+    /// Outside of all sections, e.g. data appended to the file.
+    Overlay,
+}
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Header => write!(f, "header"),
+            Self::Section { name } => write!(f, "section {}", escape_str(name)),
+            Self::Resource { path } => {
+                write!(f, "resource ")?;
+                for (i, id) in path.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "/")?;
+                    }
+                    match id {
+                        ResourceId::Number(number) => write!(f, "{}", number)?,
+                        ResourceId::Name(name) => write!(f, "{}", escape_str(name))?,
+                    }
+                }
+                Ok(())
+            },
+            Self::Overlay => write!(f, "overlay"),
+        }
+    }
+}
+
+
+/// A string found in a file.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub(crate) struct LocatedString {
+    /// The offset of the string within the file.
+    pub offset: usize,
+
+    /// The relative virtual address of the string, if it is within a section of a PE file.
+    pub rva: Option<u32>,
+
+    pub encoding: StringEncoding,
+
+    /// Where the string is stored; `None` if the file is not a PE file.
+    pub provenance: Option<Provenance>,
+
+    pub value: String,
+}
+impl fmt::Display for LocatedString {
+    /// Outputs the string as tab-separated offset, relative virtual address, encoding, provenance
+    /// and escaped value.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#010X}\t", self.offset)?;
+        match self.rva {
+            Some(rva) => write!(f, "{:#010X}\t", rva)?,
+            None => write!(f, "-\t")?,
+        }
+        match self.encoding {
+            StringEncoding::Ascii => write!(f, "ascii\t")?,
+            StringEncoding::Utf16Le => write!(f, "utf16le\t")?,
+        }
+        match &self.provenance {
+            Some(provenance) => write!(f, "{}\t", provenance)?,
+            None => write!(f, "-\t")?,
+        }
+        write!(f, "{}", escape_str(&self.value))
+    }
+}
+
+
+/// The layout of a PE file, used to find out where a string is stored.
+struct PeLayout {
+    section_table: SectionTable,
+
+    /// The raw offsets of the data of each resource along with its path.
+    resources: Vec<(Range<usize>, Vec<ResourceId>)>,
+}
+impl PeLayout {
+    fn read(data: &[u8]) -> Option<Self> {
+        let mut cursor = Cursor::new(data);
+        let executable = pe::Executable::read(&mut cursor).ok()?;
+
+        let resource_entry = match &executable.optional_header {
+            Some(OptionalHeader::Coff(coff)) => coff.optional_windows_header.as_ref()
+                .and_then(|wh| wh.known_data_directory_entry(KnownDataDirectoryEntry::ResourceTable))
+                .filter(|e| e.address != 0),
+            _ => None,
+        };
+        let mut resources = Vec::new();
+        if let Some(resource_entry) = resource_entry {
+            match ResourceDirectoryTable::read_root_from_pe(&mut cursor, &resource_entry, &executable.section_table) {
+                Ok(root) => collect_resources(&root, &executable.section_table, &mut Vec::new(), &mut resources),
+                Err(e) => debug!("failed to read resources: {}", e),
+            }
+        }
+
+        Some(Self {
+            section_table: executable.section_table,
+            resources,
+        })
+    }
+
+    /// Returns where the data at the given raw offset is stored and its relative virtual address.
+    fn locate(&self, offset: usize) -> (Provenance, Option<u32>) {
+        let section = self.section_table.as_entries()
+            .iter()
+            .filter(|s| s.raw_data_size > 0)
+            .find(|s| {
+                let start = usize::try_from(s.raw_data_pointer).unwrap();
+                start.checked_add(usize::try_from(s.raw_data_size).unwrap())
+                    .is_some_and(|end| (start..end).contains(&offset))
+            });
+        let Some(section) = section else {
+            let first_section_start = self.section_table.as_entries()
+                .iter()
+                .filter(|s| s.raw_data_size > 0)
+                .map(|s| usize::try_from(s.raw_data_pointer).unwrap())
+                .min();
+            let provenance = match first_section_start {
+                Some(start) if offset >= start => Provenance::Overlay,
+                _ => Provenance::Header,
+            };
+            return (provenance, None);
+        };
+
+        // less than the raw data size, so it fits
+        let section_offset = u32::try_from(offset - usize::try_from(section.raw_data_pointer).unwrap()).unwrap();
+        let rva = (section_offset < section.virtual_size)
+            .then(|| section.virtual_address.checked_add(section_offset))
+            .flatten();
+        let resource_path = self.resources.iter()
+            .find(|(range, _)| range.contains(&offset))
+            .map(|(_, path)| path.clone());
+        let provenance = match resource_path {
+            Some(path) => Provenance::Resource { path },
+            None => {
+                let name_bytes: &[u8] = section.name.as_ref();
+                let name = String::from_utf8_lossy(name_bytes)
+                    .trim_end_matches('\0')
+                    .to_owned();
+                Provenance::Section { name }
+            },
+        };
+        (provenance, rva)
+    }
+}
+
+fn collect_resources(
+    table: &ResourceDirectoryTable,
+    section_table: &SectionTable,
+    path: &mut Vec<ResourceId>,
+    resources: &mut Vec<(Range<usize>, Vec<ResourceId>)>,
+) {
+    for (id, child) in &table.id_to_entry {
+        path.push(ResourceId::from(id));
+        match child {
+            ResourceChild::Data(data) => {
+                if let Some(raw_start) = section_table.virtual_to_raw(data.data_rva) {
+                    let start = usize::try_from(raw_start).unwrap();
+                    if let Some(end) = start.checked_add(usize::try_from(data.size).unwrap()) {
+                        resources.push((start..end, path.clone()));
+                    }
+                }
+            },
+            ResourceChild::Subdirectory(subdirectory) => {
+                collect_resources(subdirectory, section_table, path, resources);
+            },
+        }
+        path.pop();
+    }
+}
+
+
+/// Finds the strings of at least `min_length` characters in the given encodings within a file.
+///
+/// If the file is a PE file, the section or resource containing each string is recorded as well.
+/// The strings are returned sorted by offset.
+pub(crate) fn find_located_strings(data: &[u8], min_length: usize, encodings: &[StringEncoding]) -> Vec<LocatedString> {
+    let layout = PeLayout::read(data);
+    find_strings(data, min_length, encodings)
+        .into_iter()
+        .map(|found| {
+            let (provenance, rva) = match &layout {
+                Some(layout) => {
+                    let (provenance, rva) = layout.locate(found.offset);
+                    (Some(provenance), rva)
+                },
+                None => (None, None),
+            };
+            LocatedString {
+                offset: found.offset,
+                rva,
+                encoding: found.encoding,
+                provenance,
+                value: found.value,
+            }
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use binms::strings::StringEncoding;
+    use testgen::pe;
+    use super::{find_located_strings, Provenance};
+    use crate::schema::ResourceId;
+
+    #[test]
+    fn test_find_located_strings() {
+        let mut library = pe::PortableExecutable::new("SYNTH.DLL");
+        library.exports.push(pe::Export {
+            ordinal: 1,
+            name: Some("UndocumentedFunction".to_owned()),
+            target: pe::ExportTarget::Code { rva: pe::TEXT_RVA },
+        });
+        let mut version_data = Vec::new();
+        for c in "FileDescription".encode_utf16() {
+            version_data.extend_from_slice(&c.to_le_bytes());
+        }
+        library.resources.push(pe::Resource { type_id: 16, name_id: 1, language_id: 0x0409, data: version_data });
+        let bytes = library.to_bytes();
+
+        let strings = find_located_strings(&bytes, 8, &[StringEncoding::Ascii, StringEncoding::Utf16Le]);
+        let export_name = strings.iter()
+            .find(|s| s.value == "UndocumentedFunction")
+            .unwrap();
+        assert_eq!(export_name.encoding, StringEncoding::Ascii);
+        assert_eq!(export_name.provenance, Some(Provenance::Section { name: ".edata".to_owned() }));
+        assert!(export_name.rva.is_some());
+
+        let description = strings.iter()
+            .find(|s| s.value == "FileDescription")
+            .unwrap();
+        assert_eq!(description.encoding, StringEncoding::Utf16Le);
+        assert_eq!(
+            description.provenance,
+            Some(Provenance::Resource { path: vec![ResourceId::Number(16), ResourceId::Number(1), ResourceId::Number(0x0409)] }),
+        );
+        assert_eq!(description.provenance.as_ref().unwrap().to_string(), "resource 16/1/1033");
+
+        // a section mapped to the very end of the address space has no addresses for the strings
+        // beyond it
+        let mut wrapping = bytes.clone();
+        let edata_header_offset = 0x40 + 4 + 20 + 224 + 40;
+        assert_eq!(&wrapping[edata_header_offset..edata_header_offset+8], b".edata\0\0");
+        wrapping[edata_header_offset+12..edata_header_offset+16].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+        let strings = find_located_strings(&wrapping, 8, &[StringEncoding::Ascii]);
+        let export_name = strings.iter()
+            .find(|s| s.value == "UndocumentedFunction")
+            .unwrap();
+        assert_eq!(export_name.provenance, Some(Provenance::Section { name: ".edata".to_owned() }));
+        assert_eq!(export_name.rva, None);
+
+        // other files have no provenance
+        let strings = find_located_strings(b"\x00just some text\x00", 4, &[StringEncoding::Ascii]);
+        assert_eq!(strings.len(), 1);
+        assert_eq!(strings[0].provenance, None);
+    }
+}
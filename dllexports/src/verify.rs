@@ -0,0 +1,502 @@
+//! Running all parsers over a corpus of files to find out how robust they are.
+//!
+//! Each file is passed to every parser that applies to it: format detection, the reading of
+//! symbols, the listing and extraction of files from containers (which exercises the
+//! decompressors), the headers, imports, resources and debug information of executables and
+//! `.dbg` files. Every parser runs in a harness that catches panics, so that a file that crashes a
+//! parser is counted as a failure of that parser instead of ending the run. Each file can be given
+//! a time limit, like when scanning; parsers that run out of time fail in a category of their own.
+//! The report counts the successes and failures of each parser, with the failures grouped into
+//! categories.
+
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::read_dir;
+use std::io::{self, Cursor, Seek, SeekFrom};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use binms::nt4dbg::{DbgFile, DebugDirectory, DebugType};
+use binms::pe::{self, ImportDescriptor, KnownDataDirectoryEntry, OptionalHeader, ResourceDirectoryTable};
+use binstruct::deadline;
+use expandms::DecompressionError;
+use serde::Serialize;
+use tracing::{debug, error};
+
+use crate::data_mgmt::{Error, IdentifiedFile};
+
+
+/// The size of an entry in the debug directory of a PE file.
+const DEBUG_DIRECTORY_ENTRY_SIZE: u32 = 28;
+
+/// The category of failures caused by panics.
+const PANIC_CATEGORY: &str = "panic";
+
+/// The category of failures caused by exceeding the time limit.
+const TIMEOUT_CATEGORY: &str = "timeout";
+
+
+/// A parser exercised by [`verify_corpus`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Parser {
+    /// Detection of the format and interpretation of the file; applies to every file.
+    Interpret,
+
+    /// The one-page summary output by `info`; applies to every file.
+    Info,
+
+    /// Reading the symbols of a symbol exporter.
+    Symbols,
+
+    /// Listing the files in a container.
+    ListFiles,
+
+    /// Extracting (and decompressing) every file from a container.
+    ExtractFiles,
+
+    /// The headers and section table of a PE executable.
+    PeHeaders,
+
+    /// The import descriptors and imported functions of a PE executable.
+    PeImports,
+
+    /// The resource directory of a PE executable.
+    PeResources,
+
+    /// The debug directory of a PE executable and the CodeView information it references.
+    PeDebugInfo,
+
+    /// The headers, segments and resources of an NE executable.
+    NeHeaders,
+
+    /// A `.dbg` file and the CodeView and COFF debug information within it.
+    DbgFile,
+}
+impl fmt::Display for Parser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Interpret => "interpret",
+            Self::Info => "info",
+            Self::Symbols => "symbols",
+            Self::ListFiles => "list-files",
+            Self::ExtractFiles => "extract-files",
+            Self::PeHeaders => "pe-headers",
+            Self::PeImports => "pe-imports",
+            Self::PeResources => "pe-resources",
+            Self::PeDebugInfo => "pe-debug-info",
+            Self::NeHeaders => "ne-headers",
+            Self::DbgFile => "dbg-file",
+        };
+        f.write_str(name)
+    }
+}
+
+
+/// The statistics of one parser.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub(crate) struct ParserStats {
+    /// The number of files the parser has been run on.
+    pub attempted: u64,
+
+    pub succeeded: u64,
+    pub failed: u64,
+
+    /// The fraction of the attempts that succeeded.
+    pub success_rate: f64,
+
+    /// The number of failures in each category, e.g. `io/UnexpectedEof`,
+    /// `decompression/ChecksumMismatch`, `timeout` or `panic`.
+    pub errors: BTreeMap<String, u64>,
+}
+
+/// A failure of a parser on a file.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub(crate) struct Failure {
+    pub path: PathBuf,
+    pub parser: Parser,
+    pub category: String,
+    pub message: String,
+}
+
+/// The results of running the parsers over a corpus.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub(crate) struct VerificationReport {
+    /// The number of files that have been verified.
+    pub files: u64,
+
+    /// The paths of the files and directories that could not be read from disk.
+    pub unreadable: Vec<PathBuf>,
+
+    pub parsers: BTreeMap<Parser, ParserStats>,
+    pub failures: Vec<Failure>,
+}
+impl VerificationReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs the parser in a harness that catches panics and records the outcome.
+    fn run<F: FnOnce() -> Result<(), Error>>(&mut self, path: &Path, parser: Parser, f: F) {
+        let failure = match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some((error_category(&e), e.to_string())),
+            Err(payload) => {
+                let message = payload.downcast_ref::<&str>()
+                    .map(|s| (*s).to_owned())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "panicked".to_owned());
+                Some((PANIC_CATEGORY.to_owned(), message))
+            },
+        };
+
+        let stats = self.parsers.entry(parser).or_default();
+        stats.attempted += 1;
+        match failure {
+            None => stats.succeeded += 1,
+            Some((category, message)) => {
+                debug!(?path, %parser, %category, "parser failed: {}", message);
+                stats.failed += 1;
+                *stats.errors.entry(category.clone()).or_default() += 1;
+                self.failures.push(Failure {
+                    path: path.to_owned(),
+                    parser,
+                    category,
+                    message,
+                });
+            },
+        }
+        stats.success_rate = stats.succeeded as f64 / stats.attempted as f64;
+    }
+
+    /// Whether any parser has panicked.
+    pub fn has_panics(&self) -> bool {
+        self.parsers.values()
+            .any(|stats| stats.errors.contains_key(PANIC_CATEGORY))
+    }
+}
+impl fmt::Display for VerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} files verified, {} unreadable", self.files, self.unreadable.len())?;
+        writeln!(f, "{:<14}  {:>9}  {:>9}  {:>9}  {:>7}", "parser", "attempted", "succeeded", "failed", "rate")?;
+        for (parser, stats) in &self.parsers {
+            writeln!(
+                f, "{:<14}  {:>9}  {:>9}  {:>9}  {:>6.2}%",
+                parser.to_string(), stats.attempted, stats.succeeded, stats.failed, 100.0 * stats.success_rate,
+            )?;
+            for (category, count) in &stats.errors {
+                writeln!(f, "  {:<28}  {:>9}", category, count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/// Names a failure by its kind, without the details that differ between occurrences.
+fn error_category(error: &Error) -> String {
+    fn io_category(error: &io::Error) -> String {
+        match error.kind() {
+            io::ErrorKind::TimedOut => TIMEOUT_CATEGORY.to_owned(),
+            other => format!("io/{:?}", other),
+        }
+    }
+
+    fn decompression_category(error: &DecompressionError) -> String {
+        match error {
+            DecompressionError::Io(e) => io_category(e),
+            DecompressionError::InContext { error, .. } => decompression_category(error),
+            other => format!("decompression/{}", variant_name(other)),
+        }
+    }
+
+    match error {
+        Error::Io(e) => io_category(e),
+        Error::Decompression(e) => decompression_category(e),
+        Error::TimeLimitExceeded(_) => TIMEOUT_CATEGORY.to_owned(),
+        other => variant_name(other),
+    }
+}
+
+/// Returns the name of the enum variant from the debug representation of the value.
+fn variant_name<T: fmt::Debug>(value: &T) -> String {
+    let debug = format!("{:?}", value);
+    let end = debug.find(|c: char| !c.is_alphanumeric() && c != '_')
+        .unwrap_or(debug.len());
+    debug[..end].to_owned()
+}
+
+
+/// Returns the files below the directory, sorted by path, and records the directories that
+/// cannot be read.
+fn collect_files(top_path: &Path, report: &mut VerificationReport) -> Vec<PathBuf> {
+    let mut file_list = Vec::new();
+    let mut dir_stack = vec![top_path.to_owned()];
+    while let Some(path) = dir_stack.pop() {
+        let entries = match read_dir(&path) {
+            Ok(e) => e,
+            Err(e) => {
+                error!("failed to read directory {}: {}", path.display(), e);
+                report.unreadable.push(path);
+                continue;
+            },
+        };
+        for entry_res in entries {
+            let entry = match entry_res {
+                Ok(e) => e,
+                Err(e) => {
+                    error!("failed to read directory entry from {}: {}", path.display(), e);
+                    report.unreadable.push(path.clone());
+                    continue;
+                },
+            };
+            match entry.file_type() {
+                Ok(t) if t.is_dir() => dir_stack.push(entry.path()),
+                Ok(t) if t.is_file() => file_list.push(entry.path()),
+                Ok(_) => {},
+                Err(e) => {
+                    error!("failed to read type of {}: {}", entry.path().display(), e);
+                    report.unreadable.push(entry.path());
+                },
+            }
+        }
+    }
+    file_list.sort_unstable();
+    file_list
+}
+
+
+/// Runs every applicable parser over each file below the directory, giving each file the time
+/// limit, if any.
+pub(crate) fn verify_corpus(corpus: &Path, time_limit: Option<Duration>) -> VerificationReport {
+    let mut report = VerificationReport::new();
+
+    // the panics are part of the report; don't also spew them onto stderr
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(|info| debug!("parser panicked: {}", info)));
+
+    for path in collect_files(corpus, &mut report) {
+        let data = match std::fs::read(&path) {
+            Ok(d) => d,
+            Err(e) => {
+                error!("failed to read {}: {}", path.display(), e);
+                report.unreadable.push(path);
+                continue;
+            },
+        };
+        let _deadline = deadline::set_time_limit(time_limit);
+        verify_file(&path, &data, &mut report);
+    }
+
+    panic::set_hook(previous_hook);
+    report
+}
+
+/// Runs every applicable parser over the file data.
+pub(crate) fn verify_file(path: &Path, data: &[u8], report: &mut VerificationReport) {
+    report.files += 1;
+
+    let mut identified = None;
+    report.run(path, Parser::Interpret, || {
+        identified = Some(crate::formats::interpret_file(data)?);
+        Ok(())
+    });
+    report.run(path, Parser::Info, || crate::info::summarize(data).map(|_| ()));
+
+    match identified {
+        Some(IdentifiedFile::SymbolExporter(exporter)) => {
+            report.run(path, Parser::Symbols, || exporter.read_symbols().map(|_| ()));
+        },
+        Some(IdentifiedFile::MultiFileContainer(container)) => {
+            let mut file_paths = None;
+            report.run(path, Parser::ListFiles, || {
+                file_paths = Some(container.list_files()?);
+                Ok(())
+            });
+            if let Some(file_paths) = file_paths {
+                report.run(path, Parser::ExtractFiles, || {
+                    for file_path in &file_paths {
+                        container.read_file(file_path)?;
+                    }
+                    Ok(())
+                });
+            }
+        },
+        Some(IdentifiedFile::SingleFileContainer(container)) => {
+            report.run(path, Parser::ExtractFiles, || container.read_file().map(|_| ()));
+        },
+        Some(IdentifiedFile::Metadata(_))|Some(IdentifiedFile::Unidentified)|None => {},
+    }
+
+    let new_header_signature = data.get(0..2)
+        .filter(|mz| *mz == b"MZ")
+        .and_then(|_| data.get(0x3C..0x40))
+        .and_then(|offset| usize::try_from(u32::from_le_bytes(offset.try_into().unwrap())).ok())
+        .and_then(|offset| data.get(offset..offset+2));
+    match new_header_signature {
+        Some(b"PE") => verify_pe(path, data, report),
+        Some(b"NE") => {
+            report.run(path, Parser::NeHeaders, || {
+                binms::ne::Executable::read(&mut Cursor::new(data))?;
+                Ok(())
+            });
+        },
+        _ => {},
+    }
+
+    if data.starts_with(b"DI") {
+        report.run(path, Parser::DbgFile, || verify_dbg_file(data));
+    }
+}
+
+fn verify_pe(path: &Path, data: &[u8], report: &mut VerificationReport) {
+    let mut executable = None;
+    report.run(path, Parser::PeHeaders, || {
+        executable = Some(pe::Executable::read(&mut Cursor::new(data))?);
+        Ok(())
+    });
+    let Some(executable) = executable else { return };
+    let Some(OptionalHeader::Coff(coff)) = executable.optional_header.as_ref() else { return };
+    let Some(windows) = coff.optional_windows_header.as_ref() else { return };
+    let is_pe32_plus = coff.magic == pe::PE32_PLUS_MAGIC;
+    let section_table = &executable.section_table;
+
+    if let Some(import_entry) = windows.known_data_directory_entry(KnownDataDirectoryEntry::ImportTable).filter(|e| e.size > 0) {
+        report.run(path, Parser::PeImports, || {
+            let mut reader = Cursor::new(data);
+            let descriptors = ImportDescriptor::read_all(&mut reader, &import_entry, section_table)?;
+            for descriptor in &descriptors {
+                descriptor.read_lookup_entries(&mut reader, section_table, is_pe32_plus)?;
+            }
+            Ok(())
+        });
+    }
+
+    if let Some(resource_entry) = windows.known_data_directory_entry(KnownDataDirectoryEntry::ResourceTable).filter(|e| e.size > 0) {
+        report.run(path, Parser::PeResources, || {
+            ResourceDirectoryTable::read_root_from_pe(&mut Cursor::new(data), &resource_entry, section_table)?;
+            Ok(())
+        });
+    }
+
+    if let Some(debug_entry) = windows.known_data_directory_entry(KnownDataDirectoryEntry::Debug).filter(|e| e.size > 0) {
+        report.run(path, Parser::PeDebugInfo, || {
+            let debug_offset = section_table.virtual_to_raw(debug_entry.address)
+                .ok_or(io::Error::from(io::ErrorKind::InvalidData))?;
+            let mut reader = Cursor::new(data);
+            reader.seek(SeekFrom::Start(debug_offset.into()))?;
+            let mut directories = Vec::new();
+            for _ in 0..debug_entry.size / DEBUG_DIRECTORY_ENTRY_SIZE {
+                directories.push(DebugDirectory::read(&mut reader)?);
+            }
+            verify_code_view(data, &directories)
+        });
+    }
+}
+
+fn verify_dbg_file(data: &[u8]) -> Result<(), Error> {
+    let dbg_file = DbgFile::read(&mut Cursor::new(data))?;
+    for coff_entry in dbg_file.debug_directories.iter().filter(|entry| entry.kind == DebugType::Coff) {
+        let coff_data = debug_data(data, coff_entry)?;
+        binms::coff::CoffDebugInfo::read(&mut Cursor::new(coff_data))?;
+    }
+    verify_code_view(data, &dbg_file.debug_directories)
+}
+
+/// Reads the CodeView information in the `NBxx` formats referenced by the debug directories; the
+/// newer formats only point to a PDB file.
+fn verify_code_view(data: &[u8], directories: &[DebugDirectory]) -> Result<(), Error> {
+    for entry in directories.iter().filter(|entry| entry.kind == DebugType::CodeView) {
+        let code_view_data = debug_data(data, entry)?;
+        if code_view_data.starts_with(b"NB") {
+            binms::code_view::DebugInfo::read(&mut Cursor::new(code_view_data))?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the data referenced by a debug directory entry.
+fn debug_data<'d>(data: &'d [u8], entry: &DebugDirectory) -> Result<&'d [u8], io::Error> {
+    let start = usize::try_from(entry.raw_data_pointer).unwrap();
+    let end = start.checked_add(usize::try_from(entry.size).unwrap());
+    end.and_then(|end| data.get(start..end))
+        .ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::path::Path;
+    use std::time::Duration;
+
+    use binstruct::deadline;
+    use testgen::pe;
+
+    use super::{error_category, Parser, VerificationReport, TIMEOUT_CATEGORY};
+    use crate::data_mgmt::Error;
+
+    #[test]
+    fn test_verify_synthesized_files() {
+        let mut library = pe::PortableExecutable::new("SYNTH.DLL");
+        library.exports.push(pe::Export {
+            ordinal: 1,
+            name: Some("Function".to_owned()),
+            target: pe::ExportTarget::Code { rva: pe::TEXT_RVA },
+        });
+        library.resources.push(pe::Resource { type_id: 16, name_id: 1, language_id: 0x0409, data: b"version".to_vec() });
+        let bytes = library.to_bytes();
+
+        let mut report = VerificationReport::new();
+        super::verify_file(Path::new("synth.dll"), &bytes, &mut report);
+        super::verify_file(Path::new("truncated.dll"), &bytes[..0x300], &mut report);
+
+        assert_eq!(report.files, 2);
+        assert_eq!(report.parsers[&Parser::Interpret].attempted, 2);
+        assert_eq!(report.parsers[&Parser::PeHeaders].succeeded, 2);
+        let resources = &report.parsers[&Parser::PeResources];
+        assert_eq!((resources.succeeded, resources.failed), (1, 1));
+        assert_eq!(resources.success_rate, 0.5);
+        assert!(report.failures.iter().all(|f| f.path == Path::new("truncated.dll")));
+        assert!(!report.has_panics());
+
+        report.run(Path::new("crash.bin"), Parser::Info, || panic!("index out of bounds"));
+        assert!(report.has_panics());
+        assert_eq!(report.failures.last().unwrap().message, "index out of bounds");
+    }
+
+    #[test]
+    fn test_verify_with_time_limit() {
+        let mut library = pe::PortableExecutable::new("SYNTH.DLL");
+        library.resources.push(pe::Resource { type_id: 16, name_id: 1, language_id: 0x0409, data: b"version".to_vec() });
+        let bytes = library.to_bytes();
+
+        let mut report = VerificationReport::new();
+        {
+            let _deadline = deadline::set_time_limit(Some(Duration::ZERO));
+            super::verify_file(Path::new("synth.dll"), &bytes, &mut report);
+        }
+        let resources = &report.parsers[&Parser::PeResources];
+        assert_eq!(resources.failed, 1);
+        assert_eq!(resources.errors.get(TIMEOUT_CATEGORY), Some(&1));
+        assert!(!report.has_panics());
+    }
+
+    #[test]
+    fn test_error_category() {
+        assert_eq!(error_category(&Error::Io(io::ErrorKind::UnexpectedEof.into())), "io/UnexpectedEof");
+        assert_eq!(
+            error_category(&Error::Decompression(expandms::DecompressionError::ChecksumMismatch { expected: 1, calculated: 2 })),
+            "decompression/ChecksumMismatch",
+        );
+        assert_eq!(error_category(&Error::SpannedFile), "SpannedFile");
+        assert_eq!(error_category(&Error::TimeLimitExceeded(Duration::from_secs(5))), TIMEOUT_CATEGORY);
+        assert_eq!(error_category(&Error::Io(io::ErrorKind::TimedOut.into())), TIMEOUT_CATEGORY);
+        assert_eq!(
+            error_category(&Error::Decompression(expandms::DecompressionError::Io(io::ErrorKind::TimedOut.into()))),
+            TIMEOUT_CATEGORY,
+        );
+    }
+}
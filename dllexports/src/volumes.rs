@@ -0,0 +1,331 @@
+//! Finding the other volumes of archives split across multiple disks.
+//!
+//! Setup programs spread large archives across floppy disks. Cabinet sets name the previous and
+//! next cabinet in each header, with the data of a folder continuing from one cabinet to the next;
+//! other archives are simply cut into numbered parts (`foo.cab`, `foo2.cab`, ... or `disk1.dl_`,
+//! `disk2.dl_`, ...). Once the disks have been copied into one directory or placed on one CD, the
+//! volumes are found next to each other.
+
+
+use std::fs;
+use std::io::{self, Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use expandms::cab::{CabFlags, CabHeader};
+use tracing::{debug, warn};
+
+use crate::data_mgmt::MultiFileContainer;
+
+
+/// The most volumes that are joined, in case the names in a set of cabinets form a loop.
+const MAX_VOLUMES: usize = 256;
+
+const SZDD_SIGNATURE: &[u8] = b"SZDD\x88\xF0\x27\x33";
+const SZDD_HEADER_LENGTH: usize = 14;
+
+
+/// The files next to an archive, among which its other volumes are looked for.
+pub(crate) trait Siblings {
+    /// Reads the file with the given name next to the archive, comparing names case-insensitively
+    /// as DOS does. Returns `None` if there is no such file or it cannot be read.
+    fn read_sibling(&self, name: &str) -> Option<Vec<u8>>;
+}
+
+
+/// The siblings found in any of several places, which are searched in order; e.g. the same
+/// directory on each disk of a set.
+impl<S: Siblings> Siblings for Vec<S> {
+    fn read_sibling(&self, name: &str) -> Option<Vec<u8>> {
+        self.iter().find_map(|s| s.read_sibling(name))
+    }
+}
+
+
+/// An archive without siblings, e.g. one read from standard input.
+pub(crate) struct NoSiblings;
+impl Siblings for NoSiblings {
+    fn read_sibling(&self, _name: &str) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+
+/// The files in a directory on disk.
+pub(crate) struct DirectorySiblings<'p> {
+    dir: &'p Path,
+}
+impl<'p> DirectorySiblings<'p> {
+    pub fn new(dir: &'p Path) -> Self {
+        Self {
+            dir,
+        }
+    }
+
+    /// The siblings of the file at the given path.
+    pub fn of_file(path: &'p Path) -> Self {
+        Self::new(path.parent().unwrap_or(Path::new(".")))
+    }
+}
+impl<'p> Siblings for DirectorySiblings<'p> {
+    fn read_sibling(&self, name: &str) -> Option<Vec<u8>> {
+        let dir = if self.dir.as_os_str().is_empty() { Path::new(".") } else { self.dir };
+        let entry = fs::read_dir(dir).ok()?
+            .filter_map(|e| e.ok())
+            .find(|e| e.file_name().to_string_lossy().eq_ignore_ascii_case(name))?;
+        match fs::read(entry.path()) {
+            Ok(data) => Some(data),
+            Err(e) => {
+                warn!("failed to read {}: {}", entry.path().display(), e);
+                None
+            },
+        }
+    }
+}
+
+
+/// The files in the same directory of a container.
+pub(crate) struct ContainerSiblings<'c> {
+    container: &'c dyn MultiFileContainer,
+    files: &'c [PathBuf],
+    dir: &'c Path,
+}
+impl<'c> ContainerSiblings<'c> {
+    /// The siblings of `path` among `files`, which are the files listed by the container.
+    pub fn new(container: &'c dyn MultiFileContainer, files: &'c [PathBuf], path: &'c Path) -> Self {
+        Self {
+            container,
+            files,
+            dir: path.parent().unwrap_or(Path::new("")),
+        }
+    }
+}
+impl<'c> Siblings for ContainerSiblings<'c> {
+    fn read_sibling(&self, name: &str) -> Option<Vec<u8>> {
+        let path = self.files.iter()
+            .filter(|f| f.parent().unwrap_or(Path::new("")) == self.dir)
+            .find(|f| f.file_name().is_some_and(|n| n.to_string_lossy().eq_ignore_ascii_case(name)))?;
+        match self.container.read_file(path) {
+            Ok(data) => Some(data.into_owned()),
+            Err(e) => {
+                warn!("failed to read {:?}: {}", path, e);
+                None
+            },
+        }
+    }
+}
+
+
+/// Returns the name of the volume following the one with the given name by the usual naming
+/// convention: the number at the end of the name (before the extension) is incremented, and a
+/// name without a number is taken to be the first volume.
+///
+/// `foo.cab` and `foo1.cab` are both followed by `foo2.cab`.
+pub(crate) fn next_volume_name(name: &str) -> Option<String> {
+    let (stem, extension) = match name.rfind('.') {
+        Some(dot_index) => name.split_at(dot_index),
+        None => (name, ""),
+    };
+    let prefix = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+    let digits = &stem[prefix.len()..];
+    let next_number = if digits.is_empty() {
+        2
+    } else {
+        digits.parse::<u32>().ok()?.checked_add(1)?
+    };
+    Some(format!("{}{}{}", prefix, next_number, extension))
+}
+
+
+/// Returns all the volumes of the set of cabinets the given cabinet belongs to, in order, or `None`
+/// if the cabinet is not part of a set.
+///
+/// The previous and next cabinets are found by the names in the headers or, if no file with the
+/// next cabinet's name exists, by [`next_volume_name`]. Cabinets of a different set (with a
+/// different set ID) are not joined. If a volume cannot be found, the set is cut short there.
+pub(crate) fn cabinet_set(data: &[u8], name: &str, siblings: &dyn Siblings) -> Option<Vec<Vec<u8>>> {
+    let header = CabHeader::read(&mut Cursor::new(data)).ok()?;
+    if !header.flags.intersects(CabFlags::PREV_CABINET | CabFlags::NEXT_CABINET) {
+        return None;
+    }
+
+    let mut earlier_volumes = Vec::new();
+    let mut current = header.clone();
+    while current.flags.contains(CabFlags::PREV_CABINET) && earlier_volumes.len() < MAX_VOLUMES {
+        let Some(previous_name) = current.previous_cabinet_name.as_ref().map(|n| decode_name(n)) else {
+            break;
+        };
+        let Some((previous_data, previous_header)) = read_volume(siblings, &previous_name, header.set_id) else {
+            warn!("previous cabinet {:?} of {:?} not found", previous_name, name);
+            break;
+        };
+        earlier_volumes.push(previous_data);
+        current = previous_header;
+    }
+
+    let mut volumes = earlier_volumes;
+    volumes.reverse();
+    volumes.push(data.to_vec());
+
+    let mut current = header;
+    let mut current_name = name.to_owned();
+    while current.flags.contains(CabFlags::NEXT_CABINET) && volumes.len() < MAX_VOLUMES {
+        let candidate_names: Vec<String> = current.next_cabinet_name.as_ref().map(|n| decode_name(n))
+            .into_iter()
+            .chain(next_volume_name(&current_name))
+            .collect();
+        let next = candidate_names.into_iter()
+            .find_map(|n| read_volume(siblings, &n, current.set_id).map(|(d, h)| (n, d, h)));
+        let Some((next_name, next_data, next_header)) = next else {
+            warn!("next cabinet of {:?} not found", current_name);
+            break;
+        };
+        volumes.push(next_data);
+        current = next_header;
+        current_name = next_name;
+    }
+
+    Some(volumes)
+}
+
+/// Returns whether a cabinet continues a set whose previous cabinet is among `siblings`.
+///
+/// Such a cabinet is listed along with the first cabinet of its set and not on its own.
+pub(crate) fn is_cabinet_continuation(data: &[u8], siblings: &dyn Siblings) -> bool {
+    let Ok(header) = CabHeader::read(&mut Cursor::new(data)) else {
+        return false;
+    };
+    if !header.flags.contains(CabFlags::PREV_CABINET) {
+        return false;
+    }
+    header.previous_cabinet_name.as_ref()
+        .is_some_and(|n| read_volume(siblings, &decode_name(n), header.set_id).is_some())
+}
+
+fn read_volume(siblings: &dyn Siblings, name: &str, set_id: u16) -> Option<(Vec<u8>, CabHeader)> {
+    let data = siblings.read_sibling(name)?;
+    let header = CabHeader::read(&mut Cursor::new(data.as_slice())).ok()?;
+    if header.set_id != set_id {
+        debug!("{:?} belongs to cabinet set {:#06X}, not {:#06X}", name, header.set_id, set_id);
+        return None;
+    }
+    Some((data, header))
+}
+
+/// Decodes a cabinet name in the naive ISO 8859-1 way, as the names of files in cabinets are.
+fn decode_name(name: &[u8]) -> String {
+    name.iter()
+        .map(|b| char::from(*b))
+        .collect()
+}
+
+
+/// An SZDD file joined together from its numbered parts.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) struct JoinedParts {
+    /// The data of all the parts, joined together.
+    pub data: Vec<u8>,
+
+    /// The names of the parts following the first, in order.
+    pub continuation_names: Vec<String>,
+}
+
+
+/// Returns the data of an SZDD file split into numbered parts, joined together, or `None` if the
+/// file is not split.
+///
+/// An SZDD file declares the length of its expanded data in its header. If the data expands to
+/// less than that, the rest of the compressed data is taken from the following parts, named by
+/// [`next_volume_name`]; parts with an SZDD header of their own are joined without it. The data is
+/// expanded once, with each part read as soon as the expansion reaches it.
+pub(crate) fn szdd_parts(data: &[u8], name: &str, siblings: &dyn Siblings) -> Option<JoinedParts> {
+    if !data.starts_with(SZDD_SIGNATURE) || data.len() < SZDD_HEADER_LENGTH {
+        return None;
+    }
+
+    let mut reader = PartReader {
+        siblings,
+        first_name: name,
+        part_name: name.to_owned(),
+        joined: data.to_vec(),
+        position: 0,
+        continuation_names: Vec::new(),
+        out_of_parts: false,
+    };
+    let mut expanded = expandms::CountingWriter::new(io::sink());
+    if let Err(e) = expandms::decompress(&mut reader, &mut expanded) {
+        debug!("expansion of {:?} stopped after {} bytes: {}", name, expanded.count(), e);
+    }
+
+    if reader.continuation_names.is_empty() {
+        None
+    } else {
+        Some(JoinedParts {
+            data: reader.joined,
+            continuation_names: reader.continuation_names,
+        })
+    }
+}
+
+/// Reads the parts of a split SZDD file one after the other, joining them as it goes.
+struct PartReader<'s> {
+    siblings: &'s dyn Siblings,
+    first_name: &'s str,
+    part_name: String,
+    joined: Vec<u8>,
+    position: usize,
+    continuation_names: Vec<String>,
+    out_of_parts: bool,
+}
+impl PartReader<'_> {
+    /// Appends the next part to the joined data, returning whether there is one.
+    fn append_next_part(&mut self) -> bool {
+        if self.out_of_parts || self.continuation_names.len() + 1 >= MAX_VOLUMES {
+            return false;
+        }
+        let Some(next_name) = next_volume_name(&self.part_name) else {
+            self.out_of_parts = true;
+            return false;
+        };
+        let Some(part) = self.siblings.read_sibling(&next_name) else {
+            warn!("{:?} is incomplete but its next part {:?} was not found", self.first_name, next_name);
+            self.out_of_parts = true;
+            return false;
+        };
+        if part.starts_with(SZDD_SIGNATURE) && part.len() >= SZDD_HEADER_LENGTH {
+            self.joined.extend_from_slice(&part[SZDD_HEADER_LENGTH..]);
+        } else {
+            self.joined.extend_from_slice(&part);
+        }
+        self.continuation_names.push(next_name.clone());
+        self.part_name = next_name;
+        true
+    }
+}
+impl Read for PartReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // the expansion only asks for more once it has not reached the declared length yet
+        while self.position >= self.joined.len() {
+            if !self.append_next_part() {
+                return Ok(0);
+            }
+        }
+        let count = buf.len().min(self.joined.len() - self.position);
+        buf[..count].copy_from_slice(&self.joined[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::next_volume_name;
+
+    #[test]
+    fn test_next_volume_name() {
+        assert_eq!(next_volume_name("foo.cab").as_deref(), Some("foo2.cab"));
+        assert_eq!(next_volume_name("foo1.cab").as_deref(), Some("foo2.cab"));
+        assert_eq!(next_volume_name("DISK9.DL_").as_deref(), Some("DISK10.DL_"));
+        assert_eq!(next_volume_name("setup").as_deref(), Some("setup2"));
+    }
+}
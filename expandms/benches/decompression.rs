@@ -0,0 +1,144 @@
+//! Benchmarks of reading FAT floppy disk images, inflating DEFLATE streams, decompressing XPRESS
+//! chunks and calculating CRCs.
+
+
+use std::hint::black_box;
+use std::io::{Cursor, Write};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use expandms::crc::{CRC_16_ARC, CRC_32_ISO_HDLC};
+use expandms::fat::{AllocationTableCopies, FatHeader};
+use expandms::inflate::{Inflater, MAX_LOOKBACK_DISTANCE};
+use expandms::xpress::decompress_with_size;
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
+use testgen::fat;
+use testgen::memory::report_peak_rss_growth;
+use testgen::xpress;
+
+
+/// Data that compresses about as well as the text and code found in setup files: words from a
+/// small vocabulary interspersed with less compressible binary runs.
+fn representative_data(length: usize) -> Vec<u8> {
+    const WORDS: [&[u8]; 8] = [
+        b"Setup ", b"Windows ", b"cannot ", b"copy ", b"the ", b"file ", b"SYSTEM.INI ", b"\r\n",
+    ];
+    let mut data = Vec::with_capacity(length);
+    let mut state: u32 = 0x1234_5678;
+    while data.len() < length {
+        // xorshift
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        if state.is_multiple_of(16) {
+            data.extend_from_slice(&state.to_le_bytes());
+        } else {
+            data.extend_from_slice(WORDS[(state % 8) as usize]);
+        }
+    }
+    data.truncate(length);
+    data
+}
+
+
+fn bench_fat(c: &mut Criterion) {
+    let files: Vec<fat::File> = (0..8)
+        .map(|i| fat::File {
+            name: format!("DISK{}.CAB", i),
+            data: representative_data(160 * 1024),
+        })
+        .collect();
+    let image = fat::floppy("BENCHMARK", &files);
+
+    let read_allocation_table = || {
+        let mut reader = Cursor::new(&image);
+        let header = FatHeader::read(&mut reader).unwrap();
+        let copies = AllocationTableCopies::read(&mut reader, &header).unwrap();
+        // the first file starts at the first data cluster
+        let (_, fat) = copies.primary();
+        fat.cluster_chain(2).unwrap()
+    };
+    let read_file = || {
+        let mut reader = Cursor::new(&image);
+        let header = FatHeader::read(&mut reader).unwrap();
+        let copies = AllocationTableCopies::read(&mut reader, &header).unwrap();
+        let mut output = Vec::new();
+        copies.read_cluster_chain_into(&mut reader, &header, 2, &mut output).unwrap();
+        output
+    };
+
+    report_peak_rss_growth("fat/read_file", read_file);
+    let mut group = c.benchmark_group("fat");
+    group.bench_function("allocation_table", |b| b.iter(|| black_box(read_allocation_table())));
+    group.throughput(Throughput::Bytes(files[0].data.len() as u64));
+    group.bench_function("read_file", |b| b.iter(|| black_box(read_file())));
+    group.finish();
+}
+
+
+fn bench_inflate(c: &mut Criterion) {
+    let data = representative_data(1024 * 1024);
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&data).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let inflate = || {
+        let mut reader = Cursor::new(&compressed);
+        let mut inflater = Inflater::new(&mut reader, MAX_LOOKBACK_DISTANCE);
+        let mut output = Vec::with_capacity(data.len());
+        while !inflater.inflate_block(&mut output).unwrap() {
+        }
+        output
+    };
+    let output = report_peak_rss_growth("inflate", inflate);
+    assert_eq!(output, data);
+
+    let mut group = c.benchmark_group("inflate");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("representative", |b| b.iter(|| black_box(inflate())));
+    group.finish();
+}
+
+
+/// The size of the chunks of WIM resources.
+const WIM_CHUNK_SIZE: usize = 32768;
+
+fn bench_xpress_chunks(c: &mut Criterion) {
+    let data = representative_data(8 * 1024 * 1024);
+    let compressed_chunks: Vec<(Vec<u8>, usize)> = data.chunks(WIM_CHUNK_SIZE)
+        .map(|chunk| (xpress::compress(chunk), chunk.len()))
+        .collect();
+    let chunks: Vec<(&[u8], usize)> = compressed_chunks.iter()
+        .map(|(compressed, size)| (compressed.as_slice(), *size))
+        .collect();
+
+    let decompress = || {
+        let mut output = Vec::with_capacity(data.len());
+        for (chunk, decompressed_size) in &chunks {
+            output.extend_from_slice(&decompress_with_size(chunk, *decompressed_size).unwrap());
+        }
+        output
+    };
+    let output = report_peak_rss_growth("xpress_chunks", decompress);
+    assert_eq!(output, data);
+
+    let mut group = c.benchmark_group("xpress_chunks");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("sequential", |b| b.iter(|| black_box(decompress())));
+    group.finish();
+}
+
+
+fn bench_crc(c: &mut Criterion) {
+    let data = representative_data(1024 * 1024);
+
+    let mut group = c.benchmark_group("crc");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("crc_16_arc", |b| b.iter(|| black_box(CRC_16_ARC.checksum(&data))));
+    group.bench_function("crc_32_iso_hdlc", |b| b.iter(|| black_box(CRC_32_ISO_HDLC.checksum(&data))));
+    group.finish();
+}
+
+
+criterion_group!(benches, bench_fat, bench_inflate, bench_xpress_chunks, bench_crc);
+criterion_main!(benches);
@@ -0,0 +1,284 @@
+//! Reading ARJ archives.
+//!
+//! An ARJ archive starts with a main header, followed by a local header for each file, each
+//! directly followed by the file's compressed data; a header of size 0 ends the archive. Every
+//! header starts with the identifier `0x60 0xEA` and is followed by any number of extended headers.
+//!
+//! Files stored without compression (method 0) and those compressed using methods 1 to 3, which
+//! use the same static Huffman coding as LHA's `-lh7-` with a smaller window (see
+//! [`crate::lha`]), can be extracted. Method 4 and encrypted ("garbled") files are not supported.
+
+
+use std::io::{self, Write};
+
+use bitflags::bitflags;
+use tracing::debug;
+
+use crate::DecompressionError;
+use crate::crc::{CrcWriter, CRC_32_ISO_HDLC};
+use crate::lha::{decompress_lzh, LzhParameters};
+
+
+pub const HEADER_ID: [u8; 2] = [0x60, 0xEA];
+
+/// The maximum size of a basic header.
+const MAX_BASIC_HEADER_SIZE: usize = 2600;
+
+/// The size of the fixed part of a basic header.
+const MIN_FIRST_HEADER_SIZE: usize = 30;
+
+
+bitflags! {
+    #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    pub struct ArjFlags : u8 {
+        const GARBLED = 0x01;
+        const VOLUME = 0x04;
+        const EXTFILE = 0x08;
+        const PATHSYM = 0x10;
+        const BACKUP = 0x20;
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum FileType {
+    Binary,
+    Text,
+    MainHeader,
+    Directory,
+    VolumeLabel,
+    ChapterLabel,
+    Other(u8),
+}
+impl FileType {
+    fn from_base_type(value: u8) -> Self {
+        match value {
+            0 => Self::Binary,
+            1 => Self::Text,
+            2 => Self::MainHeader,
+            3 => Self::Directory,
+            4 => Self::VolumeLabel,
+            5 => Self::ChapterLabel,
+            other => Self::Other(other),
+        }
+    }
+}
+
+
+/// A header of an ARJ archive: the main header or the local header of a file.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ArjEntry {
+    /// The offset of the header.
+    pub header_offset: usize,
+
+    pub archiver_version: u8,
+    pub host_os: u8,
+    pub flags: ArjFlags,
+    pub method: u8,
+    pub file_type: FileType,
+    pub compressed_size: u32,
+    pub original_size: u32,
+    pub crc32: u32,
+
+    /// The path of the file, with directories separated by `/`.
+    pub path: Vec<u8>,
+
+    pub comment: Vec<u8>,
+
+    /// The offset of the compressed data, after the extended headers.
+    pub data_offset: usize,
+}
+impl ArjEntry {
+    /// Reads the header at the given offset in the data.
+    ///
+    /// Returns `None` if the header marks the end of the archive.
+    pub fn read(data: &[u8], offset: usize) -> Result<Option<Self>, io::Error> {
+        let Some(id_and_size) = data.get(offset..offset+4) else {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        };
+        if id_and_size[0..2] != HEADER_ID {
+            debug!("no ARJ header at {:#X}", offset);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let basic_size = usize::from(u16::from_le_bytes(id_and_size[2..4].try_into().unwrap()));
+        if basic_size == 0 {
+            return Ok(None);
+        }
+        if basic_size > MAX_BASIC_HEADER_SIZE {
+            debug!("ARJ header at {:#X} is too large ({} bytes)", offset, basic_size);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let basic_offset = offset + 4;
+        let Some(basic) = data.get(basic_offset..basic_offset+basic_size) else {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        };
+        let first_size = usize::from(basic[0]);
+        if first_size < MIN_FIRST_HEADER_SIZE || first_size > basic_size {
+            debug!("invalid first header size {} in ARJ header at {:#X}", first_size, offset);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        if !header_crc_matches(data, basic_offset, basic_size) {
+            debug!("CRC of ARJ header at {:#X} does not match", offset);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+
+        let archiver_version = basic[1];
+        let host_os = basic[3];
+        let flags = ArjFlags::from_bits_retain(basic[4]);
+        let method = basic[5];
+        let file_type = FileType::from_base_type(basic[6]);
+        let compressed_size = u32::from_le_bytes(basic[12..16].try_into().unwrap());
+        let original_size = u32::from_le_bytes(basic[16..20].try_into().unwrap());
+        let crc32 = u32::from_le_bytes(basic[20..24].try_into().unwrap());
+
+        let mut strings = basic[first_size..].split(|b| *b == 0x00);
+        let path = strings.next().unwrap_or_default()
+            .iter()
+            .map(|b| if *b == b'\\' { b'/' } else { *b })
+            .collect();
+        let comment = strings.next().unwrap_or_default().to_vec();
+
+        // skip the CRC of the basic header and the extended headers, each followed by its CRC
+        let mut extended_offset = basic_offset + basic_size + 4;
+        loop {
+            let Some(size_bytes) = data.get(extended_offset..extended_offset+2) else {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            };
+            let extended_size = usize::from(u16::from_le_bytes(size_bytes.try_into().unwrap()));
+            extended_offset += 2;
+            if extended_size == 0 {
+                break;
+            }
+            extended_offset += extended_size + 4;
+        }
+
+        Ok(Some(Self {
+            header_offset: offset,
+            archiver_version,
+            host_os,
+            flags,
+            method,
+            file_type,
+            compressed_size,
+            original_size,
+            crc32,
+            path,
+            comment,
+            data_offset: extended_offset,
+        }))
+    }
+
+    /// The offset of the next header.
+    ///
+    /// The main header is not followed by any data.
+    pub fn next_offset(&self) -> usize {
+        if self.file_type == FileType::MainHeader {
+            self.data_offset
+        } else {
+            self.data_offset + usize::try_from(self.compressed_size).unwrap()
+        }
+    }
+
+    /// The compressed data of this entry.
+    pub fn compressed_data<'d>(&self, data: &'d [u8]) -> Result<&'d [u8], io::Error> {
+        data.get(self.data_offset..self.next_offset())
+            .ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+    }
+}
+
+
+/// Whether the data at the given offset looks like the main header of an ARJ archive.
+///
+/// The identifier, sizes and file type are checked along with the CRC of the header.
+pub fn looks_like_archive(data: &[u8], offset: usize) -> bool {
+    let Some(header) = data.get(offset..offset+4+MIN_FIRST_HEADER_SIZE) else { return false };
+    let basic_size = usize::from(u16::from_le_bytes(header[2..4].try_into().unwrap()));
+    let first_size = usize::from(header[4]);
+    header[0..2] == HEADER_ID
+        && basic_size <= MAX_BASIC_HEADER_SIZE
+        && first_size >= MIN_FIRST_HEADER_SIZE
+        && first_size <= basic_size
+        && FileType::from_base_type(header[10]) == FileType::MainHeader
+        && header_crc_matches(data, offset + 4, basic_size)
+}
+
+/// Checks the CRC-32 that follows the basic header at the given offset.
+fn header_crc_matches(data: &[u8], basic_offset: usize, basic_size: usize) -> bool {
+    let Some(basic_and_crc) = data.get(basic_offset..basic_offset+basic_size+4) else { return false };
+    let (basic, crc_bytes) = basic_and_crc.split_at(basic_size);
+    CRC_32_ISO_HDLC.checksum(basic) == u32::from_le_bytes(crc_bytes.try_into().unwrap())
+}
+
+
+/// Reads the main header and the headers of all files of the ARJ archive starting at the given
+/// offset.
+pub fn read_entries(data: &[u8], offset: usize) -> Result<(ArjEntry, Vec<ArjEntry>), io::Error> {
+    let Some(main_header) = ArjEntry::read(data, offset)? else {
+        debug!("ARJ archive at {:#X} ends before its main header", offset);
+        return Err(io::ErrorKind::InvalidData.into());
+    };
+    if main_header.file_type != FileType::MainHeader {
+        debug!("first ARJ header at {:#X} is not a main header", offset);
+        return Err(io::ErrorKind::InvalidData.into());
+    }
+
+    let mut entries = Vec::new();
+    let mut offset = main_header.next_offset();
+    while offset < data.len() {
+        let Some(entry) = ArjEntry::read(data, offset)? else { break };
+        offset = entry.next_offset();
+        entries.push(entry);
+    }
+    Ok((main_header, entries))
+}
+
+
+/// Decompresses the data of a file in an ARJ archive and checks its CRC.
+pub fn decompress<W: Write>(data: &[u8], entry: &ArjEntry, writer: &mut W) -> Result<(), DecompressionError> {
+    if entry.flags.contains(ArjFlags::GARBLED) {
+        return Err(DecompressionError::Encrypted);
+    }
+    let mut compressed = entry.compressed_data(data)?;
+    let mut crc_writer = CrcWriter::new(writer, CRC_32_ISO_HDLC.digest());
+    match entry.method {
+        0 => {
+            crc_writer.write_all(compressed)?;
+        },
+        1..=3 => {
+            decompress_lzh(&mut compressed, &mut crc_writer, entry.original_size.into(), LzhParameters::ARJ)?;
+        },
+        other => {
+            debug!("unsupported ARJ compression method {}", other);
+            return Err(DecompressionError::UnknownCompressionMethod);
+        },
+    }
+
+    let calculated = crc_writer.digest().finalize();
+    if calculated != entry.crc32 {
+        debug!("CRC of ARJ entry at {:#X} is {:#010X}, expected {:#010X}", entry.header_offset, calculated, entry.crc32);
+        return Err(DecompressionError::ChecksumMismatch { expected: entry.crc32, calculated });
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress, read_entries};
+    use crate::DecompressionError;
+    use crate::crc::CRC_32_ISO_HDLC;
+
+    #[test]
+    fn test_checksum_mismatch() {
+        let mut archive = testgen::sfx::arj(&[("README.TXT", b"Read me!")]);
+        let (_main_header, entries) = read_entries(&archive, 0).unwrap();
+        let data_offset = archive.windows(8).position(|w| w == b"Read me!").unwrap();
+        archive[data_offset] = b'F';
+
+        let mut output = Vec::new();
+        let error = decompress(&archive, &entries[0], &mut output).unwrap_err();
+        let DecompressionError::ChecksumMismatch { expected, calculated } = error else { panic!("unexpected error {:?}", error) };
+        assert_eq!(expected, entries[0].crc32);
+        assert_eq!(calculated, CRC_32_ISO_HDLC.checksum(b"Fead me!"));
+    }
+}
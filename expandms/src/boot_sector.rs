@@ -0,0 +1,188 @@
+//! Fingerprinting the boot sectors of FAT volumes.
+//!
+//! The tool that formats a FAT volume writes its own OEM name into the boot sector, and the boot
+//! code it installs looks for the files of a specific operating system loader. Together, they tell
+//! which system prepared a disk even if its directory structure does not, e.g. on a floppy that
+//! only contains a handful of drivers.
+//!
+//! Windows 9x replaces the first five characters of the OEM name with random characters followed
+//! by `IHC` when it first writes to a volume, which loses the name of the formatting system.
+
+
+use std::fmt;
+
+
+/// The size of the boot sector, which contains the OEM name.
+const BOOT_SECTOR_SIZE: usize = 512;
+
+/// Strings found in the boot code along with the loader they identify, in order of precedence.
+///
+/// The MS-DOS and Windows 9x boot code only references `IO.SYS`; Windows 9x is then told apart by
+/// the OEM name.
+const LOADER_SIGNATURES: [(&[u8], BootLoader); 7] = [
+    (b"BOOTMGR", BootLoader::WindowsBootManager),
+    (b"NTLDR", BootLoader::WindowsNt),
+    (b"WINBOOT SYS", BootLoader::Windows9x),
+    (b"IO      SYS", BootLoader::MsDos),
+    (b"IBMBIO  COM", BootLoader::PcDos),
+    (b"KERNEL  SYS", BootLoader::FreeDos),
+    (b"This is not a bootable disk", BootLoader::NotBootable),
+];
+
+
+/// The operating system loader started by the boot code of a volume.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum BootLoader {
+    /// `IO.SYS` and `MSDOS.SYS`.
+    MsDos,
+
+    /// `IO.SYS`, which contains the whole loader since Windows 95.
+    Windows9x,
+
+    /// `IBMBIO.COM` and `IBMDOS.COM`, as also used by DR-DOS.
+    PcDos,
+
+    /// `KERNEL.SYS`.
+    FreeDos,
+
+    /// `NTLDR`, from Windows NT 3.1 to Windows XP and Server 2003.
+    WindowsNt,
+
+    /// `BOOTMGR`, from Windows Vista onward.
+    WindowsBootManager,
+
+    /// The boot code only displays a message that the disk is not bootable, as written by Linux's
+    /// `mkfs.fat`.
+    NotBootable,
+}
+impl BootLoader {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::MsDos => "MS-DOS (IO.SYS)",
+            Self::Windows9x => "Windows 9x (IO.SYS)",
+            Self::PcDos => "PC DOS or DR-DOS (IBMBIO.COM)",
+            Self::FreeDos => "FreeDOS (KERNEL.SYS)",
+            Self::WindowsNt => "Windows NT (NTLDR)",
+            Self::WindowsBootManager => "Windows Vista or later (BOOTMGR)",
+            Self::NotBootable => "nothing (not bootable)",
+        }
+    }
+}
+
+
+/// What the boot sector of a FAT volume reveals about the systems that formatted it and that it
+/// boots.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct BootSectorFingerprint {
+    /// The OEM name at offset 3, generally naming the system that formatted the volume.
+    pub oem_name: [u8; 8],
+
+    /// The system that formatted the volume, as deduced from the OEM name.
+    pub formatted_by: Option<String>,
+
+    /// The loader started by the boot code.
+    pub boot_loader: Option<BootLoader>,
+}
+impl BootSectorFingerprint {
+    /// Fingerprints the reserved sectors at the start of a volume.
+    ///
+    /// The boot code is searched for in all of the given sectors, since FAT32 boot sectors
+    /// continue their boot code in further reserved sectors. Returns `None` if the data is shorter
+    /// than a boot sector.
+    pub fn from_reserved_sectors(data: &[u8]) -> Option<Self> {
+        let boot_sector = data.get(..BOOT_SECTOR_SIZE)?;
+        let oem_name: [u8; 8] = boot_sector[3..11].try_into().unwrap();
+        let formatted_by = system_from_oem_name(&oem_name);
+
+        let boot_loader = LOADER_SIGNATURES.iter()
+            .find(|(signature, _loader)| data.windows(signature.len()).any(|w| w == *signature))
+            .map(|(_signature, loader)| match loader {
+                BootLoader::MsDos if oem_name.starts_with(b"MSWIN4") || oem_name.ends_with(b"IHC") => BootLoader::Windows9x,
+                other => *other,
+            });
+
+        Some(Self {
+            oem_name,
+            formatted_by,
+            boot_loader,
+        })
+    }
+}
+impl fmt::Display for BootSectorFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OEM name \"{}\"", self.oem_name.escape_ascii())?;
+        if let Some(formatted_by) = self.formatted_by.as_ref() {
+            write!(f, " ({})", formatted_by)?;
+        }
+        match self.boot_loader {
+            Some(boot_loader) => write!(f, ", boots {}", boot_loader.description()),
+            None => write!(f, ", boot code not recognized"),
+        }
+    }
+}
+
+
+/// Deduces the system that formatted a volume from the OEM name in its boot sector.
+fn system_from_oem_name(oem_name: &[u8; 8]) -> Option<String> {
+    let version = |prefix_length: usize| {
+        String::from_utf8_lossy(&oem_name[prefix_length..]).trim().to_owned()
+    };
+    let system = match oem_name {
+        b"MSWIN4.0" => "Windows 95".to_owned(),
+        b"MSWIN4.1" => "Windows 95 OSR2, 98 or ME".to_owned(),
+        b"MSDOS5.0" => "MS-DOS 5.0 or later, or Windows NT".to_owned(),
+        b"mkfs.fat" | b"mkdosfs\0" | b"mkdosfs " => "Linux mkfs.fat".to_owned(),
+        b"EXPANDMS" => "expandms".to_owned(),
+        _ if oem_name.ends_with(b"IHC") => "unknown; overwritten by Windows 9x".to_owned(),
+        _ if oem_name.starts_with(b"MSDOS") => format!("MS-DOS {}", version(5)),
+        _ if oem_name.starts_with(b"IBM ") => format!("PC DOS {}", version(3)),
+        _ if oem_name.starts_with(b"MTOOL") => "mtools".to_owned(),
+        _ if oem_name.starts_with(b"FRDOS") || oem_name == b"FreeDOS " => "FreeDOS".to_owned(),
+        _ => return None,
+    };
+    Some(system)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{BootLoader, BootSectorFingerprint};
+
+    fn boot_sector(oem_name: &[u8; 8], boot_code: &[u8]) -> Vec<u8> {
+        let mut sector = vec![0u8; 512];
+        sector[0..3].copy_from_slice(&[0xEB, 0x3C, 0x90]);
+        sector[3..11].copy_from_slice(oem_name);
+        sector[0x100..0x100 + boot_code.len()].copy_from_slice(boot_code);
+        sector[510..512].copy_from_slice(&[0x55, 0xAA]);
+        sector
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let fingerprint = |oem_name, boot_code| BootSectorFingerprint::from_reserved_sectors(&boot_sector(oem_name, boot_code)).unwrap();
+
+        let dos = fingerprint(b"MSDOS3.3", b"IO      SYSMSDOS   SYS");
+        assert_eq!(dos.formatted_by.as_deref(), Some("MS-DOS 3.3"));
+        assert_eq!(dos.boot_loader, Some(BootLoader::MsDos));
+
+        let win98 = fingerprint(b"MSWIN4.1", b"IO      SYSMSDOS   SYS");
+        assert_eq!(win98.boot_loader, Some(BootLoader::Windows9x));
+        assert_eq!(win98.to_string(), "OEM name \"MSWIN4.1\" (Windows 95 OSR2, 98 or ME), boots Windows 9x (IO.SYS)");
+
+        let used_by_win95 = fingerprint(b"\x8a)+\x01zIHC", b"IO      SYSMSDOS   SYSWINBOOT SYS");
+        assert_eq!(used_by_win95.formatted_by.as_deref(), Some("unknown; overwritten by Windows 9x"));
+        assert_eq!(used_by_win95.boot_loader, Some(BootLoader::Windows9x));
+
+        let nt = fingerprint(b"MSDOS5.0", b"NTLDR      \r\nRemove disks or other media.");
+        assert_eq!(nt.boot_loader, Some(BootLoader::WindowsNt));
+
+        let pc_dos = fingerprint(b"IBM  3.3", b"IBMBIO  COMIBMDOS  COM");
+        assert_eq!(pc_dos.formatted_by.as_deref(), Some("PC DOS 3.3"));
+        assert_eq!(pc_dos.boot_loader, Some(BootLoader::PcDos));
+
+        let unknown = fingerprint(b"\0\0\0\0\0\0\0\0", b"");
+        assert_eq!(unknown.to_string(), "OEM name \"\\x00\\x00\\x00\\x00\\x00\\x00\\x00\\x00\", boot code not recognized");
+
+        assert_eq!(BootSectorFingerprint::from_reserved_sectors(&[0u8; 511]), None);
+    }
+}
@@ -13,8 +13,8 @@
 //! the requested file is reached.
 //!
 //! The payload may also be subdivided into multiple cabinet files; a file may span multiple cabinet
-//! files (but only one folder per cabinet file). `expandms` probably won't support extracting
-//! spanned files anytime soon, however.
+//! files (but only one folder per cabinet file). `expandms` only reads the structures of each
+//! cabinet file; joining the folders of a set is left to the caller.
 
 
 use std::io::{self, Read, Seek};
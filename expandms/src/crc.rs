@@ -0,0 +1,280 @@
+//! Calculation of cyclic redundancy checks (CRCs) as used by archive and compression formats.
+//!
+//! A CRC algorithm is described by its width (16 or 32 bits), its generator polynomial, whether the
+//! bits of each byte are processed starting with the least significant one ("reflected", as done by
+//! most formats from the PC world) or the most significant one, the initial value of the register
+//! and the value with which the register is XORed at the end. The commonly used algorithms are
+//! provided as statics named after their entries in the [catalogue of parametrised CRC algorithms].
+//!
+//! The calculation is table-driven and processes eight bytes at a time ("slicing-by-8"), with the
+//! eight tables of each algorithm computed at compile time.
+//!
+//! [catalogue of parametrised CRC algorithms]: https://reveng.sourceforge.io/crc-catalogue/
+
+
+use std::fmt;
+use std::io::{self, Write};
+
+
+/// The number of bytes processed at once, which is also the number of tables.
+const SLICE_LENGTH: usize = 8;
+
+
+macro_rules! impl_crc {
+    ($algorithm:ident, $digest:ident, $type:ty) => {
+        /// The parameters of a CRC algorithm along with its lookup tables.
+        #[derive(Clone, Eq, Hash, PartialEq)]
+        pub struct $algorithm {
+            tables: [[$type; 256]; SLICE_LENGTH],
+            reflected: bool,
+            initial: $type,
+            final_xor: $type,
+        }
+        impl $algorithm {
+            /// Defines a CRC algorithm.
+            ///
+            /// `polynomial` is given in the usual (non-reflected) notation without the highest
+            /// term, e.g. `0x04C11DB7` for the CRC-32 polynomial, regardless of whether the
+            /// algorithm is reflected. Input and output are reflected together, as is the case
+            /// with every algorithm in practical use.
+            pub const fn new(polynomial: $type, reflected: bool, initial: $type, final_xor: $type) -> Self {
+                const WIDTH: u32 = <$type>::BITS;
+
+                let mut tables = [[0; 256]; SLICE_LENGTH];
+                let mut byte = 0;
+                while byte < 256 {
+                    let mut value: $type;
+                    let mut bit = 0;
+                    if reflected {
+                        let reflected_polynomial = polynomial.reverse_bits();
+                        value = byte as $type;
+                        while bit < 8 {
+                            value = if value & 1 != 0 { (value >> 1) ^ reflected_polynomial } else { value >> 1 };
+                            bit += 1;
+                        }
+                    } else {
+                        value = (byte as $type) << (WIDTH - 8);
+                        while bit < 8 {
+                            value = if value >> (WIDTH - 1) != 0 { (value << 1) ^ polynomial } else { value << 1 };
+                            bit += 1;
+                        }
+                    }
+                    tables[0][byte] = value;
+                    byte += 1;
+                }
+
+                // tables[k][b] is the CRC of byte b followed by k zero bytes
+                let mut slice = 1;
+                while slice < SLICE_LENGTH {
+                    let mut byte = 0;
+                    while byte < 256 {
+                        let previous = tables[slice - 1][byte];
+                        tables[slice][byte] = if reflected {
+                            (previous >> 8) ^ tables[0][(previous & 0xFF) as usize]
+                        } else {
+                            (previous << 8) ^ tables[0][(previous >> (WIDTH - 8)) as usize]
+                        };
+                        byte += 1;
+                    }
+                    slice += 1;
+                }
+
+                Self {
+                    tables,
+                    reflected,
+                    initial,
+                    final_xor,
+                }
+            }
+
+            /// Calculates the CRC of the data.
+            pub fn checksum(&self, data: &[u8]) -> $type {
+                let mut digest = self.digest();
+                digest.update(data);
+                digest.finalize()
+            }
+
+            /// Starts calculating the CRC of data that is supplied piece by piece.
+            pub fn digest(&self) -> $digest<'_> {
+                $digest {
+                    algorithm: self,
+                    register: self.initial,
+                }
+            }
+
+            fn update(&self, mut register: $type, data: &[u8]) -> $type {
+                const WIDTH_BYTES: usize = <$type>::BITS as usize / 8;
+
+                let mut slices = data.chunks_exact(SLICE_LENGTH);
+                for slice in &mut slices {
+                    // the register overlaps the first bytes of the slice
+                    let mut bytes: [u8; SLICE_LENGTH] = slice.try_into().unwrap();
+                    let register_bytes = if self.reflected { register.to_le_bytes() } else { register.to_be_bytes() };
+                    for (byte, register_byte) in bytes.iter_mut().zip(register_bytes.iter()).take(WIDTH_BYTES) {
+                        *byte ^= *register_byte;
+                    }
+                    register = bytes.iter()
+                        .enumerate()
+                        .fold(0, |value, (i, byte)| value ^ self.tables[SLICE_LENGTH - 1 - i][usize::from(*byte)]);
+                }
+                for byte in slices.remainder() {
+                    register = if self.reflected {
+                        (register >> 8) ^ self.tables[0][usize::from(register.to_le_bytes()[0] ^ byte)]
+                    } else {
+                        (register << 8) ^ self.tables[0][usize::from(register.to_be_bytes()[0] ^ byte)]
+                    };
+                }
+                register
+            }
+        }
+
+        impl fmt::Debug for $algorithm {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                // the entry of the byte whose only set bit is processed last is the polynomial
+                let polynomial = if self.reflected {
+                    self.tables[0][0x80].reverse_bits()
+                } else {
+                    self.tables[0][1]
+                };
+                f.debug_struct(stringify!($algorithm))
+                    .field("polynomial", &polynomial)
+                    .field("reflected", &self.reflected)
+                    .field("initial", &self.initial)
+                    .field("final_xor", &self.final_xor)
+                    .finish_non_exhaustive()
+            }
+        }
+
+        /// The calculation of a CRC over data that is supplied piece by piece.
+        ///
+        /// Writing to the digest is equivalent to [`update`](Self::update), which allows it to be
+        /// used as the destination of [`std::io::copy`] or as one half of a [`CrcWriter`].
+        #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+        pub struct $digest<'a> {
+            algorithm: &'a $algorithm,
+            register: $type,
+        }
+        impl<'a> $digest<'a> {
+            /// Adds the data to the calculation.
+            pub fn update(&mut self, data: &[u8]) {
+                self.register = self.algorithm.update(self.register, data);
+            }
+
+            /// The CRC of the data supplied so far.
+            pub fn finalize(&self) -> $type {
+                self.register ^ self.algorithm.final_xor
+            }
+        }
+        impl<'a> Write for $digest<'a> {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.update(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_crc!(Crc16Algorithm, Crc16Digest, u16);
+impl_crc!(Crc32Algorithm, Crc32Digest, u32);
+
+
+/// CRC-16/ARC, used by LHA and ARC.
+pub static CRC_16_ARC: Crc16Algorithm = Crc16Algorithm::new(0x8005, true, 0x0000, 0x0000);
+
+/// CRC-16/XMODEM, used by UDF descriptor tags.
+pub static CRC_16_XMODEM: Crc16Algorithm = Crc16Algorithm::new(0x1021, false, 0x0000, 0x0000);
+
+/// CRC-32/ISO-HDLC, the CRC-32 used by ZIP, gzip, ARJ and PNG.
+pub static CRC_32_ISO_HDLC: Crc32Algorithm = Crc32Algorithm::new(0x04C1_1DB7, true, 0xFFFF_FFFF, 0xFFFF_FFFF);
+
+/// CRC-32/BZIP2, the non-reflected variant of CRC-32 used by bzip2.
+pub static CRC_32_BZIP2: Crc32Algorithm = Crc32Algorithm::new(0x04C1_1DB7, false, 0xFFFF_FFFF, 0xFFFF_FFFF);
+
+/// CRC-32/ISCSI (Castagnoli), used by ext4, Btrfs and iSCSI.
+pub static CRC_32_ISCSI: Crc32Algorithm = Crc32Algorithm::new(0x1EDC_6F41, true, 0xFFFF_FFFF, 0xFFFF_FFFF);
+
+
+/// A writer that passes data on to another writer while calculating its CRC.
+///
+/// This allows the CRC of decompressed data to be checked without holding on to the data.
+#[derive(Debug)]
+pub struct CrcWriter<W, D> {
+    inner: W,
+    digest: D,
+}
+impl<W: Write, D: Write> CrcWriter<W, D> {
+    /// Wraps a writer; `digest` is a [`Crc16Digest`] or [`Crc32Digest`].
+    pub fn new(inner: W, digest: D) -> Self {
+        Self {
+            inner,
+            digest,
+        }
+    }
+
+    /// The digest of the data written so far.
+    pub fn digest(&self) -> &D {
+        &self.digest
+    }
+
+    /// Returns the wrapped writer and the digest.
+    pub fn into_parts(self) -> (W, D) {
+        (self.inner, self.digest)
+    }
+}
+impl<W: Write, D: Write> Write for CrcWriter<W, D> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.digest.write_all(&buf[..written])?;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{CRC_16_ARC, CRC_16_XMODEM, CRC_32_BZIP2, CRC_32_ISCSI, CRC_32_ISO_HDLC};
+
+    /// The input whose CRC is given as the "check" value of each algorithm in the catalogue.
+    const CHECK_INPUT: &[u8] = b"123456789";
+
+    #[test]
+    fn test_check_values() {
+        assert_eq!(CRC_16_ARC.checksum(CHECK_INPUT), 0xBB3D);
+        assert_eq!(CRC_16_XMODEM.checksum(CHECK_INPUT), 0x31C3);
+        assert_eq!(CRC_32_ISO_HDLC.checksum(CHECK_INPUT), 0xCBF4_3926);
+        assert_eq!(CRC_32_BZIP2.checksum(CHECK_INPUT), 0xFC89_1918);
+        assert_eq!(CRC_32_ISCSI.checksum(CHECK_INPUT), 0xE306_9283);
+    }
+
+    #[test]
+    fn test_slices_match_bytewise() {
+        // lengths around multiples of the slice length, fed whole and in uneven pieces
+        let data: Vec<u8> = (0..100u32).map(|i| (i.wrapping_mul(0x9E37_79B9) >> 24) as u8).collect();
+        for length in [0, 1, 7, 8, 9, 16, 17, 100] {
+            let data = &data[..length];
+            let mut crc = flate2::Crc::new();
+            crc.update(data);
+            assert_eq!(CRC_32_ISO_HDLC.checksum(data), crc.sum());
+
+            let mut digest = CRC_32_BZIP2.digest();
+            for piece in data.chunks(3) {
+                digest.update(piece);
+            }
+            assert_eq!(digest.finalize(), CRC_32_BZIP2.checksum(data));
+
+            let mut digest = CRC_16_XMODEM.digest();
+            for piece in data.chunks(5) {
+                digest.update(piece);
+            }
+            assert_eq!(digest.finalize(), CRC_16_XMODEM.checksum(data));
+        }
+    }
+}
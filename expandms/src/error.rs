@@ -12,6 +12,57 @@ pub enum DecompressionError {
     DataOffsetWithinHeader,
     Inflate(crate::inflate::Error),
     Lzx(lzxd::DecompressError),
+    Xpress(crate::xpress::Error),
+    Lzh(crate::lha::Error),
+
+    /// The data is encrypted.
+    Encrypted,
+
+    /// The checksum of the decompressed data does not match the one stored along with it.
+    ChecksumMismatch { expected: u32, calculated: u32 },
+
+    /// Another error, along with where in the data it occurred.
+    InContext {
+        error: Box<DecompressionError>,
+
+        /// The number of compressed bytes consumed before the error occurred.
+        ///
+        /// Some decompressors read ahead, so the actual problem may be slightly earlier.
+        offset_in_input: u64,
+
+        /// The number of decompressed bytes output before the error occurred.
+        bytes_written_so_far: u64,
+
+        /// What was being done when the error occurred, e.g. `"decompressing SZDD data"`.
+        context: Option<String>,
+    },
+}
+impl DecompressionError {
+    /// Wraps this error with information on where in the data it occurred.
+    pub fn with_context(self, offset_in_input: u64, bytes_written_so_far: u64, context: Option<String>) -> Self {
+        Self::InContext {
+            error: Box::new(self),
+            offset_in_input,
+            bytes_written_so_far,
+            context,
+        }
+    }
+
+    /// The error at the bottom of the chain of [`DecompressionError::InContext`] wrappers.
+    pub fn innermost(&self) -> &DecompressionError {
+        match self {
+            Self::InContext { error, .. } => error.innermost(),
+            other => other,
+        }
+    }
+
+    /// The input offset stored by the outermost [`DecompressionError::InContext`] wrapper, if any.
+    pub fn offset_in_input(&self) -> Option<u64> {
+        match self {
+            Self::InContext { offset_in_input, .. } => Some(*offset_in_input),
+            _ => None,
+        }
+    }
 }
 impl fmt::Display for DecompressionError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -34,6 +85,23 @@ impl fmt::Display for DecompressionError {
                 => write!(f, "Inflate error: {}", e),
             Self::Lzx(e)
                 => write!(f, "LZX decompression error: {}", e),
+            Self::Xpress(e)
+                => write!(f, "XPRESS decompression error: {}", e),
+            Self::Lzh(e)
+                => write!(f, "LZH decompression error: {}", e),
+            Self::Encrypted
+                => write!(f, "data is encrypted"),
+            Self::ChecksumMismatch { expected, calculated }
+                => write!(f, "checksum mismatch: expected {:#X}, calculated {:#X}", expected, calculated),
+            Self::InContext { error, offset_in_input, bytes_written_so_far, context } => {
+                if let Some(context) = context {
+                    write!(f, "while {}: ", context)?;
+                }
+                write!(
+                    f, "{} (at input offset {:#X}, after {} bytes of output)",
+                    error, offset_in_input, bytes_written_so_far,
+                )
+            },
         }
     }
 }
@@ -49,6 +117,11 @@ impl std::error::Error for DecompressionError {
             Self::DataOffsetWithinHeader => None,
             Self::Inflate(e) => Some(e),
             Self::Lzx(e) => Some(e),
+            Self::Xpress(e) => Some(e),
+            Self::Lzh(e) => Some(e),
+            Self::Encrypted => None,
+            Self::ChecksumMismatch { .. } => None,
+            Self::InContext { error, .. } => Some(error.as_ref()),
         }
     }
 }
@@ -64,3 +137,9 @@ impl From<crate::inflate::Error> for DecompressionError {
 impl From<lzxd::DecompressError> for DecompressionError {
     fn from(value: lzxd::DecompressError) -> Self { Self::Lzx(value) }
 }
+impl From<crate::xpress::Error> for DecompressionError {
+    fn from(value: crate::xpress::Error) -> Self { Self::Xpress(value) }
+}
+impl From<crate::lha::Error> for DecompressionError {
+    fn from(value: crate::lha::Error) -> Self { Self::Lzh(value) }
+}
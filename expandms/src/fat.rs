@@ -1,10 +1,14 @@
-//! Routines for reading File Allocation Table file systems.
+//! Routines for reading and writing File Allocation Table file systems.
 //!
-//! Should support FAT12/FAT16/FAT32 from MS-DOS 2.0 onward.
+//! Should support FAT12/FAT16/FAT32 from MS-DOS 2.0 onward. Writing does not update the FAT32
+//! file system information sector, whose free cluster count is only a hint anyway.
 
 
-use std::io::{self, Read, Seek, SeekFrom};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
 
+use binstruct::binread_struct;
 use bitflags::bitflags;
 use display_bytes::DisplayBytes;
 use tracing::debug;
@@ -23,6 +27,50 @@ pub enum RootDirectoryLocation {
     Cluster(u32),
 }
 
+binread_struct! {
+    /// The fields at the start of the boot sector that are common to all FAT variants.
+    ///
+    /// The 16-bit sector counts are zero if the count is stored in the extended BIOS Parameter
+    /// Block instead.
+    #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    struct BootSectorStart {
+        0x00 => jump: [u8; 3],
+        0x03 => oem_name: [u8; 8],
+        0x0B => bytes_per_sector: u16,
+        0x0D => sectors_per_cluster: u8,
+        0x0E => reserved_sector_count: u16,
+        0x10 => fat_count: u8,
+        0x11 => max_root_dir_entries: u16,
+        0x13 => total_sector_count_16: u16,
+        0x15 => media_descriptor: u8,
+        0x16 => sectors_per_fat_16: u16,
+    }
+}
+
+binread_struct! {
+    /// The fields of the DOS 3.31 BIOS Parameter Block that follow [`BootSectorStart`], i.e. that
+    /// start at offset `0x18` of the boot sector.
+    #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    struct Dos331BiosParameterBlock {
+        0x00 => sectors_per_track: u16,
+        0x02 => head_count: u16,
+        0x04 => hidden_sector_count: u32,
+        0x08 => total_sector_count_32: u32,
+    }
+}
+
+binread_struct! {
+    /// The FAT32-specific fields that follow [`Dos331BiosParameterBlock`], i.e. that start at
+    /// offset `0x24` of the boot sector.
+    #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    struct Fat32BiosParameterBlock {
+        0x00 => sectors_per_fat_32: u32,
+        0x04 => mirroring_flags: u16,
+        0x06 => version: u16,
+        0x08 => root_directory_cluster: u32,
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct FatHeader {
     pub jump: [u8; 3],
@@ -41,57 +89,50 @@ pub struct FatHeader {
 }
 impl FatHeader {
     pub fn read<R: Read>(reader: &mut R) -> Result<Self, io::Error> {
-        let mut header_buf = [0u8; 24];
-        reader.read_exact(&mut header_buf)?;
-
-        let jump = header_buf[0..3].try_into().unwrap();
-        let oem_name = header_buf[3..11].try_into().unwrap();
-        let bytes_per_sector = u16::from_le_bytes(header_buf[11..13].try_into().unwrap());
-        let sectors_per_cluster = header_buf[13];
-        if sectors_per_cluster == 0 {
+        let start = BootSectorStart::read(reader)?;
+        if start.sectors_per_cluster == 0 {
             debug!("FAT sectors-per-cluster value is 0");
             return Err(io::ErrorKind::InvalidData.into());
         }
-        let reserved_sector_count = u16::from_le_bytes(header_buf[14..16].try_into().unwrap());
-        let fat_count = header_buf[16];
-        let max_root_dir_entries = u16::from_le_bytes(header_buf[17..19].try_into().unwrap());
-        let total_sector_count: u32 = u16::from_le_bytes(header_buf[19..21].try_into().unwrap()).into();
-        let media_descriptor = header_buf[21];
-        let sectors_per_fat: u32 = u16::from_le_bytes(header_buf[22..24].try_into().unwrap()).into();
+        let reserved_sector_count = start.reserved_sector_count;
+        let fat_count = start.fat_count;
 
         let mut fat_header = Self {
-            jump,
-            oem_name,
-            bytes_per_sector,
-            sectors_per_cluster,
+            jump: start.jump,
+            oem_name: start.oem_name,
+            bytes_per_sector: start.bytes_per_sector,
+            sectors_per_cluster: start.sectors_per_cluster,
             reserved_sector_count,
             fat_count,
-            max_root_dir_entries,
-            total_sector_count,
-            media_descriptor,
-            sectors_per_fat,
+            max_root_dir_entries: start.max_root_dir_entries,
+            total_sector_count: start.total_sector_count_16.into(),
+            media_descriptor: start.media_descriptor,
+            sectors_per_fat: start.sectors_per_fat_16.into(),
             root_directory_location: RootDirectoryLocation::Sector(0),
         };
 
-        if fat_header.total_sector_count == 0 || fat_header.sectors_per_fat == 0 {
-            // take 32-bit value at 0x0020 or 0x0024, respectively
-
-            // we are currently at 0x0016
-            reader.read_exact(&mut header_buf[..18])?;
-            if fat_header.total_sector_count == 0 {
-                fat_header.total_sector_count = u32::from_le_bytes(header_buf[10..14].try_into().unwrap());
-            }
-            if fat_header.sectors_per_fat == 0 {
-                fat_header.sectors_per_fat = u32::from_le_bytes(header_buf[14..18].try_into().unwrap());
-            }
+        // the boot sector is at least one (512-byte) sector long, so this is always there
+        let dos_bpb = Dos331BiosParameterBlock::read(reader)?;
+        if fat_header.total_sector_count == 0 {
+            fat_header.total_sector_count = dos_bpb.total_sector_count_32;
         }
 
+        // only FAT32 stores the FAT size in its own block
+        let fat32_bpb = if fat_header.sectors_per_fat == 0 {
+            let fat32_bpb = Fat32BiosParameterBlock::read(reader)?;
+            fat_header.sectors_per_fat = fat32_bpb.sectors_per_fat_32;
+            Some(fat32_bpb)
+        } else {
+            None
+        };
+
         if fat_header.variant() == FatVariant::Fat32 {
-            // root directory is stored in the cluster numbered at 0x002C
-            // we are currently at 0x0028
-            reader.read_exact(&mut header_buf[0..8])?;
-            let root_directory_cluster = u32::from_le_bytes(header_buf[4..8].try_into().unwrap());
-            fat_header.root_directory_location = RootDirectoryLocation::Cluster(root_directory_cluster);
+            // root directory is stored in the cluster numbered in the FAT32 block
+            let Some(fat32_bpb) = fat32_bpb else {
+                debug!("FAT32 file system has a 16-bit sectors-per-FAT value");
+                return Err(io::ErrorKind::InvalidData.into());
+            };
+            fat_header.root_directory_location = RootDirectoryLocation::Cluster(fat32_bpb.root_directory_cluster);
         } else {
             // root directory starts after reserved sectors and FATs
             // and is only one sector long
@@ -123,6 +164,24 @@ impl FatHeader {
         usize::try_from(self.sectors_per_fat).unwrap() * usize::from(self.bytes_per_sector)
     }
 
+    /// The offset of the given copy of the allocation table from the start of the image.
+    pub fn fat_offset(&self, copy: u8) -> u64 {
+        let first_fat_offset = u64::from(self.reserved_sector_count) * u64::from(self.bytes_per_sector);
+        first_fat_offset + u64::from(copy) * u64::try_from(self.fat_bytes()).unwrap()
+    }
+
+    pub fn cluster_bytes(&self) -> usize {
+        usize::from(self.sectors_per_cluster) * usize::from(self.bytes_per_sector)
+    }
+
+    /// The index one greater than that of the last cluster in the data area.
+    ///
+    /// Cluster indexes start at 2, so this is the number of data clusters plus 2.
+    pub fn cluster_limit(&self) -> u32 {
+        let data_sectors = self.total_sector_count.saturating_sub(self.first_data_sector());
+        data_sectors / u32::from(self.sectors_per_cluster) + 2
+    }
+
     pub fn first_data_sector(&self) -> u32 {
         // 1. reserved sectors
         // 2. sectors with FATs
@@ -144,6 +203,24 @@ pub enum FatEntry {
     Sentinel, // F...FF
 }
 
+impl FatEntry {
+    /// Encodes the entry as it is stored in an allocation table of the given variant.
+    pub fn to_raw(&self, variant: FatVariant) -> u32 {
+        let all_ones = match variant {
+            FatVariant::Fat12 => 0xFFF,
+            FatVariant::Fat16 => 0xFFFF,
+            FatVariant::Fat32 => 0x0FFF_FFFF,
+        };
+        match self {
+            FatEntry::Empty => 0,
+            FatEntry::Chain(next) => *next,
+            FatEntry::Bad => all_ones - 8,
+            FatEntry::MediaType(media_type) => (all_ones & !0xF) | u32::from(*media_type & 0xF),
+            FatEntry::Sentinel => all_ones,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct AllocationTable {
     pub entries: Vec<FatEntry>,
@@ -239,6 +316,174 @@ impl AllocationTable {
             entries,
         })
     }
+
+    /// Writes the allocation table in the encoding of the given variant.
+    ///
+    /// On FAT32, the top four bits of each entry are written as zero.
+    pub fn write<W: Write>(&self, writer: &mut W, variant: FatVariant) -> Result<(), io::Error> {
+        match variant {
+            FatVariant::Fat12 => {
+                // 2 entries -> 3 bytes
+                for pair in self.entries.chunks(2) {
+                    let first = pair[0].to_raw(variant);
+                    let second = pair.get(1).map(|e| e.to_raw(variant)).unwrap_or(0);
+                    let buf = [
+                        u8::try_from(first & 0xFF).unwrap(),
+                        u8::try_from(((first >> 8) & 0x0F) | ((second & 0x0F) << 4)).unwrap(),
+                        u8::try_from((second >> 4) & 0xFF).unwrap(),
+                    ];
+                    writer.write_all(&buf)?;
+                }
+            },
+            FatVariant::Fat16 => {
+                for entry in &self.entries {
+                    let raw = u16::try_from(entry.to_raw(variant)).unwrap();
+                    writer.write_all(&raw.to_le_bytes())?;
+                }
+            },
+            FatVariant::Fat32 => {
+                for entry in &self.entries {
+                    writer.write_all(&entry.to_raw(variant).to_le_bytes())?;
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Returns the indexes of the clusters in the chain starting at the given cluster.
+    ///
+    /// Fails once the [time limit](binstruct::deadline) has been exceeded, as the chains of large
+    /// volumes can be long.
+    pub fn cluster_chain(&self, first_cluster_index: u32) -> Result<Vec<u32>, io::Error> {
+        let mut chain = Vec::new();
+        let mut current_cluster_index = first_cluster_index;
+        loop {
+            binstruct::deadline::check()?;
+            if chain.len() >= self.entries.len() {
+                debug!("cluster chain starting at {} contains a loop", first_cluster_index);
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+            if current_cluster_index < 2 {
+                // the first two entries are reserved; the data area starts at cluster 2
+                debug!("cluster chain starting at {} leads to reserved cluster {}", first_cluster_index, current_cluster_index);
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+            chain.push(current_cluster_index);
+            match self.entries.get(usize::try_from(current_cluster_index).unwrap()) {
+                Some(FatEntry::Chain(next_cluster_index)) => {
+                    current_cluster_index = *next_cluster_index;
+                },
+                Some(FatEntry::Sentinel) => return Ok(chain),
+                other => {
+                    debug!("cluster {} has FAT entry {:?}; expecting chain or sentinel", current_cluster_index, other);
+                    return Err(io::ErrorKind::InvalidData.into());
+                },
+            }
+        }
+    }
+
+    /// Allocates a chain of free clusters, returning their indexes in order.
+    ///
+    /// Only clusters below `cluster_limit` (see [`FatHeader::cluster_limit`]) are considered.
+    pub fn allocate_chain(&mut self, cluster_count: usize, cluster_limit: u32) -> Result<Vec<u32>, io::Error> {
+        let limit = self.entries.len().min(usize::try_from(cluster_limit).unwrap());
+        let free_clusters: Vec<usize> = (2..limit)
+            .filter(|i| self.entries[*i] == FatEntry::Empty)
+            .take(cluster_count)
+            .collect();
+        if free_clusters.len() < cluster_count {
+            debug!("{} clusters requested but only {} are free", cluster_count, free_clusters.len());
+            return Err(io::ErrorKind::StorageFull.into());
+        }
+
+        for (i, cluster_index) in free_clusters.iter().enumerate() {
+            self.entries[*cluster_index] = match free_clusters.get(i + 1) {
+                Some(next) => FatEntry::Chain(u32::try_from(*next).unwrap()),
+                None => FatEntry::Sentinel,
+            };
+        }
+        Ok(free_clusters.into_iter()
+            .map(|c| u32::try_from(c).unwrap())
+            .collect())
+    }
+
+    /// Marks all clusters in the chain starting at the given cluster as free.
+    pub fn free_chain(&mut self, first_cluster_index: u32) -> Result<(), io::Error> {
+        for cluster_index in self.cluster_chain(first_cluster_index)? {
+            self.entries[usize::try_from(cluster_index).unwrap()] = FatEntry::Empty;
+        }
+        Ok(())
+    }
+}
+
+/// All readable copies of the allocation table of a file system.
+///
+/// File systems generally store two copies of the allocation table so that the second one can
+/// stand in if the first one is damaged. Reading cluster chains falls back to the subsequent copies
+/// if the chain cannot be followed in the first one.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct AllocationTableCopies {
+    /// The readable copies along with their indexes, in order.
+    copies: Vec<(u8, AllocationTable)>,
+}
+impl AllocationTableCopies {
+    /// Reads all copies of the allocation table, skipping those that cannot be read.
+    ///
+    /// Fails with the error encountered reading the first copy if none of the copies can be read.
+    pub fn read<R: Read + Seek>(reader: &mut R, header: &FatHeader) -> Result<Self, io::Error> {
+        let mut copies = Vec::with_capacity(header.fat_count.into());
+        let mut first_error = None;
+        for copy in 0..header.fat_count {
+            let fat = reader.seek(SeekFrom::Start(header.fat_offset(copy)))
+                .and_then(|_| AllocationTable::read(reader, header.variant(), header.fat_bytes()));
+            match fat {
+                Ok(fat) => copies.push((copy, fat)),
+                Err(e) => {
+                    debug!("failed to read allocation table copy {}: {}", copy, e);
+                    first_error.get_or_insert(e);
+                },
+            }
+        }
+        if copies.is_empty() {
+            return Err(first_error.unwrap_or_else(|| io::ErrorKind::InvalidData.into()));
+        }
+        Ok(Self {
+            copies,
+        })
+    }
+
+    /// The first readable copy along with its index.
+    pub fn primary(&self) -> (u8, &AllocationTable) {
+        let (copy, fat) = &self.copies[0];
+        (*copy, fat)
+    }
+
+    /// The indexes of the readable copies.
+    pub fn readable_copies(&self) -> Vec<u8> {
+        self.copies.iter()
+            .map(|(copy, _)| *copy)
+            .collect()
+    }
+
+    /// Reads the chain of clusters starting at the given cluster (see [`read_cluster_chain_into`])
+    /// using the first copy in which the chain is intact, i.e. ends in a sentinel entry without
+    /// passing through free or bad clusters.
+    ///
+    /// Returns the index of the copy that has been used. Fails if the chain is not intact in any
+    /// copy.
+    pub fn read_cluster_chain_into<R: Read + Seek>(&self, reader: &mut R, header: &FatHeader, first_cluster_index: u32, output: &mut Vec<u8>) -> Result<u8, io::Error> {
+        let intact_chain = self.copies.iter()
+            .find_map(|(copy, fat)| fat.cluster_chain(first_cluster_index).ok().map(|chain| (*copy, chain)));
+        let Some((copy, chain)) = intact_chain else {
+            debug!("chain starting at cluster {} is not intact in any allocation table copy", first_cluster_index);
+            return Err(io::ErrorKind::InvalidData.into());
+        };
+        if copy != self.copies[0].0 {
+            debug!("following chain starting at cluster {} using allocation table copy {}", first_cluster_index, copy);
+        }
+        read_clusters_into(reader, header, &chain, output)?;
+        Ok(copy)
+    }
 }
 
 fn read_next_sector_into<R: Read>(reader: &mut R, header: &FatHeader, output: &mut Vec<u8>) -> Result<(), io::Error> {
@@ -257,12 +502,15 @@ fn read_next_cluster_into<R: Read>(reader: &mut R, header: &FatHeader, output: &
     Ok(())
 }
 
-fn seek_to_cluster<R: Seek>(reader: &mut R, header: &FatHeader, cluster_index: u32) -> Result<(), io::Error> {
+fn cluster_offset(header: &FatHeader, cluster_index: u32) -> u64 {
     let cluster_start_sector = u64::from(header.first_data_sector())
-        + u64::try_from(cluster_index - 2).unwrap() * u64::from(header.sectors_per_cluster);
-    let cluster_start_byte = cluster_start_sector
-        * u64::from(header.bytes_per_sector);
-    reader.seek(SeekFrom::Start(cluster_start_byte))?;
+        + u64::from(cluster_index - 2) * u64::from(header.sectors_per_cluster);
+    cluster_start_sector
+        * u64::from(header.bytes_per_sector)
+}
+
+fn seek_to_cluster<R: Seek>(reader: &mut R, header: &FatHeader, cluster_index: u32) -> Result<(), io::Error> {
+    reader.seek(SeekFrom::Start(cluster_offset(header, cluster_index)))?;
     Ok(())
 }
 
@@ -272,56 +520,27 @@ pub fn read_sector_into<R: Read + Seek>(reader: &mut R, header: &FatHeader, sect
     read_next_sector_into(reader, header, output)
 }
 
+/// Reads the chain of clusters starting at the given cluster.
+///
+/// Fails if the chain passes through free or bad clusters or loops instead of ending in a sentinel
+/// entry.
 pub fn read_cluster_chain_into<R: Read + Seek>(reader: &mut R, header: &FatHeader, fat: &AllocationTable, first_cluster_index: u32, output: &mut Vec<u8>) -> Result<(), io::Error> {
-    let mut prev_cluster_index = None;
-    let mut current_cluster_index = first_cluster_index;
-
-    loop {
-        // read the cluster entry from the allocation table
-        let current_cluster_entry = fat.entries.get(usize::try_from(current_cluster_index).unwrap())
-            .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData))
-            .inspect_err(|_| debug!("failed to obtain FAT entry for cluster {}", current_cluster_index))?;
-        match current_cluster_entry {
-            FatEntry::Empty => return Ok(()),
-            FatEntry::Bad => {
-                debug!("bad FAT entry for cluster {}", current_cluster_index);
-                return Err(io::ErrorKind::InvalidData.into());
-            },
-            FatEntry::MediaType(_) => {
-                debug!("cluster {} contains a media type; expecting chain or sentinel", current_cluster_index);
-                return Err(io::ErrorKind::InvalidData.into());
-            },
-            FatEntry::Sentinel|FatEntry::Chain(_) => {},
-        }
+    let chain = fat.cluster_chain(first_cluster_index)?;
+    read_clusters_into(reader, header, &chain, output)
+}
 
-        // read the cluster
-        if let Some(pci) = prev_cluster_index {
-            if pci + 1 != current_cluster_index {
-                // fragmented file; seek
-                seek_to_cluster(reader, header, current_cluster_index)?;
-            }
-            // otherwise, we can just keep reading
-        } else {
-            // first cluster; seek
-            seek_to_cluster(reader, header, current_cluster_index)?;
+/// Reads the given clusters in order.
+fn read_clusters_into<R: Read + Seek>(reader: &mut R, header: &FatHeader, chain: &[u32], output: &mut Vec<u8>) -> Result<(), io::Error> {
+    let mut prev_cluster_index = None;
+    for &cluster_index in chain {
+        if prev_cluster_index.is_none_or(|pci: u32| pci + 1 != cluster_index) {
+            // first cluster or fragmented file; seek
+            seek_to_cluster(reader, header, cluster_index)?;
         }
+        // otherwise, we can just keep reading
         read_next_cluster_into(reader, header, output)?;
-
-        // do we continue?
-        match current_cluster_entry {
-            FatEntry::Sentinel => {
-                // we're done
-                break;
-            },
-            FatEntry::Chain(next_cluster_index) => {
-                // pointer to the next cluster
-                prev_cluster_index = Some(current_cluster_index);
-                current_cluster_index = *next_cluster_index;
-            },
-            _ => unreachable!(),
-        }
+        prev_cluster_index = Some(cluster_index);
     }
-
     Ok(())
 }
 
@@ -403,4 +622,1031 @@ impl DirectoryEntry {
             file_size_bytes,
         })
     }
+
+    pub fn write<W: Write>(&self, writer: &mut W, variant: FatVariant) -> Result<(), io::Error> {
+        let mut buf = [0u8; 32];
+        buf[0..8].copy_from_slice(self.file_name.as_ref());
+        buf[8..11].copy_from_slice(self.extension.as_ref());
+        buf[11] = self.attributes.bits();
+        buf[12] = self.reserved;
+        buf[13] = self.create_time_10ms;
+        buf[14..16].copy_from_slice(&self.create_time_h_m_2s.to_le_bytes());
+        buf[16..18].copy_from_slice(&self.create_date.to_le_bytes());
+        buf[18..20].copy_from_slice(&self.access_date.to_le_bytes());
+        let top_half = if variant == FatVariant::Fat32 {
+            u16::try_from(self.first_cluster_number >> 16).unwrap()
+        } else {
+            self.reserved2.unwrap_or(0)
+        };
+        buf[20..22].copy_from_slice(&top_half.to_le_bytes());
+        buf[22..24].copy_from_slice(&self.modification_time_h_m_2s.to_le_bytes());
+        buf[24..26].copy_from_slice(&self.modification_date.to_le_bytes());
+        let bottom_half = u16::try_from(self.first_cluster_number & 0xFFFF).unwrap();
+        buf[26..28].copy_from_slice(&bottom_half.to_le_bytes());
+        buf[28..32].copy_from_slice(&self.file_size_bytes.to_le_bytes());
+        writer.write_all(&buf)
+    }
+
+    /// Creates an entry for a file or directory with the given name, as returned by
+    /// [`short_name`].
+    ///
+    /// Dates and times are stored in the DOS format.
+    pub fn new(name: ([u8; 8], [u8; 3]), attributes: Attributes, first_cluster_number: u32, file_size_bytes: u32, date: u16, time_h_m_2s: u16) -> Self {
+        Self {
+            file_name: name.0.into(),
+            extension: name.1.into(),
+            attributes,
+            reserved: 0,
+            create_time_10ms: 0,
+            create_time_h_m_2s: time_h_m_2s,
+            create_date: date,
+            access_date: date,
+            reserved2: None,
+            modification_time_h_m_2s: time_h_m_2s,
+            modification_date: date,
+            first_cluster_number,
+            file_size_bytes,
+        }
+    }
+}
+
+
+/// Converts a file name into the space-padded 8.3 form stored in directory entries.
+///
+/// Letters are converted to uppercase. Returns `None` if the name does not fit or contains
+/// characters that are not allowed in short names.
+pub fn short_name(name: &str) -> Option<([u8; 8], [u8; 3])> {
+    const ALLOWED_PUNCTUATION: &[u8] = b"!#$%&'()-@^_`{}~";
+
+    let (base, extension) = name.rsplit_once('.')
+        .unwrap_or((name, ""));
+    if base.is_empty() || base.len() > 8 || extension.len() > 3 {
+        return None;
+    }
+
+    let mut ret = ([b' '; 8], [b' '; 3]);
+    for (source, target) in [(base, &mut ret.0[..]), (extension, &mut ret.1[..])] {
+        for (b, t) in source.bytes().zip(target.iter_mut()) {
+            if !b.is_ascii_alphanumeric() && !ALLOWED_PUNCTUATION.contains(&b) {
+                return None;
+            }
+            *t = b.to_ascii_uppercase();
+        }
+    }
+    Some(ret)
+}
+
+
+/// Writes data into the given clusters, padding the last one with zeroes.
+pub fn write_clusters<W: Write + Seek>(writer: &mut W, header: &FatHeader, clusters: &[u32], data: &[u8]) -> Result<(), io::Error> {
+    let cluster_bytes = header.cluster_bytes();
+    for (i, cluster_index) in clusters.iter().enumerate() {
+        let start = (i * cluster_bytes).min(data.len());
+        let end = ((i + 1) * cluster_bytes).min(data.len());
+        let mut cluster = data[start..end].to_vec();
+        cluster.resize(cluster_bytes, 0x00);
+
+        seek_to_cluster(writer, header, *cluster_index)?;
+        writer.write_all(&cluster)?;
+    }
+    Ok(())
+}
+
+
+/// Creates an empty FAT12 or FAT16 file system described by the header.
+///
+/// The boot sector only contains the jump instruction, the OEM name and the BIOS Parameter Block;
+/// the image is not bootable.
+pub fn format_image<W: Write + Seek>(writer: &mut W, header: &FatHeader) -> Result<(), io::Error> {
+    if !matches!(header.root_directory_location, RootDirectoryLocation::Sector(_)) {
+        debug!("formatting FAT32 file systems is not supported");
+        return Err(io::ErrorKind::Unsupported.into());
+    }
+
+    // zero out the whole image
+    let sector = vec![0u8; header.bytes_per_sector.into()];
+    writer.seek(SeekFrom::Start(0))?;
+    for _ in 0..header.total_sector_count {
+        writer.write_all(&sector)?;
+    }
+
+    let (total_sector_count_16, total_sector_count_32) = match u16::try_from(header.total_sector_count) {
+        Ok(small) => (small, 0),
+        Err(_) => (0, header.total_sector_count),
+    };
+    let sectors_per_fat_16 = u16::try_from(header.sectors_per_fat)
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    let start = BootSectorStart {
+        jump: header.jump,
+        oem_name: header.oem_name,
+        bytes_per_sector: header.bytes_per_sector,
+        sectors_per_cluster: header.sectors_per_cluster,
+        reserved_sector_count: header.reserved_sector_count,
+        fat_count: header.fat_count,
+        max_root_dir_entries: header.max_root_dir_entries,
+        total_sector_count_16,
+        media_descriptor: header.media_descriptor,
+        sectors_per_fat_16,
+    };
+    let dos_bpb = Dos331BiosParameterBlock {
+        sectors_per_track: 0,
+        head_count: 0,
+        hidden_sector_count: 0,
+        total_sector_count_32,
+    };
+
+    let mut boot_sector = sector;
+    let dos_bpb_start = BootSectorStart::SIZE;
+    let dos_bpb_end = dos_bpb_start + Dos331BiosParameterBlock::SIZE;
+    boot_sector[..dos_bpb_start].copy_from_slice(&start.to_bytes());
+    boot_sector[dos_bpb_start..dos_bpb_end].copy_from_slice(&dos_bpb.to_bytes());
+    if boot_sector.len() >= 512 {
+        boot_sector[510..512].copy_from_slice(&[0x55, 0xAA]);
+    }
+    writer.seek(SeekFrom::Start(0))?;
+    writer.write_all(&boot_sector)?;
+
+    let mut fat = AllocationTable {
+        entries: vec![FatEntry::Empty; usize::try_from(header.cluster_limit()).unwrap()],
+    };
+    fat.entries[0] = FatEntry::MediaType(header.media_descriptor & 0x0F);
+    fat.entries[1] = FatEntry::Sentinel;
+    write_allocation_tables(writer, header, &fat)
+}
+
+
+/// The location of a directory's entries.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum DirectoryLocation {
+    /// The fixed-size root directory of FAT12 and FAT16.
+    Sectors { first_sector: u32, sector_count: u32 },
+
+    /// A directory stored in a cluster chain.
+    Clusters(u32),
+}
+impl DirectoryLocation {
+    fn root(header: &FatHeader) -> Self {
+        match header.root_directory_location {
+            RootDirectoryLocation::Sector(first_sector) => Self::Sectors {
+                first_sector,
+                sector_count: u32::from(header.max_root_dir_entries) * 32 / u32::from(header.bytes_per_sector),
+            },
+            RootDirectoryLocation::Cluster(cluster) => Self::Clusters(cluster),
+        }
+    }
+
+    /// The number of the cluster that refers to this directory in `..` entries; 0 for the root.
+    fn parent_reference(&self, header: &FatHeader) -> u32 {
+        match (self, header.root_directory_location) {
+            (Self::Sectors { .. }, _) => 0,
+            (Self::Clusters(cluster), RootDirectoryLocation::Cluster(root)) if *cluster == root => 0,
+            (Self::Clusters(cluster), _) => *cluster,
+        }
+    }
+}
+
+
+/// A FAT file system opened for modification.
+///
+/// Changes to the allocation table are kept in memory until [`WritableVolume::modify`] commits
+/// them. Everything else is written immediately, but the previous contents are recorded so that
+/// they can be restored if the modification fails; this way, a failed modification never leaves
+/// directory entries pointing at clusters that the allocation table considers free.
+struct WritableVolume<'f, F: Read + Write + Seek> {
+    image: &'f mut F,
+    header: FatHeader,
+    fat: AllocationTable,
+
+    /// The offsets and previous contents of the regions written so far, in order.
+    undo_log: Vec<(u64, Vec<u8>)>,
+}
+impl<'f, F: Read + Write + Seek> WritableVolume<'f, F> {
+    fn open(image: &'f mut F) -> Result<Self, io::Error> {
+        image.seek(SeekFrom::Start(0))?;
+        let header = FatHeader::read(image)?;
+        let fat_offset = u64::from(header.reserved_sector_count) * u64::from(header.bytes_per_sector);
+        image.seek(SeekFrom::Start(fat_offset))?;
+        let fat = AllocationTable::read(image, header.variant(), header.fat_bytes())?;
+        Ok(Self {
+            image,
+            header,
+            fat,
+            undo_log: Vec::new(),
+        })
+    }
+
+    /// Opens the file system, applies the modification and writes the allocation tables.
+    ///
+    /// If any of this fails, everything written so far is restored.
+    fn modify<T, M: FnOnce(&mut Self) -> Result<T, io::Error>>(image: &'f mut F, modification: M) -> Result<T, io::Error> {
+        let mut volume = Self::open(image)?;
+        let result = modification(&mut volume)
+            .and_then(|value| volume.write_allocation_tables().map(|()| value));
+        if result.is_err() {
+            volume.roll_back();
+        }
+        result
+    }
+
+    /// Writes the data at the given offset, recording the previous contents in the undo log.
+    fn write_at(&mut self, offset: u64, data: &[u8]) -> Result<(), io::Error> {
+        let mut previous = vec![0u8; data.len()];
+        self.image.seek(SeekFrom::Start(offset))?;
+        self.image.read_exact(&mut previous)?;
+        self.undo_log.push((offset, previous));
+        self.image.seek(SeekFrom::Start(offset))?;
+        self.image.write_all(data)
+    }
+
+    /// Restores the contents of the regions written so far, newest first.
+    fn roll_back(&mut self) {
+        while let Some((offset, previous)) = self.undo_log.pop() {
+            let restored = self.image.seek(SeekFrom::Start(offset))
+                .and_then(|_| self.image.write_all(&previous));
+            if let Err(e) = restored {
+                debug!("failed to restore {} bytes at offset {:#X}: {}", previous.len(), offset, e);
+            }
+        }
+    }
+
+    /// Writes data into the given clusters, padding the last one with zeroes.
+    fn write_clusters(&mut self, clusters: &[u32], data: &[u8]) -> Result<(), io::Error> {
+        let cluster_bytes = self.header.cluster_bytes();
+        for (i, cluster_index) in clusters.iter().enumerate() {
+            let start = (i * cluster_bytes).min(data.len());
+            let end = ((i + 1) * cluster_bytes).min(data.len());
+            let mut cluster = data[start..end].to_vec();
+            cluster.resize(cluster_bytes, 0x00);
+            self.write_at(cluster_offset(&self.header, *cluster_index), &cluster)?;
+        }
+        Ok(())
+    }
+
+    fn read_directory(&mut self, location: DirectoryLocation) -> Result<Vec<u8>, io::Error> {
+        let mut data = Vec::new();
+        match location {
+            DirectoryLocation::Sectors { first_sector, sector_count } => {
+                for i in 0..sector_count {
+                    read_sector_into(self.image, &self.header, first_sector + i, &mut data)?;
+                }
+            },
+            DirectoryLocation::Clusters(first_cluster) => {
+                read_cluster_chain_into(self.image, &self.header, &self.fat, first_cluster, &mut data)?;
+            },
+        }
+        Ok(data)
+    }
+
+    fn write_directory_entry(&mut self, location: DirectoryLocation, slot: usize, entry: &DirectoryEntry) -> Result<(), io::Error> {
+        let slot_offset = slot * 32;
+        let offset = match location {
+            DirectoryLocation::Sectors { first_sector, .. } => {
+                u64::from(first_sector) * u64::from(self.header.bytes_per_sector)
+                    + u64::try_from(slot_offset).unwrap()
+            },
+            DirectoryLocation::Clusters(first_cluster) => {
+                let chain = self.fat.cluster_chain(first_cluster)?;
+                let cluster_bytes = self.header.cluster_bytes();
+                let cluster = chain.get(slot_offset / cluster_bytes)
+                    .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+                cluster_offset(&self.header, *cluster)
+                    + u64::try_from(slot_offset % cluster_bytes).unwrap()
+            },
+        };
+        let mut entry_bytes = Vec::with_capacity(32);
+        entry.write(&mut entry_bytes, self.header.variant())?;
+        self.write_at(offset, &entry_bytes)
+    }
+
+    /// Finds the entry with the given name in the directory, returning its slot index.
+    fn find_entry(&mut self, location: DirectoryLocation, name: ([u8; 8], [u8; 3])) -> Result<Option<(usize, DirectoryEntry)>, io::Error> {
+        let data = self.read_directory(location)?;
+        let mut cursor = Cursor::new(&data);
+        for slot in 0..data.len() / 32 {
+            let entry = DirectoryEntry::read(&mut cursor, self.header.variant())?;
+            if entry.file_name[0] == 0x00 {
+                // no more entries
+                break;
+            }
+            if entry.file_name[0] == 0xE5 || entry.attributes.contains(Attributes::VOLUME_LABEL) {
+                // deleted entry, volume label or VFAT long file name
+                continue;
+            }
+            if entry.file_name.as_ref() == name.0 && entry.extension.as_ref() == name.1 {
+                return Ok(Some((slot, entry)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Finds a free slot in the directory, growing it if necessary and possible.
+    fn free_slot(&mut self, location: DirectoryLocation) -> Result<usize, io::Error> {
+        let data = self.read_directory(location)?;
+        if let Some(slot) = data.chunks_exact(32).position(|e| e[0] == 0x00 || e[0] == 0xE5) {
+            return Ok(slot);
+        }
+
+        match location {
+            DirectoryLocation::Sectors { .. } => {
+                debug!("root directory is full");
+                Err(io::ErrorKind::StorageFull.into())
+            },
+            DirectoryLocation::Clusters(first_cluster) => {
+                // append a cluster to the directory
+                let new_cluster = self.fat.allocate_chain(1, self.header.cluster_limit())?[0];
+                let last_cluster = *self.fat.cluster_chain(first_cluster)?.last().unwrap();
+                self.fat.entries[usize::try_from(last_cluster).unwrap()] = FatEntry::Chain(new_cluster);
+                self.write_clusters(&[new_cluster], &[])?;
+                Ok(data.len() / 32)
+            },
+        }
+    }
+
+    /// Resolves the directory at the given path, optionally creating missing directories.
+    fn resolve_directory(&mut self, path: &[&str], create: bool, date: u16, time_h_m_2s: u16) -> Result<DirectoryLocation, io::Error> {
+        let mut location = DirectoryLocation::root(&self.header);
+        for part in path {
+            let name = short_name(part)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidFilename))?;
+            location = match self.find_entry(location, name)? {
+                Some((_, entry)) if entry.attributes.contains(Attributes::SUBDIRECTORY) => {
+                    DirectoryLocation::Clusters(entry.first_cluster_number)
+                },
+                Some(_) => return Err(io::ErrorKind::NotADirectory.into()),
+                None if create => self.create_directory(location, name, date, time_h_m_2s)?,
+                None => return Err(io::ErrorKind::NotFound.into()),
+            };
+        }
+        Ok(location)
+    }
+
+    fn create_directory(&mut self, parent: DirectoryLocation, name: ([u8; 8], [u8; 3]), date: u16, time_h_m_2s: u16) -> Result<DirectoryLocation, io::Error> {
+        let slot = self.free_slot(parent)?;
+        let cluster = self.fat.allocate_chain(1, self.header.cluster_limit())?[0];
+        self.write_clusters(&[cluster], &[])?;
+
+        let location = DirectoryLocation::Clusters(cluster);
+        let this = DirectoryEntry::new((*b".       ", *b"   "), Attributes::SUBDIRECTORY, cluster, 0, date, time_h_m_2s);
+        self.write_directory_entry(location, 0, &this)?;
+        let parent_entry = DirectoryEntry::new((*b"..      ", *b"   "), Attributes::SUBDIRECTORY, parent.parent_reference(&self.header), 0, date, time_h_m_2s);
+        self.write_directory_entry(location, 1, &parent_entry)?;
+
+        let entry = DirectoryEntry::new(name, Attributes::SUBDIRECTORY, cluster, 0, date, time_h_m_2s);
+        self.write_directory_entry(parent, slot, &entry)?;
+        Ok(location)
+    }
+
+    fn write_allocation_tables(&mut self) -> Result<(), io::Error> {
+        let fat_bytes = allocation_table_bytes(&self.header, &self.fat)?;
+        for copy in 0..self.header.fat_count {
+            self.write_at(self.header.fat_offset(copy), &fat_bytes)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Encodes the allocation table, making sure that it fits into the space the header reserves for
+/// it.
+fn allocation_table_bytes(header: &FatHeader, fat: &AllocationTable) -> Result<Vec<u8>, io::Error> {
+    let mut fat_bytes = Vec::with_capacity(header.fat_bytes());
+    fat.write(&mut fat_bytes, header.variant())?;
+    if fat_bytes.len() > header.fat_bytes() {
+        debug!("allocation table ({} bytes) does not fit into {} bytes", fat_bytes.len(), header.fat_bytes());
+        return Err(io::ErrorKind::InvalidData.into());
+    }
+    Ok(fat_bytes)
+}
+
+fn write_allocation_tables<W: Write + Seek>(writer: &mut W, header: &FatHeader, fat: &AllocationTable) -> Result<(), io::Error> {
+    let fat_bytes = allocation_table_bytes(header, fat)?;
+    for copy in 0..header.fat_count {
+        writer.seek(SeekFrom::Start(header.fat_offset(copy)))?;
+        writer.write_all(&fat_bytes)?;
+    }
+    Ok(())
+}
+
+
+/// Splits a path within a file system image into its components.
+fn split_path(path: &str) -> Vec<&str> {
+    path.split(['/', '\\'])
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+
+/// Stores a file in a FAT file system image, replacing an existing file of the same name.
+///
+/// Missing directories along the path are created. Names must be valid 8.3 names (see
+/// [`short_name`]); dates and times are stored in the DOS format. All copies of the allocation table
+/// are updated. If storing the file fails, e.g. because the file system is full, the image is left
+/// unchanged.
+pub fn put_file<F: Read + Write + Seek>(image: &mut F, path: &str, data: &[u8], date: u16, time_h_m_2s: u16) -> Result<(), io::Error> {
+    let file_size_bytes = u32::try_from(data.len())
+        .map_err(|_| io::Error::from(io::ErrorKind::FileTooLarge))?;
+    let parts = split_path(path);
+    let Some((file_name, directory_path)) = parts.split_last() else {
+        return Err(io::ErrorKind::InvalidFilename.into());
+    };
+    let name = short_name(file_name)
+        .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidFilename))?;
+
+    WritableVolume::modify(image, |volume| {
+        let directory = volume.resolve_directory(directory_path, true, date, time_h_m_2s)?;
+        let slot = match volume.find_entry(directory, name)? {
+            Some((_, entry)) if entry.attributes.contains(Attributes::SUBDIRECTORY) => {
+                return Err(io::ErrorKind::IsADirectory.into());
+            },
+            Some((slot, entry)) => {
+                if entry.first_cluster_number != 0 {
+                    volume.fat.free_chain(entry.first_cluster_number)?;
+                }
+                slot
+            },
+            None => volume.free_slot(directory)?,
+        };
+
+        let cluster_count = data.len().div_ceil(volume.header.cluster_bytes());
+        let clusters = volume.fat.allocate_chain(cluster_count, volume.header.cluster_limit())?;
+        volume.write_clusters(&clusters, data)?;
+
+        let first_cluster = clusters.first().copied().unwrap_or(0);
+        let entry = DirectoryEntry::new(name, Attributes::ARCHIVE, first_cluster, file_size_bytes, date, time_h_m_2s);
+        volume.write_directory_entry(directory, slot, &entry)
+    })
+}
+
+
+/// Removes a file from a FAT file system image, freeing its clusters.
+pub fn remove_file<F: Read + Write + Seek>(image: &mut F, path: &str) -> Result<(), io::Error> {
+    let parts = split_path(path);
+    let Some((file_name, directory_path)) = parts.split_last() else {
+        return Err(io::ErrorKind::InvalidFilename.into());
+    };
+    let name = short_name(file_name)
+        .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidFilename))?;
+
+    WritableVolume::modify(image, |volume| {
+        let directory = volume.resolve_directory(directory_path, false, 0, 0)?;
+        let Some((slot, mut entry)) = volume.find_entry(directory, name)? else {
+            return Err(io::ErrorKind::NotFound.into());
+        };
+        if entry.attributes.contains(Attributes::SUBDIRECTORY) {
+            return Err(io::ErrorKind::IsADirectory.into());
+        }
+
+        if entry.first_cluster_number != 0 {
+            volume.fat.free_chain(entry.first_cluster_number)?;
+        }
+        entry.file_name[0] = 0xE5;
+        volume.write_directory_entry(directory, slot, &entry)
+    })
+}
+
+
+/// An inconsistency found by [`check_consistency`].
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Inconsistency {
+    /// A copy of the allocation table cannot be read, e.g. because its first entries are damaged
+    /// or the image is truncated.
+    UnreadableAllocationTable { copy: u8 },
+
+    /// A copy of the allocation table differs from the first copy.
+    DifferingAllocationTable { copy: u8, differing_entries: usize, first_differing_cluster: u32 },
+
+    /// The chain of clusters of a file or directory leads to a cluster that is free, bad or
+    /// outside of the data area (`entry` is `None` in the latter case).
+    BrokenChain { path: String, cluster: u32, entry: Option<FatEntry> },
+
+    /// The chain of clusters of a file or directory leads back to one of its earlier clusters.
+    LoopingChain { path: String, cluster: u32 },
+
+    /// The chain of clusters of a file or directory leads to a cluster belonging to another file
+    /// or directory.
+    CrossLinkedChain { path: String, other_path: String, cluster: u32 },
+
+    /// A chain of allocated clusters does not belong to any file or directory.
+    OrphanedChain { first_cluster: u32, cluster_count: usize },
+
+    /// The size of a file does not match the length of its chain of clusters.
+    SizeMismatch { path: String, file_size_bytes: u32, cluster_count: usize, expected_cluster_count: usize },
+}
+impl Inconsistency {
+    /// A suggestion on how to repair the inconsistency.
+    pub fn suggestion(&self) -> &'static str {
+        match self {
+            Self::UnreadableAllocationTable { .. }
+                => "restore the copy from one of the other copies",
+            Self::DifferingAllocationTable { .. }
+                => "find out which copy agrees with the directory entries and restore the others from it",
+            Self::BrokenChain { .. }
+                => "the file is truncated; recover the rest from an orphaned chain if there is one",
+            Self::LoopingChain { .. }
+                => "end the chain at the cluster before the loop and check whether the file is complete",
+            Self::CrossLinkedChain { .. }
+                => "at most one of the files is intact; copy both elsewhere and check which one it is",
+            Self::OrphanedChain { .. }
+                => "the chain may be the remainder of a broken chain or a deleted file; inspect it using fat-data",
+            Self::SizeMismatch { .. }
+                => "correct the size in the directory entry or shorten the chain to match it",
+        }
+    }
+}
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnreadableAllocationTable { copy }
+                => write!(f, "allocation table copy {} cannot be read", copy),
+            Self::DifferingAllocationTable { copy, differing_entries, first_differing_cluster }
+                => write!(f, "allocation table copy {} differs from copy 0 in {} entries, starting at cluster {}", copy, differing_entries, first_differing_cluster),
+            Self::BrokenChain { path, cluster, entry: Some(entry) }
+                => write!(f, "chain of {:?} leads to cluster {} with FAT entry {:?}", path, cluster, entry),
+            Self::BrokenChain { path, cluster, entry: None }
+                => write!(f, "chain of {:?} leads to cluster {} outside of the data area", path, cluster),
+            Self::LoopingChain { path, cluster }
+                => write!(f, "chain of {:?} loops back to cluster {}", path, cluster),
+            Self::CrossLinkedChain { path, other_path, cluster }
+                => write!(f, "chain of {:?} is cross-linked with {:?} at cluster {}", path, other_path, cluster),
+            Self::OrphanedChain { first_cluster, cluster_count }
+                => write!(f, "chain of {} clusters starting at cluster {} belongs to no file", cluster_count, first_cluster),
+            Self::SizeMismatch { path, file_size_bytes, cluster_count, expected_cluster_count }
+                => write!(f, "{:?} is {} bytes long, which requires {} clusters, but its chain has {}", path, file_size_bytes, expected_cluster_count, cluster_count),
+        }
+    }
+}
+
+
+/// Keeps track of which file or directory each cluster belongs to.
+struct ClusterOwnership<'a> {
+    fat: &'a AllocationTable,
+    cluster_limit: u32,
+    cluster_to_owner: BTreeMap<u32, usize>,
+    owner_paths: Vec<String>,
+}
+impl ClusterOwnership<'_> {
+    /// Follows the chain starting at the given cluster and assigns its clusters to the given path.
+    ///
+    /// Returns the clusters that have been assigned and whether the chain ends properly.
+    fn claim(&mut self, path: &str, first_cluster: u32, inconsistencies: &mut Vec<Inconsistency>) -> (Vec<u32>, bool) {
+        let owner = self.owner_paths.len();
+        self.owner_paths.push(path.to_owned());
+
+        let mut chain = Vec::new();
+        let mut cluster = first_cluster;
+        loop {
+            let entry = self.fat.entries.get(usize::try_from(cluster).unwrap());
+            let Some(entry) = entry.filter(|_| cluster >= 2 && cluster < self.cluster_limit) else {
+                inconsistencies.push(Inconsistency::BrokenChain { path: path.to_owned(), cluster, entry: None });
+                return (chain, false);
+            };
+            if let Some(other_owner) = self.cluster_to_owner.get(&cluster) {
+                let inconsistency = if *other_owner == owner {
+                    Inconsistency::LoopingChain { path: path.to_owned(), cluster }
+                } else {
+                    Inconsistency::CrossLinkedChain { path: path.to_owned(), other_path: self.owner_paths[*other_owner].clone(), cluster }
+                };
+                inconsistencies.push(inconsistency);
+                return (chain, false);
+            }
+
+            self.cluster_to_owner.insert(cluster, owner);
+            chain.push(cluster);
+            match entry {
+                FatEntry::Chain(next_cluster) => cluster = *next_cluster,
+                FatEntry::Sentinel => return (chain, true),
+                other => {
+                    inconsistencies.push(Inconsistency::BrokenChain { path: path.to_owned(), cluster, entry: Some(*other) });
+                    return (chain, false);
+                },
+            }
+        }
+    }
+}
+
+
+/// Returns the name of a directory entry in its usual `NAME.EXT` form.
+fn entry_name(entry: &DirectoryEntry) -> String {
+    let base = String::from_utf8_lossy(entry.file_name.as_ref());
+    let extension = String::from_utf8_lossy(entry.extension.as_ref());
+    let (base, extension) = (base.trim_end_matches(' '), extension.trim_end_matches(' '));
+    if extension.is_empty() {
+        base.to_owned()
+    } else {
+        format!("{}.{}", base, extension)
+    }
+}
+
+
+fn check_directory<F: Read + Seek>(
+    image: &mut F,
+    header: &FatHeader,
+    ownership: &mut ClusterOwnership,
+    directory_data: &[u8],
+    directory_path: &str,
+    inconsistencies: &mut Vec<Inconsistency>,
+) -> Result<(), io::Error> {
+    let mut cursor = Cursor::new(directory_data);
+    for _ in 0..directory_data.len() / 32 {
+        let entry = DirectoryEntry::read(&mut cursor, header.variant())?;
+        if entry.file_name[0] == 0x00 {
+            // no more entries
+            break;
+        }
+        if entry.file_name[0] == 0xE5 || entry.file_name[0] == b'.' || entry.attributes.contains(Attributes::VOLUME_LABEL) {
+            // deleted entry, reference to this or the parent directory, volume label or VFAT long
+            // file name
+            continue;
+        }
+
+        let path = format!("{}{}", directory_path, entry_name(&entry));
+        if entry.attributes.contains(Attributes::SUBDIRECTORY) {
+            // clusters already belonging to another directory are not claimed again, so this
+            // cannot recurse endlessly
+            let (chain, _complete) = ownership.claim(&path, entry.first_cluster_number, inconsistencies);
+            let mut data = Vec::new();
+            for cluster in chain {
+                seek_to_cluster(image, header, cluster)?;
+                read_next_cluster_into(image, header, &mut data)?;
+            }
+            check_directory(image, header, ownership, &data, &format!("{}/", path), inconsistencies)?;
+        } else {
+            let (cluster_count, complete) = if entry.first_cluster_number == 0 {
+                (0, true)
+            } else {
+                let (chain, complete) = ownership.claim(&path, entry.first_cluster_number, inconsistencies);
+                (chain.len(), complete)
+            };
+            let expected_cluster_count = usize::try_from(entry.file_size_bytes).unwrap()
+                .div_ceil(header.cluster_bytes());
+            if complete && cluster_count != expected_cluster_count {
+                inconsistencies.push(Inconsistency::SizeMismatch {
+                    path,
+                    file_size_bytes: entry.file_size_bytes,
+                    cluster_count,
+                    expected_cluster_count,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+
+/// Checks a FAT file system image for inconsistencies without modifying it.
+///
+/// The copies of the allocation table are compared with each other, then the chains of clusters
+/// of all files and directories are followed through the first copy to find broken, looping and
+/// cross-linked chains as well as chains whose length does not match the file size. Finally, any
+/// allocated clusters that do not belong to a file or directory are reported as orphaned chains.
+///
+/// If the first copy of the allocation table cannot be read, only the copies are checked.
+pub fn check_consistency<F: Read + Seek>(image: &mut F) -> Result<Vec<Inconsistency>, io::Error> {
+    image.seek(SeekFrom::Start(0))?;
+    let header = FatHeader::read(image)?;
+    let mut inconsistencies = Vec::new();
+
+    // compare the copies of the allocation table
+    let mut fats = Vec::with_capacity(header.fat_count.into());
+    for copy in 0..header.fat_count {
+        image.seek(SeekFrom::Start(header.fat_offset(copy)))?;
+        match AllocationTable::read(image, header.variant(), header.fat_bytes()) {
+            Ok(fat) => fats.push(Some(fat)),
+            Err(e) => {
+                debug!("failed to read allocation table copy {}: {}", copy, e);
+                inconsistencies.push(Inconsistency::UnreadableAllocationTable { copy });
+                fats.push(None);
+            },
+        }
+    }
+    let Some(Some(fat)) = fats.first() else {
+        return Ok(inconsistencies);
+    };
+    for (copy, other_fat) in (1..header.fat_count).zip(fats.iter().skip(1)) {
+        let Some(other_fat) = other_fat else { continue };
+        let differing_clusters: Vec<usize> = (0..fat.entries.len().max(other_fat.entries.len()))
+            .filter(|i| fat.entries.get(*i) != other_fat.entries.get(*i))
+            .collect();
+        if let Some(first_differing_cluster) = differing_clusters.first() {
+            inconsistencies.push(Inconsistency::DifferingAllocationTable {
+                copy,
+                differing_entries: differing_clusters.len(),
+                first_differing_cluster: u32::try_from(*first_differing_cluster).unwrap(),
+            });
+        }
+    }
+
+    // follow the chains of all files and directories
+    let mut ownership = ClusterOwnership {
+        fat,
+        cluster_limit: header.cluster_limit(),
+        cluster_to_owner: BTreeMap::new(),
+        owner_paths: Vec::new(),
+    };
+    let mut root_data = Vec::new();
+    match DirectoryLocation::root(&header) {
+        DirectoryLocation::Sectors { first_sector, sector_count } => {
+            for i in 0..sector_count {
+                read_sector_into(image, &header, first_sector + i, &mut root_data)?;
+            }
+        },
+        DirectoryLocation::Clusters(first_cluster) => {
+            let (chain, _complete) = ownership.claim("/", first_cluster, &mut inconsistencies);
+            for cluster in chain {
+                seek_to_cluster(image, &header, cluster)?;
+                read_next_cluster_into(image, &header, &mut root_data)?;
+            }
+        },
+    }
+    check_directory(image, &header, &mut ownership, &root_data, "", &mut inconsistencies)?;
+
+    // collect the chains of the remaining allocated clusters
+    let limit = fat.entries.len().min(usize::try_from(header.cluster_limit()).unwrap());
+    let mut orphans: BTreeSet<u32> = (2..limit)
+        .filter(|i| matches!(fat.entries[*i], FatEntry::Chain(_) | FatEntry::Sentinel))
+        .map(|i| u32::try_from(i).unwrap())
+        .filter(|c| !ownership.cluster_to_owner.contains_key(c))
+        .collect();
+    let successors: BTreeSet<u32> = orphans.iter()
+        .filter_map(|c| match fat.entries[usize::try_from(*c).unwrap()] {
+            FatEntry::Chain(next_cluster) => Some(next_cluster),
+            _ => None,
+        })
+        .collect();
+    let mut chain_starts: Vec<u32> = orphans.difference(&successors)
+        .copied()
+        .collect();
+    chain_starts.reverse();
+    loop {
+        let first_cluster = match chain_starts.pop() {
+            Some(c) => c,
+            // the rest are loops without a start
+            None => match orphans.first() {
+                Some(c) => *c,
+                None => break,
+            },
+        };
+        let mut cluster_count = 0;
+        let mut cluster = first_cluster;
+        while orphans.remove(&cluster) {
+            cluster_count += 1;
+            match fat.entries[usize::try_from(cluster).unwrap()] {
+                FatEntry::Chain(next_cluster) => cluster = next_cluster,
+                _ => break,
+            }
+        }
+        if cluster_count > 0 {
+            inconsistencies.push(Inconsistency::OrphanedChain { first_cluster, cluster_count });
+        }
+    }
+
+    Ok(inconsistencies)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::time::Duration;
+    use super::*;
+
+    fn floppy_header() -> FatHeader {
+        FatHeader {
+            jump: [0xEB, 0x3C, 0x90],
+            oem_name: *b"EXPANDMS",
+            bytes_per_sector: 512,
+            sectors_per_cluster: 1,
+            reserved_sector_count: 1,
+            fat_count: 2,
+            max_root_dir_entries: 224,
+            total_sector_count: 2880,
+            media_descriptor: 0xF0,
+            sectors_per_fat: 9,
+            root_directory_location: RootDirectoryLocation::Sector(19),
+        }
+    }
+
+    fn read_file(image: &mut Cursor<Vec<u8>>, path: &str) -> Option<Vec<u8>> {
+        let mut volume = WritableVolume::open(image).unwrap();
+        let parts = split_path(path);
+        let (file_name, directory_path) = parts.split_last().unwrap();
+        let directory = volume.resolve_directory(directory_path, false, 0, 0).ok()?;
+        let (_, entry) = volume.find_entry(directory, short_name(file_name).unwrap()).unwrap()?;
+        let mut data = Vec::new();
+        if entry.first_cluster_number != 0 {
+            read_cluster_chain_into(volume.image, &volume.header, &volume.fat, entry.first_cluster_number, &mut data).unwrap();
+        }
+        data.truncate(entry.file_size_bytes.try_into().unwrap());
+        Some(data)
+    }
+
+    #[test]
+    fn test_read_header() {
+        let header = floppy_header();
+        let mut image = Cursor::new(Vec::new());
+        format_image(&mut image, &header).unwrap();
+        image.set_position(0);
+        assert_eq!(FatHeader::read(&mut image).unwrap(), header);
+
+        // FAT32 with 32-bit sector counts and the root directory in cluster 2
+        let mut boot_sector = vec![0u8; 512];
+        boot_sector[0x00..0x0B].copy_from_slice(b"\xEB\x58\x90MSWIN4.1");
+        boot_sector[0x0B..0x0D].copy_from_slice(&512u16.to_le_bytes());
+        boot_sector[0x0D] = 8;
+        boot_sector[0x0E..0x10].copy_from_slice(&32u16.to_le_bytes());
+        boot_sector[0x10] = 2;
+        boot_sector[0x15] = 0xF8;
+        boot_sector[0x1C..0x20].copy_from_slice(&63u32.to_le_bytes()); // hidden sectors
+        boot_sector[0x20..0x24].copy_from_slice(&4_194_304u32.to_le_bytes());
+        boot_sector[0x24..0x28].copy_from_slice(&4088u32.to_le_bytes());
+        boot_sector[0x2C..0x30].copy_from_slice(&2u32.to_le_bytes());
+        let header = FatHeader::read(&mut Cursor::new(&boot_sector)).unwrap();
+        assert_eq!(header.total_sector_count, 4_194_304);
+        assert_eq!(header.sectors_per_fat, 4088);
+        assert_eq!(header.variant(), FatVariant::Fat32);
+        assert_eq!(header.root_directory_location, RootDirectoryLocation::Cluster(2));
+
+        // as many clusters as FAT32, but with a 16-bit FAT size
+        boot_sector[0x16..0x18].copy_from_slice(&4088u16.to_le_bytes());
+        let error = FatHeader::read(&mut Cursor::new(&boot_sector)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_short_name() {
+        assert_eq!(short_name("user.exe"), Some((*b"USER    ", *b"EXE")));
+        assert_eq!(short_name("README"), Some((*b"README  ", *b"   ")));
+        assert_eq!(short_name("toolongname.txt"), None);
+        assert_eq!(short_name("file.text"), None);
+        assert_eq!(short_name("a b.txt"), None);
+        assert_eq!(short_name(".txt"), None);
+    }
+
+    #[test]
+    fn test_put_and_remove() {
+        let header = floppy_header();
+        let mut image = Cursor::new(Vec::new());
+        format_image(&mut image, &header).unwrap();
+        assert_eq!(image.get_ref().len(), 2880 * 512);
+
+        let big: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        put_file(&mut image, "README.TXT", b"hello", 0x0021, 0).unwrap();
+        put_file(&mut image, "system/sub/big.bin", &big, 0x0021, 0).unwrap();
+        put_file(&mut image, "EMPTY", b"", 0x0021, 0).unwrap();
+
+        assert_eq!(read_file(&mut image, "readme.txt").unwrap(), b"hello");
+        assert_eq!(read_file(&mut image, "SYSTEM/SUB/BIG.BIN").unwrap(), big);
+        assert_eq!(read_file(&mut image, "EMPTY").unwrap(), b"");
+
+        // replace, then remove
+        put_file(&mut image, "README.TXT", b"goodbye", 0x0021, 0).unwrap();
+        assert_eq!(read_file(&mut image, "README.TXT").unwrap(), b"goodbye");
+        remove_file(&mut image, "SYSTEM/SUB/BIG.BIN").unwrap();
+        assert_eq!(read_file(&mut image, "SYSTEM/SUB/BIG.BIN"), None);
+        assert_eq!(remove_file(&mut image, "SYSTEM").unwrap_err().kind(), io::ErrorKind::IsADirectory);
+
+        // all clusters except those of README.TXT and the two directories are free again
+        let volume = WritableVolume::open(&mut image).unwrap();
+        let used = volume.fat.entries[2..].iter()
+            .filter(|e| **e != FatEntry::Empty)
+            .count();
+        assert_eq!(used, 3);
+    }
+
+    #[test]
+    fn test_put_file_on_full_disk() {
+        let header = floppy_header();
+        let mut image = Cursor::new(Vec::new());
+        format_image(&mut image, &header).unwrap();
+
+        // leave 4 of the 2847 data clusters free
+        let filler = vec![0xAA; (2847 - 4) * 512];
+        put_file(&mut image, "FILLER.BIN", &filler, 0x0021, 0).unwrap();
+        let before = image.get_ref().clone();
+
+        // the new directory fits, the file does not
+        let big = vec![0x55; 4 * 512];
+        let error = put_file(&mut image, "NEWDIR/BIG.BIN", &big, 0x0021, 0).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::StorageFull);
+        assert!(image.get_ref() == &before, "image has been changed by failed put_file");
+        assert_eq!(check_consistency(&mut image).unwrap(), []);
+
+        // removing a file that does not exist changes nothing either
+        assert_eq!(remove_file(&mut image, "NEWDIR/BIG.BIN").unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert!(image.get_ref() == &before);
+
+        put_file(&mut image, "NEWDIR/SMALL.BIN", &big[..3 * 512], 0x0021, 0).unwrap();
+        assert_eq!(read_file(&mut image, "NEWDIR/SMALL.BIN").unwrap(), &big[..3 * 512]);
+        assert_eq!(check_consistency(&mut image).unwrap(), []);
+    }
+
+    #[test]
+    fn test_allocation_table_copies() {
+        let header = floppy_header();
+        let mut image = Cursor::new(Vec::new());
+        format_image(&mut image, &header).unwrap();
+        let big: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        put_file(&mut image, "BIG.BIN", &big, 0x0021, 0).unwrap();
+
+        // cut the chain of BIG.BIN (clusters 2 through 11) short in the first copy
+        let mut volume = WritableVolume::open(&mut image).unwrap();
+        volume.fat.entries[5] = FatEntry::Empty;
+        let mut fat_bytes = Vec::new();
+        volume.fat.write(&mut fat_bytes, header.variant()).unwrap();
+        image.get_mut()[512..512 + fat_bytes.len()].copy_from_slice(&fat_bytes);
+
+        let fats = AllocationTableCopies::read(&mut image, &header).unwrap();
+        assert_eq!(fats.primary().0, 0);
+        let mut data = Vec::new();
+        assert_eq!(fats.read_cluster_chain_into(&mut image, &header, 2, &mut data).unwrap(), 1);
+        assert_eq!(&data[..big.len()], big);
+
+        // damage the media type entry of the first copy so that it cannot be read at all
+        image.get_mut()[512] = 0x00;
+        let fats = AllocationTableCopies::read(&mut image, &header).unwrap();
+        assert_eq!(fats.readable_copies(), [1]);
+        assert_eq!(fats.primary().0, 1);
+    }
+
+    #[test]
+    fn test_no_intact_chain() {
+        let header = floppy_header();
+        let mut image = Cursor::new(Vec::new());
+        format_image(&mut image, &header).unwrap();
+        let big: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        put_file(&mut image, "BIG.BIN", &big, 0x0021, 0).unwrap();
+        let fat_size = header.fat_bytes();
+
+        let damage_all_copies = |image: &mut Cursor<Vec<u8>>, cluster: usize, entry: FatEntry| {
+            let mut volume = WritableVolume::open(image).unwrap();
+            volume.fat.entries[cluster] = entry;
+            let mut fat_bytes = Vec::new();
+            volume.fat.write(&mut fat_bytes, header.variant()).unwrap();
+            for copy in 0..2 {
+                let start = 512 + copy * fat_size;
+                image.get_mut()[start..start + fat_bytes.len()].copy_from_slice(&fat_bytes);
+            }
+        };
+
+        // a free cluster in the middle of the chain does not silently truncate the file
+        damage_all_copies(&mut image, 5, FatEntry::Empty);
+        let fats = AllocationTableCopies::read(&mut image, &header).unwrap();
+        let mut data = Vec::new();
+        let error = fats.read_cluster_chain_into(&mut image, &header, 2, &mut data).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // a loop in every copy is detected instead of followed forever
+        damage_all_copies(&mut image, 5, FatEntry::Chain(3));
+        let fats = AllocationTableCopies::read(&mut image, &header).unwrap();
+        let mut data = Vec::new();
+        let error = fats.read_cluster_chain_into(&mut image, &header, 2, &mut data).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(data.is_empty());
+    }
+
+    #[test]
+    fn test_cluster_chain_time_limit() {
+        let header = floppy_header();
+        let mut image = Cursor::new(Vec::new());
+        format_image(&mut image, &header).unwrap();
+        put_file(&mut image, "BIG.BIN", &[0x55; 5000], 0x0021, 0).unwrap();
+        let fats = AllocationTableCopies::read(&mut image, &header).unwrap();
+
+        let error = {
+            let _deadline = binstruct::deadline::set_time_limit(Some(Duration::ZERO));
+            fats.primary().1.cluster_chain(2).unwrap_err()
+        };
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(fats.primary().1.cluster_chain(2).unwrap().len(), 10);
+    }
+
+    #[test]
+    fn test_check_consistency() {
+        let header = floppy_header();
+        let mut image = Cursor::new(Vec::new());
+        format_image(&mut image, &header).unwrap();
+        let big: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        put_file(&mut image, "README.TXT", b"hello", 0x0021, 0).unwrap();
+        put_file(&mut image, "SYSTEM/BIG.BIN", &big, 0x0021, 0).unwrap();
+        assert_eq!(check_consistency(&mut image).unwrap(), []);
+
+        // README.TXT is in cluster 2, SYSTEM in cluster 3 and BIG.BIN in clusters 4 through 13;
+        // link README.TXT to BIG.BIN and leak two clusters, but only in the first copy
+        let mut volume = WritableVolume::open(&mut image).unwrap();
+        volume.fat.entries[2] = FatEntry::Chain(4);
+        volume.fat.allocate_chain(2, header.cluster_limit()).unwrap();
+        let mut fat_bytes = Vec::new();
+        volume.fat.write(&mut fat_bytes, header.variant()).unwrap();
+        image.get_mut()[512..512 + fat_bytes.len()].copy_from_slice(&fat_bytes);
+
+        assert_eq!(
+            check_consistency(&mut image).unwrap(),
+            [
+                Inconsistency::DifferingAllocationTable { copy: 1, differing_entries: 3, first_differing_cluster: 2 },
+                Inconsistency::SizeMismatch { path: "README.TXT".to_owned(), file_size_bytes: 5, cluster_count: 11, expected_cluster_count: 1 },
+                Inconsistency::CrossLinkedChain { path: "SYSTEM/BIG.BIN".to_owned(), other_path: "README.TXT".to_owned(), cluster: 4 },
+                Inconsistency::OrphanedChain { first_cluster: 14, cluster_count: 2 },
+            ],
+        );
+    }
 }
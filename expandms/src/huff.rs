@@ -1,10 +1,14 @@
+//! Huffman decoding.
+//!
+//! Trees can be built from an explicit mapping of bit sequences to symbols or, as most formats
+//! require, from the code lengths of a canonical Huffman code (see [`canonical_codes`]).
+
+
 use std::collections::BTreeMap;
 use std::fmt;
 use std::io;
 
-use tracing::debug;
-
-use crate::io_util::AnyBitReader;
+pub use crate::io_util::AnyBitReader;
 
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -12,6 +16,10 @@ pub enum HuffmanConstructionError {
     EmptySequence,
     PrefixFound { needle: Vec<bool>, haystack: Vec<bool> },
     SomeBranchesUndefined,
+    NoSymbols,
+    CodeTooLong { length: usize },
+    Oversubscribed,
+    Incomplete,
 }
 impl fmt::Display for HuffmanConstructionError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -22,6 +30,14 @@ impl fmt::Display for HuffmanConstructionError {
                 => write!(f, "sequence {:?} is a prefix of sequence {:?}", needle, haystack),
             Self::SomeBranchesUndefined
                 => write!(f, "some branches are not defined"),
+            Self::NoSymbols
+                => write!(f, "no symbol has a nonzero code length"),
+            Self::CodeTooLong { length }
+                => write!(f, "code length {} exceeds the maximum of {}", length, MAX_CODE_LENGTH),
+            Self::Oversubscribed
+                => write!(f, "code lengths are oversubscribed (more codes than fit into the code space)"),
+            Self::Incomplete
+                => write!(f, "code lengths are incomplete (some bit sequences are not assigned)"),
         }
     }
 }
@@ -29,10 +45,94 @@ impl std::error::Error for HuffmanConstructionError {
 }
 
 
+/// The maximum code length accepted by [`canonical_codes`] and [`HuffmanTree::new_canonical`].
+pub const MAX_CODE_LENGTH: usize = 32;
+
+
+/// Derives the codes of a canonical Huffman code from the code length of each symbol.
+///
+/// Returns the code (as a sequence of bits, first bit first) of each symbol in the same order as
+/// `symbol_lengths`; symbols with a length of 0 do not receive a code. Codes are assigned as
+/// described in RFC1951 § 3.2.2: shorter codes precede longer ones, and codes of the same length
+/// are assigned in the order of the symbols.
+///
+/// The lengths are validated first: at least one length must be nonzero, no length may exceed
+/// [`MAX_CODE_LENGTH`], and the codes must fill the code space exactly (i.e. satisfy the Kraft
+/// equality), otherwise decoding would be ambiguous or could hit unassigned bit sequences.
+pub fn canonical_codes(symbol_lengths: &[usize]) -> Result<Vec<Option<Vec<bool>>>, HuffmanConstructionError> {
+    // check the Kraft sum, in units of 2**(-MAX_CODE_LENGTH)
+    let mut kraft_sum: u64 = 0;
+    for &length in symbol_lengths {
+        if length == 0 {
+            continue;
+        }
+        if length > MAX_CODE_LENGTH {
+            return Err(HuffmanConstructionError::CodeTooLong { length });
+        }
+        kraft_sum += 1 << (MAX_CODE_LENGTH - length);
+        if kraft_sum > 1 << MAX_CODE_LENGTH {
+            return Err(HuffmanConstructionError::Oversubscribed);
+        }
+    }
+    if kraft_sum == 0 {
+        return Err(HuffmanConstructionError::NoSymbols);
+    }
+    if kraft_sum < 1 << MAX_CODE_LENGTH {
+        return Err(HuffmanConstructionError::Incomplete);
+    }
+
+    // count the codes of each length and find the first code of each length
+    let mut length_counts = [0u64; MAX_CODE_LENGTH + 1];
+    for &length in symbol_lengths {
+        length_counts[length] += 1;
+    }
+    length_counts[0] = 0;
+    let mut next_code = [0u64; MAX_CODE_LENGTH + 1];
+    let mut code = 0;
+    for length in 1..=MAX_CODE_LENGTH {
+        code = (code + length_counts[length - 1]) << 1;
+        next_code[length] = code;
+    }
+
+    let codes = symbol_lengths.iter()
+        .map(|&length| {
+            if length == 0 {
+                return None;
+            }
+            let code = next_code[length];
+            next_code[length] += 1;
+            let bits = (0..length)
+                .rev()
+                .map(|bit_index| code & (1 << bit_index) != 0)
+                .collect();
+            Some(bits)
+        })
+        .collect();
+    Ok(codes)
+}
+
+
 /// A tree used for Huffman decoding.
+///
+/// The tree is stored as a table with one row per branch node. Decoding from a bit reader looks up
+/// the first [`LOOKUP_BITS`] bits at once in a second table; only the rare longer codes are then
+/// walked bit by bit through the tree.
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub(crate) struct HuffmanTree<T> {
-    root_node: BranchNode<T>,
+pub struct HuffmanTree<T> {
+    /// The branch nodes; each row contains the entry reached through a 0 bit and through a 1 bit.
+    /// The root is in row 0.
+    branches: Vec<[TableEntry; 2]>,
+
+    /// The symbols referenced by the leaf entries.
+    symbols: Vec<T>,
+
+    /// The number of bits looked up at once: [`LOOKUP_BITS`] or the length of the longest code,
+    /// whichever is less.
+    lookup_bits: u8,
+
+    /// The entry reached through each sequence of `lookup_bits` bits (the first bit being the most
+    /// significant one), along with the number of bits it takes to reach it.
+    lookup: Vec<(TableEntry, u8)>,
 }
 impl<T> HuffmanTree<T> {
     /// Creates a new tree from the given mapping of bit sequences to symbols.
@@ -46,135 +146,112 @@ impl<T> HuffmanTree<T> {
     pub fn new(sequence_to_symbol: BTreeMap<Vec<bool>, T>) -> Result<Self, HuffmanConstructionError> {
         // protect against invalid Huffman trees:
         // 1. all sequences must be at least one element long
-        for (sequence, _symbol) in &sequence_to_symbol {
-            if sequence.len() < 1 {
+        for sequence in sequence_to_symbol.keys() {
+            if sequence.is_empty() {
                 return Err(HuffmanConstructionError::EmptySequence);
             }
         }
         // 2. no sequence may be a prefix of another sequence
-        for (needle, _symbol) in &sequence_to_symbol {
-            for (haystack, _symbol) in &sequence_to_symbol {
-                if haystack.starts_with(needle) && needle != haystack {
-                    return Err(HuffmanConstructionError::PrefixFound {
-                        needle: needle.clone(),
-                        haystack: haystack.clone(),
-                    });
-                }
+        // (sorted order places a prefix directly before the sequences it is a prefix of)
+        let mut previous: Option<&Vec<bool>> = None;
+        for sequence in sequence_to_symbol.keys() {
+            if let Some(needle) = previous
+                && sequence.starts_with(needle)
+            {
+                return Err(HuffmanConstructionError::PrefixFound {
+                    needle: needle.clone(),
+                    haystack: sequence.clone(),
+                });
             }
+            previous = Some(sequence);
         }
 
         // start constructing
-        let mut root_node_uc = BranchNodeUnderConstruction::new();
+        let mut branches: Vec<[Option<TableEntry>; 2]> = vec![[None, None]];
+        let mut symbols = Vec::with_capacity(sequence_to_symbol.len());
         for (sequence, symbol) in sequence_to_symbol {
-            let mut current_node_uc = &mut root_node_uc;
+            let mut current_branch = 0;
 
             // establish branches
             for &element in &sequence[..sequence.len() - 1] {
-                let wanted_branch = if element {
-                    &mut current_node_uc.true_child
-                } else {
-                    &mut current_node_uc.false_child
-                };
-
-                if wanted_branch.is_none() {
-                    *wanted_branch = Some(Box::new(HuffmanNodeUnderConstruction::Branch(BranchNodeUnderConstruction::new())));
-                }
-                current_node_uc = match wanted_branch.as_mut().unwrap().as_branch_mut() {
-                    Some(wb) => wb,
-                    None => unreachable!("sequence-not-a-prefix-of-sequence invariant violated"),
+                current_branch = match branches[current_branch][usize::from(element)] {
+                    Some(TableEntry::Branch(index)) => index,
+                    Some(TableEntry::Leaf(_)) => unreachable!("sequence-not-a-prefix-of-sequence invariant violated"),
+                    None => {
+                        let new_index = branches.len();
+                        branches.push([None, None]);
+                        branches[current_branch][usize::from(element)] = Some(TableEntry::Branch(new_index));
+                        new_index
+                    },
                 };
             }
 
             // hang up the final node
             let last_element = *sequence.last().unwrap();
-            let wanted_leaf = if last_element {
-                &mut current_node_uc.true_child
-            } else {
-                &mut current_node_uc.false_child
-            };
+            let wanted_leaf = &mut branches[current_branch][usize::from(last_element)];
             if wanted_leaf.is_some() {
                 unreachable!("reached an existing node through sequence {:?}", sequence);
             }
-            *wanted_leaf = Some(Box::new(HuffmanNodeUnderConstruction::Leaf(LeafNode::new(symbol))));
+            *wanted_leaf = Some(TableEntry::Leaf(symbols.len()));
+            symbols.push(symbol);
         }
 
-        if !root_node_uc.is_finished() {
-            return Err(HuffmanConstructionError::SomeBranchesUndefined);
-        }
-        let root_node: BranchNode<T> = match root_node_uc.try_into() {
-            Ok(rn) => rn,
-            Err(_) => unreachable!(),
-        };
+        // 3. every branch must be complete
+        let branches = branches.into_iter()
+            .map(|[false_entry, true_entry]| Some([false_entry?, true_entry?]))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(HuffmanConstructionError::SomeBranchesUndefined)?;
 
+        let (lookup_bits, lookup) = lookup_table(&branches);
         Ok(Self {
-            root_node,
+            branches,
+            symbols,
+            lookup_bits,
+            lookup,
         })
     }
 
+    /// Decodes one symbol from the given sequence of bits.
+    ///
+    /// Returns `None` if the bits run out before a symbol has been decoded.
     pub fn decode_one<I: Iterator<Item = bool>>(&self, mut iterator: I) -> Option<&T> {
-        let mut current_node = &self.root_node;
+        let mut current_branch = 0;
         loop {
-            // find out which branch to take
             let take_true_branch = iterator.next()?;
-            let branch_taken = if take_true_branch {
-                &*current_node.true_child
-            } else {
-                &*current_node.false_child
-            };
-            match branch_taken {
-                HuffmanNode::Branch(b) => {
-                    current_node = b;
-                    // keep going
-                },
-                HuffmanNode::Leaf(l) => {
-                    // we've reached the bottom
-                    return Some(&l.value);
-                },
+            match self.branches[current_branch][usize::from(take_true_branch)] {
+                TableEntry::Branch(index) => current_branch = index,
+                TableEntry::Leaf(index) => return Some(&self.symbols[index]),
             }
         }
     }
 
+    /// Decodes one symbol from the given bit reader.
+    ///
+    /// Returns `Ok(None)` if the reader is exhausted before the first bit of the symbol and an
+    /// [`io::ErrorKind::UnexpectedEof`] error if it is exhausted in the middle of the symbol.
     pub fn decode_one_from_bit_reader<R: AnyBitReader>(
         &self,
         bit_reader: &mut R,
     ) -> Result<Option<&T>, io::Error> {
-        let mut current_node = &self.root_node;
-        let mut first_iteration = true;
-        let mut branching = Vec::new();
-        loop {
-            let take_true_branch = match bit_reader.read_bit() {
-                Ok(Some(b)) => b,
-                Ok(None) => return if first_iteration {
-                    Ok(None)
-                } else {
-                    Err(io::ErrorKind::UnexpectedEof.into())
-                },
-                Err(e) => return Err(e),
-            };
-            if tracing::enabled!(tracing::Level::DEBUG) {
-                if take_true_branch {
-                    branching.push(b'1');
-                } else {
-                    branching.push(b'0');
-                }
-            }
-            first_iteration = false;
+        let (bits, available_bits) = bit_reader.peek_bits(self.lookup_bits)?;
+        if available_bits == 0 {
+            return Ok(None);
+        }
+        let (entry, length) = self.lookup[usize::try_from(bits).unwrap()];
+        if length > available_bits {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        bit_reader.skip_peeked_bits(length);
 
-            let branch_taken = if take_true_branch {
-                &*current_node.true_child
-            } else {
-                &*current_node.false_child
-            };
-            match branch_taken {
-                HuffmanNode::Branch(b) => {
-                    current_node = b;
-                },
-                HuffmanNode::Leaf(l) => {
-                    // we've reached the bottom
-                    debug!("Huffman: took branches {}", std::str::from_utf8(&branching).unwrap());
-                    branching.reverse();
-                    debug!("Huffman: in reverse:   {}", std::str::from_utf8(&branching).unwrap());
-                    return Ok(Some(&l.value));
+        // codes longer than the lookup table continue through the tree
+        let mut current_entry = entry;
+        loop {
+            match current_entry {
+                TableEntry::Leaf(index) => return Ok(Some(&self.symbols[index])),
+                TableEntry::Branch(index) => {
+                    let take_true_branch = bit_reader.read_bit()?
+                        .ok_or(io::ErrorKind::UnexpectedEof)?;
+                    current_entry = self.branches[index][usize::from(take_true_branch)];
                 },
             }
         }
@@ -192,197 +269,83 @@ impl<T: HuffmanCanonicalizable> HuffmanTree<T> {
     /// etc.
     ///
     /// A 0 value in `symbol_lengths` is a "skip", i.e. the corresponding symbol shall not be
-    /// encodable through the resulting Huffman tree.
+    /// encodable through the resulting Huffman tree. The lengths are validated as described in
+    /// [`canonical_codes`].
     pub fn new_canonical(symbol_lengths: &[usize]) -> Result<Self, HuffmanConstructionError> {
-        // at least one symbol with length > 0
-        assert!(symbol_lengths.iter().any(|sl| *sl > 0));
+        let codes = canonical_codes(symbol_lengths)?;
 
-        // convert to pairs of symbol and symbol length
-        let mut lengths_and_symbols = Vec::with_capacity(symbol_lengths.len());
+        let mut sequence_to_symbol: BTreeMap<Vec<bool>, T> = BTreeMap::new();
         let mut current_symbol = T::first_value();
-        for &symbol_length in &symbol_lengths[..symbol_lengths.len()-1] {
-            let next_symbol = current_symbol.incremented();
-            assert!(next_symbol > current_symbol);
-            if symbol_length > 0 {
-                lengths_and_symbols.push((symbol_length, current_symbol));
+        let mut codes_iter = codes.into_iter().peekable();
+        while let Some(code) = codes_iter.next() {
+            let next_symbol = if codes_iter.peek().is_some() {
+                // don't increment once too many
+                let next_symbol = current_symbol.incremented();
+                assert!(next_symbol > current_symbol);
+                Some(next_symbol)
+            } else {
+                None
+            };
+            if let Some(code) = code {
+                sequence_to_symbol.insert(code, current_symbol);
             }
-            current_symbol = next_symbol;
-        }
-        // add the final one without incrementing once too many
-        let last_symbol_length = *symbol_lengths.last().unwrap();
-        if last_symbol_length > 0 {
-            lengths_and_symbols.push((last_symbol_length, current_symbol));
-        }
-
-        // re-sort by symbol length, then by value
-        lengths_and_symbols.sort_unstable();
-
-        // construct the tree
-        let mut sequence_to_symbol: BTreeMap<Vec<bool>, T> = BTreeMap::new();
-
-        let max_symbol_length = symbol_lengths.iter().max().copied().unwrap_or(0);
-        let mut current_sequence: Vec<bool> = Vec::with_capacity(max_symbol_length);
-        let mut iterator = lengths_and_symbols.into_iter();
-
-        // first symbol: given length, all zeroes
-        let (first_length, first_symbol) = iterator.next().unwrap();
-        while current_sequence.len() < first_length {
-            current_sequence.push(false);
-        }
-        sequence_to_symbol.insert(current_sequence.clone(), first_symbol);
-
-        while let Some((next_length, next_symbol)) = iterator.next() {
-            // increment
-            increment_bools(&mut current_sequence);
-
-            // extend the sequence if necessary
-            while current_sequence.len() < next_length {
-                current_sequence.push(false);
+            match next_symbol {
+                Some(ns) => current_symbol = ns,
+                None => break,
             }
-
-            // store
-            sequence_to_symbol.insert(current_sequence.clone(), next_symbol);
         }
 
-        // and we're done
         HuffmanTree::new(sequence_to_symbol)
     }
 }
 
-#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-enum HuffmanNode<T> {
-    Branch(BranchNode<T>),
-    Leaf(LeafNode<T>),
-}
-
-#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-struct BranchNode<T> {
-    true_child: Box<HuffmanNode<T>>,
-    false_child: Box<HuffmanNode<T>>,
-}
-
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-struct LeafNode<T> {
-    value: T,
-}
-impl<T> LeafNode<T> {
-    pub fn new(value: T) -> Self {
-        Self {
-            value,
-        }
-    }
-}
-
-#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-enum HuffmanNodeUnderConstruction<T> {
-    Branch(BranchNodeUnderConstruction<T>),
-    Leaf(LeafNode<T>),
-}
-impl<T> HuffmanNodeUnderConstruction<T> {
-    pub const fn as_branch(&self) -> Option<&BranchNodeUnderConstruction<T>> {
-        match self {
-            Self::Branch(b) => Some(b),
-            _ => None,
-        }
-    }
-    pub const fn as_branch_mut(&mut self) -> Option<&mut BranchNodeUnderConstruction<T>> {
-        match self {
-            Self::Branch(b) => Some(b),
-            _ => None,
-        }
-    }
-
-    pub const fn as_leaf(&self) -> Option<&LeafNode<T>> {
-        match self {
-            Self::Leaf(l) => Some(l),
-            _ => None,
-        }
-    }
-
-    pub const fn as_leaf_mut(&mut self) -> Option<&mut LeafNode<T>> {
-        match self {
-            Self::Leaf(l) => Some(l),
-            _ => None,
-        }
-    }
-
-    pub const fn is_finished(&self) -> bool {
-        match self {
-            Self::Branch(b) => b.is_finished(),
-            Self::Leaf(_) => true,
+/// The most bits that [`HuffmanTree::decode_one_from_bit_reader`] looks up at once.
+///
+/// Codes of up to this length are decoded with a single lookup; the table has one entry for each
+/// combination of this many bits.
+pub const LOOKUP_BITS: u8 = 10;
+
+/// Derives the lookup table of a tree with the given branches.
+fn lookup_table(branches: &[[TableEntry; 2]]) -> (u8, Vec<(TableEntry, u8)>) {
+    fn depth(branches: &[[TableEntry; 2]], entry: TableEntry) -> usize {
+        match entry {
+            TableEntry::Leaf(_) => 0,
+            TableEntry::Branch(index) => 1 + branches[index].iter()
+                .map(|child| depth(branches, *child))
+                .max()
+                .unwrap(),
         }
     }
-}
-impl<T> TryFrom<HuffmanNodeUnderConstruction<T>> for HuffmanNode<T> {
-    type Error = HuffmanNodeUnderConstruction<T>;
-
-    fn try_from(value: HuffmanNodeUnderConstruction<T>) -> Result<Self, Self::Error> {
-        match value {
-            HuffmanNodeUnderConstruction::Leaf(l) => Ok(HuffmanNode::Leaf(l)),
-            HuffmanNodeUnderConstruction::Branch(b) => {
-                match b.try_into() {
-                    Ok(bn) => Ok(HuffmanNode::Branch(bn)),
-                    Err(bnuc) => Err(HuffmanNodeUnderConstruction::Branch(bnuc)),
+    let lookup_bits = depth(branches, TableEntry::Branch(0))
+        .min(usize::from(LOOKUP_BITS));
+    let lookup_bits = u8::try_from(lookup_bits).unwrap();
+
+    let lookup = (0..1usize << lookup_bits)
+        .map(|bits| {
+            let mut entry = TableEntry::Branch(0);
+            let mut length = 0;
+            while let TableEntry::Branch(index) = entry {
+                if length == lookup_bits {
+                    break;
                 }
-            },
-        }
-    }
-}
-
-#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
-struct BranchNodeUnderConstruction<T> {
-    true_child: Option<Box<HuffmanNodeUnderConstruction<T>>>,
-    false_child: Option<Box<HuffmanNodeUnderConstruction<T>>>,
-}
-impl<T> BranchNodeUnderConstruction<T> {
-    pub const fn new() -> Self {
-        Self {
-            true_child: None,
-            false_child: None,
-        }
-    }
-
-    pub const fn is_finished(&self) -> bool {
-        let true_child_is_finished = match self.true_child.as_ref() {
-            Some(tc) => tc.is_finished(),
-            None => false,
-        };
-        if !true_child_is_finished {
-            return false;
-        }
-
-        let false_child_is_finished = match self.false_child.as_ref() {
-            Some(fc) => fc.is_finished(),
-            None => false,
-        };
-        false_child_is_finished
-    }
+                let bit = (bits >> (lookup_bits - length - 1)) & 1;
+                entry = branches[index][bit];
+                length += 1;
+            }
+            (entry, length)
+        })
+        .collect();
+    (lookup_bits, lookup)
 }
-impl<T> TryFrom<BranchNodeUnderConstruction<T>> for BranchNode<T> {
-    type Error = BranchNodeUnderConstruction<T>;
-
-    fn try_from(value: BranchNodeUnderConstruction<T>) -> Result<Self, Self::Error> {
-        if value.is_finished() {
-            let true_child_uc = value.true_child.unwrap();
-            let false_child_uc = value.false_child.unwrap();
 
-            let true_child: Box<HuffmanNode<T>> = match (*true_child_uc).try_into() {
-                Ok(tc) => Box::new(tc),
-                Err(_) => unreachable!(),
-            };
-            let false_child: Box<HuffmanNode<T>> = match (*false_child_uc).try_into() {
-                Ok(fc) => Box::new(fc),
-                Err(_) => unreachable!(),
-            };
+/// An entry in the table of a [`HuffmanTree`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum TableEntry {
+    /// Continue decoding at the branch with this index.
+    Branch(usize),
 
-            Ok(Self {
-                true_child,
-                false_child,
-            })
-        } else {
-            Err(value)
-        }
-    }
+    /// The symbol with this index has been decoded.
+    Leaf(usize),
 }
 
 /// A value that can be used to generate symbols for a canonical Huffman table.
@@ -417,27 +380,12 @@ implement_num_canon!(u8, u16, u32, u64, u128, usize);
 implement_num_canon!(i8, i16, i32, i64, i128, isize);
 
 
-fn increment_bools(bools: &mut Vec<bool>) {
-    for b in bools.iter_mut().rev() {
-        if *b {
-            *b = false;
-            // keep going
-        } else {
-            *b = true;
-            // done
-            return;
-        }
-    }
-
-    // carry fell out; insert true at beginning
-    bools.insert(0, true);
-}
-
-
 #[cfg(test)]
 mod tests {
-    use super::HuffmanTree;
+    use super::{canonical_codes, HuffmanConstructionError, HuffmanTree, LOOKUP_BITS};
+    use crate::io_util::BitReader;
     use std::collections::BTreeMap;
+    use std::io;
 
     #[test]
     fn test_tree_construction() {
@@ -469,4 +417,66 @@ mod tests {
 
         assert_eq!(canonical_tree, manual_tree);
     }
+
+    #[test]
+    fn test_canonical_codes() {
+        // example from RFC1951 § 3.2.2
+        let codes = canonical_codes(&[3, 3, 3, 3, 3, 2, 4, 4]).unwrap();
+        let as_strings: Vec<String> = codes.iter()
+            .map(|c| c.as_ref().unwrap().iter().map(|&b| if b { '1' } else { '0' }).collect())
+            .collect();
+        assert_eq!(as_strings, ["010", "011", "100", "101", "110", "00", "1110", "1111"]);
+
+        assert_eq!(canonical_codes(&[1, 0, 1]).unwrap()[1], None);
+        assert_eq!(canonical_codes(&[0, 0]), Err(HuffmanConstructionError::NoSymbols));
+        assert_eq!(canonical_codes(&[1, 1, 1]), Err(HuffmanConstructionError::Oversubscribed));
+        assert_eq!(canonical_codes(&[1, 2]), Err(HuffmanConstructionError::Incomplete));
+        assert_eq!(canonical_codes(&[1, 33]), Err(HuffmanConstructionError::CodeTooLong { length: 33 }));
+
+        let tree: HuffmanTree<u8> = HuffmanTree::new_canonical(&[3, 3, 3, 3, 3, 2, 4, 4]).unwrap();
+        let bits = [true, true, true, false, false, false];
+        let mut iterator = bits.into_iter();
+        assert_eq!(tree.decode_one(&mut iterator), Some(&6));
+        assert_eq!(tree.decode_one(&mut iterator), Some(&5));
+        assert_eq!(tree.decode_one(&mut iterator), None);
+    }
+
+    #[test]
+    fn test_decode_from_bit_reader() {
+        // codes from 1 to 13 bits long, so that some are longer than the lookup table
+        let lengths = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 13];
+        assert!(lengths.iter().any(|l| *l > usize::from(LOOKUP_BITS)));
+        let codes = canonical_codes(&lengths).unwrap();
+        let tree: HuffmanTree<u8> = HuffmanTree::new_canonical(&lengths).unwrap();
+
+        // the codes of the symbols take up 168 bits, which ends at a byte boundary
+        let message: Vec<u8> = vec![13, 0, 12, 1, 11, 2, 10, 3, 9, 4, 8, 5, 7, 6, 6, 7, 5, 8, 4, 9, 3, 10, 3];
+        let bits: Vec<bool> = message.iter()
+            .flat_map(|symbol| codes[usize::from(*symbol)].clone().unwrap())
+            .collect();
+        assert_eq!(bits.len() % 8, 0);
+        let bytes: Vec<u8> = bits.chunks(8)
+            .map(|byte_bits| byte_bits.iter().fold(0, |byte, bit| (byte << 1) | u8::from(*bit)))
+            .collect();
+
+        let mut data = bytes.as_slice();
+        let mut reader: BitReader<_, true> = BitReader::new(&mut data);
+        for symbol in &message {
+            assert_eq!(tree.decode_one_from_bit_reader(&mut reader).unwrap(), Some(symbol));
+        }
+        assert_eq!(tree.decode_one_from_bit_reader(&mut reader).unwrap(), None);
+
+        // the data ends in the middle of the 13-bit code of the first symbol
+        let mut data = &bytes[..1];
+        let mut reader: BitReader<_, true> = BitReader::new(&mut data);
+        let error = tree.decode_one_from_bit_reader(&mut reader).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        // bits read outside of codes are taken from where decoding left off
+        let mut data = bytes.as_slice();
+        let mut reader: BitReader<_, true> = BitReader::new(&mut data);
+        assert_eq!(tree.decode_one_from_bit_reader(&mut reader).unwrap(), Some(&13));
+        let next_bits: Vec<bool> = (0..4).map(|_| reader.read_bit_strict().unwrap()).collect();
+        assert_eq!(next_bits, bits[13..17]);
+    }
 }
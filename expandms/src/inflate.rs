@@ -283,6 +283,35 @@ impl<'r, R: Read> Inflater<'r, R> {
         self.lookback = lookback;
     }
 
+    /// Takes the history out of the inflater, e.g. to hand it to the inflater of the next block
+    /// using [`Inflater::set_lookback`].
+    pub fn into_lookback(self) -> RingBuffer<u8> {
+        self.lookback
+    }
+
+    /// The size of the history (window) in bytes.
+    pub fn window_size(&self) -> usize {
+        self.lookback.len()
+    }
+
+    /// Replaces the history with a preset dictionary, as if it had just been inflated.
+    ///
+    /// If the dictionary is larger than the window, only its end is kept.
+    pub fn set_dictionary(&mut self, dictionary: &[u8]) {
+        let size = self.lookback.len();
+        let kept = &dictionary[dictionary.len().saturating_sub(size)..];
+        self.lookback = RingBuffer::new(0x00, size);
+        self.lookback.extend(kept.iter().copied());
+    }
+
+    /// Continues inflating a new stream from `reader` while keeping the history.
+    ///
+    /// Any bits of the current stream that have not been consumed yet are discarded. MSZIP, for
+    /// example, compresses each block separately, but matches may reach back into previous blocks.
+    pub fn reset_keep_history(&mut self, reader: &'r mut R) {
+        self.reader = BitReader::new(reader);
+    }
+
     pub fn inflate_block(&mut self, dest_buffer: &mut Vec<u8>) -> Result<bool, Error> {
         let is_final = self.reader.read_bit_strict()?;
         if is_final {
@@ -309,9 +338,7 @@ impl<'r, R: Read> Inflater<'r, R> {
                 let mut buf = vec![0u8; len.into()];
                 self.reader.read_exact(&mut buf)?;
                 dest_buffer.write_all(&buf)?;
-                for b in &buf {
-                    self.lookback.push(*b);
-                }
+                self.lookback.extend(buf);
             },
             1|2 => {
                 // Huffman table compression
@@ -486,4 +513,41 @@ mod tests {
         }
         assert_eq!(&output, plaintext);
     }
+
+    fn inflate_to_end<R: std::io::Read>(inflater: &mut Inflater<'_, R>, output: &mut Vec<u8>) {
+        loop {
+            let is_last = inflater.inflate_block(output)
+                .expect("failed to inflate block");
+            if is_last {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_inflate_with_history() {
+        // produced with zlib.compressobj(9, zlib.DEFLATED, -15, zdict=dictionary)
+        let dictionary = b"the quick brown fox jumps over the lazy dog";
+        let deflated = b"Cf\xa3\xabA3\x02\x00";
+        let mut deflated_reader = Cursor::new(deflated);
+        let mut inflater = Inflater::new(&mut deflated_reader, MAX_LOOKBACK_DISTANCE);
+        inflater.set_dictionary(dictionary);
+        let mut output = Vec::new();
+        inflate_to_end(&mut inflater, &mut output);
+        assert_eq!(&output, b"the lazy dog jumps over the quick brown fox");
+
+        // the second stream uses the plaintext of the first as its dictionary, like MSZIP blocks
+        let first = b"KL\xcaIUHN\x04\x91i`2\x1dL\x16\x83\xc9\x12\x10\t\x00";
+        let second = b"K,JD\xa8)\x81\xc8\x82)\x00";
+        let mut first_reader = Cursor::new(&first[..]);
+        let mut second_reader = Cursor::new(&second[..]);
+        let mut inflater = Inflater::new(&mut first_reader, MAX_LOOKBACK_DISTANCE);
+        let mut output = Vec::new();
+        inflate_to_end(&mut inflater, &mut output);
+        assert_eq!(&output, b"able cable fable gable sable table");
+        inflater.reset_keep_history(&mut second_reader);
+        output.clear();
+        inflate_to_end(&mut inflater, &mut output);
+        assert_eq!(&output, b"arable cable table stable");
+    }
 }
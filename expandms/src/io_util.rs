@@ -1,4 +1,4 @@
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 
 use display_bytes::DisplayBytes;
 use tracing::debug;
@@ -42,6 +42,14 @@ macro_rules! impl_read_n_bytes {
         impl_read_n_bytes!($name, $byte_count, $byte_count, $ret_type, $convert_func);
     };
 }
+/// The most bits that [`BitReader::peek_bits`] can return at once.
+pub(crate) const MAX_PEEK_BITS: u8 = 16;
+
+/// The most units a bit reader may have to take in advance to peek at [`MAX_PEEK_BITS`] bits: the
+/// bits left in the current unit can be fewer than a whole unit.
+const MAX_QUEUED_UNITS: usize = (MAX_PEEK_BITS as usize).div_ceil(8) + 1;
+
+
 macro_rules! impl_bit_reader {
     ($name:ident, $unit_type:ty, $bits_per_unit:expr, $bytes_to_unit_func:ident) => {
         #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -50,6 +58,11 @@ macro_rules! impl_bit_reader {
             unit_picked_apart: Option<$unit_type>,
             bit_index: u8,
             total_bits_read: u64,
+
+            /// Units already taken from the byte reader by [`peek_bits`](Self::peek_bits) but
+            /// not picked apart yet, in order.
+            queued_units: [$unit_type; MAX_QUEUED_UNITS],
+            queued_unit_count: usize,
         }
         impl<R: Read, const MSB_TO_LSB: bool> $name<R, MSB_TO_LSB> {
             pub fn new(byte_reader: R) -> Self {
@@ -58,30 +71,51 @@ macro_rules! impl_bit_reader {
                     unit_picked_apart: None,
                     bit_index: 0,
                     total_bits_read: 0,
+                    queued_units: [0; MAX_QUEUED_UNITS],
+                    queued_unit_count: 0,
+                }
+            }
+
+            /// Reads the next unit from the byte reader; returns `Ok(None)` at the end of the data.
+            fn fetch_unit(&mut self) -> Result<Option<$unit_type>, io::Error> {
+                // bits to bytes conversion rounding up:
+                let mut buf = [0u8; ($bits_per_unit + (8 - 1)) / 8];
+                let mut total_bytes_read = 0;
+                while total_bytes_read < buf.len() {
+                    let now_bytes_read = self.byte_reader.read(&mut buf[total_bytes_read..])?;
+                    if now_bytes_read == 0 {
+                        // EOF
+                        return if total_bytes_read == 0 {
+                            // EOF at start; that's okay
+                            Ok(None)
+                        } else {
+                            // EOF midway; that isn't
+                            Err(io::ErrorKind::UnexpectedEof.into())
+                        };
+                    }
+                    total_bytes_read += now_bytes_read;
+                }
+                Ok(Some(<$unit_type>::$bytes_to_unit_func(buf)))
+            }
+
+            /// Takes the next unit from the queue or, if it is empty, from the byte reader.
+            fn next_unit(&mut self) -> Result<Option<$unit_type>, io::Error> {
+                if self.queued_unit_count == 0 {
+                    return self.fetch_unit();
                 }
+                let unit = self.queued_units[0];
+                self.queued_units.copy_within(1.., 0);
+                self.queued_unit_count -= 1;
+                Ok(Some(unit))
             }
 
             pub fn read_bit(&mut self) -> Result<Option<bool>, io::Error> {
                 if self.bit_index == 0 {
                     // pull in new unit
-                    // bits to bytes conversion rounding up:
-                    let mut buf = [0u8; ($bits_per_unit + (8 - 1)) / 8];
-                    let mut total_bytes_read = 0;
-                    while total_bytes_read < buf.len() {
-                        let now_bytes_read = self.byte_reader.read(&mut buf[total_bytes_read..])?;
-                        if now_bytes_read == 0 {
-                            // EOF
-                            return if total_bytes_read == 0 {
-                                // EOF at start; that's okay
-                                Ok(None)
-                            } else {
-                                // EOF midway; that isn't
-                                Err(io::ErrorKind::UnexpectedEof.into())
-                            };
-                        }
-                        total_bytes_read += now_bytes_read;
+                    match self.next_unit()? {
+                        Some(unit) => self.unit_picked_apart = Some(unit),
+                        None => return Ok(None),
                     }
-                    self.unit_picked_apart = Some(<$unit_type>::$bytes_to_unit_func(buf));
                 }
 
                 // if bit_index > 0, we have already stored a unit
@@ -107,6 +141,69 @@ macro_rules! impl_bit_reader {
                 Ok(Some(bit_is_set))
             }
 
+            /// Returns the next `count` bits without consuming them, the first bit being the most
+            /// significant one, along with the number of bits that are actually left if the data
+            /// ends sooner. Missing bits are returned as 0.
+            ///
+            /// At most [`MAX_PEEK_BITS`] bits can be peeked at.
+            pub fn peek_bits(&mut self, count: u8) -> Result<(u32, u8), io::Error> {
+                assert!(count <= MAX_PEEK_BITS);
+                let bits_in_current_unit = if self.unit_picked_apart.is_some() {
+                    $bits_per_unit - self.bit_index
+                } else {
+                    0
+                };
+                let mut available_bits = usize::from(bits_in_current_unit) + self.queued_unit_count * $bits_per_unit;
+                while available_bits < usize::from(count) {
+                    let Some(unit) = self.fetch_unit()? else { break };
+                    self.queued_units[self.queued_unit_count] = unit;
+                    self.queued_unit_count += 1;
+                    available_bits += $bits_per_unit;
+                }
+
+                let current_unit = self.unit_picked_apart
+                    .map(|unit| (unit, self.bit_index));
+                let queued_units = self.queued_units[..self.queued_unit_count].iter()
+                    .map(|unit| (*unit, 0));
+                let mut bits = 0;
+                let mut bit_count = 0;
+                'units: for (unit, first_bit_index) in current_unit.into_iter().chain(queued_units) {
+                    for bit_index in first_bit_index..$bits_per_unit {
+                        if bit_count == count {
+                            break 'units;
+                        }
+                        let actual_bit_index = if MSB_TO_LSB {
+                            ($bits_per_unit - 1) - bit_index
+                        } else {
+                            bit_index
+                        };
+                        bits = (bits << 1) | u32::from((unit >> actual_bit_index) & 1);
+                        bit_count += 1;
+                    }
+                }
+                Ok((bits << (count - bit_count), bit_count))
+            }
+
+            /// Consumes `count` bits that have been peeked at using [`peek_bits`](Self::peek_bits).
+            pub fn skip_peeked_bits(&mut self, count: u8) {
+                let mut remaining = count;
+                while remaining > 0 {
+                    if self.bit_index == 0 {
+                        let unit = self.next_unit()
+                            .expect("queued units are taken without reading")
+                            .expect("more bits skipped than peeked at");
+                        self.unit_picked_apart = Some(unit);
+                    }
+                    let step = remaining.min($bits_per_unit - self.bit_index);
+                    self.bit_index += step;
+                    self.total_bits_read += u64::from(step);
+                    remaining -= step;
+                    if self.bit_index == $bits_per_unit {
+                        self.drop_rest_of_unit();
+                    }
+                }
+            }
+
             pub fn read_bit_strict(&mut self) -> Result<bool, io::Error> {
                 match self.read_bit() {
                     Ok(Some(b)) => Ok(b),
@@ -155,12 +252,12 @@ impl<R: Read, const MSB_TO_LSB: bool> BitReader<R, MSB_TO_LSB> {
     pub fn read_u8(&mut self) -> Result<u8, io::Error> {
         // optimization: are we at a byte boundary?
         if self.bit_index == 0 {
-            // yes; just read the next byte from the underlying reader
-            let mut buf = [0u8];
-            self.byte_reader.read_exact(&mut buf)?;
+            // yes; just take the next byte
+            let byte = self.next_unit()?
+                .ok_or(io::ErrorKind::UnexpectedEof)?;
             self.total_bits_read += 8;
-            debug!("reading 8 bits (speedy) gave {:#X}", buf[0]);
-            Ok(buf[0])
+            debug!("reading 8 bits (speedy) gave {:#X}", byte);
+            Ok(byte)
         } else {
             self.read_u8_bitwise()
         }
@@ -202,12 +299,12 @@ impl<R: Read, const MSB_TO_LSB: bool> BitReader16Le<R, MSB_TO_LSB> {
     pub fn read_u16(&mut self) -> Result<u16, io::Error> {
         // optimization: are we at a unit boundary?
         if self.bit_index == 0 {
-            // yes; just read the next unit from the underlying reader
-            let mut buf = [0u8; 2];
-            self.byte_reader.read_exact(&mut buf)?;
+            // yes; just take the next unit
+            let unit = self.next_unit()?
+                .ok_or(io::ErrorKind::UnexpectedEof)?;
             self.total_bits_read += 16;
-            debug!("reading 16 bits (speedy) gave {:#X}", u16::from_le_bytes(buf));
-            Ok(u16::from_le_bytes(buf))
+            debug!("reading 16 bits (speedy) gave {:#X}", unit);
+            Ok(unit)
         } else {
             self.read_u16_bitwise()
         }
@@ -228,18 +325,89 @@ impl<R: Read, const MSB_TO_LSB: bool> BitReader16Le<R, MSB_TO_LSB> {
 }
 
 
+/// A reader that counts the bytes read through it.
+pub(crate) struct CountingReader<R: Read> {
+    inner: R,
+    count: u64,
+}
+impl<R: Read> CountingReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    pub fn count(&self) -> u64 { self.count }
+}
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        self.count += u64::try_from(bytes_read).unwrap();
+        Ok(bytes_read)
+    }
+}
+
+/// A writer that counts the bytes written through it.
+pub struct CountingWriter<W: Write> {
+    inner: W,
+    count: u64,
+}
+impl<W: Write> CountingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    pub fn count(&self) -> u64 { self.count }
+}
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let bytes_written = self.inner.write(buf)?;
+        self.count += u64::try_from(bytes_written).unwrap();
+        Ok(bytes_written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+
+/// A source of single bits, e.g. for decoding Huffman codes.
 pub trait AnyBitReader {
+    /// Reads the next bit; returns `Ok(None)` once the source is exhausted.
     fn read_bit(&mut self) -> Result<Option<bool>, io::Error>;
+
+    /// Returns the next `count` bits (at most 16) without consuming them, the first bit being the
+    /// most significant one, along with the number of bits that are actually left if the source
+    /// is exhausted sooner. Missing bits are returned as 0.
+    fn peek_bits(&mut self, count: u8) -> Result<(u32, u8), io::Error>;
+
+    /// Consumes `count` bits that have been returned by [`peek_bits`](AnyBitReader::peek_bits).
+    fn skip_peeked_bits(&mut self, count: u8);
 }
 impl<R: Read, const MSB_TO_LSB: bool> AnyBitReader for BitReader<R, MSB_TO_LSB> {
     fn read_bit(&mut self) -> Result<Option<bool>, io::Error> {
         BitReader::read_bit(self)
     }
+
+    fn peek_bits(&mut self, count: u8) -> Result<(u32, u8), io::Error> {
+        BitReader::peek_bits(self, count)
+    }
+
+    fn skip_peeked_bits(&mut self, count: u8) {
+        BitReader::skip_peeked_bits(self, count)
+    }
 }
 impl<R: Read, const MSB_TO_LSB: bool> AnyBitReader for BitReader16Le<R, MSB_TO_LSB> {
     fn read_bit(&mut self) -> Result<Option<bool>, io::Error> {
         BitReader16Le::read_bit(self)
     }
+
+    fn peek_bits(&mut self, count: u8) -> Result<(u32, u8), io::Error> {
+        BitReader16Le::peek_bits(self, count)
+    }
+
+    fn skip_peeked_bits(&mut self, count: u8) {
+        BitReader16Le::skip_peeked_bits(self, count)
+    }
 }
 
 
@@ -16,13 +16,13 @@ use crate::io_util::{read_bytes_variable, ByteBufReadable, ReadEndian};
 /// According to ISO9660 and High Sierra (both § 6.1.2), each logical sector contains 2**n bytes but
 /// at least 2048 bytes. In practice, almost all CD-ROM file systems encode their data in logical
 /// sectors 2048 bytes in size.
-const BYTES_PER_LOGICAL_SECTOR: u64 = 2048;
+pub const BYTES_PER_LOGICAL_SECTOR: u64 = 2048;
 
 /// The offset of the area containing the CD's data.
 ///
 /// Logical sectors 0 to 15 contain the System Area in both ISO9660 (§ 6.2.1) and High Sierra
 /// (§ 6.3) CDs. The Data Area therefore starts with logical sector 16.
-const DATA_AREA_OFFSET: u64 = 16 * BYTES_PER_LOGICAL_SECTOR;
+pub const DATA_AREA_OFFSET: u64 = 16 * BYTES_PER_LOGICAL_SECTOR;
 
 /// The byte offset within a logical sector of the High Sierra identifier.
 ///
@@ -78,6 +78,28 @@ pub enum DescriptorType {
 impl Default for DescriptorType {
     fn default() -> Self { Self::BootRecord }
 }
+impl DescriptorType {
+    /// Obtains the type and version of the descriptor stored in the given logical sector.
+    ///
+    /// Returns `None` if the sector does not contain the standard identifier and therefore no
+    /// descriptor.
+    pub fn of_descriptor(sector: &[u8], is_high_sierra: bool) -> Option<(Self, u8)> {
+        let (identifier_offset, identifier_value) = if is_high_sierra {
+            (HIGH_SIERRA_IDENTIFIER_OFFSET, HIGH_SIERRA_IDENTIFIER_VALUE)
+        } else {
+            (ISO9660_IDENTIFIER_OFFSET, ISO9660_IDENTIFIER_VALUE)
+        };
+        // the type precedes the identifier and the version follows it
+        let identifier_offset = usize::try_from(identifier_offset).unwrap();
+        let identifier_end = identifier_offset + identifier_value.len();
+        if sector.get(identifier_offset..identifier_end) != Some(&identifier_value[..]) {
+            return None;
+        }
+        let vd_type = Self::from_base_type(sector[identifier_offset - 1]);
+        let version = *sector.get(identifier_end)?;
+        Some((vd_type, version))
+    }
+}
 
 
 /// The same value, encoded first as little endian and then as big endian.
@@ -625,6 +647,85 @@ impl PartitionDescriptor {
     }
 }
 
+/// An ISO9660 boot record.
+///
+/// See ISO9660 § 8.2.
+///
+/// Can also house a High Sierra boot record (§ 11.2).
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct BootRecord {
+    /// Volume descriptor logical block number.
+    ///
+    /// High Sierra only.
+    pub vd_lbn: Option<EndianPair<u32>>, // 9660: (), HS: [u32; 2]
+
+    /// Volume descriptor type.
+    ///
+    /// For boot records, this value is always 0x00.
+    pub vd_type: DescriptorType, // u8
+
+    /// Volume descriptor standard identifier.
+    ///
+    /// Equals [`ISO9660_IDENTIFIER_VALUE`] on ISO9660 volumes and [`HIGH_SIERRA_IDENTIFIER_VALUE`]
+    /// on High Sierra volumes.
+    pub standard_identifier: DisplayBytes<5>,
+
+    /// Volume descriptor version.
+    ///
+    /// For High Sierra and all hitherto published ISO9660 revisions, this value is always 0x01.
+    pub version: u8,
+
+    /// Identifier of the system that can act upon the boot record.
+    ///
+    /// May only contain a-characters. El Torito bootable CDs store `EL TORITO SPECIFICATION` here.
+    pub boot_system_identifier: DisplayBytes<32>,
+
+    /// Identifier of the boot system use field.
+    ///
+    /// May only contain a-characters.
+    pub boot_identifier: DisplayBytes<32>,
+
+    /// Boot system use field.
+    ///
+    /// 1977 bytes on ISO9660, 1969 bytes on High Sierra volumes (right-padded on read with 0x00).
+    pub boot_system_use: DisplayBytes<1977>, // 9660: [u8; 1977], HS: [u8; 1969]
+}
+impl BootRecord {
+    pub fn read<R: Read>(reader: &mut R, is_high_sierra: bool) -> Result<Self, io::Error> {
+        let mut buf = [0u8; 2048];
+        reader.read_exact(&mut buf)?;
+        let mut pos = 0;
+
+        let vd_lbn = if is_high_sierra {
+            Some(EndianPair::read(&buf, &mut pos))
+        } else {
+            None
+        };
+        let vd_type = DescriptorType::from_base_type(u8::read(&buf, &mut pos));
+        let standard_identifier = ByteBufReadable::read(&buf, &mut pos);
+        let version = u8::read(&buf, &mut pos);
+        let boot_system_identifier = ByteBufReadable::read(&buf, &mut pos);
+        let boot_identifier = ByteBufReadable::read(&buf, &mut pos);
+        let boot_system_use = if is_high_sierra {
+            let hs_use: [u8; 1969] = ByteBufReadable::read(&buf, &mut pos);
+            let mut padded_use = [0u8; 1977];
+            padded_use[..1969].copy_from_slice(&hs_use);
+            padded_use.into()
+        } else {
+            ByteBufReadable::read(&buf, &mut pos)
+        };
+        Ok(Self {
+            vd_lbn,
+            vd_type,
+            standard_identifier,
+            version,
+            boot_system_identifier,
+            boot_identifier,
+            boot_system_use,
+        })
+    }
+}
+
 /// An ISO9660 directory record.
 ///
 /// See ISO9660 § 9.1.
@@ -1156,3 +1257,66 @@ impl PathTableRecord {
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::{BootRecord, DescriptorType, BYTES_PER_LOGICAL_SECTOR, DATA_AREA_OFFSET};
+    use testgen::iso9660;
+
+    fn sector(image: &[u8], number: u64) -> &[u8] {
+        let start = usize::try_from(DATA_AREA_OFFSET + number * BYTES_PER_LOGICAL_SECTOR).unwrap();
+        &image[start..start + usize::try_from(BYTES_PER_LOGICAL_SECTOR).unwrap()]
+    }
+
+    fn volume() -> Vec<u8> {
+        let mut volume = iso9660::Volume::new("DESCRIPTORS");
+        volume.boot_record = Some(iso9660::BootRecord {
+            boot_system_id: "EL TORITO SPECIFICATION".to_owned(),
+            boot_id: "CATALOG".to_owned(),
+        });
+        volume.joliet_volume_id = Some("Descriptors".to_owned());
+        volume.to_bytes()
+    }
+
+    #[test]
+    fn test_descriptor_type() {
+        let image = volume();
+        let types: Vec<(u8, u8)> = (0..4)
+            .map(|n| DescriptorType::of_descriptor(sector(&image, n), false).unwrap())
+            .map(|(vd_type, version)| (vd_type.to_base_type(), version))
+            .collect();
+        assert_eq!(types, [(0x01, 1), (0x00, 1), (0x02, 1), (0xFF, 1)]);
+
+        // the first path table follows the terminator
+        assert!(DescriptorType::of_descriptor(sector(&image, 4), false).is_none());
+
+        // High Sierra keeps its identifier elsewhere
+        assert!(DescriptorType::of_descriptor(sector(&image, 0), true).is_none());
+        let mut high_sierra = [0u8; 16];
+        high_sierra[8] = 0x01;
+        high_sierra[9..14].copy_from_slice(b"CDROM");
+        high_sierra[14] = 1;
+        let (vd_type, version) = DescriptorType::of_descriptor(&high_sierra, true).unwrap();
+        assert_eq!((vd_type.to_base_type(), version), (0x01, 1));
+
+        // the version lies beyond the end of a truncated sector
+        assert!(DescriptorType::of_descriptor(&high_sierra[..14], true).is_none());
+    }
+
+    #[test]
+    fn test_boot_record() {
+        let image = volume();
+        let boot_record = BootRecord::read(&mut sector(&image, 1), false).unwrap();
+        assert!(boot_record.vd_lbn.is_none());
+        assert_eq!(boot_record.vd_type.to_base_type(), 0x00);
+        assert_eq!(boot_record.standard_identifier.as_ref(), b"CD001");
+        assert_eq!(boot_record.version, 1);
+        assert_eq!(boot_record.boot_system_identifier.as_ref(), b"EL TORITO SPECIFICATION         ");
+        assert_eq!(boot_record.boot_identifier.as_ref(), b"CATALOG                         ");
+        assert!(boot_record.boot_system_use.as_ref().iter().all(|b| *b == 0x00));
+
+        // a truncated sector
+        assert!(BootRecord::read(&mut &sector(&image, 1)[..2047], false).is_err());
+    }
+}
@@ -173,17 +173,12 @@ pub(crate) fn decompress<R: Read, W: Write>(compressed_reader: &mut R, decompres
                 (usize::from(offset_top) << 6)
                 | usize::from(offset_bottom);
             debug!("offset is {}, len is {}", match_length, offset);
-            let mut match_offset = (ring_buffer.position() + RING_BUFFER_SIZE - offset) % RING_BUFFER_SIZE;
 
-            // copy match_length bytes starting at match_offset
+            // copy match_length bytes starting offset bytes back
             // both into the output and into the current ring buffer position
-            for _ in 0..match_length {
-                let match_buf = [ring_buffer.as_slice()[match_offset]];
-                debug!("  I got {:02x}", match_buf[0]);
-                decompressed_writer.write_all(&match_buf)?;
-                ring_buffer.extend(match_buf);
-                match_offset = (match_offset + 1) % RING_BUFFER_SIZE;
-            }
+            let matched = ring_buffer.recall(offset, match_length);
+            debug!("  I got {:02x?}", matched);
+            decompressed_writer.write_all(&matched)?;
             current_lookup = &match_run_lengths;
         } else {
             // run of literals
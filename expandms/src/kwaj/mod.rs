@@ -7,7 +7,6 @@
 
 
 mod lzh;
-mod sz;
 
 
 use std::io::{Read, Write};
@@ -15,6 +14,75 @@ use std::io::{Read, Write};
 use crate::error::DecompressionError;
 
 
+const HEADER_FLAG_DECOMPRESSED_LENGTH: u16 = 0x0001;
+const HEADER_FLAG_UNKNOWN: u16 = 0x0002;
+const HEADER_FLAG_EXTRA_DATA: u16 = 0x0004;
+const HEADER_FLAG_FILE_NAME: u16 = 0x0008;
+const HEADER_FLAG_FILE_EXTENSION: u16 = 0x0010;
+
+
+/// The original file name as stored in the header of a KWAJ file.
+///
+/// Both parts are optional; the name is at most 8 and the extension at most 3 bytes long.
+pub(crate) struct StoredFileName<'a> {
+    pub name: Option<&'a [u8]>,
+    pub extension: Option<&'a [u8]>,
+}
+
+
+/// Obtains the bytes of the decompressed length stored in the header of a KWAJ file.
+///
+/// Returns `None` if the header does not store the length or is truncated.
+pub(crate) fn stored_decompressed_length(data: &[u8]) -> Option<&[u8]> {
+    let header_flags = u16::from_le_bytes(data.get(12..14)?.try_into().unwrap());
+    if header_flags & HEADER_FLAG_DECOMPRESSED_LENGTH != 0 {
+        data.get(14..18)
+    } else {
+        None
+    }
+}
+
+
+/// Obtains the original file name stored in the header of a KWAJ file.
+///
+/// Returns `None` if the header is truncated.
+pub(crate) fn stored_file_name(data: &[u8]) -> Option<StoredFileName<'_>> {
+    let header_flags = u16::from_le_bytes(data.get(12..14)?.try_into().unwrap());
+    let mut rest = data.get(14..)?;
+    if header_flags & HEADER_FLAG_DECOMPRESSED_LENGTH != 0 {
+        rest = rest.get(4..)?;
+    }
+    if header_flags & HEADER_FLAG_UNKNOWN != 0 {
+        rest = rest.get(2..)?;
+    }
+    if header_flags & HEADER_FLAG_EXTRA_DATA != 0 {
+        let extra_length = u16::from_le_bytes(rest.get(0..2)?.try_into().unwrap());
+        rest = rest.get(2 + usize::from(extra_length)..)?;
+    }
+
+    // both strings are NUL-terminated
+    let mut take_string = |max_length: usize| -> Option<&[u8]> {
+        let length = rest.iter()
+            .take(max_length + 1)
+            .position(|b| *b == 0x00)?;
+        let string = &rest[..length];
+        rest = &rest[length + 1..];
+        Some(string)
+    };
+    let name = if header_flags & HEADER_FLAG_FILE_NAME != 0 {
+        Some(take_string(8)?)
+    } else {
+        None
+    };
+    let extension = if header_flags & HEADER_FLAG_FILE_EXTENSION != 0 {
+        Some(take_string(3)?)
+    } else {
+        None
+    };
+    Some(StoredFileName { name, extension })
+}
+
+
 pub(crate) fn decompress<R: Read, W: Write>(compressed_reader: &mut R, decompressed_writer: &mut W) -> Result<(), DecompressionError> {
     // assuming we have already read the b"KWAJ\x88\xF0\x27\xD1" magic
 
@@ -61,8 +129,18 @@ pub(crate) fn decompress<R: Read, W: Write>(compressed_reader: &mut R, decompres
             }
         },
         0x02 => {
-            // "SZ" (not "SZDD")
-            crate::kwaj::sz::decompress(compressed_reader, decompressed_writer, false)?;
+            // the LZSS variant of SZDD, with its window; the last group of symbols may be cut short,
+            // so stop after the decompressed length if the header stores it
+            let mut header = vec![0u8; 8];
+            header.push(compression_type_buf[0]);
+            header.extend_from_slice(&data_offset_buf);
+            header.extend_from_slice(&wastebin);
+            let decompressed_length = stored_decompressed_length(&header)
+                .map(|length| u32::from_le_bytes(length.try_into().unwrap()));
+            crate::szdd::decompress_with_parameters(
+                compressed_reader, decompressed_writer,
+                decompressed_length, crate::szdd::Variant::Szdd.window_parameters(),
+            )?;
         },
         0x03 => {
             // LZH (Lempel-Ziv + Huffman) by Jeff Johnson
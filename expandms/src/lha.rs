@@ -0,0 +1,628 @@
+//! Reading LHA (LHarc) archives.
+//!
+//! An LHA archive is a sequence of file headers, each directly followed by the file's compressed
+//! data, and ends with a zero byte (or simply with the end of the file). Headers of levels 0 and 1
+//! store the file name in the header itself and are protected by a simple checksum; headers of
+//! levels 1 and 2 can be followed by extended headers, which may carry the directory and, for level
+//! 2, the file name.
+//!
+//! The compression method is given as a five-character identifier such as `-lh5-`. Files stored
+//! without compression (`-lh0-`, `-lz4-`) and those compressed using the static Huffman methods
+//! `-lh4-` to `-lh7-` can be extracted; the latter differ only in their window sizes. The same
+//! coding is also used by ARJ methods 1 to 3 (see [`crate::arj`]).
+
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use tracing::debug;
+
+use crate::DecompressionError;
+use crate::crc::{CrcWriter, CRC_16_ARC};
+use crate::huff::HuffmanTree;
+use crate::io_util::BitReader;
+use crate::ring_buffer::RingBuffer;
+
+
+/// The number of symbols of the code for literals and match lengths: 256 literals and the match
+/// lengths 3 to 256.
+const LITERAL_SYMBOLS: usize = 510;
+
+/// The number of bits storing the number of code lengths of the literal code.
+const LITERAL_COUNT_BITS: u8 = 9;
+
+/// The number of symbols of the code with which the code lengths of the literal code are encoded.
+const CODE_LENGTH_SYMBOLS: usize = 19;
+
+/// The number of bits storing the number of code lengths of the code length code.
+const CODE_LENGTH_COUNT_BITS: u8 = 5;
+
+/// The index after which the code lengths of the code length code contain a count of zero lengths.
+const CODE_LENGTH_ZEROES_INDEX: usize = 3;
+
+/// The length of the shortest match.
+const MIN_MATCH_LENGTH: usize = 3;
+
+/// The longest code accepted in any of the codes.
+const MAX_CODE_LENGTH: usize = 16;
+
+/// The value with which the window is initially filled.
+const WINDOW_FILL: u8 = b' ';
+
+
+/// An error encountered while decompressing LZH data.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Error {
+    /// A block lists more code lengths than its code has symbols.
+    TooManyCodeLengths { count: usize, symbol_count: usize },
+
+    /// A code length exceeds the maximum.
+    CodeTooLong,
+
+    /// A code with a single symbol names a symbol outside of the code.
+    InvalidSymbol { symbol: u16, symbol_count: usize },
+
+    /// A match reaches back further than the window.
+    DistanceTooLarge { distance: usize, window_size: usize },
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyCodeLengths { count, symbol_count }
+                => write!(f, "{} code lengths given for a code with {} symbols", count, symbol_count),
+            Self::CodeTooLong
+                => write!(f, "code length exceeds the maximum of {}", MAX_CODE_LENGTH),
+            Self::InvalidSymbol { symbol, symbol_count }
+                => write!(f, "symbol {} is outside of a code with {} symbols", symbol, symbol_count),
+            Self::DistanceTooLarge { distance, window_size }
+                => write!(f, "match distance {} exceeds the window size {}", distance, window_size),
+        }
+    }
+}
+impl std::error::Error for Error {
+}
+
+
+/// The parameters distinguishing the variants of the static Huffman coding.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct LzhParameters {
+    /// The size of the window, in bytes.
+    pub window_size: usize,
+
+    /// The number of symbols of the code for match distances, each giving the bit length of a
+    /// distance.
+    pub distance_symbols: usize,
+
+    /// The number of bits storing the number of code lengths of the distance code.
+    pub distance_count_bits: u8,
+}
+impl LzhParameters {
+    pub const LH4: Self = Self { window_size: 4096, distance_symbols: 14, distance_count_bits: 4 };
+    pub const LH5: Self = Self { window_size: 8192, distance_symbols: 14, distance_count_bits: 4 };
+    pub const LH6: Self = Self { window_size: 32768, distance_symbols: 16, distance_count_bits: 5 };
+    pub const LH7: Self = Self { window_size: 65536, distance_symbols: 17, distance_count_bits: 5 };
+
+    /// The parameters of ARJ methods 1 to 3, whose window is 26624 bytes in size.
+    pub const ARJ: Self = Self { window_size: 26624, distance_symbols: 17, distance_count_bits: 5 };
+
+    /// The parameters of the given LHA compression method, if it uses the static Huffman coding.
+    pub fn from_method(method: &[u8; 5]) -> Option<Self> {
+        match method {
+            b"-lh4-" => Some(Self::LH4),
+            b"-lh5-" => Some(Self::LH5),
+            b"-lh6-" => Some(Self::LH6),
+            b"-lh7-" => Some(Self::LH7),
+            _ => None,
+        }
+    }
+}
+
+
+/// The header of a file in an LHA archive.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct LhaEntry {
+    /// The offset of the header.
+    pub header_offset: usize,
+
+    pub header_level: u8,
+
+    /// The compression method, e.g. `-lh5-`.
+    pub method: [u8; 5],
+
+    /// The path of the file, with directories separated by `/`.
+    pub path: Vec<u8>,
+
+    pub compressed_size: u32,
+    pub original_size: u32,
+    pub crc16: u16,
+
+    /// The offset of the compressed data.
+    pub data_offset: usize,
+}
+impl LhaEntry {
+    /// Reads the header at the given offset in the data.
+    ///
+    /// Returns `None` if the archive ends at the offset.
+    pub fn read(data: &[u8], offset: usize) -> Result<Option<Self>, io::Error> {
+        let Some(header_start) = data.get(offset..) else {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        };
+        if header_start.first().is_none_or(|b| *b == 0x00) {
+            return Ok(None);
+        }
+        let Some(fixed) = header_start.get(0..22) else {
+            debug!("LHA header at {:#X} is truncated", offset);
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        };
+
+        let method: [u8; 5] = fixed[2..7].try_into().unwrap();
+        let mut compressed_size = u32::from_le_bytes(fixed[7..11].try_into().unwrap());
+        let original_size = u32::from_le_bytes(fixed[11..15].try_into().unwrap());
+        let header_level = fixed[20];
+
+        let (mut path, crc16, mut extended_offset, header_end) = match header_level {
+            0|1 => {
+                if !checksum_matches(header_start) {
+                    debug!("checksum of LHA header at {:#X} does not match", offset);
+                    return Err(io::ErrorKind::InvalidData.into());
+                }
+                let base_size = usize::from(fixed[0]) + 2;
+                let name_length = usize::from(fixed[21]);
+                let Some(base) = header_start.get(..base_size).filter(|b| b.len() >= 24 + name_length) else {
+                    debug!("LHA header at {:#X} is too short for its file name", offset);
+                    return Err(io::ErrorKind::InvalidData.into());
+                };
+                let name = base[22..22+name_length].to_vec();
+                let crc16 = u16::from_le_bytes(base[22+name_length..24+name_length].try_into().unwrap());
+                if header_level == 0 {
+                    (name, crc16, None, offset + base_size)
+                } else {
+                    // the size of the first extended header closes the base header
+                    (name, crc16, Some(offset + base_size - 2), offset + base_size)
+                }
+            },
+            2 => {
+                let total_size = usize::from(u16::from_le_bytes(fixed[0..2].try_into().unwrap()));
+                if total_size < 26 || header_start.len() < 26 {
+                    debug!("level-2 LHA header at {:#X} is too short", offset);
+                    return Err(io::ErrorKind::InvalidData.into());
+                }
+                let crc16 = u16::from_le_bytes(header_start[21..23].try_into().unwrap());
+                (Vec::new(), crc16, Some(offset + 24), offset + total_size)
+            },
+            other => {
+                debug!("unsupported LHA header level {} at {:#X}", other, offset);
+                return Err(io::ErrorKind::InvalidData.into());
+            },
+        };
+
+        // walk the extended headers
+        let mut directory = Vec::new();
+        let mut extended_size_total: usize = 0;
+        let mut data_offset = header_end;
+        while let Some(size_offset) = extended_offset {
+            let Some(size_bytes) = data.get(size_offset..size_offset+2) else {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            };
+            let size = usize::from(u16::from_le_bytes(size_bytes.try_into().unwrap()));
+            if size == 0 {
+                break;
+            }
+            if size < 3 {
+                debug!("extended LHA header at {:#X} is too short", size_offset + 2);
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+            let start = if header_level == 1 { data_offset } else { size_offset + 2 };
+            let Some(extended) = data.get(start..start+size) else {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            };
+            let content = &extended[1..size-2];
+            match extended[0] {
+                0x01 => path = content.to_vec(),
+                0x02 => directory = content.to_vec(),
+                _ => {},
+            }
+            extended_offset = Some(start + size - 2);
+            if header_level == 1 {
+                // level-1 extended headers are counted as part of the compressed data
+                extended_size_total += size;
+                data_offset += size;
+            }
+        }
+        if header_level == 1 {
+            let Some(actual_size) = usize::try_from(compressed_size).unwrap().checked_sub(extended_size_total) else {
+                debug!("extended headers of LHA entry at {:#X} exceed its compressed size", offset);
+                return Err(io::ErrorKind::InvalidData.into());
+            };
+            compressed_size = actual_size.try_into().unwrap();
+        }
+
+        if !directory.is_empty() {
+            if directory.last() != Some(&0xFF) {
+                directory.push(0xFF);
+            }
+            directory.extend_from_slice(&path);
+            path = directory;
+        }
+        for b in &mut path {
+            if *b == 0xFF || *b == b'\\' {
+                *b = b'/';
+            }
+        }
+
+        Ok(Some(Self {
+            header_offset: offset,
+            header_level,
+            method,
+            path,
+            compressed_size,
+            original_size,
+            crc16,
+            data_offset,
+        }))
+    }
+
+    /// Whether the entry describes a directory instead of a file.
+    pub fn is_directory(&self) -> bool {
+        &self.method == b"-lhd-"
+    }
+
+    /// The offset of the header of the next entry.
+    pub fn next_offset(&self) -> usize {
+        self.data_offset + usize::try_from(self.compressed_size).unwrap()
+    }
+
+    /// The compressed data of this entry.
+    pub fn compressed_data<'d>(&self, data: &'d [u8]) -> Result<&'d [u8], io::Error> {
+        data.get(self.data_offset..self.next_offset())
+            .ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+    }
+}
+
+
+/// Whether the data at the given offset looks like the header of a file in an LHA archive.
+///
+/// The method identifier is checked along with, for headers of levels 0 and 1, the checksum and, for
+/// headers of level 2, whether the chain of extended headers fills the header exactly.
+pub fn looks_like_header(data: &[u8], offset: usize) -> bool {
+    let Some(header_start) = data.get(offset..) else { return false };
+    let Some(fixed) = header_start.get(0..22) else { return false };
+    let method_matches = fixed[2] == b'-'
+        && fixed[3] == b'l'
+        && (fixed[4] == b'h' || fixed[4] == b'z')
+        && fixed[6] == b'-';
+    if !method_matches {
+        return false;
+    }
+    match fixed[20] {
+        0|1 => fixed[0] >= 22 && checksum_matches(header_start),
+        2 => level_2_extended_headers_fit(header_start),
+        _ => false,
+    }
+}
+
+/// Checks whether the extended headers of a header of level 2 end where its total size says the
+/// header ends. A single byte of padding is allowed.
+fn level_2_extended_headers_fit(header_start: &[u8]) -> bool {
+    let total_size = usize::from(u16::from_le_bytes(header_start[0..2].try_into().unwrap()));
+    if total_size < 26 || total_size > header_start.len() {
+        return false;
+    }
+    let mut size_offset = 24;
+    loop {
+        let Some(size_bytes) = header_start.get(size_offset..size_offset+2) else { return false };
+        let size = usize::from(u16::from_le_bytes(size_bytes.try_into().unwrap()));
+        if size == 0 {
+            break;
+        }
+        if size < 3 {
+            return false;
+        }
+        size_offset += size;
+        if size_offset + 2 > total_size {
+            return false;
+        }
+    }
+    let end = size_offset + 2;
+    end == total_size || end + 1 == total_size
+}
+
+/// Checks the checksum of a header of level 0 or 1, which is the sum of the bytes after the size and
+/// the checksum.
+fn checksum_matches(header_start: &[u8]) -> bool {
+    let base_size = usize::from(header_start[0]);
+    let Some(checked) = header_start.get(2..2+base_size) else { return false };
+    let sum = checked.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+    sum == header_start[1]
+}
+
+
+/// Reads the headers of all files in the LHA archive starting at the given offset.
+pub fn read_entries(data: &[u8], offset: usize) -> Result<Vec<LhaEntry>, io::Error> {
+    let mut entries = Vec::new();
+    let mut offset = offset;
+    while offset < data.len() {
+        let Some(entry) = LhaEntry::read(data, offset)? else { break };
+        offset = entry.next_offset();
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+
+/// Decompresses the data of an entry of an LHA archive and checks its CRC.
+pub fn decompress<W: Write>(data: &[u8], entry: &LhaEntry, writer: &mut W) -> Result<(), DecompressionError> {
+    let mut compressed = entry.compressed_data(data)?;
+    let mut crc_writer = CrcWriter::new(writer, CRC_16_ARC.digest());
+    match &entry.method {
+        b"-lh0-"|b"-lz4-" => {
+            crc_writer.write_all(compressed)?;
+        },
+        other => {
+            let Some(parameters) = LzhParameters::from_method(other) else {
+                debug!("unsupported LHA compression method {:?}", other.escape_ascii().to_string());
+                return Err(DecompressionError::UnknownCompressionMethod);
+            };
+            decompress_lzh(&mut compressed, &mut crc_writer, entry.original_size.into(), parameters)?;
+        },
+    }
+
+    let calculated = crc_writer.digest().finalize();
+    if calculated != entry.crc16 {
+        debug!("CRC of LHA entry at {:#X} is {:#06X}, expected {:#06X}", entry.header_offset, calculated, entry.crc16);
+        return Err(DecompressionError::ChecksumMismatch { expected: entry.crc16.into(), calculated: calculated.into() });
+    }
+    Ok(())
+}
+
+
+/// A prefix code as stored in the blocks of the static Huffman coding.
+enum Code {
+    /// The code consists of a single symbol, which takes up no bits.
+    Single(u16),
+
+    Tree(HuffmanTree<u16>),
+}
+impl Code {
+    fn decode<R: Read>(&self, reader: &mut BitReader<&mut R, true>) -> Result<u16, DecompressionError> {
+        match self {
+            Self::Single(symbol) => Ok(*symbol),
+            Self::Tree(tree) => tree.decode_one_from_bit_reader(reader)?
+                .copied()
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+        }
+    }
+}
+
+
+fn read_bits<R: Read>(reader: &mut BitReader<&mut R, true>, count: u8) -> Result<u16, io::Error> {
+    let mut value = 0;
+    for _ in 0..count {
+        value = (value << 1) | u16::from(reader.read_bit_strict()?);
+    }
+    Ok(value)
+}
+
+/// Reads the code lengths of the code length code or the distance code and builds the code.
+///
+/// If `zeroes_index` is given, the code length at that index is preceded by a 2-bit count of zero
+/// code lengths.
+fn read_short_code<R: Read>(reader: &mut BitReader<&mut R, true>, symbol_count: usize, count_bits: u8, zeroes_index: Option<usize>) -> Result<Code, DecompressionError> {
+    let count = usize::from(read_bits(reader, count_bits)?);
+    if count == 0 {
+        let symbol = read_bits(reader, count_bits)?;
+        if usize::from(symbol) >= symbol_count {
+            return Err(Error::InvalidSymbol { symbol, symbol_count }.into());
+        }
+        return Ok(Code::Single(symbol));
+    }
+    if count > symbol_count {
+        return Err(Error::TooManyCodeLengths { count, symbol_count }.into());
+    }
+
+    let mut lengths = vec![0usize; symbol_count];
+    let mut index = 0;
+    while index < count {
+        // 0 to 6 are stored in three bits; 7 and above as 7 followed by a unary count of 1 bits
+        let mut length = usize::from(read_bits(reader, 3)?);
+        if length == 7 {
+            while reader.read_bit_strict()? {
+                length += 1;
+                if length > MAX_CODE_LENGTH {
+                    return Err(Error::CodeTooLong.into());
+                }
+            }
+        }
+        lengths[index] = length;
+        index += 1;
+
+        if Some(index) == zeroes_index {
+            index += usize::from(read_bits(reader, 2)?);
+            if index > symbol_count {
+                return Err(Error::TooManyCodeLengths { count: index, symbol_count }.into());
+            }
+        }
+    }
+    Ok(Code::Tree(HuffmanTree::new_canonical(&lengths)?))
+}
+
+/// Reads the code lengths of the literal code, which are encoded using the code length code, and
+/// builds the code.
+fn read_literal_code<R: Read>(reader: &mut BitReader<&mut R, true>, code_length_code: &Code) -> Result<Code, DecompressionError> {
+    let count = usize::from(read_bits(reader, LITERAL_COUNT_BITS)?);
+    if count == 0 {
+        let symbol = read_bits(reader, LITERAL_COUNT_BITS)?;
+        if usize::from(symbol) >= LITERAL_SYMBOLS {
+            return Err(Error::InvalidSymbol { symbol, symbol_count: LITERAL_SYMBOLS }.into());
+        }
+        return Ok(Code::Single(symbol));
+    }
+    if count > LITERAL_SYMBOLS {
+        return Err(Error::TooManyCodeLengths { count, symbol_count: LITERAL_SYMBOLS }.into());
+    }
+
+    let mut lengths = vec![0usize; LITERAL_SYMBOLS];
+    let mut index = 0;
+    while index < count {
+        let code_length_symbol = code_length_code.decode(reader)?;
+        index += match code_length_symbol {
+            0 => 1,
+            1 => usize::from(read_bits(reader, 4)?) + 3,
+            2 => usize::from(read_bits(reader, LITERAL_COUNT_BITS)?) + 20,
+            length_plus_2 => {
+                lengths[index] = usize::from(length_plus_2 - 2);
+                1
+            },
+        };
+        if index > LITERAL_SYMBOLS {
+            return Err(Error::TooManyCodeLengths { count: index, symbol_count: LITERAL_SYMBOLS }.into());
+        }
+    }
+    Ok(Code::Tree(HuffmanTree::new_canonical(&lengths)?))
+}
+
+
+/// Decompresses data compressed using the static Huffman coding of `-lh4-` to `-lh7-` and ARJ
+/// methods 1 to 3.
+///
+/// The data does not mark its end, so decompression stops once `original_size` bytes have been
+/// output.
+pub fn decompress_lzh<R: Read, W: Write>(compressed_reader: &mut R, decompressed_writer: &mut W, original_size: u64, parameters: LzhParameters) -> Result<(), DecompressionError> {
+    const FLUSH_THRESHOLD: usize = 0x10000;
+
+    let mut reader = BitReader::new(compressed_reader);
+    let mut window = RingBuffer::new(WINDOW_FILL, parameters.window_size);
+    let mut output = Vec::new();
+    let mut output_size: u64 = 0;
+
+    let mut block_remaining: u16 = 0;
+    let mut literal_code = Code::Single(0);
+    let mut distance_code = Code::Single(0);
+    while output_size < original_size {
+        if block_remaining == 0 {
+            block_remaining = read_bits(&mut reader, 16)?;
+            let code_length_code = read_short_code(&mut reader, CODE_LENGTH_SYMBOLS, CODE_LENGTH_COUNT_BITS, Some(CODE_LENGTH_ZEROES_INDEX))?;
+            literal_code = read_literal_code(&mut reader, &code_length_code)?;
+            distance_code = read_short_code(&mut reader, parameters.distance_symbols, parameters.distance_count_bits, None)?;
+            debug!("LZH block of {} symbols", block_remaining);
+            continue;
+        }
+        block_remaining -= 1;
+
+        let symbol = literal_code.decode(&mut reader)?;
+        if let Ok(literal) = u8::try_from(symbol) {
+            window.push(literal);
+            output.push(literal);
+            output_size += 1;
+        } else {
+            let length = usize::from(symbol) - 256 + MIN_MATCH_LENGTH;
+
+            // the distance code gives the bit length of the distance minus one
+            let distance_bits = distance_code.decode(&mut reader)?;
+            let distance = if distance_bits == 0 {
+                1
+            } else {
+                (1usize << (distance_bits - 1)) + usize::from(read_bits(&mut reader, u8::try_from(distance_bits - 1).unwrap())?) + 1
+            };
+            if distance > window.len() {
+                return Err(Error::DistanceTooLarge { distance, window_size: window.len() }.into());
+            }
+
+            let remaining = usize::try_from(original_size - output_size).unwrap_or(usize::MAX);
+            let matched = window.recall(distance, length.min(remaining));
+            output_size += u64::try_from(matched.len()).unwrap();
+            output.extend_from_slice(&matched);
+        }
+
+        if output.len() >= FLUSH_THRESHOLD {
+            decompressed_writer.write_all(&output)?;
+            output.clear();
+        }
+    }
+    decompressed_writer.write_all(&output)?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress, decompress_lzh, looks_like_header, read_entries, LzhParameters};
+    use crate::DecompressionError;
+    use crate::crc::CRC_16_ARC;
+    use tracing_test::traced_test;
+
+    #[test]
+    #[traced_test]
+    fn test_decompress_lh5() {
+        // a single block with Huffman-coded literals, lengths and distances
+        let compressed = [
+            0x00, 0x2E, 0x43, 0x56, 0xB0, 0xEA, 0xBC, 0x0C, 0xFC, 0x68, 0x59, 0x4A, 0xA4, 0xCA, 0x8D, 0xBB,
+            0x64, 0x06, 0x6D, 0xDC, 0x40, 0xF1, 0xC8, 0x0F, 0x23, 0xD0, 0x00, 0x6D, 0x24, 0xF9, 0x10, 0x42,
+            0x9C, 0xD3, 0x39, 0x80, 0x49, 0x6F, 0xA8, 0xE0, 0x5B, 0x63, 0xE8, 0x1A, 0x99, 0x37, 0x9F, 0x70,
+            0xCC, 0xA3, 0xEB, 0x18, 0x1F, 0x91, 0xEA, 0x01, 0x61, 0xCA, 0xA0, 0x4B, 0x99, 0x30, 0x7E, 0x54,
+        ];
+        let plaintext = b"She sells sea shells by the sea shore; the shells she sells are sea shells, I'm sure. ".repeat(3);
+
+        let mut output = Vec::new();
+        decompress_lzh(&mut &compressed[..], &mut output, plaintext.len().try_into().unwrap(), LzhParameters::LH5)
+            .expect("failed to decompress");
+        assert_eq!(output, plaintext);
+    }
+
+    #[test]
+    #[traced_test]
+    fn test_read_level_1_entries() {
+        let readme: &[u8] = b"Read me!";
+        let library: &[u8] = b"MZ and more";
+        let archive = testgen::sfx::lha_level_1(&[("README.TXT", readme), ("SYSTEM/DRIVERS/SYNTH.SYS", library)]);
+
+        assert!(looks_like_header(&archive, 0));
+        let entries = read_entries(&archive, 0).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].header_level, 1);
+        assert_eq!(entries[0].path, b"README.TXT");
+        assert_eq!(entries[1].path, b"SYSTEM/DRIVERS/SYNTH.SYS");
+
+        // the directory extended header is not part of the data
+        assert_eq!(usize::try_from(entries[1].compressed_size).unwrap(), library.len());
+        let mut output = Vec::new();
+        decompress(&archive, &entries[1], &mut output).unwrap();
+        assert_eq!(output, library);
+    }
+
+    #[test]
+    fn test_level_2_header_check() {
+        let mut header = vec![26, 0];
+        header.extend_from_slice(b"-lh0-");
+        header.extend_from_slice(&[0; 13]);
+        header.push(2); // header level
+        header.extend_from_slice(&[0; 3]); // CRC and operating system
+        header.extend_from_slice(&0u16.to_le_bytes()); // no extended headers
+        assert!(looks_like_header(&header, 0));
+
+        // an extended header that runs past the end of the header
+        let mut overrunning = header.clone();
+        overrunning[24] = 5;
+        overrunning.extend_from_slice(&[0; 8]);
+        assert!(!looks_like_header(&overrunning, 0));
+
+        // a total size beyond the end of the data
+        let mut truncated = header.clone();
+        truncated[0] = 0xFF;
+        assert!(!looks_like_header(&truncated, 0));
+    }
+
+    #[test]
+    fn test_checksum_mismatch() {
+        let mut archive = testgen::sfx::lha(&[("README.TXT", b"Read me!")]);
+        let entries = read_entries(&archive, 0).unwrap();
+        let data_offset = archive.windows(8).position(|w| w == b"Read me!").unwrap();
+        archive[data_offset] = b'F';
+
+        let mut output = Vec::new();
+        let error = decompress(&archive, &entries[0], &mut output).unwrap_err();
+        let DecompressionError::ChecksumMismatch { expected, calculated } = error else { panic!("unexpected error {:?}", error) };
+        assert_eq!(expected, u32::from(entries[0].crc16));
+        assert_eq!(calculated, u32::from(CRC_16_ARC.checksum(b"Fead me!")));
+    }
+}
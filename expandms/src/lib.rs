@@ -1,33 +1,211 @@
+pub mod arj;
+pub mod boot_sector;
 pub mod cab;
+pub mod crc;
 mod error;
 pub mod fat;
-mod huff;
+pub mod huff;
 pub mod inflate;
 mod io_util;
 pub mod iso9660;
 mod kwaj;
+pub mod lha;
 pub mod ring_buffer;
-mod szdd;
+pub mod szdd;
+pub mod xpress;
+pub mod zip;
 
 
 use std::io::{Read, Write};
 
 pub use crate::error::DecompressionError;
+pub use crate::io_util::CountingWriter;
+use crate::io_util::CountingReader;
 
 
+/// The magic value with which KWAJ files start.
+pub const KWAJ_MAGIC: &[u8; 8] = b"KWAJ\x88\xF0\x27\xD1";
+
+
+/// Whether the data starts like a KWAJ, SZDD or SZ file, i.e. one that can be passed to
+/// [`decompress`].
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.starts_with(KWAJ_MAGIC) || szdd::Variant::from_magic(data).is_some()
+}
+
+
+/// Determines the original name of a KWAJ, SZDD or SZ file from its own name and the beginning of
+/// its data.
+///
+/// KWAJ files can store the original name and extension in their header; if only the extension
+/// is stored, it replaces the extension of `compressed_name`. SZDD files compressed using
+/// `COMPRESS.EXE -r` store the last character of the original name, which was replaced by an
+/// underscore. The restored parts are lowercased if `compressed_name` contains lowercase letters.
+///
+/// Returns `None` if the file does not store enough information (SZ files never do) or if the
+/// stored name contains characters that are not allowed in file names.
+pub fn original_file_name(compressed_name: &str, data: &[u8]) -> Option<String> {
+    let lowercase = compressed_name.chars().any(|c| c.is_ascii_lowercase());
+    let restore = |bytes: &[u8]| -> Option<String> {
+        let valid = !bytes.is_empty() && bytes.iter()
+            .all(|b| b.is_ascii_graphic() && !b"/\\:*?\"<>|".contains(b));
+        if !valid {
+            return None;
+        }
+        let restored = String::from_utf8(bytes.to_vec()).unwrap();
+        if lowercase {
+            Some(restored.to_ascii_lowercase())
+        } else {
+            Some(restored)
+        }
+    };
+
+    if data.starts_with(KWAJ_MAGIC) {
+        let crate::kwaj::StoredFileName { name, extension } = crate::kwaj::stored_file_name(data)?;
+        let name = match name {
+            Some(name) => restore(name)?,
+            None if extension.is_some() => {
+                // keep our own name but take the stored extension
+                compressed_name.rsplit_once('.')
+                    .map(|(stem, _extension)| stem)
+                    .unwrap_or(compressed_name)
+                    .to_owned()
+            },
+            None => return None,
+        };
+        match extension {
+            Some(extension) if !extension.is_empty() => Some(format!("{}.{}", name, restore(extension)?)),
+            _ => Some(name),
+        }
+    } else if data.starts_with(szdd::Variant::Szdd.magic()) {
+        let stem = compressed_name.strip_suffix('_')?;
+        let missing_char = restore(data.get(9..10)?)?;
+        Some(format!("{}{}", stem, missing_char))
+    } else {
+        None
+    }
+}
+
+
+/// Reads the size of the decompressed data of a KWAJ, SZDD or SZ file from its header.
+///
+/// Returns `None` if the header is truncated or does not store the size, which is optional in KWAJ
+/// files.
+pub fn decompressed_size(data: &[u8]) -> Option<u32> {
+    let size_bytes = if data.starts_with(KWAJ_MAGIC) {
+        crate::kwaj::stored_decompressed_length(data)?
+    } else {
+        match szdd::Variant::from_magic(data)? {
+            szdd::Variant::Szdd => data.get(10..14)?,
+            szdd::Variant::QBasic => data.get(8..12)?,
+        }
+    };
+    Some(u32::from_le_bytes(size_bytes.try_into().unwrap()))
+}
+
+
+/// Decompresses a KWAJ, SZDD or SZ file.
+///
+/// Errors are wrapped in [`DecompressionError::InContext`], noting the format and how far
+/// decompression had progressed.
 pub fn decompress<R: Read, W: Write>(
     compressed_reader: &mut R,
     decompressed_writer: &mut W,
+) -> Result<(), DecompressionError> {
+    let mut counting_reader = CountingReader::new(compressed_reader);
+    let mut counting_writer = CountingWriter::new(decompressed_writer);
+    let mut format = None;
+    let result = decompress_counted(&mut counting_reader, &mut counting_writer, &mut format);
+    result.map_err(|e| e.with_context(
+        counting_reader.count(),
+        counting_writer.count(),
+        format.map(|f| format!("decompressing {} data", f)),
+    ))
+}
+
+fn decompress_counted<R: Read, W: Write>(
+    compressed_reader: &mut R,
+    decompressed_writer: &mut W,
+    format: &mut Option<&'static str>,
 ) -> Result<(), DecompressionError> {
     let mut magic_buf = [0u8; 8];
     compressed_reader.read_exact(&mut magic_buf)?;
-    if &magic_buf == b"KWAJ\x88\xF0\x27\xD1" {
+    if &magic_buf == KWAJ_MAGIC {
+        *format = Some("KWAJ");
         crate::kwaj::decompress(compressed_reader, decompressed_writer)
-    } else if &magic_buf == b"SZDD\x88\xF0\x27\x33" {
-        crate::szdd::decompress_szdd(compressed_reader, decompressed_writer)
-    } else if &magic_buf == b"SZ \x88\xF0\x27\x33\xD1" {
-        crate::szdd::decompress_sz(compressed_reader, decompressed_writer)
+    } else if let Some(variant) = szdd::Variant::from_magic(&magic_buf) {
+        match variant {
+            szdd::Variant::Szdd => {
+                *format = Some("SZDD");
+                crate::szdd::decompress_szdd(compressed_reader, decompressed_writer)
+            },
+            szdd::Variant::QBasic => {
+                *format = Some("SZ");
+                crate::szdd::decompress_sz(compressed_reader, decompressed_writer)
+            },
+        }
     } else {
         Err(DecompressionError::UnknownCompressionMethod)
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress, original_file_name, DecompressionError};
+    use std::io::Cursor;
+    use testgen::kwaj::{self, Method};
+    use testgen::szdd;
+
+    #[test]
+    fn test_decompress_synthesized() {
+        // repeated, so that method 2 of KWAJ encodes matches
+        let plaintext = b"The quick brown fox jumps over the lazy dog. The quick brown fox jumps over the lazy dog.";
+
+        let compressed_files = [
+            kwaj::compress(plaintext, Method::Stored, None),
+            kwaj::compress(plaintext, Method::Xored, Some("FOX.TXT")),
+            kwaj::compress(plaintext, Method::Lzss, Some("FOX.TXT")),
+            szdd::compress(plaintext, b'T'),
+            szdd::compress_sz(plaintext),
+        ];
+        for compressed in compressed_files {
+            let mut output = Vec::new();
+            decompress(&mut Cursor::new(&compressed), &mut output)
+                .expect("failed to decompress");
+            assert_eq!(&output, plaintext);
+        }
+    }
+
+    #[test]
+    fn test_error_context() {
+        let mut compressed = szdd::compress(b"The quick brown fox jumps over the lazy dog.", b'T');
+        compressed[8] = b'B'; // unknown compression method
+        let error = decompress(&mut Cursor::new(&compressed), &mut Vec::new())
+            .expect_err("decompressed unknown compression method");
+        assert!(matches!(error.innermost(), DecompressionError::UnknownCompressionMethod));
+        assert_eq!(error.offset_in_input(), Some(14));
+        assert_eq!(
+            error.to_string(),
+            "while decompressing SZDD data: unknown compression method (at input offset 0xE, after 0 bytes of output)",
+        );
+    }
+
+    #[test]
+    fn test_original_file_name() {
+        let plaintext = b"The quick brown fox jumps over the lazy dog.";
+        let named_kwaj = kwaj::compress(plaintext, Method::Stored, Some("FOX.TXT"));
+        assert_eq!(original_file_name("FOX.TX_", &named_kwaj).as_deref(), Some("FOX.TXT"));
+        assert_eq!(original_file_name("fox.tx_", &named_kwaj).as_deref(), Some("fox.txt"));
+        let extensionless_kwaj = kwaj::compress(plaintext, Method::Stored, Some("FOX"));
+        assert_eq!(original_file_name("FOX.$", &extensionless_kwaj).as_deref(), Some("FOX"));
+        let anonymous_kwaj = kwaj::compress(plaintext, Method::Stored, None);
+        assert_eq!(original_file_name("FOX.TX_", &anonymous_kwaj), None);
+
+        let szdd = szdd::compress(plaintext, b'T');
+        assert_eq!(original_file_name("FOX.TX_", &szdd).as_deref(), Some("FOX.TXT"));
+        assert_eq!(original_file_name("FOX.TXT", &szdd), None);
+        assert_eq!(original_file_name("FOX.TX_", &szdd::compress(plaintext, b'/')), None);
+        assert_eq!(original_file_name("FOX.TX_", &szdd::compress_sz(plaintext)), None);
+    }
+}
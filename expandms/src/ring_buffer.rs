@@ -1,25 +1,46 @@
+//! The history window of LZ77-style decompressors.
+//!
+//! Such decompressors output either literal values or matches, which repeat values that have been
+//! output earlier. The window keeps the most recently output values around for matches to refer to;
+//! it is initially filled with a format-specific value, which matches may also refer to.
+
+
+/// A fixed-size window over the most recently output values.
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct RingBuffer<T: Copy> {
     buffer: Box<[T]>,
     position: usize,
+    wraparounds: u64,
 }
 impl<T: Copy> RingBuffer<T> {
+    /// Creates a window of `size` values, all set to `initial_value`, with the write position at
+    /// the beginning.
     pub fn new(initial_value: T, size: usize) -> Self {
+        assert!(size > 0, "ring buffer must not be empty");
         let buffer = vec![initial_value; size].into_boxed_slice();
         Self {
             buffer,
             position: 0,
+            wraparounds: 0,
         }
     }
 
     pub fn as_slice(&self) -> &[T] { &self.buffer }
+
+    /// The position at which the next value will be stored.
     pub fn position(&self) -> usize { self.position }
+
     pub fn len(&self) -> usize { self.buffer.len() }
 
+    /// How often the write position has wrapped around from the end of the window to its
+    /// beginning.
+    pub fn wraparounds(&self) -> u64 { self.wraparounds }
+
     pub fn set_position(&mut self, new_position: usize) {
         if new_position >= self.buffer.len() {
             panic!("new position {} >= size {}", new_position, self.buffer.len());
         }
+        self.position = new_position;
     }
 
     pub fn set_at(&mut self, position: usize, value: T) {
@@ -29,9 +50,14 @@ impl<T: Copy> RingBuffer<T> {
         self.buffer[position] = value;
     }
 
+    /// Stores a value at the write position and advances it.
     pub fn push(&mut self, value: T) {
         self.buffer[self.position] = value;
-        self.position = (self.position + 1) % self.buffer.len();
+        self.position += 1;
+        if self.position == self.buffer.len() {
+            self.position = 0;
+            self.wraparounds += 1;
+        }
     }
 
     pub fn extend<I: IntoIterator<Item = T>>(&mut self, iterable: I) {
@@ -40,14 +66,25 @@ impl<T: Copy> RingBuffer<T> {
         }
     }
 
-    pub fn recall(&mut self, lookback: usize, length: usize) -> Vec<T> {
+    /// Copies `length` values, starting `distance` values before the write position, to the write
+    /// position and returns them.
+    ///
+    /// Values are copied one at a time, so if `length` exceeds `distance`, the copy overlaps with
+    /// itself and the values copied first are repeated.
+    pub fn recall(&mut self, distance: usize, length: usize) -> Vec<T> {
+        assert!(distance <= self.buffer.len(), "distance {} > size {}", distance, self.buffer.len());
+        let start = (self.position + self.buffer.len() - distance) % self.buffer.len();
+        self.recall_from_position(start, length)
+    }
+
+    /// Copies `length` values, starting at the absolute position `start` within the window, to the
+    /// write position and returns them.
+    ///
+    /// Like with [`RingBuffer::recall`], the copy may overlap with itself.
+    pub fn recall_from_position(&mut self, start: usize, length: usize) -> Vec<T> {
+        assert!(start < self.buffer.len(), "start {} >= size {}", start, self.buffer.len());
         let mut ret = Vec::with_capacity(length);
-        let mut index = if lookback > self.position {
-            // we have to wrap around at 0
-            self.buffer.len() - (lookback - self.position)
-        } else {
-            self.position - lookback
-        };
+        let mut index = start;
         for _ in 0..length {
             let b = self.buffer[index];
             ret.push(b);
@@ -57,3 +94,26 @@ impl<T: Copy> RingBuffer<T> {
         ret
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::RingBuffer;
+
+    #[test]
+    fn test_overlapping_recall() {
+        let mut ring_buffer = RingBuffer::new(b' ', 8);
+        ring_buffer.set_position(6);
+        ring_buffer.extend(*b"ab");
+        assert_eq!(ring_buffer.position(), 0);
+        assert_eq!(ring_buffer.wraparounds(), 1);
+
+        // the copy overlaps with itself
+        assert_eq!(ring_buffer.recall(2, 5), b"ababa");
+        assert_eq!(ring_buffer.as_slice(), b"ababa ab");
+
+        // the initial fill can be referred to
+        assert_eq!(ring_buffer.recall_from_position(5, 2), b" a");
+        assert_eq!(ring_buffer.position(), 7);
+    }
+}
@@ -1,15 +1,88 @@
+//! Decompression of the "SZDD" format used by `COMPRESS.EXE` and its predecessor "SZ".
+//!
+//! Both are LZSS variants which only differ in their headers and the initial state of the window.
+
+
+use std::fmt;
 use std::io::{Read, Write};
 
 use tracing::debug;
 
 use crate::error::DecompressionError;
+use crate::ring_buffer::RingBuffer;
+
+
+/// The parameters of the window (ring buffer) from which matches are copied.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct WindowParameters {
+    /// The size of the window in bytes.
+    ///
+    /// Match positions are 12 bits wide; windows smaller than 4096 bytes wrap positions around.
+    pub size: usize,
+
+    /// The byte with which the window is filled initially.
+    pub fill_byte: u8,
+
+    /// How far before the end of the window the first decompressed byte is stored.
+    pub initial_position_from_end: usize,
+}
+impl fmt::Display for WindowParameters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "window size {}, fill byte {:#04X}, initial position {} bytes before the end",
+            self.size, self.fill_byte, self.initial_position_from_end,
+        )
+    }
+}
+
 
+/// A known variant of the format.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Variant {
+    /// SZDD, as written by `COMPRESS.EXE` from MS-DOS 5 and Windows 3.1 onward.
+    Szdd,
 
-const WINDOW_SIZE: usize = 4096;
+    /// SZ, the predecessor of SZDD, known as the QBasic variant.
+    QBasic,
+}
+impl Variant {
+    /// All known variants.
+    pub const ALL: [Variant; 2] = [Self::Szdd, Self::QBasic];
+
+    /// The magic value with which files of this variant start.
+    pub const fn magic(&self) -> &'static [u8; 8] {
+        match self {
+            Self::Szdd => b"SZDD\x88\xF0\x27\x33",
+            Self::QBasic => b"SZ \x88\xF0\x27\x33\xD1",
+        }
+    }
 
+    /// The variant of the file starting with the given magic value, if any.
+    pub fn from_magic(magic: &[u8]) -> Option<Self> {
+        Self::ALL.into_iter()
+            .find(|v| magic.starts_with(v.magic()))
+    }
 
+    /// The window parameters used by this variant.
+    pub const fn window_parameters(&self) -> WindowParameters {
+        match self {
+            Self::Szdd => WindowParameters { size: 4096, fill_byte: b' ', initial_position_from_end: 16 },
+            Self::QBasic => WindowParameters { size: 4096, fill_byte: b' ', initial_position_from_end: 18 },
+        }
+    }
+}
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Szdd => write!(f, "SZDD"),
+            Self::QBasic => write!(f, "SZ (QBasic)"),
+        }
+    }
+}
+
+
+/// Decompresses an SZDD file whose magic value has already been read.
 pub fn decompress_szdd<R: Read, W: Write>(compressed_reader: &mut R, decompressed_writer: &mut W) -> Result<(), DecompressionError> {
-    // assuming we have already read the b"SZDD\x88\xF0\x27\x33" magic
     let mut header = [0u8; 6];
     compressed_reader.read_exact(&mut header)?;
     if header[0] != b'A' {
@@ -17,29 +90,34 @@ pub fn decompress_szdd<R: Read, W: Write>(compressed_reader: &mut R, decompresse
     }
     let decompressed_size = u32::from_le_bytes(header[2..6].try_into().unwrap());
 
-    decompress_sz_generic(compressed_reader, decompressed_writer, decompressed_size, 16)
+    decompress_with_parameters(compressed_reader, decompressed_writer, Some(decompressed_size), Variant::Szdd.window_parameters())
 }
 
 
+/// Decompresses an SZ file whose magic value has already been read.
 pub fn decompress_sz<R: Read, W: Write>(compressed_reader: &mut R, decompressed_writer: &mut W) -> Result<(), DecompressionError> {
-    // assuming we have already read the b"SZ \x88\xF0\x27\x33\xD1" magic
     let mut header = [0u8; 4];
     compressed_reader.read_exact(&mut header)?;
     let decompressed_size = u32::from_le_bytes(header);
 
-    decompress_sz_generic(compressed_reader, decompressed_writer, decompressed_size, 18)
+    decompress_with_parameters(compressed_reader, decompressed_writer, Some(decompressed_size), Variant::QBasic.window_parameters())
 }
 
-fn decompress_sz_generic<R: Read, W: Write>(
+/// Decompresses LZSS data (without a header) using the given window parameters.
+///
+/// Stops after `decompressed_size` bytes have been output (if given) or at the end of the
+/// compressed data, whichever comes first.
+pub fn decompress_with_parameters<R: Read, W: Write>(
     compressed_reader: &mut R,
     decompressed_writer: &mut W,
-    decompressed_size: u32,
-    initial_window_position_from_end: usize,
+    decompressed_size: Option<u32>,
+    parameters: WindowParameters,
 ) -> Result<(), DecompressionError> {
-    let mut window = [b' '; WINDOW_SIZE];
-    let mut pos = window.len() - initial_window_position_from_end;
-    let mut bytes_written = 0;
-
+    assert!(parameters.initial_position_from_end <= parameters.size, "initial position is outside the window");
+    let window_size = parameters.size;
+    let mut window = RingBuffer::new(parameters.fill_byte, window_size);
+    window.set_position((window_size - parameters.initial_position_from_end) % window_size);
+    let mut bytes_left = decompressed_size.map(|s| usize::try_from(s).unwrap());
     loop {
         let mut control_byte_buf = [0u8];
         let bytes_read = compressed_reader.read(&mut control_byte_buf)?;
@@ -68,14 +146,10 @@ fn decompress_sz_generic<R: Read, W: Write>(
                 let mut lit_byte_buf = [0u8];
                 compressed_reader.read_exact(&mut lit_byte_buf)?;
 
-                decompressed_writer.write_all(&lit_byte_buf)?;
-                bytes_written += 1;
-                if bytes_written == decompressed_size {
+                window.push(lit_byte_buf[0]);
+                if !write_limited(decompressed_writer, &lit_byte_buf, &mut bytes_left)? {
                     return Ok(());
                 }
-
-                window[pos] = lit_byte_buf[0];
-                pos = (pos + 1) % WINDOW_SIZE;
             } else {
                 // Msb   Lsb   Msb   Lsb
                 // |       |   |       |
@@ -83,24 +157,16 @@ fn decompress_sz_generic<R: Read, W: Write>(
                 // => PPPP pppp pppp, (llll + 3)
                 let mut match_info_buf = [0u8; 2];
                 compressed_reader.read_exact(&mut match_info_buf)?;
-                let mut match_position =
+                let match_position = (
                     usize::from(match_info_buf[0])
-                    | (usize::from(match_info_buf[1] & 0xF0) << 4);
+                    | (usize::from(match_info_buf[1] & 0xF0) << 4)
+                ) % window_size;
                 let match_length = usize::from(match_info_buf[1] & 0xF) + 3;
                 debug!("match at {} for {}", match_position, match_length);
 
-                for _ in 0..match_length {
-                    let b = window[match_position];
-                    match_position = (match_position + 1) % WINDOW_SIZE;
-
-                    decompressed_writer.write_all(&[b])?;
-                    bytes_written += 1;
-                    if bytes_written == decompressed_size {
-                        return Ok(());
-                    }
-
-                    window[pos] = b;
-                    pos = (pos + 1) % WINDOW_SIZE;
+                let matched = window.recall_from_position(match_position, match_length);
+                if !write_limited(decompressed_writer, &matched, &mut bytes_left)? {
+                    return Ok(());
                 }
             }
         }
@@ -108,3 +174,47 @@ fn decompress_sz_generic<R: Read, W: Write>(
 
     Ok(())
 }
+
+/// Writes as much of `bytes` as `bytes_left` allows and returns whether more bytes may follow.
+fn write_limited<W: Write>(writer: &mut W, bytes: &[u8], bytes_left: &mut Option<usize>) -> Result<bool, DecompressionError> {
+    match bytes_left {
+        None => {
+            writer.write_all(bytes)?;
+            Ok(true)
+        },
+        Some(left) => {
+            let count = bytes.len().min(*left);
+            writer.write_all(&bytes[..count])?;
+            *left -= count;
+            Ok(*left > 0)
+        },
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress_with_parameters, Variant, WindowParameters};
+
+    #[test]
+    fn test_window_parameters() {
+        // a literal 'A', then a match of 3 bytes at 0xFEE, which is where QBasic SZ files start
+        let compressed = [0b0000_0001, b'A', 0xEE, 0xF0];
+
+        let mut qbasic = Vec::new();
+        decompress_with_parameters(&mut &compressed[..], &mut qbasic, Some(4), Variant::QBasic.window_parameters()).unwrap();
+        assert_eq!(&qbasic, b"AAAA");
+
+        let mut szdd = Vec::new();
+        decompress_with_parameters(&mut &compressed[..], &mut szdd, Some(4), Variant::Szdd.window_parameters()).unwrap();
+        assert_eq!(&szdd, b"A  A");
+
+        let zero_filled = WindowParameters { fill_byte: 0x00, ..Variant::Szdd.window_parameters() };
+        let mut zeroes = Vec::new();
+        decompress_with_parameters(&mut &compressed[..], &mut zeroes, Some(4), zero_filled).unwrap();
+        assert_eq!(&zeroes, b"A\0\0A");
+
+        assert_eq!(Variant::from_magic(b"SZ \x88\xF0\x27\x33\xD1\0\0\0\0"), Some(Variant::QBasic));
+        assert_eq!(Variant::from_magic(b"KWAJ\x88\xF0\x27\xD1"), None);
+    }
+}
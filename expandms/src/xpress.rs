@@ -0,0 +1,366 @@
+//! Decompression logic for the LZ77+Huffman variant of the XPRESS algorithm ([MS-XCA] § 2.1).
+//!
+//! The compressed data consists of blocks, each of which yields (at most) 65536 bytes of output.
+//! Every block starts with a table of 512 four-bit code lengths of a canonical Huffman code whose
+//! symbols are either literal bytes (0-255) or a combination of match length and the bit length of
+//! the match offset (256-511). The bit stream is read as little-endian 16-bit words, most
+//! significant bit first; the extra bytes of long match lengths are interleaved with these words.
+//!
+//! The stream does not store the decompressed length. This decoder stops at the end-of-stream
+//! symbol (256, which otherwise encodes a three-byte match at offset 1) once the whole input has
+//! been consumed. Since a few bits can encode a match of 65538 bytes, the output is limited to a
+//! multiple of the input's size, to a limit given by the caller or to the decompressed size, if
+//! known.
+
+
+use std::fmt;
+
+use tracing::debug;
+
+use crate::huff::canonical_codes;
+
+
+/// The number of symbols in the Huffman code of each block.
+const SYMBOL_COUNT: usize = 512;
+
+/// The length of the table of code lengths at the start of each block, in bytes.
+const CODE_LENGTH_TABLE_SIZE: usize = SYMBOL_COUNT / 2;
+
+/// The maximum length of a code.
+const MAX_CODE_BITS: u32 = 15;
+
+/// The number of output bytes after which a new block starts.
+const BLOCK_OUTPUT_SIZE: usize = 65536;
+
+/// The symbol that ends the stream if it is decoded once the input has been consumed.
+const END_OF_STREAM_SYMBOL: u16 = 256;
+
+/// The largest multiple of its input's size that a stream without a known decompressed size may
+/// decompress to.
+///
+/// Each block takes up at least 260 bytes of input and yields less than 131074 bytes of output (a
+/// full block whose last match runs over by up to 65538 bytes), so valid streams stay below this.
+pub const MAX_EXPANSION_FACTOR: usize = 512;
+
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Error {
+    /// The input ended in the middle of the stream.
+    UnexpectedEnd,
+
+    /// The code lengths of a block do not describe a valid canonical Huffman code.
+    InvalidCodeLengths { block_offset: usize },
+
+    /// A match refers to data before the start of the output.
+    MatchBeforeStart { offset: usize, output_position: usize },
+
+    /// A 16-bit match length is shorter than the lengths that can be encoded more compactly.
+    InvalidMatchLength { length: u16 },
+
+    /// The output exceeds the limit on its size.
+    OutputTooLarge { limit: usize },
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd
+                => write!(f, "unexpected end of compressed data"),
+            Self::InvalidCodeLengths { block_offset }
+                => write!(f, "invalid Huffman code lengths in block at offset {:#X}", block_offset),
+            Self::MatchBeforeStart { offset, output_position }
+                => write!(f, "match offset {} at output position {} points before the start of the output", offset, output_position),
+            Self::InvalidMatchLength { length }
+                => write!(f, "invalid 16-bit match length {}", length),
+            Self::OutputTooLarge { limit }
+                => write!(f, "decompressed data exceeds the limit of {} bytes", limit),
+        }
+    }
+}
+impl std::error::Error for Error {
+}
+
+
+/// A table mapping every 15-bit sequence to the symbol whose code it starts with and the length of
+/// that code.
+struct DecodingTable {
+    entries: Vec<(u16, u8)>,
+}
+impl DecodingTable {
+    /// Builds the decoding table from the code lengths at the start of a block, or returns `None`
+    /// if they do not describe a complete prefix code.
+    fn new(code_length_table: &[u8]) -> Option<Self> {
+        let lengths: Vec<usize> = code_length_table.iter()
+            .flat_map(|b| [usize::from(b & 0x0F), usize::from(b >> 4)])
+            .collect();
+        let codes = canonical_codes(&lengths).ok()?;
+
+        let mut entries = vec![(0, 0); 1 << MAX_CODE_BITS];
+        for (symbol, code) in codes.iter().enumerate() {
+            let Some(code) = code else { continue };
+            let code_value = code.iter()
+                .fold(0usize, |value, &bit| (value << 1) | usize::from(bit));
+            let unused_bits = MAX_CODE_BITS as usize - code.len();
+            let start = code_value << unused_bits;
+            let entry = (u16::try_from(symbol).unwrap(), u8::try_from(code.len()).unwrap());
+            entries[start..start + (1 << unused_bits)].fill(entry);
+        }
+        Some(Self { entries })
+    }
+
+    fn decode(&self, next_bits: u32) -> (u16, u8) {
+        self.entries[(next_bits >> (32 - MAX_CODE_BITS)) as usize]
+    }
+}
+
+
+/// The position within the input along with the bits that have been read ahead.
+struct BitInput<'a> {
+    data: &'a [u8],
+    position: usize,
+    next_bits: u32,
+    extra_bit_count: i32,
+}
+impl<'a> BitInput<'a> {
+    fn new(data: &'a [u8], position: usize) -> Result<Self, Error> {
+        let mut input = Self {
+            data,
+            position,
+            next_bits: 0,
+            extra_bit_count: 16,
+        };
+        let high = input.read_u16()?;
+        let low = input.read_u16()?;
+        input.next_bits = (u32::from(high) << 16) | u32::from(low);
+        Ok(input)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        let byte = *self.data.get(self.position)
+            .ok_or(Error::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn read_u16(&mut self) -> Result<u16, Error> {
+        let bytes = self.data.get(self.position..self.position + 2)
+            .ok_or(Error::UnexpectedEnd)?;
+        self.position += 2;
+        Ok(u16::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// Drops the given number of bits from the front of the read-ahead bits, refilling them if
+    /// necessary.
+    fn consume(&mut self, bit_count: u8) -> Result<(), Error> {
+        if bit_count == 0 {
+            return Ok(());
+        }
+        self.next_bits <<= bit_count;
+        self.extra_bit_count -= i32::from(bit_count);
+        if self.extra_bit_count < 0 {
+            let word = self.read_u16()?;
+            self.next_bits |= u32::from(word) << -self.extra_bit_count;
+            self.extra_bit_count += 16;
+        }
+        Ok(())
+    }
+}
+
+
+/// Whether the data starts with a table of code lengths that describes a complete canonical Huffman
+/// code, as LZ77+Huffman data does.
+///
+/// This is a cheap check that rules out most data which is not compressed; [`decompress`] verifies
+/// the rest of the stream.
+pub fn looks_compressed(data: &[u8]) -> bool {
+    data.len() >= CODE_LENGTH_TABLE_SIZE + 4
+        && DecodingTable::new(&data[..CODE_LENGTH_TABLE_SIZE]).is_some()
+}
+
+
+/// Decompresses LZ77+Huffman data that ends with an end-of-stream symbol.
+///
+/// Fails if the data decompresses to more than [`MAX_EXPANSION_FACTOR`] times its size.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    decompress_with_limit(data, data.len().saturating_mul(MAX_EXPANSION_FACTOR))
+}
+
+/// Decompresses LZ77+Huffman data that ends with an end-of-stream symbol, failing if it decompresses
+/// to more than `limit` bytes.
+pub fn decompress_with_limit(data: &[u8], limit: usize) -> Result<Vec<u8>, Error> {
+    decompress_up_to(data, None, limit)
+}
+
+/// Decompresses LZ77+Huffman data whose decompressed size is known, such as a chunk of a WIM
+/// resource; the data need not end with an end-of-stream symbol.
+pub fn decompress_with_size(data: &[u8], decompressed_size: usize) -> Result<Vec<u8>, Error> {
+    // the declared size is only trusted as far as the input can back it
+    decompress_up_to(data, Some(decompressed_size), data.len().saturating_mul(MAX_EXPANSION_FACTOR))
+}
+
+/// Decompresses up to the decompressed size, if given; otherwise up to the end-of-stream symbol,
+/// failing beyond `limit` bytes of output.
+///
+/// With a decompressed size, `limit` only caps the memory reserved up front.
+fn decompress_up_to(data: &[u8], decompressed_size: Option<usize>, limit: usize) -> Result<Vec<u8>, Error> {
+    let mut output = Vec::with_capacity(decompressed_size.unwrap_or(0).min(limit));
+    let mut block_offset = 0;
+    loop {
+        if decompressed_size.is_some_and(|size| output.len() >= size) {
+            break;
+        }
+        if decompressed_size.is_none() && output.len() > limit {
+            debug!("XPRESS stream exceeds {} bytes of output", limit);
+            return Err(Error::OutputTooLarge { limit });
+        }
+        let code_length_table = data.get(block_offset..block_offset + CODE_LENGTH_TABLE_SIZE)
+            .ok_or(Error::UnexpectedEnd)?;
+        let table = DecodingTable::new(code_length_table)
+            .ok_or(Error::InvalidCodeLengths { block_offset })?;
+        let mut input = BitInput::new(data, block_offset + CODE_LENGTH_TABLE_SIZE)?;
+
+        let mut block_end = output.len() + BLOCK_OUTPUT_SIZE;
+        match decompressed_size {
+            Some(size) => block_end = block_end.min(size),
+            // stop once past the limit; the check above then fails
+            None => block_end = block_end.min(limit.saturating_add(1)),
+        }
+        while output.len() < block_end {
+            let (symbol, code_length) = table.decode(input.next_bits);
+            input.consume(code_length)?;
+
+            if symbol < 256 {
+                output.push(symbol as u8);
+                continue;
+            }
+            if symbol == END_OF_STREAM_SYMBOL && decompressed_size.is_none() && input.position == data.len() {
+                debug!("XPRESS stream ended after {} bytes of output", output.len());
+                return Ok(output);
+            }
+
+            let match_symbol = symbol - 256;
+            let mut match_length = usize::from(match_symbol % 16);
+            let offset_bit_count = (match_symbol / 16) as u8;
+            if match_length == 15 {
+                match_length = usize::from(input.read_u8()?);
+                if match_length == 255 {
+                    let long_length = input.read_u16()?;
+                    if long_length < 15 {
+                        return Err(Error::InvalidMatchLength { length: long_length });
+                    }
+                    match_length = usize::from(long_length) - 15;
+                }
+                match_length += 15;
+            }
+            match_length += 3;
+
+            let offset_bits = if offset_bit_count == 0 {
+                0
+            } else {
+                input.next_bits >> (32 - u32::from(offset_bit_count))
+            };
+            let offset = (1usize << offset_bit_count) + offset_bits as usize;
+            input.consume(offset_bit_count)?;
+
+            let output_position = output.len();
+            let Some(start) = output_position.checked_sub(offset) else {
+                return Err(Error::MatchBeforeStart { offset, output_position });
+            };
+            // matches may overlap their own output, so copy byte by byte
+            for i in 0..match_length {
+                output.push(output[start + i]);
+            }
+        }
+
+        // the next block starts after the words that have been read ahead
+        block_offset = input.position;
+    }
+
+    // the last match may extend beyond the decompressed size
+    if let Some(size) = decompressed_size {
+        output.truncate(size);
+    }
+    Ok(output)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress, decompress_with_limit, decompress_with_size, looks_compressed, Error, CODE_LENGTH_TABLE_SIZE, MAX_EXPANSION_FACTOR};
+
+    /// Encodes literals and short matches with a code in which every symbol is nine bits long.
+    fn encode(tokens: &[(u16, u16, u16)]) -> Vec<u8> {
+        let mut data = vec![0x99; CODE_LENGTH_TABLE_SIZE];
+        let mut bits: Vec<bool> = Vec::new();
+        let push = |value: u16, count: u16, bits: &mut Vec<bool>| {
+            for i in (0..count).rev() {
+                bits.push(value & (1 << i) != 0);
+            }
+        };
+        for &(symbol, offset_value, offset_bit_count) in tokens {
+            push(symbol, 9, &mut bits);
+            push(offset_value, offset_bit_count, &mut bits);
+        }
+        push(256, 9, &mut bits);
+        bits.resize(bits.len().div_ceil(16) * 16 + 16, false);
+        for word_bits in bits.chunks(16) {
+            let word = word_bits.iter().fold(0u16, |word, &bit| (word << 1) | u16::from(bit));
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_decompress() {
+        // "abcabcabcX": three literals, a match of length 6 at offset 3 (one offset bit on top of 2),
+        // then one more literal
+        let tokens = [
+            (u16::from(b'a'), 0, 0),
+            (u16::from(b'b'), 0, 0),
+            (u16::from(b'c'), 0, 0),
+            (256 + 0x10 + 3, 1, 1),
+            (u16::from(b'X'), 0, 0),
+        ];
+        let compressed = encode(&tokens);
+        assert!(looks_compressed(&compressed));
+        assert_eq!(decompress(&compressed).unwrap(), b"abcabcabcX");
+
+        assert!(!looks_compressed(&[0u8; 300]));
+    }
+
+    #[test]
+    fn test_decompress_multiple_blocks() {
+        // longer than a block, with repetitions near and far
+        let data: Vec<u8> = (0..200_000u32)
+            .map(|i| if i % 1000 < 500 { (i % 7) as u8 } else { (i / 3 % 251) as u8 })
+            .collect();
+        let compressed = testgen::xpress::compress(&data);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+        assert_eq!(decompress_with_size(&compressed, data.len()).unwrap(), data);
+    }
+
+    #[test]
+    fn test_decompress_long_matches() {
+        // runs whose matches store their lengths in the symbol, in an extra byte and in an extra
+        // word, interleaved with the bit stream
+        let mut data = Vec::new();
+        for (byte, run_length) in [(b'a', 20), (b'b', 200), (b'c', 272), (b'd', 273), (b'e', 5000), (b'f', 70000)] {
+            data.extend(std::iter::repeat_n(byte, run_length));
+            data.extend_from_slice(b"xyz");
+        }
+        let compressed = testgen::xpress::compress(&data);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+        assert_eq!(decompress_with_size(&compressed, data.len()).unwrap(), data);
+    }
+
+    #[test]
+    fn test_output_limit() {
+        let data = vec![0u8; 200_000];
+        let compressed = testgen::xpress::compress(&data);
+        assert_eq!(decompress_with_limit(&compressed, data.len()).unwrap(), data);
+        assert_eq!(
+            decompress_with_limit(&compressed, 100_000),
+            Err(Error::OutputTooLarge { limit: 100_000 }),
+        );
+        assert!(data.len() < compressed.len() * MAX_EXPANSION_FACTOR);
+    }
+}
@@ -0,0 +1,277 @@
+//! Reading ZIP archives.
+//!
+//! The files of a ZIP archive are listed in its central directory, which is found through the end
+//! of central directory record at the end of the archive. Self-extracting archives are often
+//! created by prepending the extraction program to an existing archive, which leaves the offsets
+//! in the central directory relative to the start of the archive instead of the start of the file;
+//! like `unzip`, the difference between the recorded and the actual position of the central
+//! directory is added to every offset.
+//!
+//! Only files that are stored or compressed using DEFLATE can be extracted. ZIP64 archives are not
+//! supported.
+
+
+use std::io::{self, Write};
+
+use from_to_repr::from_to_other;
+use tracing::debug;
+
+use crate::DecompressionError;
+use crate::crc::{CrcWriter, CRC_32_ISO_HDLC};
+use crate::inflate::{Inflater, MAX_LOOKBACK_DISTANCE};
+
+
+pub const LOCAL_FILE_HEADER_SIGNATURE: [u8; 4] = *b"PK\x03\x04";
+const CENTRAL_DIRECTORY_HEADER_SIGNATURE: [u8; 4] = *b"PK\x01\x02";
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: [u8; 4] = *b"PK\x05\x06";
+
+const LOCAL_FILE_HEADER_SIZE: usize = 30;
+const CENTRAL_DIRECTORY_HEADER_SIZE: usize = 46;
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+
+/// The flag marking a file as encrypted.
+const FLAG_ENCRYPTED: u16 = 0x0001;
+
+
+#[derive(Clone, Copy, Debug)]
+#[from_to_other(base_type = u16, derive_compare = "as_int")]
+pub enum CompressionMethod {
+    Stored = 0,
+    Shrunk = 1,
+    Reduced1 = 2,
+    Reduced2 = 3,
+    Reduced3 = 4,
+    Reduced4 = 5,
+    Imploded = 6,
+    Deflated = 8,
+    Deflate64 = 9,
+    Other(u16),
+}
+
+
+/// A file listed in the central directory of a ZIP archive.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ZipEntry {
+    /// The path of the file, with directories separated by `/`.
+    pub path: Vec<u8>,
+
+    pub flags: u16,
+    pub compression_method: CompressionMethod,
+    pub crc32: u32,
+    pub compressed_size: u32,
+    pub uncompressed_size: u32,
+
+    /// The offset of the local file header, corrected for any data prepended to the archive.
+    pub local_header_offset: usize,
+}
+impl ZipEntry {
+    /// Whether the entry describes a directory instead of a file.
+    pub fn is_directory(&self) -> bool {
+        self.path.ends_with(b"/")
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & FLAG_ENCRYPTED != 0
+    }
+
+    /// The compressed data of this entry, which follows its local file header.
+    pub fn compressed_data<'d>(&self, data: &'d [u8]) -> Result<&'d [u8], io::Error> {
+        let Some(header) = data.get(self.local_header_offset..self.local_header_offset+LOCAL_FILE_HEADER_SIZE) else {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        };
+        if header[0..4] != LOCAL_FILE_HEADER_SIGNATURE {
+            debug!("no local file header at {:#X}", self.local_header_offset);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let name_length = usize::from(u16::from_le_bytes(header[26..28].try_into().unwrap()));
+        let extra_length = usize::from(u16::from_le_bytes(header[28..30].try_into().unwrap()));
+        let data_offset = self.local_header_offset + LOCAL_FILE_HEADER_SIZE + name_length + extra_length;
+        let data_end = data_offset + usize::try_from(self.compressed_size).unwrap();
+        data.get(data_offset..data_end)
+            .ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+    }
+}
+
+
+/// Finds the end of central directory record, searching backwards from the end of the data.
+///
+/// Since the record ends with a comment of up to 65535 bytes, only that part of the data is
+/// searched.
+pub fn find_end_of_central_directory(data: &[u8]) -> Option<usize> {
+    let last_start = data.len().checked_sub(END_OF_CENTRAL_DIRECTORY_SIZE)?;
+    let first_start = last_start.saturating_sub(usize::from(u16::MAX));
+    (first_start..=last_start).rev()
+        .find(|offset| {
+            let record = &data[*offset..];
+            let comment_length = usize::from(u16::from_le_bytes(record[20..22].try_into().unwrap()));
+            record[0..4] == END_OF_CENTRAL_DIRECTORY_SIGNATURE
+                && END_OF_CENTRAL_DIRECTORY_SIZE + comment_length == record.len()
+        })
+}
+
+
+/// Reads the central directory of the ZIP archive at the end of the data.
+///
+/// Returns the entries along with the offset at which the archive starts, i.e. the amount of data
+/// that has been prepended to it.
+pub fn read_central_directory(data: &[u8]) -> Result<(Vec<ZipEntry>, usize), io::Error> {
+    let Some(end_offset) = find_end_of_central_directory(data) else {
+        debug!("end of central directory record not found");
+        return Err(io::ErrorKind::InvalidData.into());
+    };
+    let end_record = &data[end_offset..end_offset+END_OF_CENTRAL_DIRECTORY_SIZE];
+    let this_disk = u16::from_le_bytes(end_record[4..6].try_into().unwrap());
+    let directory_disk = u16::from_le_bytes(end_record[6..8].try_into().unwrap());
+    let entry_count = u16::from_le_bytes(end_record[10..12].try_into().unwrap());
+    let directory_size = usize::try_from(u32::from_le_bytes(end_record[12..16].try_into().unwrap())).unwrap();
+    let directory_offset = usize::try_from(u32::from_le_bytes(end_record[16..20].try_into().unwrap())).unwrap();
+    if this_disk != directory_disk {
+        debug!("ZIP archive spans multiple disks");
+        return Err(io::ErrorKind::Unsupported.into());
+    }
+
+    // correct the offsets if data has been prepended to the archive
+    let Some(actual_directory_offset) = end_offset.checked_sub(directory_size) else {
+        debug!("central directory size {} exceeds its position {:#X}", directory_size, end_offset);
+        return Err(io::ErrorKind::InvalidData.into());
+    };
+    let Some(prefix_length) = actual_directory_offset.checked_sub(directory_offset) else {
+        debug!("central directory at {:#X} is recorded at {:#X}", actual_directory_offset, directory_offset);
+        return Err(io::ErrorKind::InvalidData.into());
+    };
+
+    let mut entries = Vec::with_capacity(entry_count.into());
+    let mut offset = actual_directory_offset;
+    for _ in 0..entry_count {
+        let Some(header) = data.get(offset..offset+CENTRAL_DIRECTORY_HEADER_SIZE) else {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        };
+        if header[0..4] != CENTRAL_DIRECTORY_HEADER_SIGNATURE {
+            debug!("no central directory header at {:#X}", offset);
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let flags = u16::from_le_bytes(header[8..10].try_into().unwrap());
+        let compression_method = CompressionMethod::from_base_type(u16::from_le_bytes(header[10..12].try_into().unwrap()));
+        let crc32 = u32::from_le_bytes(header[16..20].try_into().unwrap());
+        let compressed_size = u32::from_le_bytes(header[20..24].try_into().unwrap());
+        let uncompressed_size = u32::from_le_bytes(header[24..28].try_into().unwrap());
+        let name_length = usize::from(u16::from_le_bytes(header[28..30].try_into().unwrap()));
+        let extra_length = usize::from(u16::from_le_bytes(header[30..32].try_into().unwrap()));
+        let comment_length = usize::from(u16::from_le_bytes(header[32..34].try_into().unwrap()));
+        let local_header_offset = usize::try_from(u32::from_le_bytes(header[42..46].try_into().unwrap())).unwrap() + prefix_length;
+
+        let name_offset = offset + CENTRAL_DIRECTORY_HEADER_SIZE;
+        let Some(name) = data.get(name_offset..name_offset+name_length) else {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        };
+        let path = name.iter()
+            .map(|b| if *b == b'\\' { b'/' } else { *b })
+            .collect();
+
+        entries.push(ZipEntry {
+            path,
+            flags,
+            compression_method,
+            crc32,
+            compressed_size,
+            uncompressed_size,
+            local_header_offset,
+        });
+        offset = name_offset + name_length + extra_length + comment_length;
+    }
+    Ok((entries, prefix_length))
+}
+
+
+/// Decompresses the data of an entry of a ZIP archive and checks its CRC.
+pub fn decompress<W: Write>(data: &[u8], entry: &ZipEntry, writer: &mut W) -> Result<(), DecompressionError> {
+    if entry.is_encrypted() {
+        return Err(DecompressionError::Encrypted);
+    }
+    let mut compressed = entry.compressed_data(data)?;
+    let mut crc_writer = CrcWriter::new(writer, CRC_32_ISO_HDLC.digest());
+    match entry.compression_method {
+        CompressionMethod::Stored => {
+            crc_writer.write_all(compressed)?;
+        },
+        CompressionMethod::Deflated => {
+            let mut inflater = Inflater::new(&mut compressed, MAX_LOOKBACK_DISTANCE);
+            let mut block = Vec::new();
+            loop {
+                block.clear();
+                let last_block = inflater.inflate_block(&mut block)?;
+                crc_writer.write_all(&block)?;
+                if last_block {
+                    break;
+                }
+            }
+        },
+        other => {
+            debug!("unsupported ZIP compression method {:?}", other);
+            return Err(DecompressionError::UnknownCompressionMethod);
+        },
+    }
+
+    let calculated = crc_writer.digest().finalize();
+    if calculated != entry.crc32 {
+        debug!("CRC of ZIP entry at {:#X} is {:#010X}, expected {:#010X}", entry.local_header_offset, calculated, entry.crc32);
+        return Err(DecompressionError::ChecksumMismatch { expected: entry.crc32, calculated });
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress, read_central_directory, CompressionMethod};
+    use crate::DecompressionError;
+    use crate::crc::CRC_32_ISO_HDLC;
+    use tracing_test::traced_test;
+
+    #[test]
+    #[traced_test]
+    fn test_prepended_archive() {
+        // created with Python's zipfile module, then prefixed with a fake extraction program
+        let archive = [
+            0x50, 0x4B, 0x03, 0x04, 0x14, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x21, 0x1C, 0xB1, 0x05,
+            0x8B, 0xFF, 0x18, 0x00, 0x00, 0x00, 0x8C, 0x00, 0x00, 0x00, 0x0F, 0x00, 0x00, 0x00, 0x44, 0x4F,
+            0x43, 0x53, 0x2F, 0x52, 0x45, 0x41, 0x44, 0x4D, 0x45, 0x2E, 0x54, 0x58, 0x54, 0x4B, 0x4C, 0xCA,
+            0x49, 0x55, 0x48, 0x4E, 0x04, 0x91, 0x69, 0x60, 0x32, 0x1D, 0x4C, 0x16, 0x83, 0xC9, 0x12, 0x30,
+            0x99, 0x48, 0x2F, 0x25, 0x00, 0x50, 0x4B, 0x01, 0x02, 0x14, 0x03, 0x14, 0x00, 0x00, 0x00, 0x08,
+            0x00, 0x00, 0x00, 0x21, 0x1C, 0xB1, 0x05, 0x8B, 0xFF, 0x18, 0x00, 0x00, 0x00, 0x8C, 0x00, 0x00,
+            0x00, 0x0F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x01, 0x00,
+            0x00, 0x00, 0x00, 0x44, 0x4F, 0x43, 0x53, 0x2F, 0x52, 0x45, 0x41, 0x44, 0x4D, 0x45, 0x2E, 0x54,
+            0x58, 0x54, 0x50, 0x4B, 0x05, 0x06, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x3D, 0x00,
+            0x00, 0x00, 0x45, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let mut data = vec![0xCC; 0x123];
+        data.extend_from_slice(&archive);
+
+        let (entries, prefix_length) = read_central_directory(&data)
+            .expect("failed to read central directory");
+        assert_eq!(prefix_length, 0x123);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, b"DOCS/README.TXT");
+        assert_eq!(entries[0].compression_method, CompressionMethod::Deflated);
+        assert_eq!(entries[0].local_header_offset, 0x123);
+
+        let mut output = Vec::new();
+        decompress(&data, &entries[0], &mut output)
+            .expect("failed to decompress");
+        assert_eq!(output, b"able cable fable gable sable table ".repeat(4));
+    }
+
+    #[test]
+    fn test_checksum_mismatch() {
+        let mut archive = testgen::sfx::zip(&[("README.TXT", b"Read me!")]);
+        let (entries, _prefix_length) = read_central_directory(&archive).unwrap();
+        let data_offset = archive.windows(8).position(|w| w == b"Read me!").unwrap();
+        archive[data_offset] = b'F';
+
+        let mut output = Vec::new();
+        let error = decompress(&archive, &entries[0], &mut output).unwrap_err();
+        let DecompressionError::ChecksumMismatch { expected, calculated } = error else { panic!("unexpected error {:?}", error) };
+        assert_eq!(expected, entries[0].crc32);
+        assert_eq!(calculated, CRC_32_ISO_HDLC.checksum(b"Fead me!"));
+    }
+}
@@ -0,0 +1,290 @@
+//! Filtering and renaming exported symbols using a Rhai script.
+//!
+//! The script is evaluated once for each symbol with the following variables in scope:
+//!
+//! * `path_parts`: the path of the exporting file as an array of strings, the outermost container
+//!   first
+//! * `filename_opt`: the file name in the last path part, or `()` if there is no path
+//! * `ordinal_opt`: the ordinal of the symbol, or `()` if it is only exported by name
+//! * `name_opt`: the name of the symbol, or `()` if it is only exported by ordinal
+//!
+//! The script evaluates to `true` to keep the symbol and to `false` to drop it. A script that keeps
+//! a symbol must define `filename`, the name under which the exporting file is output, and may
+//! change `ordinal_opt` and `name_opt`.
+//!
+//! In addition to the Rhai standard library, scripts can call `remove_prefix(string, prefix)`,
+//! which returns the string without the prefix (or `()` if the string does not start with it), and
+//! `join(glue, array)`.
+
+
+use std::fmt;
+use std::path::Path;
+
+use rhai::{Array, AST, Dynamic, Engine, EvalAltResult, ImmutableString, Scope};
+
+
+/// An error that occurred while compiling or evaluating a filter script.
+#[derive(Debug)]
+pub enum Error {
+    /// The script failed to compile or to evaluate.
+    Script(Box<EvalAltResult>),
+
+    /// The script kept a symbol without defining a variable it must define.
+    MissingVariable(&'static str),
+
+    /// The script assigned a value of the wrong type to a variable.
+    WrongType { variable: &'static str, expected: &'static str },
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Script(e) => write!(f, "script error: {}", e),
+            Self::MissingVariable(variable) => write!(f, "script did not define {:?}", variable),
+            Self::WrongType { variable, expected } => write!(f, "script set {:?} to something other than {}", variable, expected),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Script(e) => Some(e),
+            Self::MissingVariable(_) => None,
+            Self::WrongType { .. } => None,
+        }
+    }
+}
+impl From<Box<EvalAltResult>> for Error {
+    fn from(value: Box<EvalAltResult>) -> Self { Self::Script(value) }
+}
+
+
+/// A symbol that has been kept by the filter script, with the values the script has assigned.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct FilteredExport {
+    pub filename: String,
+    pub ordinal: Option<u32>,
+    pub name: Option<String>,
+}
+
+
+fn remove_prefix(this: ImmutableString, prefix: ImmutableString) -> Dynamic {
+    if let Some(stripped) = this.strip_prefix(prefix.as_str()) {
+        Dynamic::from(stripped.to_owned())
+    } else {
+        Dynamic::UNIT
+    }
+}
+
+fn join(glue: ImmutableString, pieces: Array) -> Dynamic {
+    let mut ret = String::new();
+    let mut first_piece = true;
+    for piece in &pieces {
+        if first_piece {
+            first_piece = false;
+        } else {
+            ret.push_str(glue.as_str());
+        }
+
+        if let Ok(s) = piece.clone().into_string() {
+            ret.push_str(s.as_str());
+        } else {
+            ret.push_str(&piece.to_string());
+        }
+    }
+    Dynamic::from(ret)
+}
+
+fn opt_u32_to_dynamic(ou: Option<u32>) -> Dynamic {
+    if let Some(u) = ou {
+        Dynamic::from_int(u.into())
+    } else {
+        Dynamic::UNIT
+    }
+}
+fn opt_str_to_dynamic<T: Into<String>>(os: Option<T>) -> Dynamic {
+    if let Some(s) = os {
+        Dynamic::from(s.into())
+    } else {
+        Dynamic::UNIT
+    }
+}
+fn dynamic_to_opt_u32(dy: Dynamic, variable: &'static str) -> Result<Option<u32>, Error> {
+    if dy.is_unit() {
+        return Ok(None);
+    }
+    dy.try_cast::<i64>()
+        .and_then(|int_val| u32::try_from(int_val).ok())
+        .map(Some)
+        .ok_or(Error::WrongType { variable, expected: "an ordinal" })
+}
+fn dynamic_to_opt_string(dy: Dynamic, variable: &'static str) -> Result<Option<String>, Error> {
+    if dy.is_unit() {
+        return Ok(None);
+    }
+    dy.into_string()
+        .map(Some)
+        .map_err(|_| Error::WrongType { variable, expected: "a string" })
+}
+
+/// A compiled filter script along with the engine evaluating it.
+pub struct ExportFilter {
+    engine: Engine,
+    script: AST,
+}
+impl ExportFilter {
+    /// Compiles the filter script in the given file.
+    pub fn compile_file(path: &Path) -> Result<Self, Error> {
+        let mut engine = Engine::new();
+        engine.register_fn("remove_prefix", remove_prefix);
+        engine.register_fn("join", join);
+
+        let script = engine.compile_file(path.to_owned())?;
+        Ok(Self {
+            engine,
+            script,
+        })
+    }
+
+    /// Runs the script for a symbol exported by the file at the given path.
+    ///
+    /// Returns `None` if the script drops the symbol.
+    pub fn filter(&self, path_parts: &[String], ordinal: Option<u32>, name: Option<&str>) -> Result<Option<FilteredExport>, Error> {
+        let filename_opt: Option<&str> = path_parts.last()
+            .map(|pp| pp
+                .split(['/', '\\'])
+                .next_back().unwrap()
+            );
+        let path_parts_rhai: Array = path_parts.iter()
+            .map(|pp| Dynamic::from(pp.clone()))
+            .collect();
+
+        let mut scope = Scope::new();
+        scope.push("path_parts", path_parts_rhai);
+        scope.push("filename_opt", opt_str_to_dynamic(filename_opt));
+        scope.push("ordinal_opt", opt_u32_to_dynamic(ordinal));
+        scope.push("name_opt", opt_str_to_dynamic(name));
+
+        let output_export: bool = self.engine.eval_ast_with_scope(&mut scope, &self.script)?;
+        if !output_export {
+            return Ok(None);
+        }
+
+        let get_value = |variable: &'static str| scope.get_value::<Dynamic>(variable)
+            .ok_or(Error::MissingVariable(variable));
+        let filename = dynamic_to_opt_string(get_value("filename")?, "filename")?
+            .ok_or(Error::WrongType { variable: "filename", expected: "a string" })?;
+        let ordinal = dynamic_to_opt_u32(get_value("ordinal_opt")?, "ordinal_opt")?;
+        let name = dynamic_to_opt_string(get_value("name_opt")?, "name_opt")?;
+        Ok(Some(FilteredExport {
+            filename,
+            ordinal,
+            name,
+        }))
+    }
+}
+impl fmt::Debug for ExportFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExportFilter")
+            .field("script", &self.script)
+            .finish_non_exhaustive()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use super::{dynamic_to_opt_string, dynamic_to_opt_u32, Error, ExportFilter, FilteredExport};
+    use rhai::Dynamic;
+
+    fn compile(script: &str) -> ExportFilter {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(script.as_bytes()).unwrap();
+        ExportFilter::compile_file(file.path()).unwrap()
+    }
+
+    fn path_parts() -> Vec<String> {
+        vec!["DISK1.IMG".to_owned(), "SYSTEM/USER.EXE".to_owned()]
+    }
+
+    #[test]
+    fn test_filter() {
+        let filter = compile(r#"
+            if name_opt == () {
+                return false;
+            }
+            let filename = join("|", path_parts) + "|" + filename_opt;
+            let stripped = remove_prefix(name_opt, "_");
+            if stripped != () {
+                name_opt = stripped;
+            }
+            ordinal_opt = ();
+            true
+        "#);
+
+        assert_eq!(filter.filter(&path_parts(), Some(5), None).unwrap(), None);
+        assert_eq!(
+            filter.filter(&path_parts(), Some(5), Some("_Beep")).unwrap(),
+            Some(FilteredExport {
+                filename: "DISK1.IMG|SYSTEM/USER.EXE|USER.EXE".to_owned(),
+                ordinal: None,
+                name: Some("Beep".to_owned()),
+            }),
+        );
+        assert_eq!(
+            filter.filter(&[], None, Some("Beep")).unwrap().unwrap().filename,
+            "|",
+        );
+    }
+
+    #[test]
+    fn test_filter_errors() {
+        let filter = compile("true");
+        assert!(matches!(
+            filter.filter(&path_parts(), Some(1), None),
+            Err(Error::MissingVariable("filename")),
+        ));
+
+        let filter = compile(r#"let filename = (); true"#);
+        assert!(matches!(
+            filter.filter(&path_parts(), Some(1), None),
+            Err(Error::WrongType { variable: "filename", expected: "a string" }),
+        ));
+
+        let filter = compile(r#"let filename = "USER.EXE"; ordinal_opt = -1; true"#);
+        assert!(matches!(
+            filter.filter(&path_parts(), Some(1), None),
+            Err(Error::WrongType { variable: "ordinal_opt", expected: "an ordinal" }),
+        ));
+
+        let filter = compile(r#"let filename = "USER.EXE"; name_opt = 1; true"#);
+        assert!(matches!(
+            filter.filter(&path_parts(), Some(1), None),
+            Err(Error::WrongType { variable: "name_opt", expected: "a string" }),
+        ));
+
+        let filter = compile(r#""not a bool""#);
+        assert!(matches!(filter.filter(&path_parts(), Some(1), None), Err(Error::Script(_))));
+    }
+
+    #[test]
+    fn test_dynamic_conversions() {
+        assert_eq!(dynamic_to_opt_u32(Dynamic::UNIT, "ordinal_opt").unwrap(), None);
+        assert_eq!(dynamic_to_opt_u32(Dynamic::from_int(0xFFFF_FFFF), "ordinal_opt").unwrap(), Some(0xFFFF_FFFF));
+        assert!(matches!(
+            dynamic_to_opt_u32(Dynamic::from_int(0x1_0000_0000), "ordinal_opt"),
+            Err(Error::WrongType { variable: "ordinal_opt", .. }),
+        ));
+        assert!(matches!(
+            dynamic_to_opt_u32(Dynamic::from("12".to_owned()), "ordinal_opt"),
+            Err(Error::WrongType { variable: "ordinal_opt", .. }),
+        ));
+
+        assert_eq!(dynamic_to_opt_string(Dynamic::UNIT, "name_opt").unwrap(), None);
+        assert_eq!(dynamic_to_opt_string(Dynamic::from("Beep".to_owned()), "name_opt").unwrap(), Some("Beep".to_owned()));
+        assert!(matches!(
+            dynamic_to_opt_string(Dynamic::from_int(12), "name_opt"),
+            Err(Error::WrongType { variable: "name_opt", .. }),
+        ));
+    }
+}
@@ -0,0 +1,161 @@
+//! Builds sets of Microsoft Cabinet files storing their files without compression.
+//!
+//! All files are stored in a single folder whose data is cut into blocks and spread across as many
+//! cabinets as needed. The last block of each cabinet but the last is split, with its first part
+//! ending the cabinet and the rest starting the next one, as done by the cabinet tools when a disk
+//! fills up.
+
+
+/// A file to store in a cabinet set.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct File {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+
+const FOLDER_CONTINUED_FROM_PREVIOUS: u16 = 0xFFFD;
+const FOLDER_CONTINUED_TO_NEXT: u16 = 0xFFFE;
+const FOLDER_CONTINUED_PREVIOUS_AND_NEXT: u16 = 0xFFFF;
+
+const FLAG_PREV_CABINET: u16 = 0x0001;
+const FLAG_NEXT_CABINET: u16 = 0x0002;
+
+const HEADER_SIZE: usize = 36;
+const FOLDER_SIZE: usize = 8;
+const DATA_HEADER_SIZE: usize = 8;
+
+
+/// Returns the cabinets of a set storing the given files, with `blocks_per_cabinet` complete data
+/// blocks of `block_size` bytes in each cabinet.
+///
+/// The cabinets are named by `cabinet_name`, which is given the 0-based index of each cabinet; the
+/// names are stored in the headers to link the cabinets together.
+pub fn spanned_set<F: Fn(usize) -> String>(files: &[File], block_size: usize, blocks_per_cabinet: usize, cabinet_name: F) -> Vec<Vec<u8>> {
+    assert!((2..=0x8000).contains(&block_size), "block size out of range");
+    assert!(blocks_per_cabinet > 0, "cabinets must contain blocks");
+
+    let folder_data: Vec<u8> = files.iter()
+        .flat_map(|f| f.data.iter().copied())
+        .collect();
+    let blocks: Vec<&[u8]> = folder_data.chunks(block_size).collect();
+    let cabinet_blocks: Vec<&[&[u8]]> = blocks.chunks(blocks_per_cabinet).collect();
+    let cabinet_count = cabinet_blocks.len();
+
+    // the range of the folder's uncompressed data completed in each cabinet
+    let mut cabinet_ranges = Vec::with_capacity(cabinet_count);
+    let mut start = 0;
+    for blocks_here in &cabinet_blocks {
+        let length: usize = blocks_here.iter().map(|b| b.len()).sum();
+        cabinet_ranges.push(start..start+length);
+        start += length;
+    }
+
+    let mut cabinets = Vec::with_capacity(cabinet_count);
+    for (index, blocks_here) in cabinet_blocks.iter().enumerate() {
+        let is_first = index == 0;
+        let is_last = index == cabinet_count - 1;
+        let range = &cabinet_ranges[index];
+
+        // the split block: the first half of the next cabinet's first block ends this cabinet
+        let carried_in: &[u8] = if is_first { &[] } else { &blocks_here[0][..blocks_here[0].len() / 2] };
+        let split_out: Option<&[u8]> = if is_last { None } else {
+            let next_first = cabinet_blocks[index + 1][0];
+            Some(&next_first[..next_first.len() / 2])
+        };
+
+        let mut flags = 0;
+        let mut names = Vec::new();
+        if !is_first {
+            flags |= FLAG_PREV_CABINET;
+            push_name(&mut names, &cabinet_name(index - 1));
+            push_name(&mut names, &format!("Disk {}", index));
+        }
+        if !is_last {
+            flags |= FLAG_NEXT_CABINET;
+            push_name(&mut names, &cabinet_name(index + 1));
+            push_name(&mut names, &format!("Disk {}", index + 2));
+        }
+
+        // files overlapping the data completed in this cabinet
+        let mut file_entries = Vec::new();
+        let mut file_count = 0u16;
+        let mut file_start = 0;
+        for file in files {
+            let file_range = file_start..file_start+file.data.len();
+            file_start = file_range.end;
+            if file_range.start >= range.end || file_range.end <= range.start {
+                continue;
+            }
+            let folder_index = match (file_range.start < range.start, file_range.end > range.end) {
+                (false, false) => 0,
+                (true, false) => FOLDER_CONTINUED_FROM_PREVIOUS,
+                (false, true) => FOLDER_CONTINUED_TO_NEXT,
+                (true, true) => FOLDER_CONTINUED_PREVIOUS_AND_NEXT,
+            };
+            file_entries.extend_from_slice(&u32::try_from(file.data.len()).unwrap().to_le_bytes());
+            file_entries.extend_from_slice(&u32::try_from(file_range.start).unwrap().to_le_bytes());
+            file_entries.extend_from_slice(&folder_index.to_le_bytes());
+            file_entries.extend_from_slice(&0x2121u16.to_le_bytes()); // 1996-09-01
+            file_entries.extend_from_slice(&0x0000u16.to_le_bytes());
+            file_entries.extend_from_slice(&0x0020u16.to_le_bytes()); // archive
+            push_name(&mut file_entries, &file.name);
+            file_count += 1;
+        }
+
+        let mut data_blocks = Vec::new();
+        let mut block_count = 0u16;
+        for (block_index, block) in blocks_here.iter().enumerate() {
+            let stored = if block_index == 0 { &block[carried_in.len()..] } else { block };
+            push_data_block(&mut data_blocks, stored, block.len());
+            block_count += 1;
+        }
+        if let Some(split_out) = split_out {
+            push_data_block(&mut data_blocks, split_out, 0);
+            block_count += 1;
+        }
+
+        let files_offset = HEADER_SIZE + names.len() + FOLDER_SIZE;
+        let data_offset = files_offset + file_entries.len();
+        let total_size = data_offset + data_blocks.len();
+
+        let mut cabinet = Vec::with_capacity(total_size);
+        cabinet.extend_from_slice(b"MSCF");
+        cabinet.extend_from_slice(&0u32.to_le_bytes());
+        cabinet.extend_from_slice(&u32::try_from(total_size).unwrap().to_le_bytes());
+        cabinet.extend_from_slice(&0u32.to_le_bytes());
+        cabinet.extend_from_slice(&u32::try_from(files_offset).unwrap().to_le_bytes());
+        cabinet.extend_from_slice(&0u32.to_le_bytes());
+        cabinet.push(3); // minor version
+        cabinet.push(1); // major version
+        cabinet.extend_from_slice(&1u16.to_le_bytes()); // folder count
+        cabinet.extend_from_slice(&file_count.to_le_bytes());
+        cabinet.extend_from_slice(&flags.to_le_bytes());
+        cabinet.extend_from_slice(&0x1234u16.to_le_bytes()); // set ID
+        cabinet.extend_from_slice(&u16::try_from(index).unwrap().to_le_bytes());
+        cabinet.extend_from_slice(&names);
+
+        cabinet.extend_from_slice(&u32::try_from(data_offset).unwrap().to_le_bytes());
+        cabinet.extend_from_slice(&block_count.to_le_bytes());
+        cabinet.extend_from_slice(&0u16.to_le_bytes()); // no compression
+
+        cabinet.extend_from_slice(&file_entries);
+        cabinet.extend_from_slice(&data_blocks);
+        cabinets.push(cabinet);
+    }
+    cabinets
+}
+
+fn push_name(bytes: &mut Vec<u8>, name: &str) {
+    bytes.extend_from_slice(name.as_bytes());
+    bytes.push(0x00);
+}
+
+/// Appends a data block; `uncompressed_length` is 0 for the first part of a split block.
+fn push_data_block(bytes: &mut Vec<u8>, stored: &[u8], uncompressed_length: usize) {
+    let mut header = [0u8; DATA_HEADER_SIZE];
+    header[4..6].copy_from_slice(&u16::try_from(stored.len()).unwrap().to_le_bytes());
+    header[6..8].copy_from_slice(&u16::try_from(uncompressed_length).unwrap().to_le_bytes());
+    bytes.extend_from_slice(&header);
+    bytes.extend_from_slice(stored);
+}
@@ -0,0 +1,144 @@
+//! Synthesizes CLR metadata (the metadata root and its `#~`, `#Strings`, `#Blob` and `#GUID`
+//! streams) as found in managed assemblies and Windows Runtime metadata files.
+
+
+use std::collections::BTreeMap;
+
+
+const METADATA_SIGNATURE: &[u8; 4] = b"BSJB";
+
+
+/// A column value of a metadata table row.
+///
+/// All heaps and tables are kept small enough for heap indexes, table indexes and coded indexes to
+/// be two bytes long; only the columns that are always four bytes long are [`Cell::U32`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Cell {
+    U16(u16),
+    U32(u32),
+}
+
+
+/// CLR metadata to be synthesized.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Metadata {
+    /// The version string of the metadata root, e.g. `v4.0.30319` or `WindowsRuntime 1.4`.
+    pub version: String,
+
+    tables: BTreeMap<u8, Vec<Vec<Cell>>>,
+    strings: Vec<u8>,
+    blobs: Vec<u8>,
+    guids: Vec<u8>,
+}
+impl Metadata {
+    /// Creates metadata with the given version string and empty tables and heaps.
+    pub fn new<V: Into<String>>(version: V) -> Self {
+        Self {
+            version: version.into(),
+            tables: BTreeMap::new(),
+            strings: vec![0x00],
+            blobs: vec![0x00],
+            guids: Vec::new(),
+        }
+    }
+
+    /// Appends a string to the `#Strings` heap, returning its index.
+    pub fn string(&mut self, value: &str) -> u16 {
+        let index = u16::try_from(self.strings.len()).unwrap();
+        self.strings.extend_from_slice(value.as_bytes());
+        self.strings.push(0x00);
+        index
+    }
+
+    /// Appends a blob to the `#Blob` heap, returning its index.
+    pub fn blob(&mut self, value: &[u8]) -> u16 {
+        let index = u16::try_from(self.blobs.len()).unwrap();
+        let length = u16::try_from(value.len()).unwrap();
+        if length < 0x80 {
+            self.blobs.push(u8::try_from(length).unwrap());
+        } else {
+            assert!(length < 0x4000);
+            self.blobs.extend_from_slice(&(0x8000 | length).to_be_bytes());
+        }
+        self.blobs.extend_from_slice(value);
+        index
+    }
+
+    /// Appends a GUID (in its in-memory layout) to the `#GUID` heap, returning its 1-based index.
+    pub fn guid(&mut self, value: [u8; 16]) -> u16 {
+        self.guids.extend_from_slice(&value);
+        u16::try_from(self.guids.len() / 16).unwrap()
+    }
+
+    /// Appends a row to the table with the given number, returning the 1-based number of the row.
+    pub fn push_row(&mut self, table: u8, row: &[Cell]) -> u16 {
+        assert!(table < 64);
+        let rows = self.tables.entry(table).or_default();
+        rows.push(row.to_vec());
+        u16::try_from(rows.len()).unwrap()
+    }
+
+    /// Returns the metadata, starting with the metadata root.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut tables_stream = Vec::new();
+        tables_stream.extend_from_slice(&0u32.to_le_bytes()); // reserved
+        tables_stream.push(2); // major version
+        tables_stream.push(0); // minor version
+        tables_stream.push(0); // heap sizes: all narrow
+        tables_stream.push(1); // reserved
+        let valid_tables = self.tables.keys()
+            .fold(0u64, |valid, table| valid | (1 << table));
+        tables_stream.extend_from_slice(&valid_tables.to_le_bytes());
+        tables_stream.extend_from_slice(&0u64.to_le_bytes()); // sorted tables
+        for rows in self.tables.values() {
+            tables_stream.extend_from_slice(&u32::try_from(rows.len()).unwrap().to_le_bytes());
+        }
+        for cell in self.tables.values().flatten().flatten() {
+            match cell {
+                Cell::U16(value) => tables_stream.extend_from_slice(&value.to_le_bytes()),
+                Cell::U32(value) => tables_stream.extend_from_slice(&value.to_le_bytes()),
+            }
+        }
+
+        let streams: [(&str, &[u8]); 4] = [
+            ("#~", &tables_stream),
+            ("#Strings", &self.strings),
+            ("#Blob", &self.blobs),
+            ("#GUID", &self.guids),
+        ];
+
+        let mut version = self.version.as_bytes().to_vec();
+        version.push(0x00);
+        version.resize(version.len().next_multiple_of(4), 0x00);
+
+        let headers_length: usize = streams.iter()
+            .map(|(name, _)| 8 + (name.len() + 1).next_multiple_of(4))
+            .sum();
+        let mut offset = 16 + version.len() + 4 + headers_length;
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(METADATA_SIGNATURE);
+        bytes.extend_from_slice(&1u16.to_le_bytes()); // major version
+        bytes.extend_from_slice(&1u16.to_le_bytes()); // minor version
+        bytes.extend_from_slice(&0u32.to_le_bytes()); // reserved
+        bytes.extend_from_slice(&u32::try_from(version.len()).unwrap().to_le_bytes());
+        bytes.extend_from_slice(&version);
+        bytes.extend_from_slice(&0u16.to_le_bytes()); // flags
+        bytes.extend_from_slice(&u16::try_from(streams.len()).unwrap().to_le_bytes());
+        for (name, data) in &streams {
+            let padded_length = data.len().next_multiple_of(4);
+            bytes.extend_from_slice(&u32::try_from(offset).unwrap().to_le_bytes());
+            bytes.extend_from_slice(&u32::try_from(padded_length).unwrap().to_le_bytes());
+            let mut name_bytes = name.as_bytes().to_vec();
+            name_bytes.push(0x00);
+            name_bytes.resize(name_bytes.len().next_multiple_of(4), 0x00);
+            bytes.extend_from_slice(&name_bytes);
+            offset += padded_length;
+        }
+        for (_name, data) in &streams {
+            bytes.extend_from_slice(data);
+            bytes.resize(bytes.len().next_multiple_of(4), 0x00);
+        }
+        bytes
+    }
+}
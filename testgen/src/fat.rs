@@ -0,0 +1,104 @@
+//! Builds 1.44 MB FAT12 floppy disk images with files in the root directory.
+
+
+/// A file in the root directory of a floppy disk image.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct File {
+    /// The name in 8.3 format, e.g. `SETUP.EXE`.
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+
+const SECTOR_SIZE: usize = 512;
+const TOTAL_SECTORS: usize = 2880;
+const RESERVED_SECTORS: usize = 1;
+const FAT_COUNT: usize = 2;
+const SECTORS_PER_FAT: usize = 9;
+const ROOT_ENTRIES: usize = 224;
+const DIRECTORY_ENTRY_SIZE: usize = 32;
+const ROOT_DIR_SECTORS: usize = ROOT_ENTRIES * DIRECTORY_ENTRY_SIZE / SECTOR_SIZE;
+const FIRST_DATA_SECTOR: usize = RESERVED_SECTORS + FAT_COUNT * SECTORS_PER_FAT + ROOT_DIR_SECTORS;
+const MEDIA_DESCRIPTOR: u8 = 0xF0;
+const END_OF_CHAIN: u16 = 0xFFF;
+
+
+/// Returns an image of a 1.44 MB floppy disk with the given label containing the given files, each
+/// stored in consecutive clusters of one sector.
+pub fn floppy(label: &str, files: &[File]) -> Vec<u8> {
+    assert!(files.len() <= ROOT_ENTRIES, "too many files for the root directory");
+    let mut image = vec![0u8; TOTAL_SECTORS * SECTOR_SIZE];
+
+    let boot_sector = &mut image[..SECTOR_SIZE];
+    boot_sector[0..3].copy_from_slice(&[0xEB, 0x3C, 0x90]);
+    boot_sector[3..11].copy_from_slice(b"MSDOS5.0");
+    boot_sector[11..13].copy_from_slice(&u16::try_from(SECTOR_SIZE).unwrap().to_le_bytes());
+    boot_sector[13] = 1; // sectors per cluster
+    boot_sector[14..16].copy_from_slice(&u16::try_from(RESERVED_SECTORS).unwrap().to_le_bytes());
+    boot_sector[16] = u8::try_from(FAT_COUNT).unwrap();
+    boot_sector[17..19].copy_from_slice(&u16::try_from(ROOT_ENTRIES).unwrap().to_le_bytes());
+    boot_sector[19..21].copy_from_slice(&u16::try_from(TOTAL_SECTORS).unwrap().to_le_bytes());
+    boot_sector[21] = MEDIA_DESCRIPTOR;
+    boot_sector[22..24].copy_from_slice(&u16::try_from(SECTORS_PER_FAT).unwrap().to_le_bytes());
+    boot_sector[24..26].copy_from_slice(&18u16.to_le_bytes()); // sectors per track
+    boot_sector[26..28].copy_from_slice(&2u16.to_le_bytes()); // heads
+    boot_sector[38] = 0x29; // extended boot signature
+    boot_sector[39..43].copy_from_slice(&0x1996_0901u32.to_le_bytes());
+    boot_sector[43..54].copy_from_slice(&padded(label, 11));
+    boot_sector[54..62].copy_from_slice(b"FAT12   ");
+    boot_sector[510..512].copy_from_slice(&[0x55, 0xAA]);
+
+    let mut fat = vec![0u8; SECTORS_PER_FAT * SECTOR_SIZE];
+    set_fat12_entry(&mut fat, 0, 0xF00 | u16::from(MEDIA_DESCRIPTOR));
+    set_fat12_entry(&mut fat, 1, END_OF_CHAIN);
+
+    let root_dir_start = (RESERVED_SECTORS + FAT_COUNT * SECTORS_PER_FAT) * SECTOR_SIZE;
+    let mut next_cluster: u16 = 2;
+    for (index, file) in files.iter().enumerate() {
+        let cluster_count = file.data.len().div_ceil(SECTOR_SIZE);
+        let first_cluster = if cluster_count == 0 { 0 } else { next_cluster };
+        for (chunk_index, chunk) in file.data.chunks(SECTOR_SIZE).enumerate() {
+            let cluster = next_cluster + u16::try_from(chunk_index).unwrap();
+            let is_last = chunk_index == cluster_count - 1;
+            set_fat12_entry(&mut fat, cluster, if is_last { END_OF_CHAIN } else { cluster + 1 });
+            let data_start = (FIRST_DATA_SECTOR + usize::from(cluster) - 2) * SECTOR_SIZE;
+            image[data_start..data_start+chunk.len()].copy_from_slice(chunk);
+        }
+        next_cluster += u16::try_from(cluster_count).unwrap();
+
+        let (stem, extension) = file.name.split_once('.').unwrap_or((&file.name, ""));
+        let entry_start = root_dir_start + index * DIRECTORY_ENTRY_SIZE;
+        let entry = &mut image[entry_start..entry_start+DIRECTORY_ENTRY_SIZE];
+        entry[0..8].copy_from_slice(&padded(stem, 8));
+        entry[8..11].copy_from_slice(&padded(extension, 3));
+        entry[11] = 0x20; // archive
+        entry[24..26].copy_from_slice(&0x2121u16.to_le_bytes()); // 1996-09-01
+        entry[26..28].copy_from_slice(&first_cluster.to_le_bytes());
+        entry[28..32].copy_from_slice(&u32::try_from(file.data.len()).unwrap().to_le_bytes());
+    }
+    assert!(usize::from(next_cluster) - 2 <= TOTAL_SECTORS - FIRST_DATA_SECTOR, "files do not fit on the disk");
+
+    for fat_index in 0..FAT_COUNT {
+        let fat_start = (RESERVED_SECTORS + fat_index * SECTORS_PER_FAT) * SECTOR_SIZE;
+        image[fat_start..fat_start+fat.len()].copy_from_slice(&fat);
+    }
+    image
+}
+
+fn padded(name: &str, length: usize) -> Vec<u8> {
+    assert!(name.len() <= length, "name {:?} is longer than {} characters", name, length);
+    let mut bytes = name.to_ascii_uppercase().into_bytes();
+    bytes.resize(length, b' ');
+    bytes
+}
+
+fn set_fat12_entry(fat: &mut [u8], cluster: u16, value: u16) {
+    let offset = usize::from(cluster) * 3 / 2;
+    if cluster.is_multiple_of(2) {
+        fat[offset] = (value & 0xFF) as u8;
+        fat[offset + 1] = (fat[offset + 1] & 0xF0) | ((value >> 8) & 0x0F) as u8;
+    } else {
+        fat[offset] = (fat[offset] & 0x0F) | ((value & 0x0F) << 4) as u8;
+        fat[offset + 1] = (value >> 4) as u8;
+    }
+}
@@ -0,0 +1,270 @@
+//! Synthesizes ISO9660 CD-ROM images with a single root directory.
+
+
+const BLOCK_SIZE: usize = 2048;
+const SYSTEM_AREA_BLOCKS: usize = 16;
+
+const DESCRIPTOR_TYPE_BOOT_RECORD: u8 = 0x00;
+const DESCRIPTOR_TYPE_PRIMARY: u8 = 0x01;
+const DESCRIPTOR_TYPE_SUPPLEMENTARY: u8 = 0x02;
+const DESCRIPTOR_TYPE_TERMINATOR: u8 = 0xFF;
+const FILE_FLAGS_DIRECTORY: u8 = 0x02;
+const PATH_TABLE_SIZE: usize = 10;
+
+
+/// An ISO9660 volume to be synthesized.
+///
+/// All files are stored in the root directory, in the order of their names. Timestamps are left
+/// unspecified.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Volume {
+    /// The volume identifier, padded with spaces to 32 bytes.
+    pub volume_id: String,
+
+    /// The files in the root directory.
+    pub files: Vec<File>,
+
+    /// The boot record stored after the primary volume descriptor, if any.
+    pub boot_record: Option<BootRecord>,
+
+    /// The volume identifier of a Joliet supplementary volume descriptor stored after the primary
+    /// volume descriptor and the boot record, if any.
+    ///
+    /// The supplementary volume descriptor shares the root directory of the primary one, so the
+    /// file names it leads to are not actually UCS-2.
+    pub joliet_volume_id: Option<String>,
+}
+impl Volume {
+    /// Creates a description of an empty volume.
+    pub fn new<N: Into<String>>(volume_id: N) -> Self {
+        Self {
+            volume_id: volume_id.into(),
+            files: Vec::new(),
+            boot_record: None,
+            joliet_volume_id: None,
+        }
+    }
+
+    /// Assembles the volume into the bytes of an image.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut files: Vec<&File> = self.files.iter().collect();
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+        let identifiers: Vec<Vec<u8>> = files.iter()
+            .map(|f| {
+                assert!(f.name.is_ascii(), "file name {:?} is not ASCII", f.name);
+                format!("{};1", f.name).into_bytes()
+            })
+            .collect();
+
+        // the primary volume descriptor, the optional descriptors and the terminator
+        let descriptor_count = 2
+            + usize::from(self.boot_record.is_some())
+            + usize::from(self.joliet_volume_id.is_some());
+        let le_path_table_block = SYSTEM_AREA_BLOCKS + descriptor_count;
+        let be_path_table_block = le_path_table_block + 1;
+        let root_directory_block = be_path_table_block + 1;
+
+        // lay out the root directory first to know where the file data starts
+        let mut directory_length = 0;
+        for identifier in [&[0x00][..], &[0x01][..]].into_iter().chain(identifiers.iter().map(|i| i.as_slice())) {
+            directory_length = append_position(directory_length, record_length(identifier));
+        }
+        let directory_length = directory_length.next_multiple_of(BLOCK_SIZE);
+        let mut next_block = root_directory_block + directory_length / BLOCK_SIZE;
+        let mut file_blocks = Vec::with_capacity(files.len());
+        for file in &files {
+            file_blocks.push(next_block);
+            next_block += file.data.len().div_ceil(BLOCK_SIZE);
+        }
+        let total_blocks = next_block;
+
+        let mut bytes = vec![0u8; total_blocks * BLOCK_SIZE];
+
+        // root directory
+        let mut directory = Vec::with_capacity(directory_length);
+        let root_record = directory_record(&[0x00], root_directory_block, directory_length, true);
+        append_record(&mut directory, &root_record);
+        append_record(&mut directory, &directory_record(&[0x01], root_directory_block, directory_length, true));
+        for ((file, identifier), block) in files.iter().zip(&identifiers).zip(&file_blocks) {
+            append_record(&mut directory, &directory_record(identifier, *block, file.data.len(), false));
+        }
+        directory.resize(directory_length, 0x00);
+        let directory_offset = root_directory_block * BLOCK_SIZE;
+        bytes[directory_offset..directory_offset+directory_length].copy_from_slice(&directory);
+
+        // file data
+        for (file, block) in files.iter().zip(&file_blocks) {
+            let offset = block * BLOCK_SIZE;
+            bytes[offset..offset+file.data.len()].copy_from_slice(&file.data);
+        }
+
+        // path tables; they only contain the root directory
+        let root_block = u32::try_from(root_directory_block).unwrap();
+        let le_path_table = &mut bytes[le_path_table_block*BLOCK_SIZE..];
+        le_path_table[0] = 1; // identifier length
+        le_path_table[2..6].copy_from_slice(&root_block.to_le_bytes());
+        le_path_table[6..8].copy_from_slice(&1u16.to_le_bytes()); // parent directory number
+        let be_path_table = &mut bytes[be_path_table_block*BLOCK_SIZE..];
+        be_path_table[0] = 1;
+        be_path_table[2..6].copy_from_slice(&root_block.to_be_bytes());
+        be_path_table[6..8].copy_from_slice(&1u16.to_be_bytes());
+
+        // volume descriptor sequence
+        let mut descriptors = Vec::with_capacity(descriptor_count);
+        let mut pvd = volume_descriptor(DESCRIPTOR_TYPE_PRIMARY, total_blocks, &root_record, le_path_table_block);
+        pvd[40..72].copy_from_slice(&padded::<32>(&self.volume_id));
+        descriptors.push(pvd);
+        if let Some(boot_record) = &self.boot_record {
+            let mut br = descriptor_header(DESCRIPTOR_TYPE_BOOT_RECORD);
+            br[7..39].copy_from_slice(&padded::<32>(&boot_record.boot_system_id));
+            br[39..71].copy_from_slice(&padded::<32>(&boot_record.boot_id));
+            descriptors.push(br);
+        }
+        if let Some(joliet_volume_id) = &self.joliet_volume_id {
+            let mut svd = volume_descriptor(DESCRIPTOR_TYPE_SUPPLEMENTARY, total_blocks, &root_record, le_path_table_block);
+            svd[8..40].copy_from_slice(&padded_ucs2::<32>(""));
+            svd[40..72].copy_from_slice(&padded_ucs2::<32>(joliet_volume_id));
+            svd[88..91].copy_from_slice(b"%/E"); // UCS-2 level 3
+            descriptors.push(svd);
+        }
+        descriptors.push(descriptor_header(DESCRIPTOR_TYPE_TERMINATOR));
+        for (descriptor, block) in descriptors.iter().zip(SYSTEM_AREA_BLOCKS..) {
+            bytes[block*BLOCK_SIZE..(block+1)*BLOCK_SIZE].copy_from_slice(descriptor);
+        }
+
+        bytes
+    }
+}
+
+/// A boot record of an ISO9660 volume.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct BootRecord {
+    /// The boot system identifier, padded with spaces to 32 bytes. El Torito bootable CDs store
+    /// `EL TORITO SPECIFICATION` here.
+    pub boot_system_id: String,
+
+    /// The boot identifier, padded with spaces to 32 bytes.
+    pub boot_id: String,
+}
+
+
+/// A file in the root directory of an ISO9660 volume.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct File {
+    /// The name of the file, without the version suffix (`;1`), which is appended automatically.
+    pub name: String,
+
+    pub data: Vec<u8>,
+}
+
+
+fn descriptor_header(descriptor_type: u8) -> Vec<u8> {
+    let mut descriptor = vec![0u8; BLOCK_SIZE];
+    descriptor[0] = descriptor_type;
+    descriptor[1..6].copy_from_slice(b"CD001");
+    descriptor[6] = 1; // version
+    descriptor
+}
+
+/// Returns a primary or supplementary volume descriptor with ASCII system and volume identifiers
+/// consisting only of spaces.
+fn volume_descriptor(descriptor_type: u8, total_blocks: usize, root_record: &[u8], le_path_table_block: usize) -> Vec<u8> {
+    let mut vd = descriptor_header(descriptor_type);
+    vd[8..40].copy_from_slice(&padded::<32>(""));
+    vd[40..72].copy_from_slice(&padded::<32>(""));
+    vd[80..88].copy_from_slice(&both_endian_u32(total_blocks));
+    vd[120..124].copy_from_slice(&both_endian_u16(1)); // volume set size
+    vd[124..128].copy_from_slice(&both_endian_u16(1)); // volume sequence number
+    vd[128..132].copy_from_slice(&both_endian_u16(BLOCK_SIZE));
+    vd[132..140].copy_from_slice(&both_endian_u32(PATH_TABLE_SIZE));
+    vd[140..144].copy_from_slice(&u32::try_from(le_path_table_block).unwrap().to_le_bytes());
+    vd[148..152].copy_from_slice(&u32::try_from(le_path_table_block + 1).unwrap().to_be_bytes());
+    vd[156..190].copy_from_slice(root_record);
+    vd[190..318].copy_from_slice(&padded::<128>("")); // volume set identifier
+    vd[318..446].copy_from_slice(&padded::<128>("")); // publisher identifier
+    vd[446..574].copy_from_slice(&padded::<128>("")); // data preparer identifier
+    vd[574..702].copy_from_slice(&padded::<128>("")); // application identifier
+    vd[702..739].copy_from_slice(&padded::<37>("")); // copyright file identifier
+    vd[739..776].copy_from_slice(&padded::<37>("")); // abstract file identifier
+    vd[776..813].copy_from_slice(&padded::<37>("")); // bibliographic file identifier
+    for timestamp_offset in [813, 830, 847, 864] {
+        // "not specified": sixteen zero digits and a zero time zone offset
+        vd[timestamp_offset..timestamp_offset+16].copy_from_slice(b"0000000000000000");
+    }
+    vd[881] = 1; // file structure version
+    vd
+}
+
+fn record_length(identifier: &[u8]) -> usize {
+    // the identifier is padded to an even length
+    33 + identifier.len() + (1 - identifier.len() % 2)
+}
+
+/// Returns the position after appending a record of the given length at `position`, taking into
+/// account that records may not cross block boundaries.
+fn append_position(position: usize, length: usize) -> usize {
+    if position % BLOCK_SIZE + length > BLOCK_SIZE {
+        position.next_multiple_of(BLOCK_SIZE) + length
+    } else {
+        position + length
+    }
+}
+
+fn append_record(directory: &mut Vec<u8>, record: &[u8]) {
+    let new_length = append_position(directory.len(), record.len());
+    directory.resize(new_length - record.len(), 0x00);
+    directory.extend_from_slice(record);
+}
+
+fn directory_record(identifier: &[u8], block: usize, data_length: usize, is_directory: bool) -> Vec<u8> {
+    let length = record_length(identifier);
+    let mut record = vec![0u8; length];
+    record[0] = u8::try_from(length).expect("file name is too long");
+    record[2..10].copy_from_slice(&both_endian_u32(block));
+    record[10..18].copy_from_slice(&both_endian_u32(data_length));
+    // recording timestamp (18..25) remains unspecified
+    if is_directory {
+        record[25] = FILE_FLAGS_DIRECTORY;
+    }
+    record[28..32].copy_from_slice(&both_endian_u16(1)); // volume sequence number
+    record[32] = u8::try_from(identifier.len()).unwrap();
+    record[33..33+identifier.len()].copy_from_slice(identifier);
+    record
+}
+
+fn both_endian_u16(value: usize) -> [u8; 4] {
+    let value = u16::try_from(value).unwrap();
+    let mut bytes = [0u8; 4];
+    bytes[0..2].copy_from_slice(&value.to_le_bytes());
+    bytes[2..4].copy_from_slice(&value.to_be_bytes());
+    bytes
+}
+
+fn both_endian_u32(value: usize) -> [u8; 8] {
+    let value = u32::try_from(value).expect("ISO9660 image is too large");
+    let mut bytes = [0u8; 8];
+    bytes[0..4].copy_from_slice(&value.to_le_bytes());
+    bytes[4..8].copy_from_slice(&value.to_be_bytes());
+    bytes
+}
+
+fn padded<const N: usize>(value: &str) -> [u8; N] {
+    assert!(value.len() <= N, "{:?} is longer than {} bytes", value, N);
+    let mut bytes = [b' '; N];
+    bytes[..value.len()].copy_from_slice(value.as_bytes());
+    bytes
+}
+
+fn padded_ucs2<const N: usize>(value: &str) -> [u8; N] {
+    let mut bytes = [0u8; N];
+    for chunk in bytes.chunks_exact_mut(2) {
+        chunk.copy_from_slice(&0x0020u16.to_be_bytes());
+    }
+    let mut length = 0;
+    for unit in value.encode_utf16() {
+        assert!(length + 2 <= N, "{:?} is longer than {} bytes in UCS-2", value, N);
+        bytes[length..length+2].copy_from_slice(&unit.to_be_bytes());
+        length += 2;
+    }
+    bytes
+}
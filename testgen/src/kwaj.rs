@@ -0,0 +1,75 @@
+//! Wraps data in the "KWAJ" format used by `EXPAND.EXE` from MS-DOS 6 onward.
+
+
+/// How the data is stored in a KWAJ file.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Method {
+    /// The data is stored verbatim (method 0).
+    Stored,
+
+    /// Every byte of the data is XORed with 0xFF (method 1).
+    Xored,
+
+    /// The data is compressed using the LZSS variant of SZDD (method 2; see [`crate::szdd::lzss`]).
+    Lzss,
+}
+impl Method {
+    fn to_base_type(self) -> u16 {
+        match self {
+            Self::Stored => 0x0000,
+            Self::Xored => 0x0001,
+            Self::Lzss => 0x0002,
+        }
+    }
+}
+
+
+const HEADER_FLAG_DECOMPRESSED_LENGTH: u16 = 0x0001;
+const HEADER_FLAG_FILE_NAME: u16 = 0x0008;
+const HEADER_FLAG_FILE_EXTENSION: u16 = 0x0010;
+
+
+/// Returns a KWAJ file containing `data`.
+///
+/// The decompressed length is always recorded in the header. If `file_name` is given, it is split
+/// at the last dot into the name (at most 8 bytes) and extension (at most 3 bytes) fields.
+pub fn compress(data: &[u8], method: Method, file_name: Option<&str>) -> Vec<u8> {
+    let (name, extension) = match file_name {
+        Some(file_name) => match file_name.rsplit_once('.') {
+            Some((name, extension)) => (Some(name), Some(extension)),
+            None => (Some(file_name), None),
+        },
+        None => (None, None),
+    };
+    assert!(name.is_none_or(|n| n.len() <= 8), "KWAJ file name is longer than 8 bytes");
+    assert!(extension.is_none_or(|e| e.len() <= 3), "KWAJ file extension is longer than 3 bytes");
+
+    let mut header_flags = HEADER_FLAG_DECOMPRESSED_LENGTH;
+    let mut optional_header = Vec::new();
+    optional_header.extend_from_slice(&u32::try_from(data.len()).unwrap().to_le_bytes());
+    if let Some(name) = name {
+        header_flags |= HEADER_FLAG_FILE_NAME;
+        optional_header.extend_from_slice(name.as_bytes());
+        optional_header.push(0x00);
+    }
+    if let Some(extension) = extension {
+        header_flags |= HEADER_FLAG_FILE_EXTENSION;
+        optional_header.extend_from_slice(extension.as_bytes());
+        optional_header.push(0x00);
+    }
+
+    let data_offset = u16::try_from(14 + optional_header.len()).unwrap();
+
+    let mut bytes = Vec::with_capacity(usize::from(data_offset) + data.len());
+    bytes.extend_from_slice(b"KWAJ\x88\xF0\x27\xD1");
+    bytes.extend_from_slice(&method.to_base_type().to_le_bytes());
+    bytes.extend_from_slice(&data_offset.to_le_bytes());
+    bytes.extend_from_slice(&header_flags.to_le_bytes());
+    bytes.extend_from_slice(&optional_header);
+    match method {
+        Method::Stored => bytes.extend_from_slice(data),
+        Method::Xored => bytes.extend(data.iter().map(|b| *b ^ 0xFF)),
+        Method::Lzss => bytes.extend_from_slice(&crate::szdd::lzss(data)),
+    }
+    bytes
+}
@@ -0,0 +1,26 @@
+//! Synthesizes tiny but valid NE and PE files, compressed wrappers around them, cabinet sets, DOS
+//! self-extracting archives, tarballs, and floppy disk and CD-ROM images containing them, as well
+//! as XPRESS-compressed data, registry hives and CLR metadata.
+//!
+//! Tests use these instead of real Microsoft binaries, which cannot be redistributed. The files
+//! contain only as much structure as the readers in this workspace need: headers, one code
+//! section or segment, and the export, import and resource tables that were asked for.
+//!
+//! The benchmarks use the same files as fixtures, along with the helpers in [`memory`] to measure
+//! how much memory the parsers need.
+
+
+pub mod cab;
+pub mod clr;
+pub mod fat;
+pub mod iso9660;
+pub mod kwaj;
+pub mod memory;
+mod mz;
+pub mod ne;
+pub mod pe;
+pub mod regf;
+pub mod sfx;
+pub mod szdd;
+pub mod tar;
+pub mod xpress;
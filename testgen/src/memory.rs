@@ -0,0 +1,63 @@
+//! Measures the peak memory usage of benchmarked code.
+//!
+//! Criterion only measures time; whether a parser holds on to copies of its input (or reads whole
+//! files where it could stream them) shows up in the resident set size of the process instead. The
+//! peak is read from `/proc/self/status`, so the measurements are only available on Linux.
+
+
+use std::fs;
+
+
+/// Returns the value of a field of `/proc/self/status` given in kilobytes, in bytes.
+fn status_bytes(field: &str) -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines()
+        .find_map(|l| l.strip_prefix(field)?.strip_prefix(':'))?;
+    let kilobytes: u64 = line.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// The current resident set size of this process in bytes, if it can be determined.
+pub fn current_rss() -> Option<u64> {
+    status_bytes("VmRSS")
+}
+
+/// The peak resident set size of this process in bytes, if it can be determined.
+pub fn peak_rss() -> Option<u64> {
+    status_bytes("VmHWM")
+}
+
+/// Resets the peak resident set size to the current one, so that [`peak_rss`] only reflects what
+/// happens afterwards. Returns whether this was possible.
+pub fn reset_peak_rss() -> bool {
+    // supported since Linux 4.0
+    fs::write("/proc/self/clear_refs", b"5").is_ok()
+}
+
+/// Runs `f` once and returns its result along with the number of bytes by which the peak resident
+/// set size exceeded the resident set size before the call.
+///
+/// The growth is `None` if the peak cannot be measured or reset. Memory that `f` frees before
+/// returning is included, which is the point: a parser that temporarily copies its whole input
+/// shows up here even if the copy does not outlive the call.
+pub fn peak_rss_growth<T, F: FnOnce() -> T>(f: F) -> (T, Option<u64>) {
+    let can_measure = reset_peak_rss();
+    let before = current_rss();
+    let result = f();
+    let growth = match (can_measure, before, peak_rss()) {
+        (true, Some(before), Some(peak)) => Some(peak.saturating_sub(before)),
+        _ => None,
+    };
+    (result, growth)
+}
+
+/// Runs `f` once via [`peak_rss_growth`] and reports the growth on standard error under the given
+/// name, in the style of Criterion's output.
+pub fn report_peak_rss_growth<T, F: FnOnce() -> T>(name: &str, f: F) -> T {
+    let (result, growth) = peak_rss_growth(f);
+    match growth {
+        Some(bytes) => eprintln!("{:<40} peak RSS growth: {} KiB", name, bytes / 1024),
+        None => eprintln!("{:<40} peak RSS growth: not measurable on this platform", name),
+    }
+    result
+}
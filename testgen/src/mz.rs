@@ -0,0 +1,20 @@
+/// The offset at which the new-style header follows the MZ header.
+pub(crate) const NEW_HEADER_OFFSET: usize = 0x40;
+
+
+/// Returns an MZ header without a stub which points to a new-style header directly after it.
+///
+/// `file_size` is the total size of the file, which is needed to fill in the page counts.
+pub(crate) fn header(file_size: usize) -> [u8; NEW_HEADER_OFFSET] {
+    let mut bytes = [0u8; NEW_HEADER_OFFSET];
+    let last_page_bytes = u16::try_from(file_size % 512).unwrap();
+    let pages = u16::try_from(file_size.div_ceil(512)).unwrap_or(u16::MAX);
+
+    bytes[0..2].copy_from_slice(b"MZ");
+    bytes[2..4].copy_from_slice(&last_page_bytes.to_le_bytes());
+    bytes[4..6].copy_from_slice(&pages.to_le_bytes());
+    bytes[8..10].copy_from_slice(&4u16.to_le_bytes()); // header size in paragraphs
+    bytes[24..26].copy_from_slice(&0x0040u16.to_le_bytes()); // relocation table offset
+    bytes[60..64].copy_from_slice(&u32::try_from(NEW_HEADER_OFFSET).unwrap().to_le_bytes());
+    bytes
+}
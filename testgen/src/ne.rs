@@ -0,0 +1,245 @@
+//! Synthesizes 16-bit NE dynamic-link libraries.
+
+
+use std::collections::BTreeMap;
+
+use crate::mz::NEW_HEADER_OFFSET;
+
+
+const HEADER_SIZE: usize = 64;
+const SEGMENT_TABLE_ENTRY_SIZE: usize = 8;
+const ALIGNMENT_SHIFT_COUNT: u16 = 4;
+
+const FLAGS_LIBRARY_MODULE: u16 = 0x8000;
+const SEGMENT_FLAGS_PRELOAD: u16 = 0x0040;
+const ENTRY_FLAGS_EXPORTED: u8 = 0x01;
+const RESOURCE_FLAGS_MOVEABLE_PURE: u16 = 0x0030;
+const TARGET_OS_WINDOWS: u8 = 0x02;
+
+
+/// An NE dynamic-link library to be synthesized.
+///
+/// The library consists of a single code segment. Each exported entry point lies at offset
+/// `2 * (ordinal - 1)` within that segment.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct NewExecutable {
+    /// The name of the module, stored as the first entry of the resident-name table.
+    pub module_name: String,
+
+    /// The description of the module, stored as the first entry of the nonresident-name table.
+    pub description: String,
+
+    /// The exported entry points.
+    pub exports: Vec<Export>,
+
+    /// The resources.
+    pub resources: Vec<Resource>,
+}
+impl NewExecutable {
+    /// Creates a description of a library without exports or resources.
+    pub fn new<N: Into<String>>(module_name: N) -> Self {
+        Self {
+            module_name: module_name.into(),
+            description: String::new(),
+            exports: Vec::new(),
+            resources: Vec::new(),
+        }
+    }
+
+    /// Assembles the library into the bytes of an NE file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut ordinal_to_export = BTreeMap::new();
+        for export in &self.exports {
+            assert_ne!(export.ordinal, 0, "export ordinals start at 1");
+            let previous = ordinal_to_export.insert(export.ordinal, export);
+            assert!(previous.is_none(), "duplicate export ordinal {}", export.ordinal);
+        }
+        let max_ordinal = ordinal_to_export.keys().last().copied().unwrap_or(0);
+        let segment_length = usize::from(max_ordinal).max(1) * 2;
+
+        let mut resident_name_table = Vec::new();
+        write_name_table_entry(&mut resident_name_table, &self.module_name, 0);
+        let mut non_resident_name_table = Vec::new();
+        write_name_table_entry(&mut non_resident_name_table, &self.description, 0);
+        for export in ordinal_to_export.values() {
+            let Some(name) = &export.name else { continue };
+            let table = if export.resident { &mut resident_name_table } else { &mut non_resident_name_table };
+            write_name_table_entry(table, name, export.ordinal);
+        }
+        resident_name_table.push(0x00);
+        non_resident_name_table.push(0x00);
+
+        let entry_table = entry_table(&ordinal_to_export);
+
+        // the resource table needs to know where the data ends up, so lay out the tables first
+        let resource_table_size = if self.resources.is_empty() {
+            0
+        } else {
+            let type_count = self.resources_by_type().len();
+            2 + type_count*8 + self.resources.len()*12 + 2 + 1
+        };
+        let segment_table_offset = HEADER_SIZE;
+        let resource_table_offset = segment_table_offset + SEGMENT_TABLE_ENTRY_SIZE;
+        let resident_name_table_offset = resource_table_offset + resource_table_size;
+        let module_reference_table_offset = resident_name_table_offset + resident_name_table.len();
+        let imported_names_table_offset = module_reference_table_offset;
+        let entry_table_offset = imported_names_table_offset + 1;
+        let non_resident_name_table_offset = NEW_HEADER_OFFSET + entry_table_offset + entry_table.len();
+        let tables_end = non_resident_name_table_offset + non_resident_name_table.len();
+
+        let alignment = 1usize << ALIGNMENT_SHIFT_COUNT;
+        let mut bytes = vec![0u8; tables_end.next_multiple_of(alignment)];
+
+        // segment data; filled with RETF instructions
+        let segment_offset = bytes.len();
+        bytes.extend(std::iter::repeat_n(0xCB, segment_length));
+        bytes.resize(bytes.len().next_multiple_of(alignment), 0x00);
+
+        // resource data
+        let mut resource_table = Vec::with_capacity(resource_table_size);
+        if !self.resources.is_empty() {
+            resource_table.extend_from_slice(&ALIGNMENT_SHIFT_COUNT.to_le_bytes());
+            for (type_id, resources) in self.resources_by_type() {
+                resource_table.extend_from_slice(&(0x8000 | type_id).to_le_bytes());
+                resource_table.extend_from_slice(&u16::try_from(resources.len()).unwrap().to_le_bytes());
+                resource_table.extend_from_slice(&0u32.to_le_bytes());
+                for resource in resources {
+                    let data_offset = bytes.len();
+                    bytes.extend_from_slice(&resource.data);
+                    bytes.resize(bytes.len().next_multiple_of(alignment), 0x00);
+                    let length_units = resource.data.len().div_ceil(alignment);
+
+                    resource_table.extend_from_slice(&units(data_offset).to_le_bytes());
+                    resource_table.extend_from_slice(&u16::try_from(length_units).unwrap().to_le_bytes());
+                    resource_table.extend_from_slice(&RESOURCE_FLAGS_MOVEABLE_PURE.to_le_bytes());
+                    resource_table.extend_from_slice(&(0x8000 | resource.name_id).to_le_bytes());
+                    resource_table.extend_from_slice(&0u32.to_le_bytes());
+                }
+            }
+            resource_table.extend_from_slice(&0u16.to_le_bytes()); // end of types
+            resource_table.push(0x00); // end of names
+        }
+        assert_eq!(resource_table.len(), resource_table_size);
+
+        let mz_header = crate::mz::header(bytes.len());
+        bytes[0..NEW_HEADER_OFFSET].copy_from_slice(&mz_header);
+
+        let ne = &mut bytes[NEW_HEADER_OFFSET..];
+        ne[0..2].copy_from_slice(b"NE");
+        ne[2] = 5; // linker version
+        ne[3] = 10; // linker revision
+        ne[4..6].copy_from_slice(&table_offset(entry_table_offset).to_le_bytes());
+        ne[6..8].copy_from_slice(&u16::try_from(entry_table.len()).unwrap().to_le_bytes());
+        ne[12..14].copy_from_slice(&FLAGS_LIBRARY_MODULE.to_le_bytes());
+        ne[28..30].copy_from_slice(&1u16.to_le_bytes()); // segment count
+        ne[32..34].copy_from_slice(&u16::try_from(non_resident_name_table.len()).unwrap().to_le_bytes());
+        ne[34..36].copy_from_slice(&table_offset(segment_table_offset).to_le_bytes());
+        ne[36..38].copy_from_slice(&table_offset(resource_table_offset).to_le_bytes());
+        ne[38..40].copy_from_slice(&table_offset(resident_name_table_offset).to_le_bytes());
+        ne[40..42].copy_from_slice(&table_offset(module_reference_table_offset).to_le_bytes());
+        ne[42..44].copy_from_slice(&table_offset(imported_names_table_offset).to_le_bytes());
+        ne[44..48].copy_from_slice(&u32::try_from(non_resident_name_table_offset).unwrap().to_le_bytes());
+        ne[50..52].copy_from_slice(&ALIGNMENT_SHIFT_COUNT.to_le_bytes());
+        ne[52..54].copy_from_slice(&u16::try_from(self.resources.len()).unwrap().to_le_bytes());
+        ne[54] = TARGET_OS_WINDOWS;
+        ne[62..64].copy_from_slice(&0x030Au16.to_le_bytes()); // expected Windows version
+
+        let segment_entry = &mut ne[segment_table_offset..segment_table_offset+SEGMENT_TABLE_ENTRY_SIZE];
+        let segment_length_u16 = u16::try_from(segment_length).unwrap();
+        segment_entry[0..2].copy_from_slice(&units(segment_offset).to_le_bytes());
+        segment_entry[2..4].copy_from_slice(&segment_length_u16.to_le_bytes());
+        segment_entry[4..6].copy_from_slice(&SEGMENT_FLAGS_PRELOAD.to_le_bytes());
+        segment_entry[6..8].copy_from_slice(&segment_length_u16.to_le_bytes());
+
+        ne[resource_table_offset..resident_name_table_offset].copy_from_slice(&resource_table);
+        ne[resident_name_table_offset..module_reference_table_offset].copy_from_slice(&resident_name_table);
+        // the imported-names table only consists of the empty string at its beginning
+        ne[entry_table_offset..entry_table_offset+entry_table.len()].copy_from_slice(&entry_table);
+        bytes[non_resident_name_table_offset..tables_end].copy_from_slice(&non_resident_name_table);
+
+        bytes
+    }
+
+    fn resources_by_type(&self) -> BTreeMap<u16, Vec<&Resource>> {
+        let mut type_to_resources: BTreeMap<u16, Vec<&Resource>> = BTreeMap::new();
+        for resource in &self.resources {
+            assert!(resource.type_id & 0x8000 == 0, "resource type ID {} is too large", resource.type_id);
+            assert!(resource.name_id & 0x8000 == 0, "resource name ID {} is too large", resource.name_id);
+            type_to_resources.entry(resource.type_id).or_default().push(resource);
+        }
+        type_to_resources
+    }
+}
+
+/// An entry point exported by an NE library.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Export {
+    pub ordinal: u16,
+
+    /// The name of the entry point; `None` if it is only exported by ordinal.
+    pub name: Option<String>,
+
+    /// Whether the name is stored in the resident-name table instead of the nonresident-name
+    /// table.
+    pub resident: bool,
+}
+
+/// A resource stored in an NE library.
+///
+/// Only numeric identifiers below 0x8000 are supported. The data is padded with zero bytes to the
+/// alignment of the resource table (16 bytes).
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Resource {
+    pub type_id: u16,
+    pub name_id: u16,
+    pub data: Vec<u8>,
+}
+
+
+fn write_name_table_entry(table: &mut Vec<u8>, name: &str, ordinal: u16) {
+    table.push(u8::try_from(name.len()).expect("name is longer than 255 bytes"));
+    table.extend_from_slice(name.as_bytes());
+    table.extend_from_slice(&ordinal.to_le_bytes());
+}
+
+/// Assembles the entry table, with all exports in fixed bundles for segment 1.
+fn entry_table(ordinal_to_export: &BTreeMap<u16, &Export>) -> Vec<u8> {
+    let max_ordinal = ordinal_to_export.keys().last().copied().unwrap_or(0);
+    let mut table = Vec::new();
+    let mut ordinal = 1;
+    while ordinal <= max_ordinal {
+        // find the run of ordinals that are all present or all absent
+        let present = ordinal_to_export.contains_key(&ordinal);
+        let mut run_length: u8 = 0;
+        while run_length < u8::MAX
+            && ordinal + u16::from(run_length) <= max_ordinal
+            && ordinal_to_export.contains_key(&(ordinal + u16::from(run_length))) == present
+        {
+            run_length += 1;
+        }
+
+        table.push(run_length);
+        if present {
+            table.push(1); // segment number
+            for run_ordinal in ordinal..ordinal + u16::from(run_length) {
+                table.push(ENTRY_FLAGS_EXPORTED);
+                table.extend_from_slice(&(2 * (run_ordinal - 1)).to_le_bytes());
+            }
+        } else {
+            table.push(0x00); // unused entries
+        }
+        ordinal += u16::from(run_length);
+    }
+    table.push(0x00);
+    table
+}
+
+/// Converts an offset relative to the NE header into the format stored in the NE header.
+fn table_offset(offset: usize) -> u16 {
+    u16::try_from(offset).expect("NE tables are larger than 64 KiB")
+}
+
+/// Converts a file offset into units of the alignment.
+fn units(offset: usize) -> u16 {
+    u16::try_from(offset >> ALIGNMENT_SHIFT_COUNT).expect("NE file is too large")
+}
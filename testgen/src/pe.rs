@@ -0,0 +1,541 @@
+//! Synthesizes PE dynamic-link libraries, optionally with imports and bound imports.
+
+
+use std::collections::BTreeMap;
+
+use crate::mz::NEW_HEADER_OFFSET;
+
+
+/// The relative virtual address of the code section.
+///
+/// Exported code addresses must not be below this; the code section grows to cover the highest one.
+pub const TEXT_RVA: u32 = 0x1000;
+
+const PE32_OPTIONAL_HEADER_SIZE: usize = 224;
+const PE32_PLUS_OPTIONAL_HEADER_SIZE: usize = 240;
+const FILE_ALIGNMENT: usize = 0x200;
+const SECTION_ALIGNMENT: u32 = 0x1000;
+const IMAGE_BASE: u32 = 0x1000_0000;
+
+const CODE_CHARACTERISTICS: u32 = 0x6000_0020; // code, execute, read
+const DATA_CHARACTERISTICS: u32 = 0x4000_0040; // initialized data, read
+
+const DIRECTORY_EXPORT: usize = 0;
+const DIRECTORY_IMPORT: usize = 1;
+const DIRECTORY_RESOURCE: usize = 2;
+const DIRECTORY_BOUND_IMPORT: usize = 11;
+
+/// The timestamp of import descriptors whose imports are bound via the bound import directory.
+const NEW_STYLE_BINDING_TIMESTAMP: u32 = 0xFFFF_FFFF;
+
+
+/// A PE dynamic-link library to be synthesized.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct PortableExecutable {
+    /// The machine type in the COFF header, e.g. 0x014C for i386.
+    ///
+    /// The format of the optional header is chosen by `pe32_plus`, regardless of the machine type.
+    pub machine_type: u16,
+
+    /// Whether the optional header is in PE32+ format, with 64-bit import lookup table entries,
+    /// instead of PE32 format.
+    pub pe32_plus: bool,
+
+    /// The name of the module, as stored in the export directory.
+    pub module_name: String,
+
+    /// The time stamp in the COFF header and the export directory.
+    pub time_date_stamp: u32,
+
+    /// The exported symbols. If there are none, the export directory is omitted.
+    pub exports: Vec<Export>,
+
+    /// The resources. If there are none, the resource directory is omitted.
+    pub resources: Vec<Resource>,
+
+    /// The imported libraries. If there are none, the import directory is omitted.
+    pub imports: Vec<Import>,
+
+    /// The entries of the bound import directory, which is placed after the section table. If
+    /// there are none, the directory is omitted; otherwise, the imports from the libraries it
+    /// mentions are marked as bound.
+    pub bound_imports: Vec<BoundImport>,
+}
+impl PortableExecutable {
+    /// Creates a description of an i386 library without exports, resources or imports.
+    pub fn new<N: Into<String>>(module_name: N) -> Self {
+        Self {
+            machine_type: 0x014C,
+            pe32_plus: false,
+            module_name: module_name.into(),
+            time_date_stamp: 0,
+            exports: Vec::new(),
+            resources: Vec::new(),
+            imports: Vec::new(),
+            bound_imports: Vec::new(),
+        }
+    }
+
+    /// Assembles the library into the bytes of a PE file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut sections = Vec::new();
+
+        // the code section; filled with RET instructions
+        let code_end = self.exports
+            .iter()
+            .filter_map(|e| match &e.target {
+                ExportTarget::Code { rva } => {
+                    assert!(*rva >= TEXT_RVA, "exported code address {:#010X} is below TEXT_RVA", rva);
+                    Some(*rva + 1)
+                },
+                ExportTarget::Forwarder { .. } => None,
+            })
+            .max()
+            .unwrap_or(TEXT_RVA + 1);
+        let code_size = usize::try_from(code_end - TEXT_RVA).unwrap();
+        sections.push(Section {
+            name: *b".text\0\0\0",
+            rva: TEXT_RVA,
+            data: vec![0xC3; code_size],
+            characteristics: CODE_CHARACTERISTICS,
+        });
+
+        let mut directories = [(0u32, 0u32); 16];
+        if !self.exports.is_empty() {
+            let rva = next_section_rva(&sections);
+            let data = self.export_section(rva);
+            directories[DIRECTORY_EXPORT] = (rva, u32::try_from(data.len()).unwrap());
+            sections.push(Section {
+                name: *b".edata\0\0",
+                rva,
+                data,
+                characteristics: DATA_CHARACTERISTICS,
+            });
+        }
+        if !self.resources.is_empty() {
+            let rva = next_section_rva(&sections);
+            let data = self.resource_section(rva);
+            directories[DIRECTORY_RESOURCE] = (rva, u32::try_from(data.len()).unwrap());
+            sections.push(Section {
+                name: *b".rsrc\0\0\0",
+                rva,
+                data,
+                characteristics: DATA_CHARACTERISTICS,
+            });
+        }
+
+        if !self.imports.is_empty() {
+            let rva = next_section_rva(&sections);
+            let data = self.import_section(rva);
+            let descriptors_size = 20 * (self.imports.len() + 1);
+            directories[DIRECTORY_IMPORT] = (rva, u32::try_from(descriptors_size).unwrap());
+            sections.push(Section {
+                name: *b".idata\0\0",
+                rva,
+                data,
+                characteristics: DATA_CHARACTERISTICS,
+            });
+        }
+
+        let optional_header_size = if self.pe32_plus { PE32_PLUS_OPTIONAL_HEADER_SIZE } else { PE32_OPTIONAL_HEADER_SIZE };
+        let section_table_offset = NEW_HEADER_OFFSET + 4 + 20 + optional_header_size;
+        let section_table_end = section_table_offset + 40 * sections.len();
+        let bound_import_directory = self.bound_import_directory();
+        if !bound_import_directory.is_empty() {
+            directories[DIRECTORY_BOUND_IMPORT] = (
+                u32::try_from(section_table_end).unwrap(),
+                u32::try_from(bound_import_directory.len()).unwrap(),
+            );
+        }
+        let headers_size = (section_table_end + bound_import_directory.len()).next_multiple_of(FILE_ALIGNMENT);
+        let mut bytes = vec![0u8; headers_size];
+        bytes[section_table_end..section_table_end+bound_import_directory.len()]
+            .copy_from_slice(&bound_import_directory);
+
+        // PE header
+        let pe = &mut bytes[NEW_HEADER_OFFSET..];
+        pe[0..4].copy_from_slice(b"PE\0\0");
+        pe[4..6].copy_from_slice(&self.machine_type.to_le_bytes());
+        pe[6..8].copy_from_slice(&u16::try_from(sections.len()).unwrap().to_le_bytes());
+        pe[8..12].copy_from_slice(&self.time_date_stamp.to_le_bytes());
+        pe[20..22].copy_from_slice(&u16::try_from(optional_header_size).unwrap().to_le_bytes());
+        let characteristics: u16 = if self.pe32_plus {
+            0x2022 // executable, large address aware, DLL
+        } else {
+            0x2102 // executable, 32-bit, DLL
+        };
+        pe[22..24].copy_from_slice(&characteristics.to_le_bytes());
+
+        // optional header; PE32+ drops the base of data and widens the image base and the stack
+        // and heap sizes, moving the fields after them back by 16 bytes
+        let image_size = next_section_rva(&sections);
+        let opt = &mut pe[24..24+optional_header_size];
+        if self.pe32_plus {
+            opt[0..2].copy_from_slice(&0x020Bu16.to_le_bytes());
+            opt[24..32].copy_from_slice(&u64::from(IMAGE_BASE).to_le_bytes());
+        } else {
+            opt[0..2].copy_from_slice(&0x010Bu16.to_le_bytes());
+            opt[28..32].copy_from_slice(&IMAGE_BASE.to_le_bytes());
+        }
+        opt[32..36].copy_from_slice(&SECTION_ALIGNMENT.to_le_bytes());
+        opt[36..40].copy_from_slice(&u32::try_from(FILE_ALIGNMENT).unwrap().to_le_bytes());
+        opt[40..42].copy_from_slice(&4u16.to_le_bytes()); // operating system version
+        opt[48..50].copy_from_slice(&4u16.to_le_bytes()); // subsystem version
+        opt[56..60].copy_from_slice(&image_size.to_le_bytes());
+        opt[60..64].copy_from_slice(&u32::try_from(headers_size).unwrap().to_le_bytes());
+        opt[68..70].copy_from_slice(&2u16.to_le_bytes()); // Windows GUI subsystem
+        let directories_offset = optional_header_size - 8*directories.len();
+        opt[directories_offset-4..directories_offset].copy_from_slice(&16u32.to_le_bytes()); // number of data directory entries
+        for (i, (address, size)) in directories.iter().enumerate() {
+            let entry_offset = directories_offset + 8*i;
+            opt[entry_offset..entry_offset+4].copy_from_slice(&address.to_le_bytes());
+            opt[entry_offset+4..entry_offset+8].copy_from_slice(&size.to_le_bytes());
+        }
+
+        // sections
+        for (i, section) in sections.iter().enumerate() {
+            let raw_pointer = bytes.len();
+            bytes.extend_from_slice(&section.data);
+            bytes.resize(bytes.len().next_multiple_of(FILE_ALIGNMENT), 0x00);
+
+            let header_offset = section_table_offset + 40*i;
+            let header = &mut bytes[header_offset..header_offset+40];
+            header[0..8].copy_from_slice(&section.name);
+            header[8..12].copy_from_slice(&u32::try_from(section.data.len()).unwrap().to_le_bytes());
+            header[12..16].copy_from_slice(&section.rva.to_le_bytes());
+            header[16..20].copy_from_slice(&u32::try_from(section.data.len().next_multiple_of(FILE_ALIGNMENT)).unwrap().to_le_bytes());
+            header[20..24].copy_from_slice(&u32::try_from(raw_pointer).unwrap().to_le_bytes());
+            header[36..40].copy_from_slice(&section.characteristics.to_le_bytes());
+        }
+
+        let mz_header = crate::mz::header(bytes.len());
+        bytes[0..NEW_HEADER_OFFSET].copy_from_slice(&mz_header);
+        bytes
+    }
+
+    /// Assembles the export directory and the tables it points to.
+    fn export_section(&self, section_rva: u32) -> Vec<u8> {
+        let ordinal_base = self.exports.iter().map(|e| e.ordinal).min().unwrap();
+        let ordinal_max = self.exports.iter().map(|e| e.ordinal).max().unwrap();
+        let address_count = usize::try_from(ordinal_max - ordinal_base + 1).unwrap();
+
+        // the name pointer table must be sorted for binary search
+        let mut name_to_ordinal = BTreeMap::new();
+        for export in &self.exports {
+            if let Some(name) = &export.name {
+                let previous = name_to_ordinal.insert(name.as_bytes(), export.ordinal);
+                assert!(previous.is_none(), "duplicate export name {:?}", name);
+            }
+        }
+
+        let rva_of = |offset: usize| section_rva + u32::try_from(offset).unwrap();
+
+        let address_table_offset = 40;
+        let name_pointer_offset = address_table_offset + 4*address_count;
+        let ordinal_table_offset = name_pointer_offset + 4*name_to_ordinal.len();
+        let strings_offset = ordinal_table_offset + 2*name_to_ordinal.len();
+
+        let mut strings = Vec::new();
+        let mut add_string = |string: &[u8]| {
+            let offset = strings_offset + strings.len();
+            strings.extend_from_slice(string);
+            strings.push(0x00);
+            offset
+        };
+
+        let module_name_offset = add_string(self.module_name.as_bytes());
+
+        let mut address_table = vec![0u32; address_count];
+        for export in &self.exports {
+            let index = usize::try_from(export.ordinal - ordinal_base).unwrap();
+            assert_eq!(address_table[index], 0, "duplicate export ordinal {}", export.ordinal);
+            address_table[index] = match &export.target {
+                ExportTarget::Code { rva } => *rva,
+                ExportTarget::Forwarder { target } => rva_of(add_string(target.as_bytes())),
+            };
+        }
+
+        let mut name_pointers = Vec::with_capacity(name_to_ordinal.len());
+        let mut name_ordinals = Vec::with_capacity(name_to_ordinal.len());
+        for (name, ordinal) in &name_to_ordinal {
+            name_pointers.push(rva_of(add_string(name)));
+            name_ordinals.push(u16::try_from(*ordinal - ordinal_base).unwrap());
+        }
+
+        let mut bytes = vec![0u8; strings_offset];
+        bytes[4..8].copy_from_slice(&self.time_date_stamp.to_le_bytes());
+        bytes[12..16].copy_from_slice(&rva_of(module_name_offset).to_le_bytes());
+        bytes[16..20].copy_from_slice(&ordinal_base.to_le_bytes());
+        bytes[20..24].copy_from_slice(&u32::try_from(address_count).unwrap().to_le_bytes());
+        bytes[24..28].copy_from_slice(&u32::try_from(name_to_ordinal.len()).unwrap().to_le_bytes());
+        bytes[28..32].copy_from_slice(&rva_of(address_table_offset).to_le_bytes());
+        if !name_to_ordinal.is_empty() {
+            bytes[32..36].copy_from_slice(&rva_of(name_pointer_offset).to_le_bytes());
+            bytes[36..40].copy_from_slice(&rva_of(ordinal_table_offset).to_le_bytes());
+        }
+        for (i, address) in address_table.iter().enumerate() {
+            let offset = address_table_offset + 4*i;
+            bytes[offset..offset+4].copy_from_slice(&address.to_le_bytes());
+        }
+        for (i, name_pointer) in name_pointers.iter().enumerate() {
+            let offset = name_pointer_offset + 4*i;
+            bytes[offset..offset+4].copy_from_slice(&name_pointer.to_le_bytes());
+        }
+        for (i, name_ordinal) in name_ordinals.iter().enumerate() {
+            let offset = ordinal_table_offset + 2*i;
+            bytes[offset..offset+2].copy_from_slice(&name_ordinal.to_le_bytes());
+        }
+        bytes.extend_from_slice(&strings);
+        bytes
+    }
+
+    /// Assembles the three-level (type, name, language) resource directory and the resource data.
+    fn resource_section(&self, section_rva: u32) -> Vec<u8> {
+        let mut tree: BTreeMap<u32, BTreeMap<u32, BTreeMap<u32, &[u8]>>> = BTreeMap::new();
+        for resource in &self.resources {
+            let previous = tree
+                .entry(resource.type_id).or_default()
+                .entry(resource.name_id).or_default()
+                .insert(resource.language_id, &resource.data);
+            assert!(
+                previous.is_none(),
+                "duplicate resource (type {}, name {}, language {})",
+                resource.type_id, resource.name_id, resource.language_id,
+            );
+        }
+
+        // lay out the directories breadth-first, then the data entries, then the data
+        let directory_size = |entry_count: usize| 16 + 8*entry_count;
+        let mut offset = directory_size(tree.len());
+        let mut type_directory_offsets = Vec::new();
+        for names in tree.values() {
+            type_directory_offsets.push(offset);
+            offset += directory_size(names.len());
+        }
+        let mut name_directory_offsets = Vec::new();
+        for languages in tree.values().flat_map(|names| names.values()) {
+            name_directory_offsets.push(offset);
+            offset += directory_size(languages.len());
+        }
+        let leaves: Vec<&[u8]> = tree.values()
+            .flat_map(|names| names.values())
+            .flat_map(|languages| languages.values())
+            .copied()
+            .collect();
+        let mut data_entry_offsets = Vec::new();
+        for _ in &leaves {
+            data_entry_offsets.push(offset);
+            offset += 16;
+        }
+        let mut data_offsets = Vec::new();
+        for data in &leaves {
+            offset = offset.next_multiple_of(4);
+            data_offsets.push(offset);
+            offset += data.len();
+        }
+
+        let mut bytes = vec![0u8; offset];
+        let write_directory = |bytes: &mut [u8], directory_offset: usize, entries: &[(u32, u32)]| {
+            bytes[directory_offset+14..directory_offset+16].copy_from_slice(&u16::try_from(entries.len()).unwrap().to_le_bytes());
+            for (i, (id, value)) in entries.iter().enumerate() {
+                let entry_offset = directory_offset + 16 + 8*i;
+                bytes[entry_offset..entry_offset+4].copy_from_slice(&id.to_le_bytes());
+                bytes[entry_offset+4..entry_offset+8].copy_from_slice(&value.to_le_bytes());
+            }
+        };
+        let subdirectory = |offset: usize| 0x8000_0000 | u32::try_from(offset).unwrap();
+
+        let root_entries: Vec<(u32, u32)> = tree.keys()
+            .zip(&type_directory_offsets)
+            .map(|(type_id, offset)| (*type_id, subdirectory(*offset)))
+            .collect();
+        write_directory(&mut bytes, 0, &root_entries);
+
+        let mut name_directory_index = 0;
+        let mut leaf_index = 0;
+        for (names, type_directory_offset) in tree.values().zip(&type_directory_offsets) {
+            let mut type_entries = Vec::with_capacity(names.len());
+            for (name_id, languages) in names {
+                let name_directory_offset = name_directory_offsets[name_directory_index];
+                name_directory_index += 1;
+                type_entries.push((*name_id, subdirectory(name_directory_offset)));
+
+                let mut name_entries = Vec::with_capacity(languages.len());
+                for (language_id, data) in languages {
+                    let data_entry_offset = data_entry_offsets[leaf_index];
+                    let data_offset = data_offsets[leaf_index];
+                    leaf_index += 1;
+                    name_entries.push((*language_id, u32::try_from(data_entry_offset).unwrap()));
+
+                    let data_rva = section_rva + u32::try_from(data_offset).unwrap();
+                    bytes[data_entry_offset..data_entry_offset+4].copy_from_slice(&data_rva.to_le_bytes());
+                    bytes[data_entry_offset+4..data_entry_offset+8].copy_from_slice(&u32::try_from(data.len()).unwrap().to_le_bytes());
+                    bytes[data_offset..data_offset+data.len()].copy_from_slice(data);
+                }
+                write_directory(&mut bytes, name_directory_offset, &name_entries);
+            }
+            write_directory(&mut bytes, *type_directory_offset, &type_entries);
+        }
+
+        bytes
+    }
+
+    /// Assembles the import descriptors and the lookup tables, address tables and names they
+    /// point to.
+    fn import_section(&self, section_rva: u32) -> Vec<u8> {
+        let rva_of = |offset: usize| section_rva + u32::try_from(offset).unwrap();
+
+        // descriptors, then per library the lookup table and address table, then the names
+        let entry_size = if self.pe32_plus { 8 } else { 4 };
+        let mut table_offsets = Vec::with_capacity(self.imports.len());
+        let mut offset = 20 * (self.imports.len() + 1);
+        for import in &self.imports {
+            let table_size = entry_size * (import.functions.len() + 1);
+            table_offsets.push((offset, offset + table_size));
+            offset += 2 * table_size;
+        }
+
+        let mut bytes = vec![0u8; offset];
+        for (i, (import, (lookup_offset, address_offset))) in self.imports.iter().zip(&table_offsets).enumerate() {
+            let name_offset = bytes.len();
+            bytes.extend_from_slice(import.module_name.as_bytes());
+            bytes.push(0x00);
+
+            let is_bound = self.bound_imports.iter()
+                .any(|b| b.module_name.eq_ignore_ascii_case(&import.module_name));
+            let time_date_stamp = if is_bound { NEW_STYLE_BINDING_TIMESTAMP } else { 0 };
+
+            let descriptor_offset = 20 * i;
+            let descriptor = &mut bytes[descriptor_offset..descriptor_offset+20];
+            descriptor[0..4].copy_from_slice(&rva_of(*lookup_offset).to_le_bytes());
+            descriptor[4..8].copy_from_slice(&time_date_stamp.to_le_bytes());
+            descriptor[12..16].copy_from_slice(&rva_of(name_offset).to_le_bytes());
+            descriptor[16..20].copy_from_slice(&rva_of(*address_offset).to_le_bytes());
+
+            for (j, function) in import.functions.iter().enumerate() {
+                let entry = match function {
+                    ImportedFunction::ByOrdinal(ordinal) => {
+                        let ordinal_flag = 1 << (8*entry_size - 1);
+                        ordinal_flag | u64::from(*ordinal)
+                    },
+                    ImportedFunction::ByName { hint, name } => {
+                        bytes.resize(bytes.len().next_multiple_of(2), 0x00);
+                        let hint_name_offset = bytes.len();
+                        bytes.extend_from_slice(&hint.to_le_bytes());
+                        bytes.extend_from_slice(name.as_bytes());
+                        bytes.push(0x00);
+                        u64::from(rva_of(hint_name_offset))
+                    },
+                };
+                for table_offset in [lookup_offset, address_offset] {
+                    let entry_offset = table_offset + entry_size*j;
+                    bytes[entry_offset..entry_offset+entry_size].copy_from_slice(&entry.to_le_bytes()[..entry_size]);
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Assembles the bound import directory; empty if there are no bound imports.
+    fn bound_import_directory(&self) -> Vec<u8> {
+        if self.bound_imports.is_empty() {
+            return Vec::new();
+        }
+
+        // records (descriptors, each followed by its forwarder references), then the names
+        let record_count: usize = self.bound_imports.iter()
+            .map(|b| 1 + b.forwarder_refs.len())
+            .sum();
+        let mut records = Vec::with_capacity(8 * (record_count + 1));
+        let mut names = Vec::new();
+        let mut push_record = |module_name: &str, time_date_stamp: u32, count: usize| {
+            let name_offset = 8 * (record_count + 1) + names.len();
+            names.extend_from_slice(module_name.as_bytes());
+            names.push(0x00);
+            records.extend_from_slice(&time_date_stamp.to_le_bytes());
+            records.extend_from_slice(&u16::try_from(name_offset).unwrap().to_le_bytes());
+            records.extend_from_slice(&u16::try_from(count).unwrap().to_le_bytes());
+        };
+        for bound_import in &self.bound_imports {
+            push_record(&bound_import.module_name, bound_import.time_date_stamp, bound_import.forwarder_refs.len());
+            for (module_name, time_date_stamp) in &bound_import.forwarder_refs {
+                push_record(module_name, *time_date_stamp, 0);
+            }
+        }
+        records.extend_from_slice(&[0u8; 8]);
+        records.extend_from_slice(&names);
+        records
+    }
+}
+
+/// A library imported by a PE library.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Import {
+    pub module_name: String,
+    pub functions: Vec<ImportedFunction>,
+}
+
+/// A function imported from a library.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ImportedFunction {
+    ByOrdinal(u16),
+    ByName { hint: u16, name: String },
+}
+
+/// An entry of the bound import directory.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct BoundImport {
+    pub module_name: String,
+
+    /// The timestamp of the library to which the imports have been bound.
+    pub time_date_stamp: u32,
+
+    /// The libraries to which some of the imports are forwarded, with their timestamps.
+    pub forwarder_refs: Vec<(String, u32)>,
+}
+
+/// A symbol exported by a PE library.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Export {
+    pub ordinal: u32,
+
+    /// The name of the symbol; `None` if it is only exported by ordinal.
+    pub name: Option<String>,
+
+    pub target: ExportTarget,
+}
+
+/// What an exported symbol refers to.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ExportTarget {
+    /// Code at the given relative virtual address, which must not be below [`TEXT_RVA`].
+    Code { rva: u32 },
+
+    /// A symbol in a different library, e.g. `NTDLL.RtlAllocateHeap`.
+    Forwarder { target: String },
+}
+
+/// A resource stored in a PE library.
+///
+/// Only numeric identifiers are supported.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Resource {
+    pub type_id: u32,
+    pub name_id: u32,
+    pub language_id: u32,
+    pub data: Vec<u8>,
+}
+
+
+struct Section {
+    name: [u8; 8],
+    rva: u32,
+    data: Vec<u8>,
+    characteristics: u32,
+}
+
+/// Returns the relative virtual address at which the next section can be placed.
+fn next_section_rva(sections: &[Section]) -> u32 {
+    let last = sections.last().unwrap();
+    (last.rva + u32::try_from(last.data.len()).unwrap()).next_multiple_of(SECTION_ALIGNMENT)
+}
@@ -0,0 +1,128 @@
+//! Synthesizes Windows NT registry hives.
+
+
+const BASE_BLOCK_SIZE: usize = 4096;
+const HIVE_BIN_HEADER_SIZE: usize = 0x20;
+const NO_CELL: u32 = 0xFFFF_FFFF;
+
+const KEY_COMPRESSED_NAME: u16 = 0x0020;
+const VALUE_COMPRESSED_NAME: u16 = 0x0001;
+const REG_SZ: u32 = 1;
+
+
+/// A key of a registry hive to be synthesized.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Key {
+    /// The name of the key; ASCII only.
+    pub name: String,
+
+    /// The string values of the key as pairs of name (empty for the default value) and data.
+    pub values: Vec<(String, String)>,
+
+    /// The subkeys of the key.
+    pub subkeys: Vec<Key>,
+}
+impl Key {
+    /// Creates a description of a key without values or subkeys.
+    pub fn new<N: Into<String>>(name: N) -> Self {
+        Self {
+            name: name.into(),
+            values: Vec::new(),
+            subkeys: Vec::new(),
+        }
+    }
+}
+
+
+/// Returns a hive consisting of a base block and a single hive bin containing `root` and its
+/// descendants.
+///
+/// Subkeys are stored in `lf` lists, all names are stored compressed (Latin-1) and all values are
+/// stored as `REG_SZ`.
+pub fn hive(root: &Key) -> Vec<u8> {
+    let mut bins = vec![0u8; HIVE_BIN_HEADER_SIZE];
+    bins[0..4].copy_from_slice(b"hbin");
+    let root_offset = write_key(&mut bins, root);
+    bins.resize(bins.len().next_multiple_of(BASE_BLOCK_SIZE), 0);
+    let bins_size = u32::try_from(bins.len()).unwrap();
+    bins[8..12].copy_from_slice(&bins_size.to_le_bytes());
+
+    let mut bytes = vec![0u8; BASE_BLOCK_SIZE];
+    bytes[0..4].copy_from_slice(b"regf");
+    bytes[20..24].copy_from_slice(&1u32.to_le_bytes()); // major version
+    bytes[24..28].copy_from_slice(&5u32.to_le_bytes()); // minor version
+    bytes[36..40].copy_from_slice(&root_offset.to_le_bytes());
+    bytes[40..44].copy_from_slice(&bins_size.to_le_bytes());
+    bytes.extend_from_slice(&bins);
+    bytes
+}
+
+/// Appends an allocated cell with the given content, returning its offset.
+fn write_cell(bins: &mut Vec<u8>, content: &[u8]) -> u32 {
+    let offset = bins.len();
+    let size = (4 + content.len()).next_multiple_of(8);
+    bins.extend_from_slice(&(-i32::try_from(size).unwrap()).to_le_bytes());
+    bins.extend_from_slice(content);
+    bins.resize(offset + size, 0);
+    u32::try_from(offset).unwrap()
+}
+
+fn write_value(bins: &mut Vec<u8>, name: &str, data: &str) -> u32 {
+    let data_bytes: Vec<u8> = data.encode_utf16()
+        .chain(std::iter::once(0))
+        .flat_map(|w| w.to_le_bytes())
+        .collect();
+    let data_offset = write_cell(bins, &data_bytes);
+
+    let mut vk = vec![0u8; 20];
+    vk[0..2].copy_from_slice(b"vk");
+    vk[2..4].copy_from_slice(&u16::try_from(name.len()).unwrap().to_le_bytes());
+    vk[4..8].copy_from_slice(&u32::try_from(data_bytes.len()).unwrap().to_le_bytes());
+    vk[8..12].copy_from_slice(&data_offset.to_le_bytes());
+    vk[12..16].copy_from_slice(&REG_SZ.to_le_bytes());
+    vk[16..18].copy_from_slice(&VALUE_COMPRESSED_NAME.to_le_bytes());
+    vk.extend_from_slice(name.as_bytes());
+    write_cell(bins, &vk)
+}
+
+fn write_key(bins: &mut Vec<u8>, key: &Key) -> u32 {
+    let subkey_offsets: Vec<u32> = key.subkeys
+        .iter()
+        .map(|subkey| write_key(bins, subkey))
+        .collect();
+    let subkey_list_offset = if subkey_offsets.is_empty() {
+        NO_CELL
+    } else {
+        let mut lf = b"lf".to_vec();
+        lf.extend_from_slice(&u16::try_from(subkey_offsets.len()).unwrap().to_le_bytes());
+        for offset in &subkey_offsets {
+            lf.extend_from_slice(&offset.to_le_bytes());
+            lf.extend_from_slice(&[0u8; 4]); // name hint
+        }
+        write_cell(bins, &lf)
+    };
+
+    let value_offsets: Vec<u32> = key.values
+        .iter()
+        .map(|(name, data)| write_value(bins, name, data))
+        .collect();
+    let value_list_offset = if value_offsets.is_empty() {
+        NO_CELL
+    } else {
+        let list: Vec<u8> = value_offsets.iter()
+            .flat_map(|o| o.to_le_bytes())
+            .collect();
+        write_cell(bins, &list)
+    };
+
+    let mut nk = vec![0u8; 76];
+    nk[0..2].copy_from_slice(b"nk");
+    nk[2..4].copy_from_slice(&KEY_COMPRESSED_NAME.to_le_bytes());
+    nk[20..24].copy_from_slice(&u32::try_from(subkey_offsets.len()).unwrap().to_le_bytes());
+    nk[28..32].copy_from_slice(&subkey_list_offset.to_le_bytes());
+    nk[36..40].copy_from_slice(&u32::try_from(value_offsets.len()).unwrap().to_le_bytes());
+    nk[40..44].copy_from_slice(&value_list_offset.to_le_bytes());
+    nk[72..74].copy_from_slice(&u16::try_from(key.name.len()).unwrap().to_le_bytes());
+    nk.extend_from_slice(key.name.as_bytes());
+    write_cell(bins, &nk)
+}
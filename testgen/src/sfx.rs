@@ -0,0 +1,301 @@
+//! Builds DOS self-extracting archives: a tiny DOS program followed by a ZIP, LHA or ARJ archive.
+//!
+//! The files are stored without compression, except in ARJ archives of methods 1 to 3, whose files
+//! are coded as literals only. The DOS program only exits; real self-extractors contain the
+//! extraction code there, which finds the archive after the end of its own image.
+
+
+/// The DOS program: `mov ax, 4C00h; int 21h`.
+const PROGRAM: [u8; 5] = [0xB8, 0x00, 0x4C, 0xCD, 0x21];
+
+/// The size of the MZ header, in paragraphs.
+const HEADER_PARAGRAPHS: u16 = 2;
+
+
+/// Returns a DOS executable with the archive appended after the end of its image.
+pub fn self_extracting(archive: &[u8]) -> Vec<u8> {
+    let header_size = usize::from(HEADER_PARAGRAPHS) * 16;
+    let image_size = header_size + PROGRAM.len();
+    let last_page_bytes = u16::try_from(image_size % 512).unwrap();
+    let pages = u16::try_from(image_size.div_ceil(512)).unwrap();
+
+    let mut bytes = vec![0u8; header_size];
+    bytes[0..2].copy_from_slice(b"MZ");
+    bytes[2..4].copy_from_slice(&last_page_bytes.to_le_bytes());
+    bytes[4..6].copy_from_slice(&pages.to_le_bytes());
+    bytes[8..10].copy_from_slice(&HEADER_PARAGRAPHS.to_le_bytes());
+    bytes[12..14].copy_from_slice(&0xFFFFu16.to_le_bytes()); // maximum allocation
+    bytes[24..26].copy_from_slice(&0x001Cu16.to_le_bytes()); // relocation table offset
+    bytes.extend_from_slice(&PROGRAM);
+    bytes.extend_from_slice(archive);
+    bytes
+}
+
+
+/// Returns a ZIP archive storing the given files, given as paths with `/` separators and data.
+pub fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut directory = Vec::new();
+    for (path, data) in files {
+        let local_header_offset = u32::try_from(bytes.len()).unwrap();
+        let name_length = u16::try_from(path.len()).unwrap();
+        let size = u32::try_from(data.len()).unwrap();
+        let crc = crc32(data);
+
+        bytes.extend_from_slice(b"PK\x03\x04");
+        bytes.extend_from_slice(&10u16.to_le_bytes()); // version needed to extract
+        bytes.extend_from_slice(&0u16.to_le_bytes()); // flags
+        bytes.extend_from_slice(&0u16.to_le_bytes()); // stored
+        bytes.extend_from_slice(&0u32.to_le_bytes()); // modification time and date
+        bytes.extend_from_slice(&crc.to_le_bytes());
+        bytes.extend_from_slice(&size.to_le_bytes());
+        bytes.extend_from_slice(&size.to_le_bytes());
+        bytes.extend_from_slice(&name_length.to_le_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+        bytes.extend_from_slice(path.as_bytes());
+        bytes.extend_from_slice(data);
+
+        directory.extend_from_slice(b"PK\x01\x02");
+        directory.extend_from_slice(&10u16.to_le_bytes()); // version made by (MS-DOS)
+        directory.extend_from_slice(&10u16.to_le_bytes()); // version needed to extract
+        directory.extend_from_slice(&0u16.to_le_bytes()); // flags
+        directory.extend_from_slice(&0u16.to_le_bytes()); // stored
+        directory.extend_from_slice(&0u32.to_le_bytes()); // modification time and date
+        directory.extend_from_slice(&crc.to_le_bytes());
+        directory.extend_from_slice(&size.to_le_bytes());
+        directory.extend_from_slice(&size.to_le_bytes());
+        directory.extend_from_slice(&name_length.to_le_bytes());
+        directory.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+        directory.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        directory.extend_from_slice(&0u16.to_le_bytes()); // disk number
+        directory.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+        directory.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+        directory.extend_from_slice(&local_header_offset.to_le_bytes());
+        directory.extend_from_slice(path.as_bytes());
+    }
+
+    let directory_offset = u32::try_from(bytes.len()).unwrap();
+    let entry_count = u16::try_from(files.len()).unwrap();
+    bytes.extend_from_slice(&directory);
+    bytes.extend_from_slice(b"PK\x05\x06");
+    bytes.extend_from_slice(&0u16.to_le_bytes()); // this disk
+    bytes.extend_from_slice(&0u16.to_le_bytes()); // disk with the central directory
+    bytes.extend_from_slice(&entry_count.to_le_bytes());
+    bytes.extend_from_slice(&entry_count.to_le_bytes());
+    bytes.extend_from_slice(&u32::try_from(directory.len()).unwrap().to_le_bytes());
+    bytes.extend_from_slice(&directory_offset.to_le_bytes());
+    bytes.extend_from_slice(&0u16.to_le_bytes()); // comment length
+    bytes
+}
+
+
+/// Returns an LHA archive with level-0 headers storing the given files (method `-lh0-`), given as
+/// paths with `/` separators and data.
+pub fn lha(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for (path, data) in files {
+        let name = path.replace('/', "\\");
+        let size = u32::try_from(data.len()).unwrap();
+
+        let mut header = Vec::new();
+        header.extend_from_slice(b"-lh0-");
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes()); // modification time and date
+        header.push(0x20); // attributes: archive
+        header.push(0); // header level
+        header.push(u8::try_from(name.len()).unwrap());
+        header.extend_from_slice(name.as_bytes());
+        header.extend_from_slice(&crc16(data).to_le_bytes());
+
+        let checksum = header.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        bytes.push(u8::try_from(header.len()).unwrap());
+        bytes.push(checksum);
+        bytes.extend_from_slice(&header);
+        bytes.extend_from_slice(data);
+    }
+    bytes.push(0x00);
+    bytes
+}
+
+/// Returns an LHA archive with level-1 headers storing the given files (method `-lh0-`), given as
+/// paths with `/` separators and data.
+///
+/// The header itself only stores the file name; the directory is stored in an extended header.
+pub fn lha_level_1(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for (path, data) in files {
+        let (directory, name) = match path.rsplit_once('/') {
+            Some((directory, name)) => (Some(directory), name),
+            None => (None, *path),
+        };
+
+        // directory extended header, with 0xFF as the separator and the next size (none) at the end
+        let mut extended = Vec::new();
+        if let Some(directory) = directory {
+            extended.push(0x02);
+            extended.extend(directory.bytes().map(|b| if b == b'/' { 0xFF } else { b }));
+            extended.push(0xFF);
+            extended.extend_from_slice(&0u16.to_le_bytes());
+        }
+        let size = u32::try_from(data.len()).unwrap();
+        // level-1 extended headers count towards the compressed size
+        let compressed_size = size + u32::try_from(extended.len()).unwrap();
+
+        let mut header = Vec::new();
+        header.extend_from_slice(b"-lh0-");
+        header.extend_from_slice(&compressed_size.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes()); // modification time and date
+        header.push(0x20); // reserved
+        header.push(1); // header level
+        header.push(u8::try_from(name.len()).unwrap());
+        header.extend_from_slice(name.as_bytes());
+        header.extend_from_slice(&crc16(data).to_le_bytes());
+        header.push(b'M'); // operating system: MS-DOS
+        header.extend_from_slice(&u16::try_from(extended.len()).unwrap().to_le_bytes());
+
+        let checksum = header.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        bytes.push(u8::try_from(header.len()).unwrap());
+        bytes.push(checksum);
+        bytes.extend_from_slice(&header);
+        bytes.extend_from_slice(&extended);
+        bytes.extend_from_slice(data);
+    }
+    bytes.push(0x00);
+    bytes
+}
+
+
+/// Returns an ARJ archive storing the given files (method 0), given as paths with `/` separators
+/// and data.
+pub fn arj(files: &[(&str, &[u8])]) -> Vec<u8> {
+    arj_with_method(files, 0)
+}
+
+/// Returns an ARJ archive storing the given files using the given method, given as paths with `/`
+/// separators and data.
+///
+/// Methods 1 to 3 differ only in how hard the archiver tries to find matches, so the files are
+/// coded the same way for each of them: as a single block of literals (see [`lzh_literals`]).
+pub fn arj_with_method(files: &[(&str, &[u8])], method: u8) -> Vec<u8> {
+    assert!(method <= 3, "only methods 0 to 3 are supported");
+    let mut bytes = arj_header(2, 0, "SFX.ARJ", 0, 0, 0);
+    for (path, data) in files {
+        let stored = if method == 0 { data.to_vec() } else { lzh_literals(data, ARJ_DISTANCE_COUNT_BITS) };
+        let compressed_size = u32::try_from(stored.len()).unwrap();
+        let original_size = u32::try_from(data.len()).unwrap();
+        bytes.extend_from_slice(&arj_header(0, method, path, compressed_size, original_size, crc32(data)));
+        bytes.extend_from_slice(&stored);
+    }
+    bytes.extend_from_slice(&[0x60, 0xEA, 0x00, 0x00]);
+    bytes
+}
+
+/// Returns an ARJ header of the given file type (0 for binary files, 2 for the main header)
+/// without extended headers.
+fn arj_header(file_type: u8, method: u8, path: &str, compressed_size: u32, original_size: u32, crc: u32) -> Vec<u8> {
+    let mut basic = vec![
+        30, // size of the fixed part
+        11, // archiver version
+        1, // minimum version to extract
+        0, // host OS: MS-DOS
+        0x10, // flags: path separators translated to /
+        method,
+        file_type,
+        0, // reserved
+    ];
+    basic.extend_from_slice(&0u32.to_le_bytes()); // modification time and date
+    basic.extend_from_slice(&compressed_size.to_le_bytes());
+    basic.extend_from_slice(&original_size.to_le_bytes());
+    basic.extend_from_slice(&crc.to_le_bytes());
+    basic.extend_from_slice(&0u16.to_le_bytes()); // position of the file name in the path
+    basic.extend_from_slice(&0x20u16.to_le_bytes()); // attributes: archive
+    basic.extend_from_slice(&0u16.to_le_bytes()); // host data
+    basic.extend_from_slice(path.as_bytes());
+    basic.push(0x00);
+    basic.push(0x00); // empty comment
+
+    let mut bytes = vec![0x60, 0xEA];
+    bytes.extend_from_slice(&u16::try_from(basic.len()).unwrap().to_le_bytes());
+    bytes.extend_from_slice(&basic);
+    bytes.extend_from_slice(&crc32(&basic).to_le_bytes());
+    bytes.extend_from_slice(&0u16.to_le_bytes()); // no extended headers
+    bytes
+}
+
+
+/// The number of bits storing the number of code lengths of the distance code in ARJ methods 1 to 3.
+const ARJ_DISTANCE_COUNT_BITS: u8 = 5;
+
+/// Codes the data using the static Huffman coding of LHA and ARJ, in blocks consisting only of
+/// literals.
+///
+/// Each block announces that every code length is 8 using a code length code with a single symbol,
+/// which makes the code of each literal the literal itself. The distance code consists of a single
+/// unused symbol.
+pub fn lzh_literals(data: &[u8], distance_count_bits: u8) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    for block in data.chunks(0xFFFF) {
+        writer.write(u32::try_from(block.len()).unwrap(), 16);
+        writer.write(0, 5); // code length code: a single symbol...
+        writer.write(8 + 2, 5); // ...which gives a code length of 8
+        writer.write(256, 9); // literal code: code lengths for the 256 literals
+        writer.write(0, distance_count_bits); // distance code: a single symbol...
+        writer.write(0, distance_count_bits); // ...which is 0
+        for b in block {
+            writer.write((*b).into(), 8);
+        }
+    }
+    writer.finish()
+}
+
+/// Writes bits starting with the most significant bit of each byte.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit_count: usize,
+}
+impl BitWriter {
+    /// Writes the lowest `count` bits of the value, starting with the most significant one.
+    fn write(&mut self, value: u32, count: u8) {
+        for shift in (0..count).rev() {
+            if self.bit_count.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            if value & (1 << shift) != 0 {
+                *self.bytes.last_mut().unwrap() |= 0x80 >> (self.bit_count % 8);
+            }
+            self.bit_count += 1;
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+
+/// Calculates the CRC-32 (as used by ZIP and ARJ) of the data.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for b in data {
+        crc ^= u32::from(*b);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Calculates the CRC-16 (as used by LHA) of the data.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for b in data {
+        crc ^= u16::from(*b);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xA001 } else { crc >> 1 };
+        }
+    }
+    crc
+}
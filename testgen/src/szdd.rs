@@ -0,0 +1,89 @@
+//! Wraps data in the "SZDD" and "SZ" formats used by `COMPRESS.EXE`.
+//!
+//! The data is not actually compressed: every byte is stored as a literal, which any LZSS
+//! decompressor has to handle regardless of the window's initial state. [`lzss`] additionally
+//! encodes repeated data as matches, which only decode correctly with the window set up as SZDD
+//! does.
+
+
+/// Returns an SZDD file containing `data`.
+///
+/// `missing_char` is the last character of the original file name, which is usually replaced by
+/// an underscore in the name of the compressed file.
+pub fn compress(data: &[u8], missing_char: u8) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(14 + data.len() + data.len().div_ceil(8));
+    bytes.extend_from_slice(b"SZDD\x88\xF0\x27\x33");
+    bytes.push(b'A'); // compression method
+    bytes.push(missing_char);
+    bytes.extend_from_slice(&u32::try_from(data.len()).unwrap().to_le_bytes());
+    bytes.extend_from_slice(&literals(data));
+    bytes
+}
+
+/// Returns an "SZ" file (the predecessor of SZDD) containing `data`.
+pub fn compress_sz(data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(12 + data.len() + data.len().div_ceil(8));
+    bytes.extend_from_slice(b"SZ \x88\xF0\x27\x33\xD1");
+    bytes.extend_from_slice(&u32::try_from(data.len()).unwrap().to_le_bytes());
+    bytes.extend_from_slice(&literals(data));
+    bytes
+}
+
+/// Encodes `data` as an LZSS stream consisting only of literals.
+fn literals(data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(data.len() + data.len().div_ceil(8));
+    for chunk in data.chunks(8) {
+        // one control bit per literal
+        let control_byte = u8::try_from((1u16 << chunk.len()) - 1).unwrap();
+        bytes.push(control_byte);
+        bytes.extend_from_slice(chunk);
+    }
+    bytes
+}
+
+/// Encodes `data` as an LZSS stream using matches for repeated data, as found by a greedy search.
+///
+/// Matches address the window by absolute position, starting at 16 bytes before its end as in SZDD
+/// (and KWAJ method 2), so decoding the stream with the window of SZ ends up with the wrong data.
+pub fn lzss(data: &[u8]) -> Vec<u8> {
+    const WINDOW_SIZE: usize = 4096;
+    const INITIAL_POSITION: usize = WINDOW_SIZE - 16;
+    const MAX_MATCH_LENGTH: usize = 18;
+
+    let mut bytes = Vec::with_capacity(data.len() + data.len().div_ceil(8));
+    let mut control_index = 0;
+    let mut symbol_index = 0;
+    let mut position = 0;
+    while position < data.len() {
+        if symbol_index % 8 == 0 {
+            control_index = bytes.len();
+            bytes.push(0x00);
+        }
+        symbol_index += 1;
+
+        // the longest match starting in the part of the window already written
+        let earliest_start = position.saturating_sub(WINDOW_SIZE - MAX_MATCH_LENGTH);
+        let (match_start, match_length) = (earliest_start..position)
+            .map(|start| {
+                let length = data[position..].iter()
+                    .zip(&data[start..])
+                    .take(MAX_MATCH_LENGTH)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                (start, length)
+            })
+            .max_by_key(|(_start, length)| *length)
+            .unwrap_or((0, 0));
+        if match_length >= 3 {
+            let window_position = (INITIAL_POSITION + match_start) % WINDOW_SIZE;
+            bytes.push(u8::try_from(window_position & 0xFF).unwrap());
+            bytes.push(u8::try_from(((window_position >> 4) & 0xF0) | (match_length - 3)).unwrap());
+            position += match_length;
+        } else {
+            bytes[control_index] |= 1 << ((symbol_index - 1) % 8);
+            bytes.push(data[position]);
+            position += 1;
+        }
+    }
+    bytes
+}
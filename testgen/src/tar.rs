@@ -0,0 +1,150 @@
+//! Builds tarballs in the POSIX (ustar) format, with the GNU and pax extensions for long names.
+
+
+const BLOCK_LENGTH: usize = 512;
+
+
+/// The type of a tarball entry.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum EntryType {
+    File,
+    HardLink,
+    SymbolicLink,
+    Directory,
+}
+impl EntryType {
+    fn type_flag(&self) -> u8 {
+        match self {
+            Self::File => b'0',
+            Self::HardLink => b'1',
+            Self::SymbolicLink => b'2',
+            Self::Directory => b'5',
+        }
+    }
+}
+
+
+/// An entry of a tarball.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Entry {
+    pub path: String,
+    pub entry_type: EntryType,
+
+    /// The target of a link.
+    pub link_name: String,
+
+    pub data: Vec<u8>,
+}
+impl Entry {
+    pub fn file(path: &str, data: &[u8]) -> Self {
+        Self {
+            path: path.to_owned(),
+            entry_type: EntryType::File,
+            link_name: String::new(),
+            data: data.to_vec(),
+        }
+    }
+
+    pub fn directory(path: &str) -> Self {
+        Self {
+            path: path.to_owned(),
+            entry_type: EntryType::Directory,
+            link_name: String::new(),
+            data: Vec::new(),
+        }
+    }
+
+    pub fn link(entry_type: EntryType, path: &str, target: &str) -> Self {
+        Self {
+            path: path.to_owned(),
+            entry_type,
+            link_name: target.to_owned(),
+            data: Vec::new(),
+        }
+    }
+}
+
+
+/// How paths too long for the 100 bytes of the name field are stored.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum LongNames {
+    /// In a GNU `././@LongLink` entry before the entry.
+    Gnu,
+
+    /// In a pax extended header before the entry.
+    Pax,
+}
+
+
+/// Returns a tarball containing the given entries.
+pub fn tarball(entries: &[Entry], long_names: LongNames) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for entry in entries {
+        let path_too_long = entry.path.len() > 100;
+        let link_too_long = entry.link_name.len() > 100;
+        match long_names {
+            LongNames::Gnu => {
+                if path_too_long {
+                    let mut name = entry.path.as_bytes().to_vec();
+                    name.push(0x00);
+                    push_entry(&mut bytes, "././@LongLink", b'L', "", &name);
+                }
+                if link_too_long {
+                    let mut name = entry.link_name.as_bytes().to_vec();
+                    name.push(0x00);
+                    push_entry(&mut bytes, "././@LongLink", b'K', "", &name);
+                }
+            },
+            LongNames::Pax => {
+                let mut records = Vec::new();
+                if path_too_long {
+                    records.extend(pax_record("path", &entry.path));
+                }
+                if link_too_long {
+                    records.extend(pax_record("linkpath", &entry.link_name));
+                }
+                if !records.is_empty() {
+                    push_entry(&mut bytes, "PaxHeaders/entry", b'x', "", &records);
+                }
+            },
+        }
+        let path = &entry.path[..entry.path.len().min(100)];
+        let link_name = &entry.link_name[..entry.link_name.len().min(100)];
+        push_entry(&mut bytes, path, entry.entry_type.type_flag(), link_name, &entry.data);
+    }
+
+    // two zero blocks end the archive
+    bytes.resize(bytes.len() + 2*BLOCK_LENGTH, 0x00);
+    bytes
+}
+
+fn push_entry(bytes: &mut Vec<u8>, path: &str, type_flag: u8, link_name: &str, data: &[u8]) {
+    let mut header = [0u8; BLOCK_LENGTH];
+    header[..path.len()].copy_from_slice(path.as_bytes());
+    header[100..108].copy_from_slice(b"0000644\0");
+    header[124..136].copy_from_slice(format!("{:011o}\0", data.len()).as_bytes());
+    header[156] = type_flag;
+    header[157..157+link_name.len()].copy_from_slice(link_name.as_bytes());
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // the checksum is calculated with the checksum field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum: u32 = header.iter().map(|b| u32::from(*b)).sum();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+
+    bytes.extend_from_slice(&header);
+    bytes.extend_from_slice(data);
+    bytes.resize(bytes.len().next_multiple_of(BLOCK_LENGTH), 0x00);
+}
+
+/// Returns a pax record, `<length> <key>=<value>\n`, with the length counting the whole record.
+fn pax_record(key: &str, value: &str) -> Vec<u8> {
+    let rest = format!(" {}={}\n", key, value);
+    // the length of the record includes the digits of the length
+    let mut length = rest.len() + 1;
+    while format!("{}{}", length, rest).len() != length {
+        length += 1;
+    }
+    format!("{}{}", length, rest).into_bytes()
+}
@@ -0,0 +1,154 @@
+//! Compresses data using the LZ77+Huffman variant of the XPRESS algorithm.
+//!
+//! Every symbol is given a code of nine bits, which makes the table of code lengths trivial, and
+//! matches are found greedily. Long matches store their length in extra bytes, which are placed
+//! between the 16-bit words of the bit stream where the decoder expects them. The output is larger
+//! than that of Microsoft's compressor, but it exercises the same decoding paths.
+
+
+use std::collections::HashMap;
+
+
+/// The number of output bytes covered by each block.
+const BLOCK_SIZE: usize = 65536;
+
+const MIN_MATCH_LENGTH: usize = 3;
+
+/// The longest match whose length fits into the symbol.
+const MAX_SYMBOL_MATCH_LENGTH: usize = MIN_MATCH_LENGTH + 14;
+
+/// The longest match whose length fits into the symbol and an extra byte.
+const MAX_BYTE_MATCH_LENGTH: usize = MAX_SYMBOL_MATCH_LENGTH + 255;
+
+/// The longest match, whose length minus three is stored in an extra 16-bit word.
+const MAX_MATCH_LENGTH: usize = MIN_MATCH_LENGTH + 0xFFFF;
+
+/// The largest offset whose bit length fits into the symbol.
+const MAX_OFFSET: usize = 0xFFFF;
+
+const END_OF_STREAM_SYMBOL: u16 = 256;
+
+
+/// Collects bits most significant first into little-endian 16-bit words, placing the extra bytes
+/// of match lengths in between.
+///
+/// The decoder keeps two words ahead of the bits it has consumed and reads extra bytes from the
+/// position after those; a word's place in the output is therefore reserved as soon as the bits
+/// before it have been written, and filled in once its own bits are known.
+struct BitWriter {
+    output: Vec<u8>,
+    word_offsets: Vec<usize>,
+    bits: Vec<bool>,
+}
+impl BitWriter {
+    /// Starts writing at the end of the output.
+    fn new(output: Vec<u8>) -> Self {
+        let start = output.len();
+        let mut writer = Self {
+            output,
+            word_offsets: vec![start, start + 2],
+            bits: Vec::new(),
+        };
+        writer.output.extend_from_slice(&[0; 4]);
+        writer
+    }
+
+    /// Writes the bits as the decoder consumes them in one go.
+    fn push(&mut self, value: u16, count: u32) {
+        for i in (0..count).rev() {
+            self.bits.push(value & (1 << i) != 0);
+        }
+        // the decoder reads the next word once it has consumed bits beyond those it read ahead
+        while self.bits.len() > 16 * (self.word_offsets.len() - 1) {
+            self.word_offsets.push(self.output.len());
+            self.output.extend_from_slice(&[0; 2]);
+        }
+    }
+
+    fn push_extra_byte(&mut self, byte: u8) {
+        self.output.push(byte);
+    }
+
+    fn push_extra_word(&mut self, word: u16) {
+        self.output.extend_from_slice(&word.to_le_bytes());
+    }
+
+    /// Fills in the words, padding the bits to fill all of those the decoder has read.
+    fn finish(mut self) -> Vec<u8> {
+        self.bits.resize(16 * self.word_offsets.len(), false);
+        for (word_bits, offset) in self.bits.chunks(16).zip(&self.word_offsets) {
+            let word = word_bits.iter().fold(0u16, |word, &bit| (word << 1) | u16::from(bit));
+            self.output[*offset..*offset + 2].copy_from_slice(&word.to_le_bytes());
+        }
+        self.output
+    }
+}
+
+
+/// Compresses the data, ending the stream with an end-of-stream symbol.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut trigram_to_position: HashMap<&[u8], usize> = HashMap::new();
+    let mut block_start = 0;
+    loop {
+        let block_end = (block_start + BLOCK_SIZE).min(data.len());
+        output.extend(std::iter::repeat_n(0x99, 256));
+        let mut writer = BitWriter::new(output);
+
+        let mut position = block_start;
+        while position < block_end {
+            let mut match_length = 0;
+            let mut offset = 0;
+            if position + MIN_MATCH_LENGTH <= block_end {
+                let trigram = &data[position..position + MIN_MATCH_LENGTH];
+                if let Some(&candidate) = trigram_to_position.get(trigram)
+                    && position - candidate <= MAX_OFFSET
+                {
+                    offset = position - candidate;
+                    match_length = MIN_MATCH_LENGTH;
+                    while match_length < MAX_MATCH_LENGTH
+                        && position + match_length < block_end
+                        && data[candidate + match_length] == data[position + match_length]
+                    {
+                        match_length += 1;
+                    }
+                }
+            }
+
+            let step = if match_length > 0 {
+                let offset_bit_count = offset.ilog2();
+                let length_nibble = (match_length - MIN_MATCH_LENGTH).min(15);
+                let symbol = 256 + (offset_bit_count << 4) + u32::try_from(length_nibble).unwrap();
+                writer.push(u16::try_from(symbol).unwrap(), 9);
+                if match_length > MAX_BYTE_MATCH_LENGTH {
+                    writer.push_extra_byte(255);
+                    writer.push_extra_word(u16::try_from(match_length - MIN_MATCH_LENGTH).unwrap());
+                } else if match_length > MAX_SYMBOL_MATCH_LENGTH {
+                    writer.push_extra_byte(u8::try_from(match_length - MAX_SYMBOL_MATCH_LENGTH - 1).unwrap());
+                }
+                writer.push(u16::try_from(offset - (1 << offset_bit_count)).unwrap(), offset_bit_count);
+                match_length
+            } else {
+                writer.push(data[position].into(), 9);
+                1
+            };
+            for p in position..position + step {
+                if p + MIN_MATCH_LENGTH <= data.len() {
+                    trigram_to_position.insert(&data[p..p + MIN_MATCH_LENGTH], p);
+                }
+            }
+            position += step;
+        }
+
+        // a full block is followed by another one, so the end-of-stream symbol has to wait
+        let is_last = block_end - block_start < BLOCK_SIZE;
+        if is_last {
+            writer.push(END_OF_STREAM_SYMBOL, 9);
+        }
+        output = writer.finish();
+        if is_last {
+            return output;
+        }
+        block_start = block_end;
+    }
+}
@@ -1,4 +1,6 @@
+mod temp_file;
 mod udf;
+mod verify;
 
 
 use std::ffi::CString;
@@ -8,17 +10,32 @@ use std::path::PathBuf;
 
 use clap::Parser;
 use sxd_document::QName;
-use tempfile::NamedTempFile;
-use wimlib::{ExtractFlags, IterateDirTreeFlags, OpenFlags, WimLib};
+use wimlib::{ExtractFlags, FileAttributes, IterateDirTreeFlags, OpenFlags, WimLib};
 use wimlib::string::{TStr, ThinTStr};
 
+use crate::temp_file::TempFile;
 use crate::udf::Udf;
+use crate::verify::Verifier;
 
 
 #[derive(Parser)]
 struct Opts {
     pub iso_path: PathBuf,
     pub out_path: PathBuf,
+
+    /// After extraction, compares each extracted file against the hash stored in the WIM and
+    /// reports mismatches.
+    ///
+    /// Mismatches are a frequent symptom of a truncated ISO file.
+    #[arg(long)]
+    pub verify: bool,
+
+    /// The directory in which to store install.wim (or install.esd) while extracting files from it.
+    ///
+    /// The file can be several gigabytes large, so this should be on a volume with enough free
+    /// space.
+    #[arg(short, long, default_value = ".")]
+    pub temp_dir: PathBuf,
 }
 
 fn main() {
@@ -69,7 +86,7 @@ fn main() {
     let wim_size_bytes: usize = install_wim
         .file_length().expect("failed to obtain .wim file size")
         .try_into().expect("failed to convert .wim size to usize");
-    let mut wim_temp_file_holder = NamedTempFile::new_in(".")
+    let mut wim_temp_file_holder = TempFile::create(&opts.temp_dir, wim_size_bytes.try_into().unwrap())
         .expect("failed to create temp file for .wim");
     let wim_path = wim_temp_file_holder.path().to_path_buf();
     let wim_temp_file = wim_temp_file_holder.as_file_mut();
@@ -120,6 +137,10 @@ fn main() {
     let wim_path_tstr = TStr::from_impl(&wim_path_c_string);
     let wim = wim_lib.open_wim(wim_path_tstr, OpenFlags::CHECK_INTEGRITY)
         .expect("failed to open WIM file");
+
+    // wimlib keeps the file open; we no longer need its name
+    wim_temp_file_holder.unlink_while_open()
+        .expect("failed to remove name of temp file for .wim");
     let xml_data = wim.xml_data()
         .expect("failed to obtain WIM XML data");
     let mut xml_data_string = xml_data.to_string()
@@ -200,16 +221,38 @@ fn main() {
 
     // pick out the paths we want
     let mut want_paths = Vec::new();
+    let mut verifier = Verifier::new();
     best_image.iterate_dir_tree(
         TStr::from_impl(c"/"),
         IterateDirTreeFlags::RECURSIVE,
         |entry| {
+            let full_path = entry.full_path.to_str();
+            let lower_path = full_path.to_lowercase();
             let want =
-                entry.full_path.to_str().to_lowercase() == "/windows/system32"
-                || entry.full_path.to_str().to_lowercase() == "/windows/syswow64"
+                lower_path == "/windows/system32"
+                || lower_path == "/windows/syswow64"
             ;
             if want {
-                want_paths.push(CString::new(entry.full_path.to_str().as_bytes()).unwrap());
+                want_paths.push(CString::new(full_path.as_bytes()).unwrap());
+            }
+
+            let is_wanted_file =
+                !entry.file_attributes.intersects(FileAttributes::DIRECTORY | FileAttributes::REPARSE_POINT)
+                && (
+                    lower_path.starts_with("/windows/system32/")
+                    || lower_path.starts_with("/windows/syswow64/")
+                )
+            ;
+            if opts.verify && is_wanted_file {
+                // the directory structure is preserved during extraction
+                let unnamed_stream = entry.streams_converted()
+                    .iter()
+                    .find(|s| s.stream_name.is_none())
+                    .map(|s| s.resource.sha1_hash);
+                if let Some(expected_hash) = unnamed_stream {
+                    let out_path = opts.out_path.join(full_path.trim_start_matches('/'));
+                    verifier.expect(out_path, expected_hash);
+                }
             }
             Ok(())
         },
@@ -225,4 +268,17 @@ fn main() {
         ExtractFlags::empty(),
     )
         .expect("extraction failed");
+
+    if opts.verify {
+        println!("verifying {} extracted files", verifier.len());
+        let mismatches = verifier.verify();
+        for (path, mismatch) in &mismatches {
+            println!("MISMATCH {}: {}", path.display(), mismatch);
+        }
+        if !mismatches.is_empty() {
+            eprintln!("{} of {} extracted files failed verification", mismatches.len(), verifier.len());
+            std::process::exit(1);
+        }
+        println!("all extracted files verified");
+    }
 }
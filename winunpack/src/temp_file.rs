@@ -0,0 +1,56 @@
+//! The temporary file holding the WIM extracted from the ISO.
+//!
+//! `install.wim` is several gigabytes large, so the file is created in a directory of the user's
+//! choosing and its size is reserved up front. Since wimlib opens the WIM by path, the file must
+//! have a name; it is removed as early as the platform allows so that a crash does not leave it
+//! behind.
+
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use tempfile::NamedTempFile;
+
+
+pub struct TempFile {
+    inner: NamedTempFile,
+}
+impl TempFile {
+    /// Creates a temporary file in the given directory and sets its length to `size` bytes.
+    ///
+    /// On Windows, setting the length allocates the space, so a volume without enough free space
+    /// is noticed before copying gigabytes of data; on other platforms, the file may remain sparse
+    /// until it is written.
+    pub fn create(dir: &Path, size: u64) -> Result<Self, io::Error> {
+        let inner = NamedTempFile::new_in(dir)?;
+        inner.as_file().set_len(size)?;
+        Ok(Self {
+            inner,
+        })
+    }
+
+    /// The path of the file. Not valid anymore after [`unlink_while_open`](Self::unlink_while_open).
+    pub fn path(&self) -> &Path {
+        self.inner.path()
+    }
+
+    pub fn as_file_mut(&mut self) -> &mut File {
+        self.inner.as_file_mut()
+    }
+
+    /// Removes the name of the file once it has been opened by path elsewhere, if the platform
+    /// allows it.
+    ///
+    /// On Unix, the contents remain accessible through the open handles until they are closed, so
+    /// the file disappears even if the process crashes. On Windows, files that are open without
+    /// sharing delete access cannot be deleted (and wimlib opens them without), so the file is only
+    /// deleted when this value is dropped.
+    pub fn unlink_while_open(&mut self) -> Result<(), io::Error> {
+        #[cfg(unix)]
+        {
+            std::fs::remove_file(self.inner.path())?;
+        }
+        Ok(())
+    }
+}
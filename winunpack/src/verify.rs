@@ -0,0 +1,192 @@
+//! Verification of extracted files against the hashes stored in the WIM.
+//!
+//! WIM files store the SHA-1 hash of the uncompressed contents of each resource. Comparing the
+//! extracted files against these hashes catches damaged images; the most frequent cause is an ISO
+//! file that has been truncated during download.
+
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use sha1::{Digest, Sha1};
+
+
+/// The hash stored for empty resources.
+///
+/// Empty files have no resource in the WIM, so their hash is stored as all zeroes instead of the
+/// SHA-1 hash of no data.
+pub const EMPTY_HASH: [u8; 20] = [0u8; 20];
+
+
+/// The outcome of verifying one extracted file.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Mismatch {
+    /// The file was not extracted at all.
+    Missing,
+
+    /// The file could not be read.
+    Unreadable(String),
+
+    /// The contents of the file have a different hash than the one stored in the WIM.
+    WrongHash { expected: [u8; 20], obtained: [u8; 20] },
+}
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => write!(f, "file is missing"),
+            Self::Unreadable(e) => write!(f, "file cannot be read: {}", e),
+            Self::WrongHash { expected, obtained } => write!(
+                f,
+                "expected hash {}, obtained {}",
+                HexHash(expected), HexHash(obtained),
+            ),
+        }
+    }
+}
+
+
+struct HexHash<'a>(&'a [u8; 20]);
+impl fmt::Display for HexHash<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for b in self.0 {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Collects the expected hashes of the files extracted from a WIM and verifies them once they
+/// have been written.
+///
+/// The verifier does not depend on how the WIM is read; whichever reader performs the extraction
+/// only needs to register the path and stored hash of each file it writes.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Verifier {
+    path_to_hash: BTreeMap<PathBuf, [u8; 20]>,
+}
+impl Verifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a file that will be extracted to `path` and whose contents have the hash
+    /// `expected_hash` according to the WIM.
+    pub fn expect(&mut self, path: PathBuf, expected_hash: [u8; 20]) {
+        self.path_to_hash.insert(path, expected_hash);
+    }
+
+    /// The number of registered files.
+    pub fn len(&self) -> usize {
+        self.path_to_hash.len()
+    }
+
+    /// Hashes all registered files and returns those that do not match their expected hash.
+    pub fn verify(&self) -> Vec<(&Path, Mismatch)> {
+        let mut mismatches = Vec::new();
+        for (path, expected) in &self.path_to_hash {
+            let obtained = match hash_file(path) {
+                Ok(h) => h,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    mismatches.push((path.as_path(), Mismatch::Missing));
+                    continue;
+                },
+                Err(e) => {
+                    mismatches.push((path.as_path(), Mismatch::Unreadable(e.to_string())));
+                    continue;
+                },
+            };
+            if obtained != *expected {
+                mismatches.push((path.as_path(), Mismatch::WrongHash { expected: *expected, obtained }));
+            }
+        }
+        mismatches
+    }
+}
+
+
+/// Returns the hash of the file at the given path the way the WIM stores it, i.e. with
+/// [`EMPTY_HASH`] for empty files.
+fn hash_file(path: &Path) -> Result<[u8; 20], io::Error> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha1::new();
+    let mut buf = vec![0u8; 1024*1024];
+    let mut total_bytes_read: u64 = 0;
+    loop {
+        let bytes_read = file.read(&mut buf)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buf[..bytes_read]);
+        total_bytes_read += u64::try_from(bytes_read).unwrap();
+    }
+    if total_bytes_read == 0 {
+        Ok(EMPTY_HASH)
+    } else {
+        Ok(hasher.finalize().into())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use sha1::{Digest, Sha1};
+
+    use super::{Mismatch, Verifier, EMPTY_HASH};
+
+
+    #[test]
+    fn test_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        let intact = dir.path().join("intact.dll");
+        let truncated = dir.path().join("truncated.dll");
+        let empty = dir.path().join("empty.txt");
+        let missing = dir.path().join("missing.dll");
+        fs::write(&intact, b"MZ intact").unwrap();
+        fs::write(&truncated, b"MZ trunc").unwrap();
+        fs::write(&empty, b"").unwrap();
+
+        let intact_hash: [u8; 20] = Sha1::digest(b"MZ intact").into();
+        let truncated_hash: [u8; 20] = Sha1::digest(b"MZ truncated").into();
+        let obtained_hash: [u8; 20] = Sha1::digest(b"MZ trunc").into();
+
+        let mut verifier = Verifier::new();
+        verifier.expect(intact.clone(), intact_hash);
+        verifier.expect(truncated.clone(), truncated_hash);
+        verifier.expect(empty.clone(), EMPTY_HASH);
+        verifier.expect(missing.clone(), intact_hash);
+        assert_eq!(verifier.len(), 4);
+
+        let mismatches = verifier.verify();
+        assert_eq!(mismatches, vec![
+            (missing.as_path(), Mismatch::Missing),
+            (truncated.as_path(), Mismatch::WrongHash { expected: truncated_hash, obtained: obtained_hash }),
+        ]);
+    }
+
+    #[test]
+    fn test_verify_all_intact() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("kernel32.dll");
+        fs::write(&path, vec![0x90; 3 * 1024 * 1024 + 7]).unwrap();
+
+        let mut verifier = Verifier::new();
+        verifier.expect(path, Sha1::digest(vec![0x90; 3 * 1024 * 1024 + 7]).into());
+        assert_eq!(verifier.verify(), Vec::new());
+    }
+
+    #[test]
+    fn test_mismatch_display() {
+        let mismatch = Mismatch::WrongHash { expected: [0x01; 20], obtained: [0xAB; 20] };
+        assert_eq!(
+            mismatch.to_string(),
+            format!("expected hash {}, obtained {}", "01".repeat(20), "ab".repeat(20)),
+        );
+        assert_eq!(Mismatch::Missing.to_string(), "file is missing");
+    }
+}